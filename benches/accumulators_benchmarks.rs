@@ -1,3 +1,6 @@
+// The classgroup benches are kept around until the backend lands.
+#![allow(unexpected_cfgs)]
+
 extern crate accumulators;
 #[cfg(feature = "classgroup")]
 extern crate classgroup;
@@ -17,8 +20,8 @@ use criterion::Criterion;
 mod rsa_benches {
     use super::*;
     use accumulators::group::RSAGroup;
-    use accumulators::traits::{BatchedAccumulator, StaticAccumulator};
-    use accumulators::Accumulator;
+    use accumulators::traits::{AccumulatorSetup, BatchUpdateAccumulator, StaticAccumulator};
+    use accumulators::{Accumulator, NewState, OldState};
     use num_bigint::RandPrime;
     use rand::SeedableRng;
     use rand_chacha::ChaChaRng;
@@ -72,11 +75,26 @@ mod rsa_benches {
 
         let mut acc = Accumulator::setup::<RSAGroup, _>(rng, N);
         let xs = vec![rng.gen_prime(L)];
-        let a_t = acc.state().clone();
+        let old = OldState(acc.state().clone());
         let w = acc.batch_add(&xs);
+        let new = NewState(acc.state().clone());
 
         c.bench_function("bench_ver_batch_add_1", move |b| {
-            b.iter(|| acc.ver_batch_add(&w, &a_t, &xs))
+            b.iter(|| acc.ver_batch_add_transition(&w, &old, &new, &xs))
+        });
+    }
+
+    fn bench_batch_del_1000(c: &mut Criterion) {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, N);
+        let xs = (0..1000).map(|_| rng.gen_prime(L)).collect::<Vec<_>>();
+        acc.add_batch_no_proof(&xs);
+        let ws = acc.create_all_mem_wit(&xs);
+        let pairs = xs.into_iter().zip(ws).collect::<Vec<_>>();
+
+        c.bench_function("bench_batch_del_1000", move |b| {
+            b.iter(|| acc.clone().batch_del(&pairs))
         });
     }
 
@@ -86,10 +104,16 @@ mod rsa_benches {
         targets =
             bench_add_1,
             bench_mem_wit_create_1,
+            bench_ver_mem_1,
             bench_batch_add_1,
             bench_ver_batch_add_1,
     }
 
+    criterion_group! {
+        name = rsa_slow_benches;
+        config = Criterion::default().sample_size(10);
+        targets = bench_batch_del_1000
+    }
 }
 
 #[cfg(not(feature = "classgroup"))]
@@ -150,5 +174,6 @@ mod classgroup_benches {
 
 criterion_main!(
     rsa_benches::rsa_benches,
+    rsa_benches::rsa_slow_benches,
     classgroup_benches::classgroup_benches
 );
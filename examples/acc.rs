@@ -0,0 +1,349 @@
+//! Command line tool for accumulator operations on files, for debugging.
+//!
+//! Run with `cargo run --example acc --features cli -- <command>`, see `USAGE` for the
+//! commands. Files use the formats of the library:
+//!
+//! - accumulators are JSON, as serialized with `serde`,
+//! - `params.json` holds `lambda`, `n` and `g`, the integers as hex strings,
+//! - states are the root as hex, with the fixed width of the modulus,
+//! - witnesses and proofs are binary, in the `FixedBytes` encoding,
+//! - element lists have one hex element per line, empty lines and lines starting with `#`
+//!   are skipped.
+//!
+//! Exits with `1` if a verification fails, and with `2` on any other error.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::process;
+
+use accumulators::encoding::hex::{biguint_from_hex, from_hex, to_hex};
+use accumulators::encoding::{from_fixed_bytes, modulus_bytes, to_fixed_bytes, FixedBytes};
+use accumulators::group::RSAGroup;
+use accumulators::primality::{is_probable_prime, PrimalityConfig};
+use accumulators::traits::*;
+use accumulators::{
+    Accumulator, AccumulatorError, BatchProof, MembershipWitness, NewState, OldState, PublicState,
+};
+use num_bigint::BigUint;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use serde::{Deserialize, Serialize};
+
+const USAGE: &str = "\
+usage: acc <command> [args]
+
+commands:
+  setup --lambda <bits> [--seed <hex>] -o <acc.json>
+  params <acc.json> -o <params.json>
+  state <acc.json> -o <state.hex>
+  add <acc.json> <elements.txt>
+  batch-add <acc.json> <elements.txt> -o <proof.bin>
+  witness <acc.json> <element-hex> -o <wit.bin>
+  verify <params.json> <state.hex> <wit.bin> <element-hex>
+  verify-batch <params.json> <old-state.hex> <new-state.hex> <elements.txt> <proof.bin>";
+
+/// The contents of `params.json`.
+#[derive(Serialize, Deserialize)]
+struct Params {
+    lambda: usize,
+    #[serde(with = "accumulators::encoding::serde_hex::biguint")]
+    n: BigUint,
+    #[serde(with = "accumulators::encoding::serde_hex::biguint")]
+    g: BigUint,
+}
+
+#[derive(Debug)]
+enum Error {
+    /// Invalid arguments.
+    Usage(String),
+    /// Reading, writing or parsing `path` failed.
+    File {
+        path: String,
+        msg: String,
+    },
+    /// The accumulator refused the operation.
+    Accumulator(AccumulatorError),
+    Other(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Usage(msg) => write!(f, "{}\n\n{}", msg, USAGE),
+            Error::File { path, msg } => write!(f, "{}: {}", path, msg),
+            Error::Accumulator(err) => write!(f, "{}", err),
+            Error::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<AccumulatorError> for Error {
+    fn from(err: AccumulatorError) -> Self {
+        Error::Accumulator(err)
+    }
+}
+
+fn file_error<E: fmt::Display>(path: &str) -> impl FnOnce(E) -> Error + '_ {
+    move |err| Error::File {
+        path: path.to_string(),
+        msg: err.to_string(),
+    }
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    match run(&args) {
+        Ok(true) => {}
+        Ok(false) => {
+            eprintln!("verification failed");
+            process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(2);
+        }
+    }
+}
+
+/// Runs the command, returns `false` if a verification failed.
+fn run(args: &[String]) -> Result<bool, Error> {
+    let (command, args) = args
+        .split_first()
+        .ok_or_else(|| Error::Usage("missing command".into()))?;
+    let (positional, output, options) = parse_args(args)?;
+
+    let pos = |i: usize| -> Result<&str, Error> {
+        positional
+            .get(i)
+            .map(String::as_str)
+            .ok_or_else(|| Error::Usage(format!("{}: missing argument {}", command, i + 1)))
+    };
+    let out = || -> Result<&str, Error> {
+        output
+            .as_deref()
+            .ok_or_else(|| Error::Usage(format!("{}: missing output file, -o <path>", command)))
+    };
+
+    match command.as_str() {
+        "setup" => {
+            let lambda = options
+                .lambda
+                .ok_or_else(|| Error::Usage("setup: missing --lambda <bits>".into()))?;
+            if lambda < 64 {
+                return Err(Error::Usage("setup: --lambda must be at least 64".into()));
+            }
+            let seed = match &options.seed {
+                Some(seed) => parse_seed(seed)?,
+                None => rand::random(),
+            };
+
+            let rng = &mut ChaChaRng::from_seed(seed);
+            let acc = Accumulator::setup::<RSAGroup, _>(rng, lambda);
+            write_acc(out()?, &acc)?;
+        }
+        "params" => {
+            let acc = read_acc(pos(0)?)?;
+            let params = Params {
+                lambda: acc.lambda(),
+                n: acc.modulus().clone(),
+                g: acc.generator().clone(),
+            };
+            let json = serde_json::to_string_pretty(&params).expect("serializable");
+            write_file(out()?, json.as_bytes())?;
+        }
+        "state" => {
+            let acc = read_acc(pos(0)?)?;
+            write_file(out()?, state_hex(&acc).as_bytes())?;
+        }
+        "add" => {
+            let path = pos(0)?;
+            let mut acc = read_acc(path)?;
+            let xs = read_elements(pos(1)?, &acc)?;
+            acc.batch_add_checked(&xs)?;
+            write_acc(path, &acc)?;
+            println!("{}", state_hex(&acc));
+        }
+        "batch-add" => {
+            let path = pos(0)?;
+            let mut acc = read_acc(path)?;
+            let xs = read_elements(pos(1)?, &acc)?;
+            let old_state = state_hex(&acc);
+            let proof = acc.batch_add_checked(&xs)?;
+            write_acc(path, &acc)?;
+
+            let bytes = proof.to_fixed_bytes(modulus_bytes(acc.modulus()));
+            write_file(out()?, &bytes)?;
+            println!("{}\n{}", old_state, state_hex(&acc));
+        }
+        "witness" => {
+            let acc = read_acc(pos(0)?)?;
+            let x = parse_element(pos(1)?)?;
+            let w = acc.try_mem_wit_create(&x)?;
+
+            let bytes = w.to_fixed_bytes(modulus_bytes(acc.modulus()));
+            write_file(out()?, &bytes)?;
+        }
+        "verify" => {
+            let params = read_params(pos(0)?)?;
+            let state = read_state(pos(1)?, &params)?;
+            let w: MembershipWitness = read_fixed(pos(2)?, &state.n)?;
+            let x = parse_element(pos(3)?)?;
+
+            return Ok(state.ver_mem(&w, &x));
+        }
+        "verify-batch" => {
+            let params = read_params(pos(0)?)?;
+            let old_state = read_state(pos(1)?, &params)?;
+            let new_state = read_state(pos(2)?, &params)?;
+            let xs = read_element_lines(pos(3)?)?;
+            let proof: BatchProof = read_fixed(pos(4)?, &new_state.n)?;
+
+            let (old, new) = (OldState(old_state.root), NewState(new_state.root.clone()));
+            return Ok(new_state.ver_batch_add_transition(&proof, &old, &new, &xs));
+        }
+        _ => return Err(Error::Usage(format!("unknown command: {}", command))),
+    }
+
+    Ok(true)
+}
+
+#[derive(Default)]
+struct Options {
+    lambda: Option<usize>,
+    seed: Option<String>,
+}
+
+/// Splits `args` into positional arguments, the output file and the options of `setup`.
+fn parse_args(args: &[String]) -> Result<(Vec<String>, Option<String>, Options), Error> {
+    let mut positional = Vec::new();
+    let mut output = None;
+    let mut options = Options::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| Error::Usage(format!("missing value for {}", arg)))
+        };
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(value()?),
+            "--lambda" => {
+                let v = value()?;
+                let lambda = v
+                    .parse()
+                    .map_err(|_| Error::Usage(format!("invalid --lambda: {}", v)))?;
+                options.lambda = Some(lambda);
+            }
+            "--seed" => options.seed = Some(value()?),
+            _ if arg.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option: {}", arg)));
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    Ok((positional, output, options))
+}
+
+fn parse_seed(s: &str) -> Result<[u8; 32], Error> {
+    let bytes = from_hex(s).map_err(|err| Error::Usage(format!("invalid --seed: {}", err)))?;
+    if bytes.len() != 32 {
+        return Err(Error::Usage(format!(
+            "invalid --seed: expected 32 bytes, got {}",
+            bytes.len()
+        )));
+    }
+
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&bytes);
+    Ok(seed)
+}
+
+/// Parses an element, as big-endian hex.
+fn parse_element(s: &str) -> Result<BigUint, Error> {
+    let s = s.trim();
+    let padded = if s.len() % 2 == 1 {
+        format!("0{}", s)
+    } else {
+        s.to_string()
+    };
+
+    biguint_from_hex(&padded).map_err(|err| Error::Other(format!("invalid element {}: {}", s, err)))
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, Error> {
+    fs::read(path).map_err(file_error(path))
+}
+
+fn write_file(path: &str, bytes: &[u8]) -> Result<(), Error> {
+    fs::write(path, bytes).map_err(file_error(path))
+}
+
+fn read_acc(path: &str) -> Result<Accumulator, Error> {
+    serde_json::from_slice(&read_file(path)?).map_err(file_error(path))
+}
+
+fn write_acc(path: &str, acc: &Accumulator) -> Result<(), Error> {
+    let json = serde_json::to_string(acc).expect("serializable");
+    write_file(path, json.as_bytes())
+}
+
+fn read_params(path: &str) -> Result<Params, Error> {
+    serde_json::from_slice(&read_file(path)?).map_err(file_error(path))
+}
+
+/// Reads the state in `path`, and checks it together with `params`.
+fn read_state(path: &str, params: &Params) -> Result<PublicState, Error> {
+    let bytes = read_file(path)?;
+    let hex = String::from_utf8_lossy(&bytes);
+    let root = from_hex(hex.trim())
+        .map_err(file_error(path))
+        .and_then(|bytes| from_fixed_bytes(&bytes, &params.n).map_err(file_error(path)))?;
+
+    PublicState::from_state(params.lambda, params.n.clone(), params.g.clone(), root)
+        .map_err(|err| Error::Other(format!("invalid parameters: {}", err)))
+}
+
+fn read_fixed<T: FixedBytes>(path: &str, n: &BigUint) -> Result<T, Error> {
+    T::from_fixed_bytes(&read_file(path)?, n).map_err(file_error(path))
+}
+
+fn state_hex(acc: &Accumulator) -> String {
+    to_hex(&to_fixed_bytes(acc.state(), modulus_bytes(acc.modulus())))
+}
+
+fn read_element_lines(path: &str) -> Result<Vec<BigUint>, Error> {
+    let bytes = read_file(path)?;
+    String::from_utf8_lossy(&bytes)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| parse_element(line).map_err(file_error(path)))
+        .collect()
+}
+
+/// Reads the elements to add to `acc`, checking that they are new primes.
+fn read_elements(path: &str, acc: &Accumulator) -> Result<Vec<BigUint>, Error> {
+    let xs = read_element_lines(path)?;
+
+    let cfg = PrimalityConfig::default();
+    let mut seen = HashSet::new();
+    for x in &xs {
+        let hex = x.to_str_radix(16);
+        if !is_probable_prime(x, &cfg) {
+            return Err(Error::File {
+                path: path.to_string(),
+                msg: format!("element {} is not a prime", hex),
+            });
+        }
+        if acc.contains(x) || !seen.insert(x) {
+            let err = AccumulatorError::IsMember;
+            return Err(Error::Other(format!("element {}: {}", hex, err)));
+        }
+    }
+
+    Ok(xs)
+}
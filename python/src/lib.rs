@@ -0,0 +1,328 @@
+//! Python bindings for the RSA accumulator, built with maturin.
+//!
+//! Integers are Python `int`s, inputs may also be given as big-endian `bytes`. Membership
+//! witnesses and batch proofs are `int`s and non-membership witnesses `(d, b)` tuples, so they
+//! can be stored and sent like any other value. The exponentiations run without the GIL.
+
+// `#[pymethods]` of pyo3 0.20 expands to impls inside functions
+#![allow(non_local_definitions)]
+
+use accumulators_rs::traits::*;
+use accumulators_rs::{
+    BatchProof, MembershipWitness, NewState, NonMembershipWitness, OldState, PublicState,
+    RsaAccumulatorBuilder, DEFAULT_LAMBDA,
+};
+use num_bigint::{BigInt, BigUint, Sign};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyLong};
+use rand::rngs::OsRng;
+
+create_exception!(
+    accumulators,
+    AccumulatorError,
+    PyValueError,
+    "An operation of the accumulator failed."
+);
+create_exception!(
+    accumulators,
+    NotAMemberError,
+    AccumulatorError,
+    "The element is not a member."
+);
+create_exception!(
+    accumulators,
+    IsMemberError,
+    AccumulatorError,
+    "The element is a member, or shares a factor with one."
+);
+create_exception!(
+    accumulators,
+    ParamError,
+    PyValueError,
+    "The modulus, generator or state are invalid."
+);
+
+fn accumulator_error(err: accumulators_rs::AccumulatorError) -> PyErr {
+    use accumulators_rs::AccumulatorError as E;
+
+    match err {
+        E::NotAMember => NotAMemberError::new_err(err.to_string()),
+        E::IsMember => IsMemberError::new_err(err.to_string()),
+        _ => AccumulatorError::new_err(err.to_string()),
+    }
+}
+
+fn param_error(err: accumulators_rs::ParamError) -> PyErr {
+    ParamError::new_err(err.to_string())
+}
+
+/// Converts a non-negative `int`, or big-endian `bytes`.
+fn to_biguint(x: &PyAny) -> PyResult<BigUint> {
+    if let Ok(bytes) = x.downcast::<PyBytes>() {
+        return Ok(BigUint::from_bytes_be(bytes.as_bytes()));
+    }
+
+    let x: &PyLong = x.downcast()?;
+    if x.lt(0)? {
+        return Err(PyValueError::new_err("expected a non-negative integer"));
+    }
+    let bits: usize = x.call_method0("bit_length")?.extract()?;
+    let bytes: &PyBytes = x
+        .call_method1("to_bytes", (bits.div_ceil(8), "big"))?
+        .downcast()?;
+
+    Ok(BigUint::from_bytes_be(bytes.as_bytes()))
+}
+
+/// Converts an `int` of any sign.
+fn to_bigint(x: &PyAny) -> PyResult<BigInt> {
+    let x: &PyLong = x.downcast()?;
+    if x.lt(0)? {
+        let abs = to_biguint(x.call_method0("__neg__")?)?;
+        Ok(BigInt::from_biguint(Sign::Minus, abs))
+    } else {
+        Ok(BigInt::from_biguint(Sign::Plus, to_biguint(x)?))
+    }
+}
+
+fn to_biguints(xs: Vec<&PyAny>) -> PyResult<Vec<BigUint>> {
+    xs.into_iter().map(to_biguint).collect()
+}
+
+fn to_int(py: Python, x: &BigUint) -> PyResult<PyObject> {
+    from_bytes(py, &x.to_bytes_be())
+}
+
+fn to_signed_int(py: Python, x: &BigInt) -> PyResult<PyObject> {
+    let (sign, bytes) = x.to_bytes_be();
+    let abs = from_bytes(py, &bytes)?;
+    match sign {
+        Sign::Minus => abs.call_method0(py, "__neg__"),
+        _ => Ok(abs),
+    }
+}
+
+/// `int.from_bytes(bytes, "big")`
+fn from_bytes(py: Python, bytes: &[u8]) -> PyResult<PyObject> {
+    let x = py
+        .get_type::<PyLong>()
+        .call_method1("from_bytes", (PyBytes::new(py, bytes), "big"))?;
+
+    Ok(x.into())
+}
+
+/// An accumulator, with the operations of the prover.
+#[pyclass(name = "Accumulator")]
+struct PyAccumulator {
+    inner: accumulators_rs::Accumulator,
+}
+
+#[pymethods]
+impl PyAccumulator {
+    /// Creates an empty accumulator in `Z_n`, with the generator `g`.
+    ///
+    /// The factorization of `n` must be unknown.
+    #[new]
+    fn new(n: &PyAny, g: &PyAny) -> PyResult<Self> {
+        let (n, g) = (to_biguint(n)?, to_biguint(g)?);
+        // checked like a received state, an empty accumulator has the generator as state
+        PublicState::from_state(n.bits(), n.clone(), g.clone(), g.clone()).map_err(param_error)?;
+
+        Ok(PyAccumulator {
+            inner: accumulators_rs::Accumulator::new(n, g),
+        })
+    }
+
+    /// Runs a trusted setup for a modulus of `lambda_bits` bits, with randomness from the OS.
+    #[staticmethod]
+    #[pyo3(signature = (lambda_bits = DEFAULT_LAMBDA))]
+    fn setup(py: Python, lambda_bits: usize) -> PyResult<Self> {
+        let mut rng = OsRng::new().map_err(|err| PyOSError::new_err(err.to_string()))?;
+        let inner = py
+            .allow_threads(move || {
+                RsaAccumulatorBuilder::new()
+                    .lambda(lambda_bits)
+                    .build(&mut rng)
+            })
+            .map_err(param_error)?;
+
+        Ok(PyAccumulator { inner })
+    }
+
+    #[getter]
+    fn modulus(&self, py: Python) -> PyResult<PyObject> {
+        to_int(py, self.inner.modulus())
+    }
+
+    #[getter]
+    fn generator(&self, py: Python) -> PyResult<PyObject> {
+        to_int(py, self.inner.generator())
+    }
+
+    /// The current state.
+    #[getter]
+    fn state(&self, py: Python) -> PyResult<PyObject> {
+        to_int(py, self.inner.state())
+    }
+
+    #[getter]
+    fn lambda_bits(&self) -> usize {
+        self.inner.lambda()
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn __contains__(&self, x: &PyAny) -> PyResult<bool> {
+        Ok(self.inner.contains(&to_biguint(x)?))
+    }
+
+    /// Adds the prime `x`.
+    fn add(&mut self, py: Python, x: &PyAny) -> PyResult<()> {
+        let x = to_biguint(x)?;
+        let inner = &mut self.inner;
+        py.allow_threads(move || inner.try_add(&x))
+            .map_err(accumulator_error)
+    }
+
+    /// Adds the primes `xs`, returning the batch proof for `Verifier.ver_batch_add`.
+    fn batch_add(&mut self, py: Python, xs: Vec<&PyAny>) -> PyResult<PyObject> {
+        let xs = to_biguints(xs)?;
+        let inner = &mut self.inner;
+        let proof = py
+            .allow_threads(move || inner.try_batch_add(&xs))
+            .map_err(accumulator_error)?;
+
+        to_int(py, &proof.0.q)
+    }
+
+    /// Deletes `x`, raising `NotAMemberError` if it is not a member.
+    fn delete(&mut self, py: Python, x: &PyAny) -> PyResult<()> {
+        let x = to_biguint(x)?;
+        let inner = &mut self.inner;
+        py.allow_threads(move || inner.del(&x))
+            .ok_or_else(|| accumulator_error(accumulators_rs::AccumulatorError::NotAMember))
+    }
+
+    /// Creates the membership witness for `x`.
+    fn witness(&mut self, py: Python, x: &PyAny) -> PyResult<PyObject> {
+        let x = to_biguint(x)?;
+        // `&mut`, as the witness cache is not shared between threads
+        let inner = &mut self.inner;
+        let w = py
+            .allow_threads(move || inner.try_mem_wit_create(&x))
+            .map_err(accumulator_error)?;
+
+        to_int(py, &w.0)
+    }
+
+    /// Creates the non-membership witness `(d, b)` for `x`.
+    fn non_mem_witness(&mut self, py: Python, x: &PyAny) -> PyResult<(PyObject, PyObject)> {
+        let x = to_biguint(x)?;
+        let inner = &mut self.inner;
+        let w = py
+            .allow_threads(move || inner.try_non_mem_wit_create(&x))
+            .map_err(accumulator_error)?;
+
+        Ok((to_int(py, &w.d)?, to_signed_int(py, &w.b)?))
+    }
+
+    /// Returns a verifier for the current state.
+    fn public_state(&self) -> Verifier {
+        Verifier {
+            state: self.inner.public_state(),
+        }
+    }
+}
+
+/// The public state of an accumulator, for verifying witnesses and proofs.
+#[pyclass]
+struct Verifier {
+    state: PublicState,
+}
+
+#[pymethods]
+impl Verifier {
+    /// Creates a verifier for the state `root` of an accumulator over `n`, with generator `g`.
+    ///
+    /// Checks the values like `PublicState::from_state`, with the size of `n` as
+    /// `lambda_bits` unless given.
+    #[new]
+    #[pyo3(signature = (n, g, root, lambda_bits = None))]
+    fn new(n: &PyAny, g: &PyAny, root: &PyAny, lambda_bits: Option<usize>) -> PyResult<Self> {
+        let n = to_biguint(n)?;
+        let bits = lambda_bits.unwrap_or_else(|| n.bits());
+        let state = PublicState::from_state(bits, n, to_biguint(g)?, to_biguint(root)?)
+            .map_err(param_error)?;
+
+        Ok(Verifier { state })
+    }
+
+    #[getter]
+    fn modulus(&self, py: Python) -> PyResult<PyObject> {
+        to_int(py, &self.state.n)
+    }
+
+    #[getter]
+    fn generator(&self, py: Python) -> PyResult<PyObject> {
+        to_int(py, &self.state.g)
+    }
+
+    #[getter]
+    fn state(&self, py: Python) -> PyResult<PyObject> {
+        to_int(py, &self.state.root)
+    }
+
+    /// Verifies the membership witness `w` for `x`.
+    fn ver_mem(&self, py: Python, w: &PyAny, x: &PyAny) -> PyResult<bool> {
+        let w = MembershipWitness(to_biguint(w)?);
+        let x = to_biguint(x)?;
+        let state = &self.state;
+
+        Ok(py.allow_threads(|| state.ver_mem(&w, &x)))
+    }
+
+    /// Verifies the non-membership witness `(d, b)` for `x`.
+    fn ver_non_mem(&self, py: Python, w: (&PyAny, &PyAny), x: &PyAny) -> PyResult<bool> {
+        let w = NonMembershipWitness {
+            d: to_biguint(w.0)?,
+            b: to_bigint(w.1)?,
+        };
+        let x = to_biguint(x)?;
+        let state = &self.state;
+
+        Ok(py.allow_threads(|| state.ver_non_mem(&w, &x)))
+    }
+
+    /// Verifies that `xs` were added to the state `old_root`, resulting in this state.
+    fn ver_batch_add(
+        &self,
+        py: Python,
+        proof: &PyAny,
+        old_root: &PyAny,
+        xs: Vec<&PyAny>,
+    ) -> PyResult<bool> {
+        let proof = BatchProof::from(to_biguint(proof)?);
+        let old = OldState(to_biguint(old_root)?);
+        let new = NewState(self.state.root.clone());
+        let xs = to_biguints(xs)?;
+        let state = &self.state;
+
+        Ok(py.allow_threads(|| state.ver_batch_add_transition(&proof, &old, &new, &xs)))
+    }
+}
+
+#[pymodule]
+fn accumulators(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyAccumulator>()?;
+    m.add_class::<Verifier>()?;
+    m.add("AccumulatorError", py.get_type::<AccumulatorError>())?;
+    m.add("NotAMemberError", py.get_type::<NotAMemberError>())?;
+    m.add("IsMemberError", py.get_type::<IsMemberError>())?;
+    m.add("ParamError", py.get_type::<ParamError>())?;
+
+    Ok(())
+}
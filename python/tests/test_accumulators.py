@@ -0,0 +1,142 @@
+"""Round trips between the Rust accumulator and verification from Python.
+
+Run from the `python` directory with
+
+    maturin develop && pytest
+"""
+
+import json
+import pathlib
+
+import pytest
+
+from accumulators import (
+    Accumulator,
+    AccumulatorError,
+    IsMemberError,
+    NotAMemberError,
+    ParamError,
+    Verifier,
+)
+
+VECTORS = pathlib.Path(__file__).resolve().parents[2] / "test-vectors"
+
+# Mersenne primes
+PRIMES = [2**p - 1 for p in (31, 61, 89, 107, 127)]
+
+
+@pytest.fixture(scope="module")
+def params():
+    acc = Accumulator.setup(256)  # insecure, but faster tests
+    return acc.modulus, acc.generator
+
+
+@pytest.fixture
+def acc(params):
+    return Accumulator(*params)
+
+
+def test_membership(acc):
+    for x in PRIMES[:3]:
+        acc.add(x)
+    assert len(acc) == 3
+    assert PRIMES[0] in acc and PRIMES[3] not in acc
+    assert 0 not in acc and 1 not in acc
+
+    verifier = acc.public_state()
+    assert verifier.state == acc.state
+    for x in PRIMES[:3]:
+        w = acc.witness(x)
+        assert verifier.ver_mem(w, x)
+        assert not verifier.ver_mem(w, PRIMES[3])
+        assert not verifier.ver_mem(w + 1, x)
+
+    with pytest.raises(NotAMemberError):
+        acc.witness(PRIMES[3])
+
+
+def test_batch_add(acc):
+    acc.add(PRIMES[0])
+    old_root = acc.state
+    proof = acc.batch_add(PRIMES[1:])
+
+    verifier = acc.public_state()
+    assert verifier.ver_batch_add(proof, old_root, PRIMES[1:])
+    assert not verifier.ver_batch_add(proof, old_root, PRIMES[2:])
+    assert not verifier.ver_batch_add(proof + 1, old_root, PRIMES[1:])
+
+
+def test_non_membership(acc):
+    acc.batch_add(PRIMES[:3])
+    verifier = acc.public_state()
+
+    d, b = acc.non_mem_witness(PRIMES[4])
+    assert verifier.ver_non_mem((d, b), PRIMES[4])
+    assert not verifier.ver_non_mem((d, -b), PRIMES[4])
+    assert not verifier.ver_non_mem((d, b), PRIMES[3])
+
+    with pytest.raises(IsMemberError):
+        acc.non_mem_witness(PRIMES[0])
+
+
+def test_delete(acc):
+    acc.batch_add(PRIMES[:2])
+    w = acc.witness(PRIMES[1])
+    acc.delete(PRIMES[0])
+    assert PRIMES[0] not in acc
+    assert not acc.public_state().ver_mem(w, PRIMES[1])
+    assert acc.public_state().ver_mem(acc.witness(PRIMES[1]), PRIMES[1])
+
+    with pytest.raises(NotAMemberError):
+        acc.delete(PRIMES[0])
+    assert issubclass(NotAMemberError, AccumulatorError)
+
+
+def test_conversions(acc):
+    x = PRIMES[1]
+    acc.add(x.to_bytes(8, "big"))
+    assert x in acc
+    w = acc.witness(x)
+    n_bytes = (acc.modulus.bit_length() + 7) // 8
+    assert acc.public_state().ver_mem(w.to_bytes(n_bytes, "big"), x)
+
+    with pytest.raises(ValueError):
+        acc.add(-x)
+    with pytest.raises(TypeError):
+        acc.add("x")
+
+
+def test_invalid_params(params):
+    n, g = params
+    with pytest.raises(ParamError):
+        Accumulator(n + 1, g)
+    with pytest.raises(ParamError):
+        Verifier(n, g, n)
+    with pytest.raises(ParamError):
+        Verifier(n, g, g, lambda_bits=2048)
+    with pytest.raises(ParamError):
+        Accumulator.setup(32)
+
+
+def test_vectors():
+    """Witnesses and proofs from the Rust test vectors verify in Python."""
+    hex_int = lambda s: int(s, 16)
+
+    for path in VECTORS.glob("*.json"):
+        v = json.loads(path.read_text())
+        n, g = hex_int(v["n"]), hex_int(v["g"])
+
+        added = Verifier(n, g, hex_int(v["add"][-1]["state"]))
+        for step in v["add"]:
+            x = hex_int(step["element"])
+            assert added.ver_mem(hex_int(step["witness"]), x)
+
+        batch = v["batch_add"]
+        batched = Verifier(n, g, hex_int(batch["state"]))
+        xs = [hex_int(x) for x in batch["elements"]]
+        assert batched.ver_batch_add(hex_int(batch["proof"]), added.state, xs)
+        assert not batched.ver_batch_add(hex_int(batch["proof"]), added.state, xs[1:])
+
+        non_mem = v["non_membership"]
+        w = (hex_int(non_mem["witness"]["d"]), hex_int(non_mem["witness"]["b"]))
+        assert batched.ver_non_mem(w, hex_int(non_mem["element"]))
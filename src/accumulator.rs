@@ -1,709 +1,5785 @@
-use num_bigint::traits::{ExtendedGcd, ModInverse};
-use num_bigint::{BigInt, BigUint, IntoBigUint};
+#[cfg(feature = "prover")]
+use std::cell::RefCell;
+#[cfg(feature = "prover")]
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::fmt;
+#[cfg(feature = "prover")]
+use std::marker::PhantomData;
+#[cfg(feature = "prover")]
+use std::sync::Arc;
+
+use num_bigint::traits::ModInverse;
+use num_bigint::{BigInt, BigUint, IntoBigUint, Sign};
 use num_integer::Integer;
-use num_traits::{One, Zero};
+use num_traits::{One, Signed, Zero};
+#[cfg(feature = "prover")]
 use rand::CryptoRng;
+#[cfg(feature = "prover")]
 use rand::Rng;
 
-use crate::math::{modpow_uint_int, root_factor, shamir_trick};
-use crate::proofs;
+#[cfg(feature = "prover")]
+use crate::bloom::CountingBloomFilter;
+use crate::element::epoch_element;
+#[cfg(feature = "prover")]
+use crate::element::PrimeElement;
+#[cfg(feature = "prover")]
+use crate::encoding::Packed;
+#[cfg(feature = "prover")]
+use crate::frozen::FrozenAccumulator;
+#[cfg(all(feature = "prover", feature = "rsa_group"))]
+use crate::group::RSAGroup;
+#[cfg(feature = "prover")]
+use crate::inclusion::{
+    overlap, prove_inclusion_exclusion, InclusionExclusionError, InclusionExclusionProof,
+};
+use crate::math::{
+    common_factor, cross_power, extended_gcd, modpow_uint_int, multi_modpow, product, product_iter,
+    shamir_trick, MathError,
+};
+#[cfg(feature = "prover")]
+use crate::math::{root_factor, SubproductTree};
+#[cfg(feature = "prover")]
+use crate::merge::{prove_merge, MergeError, MergeProof};
+#[cfg(feature = "prover")]
+use crate::migration::{digest_product, prove_migration, MigrationProof};
+#[cfg(feature = "prover")]
+use crate::package::{PackageKind, ProofPackage};
+use crate::par;
+#[cfg(all(feature = "prover", feature = "params"))]
+use crate::params::StandardParams;
+#[cfg(feature = "prover")]
+use crate::primes::{ModulusError, RandPrimes};
+use crate::proofs::{self, GroupParams, PoeProof, Poke2Proof, VerifyError};
+#[cfg(feature = "prover")]
+use crate::rerandomize::{RerandomizationSecret, RERANDOMIZATION_BITS};
+#[cfg(feature = "prover")]
+use crate::security::Dynamic;
+use crate::security::SecurityLevel;
+#[cfg(feature = "prover")]
+use crate::setup::{GroupMode, SetupParams};
+#[cfg(feature = "prover")]
+use crate::stateless::BlockProof;
+#[cfg(feature = "prover")]
+use crate::sync::{UpdateLog, UpdateMessage, UpdateRecorder, VerifierState};
+#[cfg(feature = "prover")]
 use crate::traits::*;
+#[cfg(feature = "prover")]
+use crate::witness::WitnessCache;
 
 // All accumulated values are small odd primes.
 // Arbitrary data values can be hashed to small primes,
 // It is also assumed that no item is added twice to the accumulator !!!
+#[cfg(feature = "prover")]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "AccumulatorRepr",
+        bound(serialize = "", deserialize = "L: SecurityLevel")
+    )
+)]
 #[derive(Debug, Clone)]
-pub struct Accumulator {
-    /// Length of the Integer we use in bits, This is Lambda and our security parameter
-    int_size_bits: usize, //change this to u16
-
-    /// An accumulator must have a public key divided into two parts.
-    /// A common reference string pp generated by the Setup algorithm in place of private/public keys.
-    /// Generator
-    g: BigUint,
-
-    /// Our Modulus, generated by using a public randomness known by the adversary
-    n: BigUint,
+pub struct Accumulator<L = Dynamic> {
+    /// The modulus, generator and security parameter, possibly shared with other
+    /// accumulators, see `new_with_params`.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    params: Arc<SetupParams>,
 
     /// Current accumulator state
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
     root: BigUint,
 
     /// The set of elements currently accumulated (product of the current set)
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
     set: BigUint,
-}
-
-impl Accumulator {}
 
-impl StaticAccumulator for Accumulator {
-    /// Returns the current public state.
-    fn state(&self) -> &BigUint {
-        &self.root
-    }
+    /// Number of elements currently accumulated.
+    #[cfg_attr(feature = "serde", serde(default))]
+    len: usize,
 
-    /// Generates a group of unknown order and initializes the group with a generator of that group.
-    /// Setup(λ, z) → pp, A0 Generate the public parameters
-    fn setup<T, R>(rng: &mut R, int_size_bits: usize) -> Self
-    where
-        T: PrimeGroup,
-        R: CryptoRng + Rng,
-    {
-        // Generate n = p q, |n| = int_size_bits
-        // This is a trusted setup, as we do know `p` and `q`, even though
-        // we choose not to store them.
+    /// Current epoch, see `advance_epoch`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    epoch: u64,
 
-        let (n, g) = T::generate_primes(rng, int_size_bits).unwrap();
+    /// Identifiers added under each epoch, if enabled, see `with_epoch_index`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    epoch_index: Option<BTreeMap<u64, Vec<Vec<u8>>>>,
 
-        Accumulator {
-            int_size_bits,
-            root: g.clone(),
-            g,
-            n,
-            set: BigUint::one(),
-        }
-    }
+    /// Limits on the size of the set, checked when adding.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    limits: CapacityLimits,
 
-    ///Takes the current accumulator At, an element from the odd primes domain, and computes At+1 = At.
-    #[inline]
-    fn add(&mut self, x: &BigUint) {
-        debug_assert!(
-            self.g.clone().modpow(&self.set, &self.n) == self.root,
-            "invalid state - pre add"
-        );
+    /// Sizes of elements accepted by the checked additions.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    policy: ElementPolicy,
 
-        // assumes x is already a prime
-        self.set *= x;
-        self.root = self.root.modpow(x, &self.n);
-    }
+    /// Memoized membership witnesses, if enabled.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cache: Option<RefCell<WitnessCache>>,
 
-    //A membership witness is simply the accumulator without the aggregated item.
-    #[inline]
-    fn mem_wit_create(&self, x: &BigUint) -> BigUint {
-        debug_assert!(
-            self.g.clone().modpow(&self.set, &self.n) == self.root,
-            "invalid state"
-        );
+    /// Bloom filter over the elements, if enabled.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    filter: Option<CountingBloomFilter>,
 
-        let (set, r) = self.set.clone().div_rem(x);
-        debug_assert!(r.is_zero(), "x was not a valid member of set");
+    /// Mutations recorded for followers, if enabled, see `record_updates`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    updates: Option<UpdateRecorder>,
 
-        self.g.clone().modpow(&set, &self.n)
-    }
+    /// The security level, see `into_level`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    level: PhantomData<L>,
+}
 
-    #[inline]
-    fn ver_mem(&self, w: &BigUint, x: &BigUint) -> bool {
-        w.modpow(x, &self.n) == self.root
-    }
+/// Deserialized form of `Accumulator`, checked before use.
+#[cfg(all(feature = "prover", feature = "serde"))]
+#[derive(Deserialize)]
+struct AccumulatorRepr {
+    int_size_bits: usize,
+    #[serde(with = "crate::encoding::serde_hex::biguint")]
+    g: BigUint,
+    #[serde(with = "crate::encoding::serde_hex::biguint")]
+    n: BigUint,
+    #[serde(with = "crate::encoding::serde_hex::biguint")]
+    root: BigUint,
+    #[serde(with = "crate::encoding::serde_hex::biguint")]
+    set: BigUint,
+    #[serde(default)]
+    len: usize,
+    #[serde(default)]
+    epoch: u64,
+    #[serde(default)]
+    epoch_index: Option<BTreeMap<u64, Vec<Vec<u8>>>>,
+    #[serde(default)]
+    group_mode: GroupMode,
 }
 
-impl DynamicAccumulator for Accumulator {
-    #[inline]
-    fn del(&mut self, x: &BigUint) -> Option<()> {
-        let old_s = self.set.clone();
-        self.set /= x;
+#[cfg(all(feature = "prover", feature = "serde"))]
+impl<L: SecurityLevel> std::convert::TryFrom<AccumulatorRepr> for Accumulator<L> {
+    type Error = ParamError;
 
-        if self.set == old_s {
-            return None;
+    fn try_from(acc: AccumulatorRepr) -> Result<Self, ParamError> {
+        if acc.g >= acc.n {
+            return Err(ParamError::InvalidGenerator);
+        }
+        if acc.root >= acc.n {
+            return Err(ParamError::InvalidState);
         }
+        L::check_lambda(acc.int_size_bits)?;
+        L::check_lambda(acc.n.bits())?;
 
-        self.root = self.g.clone().modpow(&self.set, &self.n); //Returns (self ^ exponent) % modulus.
-        Some(())
+        let AccumulatorRepr {
+            int_size_bits,
+            g,
+            n,
+            root,
+            set,
+            len,
+            epoch,
+            epoch_index,
+            group_mode,
+        } = acc;
+        let params = SetupParams {
+            lambda: int_size_bits,
+            n,
+            g,
+            mode: group_mode,
+        };
+        let state = PublicState {
+            n: params.n.clone(),
+            g: params.g.clone(),
+            root,
+        };
+        params.check_state(&state)?;
+
+        Ok(Accumulator {
+            params: Arc::new(params),
+            root: state.root,
+            set,
+            len,
+            epoch,
+            epoch_index,
+            limits: CapacityLimits::default(),
+            policy: ElementPolicy::for_level::<L>(),
+            cache: None,
+            filter: None,
+            updates: None,
+            level: PhantomData,
+        })
     }
 }
 
-impl UniversalAccumulator for Accumulator {
-    fn non_mem_wit_create(&self, x: &BigUint) -> (BigUint, BigInt) {
-        // set* <- \prod_{set\in S} set
-        let s_star = &self.set;
+#[cfg(feature = "prover")]
+impl Accumulator {
+    /// Creates an empty accumulator in `Z_n`, using the generator `g`.
+    ///
+    /// The factorization of `n` must be unknown, for the accumulator to be secure.
+    pub fn new(n: BigUint, g: BigUint) -> Self {
+        Self::new_with_params(Arc::new(SetupParams {
+            lambda: n.bits(),
+            n,
+            g,
+            mode: GroupMode::Full,
+        }))
+    }
 
-        // a, b <- Bezout(x, set*)
-        //let (_, a, b) = x.extended_gcd(s_star);
-        let (_, a, b) = num_bigint::traits::ExtendedGcd::extended_gcd(x, s_star);
-        let d = modpow_uint_int(&self.g, &a, &self.n).expect("prime");
+    /// Creates an empty accumulator referencing the shared `params`.
+    ///
+    /// Accumulators created from the same parameters can be merged, and elements moved
+    /// between them, see `shares_params`.
+    pub fn new_with_params(params: Arc<SetupParams>) -> Self {
+        Accumulator {
+            root: params.g.clone(),
+            params,
+            set: BigUint::one(),
+            len: 0,
+            epoch: 0,
+            epoch_index: None,
+            limits: CapacityLimits::default(),
+            policy: ElementPolicy::default(),
+            cache: None,
+            filter: None,
+            updates: None,
+            level: PhantomData,
+        }
+    }
 
-        (d, b)
+    /// Initializes an empty accumulator from one of the precomputed parameter sets.
+    #[cfg(feature = "params")]
+    pub fn with_params(params: StandardParams) -> Self {
+        Accumulator::new(params.modulus().clone(), params.generator().clone())
     }
 
-    fn ver_non_mem(&self, w: &(BigUint, BigInt), x: &BigUint) -> bool {
-        let (d, b) = w;
+    /// Runs the setup for a modulus of `lambda` bits, like `setup::<RSAGroup, _>`, and adds
+    /// all `elements` with `try_add_iter`. Returns the accumulator and the number of elements.
+    ///
+    /// To check the elements against a strict policy, or to limit the capacity, build the
+    /// accumulator with `RsaAccumulatorBuilder` and call `try_add_iter` instead.
+    #[cfg(feature = "rsa_group")]
+    pub fn with_elements<R, I>(
+        rng: &mut R,
+        lambda: usize,
+        elements: I,
+    ) -> Result<(Self, usize), AccumulatorError>
+    where
+        R: CryptoRng + Rng,
+        I: IntoIterator<Item = BigUint>,
+    {
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, lambda);
+        let count = acc.try_add_iter(elements)?;
 
-        // A^b
-        let a_b = modpow_uint_int(&self.root, b, &self.n).expect("prime");
-        // d^x
-        let d_x = d.modpow(x, &self.n);
+        Ok((acc, count))
+    }
 
-        // d^x A^b == g
-        (d_x * &a_b) % &self.n == self.g
+    /// Moves the set to an accumulator with the parameters `new_params`, e.g. a fresh setup
+    /// replacing a retired modulus. Returns the new accumulator and a proof for
+    /// `ver_migration` that both hold the same set.
+    ///
+    /// Keeps the limits, policy, epochs and Bloom filter. The witness cache is not kept, as
+    /// the witnesses of the old parameters don't verify under the new ones.
+    pub fn migrate(&self, new_params: &SetupParams) -> (Self, MigrationProof) {
+        let _span = span!("migrate", set_bits = self.set.bits());
+        let params = Arc::new(new_params.clone());
+        let new = Accumulator {
+            root: params.g.modpow(&self.set, &params.n),
+            params,
+            set: self.set.clone(),
+            len: self.len,
+            epoch: self.epoch,
+            epoch_index: self.epoch_index.clone(),
+            limits: self.limits,
+            policy: self.policy,
+            cache: None,
+            filter: self.filter.clone(),
+            updates: None,
+            level: PhantomData,
+        };
+        let digest = digest_product(&self.set);
+        let pi = prove_migration(self, &new, &self.set, &digest);
+
+        (new, pi)
     }
-}
 
-impl BatchedAccumulator for Accumulator {
-    fn batch_add(&mut self, xs: &[BigUint]) -> BigUint {
-        //begin our summation of the added elements
-        let mut x_star = BigUint::one();
-        for x in xs {
-            x_star *= x;
-            //add into element
-            self.set *= x;
+    /// Converts into an accumulator of the security level `L`, e.g. `Lambda2048`, checking
+    /// both the declared size and the size of the modulus.
+    ///
+    /// The default element policy is replaced by the one of the level, a policy set with
+    /// `with_element_policy` is kept.
+    pub fn into_level<L: SecurityLevel>(self) -> Result<Accumulator<L>, ParamError> {
+        L::check_lambda(self.params.lambda)?;
+        L::check_lambda(self.params.n.bits())?;
+
+        let mut acc = self.with_level();
+        if acc.policy == ElementPolicy::default() {
+            acc.policy = ElementPolicy::for_level::<L>();
         }
 
-        //temp clone our old root
-        let root_t = self.root.clone();
-        //calculate our new root after all the added elements
-        self.root = self.root.modpow(&x_star, &self.n); //Returns (self ^ exponent) % modulus.
-                                                        //create our proof for the procedure
-        proofs::ni_poe_prove(&x_star, &root_t, &self.root, &self.n)
+        Ok(acc)
     }
+}
 
-    fn ver_batch_add(&self, w: &BigUint, root: &BigUint, xs: &[BigUint]) -> bool {
-        let mut x_star = BigUint::one();
-        for x in xs {
-            x_star *= x
-        }
+#[cfg(feature = "prover")]
+impl<L: SecurityLevel> Accumulator<L> {
+    /// Converts into an accumulator of any size, as all accumulators were before security
+    /// levels.
+    pub fn into_dynamic(self) -> Accumulator {
+        self.with_level()
+    }
 
-        proofs::ni_poe_verify(&x_star, root, &self.root, &w, &self.n)
+    /// Returns a copy of the accumulator with the generator and state raised to a secret
+    /// random prime `r`, for publishing the same set where it shouldn't be linked to this
+    /// state, see the `rerandomize` module for what that hides. Returns the secret, to transform
+    /// the witnesses for this state with `transform_witness`.
+    ///
+    /// Keeps the limits, policy, epochs and Bloom filter, like `migrate`, but not the witness
+    /// cache.
+    pub fn rerandomize<R: CryptoRng + Rng>(&self, rng: &mut R) -> (Self, RerandomizationSecret) {
+        let _span = span!("rerandomize", modulus_bits = self.params.n.bits());
+        let r = rng.gen_distinct_primes(1, RERANDOMIZATION_BITS).remove(0);
+        let secret = RerandomizationSecret::new(r, self.params.n.clone());
+
+        let params = Arc::new(SetupParams {
+            g: secret.apply(&self.params.g),
+            ..(*self.params).clone()
+        });
+        let copy = Accumulator {
+            root: secret.apply(&self.root),
+            params,
+            set: self.set.clone(),
+            len: self.len,
+            epoch: self.epoch,
+            epoch_index: self.epoch_index.clone(),
+            limits: self.limits,
+            policy: self.policy,
+            cache: None,
+            filter: self.filter.clone(),
+            updates: None,
+            level: PhantomData,
+        };
+
+        (copy, secret)
     }
 
-    fn batch_del(&mut self, pairs: &[(BigUint, BigUint)]) -> Option<BigUint> {
-        if pairs.is_empty() {
-            return None;
+    fn with_level<M>(self) -> Accumulator<M> {
+        Accumulator {
+            params: self.params,
+            root: self.root,
+            set: self.set,
+            len: self.len,
+            epoch: self.epoch,
+            epoch_index: self.epoch_index,
+            limits: self.limits,
+            policy: self.policy,
+            cache: self.cache,
+            filter: self.filter,
+            updates: self.updates,
+            level: PhantomData,
         }
-        let mut pairs = pairs.iter();
-        let root_t = self.root.clone();
-
-        let (x0, w0) = pairs.next().unwrap();
-        let mut x_star = x0.clone();
-        let mut new_root = w0.clone();
+    }
 
-        for (xi, wi) in pairs {
-            new_root = shamir_trick(&new_root, wi, &x_star, xi, &self.n).unwrap();
-            x_star *= xi;
-            // for now this is not great, depends on this impl, not on the general design
-            self.set /= xi;
-        }
+    /// Enables a cache for up to `capacity` membership witnesses.
+    ///
+    /// `mem_wit_create` then remembers the witnesses it returns, and serves repeated requests
+    /// by updating the cached witness with the elements added and deleted since, instead of
+    /// recomputing it from the whole set. Once full, the stalest witness is evicted.
+    pub fn with_witness_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(RefCell::new(WitnessCache::new(capacity)));
+        self
+    }
 
-        self.root = new_root;
+    /// Enables a counting Bloom filter over the elements, sized for `capacity` elements with
+    /// a false positive rate of `fp_rate`, see `CountingBloomFilter::new`.
+    ///
+    /// `contains` then answers for most non-members without dividing the set, and
+    /// `non_mem_wit_create` rejects most members before computing the Bezout coefficients.
+    /// The filter is kept up to date on every addition and deletion, and is not serialized.
+    ///
+    /// Panics if the accumulator is not empty, as its elements are not known.
+    pub fn with_bloom_filter(mut self, capacity: usize, fp_rate: f64) -> Self {
+        assert!(self.is_empty(), "the filter must start with an empty set");
+        self.filter = Some(CountingBloomFilter::new(capacity, fp_rate));
+        self
+    }
 
-        Some(proofs::ni_poe_prove(&x_star, &self.root, &root_t, &self.n))
+    /// Returns the Bloom filter, if enabled.
+    pub fn bloom_filter(&self) -> Option<&CountingBloomFilter> {
+        self.filter.as_ref()
     }
 
-    fn ver_batch_del(&self, w: &BigUint, root: &BigUint, xs: &[BigUint]) -> bool {
-        let mut x_star = BigUint::one();
-        for x in xs {
-            x_star *= x
-        }
+    /// Records the identifiers added by `add_epoch` and `advance_epoch`, so that
+    /// `expire_epoch` can delete all elements of an epoch at once.
+    ///
+    /// Only identifiers added from now on are recorded. The index is serialized with the
+    /// accumulator.
+    pub fn with_epoch_index(mut self) -> Self {
+        self.epoch_index.get_or_insert_with(BTreeMap::new);
+        self
+    }
 
-        proofs::ni_poe_verify(&x_star, &self.root, root, &w, &self.n)
+    /// Limits the size of the set, additions exceeding the `limits` fail, see `try_add`.
+    pub fn with_capacity_limits(mut self, limits: CapacityLimits) -> Self {
+        self.limits = limits;
+        self
     }
 
-    fn del_w_mem(&mut self, w: &BigUint, x: &BigUint) -> Option<()> {
-        if !self.ver_mem(w, x) {
-            return None;
-        }
+    /// Sets the sizes of elements accepted by `add_checked` and `batch_add_checked`.
+    pub fn with_element_policy(mut self, policy: ElementPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
 
-        self.set /= x;
-        // w is root without x, so need to recompute
-        self.root = w.clone();
+    /// Returns the size of the modulus in bits, the security parameter.
+    pub fn lambda(&self) -> usize {
+        self.params.lambda
+    }
 
-        Some(())
+    /// Returns the parameters of the accumulator.
+    pub fn params(&self) -> &Arc<SetupParams> {
+        &self.params
     }
 
-    #[inline]
-    fn create_all_mem_wit(&self, set: &[BigUint]) -> Vec<BigUint> {
-        root_factor(&self.g, &set, &self.n)
+    /// Returns `true` if both accumulators use the same parameters, either the same shared
+    /// instance or one with the same `SetupParams::params_hash`.
+    pub fn shares_params(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.params, &other.params) || self.params == other.params
     }
 
-    fn agg_mem_wit(
+    /// Aggregates the witness `w_x` of `x` in this accumulator and `w_y` of `y` in `other`
+    /// into a single witness, like `mem_wit_x`, for `ver_mem_x` with the state of `other`.
+    ///
+    /// Returns `None` if the accumulators don't share their parameters.
+    pub fn mem_wit_x_shared(
         &self,
-        w_x: &BigUint,
-        w_y: &BigUint,
+        other: &Self,
+        w_x: &MembershipWitness,
+        w_y: &MembershipWitness,
         x: &BigUint,
         y: &BigUint,
-    ) -> (BigUint, BigUint) {
-        // TODO: check this matches, sth is not quite right in the paper here
-        let w_xy = shamir_trick(w_x, w_y, x, y, &self.n).unwrap();
-        let xy = x.clone() * y;
+    ) -> Option<MembershipWitness> {
+        if !self.shares_params(other) {
+            return None;
+        }
 
-        debug_assert!(
-            w_xy.modpow(&xy, &self.n) == self.root,
-            "invalid shamir trick"
-        );
+        Some(self.mem_wit_x(&other.root, w_x, w_y, x, y))
+    }
 
-        let pi = proofs::ni_poe_prove(&xy, &w_xy, &self.root, &self.n);
+    /// Aggregates membership witnesses across accumulators sharing the parameters of this
+    /// one, see `PublicState::mem_wit_x_many`.
+    pub fn mem_wit_x_many(
+        &self,
+        states: &[BigUint],
+        wits: &[MembershipWitness],
+        xs: &[BigUint],
+    ) -> Result<MembershipWitness, AggregateError> {
+        self.public_state().mem_wit_x_many(states, wits, xs)
+    }
 
-        (w_xy, pi)
+    /// Verifies a witness from `mem_wit_x_many`, see `PublicState::ver_mem_x_many`.
+    pub fn ver_mem_x_many(
+        &self,
+        states: &[BigUint],
+        pi: &MembershipWitness,
+        xs: &[BigUint],
+    ) -> bool {
+        self.public_state().ver_mem_x_many(states, pi, xs)
     }
 
-    fn ver_agg_mem_wit(&self, w_xy: &BigUint, pi: &BigUint, x: &BigUint, y: &BigUint) -> bool {
-        let xy = x.clone() * y;
-        proofs::ni_poe_verify(&xy, w_xy, &self.root, pi, &self.n)
+    /// Returns the sizes of elements accepted by the checked additions.
+    pub fn element_bits_policy(&self) -> &ElementPolicy {
+        &self.policy
     }
 
-    fn mem_wit_create_star(&self, x: &BigUint) -> (BigUint, BigUint) {
-        let w_x = self.mem_wit_create(x);
-        debug_assert!(self.root != w_x, "{} was not a member", x);
-        let p = proofs::ni_poe_prove(x, &w_x, &self.root, &self.n);
+    /// Adds `x`, like `try_add`, if its size is allowed by the element policy.
+    ///
+    /// Fails with `AccumulatorError::ElementSize` otherwise.
+    pub fn add_checked(&mut self, x: &BigUint) -> Result<(), AccumulatorError> {
+        self.policy.check(x)?;
+        self.try_add(x)
+    }
 
-        (w_x, p)
+    /// Adds all `xs`, like `try_batch_add`, if all their sizes are allowed by the element
+    /// policy, and no two of them share a factor. Adds none of them otherwise.
+    ///
+    /// For primes, sharing a factor means being repeated. Fails with
+    /// `AccumulatorError::CommonFactor` for the first such pair, found with `common_factor`.
+    pub fn batch_add_checked(&mut self, xs: &[BigUint]) -> Result<BatchProof, AccumulatorError> {
+        for x in xs {
+            if x <= &BigUint::one() {
+                return Err(AccumulatorError::InvalidElement);
+            }
+            self.policy.check(x)?;
+        }
+        if let Some((first, second)) = common_factor(xs) {
+            return Err(AccumulatorError::CommonFactor { first, second });
+        }
+        self.try_batch_add(xs)
     }
 
-    fn ver_mem_star(&self, x: &BigUint, pi: &(BigUint, BigUint)) -> bool {
-        proofs::ni_poe_verify(x, &pi.0, &self.root, &pi.1, &self.n)
+    /// Returns the number of accumulated elements.
+    ///
+    /// Accumulators deserialized from snapshots without the count start counting from zero.
+    pub fn len(&self) -> usize {
+        self.len
     }
 
-    fn mem_wit_x(
-        &self,
-        _other: &BigUint,
-        w_x: &BigUint,
-        w_y: &BigUint,
-        _x: &BigUint,
-        _y: &BigUint,
-    ) -> BigUint {
-        (w_x * w_y) % &self.n
+    /// Returns `true` if no elements are accumulated.
+    pub fn is_empty(&self) -> bool {
+        self.set.is_one()
     }
 
-    fn ver_mem_x(&self, other: &BigUint, pi: &BigUint, x: &BigUint, y: &BigUint) -> bool {
-        // assert x and y are coprime
-        let q = x.gcd(y);
-        if !q.is_one() {
-            return false;
-        }
+    /// Returns the size of the product of all elements, in bits.
+    pub fn set_bits(&self) -> usize {
+        self.set.bits()
+    }
 
-        // A_1^y
-        let rhs_a = self.root.modpow(y, &self.n);
-        // A_2^x
-        let rhs_b = other.modpow(x, &self.n);
+    /// Adds `x`, like `add`.
+    ///
+    /// Fails with `AccumulatorError::CapacityExceeded`, leaving the accumulator unchanged, if
+    /// this would exceed the capacity limits.
+    pub fn try_add(&mut self, x: &BigUint) -> Result<(), AccumulatorError> {
+        debug_assert!(
+            self.params.g.clone().modpow(&self.set, &self.params.n) == self.root,
+            "invalid state - pre add"
+        );
 
-        // A_1^y * A_2^x
-        let rhs = (rhs_a * rhs_b) % &self.n;
-        // pi^{x * y}
-        let lhs = pi.modpow(&(x.clone() * y), &self.n);
+        // assumes x is already a prime
+        let set = &self.set * x;
+        self.check_capacity(1, &set)?;
+
+        let old_root = self.root.clone();
+        self.set = set;
+        self.len += 1;
+        self.root = self.root.modpow(x, &self.params.n);
+        self.cache_added(x);
+        self.filter_added(std::slice::from_ref(x));
+        self.log_added(&old_root, std::slice::from_ref(x));
+        Ok(())
+    }
+
+    /// Adds all `xs`, like `batch_add`.
+    ///
+    /// Fails like `try_add`, adding none of the elements.
+    pub fn try_batch_add(&mut self, xs: &[BigUint]) -> Result<BatchProof, AccumulatorError> {
+        let _span = span!(
+            "batch_add",
+            elements = xs.len(),
+            modulus_bits = self.params.n.bits()
+        );
+        //temp clone our old root
+        let root_t = self.root.clone();
+        //calculate our new root after all the added elements
+        let x_star = self.add_product(xs)?;
+        //create our proof for the procedure, streaming the quotient over the elements
+        let params = self.group_params();
+        Ok(BatchProof(proofs::prove_factored(
+            &params.proof_params.context,
+            &x_star,
+            xs,
+            &root_t,
+            &self.root,
+            &params.n,
+        )))
+    }
 
-        lhs == rhs
+    /// Adds all `xs`, like `try_batch_add`, and packages the proof for archiving, see
+    /// `ProofPackage`.
+    pub fn package_batch_add(&mut self, xs: &[BigUint]) -> Result<ProofPackage, AccumulatorError> {
+        let old_state = self.root.clone();
+        let w = self.try_batch_add(xs)?;
+
+        Ok(ProofPackage::new(
+            &self.params,
+            &self.root,
+            xs.to_vec(),
+            PackageKind::BatchAdd { old_state },
+            &w,
+            self.epoch,
+        ))
     }
 
-    fn non_mem_wit_create_star(
-        &self,
+    /// Deletes `x`, like `del`, and returns the new state as a membership witness for `x`,
+    /// e.g. to archive the inclusion of what was just removed.
+    ///
+    /// The witness verifies against the state before the call, with `ver_mem_at`, not the
+    /// current one. Deleting doesn't start a new epoch, so that is a state of the current
+    /// epoch. Fails with `AccumulatorError::NotAMember`, leaving the accumulator unchanged, if
+    /// `x` is not a member.
+    pub fn del_returning_witness(
+        &mut self,
         x: &BigUint,
-    ) -> (BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint) {
-        let g = &self.g;
-        let n = &self.n;
+    ) -> Result<MembershipWitness, AccumulatorError> {
+        self.del(x).ok_or(AccumulatorError::NotAMember)?;
 
-        // a, b <- Bezout(x, s_star)
-        //let (_, a, b) = x.extended_gcd(&self.set);
-        let (_, a, b) = num_bigint::traits::ExtendedGcd::extended_gcd(x, &self.set);
+        Ok(MembershipWitness(self.root.clone()))
+    }
 
-        // d <- g^a
-        let d = modpow_uint_int(g, &a, n).expect("invalid state");
-        // v <- A^b
-        let v = modpow_uint_int(&self.root, &b, n).expect("invalid state");
+    /// Deletes `x` given its membership witness `w`, like `del_w_mem`, and returns the new
+    /// state as a membership witness for `x` against the state before, like
+    /// `del_returning_witness`.
+    ///
+    /// Fails with `AccumulatorError::NotAMember`, leaving the accumulator unchanged, if `w`
+    /// is not a valid witness for `x`.
+    pub fn del_w_mem_returning_witness(
+        &mut self,
+        w: &MembershipWitness,
+        x: &BigUint,
+    ) -> Result<MembershipWitness, AccumulatorError> {
+        self.del_w_mem(w, x).ok_or(AccumulatorError::NotAMember)?;
 
-        // pi_d <- NI-PoKE2(b, A, v)
-        let pi_d = proofs::ni_poke2_prove(b, &self.root, &v, n);
+        Ok(MembershipWitness(self.root.clone()))
+    }
 
-        // k <- g * v^-1
-        let k = (g * v
-            .clone()
-            .mod_inverse(n)
-            .expect("invalid state")
-            .into_biguint()
-            .unwrap())
-            % n;
+    /// Fails if adding `count` elements, resulting in the product `set`, exceeds the limits.
+    fn check_capacity(&self, count: usize, set: &BigUint) -> Result<(), AccumulatorError> {
+        let elements = self.len.saturating_add(count);
+        let set_bits = set.bits();
 
-        // pi_g <- NI-PoE(x, d, g * v^-1)
-        let pi_g = proofs::ni_poe_prove(x, &d, &k, n);
+        let too_many = self.limits.max_elements.is_some_and(|max| elements > max);
+        let too_large = self.limits.max_set_bits.is_some_and(|max| set_bits > max);
+        if too_many || too_large {
+            return Err(AccumulatorError::CapacityExceeded { elements, set_bits });
+        }
 
-        // return {d, v, pi_d, pi_g}
-        (d, v, pi_d, pi_g)
+        Ok(())
     }
 
-    fn ver_non_mem_star(
-        &self,
-        x: &BigUint,
-        pi: &(BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint),
-    ) -> bool {
-        let g = &self.g;
-        let n = &self.n;
+    /// Merges `other` into this accumulator, resulting in one over the union of both sets.
+    /// Returns the merged accumulator, and a proof for `ver_merge`.
+    ///
+    /// Keeps the limits, policy and witness cache of `self`. Fails if the accumulators use
+    /// different parameters, the sets overlap, or the union exceeds the capacity limits.
+    pub fn merge(mut self, other: Self) -> Result<(Self, MergeProof), MergeError> {
+        if !self.shares_params(&other) {
+            return Err(MergeError::ParamMismatch);
+        }
+        if !self.set.gcd(&other.set).is_one() {
+            return Err(MergeError::Overlap);
+        }
 
-        let (d, v, pi_d, pi_g) = pi;
+        let set = &self.set * &other.set;
+        self.check_capacity(other.len, &set)
+            .map_err(MergeError::Capacity)?;
+
+        // g^(s_1 s_2) = A_1^s_2
+        let root = self.root.modpow(&other.set, &self.params.n);
+        let pi = prove_merge(
+            &self.public_state(),
+            &other.public_state(),
+            &root,
+            &other.set,
+        );
 
-        // verify NI-PoKE2
-        if !proofs::ni_poke2_verify(&self.root, &v, pi_d, n) {
-            return false;
+        self.set = set;
+        self.len += other.len;
+        self.root = root;
+        self.cache_added(&other.set);
+        let new_state = self.root.clone();
+        if let Some(log) = &mut self.updates {
+            log.push(|seq| UpdateMessage::Merge {
+                seq,
+                other_state: other.root.clone(),
+                proof: pi.clone(),
+                new_state,
+            });
+        }
+        // the elements of `other` are unknown, so they can only come from its filter
+        if let Some(filter) = &mut self.filter {
+            if other
+                .filter
+                .as_ref()
+                .and_then(|f| filter.union_with(f))
+                .is_none()
+            {
+                self.filter = None;
+            }
+        }
+        if let (Some(index), Some(other_index)) = (&mut self.epoch_index, other.epoch_index) {
+            for (epoch, ids) in other_index {
+                index.entry(epoch).or_default().extend(ids);
+            }
         }
 
-        // verify NI-PoE
-        let k = (g * v
-            .clone()
-            .mod_inverse(n)
-            .expect("invalid state")
-            .into_biguint()
-            .unwrap())
-            % n;
+        Ok((self, pi))
+    }
 
-        if !proofs::ni_poe_verify(x, d, &k, pi_g, n) {
-            return false;
+    /// Deletes the `(element, witness)` pairs and then adds all `xs`, proven together with a
+    /// MultiSwap proof, see `verify_block`. Returns `None` and leaves the accumulator unchanged
+    /// if a witness does not verify, or an added element is already a member.
+    ///
+    /// Panics if the additions exceed the capacity limits.
+    pub fn multi_swap(
+        &mut self,
+        pairs: &[(BigUint, MembershipWitness)],
+        xs: &[BigUint],
+    ) -> Option<BlockProof> {
+        if !pairs.iter().all(|(x, w)| self.ver_mem(w, x)) || xs.iter().any(|x| self.contains(x)) {
+            return None;
         }
 
-        true
-    }
-}
+        // recorded as a single update
+        let updates = self.updates.take();
+        let del = match self.batch_del(pairs) {
+            Some(proof) => proof,
+            // nothing deleted, prove the identity
+            None => BatchProof(proofs::poe_prove(
+                &self.group_params(),
+                &BigUint::one(),
+                &self.root,
+                &self.root,
+            )),
+        };
+        let intermediate = self.root.clone();
+        let add = self.batch_add(xs);
+        self.updates = updates;
+
+        let proof = BlockProof {
+            intermediate,
+            del,
+            add,
+        };
+        let new_state = self.root.clone();
+        if let Some(log) = &mut self.updates {
+            log.push(|seq| UpdateMessage::MultiSwap {
+                seq,
+                deleted: pairs.iter().map(|(x, _)| x.clone()).collect(),
+                added: xs.to_vec(),
+                proof: proof.clone(),
+                new_state,
+            });
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        Some(proof)
+    }
 
-    use crate::group::RSAGroup;
-    use num_bigint::RandPrime;
-    use num_bigint::Sign;
-    use num_traits::FromPrimitive;
-    use rand::SeedableRng;
-    use rand_chacha::ChaChaRng;
+    /// Takes an immutable snapshot of the current state, for sharing between threads.
+    ///
+    /// This copies the set once, clones of the snapshot are cheap.
+    pub fn freeze(&self) -> FrozenAccumulator {
+        FrozenAccumulator::new(
+            Arc::clone(&self.params),
+            self.root.clone(),
+            self.set.clone(),
+        )
+    }
 
-    #[test]
-    fn test_static() {
-        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+    /// Returns the product of all accumulated elements.
+    pub(crate) fn product(&self) -> &BigUint {
+        &self.set
+    }
 
-        for _ in 0..100 {
-            let int_size_bits = 256; // insecure, but faster tests
-            let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+    /// Returns the number of cached membership witnesses.
+    pub fn cached_witnesses(&self) -> usize {
+        self.cache.as_ref().map_or(0, |cache| cache.borrow().len())
+    }
 
-            let xs = (0..5)
-                .map(|_| rng.gen_prime(int_size_bits))
-                .collect::<Vec<_>>();
+    /// Records added elements in the witness cache.
+    fn cache_added(&mut self, x_star: &BigUint) {
+        if let Some(cache) = &mut self.cache {
+            cache.get_mut().added(x_star);
+        }
+    }
 
-            for x in &xs {
-                acc.add(x);
+    /// Records deleted elements in the witness cache and the Bloom filter.
+    fn record_deleted(&mut self, xs: &[BigUint]) {
+        if let Some(cache) = &mut self.cache {
+            cache.get_mut().deleted(xs);
+        }
+        if let Some(filter) = &mut self.filter {
+            for x in xs {
+                filter.remove(x);
             }
+        }
+    }
 
-            for x in &xs {
-                let w = acc.mem_wit_create(x);
-                assert!(acc.ver_mem(&w, x));
+    /// Records added elements in the Bloom filter.
+    fn filter_added(&mut self, xs: &[BigUint]) {
+        if let Some(filter) = &mut self.filter {
+            for x in xs {
+                filter.insert(x);
             }
         }
     }
 
-    #[test]
-    fn test_dynamic() {
-        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+    /// Records the addition of `xs` to `old_root` in the update log, with a proof for it.
+    fn log_added(&mut self, old_root: &BigUint, xs: &[BigUint]) {
+        if self.updates.is_none() {
+            return;
+        }
 
-        for _ in 0..20 {
-            let int_size_bits = 256; // insecure, but faster tests
-            let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let proof = proofs::poe_prove(&self.group_params(), &product(xs), old_root, &self.root);
+        let new_state = self.root.clone();
+        if let Some(log) = &mut self.updates {
+            log.push(|seq| UpdateMessage::BatchAdd {
+                seq,
+                elements: xs.to_vec(),
+                proof: BatchProof(proof),
+                new_state,
+            });
+        }
+    }
 
-            let xs = (0..5)
-                .map(|_| rng.gen_prime(int_size_bits))
-                .collect::<Vec<_>>();
+    /// Records the deletion of `xs` from `old_root` in the update log, with a proof for it.
+    fn log_deleted(&mut self, old_root: &BigUint, xs: &[BigUint]) {
+        if self.updates.is_none() {
+            return;
+        }
 
-            for x in &xs {
-                acc.add(x);
-            }
+        let proof = proofs::poe_prove(&self.group_params(), &product(xs), &self.root, old_root);
+        let new_state = self.root.clone();
+        if let Some(log) = &mut self.updates {
+            log.push(|seq| UpdateMessage::BatchDel {
+                seq,
+                elements: xs.to_vec(),
+                proof: BatchProof(proof),
+                new_state,
+            });
+        }
+    }
 
-            let ws = xs
-                .iter()
-                .map(|x| {
-                    let w = acc.mem_wit_create(x);
-                    assert!(acc.ver_mem(&w, x));
-                    w
-                })
-                .collect::<Vec<_>>();
+    /// Starts recording all mutations for followers, and returns the messages recorded since
+    /// the last call, in order. The first call returns an empty log.
+    ///
+    /// Every mutation is recorded with a proof against the state before it, so that a
+    /// `VerifierState` created with `verifier_state` can follow along with `apply_update`.
+    /// Mutations without a proof of their own, like `add`, are proven when recorded.
+    pub fn record_updates(&mut self) -> UpdateLog {
+        self.updates
+            .get_or_insert_with(UpdateRecorder::default)
+            .take()
+    }
 
-            for (x, w) in xs.iter().zip(ws.iter()) {
-                // remove x
-                acc.del(x).unwrap();
-                // make sure test now fails
-                assert!(!acc.ver_mem(w, x));
-            }
-        }
+    /// Returns a follower for the current state, expecting the next recorded update.
+    pub fn verifier_state(&self) -> VerifierState {
+        let next_seq = self.updates.as_ref().map_or(0, UpdateRecorder::next_seq);
+        VerifierState::new(self.public_state(), next_seq)
     }
 
-    #[test]
-    fn test_universal() {
-        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+    /// Returns the group of the accumulator, with the default proof parameters used for all
+    /// of its proofs.
+    pub fn group_params(&self) -> GroupParams {
+        GroupParams::new(self.params.n.clone())
+    }
 
-        for _ in 0..20 {
-            let int_size_bits = 256; // insecure, but faster tests
-            let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+    /// Returns the modulus `n` of the underlying group.
+    pub fn modulus(&self) -> &BigUint {
+        &self.params.n
+    }
 
-            let xs = (0..5)
-                .map(|_| rng.gen_prime(int_size_bits))
-                .collect::<Vec<_>>();
+    /// Returns the generator `g` the accumulator was initialized with.
+    pub fn generator(&self) -> &BigUint {
+        &self.params.g
+    }
 
-            for x in &xs {
+    /// Returns the public part of the accumulator.
+    pub fn public_state(&self) -> PublicState {
+        PublicState {
+            n: self.params.n.clone(),
+            g: self.params.g.clone(),
+            root: self.root.clone(),
+        }
+    }
+
+    /// Verifies a membership witness for `x` against the past state `root`, see
+    /// `PublicState::ver_mem_at`.
+    ///
+    /// Unlike the `PublicState` verifiers, the verifiers of the accumulator also reject
+    /// witnesses and proofs with group elements outside of its group, see
+    /// `SetupParams::in_group`.
+    pub fn ver_mem_at(&self, root: &BigUint, w: &MembershipWitness, x: &BigUint) -> bool {
+        self.in_group(&[&w.0]) && self.public_state().ver_mem_at(root, w, x)
+    }
+
+    /// Verifies a non-membership witness for `x` against the past state `root`.
+    ///
+    /// Fails if the size of `x` is not allowed by the element policy.
+    pub fn ver_non_mem_at(&self, root: &BigUint, w: &NonMembershipWitness, x: &BigUint) -> bool {
+        self.policy.check(x).is_ok()
+            && self.in_group(&[&w.d])
+            && self.public_state().ver_non_mem_at(root, w, x)
+    }
+
+    /// Verifies a membership proof for `x` against the past state `root`.
+    pub fn ver_mem_star_at(&self, root: &BigUint, x: &BigUint, pi: &MembershipProof) -> bool {
+        self.in_group(&[&pi.w, &pi.pi.q]) && self.public_state().ver_mem_star_at(root, x, pi)
+    }
+
+    /// Verifies all membership witnesses of `pairs`, see `PublicState::ver_mem_all`.
+    pub fn ver_mem_all(&self, pairs: &[(BigUint, MembershipWitness)]) -> Vec<usize> {
+        let outside = pairs.iter().map(|(_, w)| !self.in_group(&[&w.0]));
+        merge_failed(self.public_state().ver_mem_all(pairs), outside)
+    }
+
+    /// Verifies all membership proofs of `pairs`, see `PublicState::ver_mem_star_all`.
+    pub fn ver_mem_star_all(&self, pairs: &[(BigUint, MembershipProof)]) -> Vec<usize> {
+        let outside = pairs
+            .iter()
+            .map(|(_, pi)| !self.in_group(&[&pi.w, &pi.pi.q]));
+        merge_failed(self.public_state().ver_mem_star_all(pairs), outside)
+    }
+
+    /// Returns `false` if any of `vs`, received from a prover, is certainly not in the group.
+    fn in_group(&self, vs: &[&BigUint]) -> bool {
+        vs.iter().all(|v| self.params.in_group(v))
+    }
+
+    /// Returns `true` if `x` is a member.
+    pub fn contains(&self, x: &BigUint) -> bool {
+        if self.filter.as_ref().is_some_and(|f| !f.may_contain(x)) {
+            return false;
+        }
+
+        !x.is_zero() && !x.is_one() && self.set.is_multiple_of(x)
+    }
+
+    /// Creates a membership witness for `x`, like `mem_wit_create`.
+    ///
+    /// Fails with `AccumulatorError::NotAMember`, instead of panicking, if `x` is not a member.
+    pub fn try_mem_wit_create(&self, x: &BigUint) -> Result<MembershipWitness, AccumulatorError> {
+        debug_assert!(
+            self.params.g.clone().modpow(&self.set, &self.params.n) == self.root,
+            "invalid state"
+        );
+
+        if let Some(cache) = &self.cache {
+            if let Some(w) = cache.borrow_mut().get(x, &self.root, &self.params.n) {
+                debug_assert!(self.ver_mem(&w, x), "invalid cached witness");
+                return Ok(w);
+            }
+        }
+
+        let w = mem_witness(&self.params.g, &self.params.n, &self.set, x)?;
+        if let Some(cache) = &self.cache {
+            cache.borrow_mut().insert(x.clone(), w.clone());
+        }
+
+        Ok(w)
+    }
+
+    /// Creates a membership witness for `x` and a NI-PoE for it, like `mem_wit_create_star`.
+    ///
+    /// Fails with `AccumulatorError::NotAMember`, instead of panicking, if `x` is not a member.
+    pub fn try_mem_wit_create_star(
+        &self,
+        x: &BigUint,
+    ) -> Result<MembershipProof, AccumulatorError> {
+        let w_x = self.try_mem_wit_create(x)?;
+
+        Ok(mem_proof(&self.group_params(), &self.root, x, w_x))
+    }
+
+    /// Creates a single witness for all of `xs`, the root of the state by their product, with
+    /// one NI-PoE for it. Verified with `ver_batch_mem`.
+    ///
+    /// Gives the same witness as `compress_witnesses` on their individual witnesses, but
+    /// divides the product out of the set once instead of computing and folding them. Fails
+    /// with the index of the first element that is repeated or not a member.
+    pub fn batch_mem_wit_create(&self, xs: &[BigUint]) -> Result<CompressedWitness, BatchMemError> {
+        let (w, x_star) = self.batch_mem_root(xs)?;
+        let pi = proofs::poe_prove(&self.group_params(), &x_star, &w, &self.root);
+
+        Ok(CompressedWitness { w, pi })
+    }
+
+    /// Returns the root of the state by the product of `xs`, and the product, checking `xs`
+    /// like `batch_mem_wit_create`.
+    fn batch_mem_root(&self, xs: &[BigUint]) -> Result<(BigUint, BigUint), BatchMemError> {
+        if xs.is_empty() {
+            return Err(BatchMemError::Empty);
+        }
+
+        let mut seen = HashSet::with_capacity(xs.len());
+        for (i, x) in xs.iter().enumerate() {
+            if !seen.insert(x) {
+                return Err(BatchMemError::Duplicate(i));
+            }
+            if !self.contains(x) {
+                return Err(BatchMemError::NotAMember(i));
+            }
+        }
+
+        let x_star = product(xs);
+        let (set, r) = self.set.div_rem(&x_star);
+        if !r.is_zero() {
+            // members sharing a factor, find the first one not dividing what is left
+            let mut set = self.set.clone();
+            for (i, x) in xs.iter().enumerate() {
+                let (q, r) = set.div_rem(x);
+                if !r.is_zero() {
+                    return Err(BatchMemError::NotAMember(i));
+                }
+                set = q;
+            }
+            unreachable!("the product divides the set");
+        }
+
+        Ok((self.params.g.modpow(&set, &self.params.n), x_star))
+    }
+
+    /// Verifies a witness from `batch_mem_wit_create`, see `PublicState::ver_batch_mem`.
+    pub fn ver_batch_mem(&self, xs: &[BigUint], cw: &CompressedWitness) -> bool {
+        self.public_state().ver_batch_mem(xs, cw)
+    }
+
+    /// Creates a non-membership witness for `x`, like `non_mem_wit_create`.
+    ///
+    /// Fails with `AccumulatorError::IsMember`, instead of panicking, if `x` is not coprime
+    /// to the members, and with `AccumulatorError::InvalidElement` for `0` and `1`.
+    pub fn try_non_mem_wit_create(
+        &self,
+        x: &BigUint,
+    ) -> Result<NonMembershipWitness, AccumulatorError> {
+        // a division is much cheaper than the Bezout coefficients, check likely members first
+        if self.filter.is_some() && self.contains(x) {
+            return Err(AccumulatorError::IsMember);
+        }
+
+        non_mem_witness(&self.params.g, &self.params.n, &self.set, x)
+    }
+
+    /// Creates a non-membership proof for `x`, like `non_mem_wit_create_star`.
+    ///
+    /// Fails like `try_non_mem_wit_create`.
+    pub fn try_non_mem_wit_create_star(
+        &self,
+        x: &BigUint,
+    ) -> Result<NonMembershipProof, AccumulatorError> {
+        non_mem_proof(&self.params.g, &self.params.n, &self.root, &self.set, x)
+    }
+
+    /// Creates a membership proof for `x`, like `try_mem_wit_create_star`, packaged for
+    /// archiving, see `ProofPackage`.
+    pub fn package_mem_proof(&self, x: &BigUint) -> Result<ProofPackage, AccumulatorError> {
+        let pi = self.try_mem_wit_create_star(x)?;
+
+        Ok(self.package(x, PackageKind::Membership, &pi))
+    }
+
+    /// Creates a non-membership proof for `x`, like `try_non_mem_wit_create_star`, packaged
+    /// for archiving, see `ProofPackage`.
+    pub fn package_non_mem_proof(&self, x: &BigUint) -> Result<ProofPackage, AccumulatorError> {
+        let pi = self.try_non_mem_wit_create_star(x)?;
+
+        Ok(self.package(x, PackageKind::NonMembership, &pi))
+    }
+
+    fn package<P: Packed>(&self, x: &BigUint, kind: PackageKind, pi: &P) -> ProofPackage {
+        ProofPackage::new(
+            &self.params,
+            &self.root,
+            vec![x.clone()],
+            kind,
+            pi,
+            self.epoch,
+        )
+    }
+
+    /// Creates a single constant size non-membership proof for all of `xs`, the proof of
+    /// `non_mem_wit_create_star` for their product. Verified with `ver_batch_non_mem_star`.
+    ///
+    /// Takes one Bezout computation against the set, one NI-PoKE2 and one NI-PoE, instead of
+    /// one each per element. Fails with the index of the first element that is a member, or
+    /// shares a factor with one.
+    pub fn batch_non_mem_wit_create_star(
+        &self,
+        xs: &[BigUint],
+    ) -> Result<BatchNonMemStarProof, BatchNonMemError> {
+        let (x_star, a, b) = self.batch_non_mem_bezout(xs)?;
+        let d = modpow_uint_int(&self.params.g, &a, &self.params.n)?;
+        let v = modpow_uint_int(&self.root, &b, &self.params.n)?;
+        let params = self.group_params();
+
+        Ok(BatchNonMemStarProof(non_mem_proof_parts(
+            &params,
+            &self.params.g,
+            &self.root,
+            &x_star,
+            b,
+            d,
+            v,
+        )?))
+    }
+
+    /// Returns the product of `xs` and its Bezout coefficients with the set, checking `xs`
+    /// like `batch_non_mem_wit_create_star`.
+    fn batch_non_mem_bezout(
+        &self,
+        xs: &[BigUint],
+    ) -> Result<(BigUint, BigInt, BigInt), BatchNonMemError> {
+        if xs.is_empty() {
+            return Err(BatchNonMemError::Empty);
+        }
+        if let Some(i) = xs.iter().position(|x| x.is_zero() || x.is_one()) {
+            return Err(BatchNonMemError::InvalidElement(i));
+        }
+        if self.filter.is_some() {
+            if let Some(i) = xs.iter().position(|x| self.contains(x)) {
+                return Err(BatchNonMemError::IsMember(i));
+            }
+        }
+
+        let x_star = product(xs);
+        match non_mem_bezout(&self.set, &x_star) {
+            Ok((a, b)) => Ok((x_star, a, b)),
+            Err(_) => {
+                // only now pay for one gcd per element, to find the offending one
+                let i = xs
+                    .iter()
+                    .position(|x| !x.gcd(&self.set).is_one())
+                    .expect("the product shares a factor with the set");
+                Err(BatchNonMemError::IsMember(i))
+            }
+        }
+    }
+
+    /// Verifies a proof from `batch_non_mem_wit_create_star`, see
+    /// `PublicState::ver_batch_non_mem_star`.
+    pub fn ver_batch_non_mem_star(&self, xs: &[BigUint], pi: &BatchNonMemStarProof) -> bool {
+        self.public_state().ver_batch_non_mem_star(xs, pi)
+    }
+
+    /// Proves that all of `include` are members and none of `exclude` are, in one bundle for
+    /// `ver_inclusion_exclusion`.
+    ///
+    /// Checks the elements like `batch_mem_wit_create` and `batch_non_mem_wit_create_star`,
+    /// and fails if an element is in both.
+    pub fn prove_inclusion_exclusion(
+        &self,
+        include: &[BigUint],
+        exclude: &[BigUint],
+    ) -> Result<InclusionExclusionProof, InclusionExclusionError> {
+        if let Some(i) = overlap(include, exclude) {
+            return Err(InclusionExclusionError::Overlap(i));
+        }
+        let (w, x_in) = self
+            .batch_mem_root(include)
+            .map_err(InclusionExclusionError::Include)?;
+        let (x_out, a, b) = self
+            .batch_non_mem_bezout(exclude)
+            .map_err(InclusionExclusionError::Exclude)?;
+
+        prove_inclusion_exclusion(&self.public_state(), w, &x_in, &x_out, &a, b)
+            .map_err(|err| InclusionExclusionError::Exclude(err.into()))
+    }
+
+    /// Adds the prime `x`, like `try_add`.
+    pub fn add_prime(&mut self, x: &PrimeElement) -> Result<(), AccumulatorError> {
+        self.try_add(x.as_biguint())
+    }
+
+    /// Adds all primes `xs`, like `try_batch_add`.
+    pub fn batch_add_primes(
+        &mut self,
+        xs: &[PrimeElement],
+    ) -> Result<BatchProof, AccumulatorError> {
+        let xs = xs
+            .iter()
+            .map(|x| x.as_biguint().clone())
+            .collect::<Vec<_>>();
+        self.try_batch_add(&xs)
+    }
+
+    /// Deletes the prime `x`, like `del`.
+    pub fn del_prime(&mut self, x: &PrimeElement) -> Option<()> {
+        self.del(x.as_biguint())
+    }
+
+    /// Creates a membership witness for the prime `x`, like `try_mem_wit_create`.
+    pub fn mem_wit_create_prime(
+        &self,
+        x: &PrimeElement,
+    ) -> Result<MembershipWitness, AccumulatorError> {
+        self.try_mem_wit_create(x.as_biguint())
+    }
+
+    /// Creates a non-membership witness for the prime `x`, like `try_non_mem_wit_create`.
+    pub fn non_mem_wit_create_prime(
+        &self,
+        x: &PrimeElement,
+    ) -> Result<NonMembershipWitness, AccumulatorError> {
+        self.try_non_mem_wit_create(x.as_biguint())
+    }
+
+    /// Returns the current epoch, starting at `0`.
+    pub fn current_epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Adds the identifier `id`, valid during `epoch`, as the prime `epoch_element(id, epoch)`.
+    pub fn add_epoch(&mut self, id: &[u8], epoch: u64) -> Result<(), AccumulatorError> {
+        self.add_prime(&epoch_element(id, epoch))?;
+        self.index_epoch(epoch, &[id]);
+
+        Ok(())
+    }
+
+    /// Creates a membership witness for the identifier `id` in `epoch`.
+    pub fn mem_wit_create_epoch(
+        &self,
+        id: &[u8],
+        epoch: u64,
+    ) -> Result<MembershipWitness, AccumulatorError> {
+        self.mem_wit_create_prime(&epoch_element(id, epoch))
+    }
+
+    /// Verifies a membership witness for the identifier `id` in `epoch`, see
+    /// `PublicState::ver_mem_epoch`.
+    pub fn ver_mem_epoch(&self, w: &MembershipWitness, id: &[u8], epoch: u64) -> bool {
+        self.public_state().ver_mem_epoch(w, id, epoch)
+    }
+
+    /// Starts the next epoch, and adds the identifiers in `carry_over` under it, with a single
+    /// proof for all of them.
+    ///
+    /// The elements of earlier epochs stay members until they are deleted. Fails like
+    /// `try_batch_add`, staying in the current epoch.
+    pub fn advance_epoch<I: AsRef<[u8]>>(
+        &mut self,
+        carry_over: &[I],
+    ) -> Result<BatchProof, AccumulatorError> {
+        let epoch = self.epoch + 1;
+        let xs = carry_over
+            .iter()
+            .map(|id| epoch_element(id.as_ref(), epoch).into_biguint())
+            .collect::<Vec<_>>();
+
+        let pi = self.try_batch_add(&xs)?;
+        self.epoch = epoch;
+        self.index_epoch(epoch, carry_over);
+
+        Ok(pi)
+    }
+
+    /// Deletes the elements of all identifiers recorded under `epoch`, see `with_epoch_index`,
+    /// with a single exponentiation. Returns them with a proof for `ver_expiry`.
+    ///
+    /// Identifiers that were deleted since are skipped. Membership witnesses of the remaining
+    /// elements are updated like for `batch_del`, with `ExpiryProof::elements`. Fails with
+    /// `AccumulatorError::NoEpochIndex` if the index is not enabled.
+    pub fn expire_epoch(&mut self, epoch: u64) -> Result<ExpiryProof, AccumulatorError> {
+        let mut ids = self
+            .epoch_index
+            .as_mut()
+            .ok_or(AccumulatorError::NoEpochIndex)?
+            .remove(&epoch)
+            .unwrap_or_default();
+        let _span = span!("expire_epoch", epoch = epoch, elements = ids.len());
+
+        let mut xs = ids
+            .iter()
+            .map(|id| epoch_element(id, epoch).into_biguint())
+            .collect::<Vec<_>>();
+        let (mut set, r) = self.set.div_rem(&product(&xs));
+        if !r.is_zero() {
+            // some were deleted, remove them one by one
+            set = self.set.clone();
+            let mut kept = Vec::with_capacity(xs.len());
+            for (id, x) in ids.into_iter().zip(xs) {
+                let (q, r) = set.div_rem(&x);
+                if r.is_zero() {
+                    set = q;
+                    kept.push((id, x));
+                }
+            }
+            let (kept_ids, kept_xs) = kept.into_iter().unzip();
+            ids = kept_ids;
+            xs = kept_xs;
+        }
+
+        let old_root = self.root.clone();
+        self.set = set;
+        self.len = self.len.saturating_sub(xs.len());
+        self.root = self.params.g.modpow(&self.set, &self.params.n);
+        self.record_deleted(&xs);
+        self.log_deleted(&old_root, &xs);
+
+        let proof = proofs::poe_prove(&self.group_params(), &product(&xs), &self.root, &old_root);
+        Ok(ExpiryProof {
+            epoch,
+            ids,
+            proof: BatchProof(proof),
+        })
+    }
+
+    /// Verifies that the elements of `pi` were deleted from the state `root`, resulting in
+    /// the current state, see `PublicState::ver_expiry`.
+    pub fn ver_expiry(&self, pi: &ExpiryProof, root: &BigUint) -> bool {
+        self.public_state().ver_expiry(pi, root)
+    }
+
+    /// Records `ids` under `epoch`, if the epoch index is enabled.
+    fn index_epoch<I: AsRef<[u8]>>(&mut self, epoch: u64, ids: &[I]) {
+        if let Some(index) = &mut self.epoch_index {
+            let entry = index.entry(epoch).or_default();
+            entry.extend(ids.iter().map(|id| id.as_ref().to_vec()));
+        }
+    }
+
+    /// Adds all `xs`, like `batch_add`, but without creating a proof.
+    /// Meant for bulk insertion, e.g. when restoring the accumulator from a snapshot.
+    ///
+    /// Panics if this exceeds the capacity limits.
+    pub fn add_batch_no_proof(&mut self, xs: &[BigUint]) {
+        self.add_product(xs).expect("capacity exceeded");
+    }
+
+    /// Adds all elements yielded by `xs`, see `add_batch_no_proof`.
+    pub fn add_iter_no_proof<I: IntoIterator<Item = BigUint>>(&mut self, xs: I) {
+        let xs = xs.into_iter().collect::<Vec<_>>();
+        self.add_product(&xs).expect("capacity exceeded");
+    }
+
+    /// Adds all elements yielded by `xs` with a single exponentiation, if their sizes are
+    /// allowed by the element policy. Returns the number of elements added.
+    ///
+    /// The product is folded as the elements arrive, see `product_iter`, so they are never
+    /// collected. Fails with `AccumulatorError::InvalidElement` for `0` and `1`, and checks the
+    /// sizes like `batch_add_checked` otherwise, adding none of the elements. Unlike it, this
+    /// can't check for common factors without collecting the elements.
+    pub fn try_add_iter<I: IntoIterator<Item = BigUint>>(
+        &mut self,
+        xs: I,
+    ) -> Result<usize, AccumulatorError> {
+        let policy = self.policy;
+        let filter = self.filter.as_ref();
+        let recording = self.updates.is_some();
+        let mut positions = Vec::new();
+        let mut logged = Vec::new();
+        let mut count = 0;
+        let mut result = Ok(());
+
+        let x_star = product_iter(xs.into_iter().map_while(|x| {
+            let checked = if x <= BigUint::one() {
+                Err(AccumulatorError::InvalidElement)
+            } else {
+                policy.check(&x)
+            };
+            match checked {
+                Ok(()) => {
+                    count += 1;
+                    if let Some(filter) = filter {
+                        positions.extend(filter.positions(&x));
+                    }
+                    if recording {
+                        logged.push(x.clone());
+                    }
+                    Some(x)
+                }
+                Err(err) => {
+                    result = Err(err);
+                    None
+                }
+            }
+        }));
+        result?;
+
+        let old_root = self.root.clone();
+        self.add_x_star(x_star, count)?;
+        if let Some(filter) = &mut self.filter {
+            for i in positions {
+                filter.increment(i);
+            }
+        }
+        self.log_added(&old_root, &logged);
+
+        Ok(count)
+    }
+
+    /// Adds `xs` in chunks of at most `chunk_size` elements, each with its own proof, so that
+    /// no single exponent is the product of more than `chunk_size` elements.
+    ///
+    /// Chunks are committed in order, each one atomically, and the `i`th result describes the
+    /// `i`th chunk. Adding only fails at the capacity limits, so the results otherwise cover
+    /// the whole batch. A caller that stops publishing them partway through can resume from
+    /// the `root` of the last published result, or roll back by keeping a clone of the
+    /// accumulator from before.
+    ///
+    /// Panics if `chunk_size` is zero, or a chunk exceeds the capacity limits. The chunks
+    /// before it stay added.
+    pub fn batch_add_chunked(&mut self, xs: &[BigUint], chunk_size: usize) -> Vec<BatchAddResult> {
+        assert!(chunk_size > 0, "chunk size must not be zero");
+
+        xs.chunks(chunk_size)
+            .map(|chunk| {
+                let old_root = self.root.clone();
+                let proof = self.batch_add(chunk);
+
+                BatchAddResult {
+                    old_root,
+                    root: self.root.clone(),
+                    proof,
+                }
+            })
+            .collect()
+    }
+
+    /// Verifies the proof from `batch_add`, see `PublicState::ver_batch_add_iter`.
+    pub fn ver_batch_add_iter<I: IntoIterator<Item = BigUint>>(
+        &self,
+        w: &BatchProof,
+        root: &BigUint,
+        xs: I,
+    ) -> bool {
+        self.public_state().ver_batch_add_iter(w, root, xs)
+    }
+
+    /// Verifies the results of `batch_add_chunked`, see `PublicState::ver_batch_add_chunked`.
+    pub fn ver_batch_add_chunked(
+        &self,
+        root: &BigUint,
+        xs: &[BigUint],
+        chunk_size: usize,
+        results: &[BatchAddResult],
+    ) -> bool {
+        self.public_state()
+            .ver_batch_add_chunked(root, xs, chunk_size, results)
+    }
+
+    /// Deletes the elements of `pairs` that are members with a valid witness, like
+    /// `batch_del`, and skips the rest. Returns the result, with the deleted elements, and the
+    /// indices of the skipped pairs.
+    ///
+    /// A pair is skipped if its witness is not for the current state, its element is not a
+    /// member, e.g. as it was deleted already, or it repeats an earlier element. If all are
+    /// skipped, the state is unchanged and the result has no proof.
+    pub fn batch_del_tolerant(
+        &mut self,
+        pairs: &[(BigUint, MembershipWitness)],
+    ) -> (BatchDelResult, Vec<usize>) {
+        let state = self.public_state();
+        let valid = par::map(pairs, |(x, w)| state.ver_mem(w, x));
+
+        let mut seen = HashSet::with_capacity(pairs.len());
+        let mut removed = Vec::with_capacity(pairs.len());
+        let mut skipped = Vec::new();
+        for (i, ((x, w), valid)) in pairs.iter().zip(valid).enumerate() {
+            if valid && !x.is_one() && self.contains(x) && seen.insert(x) {
+                removed.push((x.clone(), w.clone()));
+            } else {
+                skipped.push(i);
+            }
+        }
+
+        let old_root = self.root.clone();
+        let proof = self.batch_del(&removed);
+        let result = BatchDelResult {
+            old_root,
+            root: self.root.clone(),
+            removed: removed.into_iter().map(|(x, _)| x).collect(),
+            proof,
+        };
+
+        (result, skipped)
+    }
+
+    /// Verifies the result of `batch_del_tolerant`, see `PublicState::ver_batch_del_result`.
+    pub fn ver_batch_del_result(&self, result: &BatchDelResult) -> bool {
+        self.public_state().ver_batch_del_result(result)
+    }
+
+    /// Adds all `xs` to the set, and updates the state with a single exponentiation by
+    /// their product. Returns the product.
+    fn add_product(&mut self, xs: &[BigUint]) -> Result<BigUint, AccumulatorError> {
+        let x_star = {
+            let _span = span!("product", elements = xs.len());
+            product(xs)
+        };
+        let old_root = self.root.clone();
+        let x_star = self.add_x_star(x_star, xs.len())?;
+        self.filter_added(xs);
+        self.log_added(&old_root, xs);
+
+        Ok(x_star)
+    }
+
+    /// Adds `count` elements with the product `x_star`, see `add_product`.
+    fn add_x_star(&mut self, x_star: BigUint, count: usize) -> Result<BigUint, AccumulatorError> {
+        let set = &self.set * &x_star;
+        self.check_capacity(count, &set)?;
+
+        self.set = set;
+        self.len += count;
+        {
+            let _span = span!("update_root", exponent_bits = x_star.bits());
+            self.root = self.root.modpow(&x_star, &self.params.n); //Returns (self ^ exponent) % modulus.
+        }
+        self.cache_added(&x_star);
+
+        Ok(x_star)
+    }
+}
+
+/// The public part of an accumulator, everything a verifier needs to know.
+///
+/// With `serde`, `g` and `root` are encoded with the width of the modulus, and rejected if
+/// they are not smaller than it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PublicState {
+    /// Modulus of the group.
+    pub n: BigUint,
+    /// Generator.
+    pub g: BigUint,
+    /// Current accumulator state.
+    pub root: BigUint,
+}
+
+/// Invalid parameters for `PublicState::from_state` and `RsaAccumulatorBuilder::build`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamError {
+    /// The modulus is even.
+    EvenModulus,
+    /// The modulus does not have the expected number of bits.
+    ModulusSize { expected: usize, actual: usize },
+    /// The generator is not in `(1, n)`.
+    InvalidGenerator,
+    /// The state is not in `[1, n)`.
+    InvalidState,
+    /// Generating a modulus of `bits` bits is not supported, see `RSAGroup::MIN_MODULUS_BITS`.
+    ModulusTooSmall { bits: usize },
+    /// The generator is to be taken from the setup, but the modulus was not generated by one.
+    MissingGenerator,
+    /// The modulus fails `check_modulus`.
+    #[cfg(feature = "prover")]
+    WeakModulus(ModulusError),
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamError::EvenModulus => write!(f, "the modulus is even"),
+            ParamError::ModulusSize { expected, actual } => write!(
+                f,
+                "expected a modulus of {} bits, got {} bits",
+                expected, actual
+            ),
+            ParamError::InvalidGenerator => write!(f, "the generator is out of range"),
+            ParamError::InvalidState => write!(f, "the state is out of range"),
+            ParamError::ModulusTooSmall { bits } => {
+                write!(f, "can't generate a modulus of only {} bits", bits)
+            }
+            ParamError::MissingGenerator => write!(f, "the modulus source has no generator"),
+            #[cfg(feature = "prover")]
+            ParamError::WeakModulus(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ParamError {}
+
+/// Errors of accumulator operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccumulatorError {
+    /// The element is not a member of the accumulator.
+    NotAMember,
+    /// The element is a member of the accumulator, or shares a factor with one.
+    IsMember,
+    /// The element is `0` or `1`, which can neither be members nor non-members.
+    InvalidElement,
+    /// Adding would exceed the capacity limits, resulting in `elements` elements with a
+    /// product of `set_bits` bits.
+    CapacityExceeded { elements: usize, set_bits: usize },
+    /// The element has `bits` bits, outside of the range allowed by the element policy.
+    ElementSize { bits: usize },
+    /// The accumulator does not record the epochs of its elements.
+    NoEpochIndex,
+    /// The elements at the indices `first` and `second` of a batch share a factor.
+    CommonFactor { first: usize, second: usize },
+    /// The parameters are invalid, e.g. the generator shares a factor with the modulus and
+    /// has no inverse. Only possible for parameters that were not checked, as with
+    /// `Accumulator::new`.
+    Math(MathError),
+}
+
+impl fmt::Display for AccumulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccumulatorError::NotAMember => write!(f, "the element is not a member"),
+            AccumulatorError::IsMember => write!(f, "the element is a member"),
+            AccumulatorError::InvalidElement => write!(f, "the element must be larger than 1"),
+            AccumulatorError::ElementSize { bits } => {
+                write!(f, "elements of {} bits are not allowed", bits)
+            }
+            AccumulatorError::NoEpochIndex => write!(f, "the epoch index is not enabled"),
+            AccumulatorError::CommonFactor { first, second } => {
+                write!(f, "elements {} and {} share a factor", first, second)
+            }
+            AccumulatorError::Math(err) => write!(f, "invalid parameters: {}", err),
+            AccumulatorError::CapacityExceeded { elements, set_bits } => write!(
+                f,
+                "capacity exceeded, {} elements with a product of {} bits",
+                elements, set_bits
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AccumulatorError {}
+
+impl From<MathError> for AccumulatorError {
+    fn from(err: MathError) -> Self {
+        AccumulatorError::Math(err)
+    }
+}
+
+/// Errors of `Accumulator::batch_mem_wit_create`, with the index of the offending element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchMemError {
+    /// No elements were given.
+    Empty,
+    /// The element appears earlier in the list as well.
+    Duplicate(usize),
+    /// The element is not a member.
+    NotAMember(usize),
+}
+
+impl fmt::Display for BatchMemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchMemError::Empty => write!(f, "no elements given"),
+            BatchMemError::Duplicate(i) => write!(f, "element {} is repeated", i),
+            BatchMemError::NotAMember(i) => write!(f, "element {} is not a member", i),
+        }
+    }
+}
+
+impl std::error::Error for BatchMemError {}
+
+/// Errors of `Accumulator::batch_non_mem_wit_create_star`, with the index of the offending
+/// element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchNonMemError {
+    /// No elements were given.
+    Empty,
+    /// The element is `0` or `1`.
+    InvalidElement(usize),
+    /// The element is a member, or shares a factor with one.
+    IsMember(usize),
+    /// The parameters are invalid, see `AccumulatorError::Math`.
+    Math(MathError),
+}
+
+impl fmt::Display for BatchNonMemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchNonMemError::Empty => write!(f, "no elements given"),
+            BatchNonMemError::InvalidElement(i) => write!(f, "element {} is invalid", i),
+            BatchNonMemError::IsMember(i) => write!(f, "element {} is a member", i),
+            BatchNonMemError::Math(err) => write!(f, "invalid parameters: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for BatchNonMemError {}
+
+impl From<MathError> for BatchNonMemError {
+    fn from(err: MathError) -> Self {
+        BatchNonMemError::Math(err)
+    }
+}
+
+/// Errors of `PublicState::mem_wit_x_many`, with the indices of the offending inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AggregateError {
+    /// No witnesses were given, or not one state, witness and element each.
+    LengthMismatch,
+    /// The element, witness or state is out of range.
+    InvalidInput(usize),
+    /// The two elements share a factor, e.g. are equal.
+    NotCoprime(usize, usize),
+    /// The witness is not a witness of its element in its state.
+    InvalidWitness(usize),
+}
+
+impl fmt::Display for AggregateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AggregateError::LengthMismatch => {
+                write!(f, "expected one state, witness and element each")
+            }
+            AggregateError::InvalidInput(i) => write!(f, "input {} is invalid", i),
+            AggregateError::NotCoprime(i, j) => {
+                write!(f, "elements {} and {} are not coprime", i, j)
+            }
+            AggregateError::InvalidWitness(i) => write!(f, "witness {} is invalid", i),
+        }
+    }
+}
+
+impl std::error::Error for AggregateError {}
+
+/// Sizes of elements accepted by `Accumulator::add_checked` and `batch_add_checked`.
+///
+/// The security of the accumulator relies on nobody being able to find a new element
+/// dividing the product of the accumulated ones, so elements must be primes picked from a
+/// large enough range, and not chosen by whoever adds them. For 128 bit security they should
+/// have at least 256 bits, and be much smaller than the modulus, i.e. `max_bits` well below
+/// `lambda()`. Note that `hash_prime` currently only outputs primes of up to 128 bits.
+///
+/// The default accepts all elements, the one of a security level only those of at least
+/// `SecurityLevel::ELEMENT_BITS` bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElementPolicy {
+    /// Minimum size of an element, in bits.
+    pub min_bits: usize,
+    /// Maximum size of an element, in bits.
+    pub max_bits: usize,
+}
+
+impl Default for ElementPolicy {
+    fn default() -> Self {
+        ElementPolicy {
+            min_bits: 0,
+            max_bits: usize::MAX,
+        }
+    }
+}
+
+impl ElementPolicy {
+    /// The default policy of an `Accumulator<L>`, accepting elements of at least
+    /// `L::ELEMENT_BITS` bits.
+    pub fn for_level<L: SecurityLevel>() -> Self {
+        ElementPolicy {
+            min_bits: L::ELEMENT_BITS,
+            ..ElementPolicy::default()
+        }
+    }
+
+    /// Fails if the size of `x` is not allowed.
+    pub fn check(&self, x: &BigUint) -> Result<(), AccumulatorError> {
+        let bits = x.bits();
+        if bits < self.min_bits || bits > self.max_bits {
+            return Err(AccumulatorError::ElementSize { bits });
+        }
+
+        Ok(())
+    }
+}
+
+/// Limits on the size of an accumulator, see `Accumulator::with_capacity_limits`.
+///
+/// Everything touching the product of all elements slows down as it grows, so limiting it
+/// stops an accumulator before it becomes unusable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CapacityLimits {
+    /// Maximum number of elements.
+    pub max_elements: Option<usize>,
+    /// Maximum size of the product of all elements, in bits.
+    pub max_set_bits: Option<usize>,
+}
+
+impl PublicState {
+    /// Creates the public state from parameters and a state received from elsewhere, for
+    /// verifying proofs against it.
+    ///
+    /// Checks that `n` is odd and has `int_size_bits` bits, `1 < g < n` and `1 <= root < n`.
+    pub fn from_state(
+        int_size_bits: usize,
+        n: BigUint,
+        g: BigUint,
+        root: BigUint,
+    ) -> Result<Self, ParamError> {
+        if n.is_even() {
+            return Err(ParamError::EvenModulus);
+        }
+        if n.bits() != int_size_bits {
+            return Err(ParamError::ModulusSize {
+                expected: int_size_bits,
+                actual: n.bits(),
+            });
+        }
+        if g <= BigUint::one() || g >= n {
+            return Err(ParamError::InvalidGenerator);
+        }
+        if root.is_zero() || root >= n {
+            return Err(ParamError::InvalidState);
+        }
+
+        Ok(PublicState { n, g, root })
+    }
+
+    /// Returns the group of the accumulator, see `Accumulator::group_params`.
+    pub fn group_params(&self) -> GroupParams {
+        GroupParams::new(self.n.clone())
+    }
+
+    /// Fails if `v` is not a reduced, non-zero group element.
+    ///
+    /// The verifiers check all untrusted inputs like this, and with `check_element`, before
+    /// any arithmetic, so that oversized values are rejected at the cost of a comparison.
+    pub(crate) fn check_group_element(&self, v: &BigUint) -> Result<(), VerifyError> {
+        check_group_element(&self.n, v)
+    }
+
+    /// Fails if `x` can't be an element. Elements are larger than one and, see
+    /// `ElementPolicy`, much smaller than the modulus, so anything wider is rejected. Stricter
+    /// policies are checked with `ElementPolicy::check` before verifying.
+    fn check_element(&self, x: &BigUint) -> Result<(), VerifyError> {
+        check_element(&self.n, x)
+    }
+
+    pub(crate) fn check_elements(&self, xs: &[BigUint]) -> Result<(), VerifyError> {
+        xs.iter().try_for_each(|x| self.check_element(x))
+    }
+
+    /// Moves to `root`, the state after adding `xs`, if `w` is the proof from `batch_add` for
+    /// that. Returns `None` and keeps the current state otherwise.
+    pub fn advance_to(&mut self, root: BigUint, w: &BatchProof, xs: &[BigUint]) -> Option<()> {
+        if root.is_zero() || root >= self.n {
+            return None;
+        }
+
+        if self.check_batch_add(w, &self.root, &root, xs).is_err() {
+            return None;
+        }
+
+        self.root = root;
+        Some(())
+    }
+
+    /// Verify a membership witness for `x`.
+    pub fn ver_mem(&self, w: &MembershipWitness, x: &BigUint) -> bool {
+        self.ver_mem_at(&self.root, w, x)
+    }
+
+    /// Verify a membership witness for `x` against the past state `root`, instead of the
+    /// current one, e.g. the state at the epoch the witness was created in.
+    pub fn ver_mem_at(&self, root: &BigUint, w: &MembershipWitness, x: &BigUint) -> bool {
+        self.check_mem(root, w, x).is_ok()
+    }
+
+    /// Verifies the membership witness of every element of `pairs`, and returns the indices
+    /// of those that fail, in order.
+    ///
+    /// Unlike a batch check, each pair is checked on its own and all of them are checked, so
+    /// the report is complete, e.g. for auditing exported witnesses. With the `parallel`
+    /// feature the checks run on rayon's threadpool, with the same result.
+    pub fn ver_mem_all(&self, pairs: &[(BigUint, MembershipWitness)]) -> Vec<usize> {
+        let _span = span!("ver_mem_all", pairs = pairs.len());
+        let valid = par::map(pairs, |(x, w)| self.ver_mem(w, x));
+
+        failed_indices(valid)
+    }
+
+    /// Verifies a membership witness for `x`, like `ver_mem`, but returns why it failed.
+    pub fn ver_mem_detailed(&self, w: &MembershipWitness, x: &BigUint) -> Result<(), VerifyError> {
+        self.check_mem(&self.root, w, x)
+    }
+
+    fn check_mem(
+        &self,
+        root: &BigUint,
+        w: &MembershipWitness,
+        x: &BigUint,
+    ) -> Result<(), VerifyError> {
+        let _span = span!("ver_mem", element_bits = x.bits());
+        self.check_group_element(root)?;
+        self.check_group_element(&w.0)?;
+        self.check_element(x)?;
+
+        if &w.0.modpow(x, &self.n) != root {
+            return Err(VerifyError::WrongState);
+        }
+
+        Ok(())
+    }
+
+    /// Verify a membership witness for the identifier `id` in `epoch`, i.e. for the element
+    /// `epoch_element(id, epoch)`.
+    pub fn ver_mem_epoch(&self, w: &MembershipWitness, id: &[u8], epoch: u64) -> bool {
+        self.ver_mem(w, epoch_element(id, epoch).as_biguint())
+    }
+
+    /// Verifies that the elements of `pi`, all identifiers of one epoch, were deleted from
+    /// the state `root`, resulting in the current state.
+    ///
+    /// This does not show that no other elements of the epoch remain.
+    pub fn ver_expiry(&self, pi: &ExpiryProof, root: &BigUint) -> bool {
+        self.check_batch_del(&pi.proof, root, &self.root, &pi.elements())
+            .is_ok()
+    }
+
+    /// Verifies the result of `Accumulator::batch_del_tolerant`, that its `removed` elements
+    /// were deleted from its `old_root`, resulting in the current state.
+    ///
+    /// A result without a proof must not change the state.
+    pub fn ver_batch_del_result(&self, result: &BatchDelResult) -> bool {
+        if result.root != self.root || result.old_root >= self.n {
+            return false;
+        }
+
+        match &result.proof {
+            Some(proof) => self
+                .check_batch_del(proof, &result.old_root, &result.root, &result.removed)
+                .is_ok(),
+            None => result.removed.is_empty() && result.old_root == result.root,
+        }
+    }
+
+    /// Verify a non-membership witness for `x`.
+    pub fn ver_non_mem(&self, w: &NonMembershipWitness, x: &BigUint) -> bool {
+        self.ver_non_mem_at(&self.root, w, x)
+    }
+
+    /// Verify a non-membership witness for `x` against the past state `root`.
+    pub fn ver_non_mem_at(&self, root: &BigUint, w: &NonMembershipWitness, x: &BigUint) -> bool {
+        self.check_non_mem(root, w, x).is_ok()
+    }
+
+    /// Verifies a non-membership witness for `x`, like `ver_non_mem`, but returns why it
+    /// failed.
+    pub fn ver_non_mem_detailed(
+        &self,
+        w: &NonMembershipWitness,
+        x: &BigUint,
+    ) -> Result<(), VerifyError> {
+        self.check_non_mem(&self.root, w, x)
+    }
+
+    fn check_non_mem(
+        &self,
+        root: &BigUint,
+        w: &NonMembershipWitness,
+        x: &BigUint,
+    ) -> Result<(), VerifyError> {
+        let _span = span!("ver_non_mem", element_bits = x.bits());
+        let NonMembershipWitness { d, b } = w;
+        self.check_group_element(root)?;
+        self.check_group_element(d)?;
+        // with d = 1 the equation doesn't depend on x, only the empty state has such a
+        // witness, (1, 1)
+        if d.is_one() && root != &self.g {
+            return Err(VerifyError::MalformedInput);
+        }
+        self.check_element(x)?;
+        // honest witnesses have |b| < x, see `non_mem_witness` and `update_on_add`, so the
+        // size of x, and of the elements allowed by an `ElementPolicy`, bounds the work
+        if b.bits() > x.bits() || &b.abs().to_biguint().unwrap() >= x {
+            return Err(VerifyError::MalformedInput);
+        }
+
+        // A^b, for negative b this needs A to be invertible
+        let a_b = modpow_uint_int(root, b, &self.n).map_err(|err| match err {
+            MathError::NotInvertible => VerifyError::NotInvertible,
+            MathError::ZeroModulus => VerifyError::MalformedInput,
+        })?;
+        // d^x
+        let d_x = d.modpow(x, &self.n);
+
+        // d^x A^b == g
+        if (d_x * &a_b) % &self.n != self.g {
+            return Err(VerifyError::WrongState);
+        }
+
+        Ok(())
+    }
+
+    /// Verify Batch Add.
+    /// Given the proof `w` from `batch_add`, the list of added members `xs` and the
+    /// previous state `root`, this verifies that the `add` was done correctly.
+    #[deprecated(note = "use `ver_batch_add_transition` instead, with both states explicit")]
+    pub fn ver_batch_add(&self, w: &BatchProof, root: &BigUint, xs: &[BigUint]) -> bool {
+        self.check_batch_add(w, root, &self.root, xs).is_ok()
+    }
+
+    /// Verifies that `xs` were added to the state `old`, resulting in `new`, given the proof
+    /// `w` from `batch_add`.
+    ///
+    /// Only the parameters of this state are used, its current state is not.
+    pub fn ver_batch_add_transition(
+        &self,
+        w: &BatchProof,
+        old: &OldState,
+        new: &NewState,
+        xs: &[BigUint],
+    ) -> bool {
+        self.ver_batch_add_detailed(w, old, new, xs).is_ok()
+    }
+
+    /// Verifies the proof from `batch_add`, like `ver_batch_add_transition`, but returns why
+    /// it failed.
+    pub fn ver_batch_add_detailed(
+        &self,
+        w: &BatchProof,
+        old: &OldState,
+        new: &NewState,
+        xs: &[BigUint],
+    ) -> Result<(), VerifyError> {
+        self.check_batch_add(w, &old.0, &new.0, xs)
+    }
+
+    fn check_batch_add(
+        &self,
+        w: &BatchProof,
+        old: &BigUint,
+        new: &BigUint,
+        xs: &[BigUint],
+    ) -> Result<(), VerifyError> {
+        check_batch_add_between(old, new, xs, w, &self.group_params())
+    }
+
+    /// Verifies the proof from `batch_add`, like `ver_batch_add_transition`, but with the added
+    /// elements streamed from `xs`, e.g. when reading a large batch from disk.
+    ///
+    /// The challenge is derived from the product of all elements, so this still computes the
+    /// full product, but never holds the individual elements.
+    pub fn ver_batch_add_iter<I: IntoIterator<Item = BigUint>>(
+        &self,
+        w: &BatchProof,
+        root: &BigUint,
+        xs: I,
+    ) -> bool {
+        let _span = span!("ver_batch_add_iter");
+        if self.check_group_element(root).is_err() {
+            return false;
+        }
+
+        // stops reading at the first invalid element
+        let mut valid = true;
+        let x_star = product_iter(xs.into_iter().take_while(|x| {
+            valid = self.check_element(x).is_ok();
+            valid
+        }));
+        if !valid {
+            return false;
+        }
+
+        proofs::poe_verify(&self.group_params(), &x_star, root, &self.root, &w.0)
+    }
+
+    /// Verifies the results of `batch_add_chunked`, which added `xs` in chunks of `chunk_size`
+    /// to the accumulator with state `root`, resulting in the current state.
+    ///
+    /// Every chunk must start from the state the previous one ended in.
+    pub fn ver_batch_add_chunked(
+        &self,
+        root: &BigUint,
+        xs: &[BigUint],
+        chunk_size: usize,
+        results: &[BatchAddResult],
+    ) -> bool {
+        let _span = span!(
+            "ver_batch_add_chunked",
+            elements = xs.len(),
+            chunks = results.len()
+        );
+        if chunk_size == 0 || results.len() != xs.chunks(chunk_size).count() {
+            return false;
+        }
+        let roots_valid = results.iter().all(|result| {
+            self.check_group_element(&result.old_root).is_ok()
+                && self.check_group_element(&result.root).is_ok()
+        });
+        if !roots_valid || self.check_elements(xs).is_err() {
+            return false;
+        }
+
+        let mut current = root;
+        for (chunk, result) in xs.chunks(chunk_size).zip(results) {
+            if &result.old_root != current {
+                return false;
+            }
+            let x_star = product(chunk);
+            if !proofs::poe_verify(
+                &self.group_params(),
+                &x_star,
+                current,
+                &result.root,
+                &result.proof.0,
+            ) {
+                return false;
+            }
+            current = &result.root;
+        }
+
+        current == &self.root
+    }
+
+    /// Verify Batch Del.
+    /// Given the proof `w` from `batch_del`, the list of deleted members `xs` and the
+    /// previous state `root`, this verifies that the `del` was done correctly.
+    #[deprecated(note = "use `ver_batch_del_transition` instead, with both states explicit")]
+    pub fn ver_batch_del(&self, w: &BatchProof, root: &BigUint, xs: &[BigUint]) -> bool {
+        self.check_batch_del(w, root, &self.root, xs).is_ok()
+    }
+
+    /// Verifies that `xs` were deleted from the state `old`, resulting in `new`, given the
+    /// proof `w` from `batch_del`.
+    ///
+    /// Only the parameters of this state are used, its current state is not.
+    pub fn ver_batch_del_transition(
+        &self,
+        w: &BatchProof,
+        old: &OldState,
+        new: &NewState,
+        xs: &[BigUint],
+    ) -> bool {
+        self.ver_batch_del_detailed(w, old, new, xs).is_ok()
+    }
+
+    /// Verifies the proof from `batch_del`, like `ver_batch_del_transition`, but returns why
+    /// it failed.
+    pub fn ver_batch_del_detailed(
+        &self,
+        w: &BatchProof,
+        old: &OldState,
+        new: &NewState,
+        xs: &[BigUint],
+    ) -> Result<(), VerifyError> {
+        self.check_batch_del(w, &old.0, &new.0, xs)
+    }
+
+    fn check_batch_del(
+        &self,
+        w: &BatchProof,
+        old: &BigUint,
+        new: &BigUint,
+        xs: &[BigUint],
+    ) -> Result<(), VerifyError> {
+        check_batch_del_between(old, new, xs, w, &self.group_params())
+    }
+
+    /// Verify an aggregated membership witness for `x` and `y`.
+    pub fn ver_agg_mem_wit(&self, pi: &MembershipProof, x: &BigUint, y: &BigUint) -> bool {
+        let _span = span!("ver_agg_mem_wit", element_bits = x.bits() + y.bits());
+        let valid = self.check_group_element(&pi.w).is_ok()
+            && self.check_element(x).is_ok()
+            && self.check_element(y).is_ok();
+        if !valid {
+            return false;
+        }
+        let xy = x.clone() * y;
+        proofs::poe_verify(&self.group_params(), &xy, &pi.w, &self.root, &pi.pi)
+    }
+
+    /// Verify a membership witness with a NI-PoE.
+    pub fn ver_mem_star(&self, x: &BigUint, pi: &MembershipProof) -> bool {
+        self.ver_mem_star_at(&self.root, x, pi)
+    }
+
+    /// Verify a membership witness with a NI-PoE against the past state `root`.
+    ///
+    /// `root` is part of the NI-PoE challenge, so the proof only verifies against the state it
+    /// was created for.
+    pub fn ver_mem_star_at(&self, root: &BigUint, x: &BigUint, pi: &MembershipProof) -> bool {
+        self.check_mem_star(root, x, pi).is_ok()
+    }
+
+    /// Verifies the membership proof of every element of `pairs`, and returns the indices of
+    /// those that fail, like `ver_mem_all`.
+    pub fn ver_mem_star_all(&self, pairs: &[(BigUint, MembershipProof)]) -> Vec<usize> {
+        let _span = span!("ver_mem_star_all", pairs = pairs.len());
+        let valid = par::map(pairs, |(x, pi)| self.ver_mem_star(x, pi));
+
+        failed_indices(valid)
+    }
+
+    /// Verifies a membership witness with a NI-PoE, like `ver_mem_star`, but returns why it
+    /// failed.
+    pub fn ver_mem_star_detailed(
+        &self,
+        x: &BigUint,
+        pi: &MembershipProof,
+    ) -> Result<(), VerifyError> {
+        self.check_mem_star(&self.root, x, pi)
+    }
+
+    fn check_mem_star(
+        &self,
+        root: &BigUint,
+        x: &BigUint,
+        pi: &MembershipProof,
+    ) -> Result<(), VerifyError> {
+        let _span = span!("ver_mem_star", element_bits = x.bits());
+        // `x` may be a product of elements here, verifying only takes its remainder
+        self.check_group_element(root)?;
+        self.check_group_element(&pi.w)?;
+
+        proofs::poe_verify_detailed(&self.group_params(), x, &pi.w, root, &pi.pi)
+    }
+
+    /// Verify a membership witness aggregated from this and the `other` accumulator state.
+    pub fn ver_mem_x(
+        &self,
+        other: &BigUint,
+        pi: &MembershipWitness,
+        x: &BigUint,
+        y: &BigUint,
+    ) -> bool {
+        self.ver_mem_x_detailed(other, pi, x, y).is_ok()
+    }
+
+    /// Verifies a membership witness aggregated from this and the `other` accumulator state,
+    /// like `ver_mem_x`, but returns why it failed.
+    pub fn ver_mem_x_detailed(
+        &self,
+        other: &BigUint,
+        pi: &MembershipWitness,
+        x: &BigUint,
+        y: &BigUint,
+    ) -> Result<(), VerifyError> {
+        let _span = span!("ver_mem_x", element_bits = x.bits() + y.bits());
+        self.check_group_element(other)?;
+        self.check_group_element(&pi.0)?;
+        self.check_element(x)?;
+        self.check_element(y)?;
+        // assert x and y are coprime
+        let q = x.gcd(y);
+        if !q.is_one() {
+            return Err(VerifyError::NotCoprime);
+        }
+
+        // A_1^y * A_2^x
+        let rhs = multi_modpow(&self.root, y, other, x, &self.n);
+        // pi^{x * y}
+        let lhs = pi.0.modpow(&(x.clone() * y), &self.n);
+
+        if lhs != rhs {
+            return Err(VerifyError::WrongState);
+        }
+
+        Ok(())
+    }
+
+    /// Aggregates the witnesses `wits` of the elements `xs` in the accumulators with the
+    /// states `states`, one each, into a single witness, like `mem_wit_x` for more than two
+    /// accumulators.
+    ///
+    /// The aggregate is the product of the witnesses. Only the modulus of this state is used,
+    /// all accumulators must share it. Fails if the elements are not pairwise coprime, or a
+    /// witness doesn't verify.
+    pub fn mem_wit_x_many(
+        &self,
+        states: &[BigUint],
+        wits: &[MembershipWitness],
+        xs: &[BigUint],
+    ) -> Result<MembershipWitness, AggregateError> {
+        let _span = span!("mem_wit_x_many", count = xs.len());
+        if xs.is_empty() || states.len() != xs.len() || wits.len() != xs.len() {
+            return Err(AggregateError::LengthMismatch);
+        }
+        for (i, ((root, w), x)) in states.iter().zip(wits).zip(xs).enumerate() {
+            if self.check_group_element(root).is_err()
+                || self.check_group_element(&w.0).is_err()
+                || self.check_element(x).is_err()
+            {
+                return Err(AggregateError::InvalidInput(i));
+            }
+        }
+        if let Some((i, j)) = common_factor(xs) {
+            return Err(AggregateError::NotCoprime(i, j));
+        }
+        let ok = par::map(&(0..xs.len()).collect::<Vec<_>>(), |&i| {
+            wits[i].0.modpow(&xs[i], &self.n) == states[i]
+        });
+        if let Some(i) = ok.iter().position(|ok| !ok) {
+            return Err(AggregateError::InvalidWitness(i));
+        }
+
+        let w = wits
+            .iter()
+            .fold(BigUint::one(), |acc, w| acc * &w.0 % &self.n);
+        Ok(MembershipWitness(w))
+    }
+
+    /// Verifies a witness from `mem_wit_x_many` that each of `xs` is a member of the
+    /// accumulator with the state at the same index of `states`.
+    ///
+    /// Only the modulus of this state is used.
+    pub fn ver_mem_x_many(
+        &self,
+        states: &[BigUint],
+        pi: &MembershipWitness,
+        xs: &[BigUint],
+    ) -> bool {
+        self.ver_mem_x_many_detailed(states, pi, xs).is_ok()
+    }
+
+    /// Verifies a witness aggregated across many accumulators, like `ver_mem_x_many`, but
+    /// returns why it failed.
+    pub fn ver_mem_x_many_detailed(
+        &self,
+        states: &[BigUint],
+        pi: &MembershipWitness,
+        xs: &[BigUint],
+    ) -> Result<(), VerifyError> {
+        let _span = span!("ver_mem_x_many", count = xs.len());
+        if xs.is_empty() || states.len() != xs.len() {
+            return Err(VerifyError::MalformedInput);
+        }
+        self.check_group_element(&pi.0)?;
+        states
+            .iter()
+            .try_for_each(|root| self.check_group_element(root))?;
+        self.check_elements(xs)?;
+        if common_factor(xs).is_some() {
+            return Err(VerifyError::NotCoprime);
+        }
+
+        // pi^{x_1 ... x_k} = \prod A_i^{x_1 ... x_k / x_i}
+        let lhs = pi.0.modpow(&product(xs), &self.n);
+        let rhs = cross_power(states, xs, &self.n);
+
+        if lhs != rhs {
+            return Err(VerifyError::WrongState);
+        }
+
+        Ok(())
+    }
+
+    /// Verify a constant size non-membership proof.
+    pub fn ver_non_mem_star(&self, x: &BigUint, pi: &NonMembershipProof) -> bool {
+        self.ver_non_mem_star_detailed(x, pi).is_ok()
+    }
+
+    /// Verifies a constant size non-membership proof, like `ver_non_mem_star`, but returns
+    /// why it failed.
+    pub fn ver_non_mem_star_detailed(
+        &self,
+        x: &BigUint,
+        pi: &NonMembershipProof,
+    ) -> Result<(), VerifyError> {
+        let _span = span!("ver_non_mem_star", element_bits = x.bits());
+        self.check_non_mem_star(&self.group_params(), x, pi)
+    }
+
+    /// Verifies a non-membership proof for `x`, with the sub-proofs in `params`.
+    pub(crate) fn check_non_mem_star(
+        &self,
+        params: &GroupParams,
+        x: &BigUint,
+        pi: &NonMembershipProof,
+    ) -> Result<(), VerifyError> {
+        let g = &self.g;
+        let n = &self.n;
+
+        let NonMembershipProof { d, v, pi_d, pi_g } = pi;
+        self.check_group_element(d)?;
+        self.check_group_element(v)?;
+
+        // verify NI-PoKE2
+        proofs::poke2_verify_detailed(params, &self.root, v, pi_d)?;
+
+        // verify NI-PoE
+        let v_inv = v
+            .clone()
+            .mod_inverse(n)
+            .and_then(|v_inv| v_inv.into_biguint())
+            .ok_or(VerifyError::NotInvertible)?;
+        let k = (g * v_inv) % n;
+
+        proofs::poe_verify_detailed(params, x, d, &k, pi_g)
+    }
+
+    /// Verifies a non-membership proof for all of `xs`, from `batch_non_mem_wit_create_star`.
+    pub fn ver_batch_non_mem_star(&self, xs: &[BigUint], pi: &BatchNonMemStarProof) -> bool {
+        let _span = span!("ver_batch_non_mem_star", elements = xs.len());
+        if xs.is_empty() || self.check_elements(xs).is_err() {
+            return false;
+        }
+
+        self.ver_non_mem_star(&product(xs), &pi.0)
+    }
+
+    /// Compresses the membership witnesses of many elements into a single witness for their
+    /// product, using the Shamir trick, together with one NI-PoE for it.
+    ///
+    /// `items` are pairs of elements and their witnesses. Returns `None` if any of the witnesses
+    /// is invalid, or the elements are not pairwise coprime.
+    #[cfg(feature = "prover")]
+    pub fn compress_witnesses(
+        &self,
+        items: &[(BigUint, MembershipWitness)],
+    ) -> Option<CompressedWitness> {
+        let mut items = items.iter();
+
+        let (x0, w0) = items.next()?;
+        if !self.ver_mem(w0, x0) {
+            return None;
+        }
+
+        let mut x_star = x0.clone();
+        let mut w = w0.0.clone();
+
+        for (xi, wi) in items {
+            if !x_star.gcd(xi).is_one() {
+                return None;
+            }
+            // fails if `wi` is not an `xi`-th root of the state
+            w = shamir_trick(&w, &wi.0, &x_star, xi, &self.n)?;
+            x_star *= xi;
+        }
+
+        let pi = proofs::poe_prove(&self.group_params(), &x_star, &w, &self.root);
+
+        Some(CompressedWitness { w, pi })
+    }
+
+    /// Verify a compressed witness for all of `elements`.
+    pub fn ver_compressed(&self, elements: &[BigUint], cw: &CompressedWitness) -> bool {
+        let _span = span!("ver_compressed", elements = elements.len());
+        if elements.is_empty()
+            || self.check_group_element(&cw.w).is_err()
+            || self.check_elements(elements).is_err()
+        {
+            return false;
+        }
+
+        let mut x_star = BigUint::one();
+        for x in elements {
+            x_star *= x;
+        }
+
+        proofs::poe_verify(&self.group_params(), &x_star, &cw.w, &self.root, &cw.pi)
+    }
+
+    /// Verifies a witness for all of `xs` from `batch_mem_wit_create`, or `compress_witnesses`.
+    ///
+    /// Fails if an element is repeated, as only distinct elements are accumulated.
+    pub fn ver_batch_mem(&self, xs: &[BigUint], cw: &CompressedWitness) -> bool {
+        let mut seen = HashSet::with_capacity(xs.len());
+        if !xs.iter().all(|x| seen.insert(x)) {
+            return false;
+        }
+
+        self.ver_compressed(xs, cw)
+    }
+
+    /// Recovers the membership witness of a single element from a compressed witness,
+    /// given all `others` elements it was compressed with.
+    pub fn decompress_witness(
+        &self,
+        cw: &CompressedWitness,
+        others: &[BigUint],
+    ) -> MembershipWitness {
+        let mut w = cw.w.clone();
+        for x in others {
+            w = w.modpow(x, &self.n);
+        }
+
+        MembershipWitness(w)
+    }
+}
+
+/// Verifies that `xs` were added to the state `old`, resulting in `new`, given the proof
+/// `proof` from `batch_add`, in the group of `params`.
+///
+/// Needs neither the generator nor a current state, so a verifier can check an update before
+/// adopting its new state.
+pub fn ver_batch_add_between(
+    old: &OldState,
+    new: &NewState,
+    xs: &[BigUint],
+    proof: &BatchProof,
+    params: &GroupParams,
+) -> bool {
+    check_batch_add_between(&old.0, &new.0, xs, proof, params).is_ok()
+}
+
+/// Verifies that `xs` were deleted from the state `old`, resulting in `new`, given the proof
+/// `proof` from `batch_del`, like `ver_batch_add_between`.
+pub fn ver_batch_del_between(
+    old: &OldState,
+    new: &NewState,
+    xs: &[BigUint],
+    proof: &BatchProof,
+    params: &GroupParams,
+) -> bool {
+    check_batch_del_between(&old.0, &new.0, xs, proof, params).is_ok()
+}
+
+fn check_batch_add_between(
+    old: &BigUint,
+    new: &BigUint,
+    xs: &[BigUint],
+    proof: &BatchProof,
+    params: &GroupParams,
+) -> Result<(), VerifyError> {
+    let _span = span!("ver_batch_add", elements = xs.len());
+    let x_star = check_transition(old, new, xs, params)?;
+
+    // old^x* = new
+    proofs::poe_verify_detailed(params, &x_star, old, new, &proof.0)
+}
+
+fn check_batch_del_between(
+    old: &BigUint,
+    new: &BigUint,
+    xs: &[BigUint],
+    proof: &BatchProof,
+    params: &GroupParams,
+) -> Result<(), VerifyError> {
+    let _span = span!("ver_batch_del", elements = xs.len());
+    let x_star = check_transition(old, new, xs, params)?;
+
+    // new^x* = old
+    proofs::poe_verify_detailed(params, &x_star, new, old, &proof.0)
+}
+
+/// Checks the inputs of a batch update, and returns the product of `xs`.
+fn check_transition(
+    old: &BigUint,
+    new: &BigUint,
+    xs: &[BigUint],
+    params: &GroupParams,
+) -> Result<BigUint, VerifyError> {
+    check_group_element(&params.n, old)?;
+    check_group_element(&params.n, new)?;
+    xs.iter().try_for_each(|x| check_element(&params.n, x))?;
+    let mut x_star = BigUint::one();
+    for x in xs {
+        x_star *= x
+    }
+
+    Ok(x_star)
+}
+
+/// Fails if `v` is not a reduced, non-zero element of the group with modulus `n`.
+fn check_group_element(n: &BigUint, v: &BigUint) -> Result<(), VerifyError> {
+    if v.is_zero() || v >= n {
+        return Err(VerifyError::MalformedInput);
+    }
+
+    Ok(())
+}
+
+/// Fails if `x` is not an element of an accumulator with modulus `n`, see
+/// `PublicState::check_element`.
+fn check_element(n: &BigUint, x: &BigUint) -> Result<(), VerifyError> {
+    if x.is_zero() || x.is_one() || x.bits() > n.bits() {
+        return Err(VerifyError::MalformedInput);
+    }
+
+    Ok(())
+}
+
+/// Membership witness for an element `x`, the `x`-th root of the accumulator state.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MembershipWitness(
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))] pub BigUint,
+);
+
+/// Non-membership witness for an element `x`, `(d, b)` such that `d^x A^b = g`.
+///
+/// Created and updated witnesses have `0 <= b < x`, and verifiers reject `|b| >= x`, so the
+/// witness is one group element and at most the size of `x`, however large the set.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NonMembershipWitness {
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub d: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::bigint"))]
+    pub b: BigInt,
+}
+
+/// Membership witness `w` for a (product of) element(s) together with a NI-PoE `pi` for it.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MembershipProof {
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub w: BigUint,
+    pub pi: PoeProof,
+}
+
+/// Constant size non-membership proof, as returned by `non_mem_wit_create_star`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NonMembershipProof {
+    /// `d = g^a`
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub d: BigUint,
+    /// `v = A^b`
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub v: BigUint,
+    /// NI-PoKE2 for `b`, such that `A^b = v`.
+    pub pi_d: Poke2Proof,
+    /// NI-PoE for `x`, such that `d^x = g v^-1`.
+    pub pi_g: PoeProof,
+}
+
+/// Constant size non-membership proof for many elements, the `NonMembershipProof` of their
+/// product `x*`. Created by `batch_non_mem_wit_create_star`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BatchNonMemStarProof(pub NonMembershipProof);
+
+/// Membership witness `w` for the product of many elements, with a NI-PoE `pi` for `w^{x*} = A`.
+///
+/// Created by `compress_witnesses`, this replaces the individual witnesses of all elements.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CompressedWitness {
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub w: BigUint,
+    pub pi: PoeProof,
+}
+
+/// NI-PoE that a batch of elements was added or deleted correctly.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BatchProof(pub PoeProof);
+
+/// The state of an accumulator before a batch update, see `ver_batch_add_transition` and
+/// `ver_batch_del_transition`.
+///
+/// The states before and after an update have distinct types, so they can't be swapped:
+///
+/// ```
+/// # use accumulators::{BatchProof, NewState, OldState, PublicState};
+/// fn verify(state: &PublicState, w: &BatchProof, old: &OldState, new: &NewState) -> bool {
+///     state.ver_batch_add_transition(w, old, new, &[])
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use accumulators::{BatchProof, NewState, OldState, PublicState};
+/// fn verify(state: &PublicState, w: &BatchProof, old: &OldState, new: &NewState) -> bool {
+///     state.ver_batch_add_transition(w, new, old, &[])
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OldState(pub BigUint);
+
+/// The state of an accumulator after a batch update, see `OldState`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NewState(pub BigUint);
+
+impl From<BigUint> for OldState {
+    fn from(root: BigUint) -> Self {
+        OldState(root)
+    }
+}
+
+impl From<BigUint> for NewState {
+    fn from(root: BigUint) -> Self {
+        NewState(root)
+    }
+}
+
+/// The identifiers deleted by `Accumulator::expire_epoch`, with a proof of the deletion.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExpiryProof {
+    /// The expired epoch.
+    pub epoch: u64,
+    /// The identifiers deleted, in the order they were added.
+    pub ids: Vec<Vec<u8>>,
+    /// NI-PoE that their elements were deleted.
+    pub proof: BatchProof,
+}
+
+impl ExpiryProof {
+    /// Returns the number of deleted elements.
+    pub fn count(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns the deleted elements, e.g. for `WitnessManager::observe_del`.
+    pub fn elements(&self) -> Vec<BigUint> {
+        self.ids
+            .iter()
+            .map(|id| epoch_element(id, self.epoch).into_biguint())
+            .collect()
+    }
+}
+
+/// One chunk of a `batch_add_chunked`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BatchAddResult {
+    /// State before the chunk was added.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub old_root: BigUint,
+    /// State after the chunk was added.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub root: BigUint,
+    /// Proof that the chunk was added to `old_root`.
+    pub proof: BatchProof,
+}
+
+/// The result of `Accumulator::batch_del_tolerant`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BatchDelResult {
+    /// State before the deletion.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub old_root: BigUint,
+    /// State after the deletion.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub root: BigUint,
+    /// The deleted elements, in the order given.
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::encoding::serde_hex::biguint_vec")
+    )]
+    pub removed: Vec<BigUint>,
+    /// Proof that `removed` were deleted from `old_root`, `None` if nothing was deleted.
+    pub proof: Option<BatchProof>,
+}
+
+impl From<BigUint> for MembershipWitness {
+    fn from(w: BigUint) -> Self {
+        MembershipWitness(w)
+    }
+}
+
+impl From<MembershipWitness> for BigUint {
+    fn from(w: MembershipWitness) -> Self {
+        w.0
+    }
+}
+
+impl NonMembershipWitness {
+    /// Updates the witness for `x` after `y` was added to the accumulator with state `root`,
+    /// the state before the addition, in `Z_n`.
+    ///
+    /// Returns `None`, leaving the witness unchanged, if `x` and `y` are not coprime, as `x`
+    /// is no longer a non-member then.
+    pub fn update_on_add(
+        &mut self,
+        x: &BigUint,
+        y: &BigUint,
+        root: &BigUint,
+        n: &BigUint,
+    ) -> Option<()> {
+        // a x + b0 y = 1
+        let (gcd, _, b0) = extended_gcd(x, y);
+        if !gcd.is_one() {
+            return None;
+        }
+        let x = BigInt::from_biguint(Sign::Plus, x.clone());
+        let y = BigInt::from_biguint(Sign::Plus, y.clone());
+
+        // with A' = A^y, b' y = b + m x for b' = b b0 mod x, so d' = d A^-m
+        let b = (&self.b * b0).mod_floor(&x);
+        let m = (&b * y - &self.b) / &x;
+        let d = (&self.d * modpow_uint_int(root, &-m, n).ok()?) % n;
+
+        self.d = d;
+        self.b = b;
+        Some(())
+    }
+
+    /// Updates the witness for `x` after `y` was deleted from the accumulator, resulting in
+    /// the state `root`, in `Z_n`.
+    pub fn update_on_del(
+        &mut self,
+        x: &BigUint,
+        y: &BigUint,
+        root: &BigUint,
+        n: &BigUint,
+    ) -> Option<()> {
+        let x = BigInt::from_biguint(Sign::Plus, x.clone());
+        let y = BigInt::from_biguint(Sign::Plus, y.clone());
+
+        // with A = A'^y, b' = b y, reduced mod x by moving A'^(k x) into d
+        let (k, b) = (&self.b * y).div_mod_floor(&x);
+        let d = (&self.d * modpow_uint_int(root, &k, n).ok()?) % n;
+
+        self.d = d;
+        self.b = b;
+        Some(())
+    }
+}
+
+impl From<(BigUint, BigInt)> for NonMembershipWitness {
+    fn from((d, b): (BigUint, BigInt)) -> Self {
+        NonMembershipWitness { d, b }
+    }
+}
+
+impl From<NonMembershipWitness> for (BigUint, BigInt) {
+    fn from(w: NonMembershipWitness) -> Self {
+        (w.d, w.b)
+    }
+}
+
+impl From<(BigUint, BigUint)> for MembershipProof {
+    fn from((w, pi): (BigUint, BigUint)) -> Self {
+        MembershipProof { w, pi: pi.into() }
+    }
+}
+
+impl From<MembershipProof> for (BigUint, BigUint) {
+    fn from(p: MembershipProof) -> Self {
+        (p.w, p.pi.into())
+    }
+}
+
+impl From<(BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint)> for NonMembershipProof {
+    fn from((d, v, pi_d, pi_g): (BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint)) -> Self {
+        NonMembershipProof {
+            d,
+            v,
+            pi_d: pi_d.into(),
+            pi_g: pi_g.into(),
+        }
+    }
+}
+
+impl From<NonMembershipProof> for (BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint) {
+    fn from(p: NonMembershipProof) -> Self {
+        (p.d, p.v, p.pi_d.into(), p.pi_g.into())
+    }
+}
+
+impl From<BigUint> for BatchProof {
+    fn from(pi: BigUint) -> Self {
+        BatchProof(pi.into())
+    }
+}
+
+impl From<BatchProof> for BigUint {
+    fn from(pi: BatchProof) -> Self {
+        pi.0.into()
+    }
+}
+
+/// Shims with the tuple based signatures the traits had before the typed witnesses and proofs.
+#[cfg(feature = "prover")]
+impl<L: SecurityLevel> Accumulator<L> {
+    /// `ver_mem`, for a bare witness.
+    #[deprecated(note = "use `ver_mem` with a `MembershipWitness` instead")]
+    pub fn ver_mem_untyped(&self, w: &BigUint, x: &BigUint) -> bool {
+        StaticAccumulator::ver_mem(self, &MembershipWitness(w.clone()), x)
+    }
+
+    /// `non_mem_wit_create`, returning the witness as `(d, b)`.
+    #[deprecated(note = "use `non_mem_wit_create`, which returns a `NonMembershipWitness`")]
+    pub fn non_mem_wit_create_untyped(&self, x: &BigUint) -> (BigUint, BigInt) {
+        UniversalAccumulator::non_mem_wit_create(self, x).into()
+    }
+
+    /// `non_mem_wit_create_star`, returning the proof as `(d, v, (z, q, r), q)`.
+    #[deprecated(note = "use `non_mem_wit_create_star`, which returns a `NonMembershipProof`")]
+    #[allow(clippy::type_complexity)]
+    pub fn non_mem_wit_create_star_untyped(
+        &self,
+        x: &BigUint,
+    ) -> (BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint) {
+        AggregatableAccumulator::non_mem_wit_create_star(self, x).into()
+    }
+}
+
+#[cfg(feature = "prover")]
+impl AccumulatorSetup for Accumulator {
+    /// Generates a group of unknown order and initializes the group with a generator of that group.
+    /// Setup(λ, z) → pp, A0 Generate the public parameters
+    fn setup<T, R>(rng: &mut R, int_size_bits: usize) -> Self
+    where
+        T: PrimeGroup,
+        R: CryptoRng + Rng,
+    {
+        // Generate n = p q, |n| = int_size_bits
+        // This is a trusted setup, as we do know `p` and `q`, even though
+        // we choose not to store them, they are overwritten (best effort) when `setup` is
+        // dropped.
+        let setup = T::generate_primes(rng, int_size_bits).unwrap();
+
+        Accumulator::new_with_params(Arc::new(SetupParams {
+            lambda: int_size_bits,
+            n: setup.n.clone(),
+            g: setup.g.clone(),
+            mode: GroupMode::Full,
+        }))
+    }
+}
+
+#[cfg(feature = "prover")]
+impl<L: SecurityLevel> StaticAccumulator for Accumulator<L> {
+    type MemWitness = MembershipWitness;
+
+    /// Returns the current public state.
+    fn state(&self) -> &BigUint {
+        &self.root
+    }
+
+    ///Takes the current accumulator At, an element from the odd primes domain, and computes At+1 = At.
+    #[inline]
+    fn add(&mut self, x: &BigUint) {
+        self.try_add(x).expect("capacity exceeded")
+    }
+
+    //A membership witness is simply the accumulator without the aggregated item.
+    #[inline]
+    fn mem_wit_create(&self, x: &BigUint) -> MembershipWitness {
+        self.try_mem_wit_create(x)
+            .expect("x was not a valid member of set")
+    }
+
+    #[inline]
+    fn ver_mem(&self, w: &MembershipWitness, x: &BigUint) -> bool {
+        self.ver_mem_at(self.state(), w, x)
+    }
+}
+
+#[cfg(feature = "prover")]
+impl<L: SecurityLevel> DynamicAccumulator for Accumulator<L> {
+    #[inline]
+    fn del(&mut self, x: &BigUint) -> Option<()> {
+        let (set, r) = self.set.div_rem(x);
+
+        // x must be a member
+        if !r.is_zero() || set == self.set {
+            return None;
+        }
+        self.set = set;
+        self.len = self.len.saturating_sub(1);
+
+        let root = self.params.g.clone().modpow(&self.set, &self.params.n); //Returns (self ^ exponent) % modulus.
+        let old_root = std::mem::replace(&mut self.root, root);
+        self.record_deleted(std::slice::from_ref(x));
+        self.log_deleted(&old_root, std::slice::from_ref(x));
+        Some(())
+    }
+}
+
+#[cfg(feature = "prover")]
+impl<L: SecurityLevel> UniversalAccumulator for Accumulator<L> {
+    type NonMemWitness = NonMembershipWitness;
+
+    fn non_mem_wit_create(&self, x: &BigUint) -> NonMembershipWitness {
+        self.try_non_mem_wit_create(x)
+            .expect("x was a member of set")
+    }
+
+    fn ver_non_mem(&self, w: &NonMembershipWitness, x: &BigUint) -> bool {
+        self.ver_non_mem_at(self.state(), w, x)
+    }
+}
+
+#[cfg(feature = "prover")]
+impl<L: SecurityLevel> BatchUpdateAccumulator for Accumulator<L> {
+    type BatchProof = BatchProof;
+
+    fn batch_add(&mut self, xs: &[BigUint]) -> BatchProof {
+        self.try_batch_add(xs).expect("capacity exceeded")
+    }
+
+    fn ver_batch_add(&self, w: &BatchProof, root: &BigUint, xs: &[BigUint]) -> bool {
+        self.public_state()
+            .check_batch_add(w, root, &self.root, xs)
+            .is_ok()
+    }
+
+    fn ver_batch_add_transition(
+        &self,
+        w: &BatchProof,
+        old: &OldState,
+        new: &NewState,
+        xs: &[BigUint],
+    ) -> bool {
+        self.public_state()
+            .ver_batch_add_transition(w, old, new, xs)
+    }
+
+    fn batch_del(&mut self, pairs: &[(BigUint, MembershipWitness)]) -> Option<BatchProof> {
+        if pairs.is_empty() {
+            return None;
+        }
+        let xs = pairs.iter().map(|(x, _)| x.clone()).collect::<Vec<_>>();
+        let (root, proof) = batch_del_proof(&self.params.n, &self.root, pairs);
+
+        for x in &xs {
+            // for now this is not great, depends on this impl, not on the general design
+            self.set /= x;
+        }
+
+        let old_root = std::mem::replace(&mut self.root, root);
+        self.len = self.len.saturating_sub(xs.len());
+        self.record_deleted(&xs);
+        self.log_deleted(&old_root, &xs);
+
+        Some(proof)
+    }
+
+    fn ver_batch_del(&self, w: &BatchProof, root: &BigUint, xs: &[BigUint]) -> bool {
+        self.public_state()
+            .check_batch_del(w, root, &self.root, xs)
+            .is_ok()
+    }
+
+    fn ver_batch_del_transition(
+        &self,
+        w: &BatchProof,
+        old: &OldState,
+        new: &NewState,
+        xs: &[BigUint],
+    ) -> bool {
+        self.public_state()
+            .ver_batch_del_transition(w, old, new, xs)
+    }
+
+    fn del_w_mem(&mut self, w: &MembershipWitness, x: &BigUint) -> Option<()> {
+        if !self.ver_mem(w, x) {
+            return None;
+        }
+
+        self.set /= x;
+        self.len = self.len.saturating_sub(1);
+        // w is root without x, so need to recompute
+        let old_root = std::mem::replace(&mut self.root, w.0.clone());
+        self.record_deleted(std::slice::from_ref(x));
+        self.log_deleted(&old_root, std::slice::from_ref(x));
+
+        Some(())
+    }
+
+    #[inline]
+    fn create_all_mem_wit(&self, set: &[BigUint]) -> Vec<MembershipWitness> {
+        // root_factor halves the set on every level
+        let _span = span!(
+            "create_all_mem_wit",
+            elements = set.len(),
+            depth = set.len().next_power_of_two().trailing_zeros()
+        );
+        root_factor(&self.params.g, set, &self.params.n)
+            .into_iter()
+            .map(MembershipWitness)
+            .collect()
+    }
+
+    fn extend_all_mem_wit(
+        &self,
+        existing: &mut Vec<(BigUint, MembershipWitness)>,
+        new_elements: &[BigUint],
+    ) {
+        extend_mem_wits(&self.params.g, &self.params.n, existing, new_elements)
+    }
+}
+
+#[cfg(feature = "prover")]
+impl<L: SecurityLevel> AggregatableAccumulator for Accumulator<L> {
+    type MemProof = MembershipProof;
+    type NonMemProof = NonMembershipProof;
+
+    fn agg_mem_wit(
+        &self,
+        w_x: &MembershipWitness,
+        w_y: &MembershipWitness,
+        x: &BigUint,
+        y: &BigUint,
+    ) -> MembershipProof {
+        agg_mem_proof(&self.params.n, &self.root, w_x, w_y, x, y)
+    }
+
+    fn ver_agg_mem_wit(&self, pi: &MembershipProof, x: &BigUint, y: &BigUint) -> bool {
+        self.public_state().ver_agg_mem_wit(pi, x, y)
+    }
+
+    fn mem_wit_create_star(&self, x: &BigUint) -> MembershipProof {
+        self.try_mem_wit_create_star(x)
+            .expect("x was not a valid member of set")
+    }
+
+    fn ver_mem_star(&self, x: &BigUint, pi: &MembershipProof) -> bool {
+        self.ver_mem_star_at(self.state(), x, pi)
+    }
+
+    fn mem_wit_x(
+        &self,
+        _other: &BigUint,
+        w_x: &MembershipWitness,
+        w_y: &MembershipWitness,
+        _x: &BigUint,
+        _y: &BigUint,
+    ) -> MembershipWitness {
+        MembershipWitness((&w_x.0 * &w_y.0) % &self.params.n)
+    }
+
+    fn ver_mem_x(&self, other: &BigUint, pi: &MembershipWitness, x: &BigUint, y: &BigUint) -> bool {
+        self.public_state().ver_mem_x(other, pi, x, y)
+    }
+
+    fn non_mem_wit_create_star(&self, x: &BigUint) -> NonMembershipProof {
+        self.try_non_mem_wit_create_star(x)
+            .expect("x was a member of set")
+    }
+
+    fn ver_non_mem_star(&self, x: &BigUint, pi: &NonMembershipProof) -> bool {
+        // `pi_d` is for a hashed generator, which may be outside of the group
+        self.in_group(&[&pi.d, &pi.v, &pi.pi_g.q]) && self.public_state().ver_non_mem_star(x, pi)
+    }
+}
+
+/// Deletes all elements of `pairs`, given their membership witnesses, from the accumulator
+/// with state `root`. Returns the new state and the proof for it.
+///
+/// `pairs` must not be empty.
+#[cfg(feature = "prover")]
+pub(crate) fn batch_del_proof(
+    n: &BigUint,
+    root: &BigUint,
+    pairs: &[(BigUint, MembershipWitness)],
+) -> (BigUint, BatchProof) {
+    let (new_root, x_star) = batch_del_root(n, pairs).expect("invalid witnesses");
+    let xs = pairs.iter().map(|(x, _)| x.clone()).collect::<Vec<_>>();
+
+    let params = GroupParams::new(n.clone());
+    let proof = BatchProof(proofs::prove_factored(
+        &params.proof_params.context,
+        &x_star,
+        &xs,
+        &new_root,
+        root,
+        n,
+    ));
+    (new_root, proof)
+}
+
+/// Computes the state after deleting all elements of `pairs`, given their membership witnesses
+/// for the same state. Returns the new state and the product of the deleted elements.
+///
+/// Returns `None` if `pairs` is empty, or the witnesses are not all for the same state.
+///
+/// The witnesses are merged pairwise in a balanced tree, so the exponents of each merge stay
+/// as small as possible and the halves run in parallel. Folding them one by one instead gives
+/// the same state, but every merge works with the product of all elements before it.
+pub(crate) fn batch_del_root(
+    n: &BigUint,
+    pairs: &[(BigUint, MembershipWitness)],
+) -> Option<(BigUint, BigUint)> {
+    match pairs.len() {
+        0 => None,
+        1 => Some((pairs[0].1 .0.clone(), pairs[0].0.clone())),
+        len => {
+            let (l, r) = pairs.split_at(len / 2);
+            let ((root_l, x_l), (root_r, x_r)) = if len < par::MIN_SPLIT_LEN {
+                (batch_del_root(n, l)?, batch_del_root(n, r)?)
+            } else {
+                match par::join(|| batch_del_root(n, l), || batch_del_root(n, r)) {
+                    (Some(l), Some(r)) => (l, r),
+                    _ => return None,
+                }
+            };
+
+            let new_root = shamir_trick(&root_l, &root_r, &x_l, &x_r, n)?;
+            Some((new_root, x_l * x_r))
+        }
+    }
+}
+
+/// Updates the membership witnesses of `existing`, created with the generator `g`, to the set
+/// extended by `new_elements`, and appends the witnesses for those.
+#[cfg(feature = "prover")]
+pub(crate) fn extend_mem_wits(
+    g: &BigUint,
+    n: &BigUint,
+    existing: &mut Vec<(BigUint, MembershipWitness)>,
+    new_elements: &[BigUint],
+) {
+    if new_elements.is_empty() {
+        return;
+    }
+
+    // g raised to the product of the existing elements
+    let base = match existing.first() {
+        Some((x, w)) => w.0.modpow(x, n),
+        None => g.clone(),
+    };
+
+    let tree = SubproductTree::build(new_elements);
+    let y_star = tree.product();
+    let ws = par::map(existing, |(_, w)| MembershipWitness(w.0.modpow(&y_star, n)));
+    for ((_, w), w_new) in existing.iter_mut().zip(ws) {
+        *w = w_new;
+    }
+
+    let roots = tree.roots(&base, n);
+    existing.extend(
+        new_elements
+            .iter()
+            .cloned()
+            .zip(roots.into_iter().map(MembershipWitness)),
+    );
+}
+
+/// Aggregates the membership witnesses for `x` and `y`, in the accumulator with state
+/// `root`, and proves the result.
+#[cfg(feature = "prover")]
+pub(crate) fn agg_mem_proof(
+    n: &BigUint,
+    root: &BigUint,
+    w_x: &MembershipWitness,
+    w_y: &MembershipWitness,
+    x: &BigUint,
+    y: &BigUint,
+) -> MembershipProof {
+    // TODO: check this matches, sth is not quite right in the paper here
+    let w_xy = shamir_trick(&w_x.0, &w_y.0, x, y, n).unwrap();
+    let xy = x.clone() * y;
+
+    debug_assert!(w_xy.modpow(&xy, n) == *root, "invalid shamir trick");
+
+    let pi = proofs::poe_prove(&GroupParams::new(n.clone()), &xy, &w_xy, root);
+
+    MembershipProof { w: w_xy, pi }
+}
+
+/// Creates the non-membership proof for `x`, in the accumulator with state `root`
+/// and set product `set`.
+#[cfg(feature = "prover")]
+pub(crate) fn non_mem_proof(
+    g: &BigUint,
+    n: &BigUint,
+    root: &BigUint,
+    set: &BigUint,
+    x: &BigUint,
+) -> Result<NonMembershipProof, AccumulatorError> {
+    // a, b <- Bezout(x, s_star)
+    let (a, b) = non_mem_bezout(set, x)?;
+
+    // d <- g^a
+    let d = modpow_uint_int(g, &a, n)?;
+    // v <- A^b
+    let v = modpow_uint_int(root, &b, n)?;
+
+    let params = GroupParams::new(n.clone());
+    Ok(non_mem_proof_parts(&params, g, root, x, b, d, v)?)
+}
+
+/// Completes the non-membership proof for `x` from `d = g^a` and `v = A^b`, with the proofs
+/// in `params`.
+///
+/// Fails if `g`, and so `v`, has no inverse.
+#[cfg(feature = "prover")]
+pub(crate) fn non_mem_proof_parts(
+    params: &GroupParams,
+    g: &BigUint,
+    root: &BigUint,
+    x: &BigUint,
+    b: BigInt,
+    d: BigUint,
+    v: BigUint,
+) -> Result<NonMembershipProof, MathError> {
+    let n = &params.n;
+
+    // pi_d <- NI-PoKE2(b, A, v)
+    let pi_d = proofs::poke2_prove(params, b, root, &v)?;
+
+    // k <- g * v^-1
+    let v_inv = v.clone().mod_inverse(n).ok_or(MathError::NotInvertible)?;
+    let k = (g * v_inv.into_biguint().unwrap()) % n;
+
+    // pi_g <- NI-PoE(x, d, g * v^-1)
+    let pi_g = proofs::poe_prove(params, x, &d, &k);
+
+    // return {d, v, pi_d, pi_g}
+    Ok(NonMembershipProof { d, v, pi_d, pi_g })
+}
+
+/// Creates the membership witness for `x` in the accumulator of `set`, with generator `g`, in
+/// `Z_n`.
+#[cfg(feature = "prover")]
+pub(crate) fn mem_witness(
+    g: &BigUint,
+    n: &BigUint,
+    set: &BigUint,
+    x: &BigUint,
+) -> Result<MembershipWitness, AccumulatorError> {
+    if x.is_zero() {
+        return Err(AccumulatorError::NotAMember);
+    }
+    let (set, r) = set.div_rem(x);
+    if !r.is_zero() || x.is_one() {
+        return Err(AccumulatorError::NotAMember);
+    }
+
+    Ok(MembershipWitness(g.modpow(&set, n)))
+}
+
+/// Completes the membership proof for `x` from its witness `w`, in the accumulator with state
+/// `root`.
+#[cfg(feature = "prover")]
+pub(crate) fn mem_proof(
+    params: &GroupParams,
+    root: &BigUint,
+    x: &BigUint,
+    w: MembershipWitness,
+) -> MembershipProof {
+    let pi = proofs::poe_prove(params, x, &w.0, root);
+
+    MembershipProof { w: w.0, pi }
+}
+
+/// Creates the non-membership witness for `x` in the accumulator of `set`, with generator `g`,
+/// in `Z_n`.
+#[cfg(feature = "prover")]
+pub(crate) fn non_mem_witness(
+    g: &BigUint,
+    n: &BigUint,
+    set: &BigUint,
+    x: &BigUint,
+) -> Result<NonMembershipWitness, AccumulatorError> {
+    // a, b <- Bezout(x, set*)
+    let (a, b) = non_mem_bezout(set, x)?;
+    let d = modpow_uint_int(g, &a, n)?;
+
+    Ok(NonMembershipWitness { d, b })
+}
+
+/// Returns the Bezout coefficients `a x + b set = 1`, if `x` is coprime to the members.
+///
+/// `b` is reduced to `0 <= b < x`, by moving multiples of `set` into `a`, so that witnesses
+/// are no larger than the element, however large the set.
+#[cfg(feature = "prover")]
+fn non_mem_bezout(set: &BigUint, x: &BigUint) -> Result<(BigInt, BigInt), AccumulatorError> {
+    if x.is_zero() || x.is_one() {
+        return Err(AccumulatorError::InvalidElement);
+    }
+
+    let _span = span!(
+        "non_mem_bezout",
+        set_bits = set.bits(),
+        element_bits = x.bits()
+    );
+    let (gcd, a, b) = extended_gcd(x, set);
+    if !gcd.is_one() {
+        return Err(AccumulatorError::IsMember);
+    }
+
+    // b = k x + b', so (a + k set) x + b' set = 1
+    let (k, b) = b.div_mod_floor(&BigInt::from_biguint(Sign::Plus, x.clone()));
+    let a = a + k * BigInt::from_biguint(Sign::Plus, set.clone());
+
+    Ok((a, b))
+}
+
+/// Adds the indices of the `true` items of `outside` to the sorted indices `failed`.
+#[cfg(feature = "prover")]
+fn merge_failed(failed: Vec<usize>, outside: impl Iterator<Item = bool>) -> Vec<usize> {
+    let mut failed = failed;
+    failed.extend(outside.enumerate().filter(|(_, o)| *o).map(|(i, _)| i));
+    failed.sort_unstable();
+    failed.dedup();
+    failed
+}
+
+/// The indices of the `false` results.
+fn failed_indices(valid: Vec<bool>) -> Vec<usize> {
+    valid
+        .into_iter()
+        .enumerate()
+        .filter(|(_, valid)| !valid)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::encoding::{Packed, DEFAULT_MAX_INT_BYTES};
+    use crate::group::RSAGroup;
+    use crate::math::MathError;
+    use crate::primes::RandPrimes;
+    use num_bigint::Sign;
+    use num_bigint::{RandBigInt, RandPrime};
+    use num_traits::FromPrimitive;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_static() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        for _ in 0..100 {
+            let int_size_bits = 256; // insecure, but faster tests
+            let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+            let xs = rng.gen_distinct_primes(5, int_size_bits);
+
+            for x in &xs {
+                acc.add(x);
+            }
+
+            for x in &xs {
+                let w = acc.mem_wit_create(x);
+                assert!(acc.ver_mem(&w, x));
+            }
+        }
+    }
+
+    #[test]
+    fn test_dynamic() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        for _ in 0..20 {
+            let int_size_bits = 256; // insecure, but faster tests
+            let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+            let xs = rng.gen_distinct_primes(5, int_size_bits);
+
+            for x in &xs {
+                acc.add(x);
+            }
+
+            let ws = xs
+                .iter()
+                .map(|x| {
+                    let w = acc.mem_wit_create(x);
+                    assert!(acc.ver_mem(&w, x));
+                    w
+                })
+                .collect::<Vec<_>>();
+
+            for (x, w) in xs.iter().zip(ws.iter()) {
+                // remove x
+                acc.del(x).unwrap();
+                // make sure test now fails
+                assert!(!acc.ver_mem(w, x));
+                // and x can not be deleted again
+                let state = acc.state().clone();
+                assert!(acc.del(x).is_none());
+                assert_eq!(acc.state(), &state);
+            }
+        }
+    }
+
+    #[test]
+    fn test_universal() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        for _ in 0..20 {
+            let int_size_bits = 256; // insecure, but faster tests
+            let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+            let xs = rng.gen_distinct_primes(5, int_size_bits);
+
+            for x in &xs {
+                acc.add(x);
+            }
+
+            for _ in 0..5 {
+                let y = rng.gen_prime(int_size_bits);
+
+                let w = acc.non_mem_wit_create(&y);
+                assert!(acc.ver_non_mem(&w, &y));
+            }
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_untyped_shims() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = rng.gen_distinct_primes(3, 128);
+        for x in &xs {
+            acc.add(x);
+        }
+
+        let w: BigUint = acc.mem_wit_create(&xs[0]).into();
+        assert!(acc.ver_mem_untyped(&w, &xs[0]));
+        assert!(!acc.ver_mem_untyped(&w, &xs[1]));
+
+        let y = rng.gen_prime(128);
+        let (d, b) = acc.non_mem_wit_create_untyped(&y);
+        assert!(acc.ver_non_mem(&NonMembershipWitness { d, b }, &y));
+
+        let pi = acc.non_mem_wit_create_star_untyped(&y);
+        assert!(acc.ver_non_mem_star(&y, &pi.into()));
+    }
+
+    #[test]
+    fn test_ver_at_past_state() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = rng.gen_distinct_primes(3, 128);
+        acc.add(&xs[0]);
+        acc.add(&xs[1]);
+
+        let old = acc.public_state();
+        let w = acc.mem_wit_create(&xs[0]);
+        let pi = acc.mem_wit_create_star(&xs[1]);
+        let u = acc.non_mem_wit_create(&xs[2]);
+
+        acc.add(&xs[2]);
+        let new = acc.public_state();
+
+        // the old witnesses against the old state
+        assert!(acc.ver_mem_at(&old.root, &w, &xs[0]));
+        assert!(acc.ver_mem_star_at(&old.root, &xs[1], &pi));
+        assert!(acc.ver_non_mem_at(&old.root, &u, &xs[2]));
+        assert!(new.ver_mem_at(&old.root, &w, &xs[0]));
+        assert!(new.ver_mem_star_at(&old.root, &xs[1], &pi));
+        assert!(new.ver_non_mem_at(&old.root, &u, &xs[2]));
+
+        // and against the current one
+        assert!(!acc.ver_mem(&w, &xs[0]));
+        assert!(!acc.ver_mem_star(&xs[1], &pi));
+        assert!(!acc.ver_non_mem(&u, &xs[2]));
+        assert!(!old.ver_mem_at(&new.root, &w, &xs[0]));
+        assert!(!old.ver_mem_star_at(&new.root, &xs[1], &pi));
+        assert!(!old.ver_non_mem_at(&new.root, &u, &xs[2]));
+
+        // a witness for the current state doesn't verify against the old one
+        let w = acc.mem_wit_create(&xs[0]);
+        assert!(!acc.ver_mem_at(&old.root, &w, &xs[0]));
+        assert!(acc.ver_mem_at(&new.root, &w, &xs[0]));
+    }
+
+    #[test]
+    fn test_try_mem_wit_create() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits).with_witness_cache(4);
+
+        let xs = rng.gen_distinct_primes(3, 128);
+        acc.batch_add(&xs);
+        let y = rng.gen_prime(128);
+
+        let w = acc.try_mem_wit_create(&xs[0]).unwrap();
+        assert!(acc.ver_mem(&w, &xs[0]));
+        let pi = acc.try_mem_wit_create_star(&xs[1]).unwrap();
+        assert!(acc.ver_mem_star(&xs[1], &pi));
+
+        // also without debug assertions, non-members must not get a witness
+        assert_eq!(
+            acc.try_mem_wit_create(&y),
+            Err(AccumulatorError::NotAMember)
+        );
+        assert_eq!(
+            acc.try_mem_wit_create_star(&y),
+            Err(AccumulatorError::NotAMember)
+        );
+        assert_eq!(
+            acc.try_mem_wit_create(&BigUint::one()),
+            Err(AccumulatorError::NotAMember)
+        );
+        // products of members divide the set, and get the witness for all of them
+        assert_eq!(
+            acc.try_mem_wit_create(&(&xs[0] * &xs[1])),
+            Ok(MembershipWitness(
+                acc.params.g.modpow(&xs[2], &acc.params.n)
+            ))
+        );
+
+        acc.del(&xs[0]).unwrap();
+        assert_eq!(
+            acc.try_mem_wit_create(&xs[0]),
+            Err(AccumulatorError::NotAMember)
+        );
+    }
+
+    #[test]
+    fn test_element_policy() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        assert_eq!(acc.lambda(), int_size_bits);
+        assert_eq!(acc.element_bits_policy(), &ElementPolicy::default());
+
+        let policy = ElementPolicy {
+            min_bits: 64,
+            max_bits: 96,
+        };
+        let mut acc = acc.with_element_policy(policy);
+        assert_eq!(acc.element_bits_policy(), &policy);
+
+        let small = rng.gen_prime(32);
+        let large = rng.gen_prime(128);
+        let xs = [rng.gen_prime(64), rng.gen_prime(80), rng.gen_prime(96)];
+
+        assert_eq!(
+            acc.add_checked(&small),
+            Err(AccumulatorError::ElementSize { bits: 32 })
+        );
+        assert_eq!(
+            acc.add_checked(&large),
+            Err(AccumulatorError::ElementSize { bits: 128 })
+        );
+        for bad in &[&small, &large] {
+            let batch = vec![xs[0].clone(), (*bad).clone()];
+            assert!(matches!(
+                acc.batch_add_checked(&batch),
+                Err(AccumulatorError::ElementSize { .. })
+            ));
+        }
+        assert!(acc.is_empty());
+        assert_eq!(acc.state(), acc.generator());
+
+        acc.add_checked(&xs[0]).unwrap();
+        let root = acc.state().clone();
+        let proof = acc.batch_add_checked(&xs[1..]).unwrap();
+        let (old, new) = (OldState(root), NewState(acc.state().clone()));
+        assert!(acc.ver_batch_add_transition(&proof, &old, &new, &xs[1..]));
+        assert_eq!(acc.len(), 3);
+
+        // the unchecked additions ignore the policy
+        acc.add(&small);
+        assert!(acc.contains(&small));
+        assert!(!acc.contains(&BigUint::zero()));
+        assert!(!acc.contains(&BigUint::one()));
+    }
+
+    #[test]
+    fn test_batch_add_checked_common_factor() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = rng.gen_distinct_primes(6, 64);
+
+        // a planted duplicate
+        let mut batch = xs[..4].to_vec();
+        batch.push(xs[2].clone());
+        assert_eq!(
+            acc.batch_add_checked(&batch),
+            Err(AccumulatorError::CommonFactor {
+                first: 2,
+                second: 4
+            })
+        );
+        // composites sharing one prime
+        let batch = vec![
+            xs[0].clone(),
+            &xs[1] * &xs[4],
+            xs[2].clone(),
+            &xs[3] * &xs[4],
+        ];
+        assert_eq!(
+            acc.batch_add_checked(&batch),
+            Err(AccumulatorError::CommonFactor {
+                first: 1,
+                second: 3
+            })
+        );
+        assert_eq!(
+            acc.batch_add_checked(&[xs[0].clone(), BigUint::one()]),
+            Err(AccumulatorError::InvalidElement)
+        );
+        assert!(acc.is_empty());
+
+        // only within the batch, repeating an accumulated element is allowed
+        acc.batch_add_checked(&xs[..3]).unwrap();
+        assert!(acc.batch_add_checked(&xs[2..]).is_ok());
+        assert_eq!(acc.len(), 7);
+    }
+
+    #[test]
+    fn test_with_elements() {
+        let int_size_bits = 256; // insecure, but faster tests
+        let xs = ChaChaRng::from_seed([1u8; 32]).gen_distinct_primes(100, 128);
+
+        let (acc, count) = Accumulator::with_elements(
+            &mut ChaChaRng::from_seed([0u8; 32]),
+            int_size_bits,
+            xs.iter().cloned(),
+        )
+        .unwrap();
+        assert_eq!(count, xs.len());
+        assert_eq!(acc.len(), xs.len());
+
+        let mut expected =
+            Accumulator::setup::<RSAGroup, _>(&mut ChaChaRng::from_seed([0u8; 32]), int_size_bits);
+        for x in &xs {
+            expected.add(x);
+        }
+        assert_eq!(acc.public_state(), expected.public_state());
+        for x in &xs[..3] {
+            assert!(acc.ver_mem(&acc.mem_wit_create(x), x));
+        }
+
+        // nothing is added if any element is rejected
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let bad = xs[..3].iter().cloned().chain(Some(BigUint::one()));
+        assert_eq!(
+            Accumulator::with_elements(rng, int_size_bits, bad).err(),
+            Some(AccumulatorError::InvalidElement)
+        );
+
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits).with_element_policy(
+            ElementPolicy {
+                min_bits: 128,
+                max_bits: 128,
+            },
+        );
+        let small = xs[..3].iter().cloned().chain(Some(rng.gen_prime(64)));
+        assert_eq!(
+            acc.try_add_iter(small),
+            Err(AccumulatorError::ElementSize { bits: 64 })
+        );
+        assert!(acc.is_empty());
+        assert_eq!(acc.state(), acc.generator());
+        assert_eq!(acc.try_add_iter(Vec::new()), Ok(0));
+        assert_eq!(acc.try_add_iter(xs[..3].to_vec()), Ok(3));
+        assert_eq!(acc.len(), 3);
+    }
+
+    #[test]
+    fn test_capacity_limits() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = rng.gen_distinct_primes(6, 128);
+        assert!(acc.is_empty());
+        assert_eq!((acc.len(), acc.set_bits()), (0, 1));
+
+        // element limit, single and batch adds
+        let mut acc = acc.with_capacity_limits(CapacityLimits {
+            max_elements: Some(3),
+            max_set_bits: None,
+        });
+        acc.try_batch_add(&xs[..2]).unwrap();
+        let before = acc.clone();
+        assert_eq!(
+            acc.try_batch_add(&xs[2..4]),
+            Err(AccumulatorError::CapacityExceeded {
+                elements: 4,
+                set_bits: (&acc.set * &xs[2] * &xs[3]).bits(),
+            })
+        );
+        assert_eq!(acc.public_state(), before.public_state());
+        assert_eq!((acc.len(), &acc.set), (2, &before.set));
+
+        acc.try_add(&xs[2]).unwrap();
+        assert!(matches!(
+            acc.try_add(&xs[3]),
+            Err(AccumulatorError::CapacityExceeded { elements: 4, .. })
+        ));
+        assert_eq!(acc.len(), 3);
+
+        // deleting frees capacity
+        acc.del(&xs[0]).unwrap();
+        assert_eq!(acc.len(), 2);
+        acc.try_add(&xs[3]).unwrap();
+        assert!(acc.ver_mem(&acc.mem_wit_create(&xs[3]), &xs[3]));
+
+        // bit limit, single and batch adds
+        let max_set_bits = acc.set_bits() + 200;
+        let mut acc = acc.with_capacity_limits(CapacityLimits {
+            max_elements: None,
+            max_set_bits: Some(max_set_bits),
+        });
+        let root = acc.state().clone();
+        assert!(matches!(
+            acc.try_batch_add(&xs[4..6]),
+            Err(AccumulatorError::CapacityExceeded { elements: 5, .. })
+        ));
+        assert!(matches!(
+            acc.try_add(&(&xs[4] * &xs[5])),
+            Err(AccumulatorError::CapacityExceeded { .. })
+        ));
+        assert_eq!(acc.state(), &root);
+        acc.try_add(&xs[4]).unwrap();
+        assert_eq!(acc.len(), 4);
+        assert!(acc.set_bits() <= 3 * 128 + 200);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity exceeded")]
+    fn test_add_capacity_exceeded() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits).with_capacity_limits(
+            CapacityLimits {
+                max_elements: Some(1),
+                max_set_bits: None,
+            },
+        );
+
+        acc.add(&rng.gen_prime(128));
+        acc.add(&rng.gen_prime(128));
+    }
+
+    #[test]
+    fn test_try_non_mem_wit_create() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = rng.gen_distinct_primes(3, 128);
+        acc.batch_add(&xs);
+        let y = rng.gen_prime(128);
+
+        let w = acc.try_non_mem_wit_create(&y).unwrap();
+        assert!(acc.ver_non_mem(&w, &y));
+        let pi = acc.try_non_mem_wit_create_star(&y).unwrap();
+        assert!(acc.ver_non_mem_star(&y, &pi));
+
+        // members, and elements sharing a factor with them
+        for x in &[xs[0].clone(), &xs[1] * &y] {
+            assert_eq!(
+                acc.try_non_mem_wit_create(x),
+                Err(AccumulatorError::IsMember)
+            );
+            assert_eq!(
+                acc.try_non_mem_wit_create_star(x),
+                Err(AccumulatorError::IsMember)
+            );
+        }
+
+        for x in &[BigUint::zero(), BigUint::one()] {
+            assert_eq!(
+                acc.try_non_mem_wit_create(x),
+                Err(AccumulatorError::InvalidElement)
+            );
+            assert_eq!(
+                acc.try_non_mem_wit_create_star(x),
+                Err(AccumulatorError::InvalidElement)
+            );
+        }
+        assert_eq!(
+            acc.try_mem_wit_create(&BigUint::zero()),
+            Err(AccumulatorError::NotAMember)
+        );
+    }
+
+    #[test]
+    fn test_non_mem_wit_reduced() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let n = acc.modulus().clone();
+        acc.batch_add(&rng.gen_distinct_primes(64, 128));
+
+        for _ in 0..8 {
+            let y = rng.gen_prime(128);
+            let y_int = BigInt::from_biguint(Sign::Plus, y.clone());
+
+            // the coefficients of the Euclidean algorithm, before the reduction
+            let (_, a, b) = extended_gcd(&y, acc.product());
+            let raw = NonMembershipWitness {
+                d: modpow_uint_int(acc.generator(), &a, &n).unwrap(),
+                b: b.clone(),
+            };
+
+            let w = acc.non_mem_wit_create(&y);
+            assert!(w.b.sign() != Sign::Minus && w.b < y_int);
+            assert_eq!(w.b, b.mod_floor(&y_int));
+            assert_eq!(acc.ver_non_mem(&w, &y), acc.ver_non_mem(&raw, &y));
+            assert!(acc.ver_non_mem(&w, &y));
+
+            let pi = acc.non_mem_wit_create_star(&y);
+            assert!(acc.ver_non_mem_star(&y, &pi));
+
+            // a valid witness with |b| >= x is rejected by its size
+            let k = BigInt::from(3);
+            let big = NonMembershipWitness {
+                d: (&w.d * modpow_uint_int(acc.state(), &-&k, &n).unwrap()) % &n,
+                b: &w.b + &k * &y_int,
+            };
+            let d_x = big.d.modpow(&y, &n);
+            let a_b = modpow_uint_int(acc.state(), &big.b, &n).unwrap();
+            assert_eq!((d_x * a_b) % &n, *acc.generator());
+            assert_eq!(
+                acc.public_state().ver_non_mem_detailed(&big, &y),
+                Err(VerifyError::MalformedInput)
+            );
+        }
+    }
+
+    #[test]
+    fn test_del_returning_witness() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = rng.gen_distinct_primes(4, 128);
+        acc.batch_add(&xs[..3]);
+
+        let old_root = acc.state().clone();
+        let epoch = acc.current_epoch();
+        let w = acc.del_returning_witness(&xs[0]).unwrap();
+        assert_eq!(&w.0, acc.state());
+        assert_eq!(acc.current_epoch(), epoch);
+        assert!(acc.ver_mem_at(&old_root, &w, &xs[0]));
+        assert!(!acc.ver_mem(&w, &xs[0]));
+        assert!(!acc.ver_mem_at(&old_root, &w, &xs[1]));
+        // the same witness as created before the deletion
+        let mut before = Accumulator::new(acc.modulus().clone(), acc.generator().clone());
+        before.batch_add(&xs[..3]);
+        assert_eq!(w, before.mem_wit_create(&xs[0]));
+
+        let state = acc.state().clone();
+        assert_eq!(
+            acc.del_returning_witness(&xs[0]),
+            Err(AccumulatorError::NotAMember)
+        );
+        assert_eq!(
+            acc.del_returning_witness(&xs[3]),
+            Err(AccumulatorError::NotAMember)
+        );
+        assert_eq!(acc.state(), &state);
+
+        // with the witness of the deleted element
+        let old_root = acc.state().clone();
+        let w1 = acc.mem_wit_create(&xs[1]);
+        assert_eq!(
+            acc.del_w_mem_returning_witness(&w1, &xs[2]),
+            Err(AccumulatorError::NotAMember)
+        );
+        let w = acc.del_w_mem_returning_witness(&w1, &xs[1]).unwrap();
+        assert_eq!(w, w1);
+        assert!(acc.ver_mem_at(&old_root, &w, &xs[1]));
+        assert_eq!(acc.len(), 1);
+    }
+
+    #[test]
+    fn test_non_mem_degenerate() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let n = acc.modulus().clone();
+        let xs = rng.gen_distinct_primes(3, 128);
+        let malformed = Err(VerifyError::MalformedInput);
+
+        // the only witness with d = 1 is the one for the empty state
+        let w = acc.non_mem_wit_create(&xs[2]);
+        assert_eq!((&w.d, &w.b), (&BigUint::one(), &BigInt::one()));
+        assert!(acc.ver_non_mem(&w, &xs[2]));
+
+        acc.add_batch_no_proof(&xs[..2]);
+        let state = acc.public_state();
+        let w = acc.non_mem_wit_create(&xs[2]);
+        assert!(state.ver_non_mem(&w, &xs[2]));
+        let with_d = |d: BigUint| NonMembershipWitness { d, ..w.clone() };
+        for d in [BigUint::zero(), BigUint::one(), n.clone(), &w.d + &n] {
+            assert_eq!(state.ver_non_mem_detailed(&with_d(d), &xs[2]), malformed);
+        }
+        let with_b = |b: BigInt| NonMembershipWitness { b, ..w.clone() };
+        let x_int = BigInt::from_biguint(Sign::Plus, xs[2].clone());
+        for b in [x_int.clone(), -x_int, BigInt::one() << 4096] {
+            assert_eq!(state.ver_non_mem_detailed(&with_b(b), &xs[2]), malformed);
+        }
+        for x in &[BigUint::zero(), BigUint::one(), &n << 1] {
+            assert_eq!(state.ver_non_mem_detailed(&w, x), malformed);
+        }
+
+        // a crafted state A = g^-1, where (1, -1) would pass for any x
+        let g_inv = acc
+            .generator()
+            .clone()
+            .mod_inverse(&n)
+            .and_then(|g_inv| g_inv.into_biguint())
+            .unwrap();
+        let crafted = PublicState {
+            root: g_inv,
+            ..state.clone()
+        };
+        let w_one = NonMembershipWitness {
+            d: BigUint::one(),
+            b: -BigInt::one(),
+        };
+        for x in &xs {
+            assert_eq!(crafted.ver_non_mem_detailed(&w_one, x), malformed);
+        }
+
+        // elements outside of the policy are rejected before verifying
+        let acc = acc.with_element_policy(ElementPolicy {
+            min_bits: 192,
+            max_bits: 256,
+        });
+        assert!(!acc.ver_non_mem(&w, &xs[2]));
+        assert!(!acc.ver_non_mem_at(&state.root, &w, &xs[2]));
+        let y = rng.gen_prime(200);
+        let w = acc.non_mem_wit_create(&y);
+        assert!(acc.ver_non_mem(&w, &y));
+    }
+
+    #[test]
+    #[should_panic(expected = "was a member")]
+    fn test_non_mem_wit_create_member() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let x = rng.gen_prime(128);
+        acc.add(&x);
+        acc.non_mem_wit_create(&x);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a valid member")]
+    fn test_mem_wit_create_non_member() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        acc.add(&rng.gen_prime(128));
+        acc.mem_wit_create(&rng.gen_prime(128));
+    }
+
+    #[test]
+    fn test_non_mem_wit_update() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let n = acc.modulus().clone();
+
+        let mut members = rng.gen_distinct_primes(4, 128);
+        acc.batch_add(&members);
+
+        // tracked non-members and their witnesses, with both signs of b, moving A^x into d
+        // for every other one
+        let ws = (0..8)
+            .map(|i| {
+                let y = rng.gen_prime(128);
+                let mut w = acc.non_mem_wit_create(&y);
+                if i % 2 == 1 {
+                    w.d = (&w.d * acc.state()) % &n;
+                    w.b -= BigInt::from_biguint(Sign::Plus, y.clone());
+                }
+                (y, w)
+            })
+            .collect::<Vec<_>>();
+        assert!(ws.iter().any(|(_, w)| w.b.sign() == Sign::Minus));
+        assert!(ws.iter().any(|(_, w)| w.b.sign() == Sign::Plus));
+        let mut ws = ws;
+
+        for i in 0..12 {
+            if i % 3 == 2 {
+                let y = members.swap_remove(rng.gen_range(0, members.len()));
+                acc.del(&y).unwrap();
+                for (x, w) in ws.iter_mut() {
+                    w.update_on_del(x, &y, acc.state(), &n).unwrap();
+                }
+            } else {
+                let y = rng.gen_prime(128);
+                let root = acc.state().clone();
+                acc.add(&y);
+                for (x, w) in ws.iter_mut() {
+                    w.update_on_add(x, &y, &root, &n).unwrap();
+                }
+                members.push(y);
+            }
+
+            for (x, w) in &ws {
+                assert!(acc.ver_non_mem(w, x));
+            }
+        }
+
+        // adding a tracked element turns it into a member
+        let (x, mut w) = ws.pop().unwrap();
+        let root = acc.state().clone();
+        acc.add(&x);
+        let before = w.clone();
+        assert_eq!(w.update_on_add(&x, &x, &root, &n), None);
+        assert_eq!(w, before);
+    }
+
+    #[test]
+    fn test_math_non_mempership() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let int_size_bits = 32;
+
+        let x = rng.gen_prime(int_size_bits);
+        let s1 = rng.gen_prime(int_size_bits);
+        let s2 = rng.gen_prime(int_size_bits);
+
+        let n = BigUint::from_u32(43 * 67).unwrap();
+        let g = BigUint::from_u32(49).unwrap();
+
+        // set* = \prod set
+        let mut s_star = BigUint::one();
+        s_star *= &s1;
+        s_star *= &s2;
+
+        // A = g ^ set*
+        let root = g.modpow(&s_star, &n);
+
+        let (_, a, b) = extended_gcd(&x, &s_star);
+        println!("{} {} {} {}", &g, &a, &b, &n);
+
+        let u = BigInt::from_biguint(Sign::Plus, x.clone());
+        let v = BigInt::from_biguint(Sign::Plus, s_star);
+        let lhs = a.clone() * &u;
+        let rhs = b.clone() * &v;
+        println!("> {} * {} + {} * {} == 1", &a, &u, &b, &v);
+        assert_eq!(lhs + &rhs, BigInt::one());
+
+        // d = g^a mod n
+        let d = modpow_uint_int(&g, &a, &n).unwrap();
+        println!("> {} = {}^{} mod {}", &d, &g, &a, &n);
+
+        // A^b
+        let a_b = modpow_uint_int(&root, &b, &n).unwrap();
+        println!("> {} = {}^{} mod {}", &a_b, &root, &b, &n);
+
+        // A^b == g^{set* * b}
+        let res = modpow_uint_int(&g, &(&v * &b), &n).unwrap();
+        println!("> {} = {}^({} * {}) mod {}", &res, &g, &v, &b, &n);
+        assert_eq!(a_b, res);
+
+        // d^x
+        let d_x = d.modpow(&x, &n);
+        println!("> (d_x) {} = {}^{} mod {}", &d_x, &d, &x, &n);
+
+        // d^x == g^{a * x}
+        let res = modpow_uint_int(&g, &(&a * &u), &n).unwrap();
+        println!("> (d_x) {} = {}^({} * {}) mod {}", &res, &g, &a, &u, &n);
+        assert_eq!(d_x, res);
+
+        // d^x A^b == g
+        let lhs = (&d_x * &a_b) % &n;
+        println!("> {} = {} * {} mod {}", &lhs, &d_x, &a_b, &n);
+        assert_eq!(lhs, g);
+    }
+
+    fn test_batch_add_size(size: usize) {
+        println!("batch_add_size {}", size);
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        // regular add
+        let x0 = rng.gen_prime(int_size_bits);
+        acc.add(&x0);
+
+        // batch add
+        let root = acc.state().clone();
+        let xs = rng.gen_distinct_primes(size, int_size_bits);
+        let w = acc.batch_add(&xs);
+
+        // verify batch add
+        let (old, new) = (OldState(root), NewState(acc.state().clone()));
+        assert!(
+            acc.ver_batch_add_transition(&w, &old, &new, &xs),
+            "ver_batch_add failed"
+        );
+
+        // delete with member
+        let x = &xs[2];
+        let w = acc.mem_wit_create(x);
+        assert!(acc.ver_mem(&w, x), "failed to verify valid witness");
+
+        acc.del_w_mem(&w, x).unwrap();
+        assert!(
+            !acc.ver_mem(&w, x),
+            "witness verified, even though it was deleted"
+        );
+
+        // create all members witness
+        // current state contains xs\x + x0
+        let mut set = vec![x0.clone(), xs[0].clone(), xs[1].clone()];
+        set.extend(xs.iter().skip(3).cloned());
+
+        let ws = acc.create_all_mem_wit(&set);
+
+        for (w, x) in ws.iter().zip(set.iter()) {
+            assert!(acc.ver_mem(w, x));
+        }
+
+        // batch delete
+        let root = acc.state().clone();
+        let pairs = set
+            .iter()
+            .cloned()
+            .zip(ws.iter().cloned())
+            .take(3)
+            .collect::<Vec<_>>();
+        let w = acc.batch_del(&pairs[..]).unwrap();
+
+        let (old, new) = (OldState(root), NewState(acc.state().clone()));
+        assert!(
+            acc.ver_batch_del_transition(&w, &old, &new, &set[..3]),
+            "ver_batch_del failed"
+        );
+    }
+
+    #[test]
+    fn test_batch_transition() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        // larger than the challenges, so that the proofs are never trivial
+        let xs = rng.gen_distinct_primes(3, 200);
+        let before = acc.state().clone();
+        let w_add = acc.batch_add(&xs);
+        let after = acc.state().clone();
+        let ws = acc.create_all_mem_wit(&xs);
+        let pairs = xs.iter().cloned().zip(ws).collect::<Vec<_>>();
+        let w_del = acc.batch_del(&pairs[..2]).unwrap();
+        let state = acc.public_state();
+
+        let (old, new) = (OldState(before.clone()), NewState(after.clone()));
+        assert!(state.ver_batch_add_transition(&w_add, &old, &new, &xs));
+        assert!(!state.ver_batch_add_transition(&w_add, &old, &new, &xs[1..]));
+        let swapped = (OldState(after.clone()), NewState(before.clone()));
+        assert!(!state.ver_batch_add_transition(&w_add, &swapped.0, &swapped.1, &xs));
+
+        // verified against the given states, not the current one
+        let (old, new) = (OldState(after.clone()), NewState(state.root.clone()));
+        assert!(state.ver_batch_del_transition(&w_del, &old, &new, &xs[..2]));
+        assert!(acc.ver_batch_del_transition(&w_del, &old, &new, &xs[..2]));
+        let swapped = (OldState(state.root.clone()), NewState(after.clone()));
+        assert!(!state.ver_batch_del_transition(&w_del, &swapped.0, &swapped.1, &xs[..2]));
+        let stale = (OldState(before), NewState(after));
+        assert!(state.ver_batch_add_transition(&w_add, &stale.0, &stale.1, &xs));
+        assert!(!state.ver_batch_del_transition(&w_del, &stale.0, &stale.1, &xs[..2]));
+    }
+
+    #[test]
+    fn test_batch_between() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = rng.gen_distinct_primes(3, 200);
+        let params = acc.group_params();
+
+        // a verifier checks the update before adopting the new state
+        let mut verifier = acc.public_state();
+        let w_add = acc.batch_add(&xs);
+        let (old, new) = (
+            OldState(verifier.root.clone()),
+            NewState(acc.state().clone()),
+        );
+        assert!(ver_batch_add_between(&old, &new, &xs, &w_add, &params));
+        assert!(!ver_batch_add_between(
+            &old,
+            &new,
+            &xs[1..],
+            &w_add,
+            &params
+        ));
+        verifier.root = new.0.clone();
+        assert_eq!(verifier, acc.public_state());
+
+        // the same proof, with the endpoints swapped
+        let swapped = (OldState(new.0.clone()), NewState(old.0.clone()));
+        assert!(!ver_batch_add_between(
+            &swapped.0, &swapped.1, &xs, &w_add, &params
+        ));
+
+        let ws = acc.create_all_mem_wit(&xs);
+        let pairs = xs.iter().cloned().zip(ws).collect::<Vec<_>>();
+        let w_del = acc.batch_del(&pairs[..2]).unwrap();
+        let (old, new) = (
+            OldState(verifier.root.clone()),
+            NewState(acc.state().clone()),
+        );
+        assert!(ver_batch_del_between(&old, &new, &xs[..2], &w_del, &params));
+        assert!(!ver_batch_add_between(
+            &old,
+            &new,
+            &xs[..2],
+            &w_del,
+            &params
+        ));
+        let swapped = (OldState(new.0.clone()), NewState(old.0.clone()));
+        assert!(!ver_batch_del_between(
+            &swapped.0,
+            &swapped.1,
+            &xs[..2],
+            &w_del,
+            &params
+        ));
+
+        assert!(verifier.ver_batch_del_transition(&w_del, &old, &new, &xs[..2]));
+
+        // states outside of the group
+        let (zero, big) = (OldState(BigUint::zero()), NewState(params.n.clone()));
+        assert!(!ver_batch_del_between(
+            &zero,
+            &new,
+            &xs[..2],
+            &w_del,
+            &params
+        ));
+        assert!(!ver_batch_del_between(
+            &old,
+            &big,
+            &xs[..2],
+            &w_del,
+            &params
+        ));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_batch_transition_compat() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = rng.gen_distinct_primes(3, 200);
+        let root = acc.state().clone();
+        let w_add = acc.batch_add(&xs);
+        assert!(acc.ver_batch_add(&w_add, &root, &xs));
+        assert!(acc.public_state().ver_batch_add(&w_add, &root, &xs));
+
+        let w = acc.mem_wit_create(&xs[0]);
+        let added = acc.state().clone();
+        let w_del = acc.batch_del(&[(xs[0].clone(), w)]).unwrap();
+        assert!(acc.ver_batch_del(&w_del, &added, &xs[..1]));
+        assert!(acc.public_state().ver_batch_del(&w_del, &added, &xs[..1]));
+        assert!(!acc.ver_batch_del(&w_del, &root, &xs[..1]));
+    }
+
+    #[test]
+    fn test_batch_add_small() {
+        for i in 4..14 {
+            test_batch_add_size(i)
+        }
+    }
+
+    #[test]
+    fn test_batch_add_chunked() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = rng.gen_distinct_primes(20, 128);
+
+        let mut expected = acc.clone();
+        expected.batch_add(&xs);
+
+        for &chunk_size in &[1, 7, 20, 64] {
+            let mut acc = acc.clone();
+            let root = acc.state().clone();
+            let results = acc.batch_add_chunked(&xs, chunk_size);
+
+            assert_eq!(results.len(), xs.len().div_ceil(chunk_size));
+            assert_eq!(acc.state(), expected.state());
+            assert!(acc.ver_batch_add_chunked(&root, &xs, chunk_size, &results));
+            for x in &xs {
+                assert!(acc.contains(x));
+            }
+
+            // wrong start, elements or chunking
+            assert!(!acc.ver_batch_add_chunked(acc.state(), &xs, chunk_size, &results));
+            assert!(!acc.ver_batch_add_chunked(&root, &xs[1..], chunk_size, &results));
+            assert!(!acc.ver_batch_add_chunked(&root, &xs, 0, &results));
+            if chunk_size < xs.len() {
+                assert!(!acc.ver_batch_add_chunked(&root, &xs, chunk_size + 1, &results));
+            }
+        }
+
+        // the chain must line up
+        let mut acc = acc.clone();
+        let root = acc.state().clone();
+        let mut results = acc.batch_add_chunked(&xs, 7);
+        assert!(!acc.ver_batch_add_chunked(&root, &xs, 7, &results[..2]));
+        results.swap(0, 1);
+        assert!(!acc.ver_batch_add_chunked(&root, &xs, 7, &results));
+    }
+
+    #[test]
+    fn test_ver_batch_add_iter() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        // elements only need to be odd for verification
+        let xs = (0..2000)
+            .map(|_| rng.gen_biguint(128) | BigUint::one())
+            .collect::<Vec<_>>();
+        let root = acc.state().clone();
+        let w = acc.batch_add(&xs);
+
+        let other = BatchProof(PoeProof { q: &w.0.q + 1u32 });
+        let mut ys = xs.clone();
+        ys[1234] += 2u32;
+
+        let (old, new) = (OldState(root.clone()), NewState(acc.state().clone()));
+        for (w, xs) in &[(&w, &xs), (&w, &ys), (&other, &xs)] {
+            for len in &[0, 1, 1000, 1999, 2000] {
+                let xs = &xs[..*len];
+                assert_eq!(
+                    acc.ver_batch_add_iter(w, &root, xs.iter().cloned()),
+                    acc.ver_batch_add_transition(w, &old, &new, xs)
+                );
+            }
+        }
+        assert!(acc.ver_batch_add_iter(&w, &root, xs.into_iter()));
+    }
+
+    #[test]
+    fn test_batch_add_large() {
+        let size = 128;
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        // regular add
+        let x0 = rng.gen_prime(int_size_bits);
+        acc.add(&x0);
+
+        // batch add
+        let root = acc.state().clone();
+        let xs = rng.gen_distinct_primes(size, int_size_bits);
+        let w = acc.batch_add(&xs);
+
+        // verify batch add
+        let (old, new) = (OldState(root), NewState(acc.state().clone()));
+        assert!(
+            acc.ver_batch_add_transition(&w, &old, &new, &xs),
+            "ver_batch_add failed"
+        );
+
+        // batch add
+        let root = acc.state().clone();
+        let xs = rng.gen_distinct_primes(size, int_size_bits);
+        let w = acc.batch_add(&xs);
+
+        // verify batch add
+        let (old, new) = (OldState(root), NewState(acc.state().clone()));
+        assert!(
+            acc.ver_batch_add_transition(&w, &old, &new, &xs),
+            "ver_batch_add failed"
+        );
+    }
+
+    #[test]
+    fn test_aggregation() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        for _ in 0..10 {
+            let int_size_bits = 256; // insecure, but faster tests
+            let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+            // regular add
+            let xs = rng.gen_distinct_primes(5, int_size_bits);
+
+            for x in &xs {
                 acc.add(x);
             }
 
-            for _ in 0..5 {
-                let y = rng.gen_prime(int_size_bits);
+            // AggMemWit
+            {
+                let x = &xs[0];
+                let y = &xs[1];
+                let w_x = acc.mem_wit_create(x);
+                let w_y = acc.mem_wit_create(y);
 
-                let w = acc.non_mem_wit_create(&y);
-                assert!(acc.ver_non_mem(&w, &y));
+                let pi = acc.agg_mem_wit(&w_x, &w_y, x, y);
+
+                assert!(acc.ver_agg_mem_wit(&pi, x, y), "invalid agg_mem_wit proof");
+            }
+
+            // MemWitCreate*
+            {
+                let pis = (0..5)
+                    .map(|i| acc.mem_wit_create_star(&xs[i]))
+                    .collect::<Vec<_>>();
+                for (pi, x) in pis.iter().zip(&xs) {
+                    assert!(acc.ver_mem_star(x, pi), "invalid mem_wit_create_star proof");
+                }
+            }
+
+            // MemWitX
+            {
+                let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+                let mut other = acc.clone(); // should use the same `n` and `g`.
+                let x = rng.gen_prime(128);
+                let y = rng.gen_prime(128);
+
+                assert!(x.gcd(&y).is_one(), "x, y must be coprime");
+                // regular add
+                let xs = rng.gen_distinct_primes(5, 128);
+                for x in &xs {
+                    acc.add(x);
+                }
+                let ys = rng.gen_distinct_primes(5, 128);
+                for y in &ys {
+                    other.add(y);
+                }
+
+                acc.add(&x);
+                other.add(&y);
+
+                let w_x = acc.mem_wit_create(&x);
+                let w_y = other.mem_wit_create(&y);
+
+                assert!(acc.ver_mem(&w_x, &x));
+                assert!(other.ver_mem(&w_y, &y));
+
+                let w_xy = acc.mem_wit_x(other.state(), &w_x, &w_y, &x, &y);
+                assert!(
+                    acc.ver_mem_x(other.state(), &w_xy, &x, &y),
+                    "invalid ver_mem_x witness"
+                );
             }
         }
     }
 
     #[test]
-    fn test_math_non_mempership() {
+    fn test_mem_wit_x_many() {
         let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = Arc::new(SetupParams::generate(rng, int_size_bits).unwrap());
+        let xs = rng.gen_distinct_primes(15, 128);
+        let accs = xs
+            .chunks(3)
+            .map(|xs| {
+                let mut acc = Accumulator::new_with_params(params.clone());
+                acc.add_batch_no_proof(xs);
+                acc
+            })
+            .collect::<Vec<_>>();
+        let states = accs
+            .iter()
+            .map(|acc| acc.state().clone())
+            .collect::<Vec<_>>();
+        let members = (0..5).map(|i| xs[3 * i + 1].clone()).collect::<Vec<_>>();
+        let wits = accs
+            .iter()
+            .zip(&members)
+            .map(|(acc, x)| acc.mem_wit_create(x))
+            .collect::<Vec<_>>();
 
-        let int_size_bits = 32;
+        // the same as mem_wit_x for two accumulators
+        let (a, b) = (&accs[0], &accs[1]);
+        let w = a
+            .mem_wit_x_many(&states[..2], &wits[..2], &members[..2])
+            .unwrap();
+        let w_xy = a.mem_wit_x(b.state(), &wits[0], &wits[1], &members[0], &members[1]);
+        assert_eq!(w, w_xy);
+        assert!(a.ver_mem_x_many(&states[..2], &w, &members[..2]));
+        assert!(a.ver_mem_x(b.state(), &w, &members[0], &members[1]));
+
+        let w = a.mem_wit_x_many(&states, &wits, &members).unwrap();
+        assert!(a.ver_mem_x_many(&states, &w, &members));
+        let state = a.public_state();
+        assert!(state.ver_mem_x_many(&states, &w, &members));
+
+        // in other accumulators, or missing one
+        let mut swapped = members.clone();
+        swapped.swap(0, 1);
+        assert!(!state.ver_mem_x_many(&states, &w, &swapped));
+        assert!(!state.ver_mem_x_many(&states[1..], &w, &members[1..]));
+        assert_eq!(
+            state.mem_wit_x_many(&states, &wits, &swapped),
+            Err(AggregateError::InvalidWitness(0))
+        );
+        assert_eq!(
+            state.mem_wit_x_many(&states[1..], &wits, &members),
+            Err(AggregateError::LengthMismatch)
+        );
+        assert_eq!(
+            state.mem_wit_x_many(&[], &[], &[]),
+            Err(AggregateError::LengthMismatch)
+        );
 
-        let x = rng.gen_prime(int_size_bits);
-        let s1 = rng.gen_prime(int_size_bits);
-        let s2 = rng.gen_prime(int_size_bits);
+        // the same element in two accumulators
+        let mut other = Accumulator::new_with_params(params);
+        other.add(&members[1]);
+        let mut states = states;
+        let (mut wits, mut members) = (wits, members);
+        states[3] = other.state().clone();
+        wits[3] = other.mem_wit_create(&members[1]);
+        members[3] = members[1].clone();
+        assert_eq!(
+            state.mem_wit_x_many(&states, &wits, &members),
+            Err(AggregateError::NotCoprime(1, 3))
+        );
+        let w = MembershipWitness(
+            wits.iter()
+                .fold(BigUint::one(), |acc, w| acc * &w.0 % &state.n),
+        );
+        assert_eq!(
+            state.ver_mem_x_many_detailed(&states, &w, &members),
+            Err(VerifyError::NotCoprime)
+        );
+    }
 
-        let n = BigUint::from_u32(43 * 67).unwrap();
-        let g = BigUint::from_u32(49).unwrap();
+    #[test]
+    fn test_aggregation_non_mem_star() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
 
-        // set* = \prod set
-        let mut s_star = BigUint::one();
-        s_star *= &s1;
-        s_star *= &s2;
+        for _ in 0..10 {
+            let int_size_bits = 256; // insecure, but faster tests
+            let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
 
-        // A = g ^ set*
-        let root = g.modpow(&s_star, &n);
+            // regular add
+            let xs = rng.gen_distinct_primes(5, int_size_bits);
 
-        //let (_, a, b) = (&x).extended_gcd(&s_star);
-        let (_, a, b) = num_bigint::traits::ExtendedGcd::extended_gcd(&x, &s_star);
-        println!("{} {} {} {}", &g, &a, &b, &n);
+            for x in &xs {
+                acc.add(x);
+            }
 
-        let u = BigInt::from_biguint(Sign::Plus, x.clone());
-        let v = BigInt::from_biguint(Sign::Plus, s_star);
-        let lhs = a.clone() * &u;
-        let rhs = b.clone() * &v;
-        println!("> {} * {} + {} * {} == 1", &a, &u, &b, &v);
-        assert_eq!(lhs + &rhs, BigInt::one());
+            let x = rng.gen_prime(int_size_bits);
+            let pi = acc.non_mem_wit_create_star(&x);
 
-        // d = g^a mod n
-        let d = modpow_uint_int(&g, &a, &n).unwrap();
-        println!("> {} = {}^{} mod {}", &d, &g, &a, &n);
+            assert!(acc.ver_non_mem_star(&x, &pi), "invalid ver_non_mem_star");
+        }
+    }
 
-        // A^b
-        let a_b = modpow_uint_int(&root, &b, &n).unwrap();
-        println!("> {} = {}^{} mod {}", &a_b, &root, &b, &n);
+    #[test]
+    fn test_add_batch_no_proof() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
 
-        // A^b == g^{set* * b}
-        let res = modpow_uint_int(&g, &(&v * &b), &n).unwrap();
-        println!("> {} = {}^({} * {}) mod {}", &res, &g, &v, &b, &n);
-        assert_eq!(a_b, res);
+        let xs = rng.gen_distinct_primes(100, 128);
 
-        // d^x
-        let d_x = d.modpow(&x, &n);
-        println!("> (d_x) {} = {}^{} mod {}", &d_x, &d, &x, &n);
+        let mut acc_seq = acc.clone();
+        for x in &xs {
+            acc_seq.add(x);
+        }
 
-        // d^x == g^{a * x}
-        let res = modpow_uint_int(&g, &(&a * &u), &n).unwrap();
-        println!("> (d_x) {} = {}^({} * {}) mod {}", &res, &g, &a, &u, &n);
-        assert_eq!(d_x, res);
+        let mut acc_batch = acc.clone();
+        acc_batch.add_batch_no_proof(&xs[..50]);
+        acc_batch.add_batch_no_proof(&xs[50..]);
+        assert_eq!(acc_batch.state(), acc_seq.state());
+
+        let mut acc_iter = acc.clone();
+        acc_iter.add_iter_no_proof(xs.iter().cloned());
+        assert_eq!(acc_iter.state(), acc_seq.state());
+
+        // the set is updated as well
+        for x in &xs[..5] {
+            let w = acc_batch.mem_wit_create(x);
+            assert!(acc_seq.ver_mem(&w, x));
+        }
+        acc_batch.del(&xs[0]).unwrap();
+        acc_seq.del(&xs[0]).unwrap();
+        assert_eq!(acc_batch.state(), acc_seq.state());
+    }
+
+    #[test]
+    fn test_create_all_mem_wit_map() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let mut xs = rng.gen_distinct_primes(10, 128);
+        for x in &xs {
+            acc.add(x);
+        }
+
+        let ws = acc.create_all_mem_wit_map(&xs);
+        assert_eq!(ws.len(), xs.len());
+
+        xs.shuffle(rng);
+        for x in &xs {
+            assert!(acc.ver_mem(&ws[x], x));
+        }
+    }
+
+    #[test]
+    fn test_extend_all_mem_wit() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = rng.gen_distinct_primes(12, 128);
+        let mut ws = Vec::new();
+        // from nothing, by single elements and in batches
+        for chunk in &[&xs[..3], &xs[3..4], &xs[4..5], &xs[5..5], &xs[5..12]] {
+            acc.batch_add(chunk);
+            acc.extend_all_mem_wit(&mut ws, chunk);
+
+            let len = ws.len();
+            let fresh = acc.create_all_mem_wit(&xs[..len]);
+            assert_eq!(ws.iter().map(|(_, w)| w.clone()).collect::<Vec<_>>(), fresh);
+            for (x, w) in &ws {
+                assert!(acc.ver_mem(w, x));
+            }
+        }
+        assert_eq!(ws.len(), xs.len());
+    }
+
+    #[test]
+    fn test_compress_witnesses() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = rng.gen_distinct_primes(6, 128);
+        for x in &xs {
+            acc.add(x);
+        }
+        let ws = acc.create_all_mem_wit(&xs);
+        let state = acc.public_state();
+
+        // disjoint subsets compress independently
+        let (xs_a, xs_b) = xs.split_at(3);
+        let (ws_a, ws_b) = ws.split_at(3);
+        let pairs = |xs: &[BigUint], ws: &[MembershipWitness]| {
+            xs.iter()
+                .cloned()
+                .zip(ws.iter().cloned())
+                .collect::<Vec<_>>()
+        };
+        let cw_a = state.compress_witnesses(&pairs(xs_a, ws_a)).unwrap();
+        let cw_b = state.compress_witnesses(&pairs(xs_b, ws_b)).unwrap();
+
+        assert!(state.ver_compressed(xs_a, &cw_a));
+        assert!(state.ver_compressed(xs_b, &cw_b));
+        assert!(!state.ver_compressed(xs_b, &cw_a));
+        assert!(!state.ver_compressed(&xs, &cw_a));
+        assert!(!state.ver_compressed(&[], &cw_a));
+
+        // individual witnesses can be recovered
+        for (i, (x, w)) in xs_a.iter().zip(ws_a).enumerate() {
+            let others = xs_a
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, x)| x.clone())
+                .collect::<Vec<_>>();
+            let w_x = state.decompress_witness(&cw_a, &others);
+            assert_eq!(&w_x, w);
+            assert!(state.ver_mem(&w_x, x));
+        }
+
+        // everything at once
+        let cw = state.compress_witnesses(&pairs(&xs, &ws)).unwrap();
+        assert!(state.ver_compressed(&xs, &cw));
+        assert!(state.compress_witnesses(&[]).is_none());
+    }
+
+    #[test]
+    fn test_compress_witnesses_non_member() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = rng.gen_distinct_primes(3, 128);
+        for x in &xs {
+            acc.add(x);
+        }
+        let ws = acc.create_all_mem_wit(&xs);
+        let state = acc.public_state();
+
+        // `y` is not a member, so no witness for it exists
+        let y = rng.gen_prime(128);
+        let mut items = xs.iter().cloned().zip(ws).collect::<Vec<_>>();
+        items.push((y.clone(), items[0].1.clone()));
+        assert!(state.compress_witnesses(&items).is_none());
+        items.swap(0, 3);
+        assert!(state.compress_witnesses(&items).is_none());
+
+        // a valid compressed witness does not cover `y`
+        let cw = state.compress_witnesses(&items[1..]).unwrap();
+        let mut elements = xs.clone();
+        assert!(state.ver_compressed(&elements, &cw));
+        elements.push(y);
+        assert!(!state.ver_compressed(&elements, &cw));
+    }
+
+    #[test]
+    fn test_non_invertible_elements() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        // a modulus with known factorization
+        let p = rng.gen_prime(128);
+        let n = &p * rng.gen_prime(128);
+        let g = BigUint::from(4u32);
+        let x = rng.gen_prime(128);
+
+        // a state that shares the factor `p` with the modulus
+        let state = PublicState {
+            n: n.clone(),
+            g: g.clone(),
+            root: (&p * 3u32) % &n,
+        };
+
+        // v = A^b is not invertible either, but the PoKE2 for it is valid
+        let b = BigInt::from(rng.gen_prime(64));
+        let v = modpow_uint_int(&state.root, &b, &n).unwrap();
+        let pi_d = proofs::ni_poke2_prove(b.clone(), &state.root, &v, &n).unwrap();
+        assert!(proofs::ni_poke2_verify(&state.root, &v, &pi_d, &n));
+
+        let pi = NonMembershipProof {
+            d: BigUint::from(5u32),
+            v,
+            pi_d,
+            pi_g: PoeProof {
+                q: BigUint::from(2u32),
+            },
+        };
+        assert!(!state.ver_non_mem_star(&x, &pi));
+
+        // negative exponents need the inverse of the state
+        let w = NonMembershipWitness {
+            d: g.clone(),
+            b: -b.clone(),
+        };
+        assert!(!state.ver_non_mem(&w, &x));
+        assert_eq!(
+            proofs::ni_poke2_prove(-b, &state.root, &g, &n),
+            Err(MathError::NotInvertible)
+        );
+
+        // the provers fail instead of panicking for an unchecked generator without inverse
+        let mut acc = Accumulator::new(n.clone(), &p * 2u32);
+        acc.add(&x);
+        let y = rng.gen_prime(128);
+        let err = AccumulatorError::Math(MathError::NotInvertible);
+        assert_eq!(acc.try_non_mem_wit_create(&y), Err(err.clone()));
+        assert_eq!(acc.try_non_mem_wit_create_star(&y), Err(err));
+        assert_eq!(
+            acc.batch_non_mem_wit_create_star(&[y]),
+            Err(BatchNonMemError::Math(MathError::NotInvertible))
+        );
+    }
+
+    #[test]
+    fn test_from_state() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = rng.gen_distinct_primes(4, 128);
+        acc.add(&xs[0]);
+
+        let (n, g) = (acc.modulus().clone(), acc.generator().clone());
+        let mut state =
+            PublicState::from_state(int_size_bits, n.clone(), g.clone(), acc.state().clone())
+                .unwrap();
+        assert_eq!(state, acc.public_state());
+
+        let w = acc.mem_wit_create(&xs[0]);
+        assert!(state.ver_mem(&w, &xs[0]));
+
+        // follow the accumulator
+        let w = acc.batch_add(&xs[1..3]);
+        assert_eq!(state.advance_to(acc.state().clone(), &w, &xs[1..2]), None);
+        assert_eq!(state.advance_to(n.clone(), &w, &xs[1..3]), None);
+        assert_eq!(
+            state.advance_to(acc.state().clone(), &w, &xs[1..3]),
+            Some(())
+        );
+        assert_eq!(state, acc.public_state());
+
+        let w = acc.mem_wit_create(&xs[2]);
+        assert!(state.ver_mem(&w, &xs[2]));
+        let w = acc.non_mem_wit_create(&xs[3]);
+        assert!(state.ver_non_mem(&w, &xs[3]));
+        let pi = acc.non_mem_wit_create_star(&xs[3]);
+        assert!(state.ver_non_mem_star(&xs[3], &pi));
+    }
+
+    #[test]
+    fn test_from_state_invalid() {
+        let n = (BigUint::one() << 255) + 1u32;
+        let g = BigUint::from(65547u32);
+        let root = BigUint::from(7u32);
+        let from_state = |bits, n: &BigUint, g: &BigUint, root: &BigUint| {
+            PublicState::from_state(bits, n.clone(), g.clone(), root.clone())
+        };
+
+        assert!(from_state(256, &n, &g, &root).is_ok());
+        assert!(from_state(256, &n, &g, &BigUint::one()).is_ok());
+
+        assert_eq!(
+            from_state(256, &(&n + 1u32), &g, &root),
+            Err(ParamError::EvenModulus)
+        );
+        assert_eq!(
+            from_state(2048, &n, &g, &root),
+            Err(ParamError::ModulusSize {
+                expected: 2048,
+                actual: 256
+            })
+        );
+        for g in &[BigUint::zero(), BigUint::one(), n.clone(), &n + 2u32] {
+            assert_eq!(
+                from_state(256, &n, g, &root),
+                Err(ParamError::InvalidGenerator)
+            );
+        }
+        for root in &[BigUint::zero(), n.clone(), &n + 2u32] {
+            assert_eq!(from_state(256, &n, &g, root), Err(ParamError::InvalidState));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_json() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let x = rng.gen_prime(128);
+        acc.add(&x);
+
+        let s = serde_json::to_string(&acc).unwrap();
+        let acc2: Accumulator = serde_json::from_str(&s).unwrap();
+        assert_eq!(acc2.public_state(), acc.public_state());
+        assert!(acc2.contains(&x));
+        assert_eq!(acc2.len(), 1);
+
+        // with the epoch index
+        let mut acc3 = acc2.with_epoch_index();
+        acc3.add_epoch(b"id", 0).unwrap();
+        let s3 = serde_json::to_string(&acc3).unwrap();
+        assert!(!s.contains("epoch_index"));
+        let mut acc4: Accumulator = serde_json::from_str(&s3).unwrap();
+        assert_eq!(acc4.expire_epoch(0).unwrap().count(), 1);
+        assert!(acc4.contains(&x));
+
+        // the shared parameters are flattened into the accumulator
+        let value: serde_json::Value = serde_json::from_str(&s).unwrap();
+        let mut keys = value.as_object().unwrap().keys().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(
+            keys,
+            ["epoch", "g", "int_size_bits", "len", "n", "root", "set"]
+        );
+
+        let n = crate::encoding::hex::biguint_to_hex(&acc.params.n);
+        let mut value: serde_json::Value = serde_json::from_str(&s).unwrap();
+        value["root"] = serde_json::Value::String(n);
+        assert!(serde_json::from_value::<Accumulator>(value).is_err());
+    }
+
+    #[test]
+    fn test_ver_mem_all() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = rng.gen_distinct_primes(40, 128);
+        acc.add_batch_no_proof(&xs);
+
+        let mut pairs = xs
+            .iter()
+            .cloned()
+            .zip(acc.create_all_mem_wit(&xs))
+            .collect::<Vec<_>>();
+        assert!(acc.ver_mem_all(&pairs).is_empty());
+
+        // a wrong witness, a swapped element, a non-member and an invalid group element
+        pairs[3].1 = pairs[4].1.clone();
+        pairs[17].0 = xs[18].clone();
+        pairs[25].0 = rng.gen_prime(128);
+        pairs[39].1 = MembershipWitness(acc.params.n.clone());
+        let failed = vec![3, 17, 25, 39];
+        assert_eq!(acc.ver_mem_all(&pairs), failed);
+        assert_eq!(acc.public_state().ver_mem_all(&pairs), failed);
+        assert!(acc.ver_mem_all(&[]).is_empty());
+
+        let mut proofs = xs[..10]
+            .iter()
+            .map(|x| (x.clone(), acc.mem_wit_create_star(x)))
+            .collect::<Vec<_>>();
+        assert!(acc.ver_mem_star_all(&proofs).is_empty());
+        proofs[0].1 = proofs[1].1.clone();
+        proofs[9].0 = xs[10].clone();
+        assert_eq!(acc.ver_mem_star_all(&proofs), vec![0, 9]);
+    }
 
-        // d^x A^b == g
-        let lhs = (&d_x * &a_b) % &n;
-        println!("> {} = {} * {} mod {}", &lhs, &d_x, &a_b, &n);
-        assert_eq!(lhs, g);
+    #[test]
+    fn test_clone_shares_params() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = rng.gen_distinct_primes(3, 128);
+        acc.add(&xs[0]);
+
+        let mut copy = acc.clone();
+        assert!(Arc::ptr_eq(acc.params(), copy.params()));
+        assert_eq!(Arc::strong_count(acc.params()), 2);
+
+        // mutating one clone leaves the other unchanged
+        let state = acc.public_state();
+        copy.add(&xs[1]);
+        assert_eq!(acc.public_state(), state);
+        assert!(!acc.contains(&xs[1]) && acc.len() == 1);
+
+        acc.add(&xs[2]);
+        copy.del(&xs[0]).unwrap();
+        assert!(acc.contains(&xs[0]) && !copy.contains(&xs[2]));
+        assert_eq!(copy.len(), 1);
+        assert_ne!(acc.state(), copy.state());
+        assert!(Arc::ptr_eq(acc.params(), copy.params()));
+
+        drop(copy);
+        assert_eq!(Arc::strong_count(acc.params()), 1);
+
+        // and so do frozen snapshots, and their clones
+        let frozen = acc.freeze();
+        let shared = frozen.clone();
+        assert!(Arc::ptr_eq(acc.params(), frozen.params()));
+        assert_eq!(Arc::strong_count(acc.params()), 2);
+        assert_eq!(frozen.public_state(), &acc.public_state());
+        acc.add(&xs[1]);
+        assert_ne!(shared.state(), acc.state());
+        drop((frozen, shared));
+        assert_eq!(Arc::strong_count(acc.params()), 1);
     }
 
-    fn test_batch_add_size(size: usize) {
-        println!("batch_add_size {}", size);
+    #[test]
+    fn test_trait_objects() {
         let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
 
+        let mut acc: Box<dyn DynamicAccumulator<MemWitness = MembershipWitness>> =
+            Box::new(Accumulator::setup::<RSAGroup, _>(rng, int_size_bits));
+
+        let x = rng.gen_prime(128);
+        acc.add(&x);
+        let w = acc.mem_wit_create(&x);
+        assert!(acc.ver_mem(&w, &x));
+        acc.del(&x).unwrap();
+        assert!(!acc.ver_mem(&w, &x));
+
+        let mut acc: Box<
+            dyn UniversalAccumulator<
+                MemWitness = MembershipWitness,
+                NonMemWitness = NonMembershipWitness,
+            >,
+        > = Box::new(Accumulator::setup::<RSAGroup, _>(rng, int_size_bits));
+        acc.add(&x);
+        let y = rng.gen_prime(128);
+        let w = acc.non_mem_wit_create(&y);
+        assert!(acc.ver_non_mem(&w, &y));
+
+        let mut acc: Box<
+            dyn BatchUpdateAccumulator<MemWitness = MembershipWitness, BatchProof = BatchProof>,
+        > = Box::new(Accumulator::setup::<RSAGroup, _>(rng, int_size_bits));
+        let root = acc.state().clone();
+        let xs = vec![x.clone(), y.clone()];
+        let pi = acc.batch_add(&xs);
+        let (old, new) = (OldState(root), NewState(acc.state().clone()));
+        assert!(acc.ver_batch_add_transition(&pi, &old, &new, &xs));
+
+        let mut acc: Box<
+            dyn AggregatableAccumulator<
+                MemWitness = MembershipWitness,
+                MemProof = MembershipProof,
+                NonMemProof = NonMembershipProof,
+            >,
+        > = Box::new(Accumulator::setup::<RSAGroup, _>(rng, int_size_bits));
+        acc.add(&x);
+        let pi = acc.mem_wit_create_star(&x);
+        assert!(acc.ver_mem_star(&x, &pi));
+        let pi = acc.non_mem_wit_create_star(&y);
+        assert!(acc.ver_non_mem_star(&y, &pi));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_batched_accumulator_compat() {
+        fn batch_add_star<A: BatchedAccumulator>(acc: &mut A, xs: &[BigUint]) -> A::MemProof {
+            acc.batch_add(xs);
+            acc.mem_wit_create_star(&xs[0])
+        }
+
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
         let int_size_bits = 256; // insecure, but faster tests
         let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
 
-        // regular add
-        let x0 = rng.gen_prime(int_size_bits);
-        acc.add(&x0);
+        let xs = vec![rng.gen_prime(128), rng.gen_prime(128)];
+        let pi = batch_add_star(&mut acc, &xs);
+        assert!(acc.ver_mem_star(&xs[0], &pi));
+    }
 
-        // batch add
-        let root = acc.state().clone();
-        let xs = (0..size)
-            .map(|_| rng.gen_prime(int_size_bits))
+    #[test]
+    fn test_prime_elements() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut raw = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut typed = raw.clone();
+
+        let xs = rng.gen_distinct_primes(5, 128);
+        let primes = xs
+            .iter()
+            .map(|x| PrimeElement::new_checked(x.clone(), &Default::default()).unwrap())
             .collect::<Vec<_>>();
-        let w = acc.batch_add(&xs);
 
-        // verify batch add
-        assert!(acc.ver_batch_add(&w, &root, &xs), "ver_batch_add failed");
+        // the typed path gives the same states, proofs and witnesses
+        raw.try_add(&xs[0]).unwrap();
+        typed.add_prime(&primes[0]).unwrap();
+        assert_eq!(typed.state(), raw.state());
 
-        // delete with member
-        let x = &xs[2];
-        let w = acc.mem_wit_create(x);
-        assert!(acc.ver_mem(&w, x), "failed to verify valid witness");
+        let pi = raw.try_batch_add(&xs[1..4]).unwrap();
+        assert_eq!(typed.batch_add_primes(&primes[1..4]).unwrap(), pi);
+        assert_eq!(typed.state(), raw.state());
 
-        acc.del_w_mem(&w, x).unwrap();
-        assert!(
-            !acc.ver_mem(&w, x),
-            "witness verified, even though it was deleted"
+        assert_eq!(
+            typed.mem_wit_create_prime(&primes[2]),
+            raw.try_mem_wit_create(&xs[2])
+        );
+        assert_eq!(
+            typed.non_mem_wit_create_prime(&primes[4]),
+            raw.try_non_mem_wit_create(&xs[4])
+        );
+        assert_eq!(
+            typed.mem_wit_create_prime(&primes[4]),
+            Err(AccumulatorError::NotAMember)
         );
 
-        // create all members witness
-        // current state contains xs\x + x0
-        let mut set = vec![x0.clone(), xs[0].clone(), xs[1].clone()];
-        set.extend(xs.iter().skip(3).cloned());
+        raw.del(&xs[1]).unwrap();
+        typed.del_prime(&primes[1]).unwrap();
+        assert_eq!(typed.state(), raw.state());
+        assert_eq!(typed.del_prime(&primes[1]), None);
+    }
 
-        let ws = acc.create_all_mem_wit(&set);
+    #[test]
+    fn test_epochs() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        assert_eq!(acc.current_epoch(), 0);
+
+        acc.add_epoch(b"alice", 0).unwrap();
+        acc.add_epoch(b"bob", 0).unwrap();
+        let w = acc.mem_wit_create_epoch(b"alice", 0).unwrap();
+        assert!(acc.ver_mem_epoch(&w, b"alice", 0));
+        assert!(!acc.ver_mem_epoch(&w, b"alice", 1));
+        assert!(!acc.ver_mem_epoch(&w, b"bob", 0));
+        assert_eq!(
+            acc.mem_wit_create_epoch(b"alice", 1),
+            Err(AccumulatorError::NotAMember)
+        );
 
-        for (w, x) in ws.iter().zip(set.iter()) {
-            assert!(acc.ver_mem(w, x));
+        // only alice is carried over
+        let root = acc.state().clone();
+        let pi = acc.advance_epoch(&[b"alice"]).unwrap();
+        assert_eq!(acc.current_epoch(), 1);
+        let (old, new) = (OldState(root), NewState(acc.state().clone()));
+        let alice = [epoch_element(b"alice", 1).into_biguint()];
+        assert!(acc.ver_batch_add_transition(&pi, &old, &new, &alice));
+
+        let w1 = acc.mem_wit_create_epoch(b"alice", 1).unwrap();
+        let state = acc.public_state();
+        assert!(state.ver_mem_epoch(&w1, b"alice", 1));
+        assert!(!state.ver_mem_epoch(&w1, b"alice", 0));
+        assert!(acc.mem_wit_create_epoch(b"bob", 1).is_err());
+
+        // failing to carry over stays in the epoch
+        let mut limited = acc.clone().with_capacity_limits(CapacityLimits {
+            max_elements: Some(acc.len()),
+            max_set_bits: None,
+        });
+        assert!(limited.advance_epoch(&[b"bob"]).is_err());
+        assert_eq!(limited.current_epoch(), 1);
+        assert_eq!(
+            limited.advance_epoch::<&[u8]>(&[]).unwrap(),
+            limited.try_batch_add(&[]).unwrap()
+        );
+        assert_eq!(limited.current_epoch(), 2);
+    }
+
+    #[test]
+    fn test_expire_epoch() {
+        use crate::witness::WitnessManager;
+
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        assert_eq!(acc.expire_epoch(0), Err(AccumulatorError::NoEpochIndex));
+        let mut acc = acc.with_epoch_index();
+
+        // interleaved adds to epochs 0 and 1, with b carried over
+        let ids: Vec<&[u8]> = vec![b"a", b"b", b"c", b"d", b"e"];
+        acc.add_epoch(ids[0], 0).unwrap();
+        acc.add_epoch(ids[1], 0).unwrap();
+        acc.advance_epoch(&ids[1..2]).unwrap();
+        acc.add_epoch(ids[2], 0).unwrap();
+        acc.add_epoch(ids[3], 1).unwrap();
+        acc.add_epoch(ids[4], 0).unwrap();
+        assert_eq!(acc.len(), 6);
+
+        let survivor = epoch_element(ids[3], 1).into_biguint();
+        let mut manager = WitnessManager::new(&acc);
+        manager.register(&acc, &survivor);
+        let w_b = acc.mem_wit_create_epoch(ids[1], 1).unwrap();
+
+        // e was deleted on its own before
+        let e = epoch_element(ids[4], 0).into_biguint();
+        manager.del(&mut acc, &e).unwrap();
+
+        let root = acc.state().clone();
+        let pi = acc.expire_epoch(0).unwrap();
+        assert_eq!(pi.count(), 4 - 1);
+        assert_eq!(
+            pi.ids,
+            vec![ids[0].to_vec(), ids[1].to_vec(), ids[2].to_vec()]
+        );
+        assert_eq!(acc.len(), 2);
+        assert!(acc.ver_expiry(&pi, &root));
+        assert!(acc.public_state().ver_expiry(&pi, &root));
+
+        // the same state as adding the survivors only
+        let mut expected = Accumulator::new(acc.modulus().clone(), acc.generator().clone());
+        expected.add_epoch(ids[1], 1).unwrap();
+        expected.add_epoch(ids[3], 1).unwrap();
+        assert_eq!(acc.state(), expected.state());
+        for id in &ids {
+            assert!(acc.mem_wit_create_epoch(id, 0).is_err());
         }
 
-        // batch delete
+        // witnesses of the survivors can be updated
+        manager.observe_del(&pi.elements(), acc.state());
+        let w = manager.witness_for(&survivor).unwrap().clone();
+        assert!(acc.ver_mem(&w, &survivor));
+        assert!(!acc.ver_mem_epoch(&w_b, ids[1], 1));
+
+        // forged proofs
+        let mut forged = pi.clone();
+        forged.ids.pop();
+        assert!(!acc.ver_expiry(&forged, &root));
+        let mut forged = pi.clone();
+        forged.epoch = 1;
+        assert!(!acc.ver_expiry(&forged, &root));
+
+        // expiring again does nothing
         let root = acc.state().clone();
-        let pairs = set
-            .iter()
-            .cloned()
-            .zip(ws.iter().cloned())
-            .take(3)
-            .collect::<Vec<_>>();
-        let w = acc.batch_del(&pairs[..]).unwrap();
+        let pi = acc.expire_epoch(0).unwrap();
+        assert_eq!(pi.count(), 0);
+        assert_eq!(acc.state(), &root);
+        assert!(acc.ver_expiry(&pi, &root));
+
+        let pi = acc.expire_epoch(1).unwrap();
+        assert_eq!(pi.count(), 2);
+        assert!(acc.is_empty());
+        assert_eq!(acc.state(), acc.generator());
+    }
 
+    #[test]
+    fn test_bloom_filter() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut plain = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc = plain.clone().with_bloom_filter(100, 0.05);
+
+        let pool = rng.gen_distinct_primes(150, 64);
+        let mut members: Vec<BigUint> = Vec::new();
+        let mut filtered = 0;
+        for round in 0..60 {
+            let free = pool
+                .iter()
+                .filter(|x| !members.contains(x))
+                .cloned()
+                .collect::<Vec<_>>();
+            match rng.gen_range(0, 5) {
+                0 => {
+                    let x = free.choose(rng).unwrap();
+                    acc.add(x);
+                    plain.add(x);
+                    members.push(x.clone());
+                }
+                1 => {
+                    let xs = free.choose_multiple(rng, 4).cloned().collect::<Vec<_>>();
+                    acc.batch_add(&xs);
+                    plain.batch_add(&xs);
+                    members.extend(xs);
+                }
+                2 => {
+                    let xs = free.choose_multiple(rng, 3).cloned().collect::<Vec<_>>();
+                    acc.try_add_iter(xs.clone()).unwrap();
+                    plain.try_add_iter(xs.clone()).unwrap();
+                    members.extend(xs);
+                }
+                3 if !members.is_empty() => {
+                    let x = members.swap_remove(rng.gen_range(0, members.len()));
+                    acc.del(&x).unwrap();
+                    plain.del(&x).unwrap();
+                }
+                _ if members.len() >= 2 => {
+                    members.shuffle(rng);
+                    let xs = members.split_off(members.len() - 2);
+                    let pairs = xs
+                        .iter()
+                        .map(|x| (x.clone(), acc.mem_wit_create(x)))
+                        .collect::<Vec<_>>();
+                    acc.batch_del(&pairs).unwrap();
+                    plain.batch_del(&pairs).unwrap();
+                }
+                _ => {}
+            }
+            assert_eq!(acc.state(), plain.state(), "round {}", round);
+
+            // the exact and filtered answers agree
+            for x in &pool {
+                let exact = acc.set.is_multiple_of(x);
+                assert_eq!(acc.contains(x), exact);
+                assert_eq!(exact, members.contains(x));
+                assert_eq!(
+                    acc.try_non_mem_wit_create(x).is_ok(),
+                    plain.try_non_mem_wit_create(x).is_ok()
+                );
+                if !acc.bloom_filter().unwrap().may_contain(x) {
+                    assert!(!exact);
+                    filtered += 1;
+                }
+            }
+        }
+        // most non-members never touch the set
+        let non_members = 60 * pool.len() - members.len();
         assert!(
-            acc.ver_batch_del(&w, &root, &set[..3]),
-            "ver_batch_del failed"
+            filtered > non_members / 2,
+            "{} of {}",
+            filtered,
+            non_members
         );
     }
 
     #[test]
-    fn test_batch_add_small() {
-        for i in 4..14 {
-            test_batch_add_size(i)
-        }
+    fn test_bloom_filter_merge() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = rng.gen_distinct_primes(4, 64);
+
+        let mut acc1 = acc.clone().with_bloom_filter(10, 0.01);
+        let mut acc2 = acc.clone().with_bloom_filter(10, 0.01);
+        acc1.add(&xs[0]);
+        acc2.add(&xs[1]);
+        let (merged, _) = acc1.clone().merge(acc2).unwrap();
+        assert!(merged.contains(&xs[0]) && merged.contains(&xs[1]));
+        assert!(merged.bloom_filter().is_some());
+
+        // without a compatible filter, the merged one is dropped
+        let mut acc3 = acc.clone().with_bloom_filter(1000, 0.01);
+        acc3.add(&xs[2]);
+        let (merged, _) = acc1.clone().merge(acc3).unwrap();
+        assert!(merged.bloom_filter().is_none());
+        assert!(merged.contains(&xs[2]));
+
+        let mut acc4 = acc;
+        acc4.add(&xs[3]);
+        let (merged, _) = acc1.merge(acc4).unwrap();
+        assert!(merged.bloom_filter().is_none());
+        assert!(merged.contains(&xs[3]));
     }
 
     #[test]
-    fn test_batch_add_large() {
-        let size = 128;
+    fn test_batch_mem_wit() {
         let rng = &mut ChaChaRng::from_seed([0u8; 32]);
         let int_size_bits = 256; // insecure, but faster tests
         let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
 
-        // regular add
-        let x0 = rng.gen_prime(int_size_bits);
-        acc.add(&x0);
+        let xs = rng.gen_distinct_primes(7, 128);
+        acc.add_batch_no_proof(&xs[..6]);
+        let ws = acc.create_all_mem_wit(&xs[..6]);
 
-        // batch add
-        let root = acc.state().clone();
-        let xs = (0..size)
-            .map(|_| rng.gen_prime(int_size_bits))
-            .collect::<Vec<_>>();
-        let w = acc.batch_add(&xs);
+        for k in 1..=6 {
+            let subset = &xs[6 - k..6];
+            let cw = acc.batch_mem_wit_create(subset).unwrap();
+            assert!(acc.ver_batch_mem(subset, &cw));
 
-        // verify batch add
-        assert!(acc.ver_batch_add(&w, &root, &xs), "ver_batch_add failed");
+            // the same as folding the individual witnesses with the Shamir trick
+            let items = subset
+                .iter()
+                .cloned()
+                .zip(ws[6 - k..].iter().cloned())
+                .collect::<Vec<_>>();
+            assert_eq!(acc.public_state().compress_witnesses(&items).unwrap(), cw);
+        }
 
-        // batch add
-        let root = acc.state().clone();
-        let xs = (0..size)
-            .map(|_| rng.gen_prime(int_size_bits))
-            .collect::<Vec<_>>();
-        let w = acc.batch_add(&xs);
+        let cw = acc.batch_mem_wit_create(&xs[..3]).unwrap();
+        assert!(!acc.ver_batch_mem(&xs[..2], &cw));
+        assert!(!acc.ver_batch_mem(&xs[1..4], &cw));
+        assert!(!acc.ver_batch_mem(&[xs[0].clone(), xs[0].clone(), xs[1].clone()], &cw));
+
+        // the offenders are reported
+        let mut ys = xs[..3].to_vec();
+        ys.push(xs[6].clone());
+        assert_eq!(
+            acc.batch_mem_wit_create(&ys),
+            Err(BatchMemError::NotAMember(3))
+        );
+        ys[3] = xs[1].clone();
+        assert_eq!(
+            acc.batch_mem_wit_create(&ys),
+            Err(BatchMemError::Duplicate(3))
+        );
+        assert_eq!(
+            acc.batch_mem_wit_create(&[BigUint::one()]),
+            Err(BatchMemError::NotAMember(0))
+        );
+        assert_eq!(acc.batch_mem_wit_create(&[]), Err(BatchMemError::Empty));
 
-        // verify batch add
-        assert!(acc.ver_batch_add(&w, &root, &xs), "ver_batch_add failed");
+        // each divides the set, but not their product
+        let x2 = &xs[0] * &xs[1];
+        assert_eq!(
+            acc.batch_mem_wit_create(&[xs[0].clone(), x2]),
+            Err(BatchMemError::NotAMember(1))
+        );
     }
 
     #[test]
-    fn test_aggregation() {
+    fn test_batch_non_mem_star() {
         let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
 
-        for _ in 0..10 {
-            let int_size_bits = 256; // insecure, but faster tests
-            let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = rng.gen_distinct_primes(12, 128);
+        acc.add_batch_no_proof(&xs[..4]);
+        let ys = &xs[4..];
+
+        let pi = acc.batch_non_mem_wit_create_star(ys).unwrap();
+        assert!(acc.ver_batch_non_mem_star(ys, &pi));
+        assert!(acc.ver_batch_non_mem_star(
+            &ys[..1],
+            &acc.batch_non_mem_wit_create_star(&ys[..1]).unwrap()
+        ));
+        assert!(!acc.ver_batch_non_mem_star(&ys[1..], &pi));
+        assert!(!acc.ver_batch_non_mem_star(&[], &pi));
+
+        // a member snuck in
+        let mut with_member = ys.to_vec();
+        with_member[5] = xs[2].clone();
+        assert_eq!(
+            acc.batch_non_mem_wit_create_star(&with_member),
+            Err(BatchNonMemError::IsMember(5))
+        );
+        assert!(!acc.ver_batch_non_mem_star(&with_member, &pi));
+        with_member[5] = &xs[2] * &xs[3];
+        assert_eq!(
+            acc.batch_non_mem_wit_create_star(&with_member),
+            Err(BatchNonMemError::IsMember(5))
+        );
+        assert_eq!(
+            acc.batch_non_mem_wit_create_star(&[ys[0].clone(), BigUint::one()]),
+            Err(BatchNonMemError::InvalidElement(1))
+        );
+        assert_eq!(
+            acc.batch_non_mem_wit_create_star(&[]),
+            Err(BatchNonMemError::Empty)
+        );
 
-            // regular add
-            let xs = (0..5)
-                .map(|_| rng.gen_prime(int_size_bits))
-                .collect::<Vec<_>>();
+        // the size of a single proof, instead of one per element
+        let sizes = ys
+            .iter()
+            .map(|y| acc.non_mem_wit_create_star(y).to_packed_bytes().len())
+            .collect::<Vec<_>>();
+        let batch = pi.to_packed_bytes().len();
+        assert!(batch <= sizes.iter().max().unwrap() + 8);
+        assert!(batch * 4 < sizes.iter().sum::<usize>());
 
-            for x in &xs {
-                acc.add(x);
-            }
+        // stale after adding one of them
+        acc.add(&ys[0]);
+        assert!(!acc.ver_batch_non_mem_star(ys, &pi));
+    }
 
-            // AggMemWit
-            {
-                let x = &xs[0];
-                let y = &xs[1];
-                let w_x = acc.mem_wit_create(x);
-                let w_y = acc.mem_wit_create(y);
+    /// `batch_del_root` as a left to right fold.
+    fn fold_del_root(
+        n: &BigUint,
+        pairs: &[(BigUint, MembershipWitness)],
+    ) -> Option<(BigUint, BigUint)> {
+        let (x0, w0) = pairs.first()?;
+        let mut x_star = x0.clone();
+        let mut new_root = w0.0.clone();
 
-                let (w_xy, p_wxy) = acc.agg_mem_wit(&w_x, &w_y, x, y);
+        for (xi, wi) in &pairs[1..] {
+            new_root = shamir_trick(&new_root, &wi.0, &x_star, xi, n)?;
+            x_star *= xi;
+        }
 
-                assert!(
-                    acc.ver_agg_mem_wit(&w_xy, &p_wxy, x, y),
-                    "invalid agg_mem_wit proof"
-                );
-            }
+        Some((new_root, x_star))
+    }
 
-            // MemWitCreate*
-            {
-                let pis = (0..5)
-                    .map(|i| acc.mem_wit_create_star(&xs[i]))
-                    .collect::<Vec<_>>();
-                for (pi, x) in pis.iter().zip(&xs) {
-                    assert!(acc.ver_mem_star(x, pi), "invalid mem_wit_create_star proof");
-                }
-            }
+    #[test]
+    fn test_batch_del_root() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
 
-            // MemWitX
-            {
-                let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
-                let mut other = acc.clone(); // should use the same `n` and `g`.
-                //let mut other = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
-                let x = rng.gen_prime(128);
-                let y = rng.gen_prime(128);
+        let xs = rng.gen_distinct_primes(100, 64);
+        acc.add_batch_no_proof(&xs);
+        let ws = acc.create_all_mem_wit(&xs);
+        let mut pairs = xs.into_iter().zip(ws).collect::<Vec<_>>();
 
-                assert!(x.gcd(&y).is_one(), "x, y must be coprime");
-                // regular add
-                let xs = (0..5)
-                    .map(|_| rng.gen_prime(128))
-                    .collect::<Vec<_>>();
-                for x in &xs {
-                    acc.add(x);
-                }
-                let ys = (0..5)
-                    .map(|_| rng.gen_prime(128))
-                    .collect::<Vec<_>>();
-                for y in &ys {
-                    other.add(y);
-                }
+        for len in &[1, 2, 3, 7, 31, 32, 33, 64, 100] {
+            pairs.shuffle(rng);
+            let pairs = &pairs[..*len];
+            let expected = fold_del_root(acc.modulus(), pairs).unwrap();
+            assert_eq!(batch_del_root(acc.modulus(), pairs), Some(expected));
+        }
 
-                acc.add(&x);
-                other.add(&y);
+        // a witness for another state, in either half
+        assert_eq!(batch_del_root(acc.modulus(), &[]), None);
+        for i in &[0, 99] {
+            let mut invalid = pairs.clone();
+            invalid[*i].1 = MembershipWitness(acc.generator().clone());
+            assert_eq!(batch_del_root(acc.modulus(), &invalid), None);
+        }
+    }
 
-                let w_x = acc.mem_wit_create(&x);
-                let w_y = other.mem_wit_create(&y);
+    #[test]
+    fn test_batch_del_tolerant() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
 
-                assert!(acc.ver_mem(&w_x, &x));
-                assert!(other.ver_mem(&w_y, &y));
+        let xs = rng.gen_distinct_primes(7, 128);
+        acc.add_batch_no_proof(&xs[..6]);
+        let ws = acc.create_all_mem_wit(&xs[..6]);
+        let mut pairs = xs.iter().cloned().zip(ws).collect::<Vec<_>>();
+
+        // deleted by someone else, with its witness now outdated
+        let stale = pairs.remove(0);
+        acc.del(&stale.0).unwrap();
+        let ws = acc.create_all_mem_wit(&xs[1..6]);
+        for ((_, w), w_new) in pairs.iter_mut().zip(ws) {
+            *w = w_new;
+        }
 
-                let w_xy = acc.mem_wit_x(other.state(), &w_x, &w_y, &x, &y);
-                assert!(
-                    acc.ver_mem_x(other.state(), &w_xy, &x, &y),
-                    "invalid ver_mem_x witness"
-                );
-            }
+        let mut batch = vec![
+            pairs[0].clone(),
+            stale.clone(),
+            pairs[1].clone(),
+            // a non-member, with another element's witness
+            (xs[6].clone(), pairs[2].1.clone()),
+            pairs[0].clone(),
+            // a member with a wrong witness
+            (pairs[2].0.clone(), pairs[3].1.clone()),
+            pairs[3].clone(),
+        ];
+        let state = acc.public_state();
+        let (result, skipped) = acc.batch_del_tolerant(&batch);
+
+        assert_eq!(skipped, vec![1, 3, 4, 5]);
+        assert_eq!(
+            result.removed,
+            vec![xs[1].clone(), xs[2].clone(), xs[4].clone()]
+        );
+        assert_eq!(result.old_root, state.root);
+        assert_eq!(&result.root, acc.state());
+        assert!(acc.ver_batch_del_result(&result));
+        assert_eq!(acc.len(), 2);
+        for x in &result.removed {
+            assert!(!acc.contains(x));
         }
+
+        // the proof only covers the reported elements
+        let mut forged = result.clone();
+        forged.removed.pop();
+        assert!(!acc.ver_batch_del_result(&forged));
+        forged.removed = batch.iter().map(|(x, _)| x.clone()).collect();
+        assert!(!acc.ver_batch_del_result(&forged));
+        assert!(!state.ver_batch_del_result(&result));
+
+        // nothing left to delete
+        batch.truncate(2);
+        let root = acc.state().clone();
+        let (result, skipped) = acc.batch_del_tolerant(&batch);
+        assert_eq!(skipped, vec![0, 1]);
+        assert!(result.removed.is_empty() && result.proof.is_none());
+        assert_eq!(acc.state(), &root);
+        assert_eq!(result.root, root);
+        assert!(acc.ver_batch_del_result(&result));
+
+        let (result, skipped) = acc.batch_del_tolerant(&[]);
+        assert!(skipped.is_empty() && result.proof.is_none());
+
+        // a result without a proof can't change the state
+        let forged = BatchDelResult {
+            old_root: state.root.clone(),
+            ..result
+        };
+        assert!(!acc.ver_batch_del_result(&forged));
     }
 
     #[test]
-    fn test_aggregation_non_mem_star() {
+    fn test_verify_detailed() {
         let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
 
-        for _ in 0..10 {
-            let int_size_bits = 256; // insecure, but faster tests
-            let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        // larger than the challenges, so that the proofs are never trivial
+        let xs = rng.gen_distinct_primes(4, 200);
+        acc.add_batch_no_proof(&xs[..2]);
+        let old = acc.public_state();
+        let w = acc.mem_wit_create(&xs[0]);
+        let w_non = acc.non_mem_wit_create(&xs[3]);
+        let pi_non = acc.non_mem_wit_create_star(&xs[3]);
+        let pi_mem = acc.mem_wit_create_star(&xs[0]);
+
+        // the detailed verifiers agree with the boolean ones
+        assert_eq!(old.ver_mem_detailed(&w, &xs[0]), Ok(()));
+        assert_eq!(old.ver_non_mem_detailed(&w_non, &xs[3]), Ok(()));
+        assert_eq!(old.ver_mem_star_detailed(&xs[0], &pi_mem), Ok(()));
+        assert_eq!(old.ver_non_mem_star_detailed(&xs[3], &pi_non), Ok(()));
+
+        let proof = acc.batch_add(&xs[2..3]);
+        let state = acc.public_state();
+        let (before, after) = (OldState(old.root.clone()), NewState(state.root.clone()));
+        assert_eq!(
+            state.ver_batch_add_detailed(&proof, &before, &after, &xs[2..3]),
+            Ok(())
+        );
 
-            // regular add
-            let xs = (0..5)
-                .map(|_| rng.gen_prime(int_size_bits))
-                .collect::<Vec<_>>();
+        // stale witnesses, and witnesses for other elements
+        assert_eq!(
+            state.ver_mem_detailed(&w, &xs[0]),
+            Err(VerifyError::WrongState)
+        );
+        assert_eq!(
+            old.ver_mem_detailed(&w, &xs[1]),
+            Err(VerifyError::WrongState)
+        );
+        assert_eq!(
+            old.ver_non_mem_detailed(&w_non, &xs[2]),
+            Err(VerifyError::WrongState)
+        );
+        assert!(!state.ver_mem(&w, &xs[0]));
 
-            for x in &xs {
-                acc.add(x);
-            }
+        // proofs for other statements
+        assert_eq!(
+            state.ver_batch_add_detailed(&proof, &OldState(state.root.clone()), &after, &xs[2..3]),
+            Err(VerifyError::ChallengeMismatch)
+        );
+        assert_eq!(
+            old.ver_batch_del_detailed(&proof, &before, &NewState(old.root.clone()), &xs[2..3]),
+            Err(VerifyError::ChallengeMismatch)
+        );
+        assert_eq!(
+            // deleting the element again goes back to the old state
+            old.ver_batch_del_detailed(
+                &proof,
+                &OldState(state.root.clone()),
+                &NewState(old.root.clone()),
+                &xs[2..3]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            state.ver_mem_star_detailed(&xs[0], &pi_mem),
+            Err(VerifyError::ChallengeMismatch)
+        );
+        assert_eq!(
+            old.ver_non_mem_star_detailed(&xs[2], &pi_non),
+            Err(VerifyError::ChallengeMismatch)
+        );
 
-            let x = rng.gen_prime(int_size_bits);
-            let pi = acc.non_mem_wit_create_star(&x);
+        // proofs out of range
+        let unreduced = BatchProof(PoeProof {
+            q: &proof.0.q + &state.n,
+        });
+        assert_eq!(
+            state.ver_batch_add_detailed(&unreduced, &before, &after, &xs[2..3]),
+            Err(VerifyError::MalformedInput)
+        );
+        let mut malformed = pi_non.clone();
+        malformed.pi_d.z = BigUint::zero();
+        assert_eq!(
+            old.ver_non_mem_star_detailed(&xs[3], &malformed),
+            Err(VerifyError::MalformedInput)
+        );
 
-            assert!(acc.ver_non_mem_star(&x, &pi), "invalid ver_non_mem_star");
-        }
+        // elements sharing a factor
+        let w_xy = acc.mem_wit_x(&old.root, &w, &w, &xs[0], &xs[0]);
+        assert_eq!(
+            state.ver_mem_x_detailed(&old.root, &w_xy, &xs[0], &xs[0]),
+            Err(VerifyError::NotCoprime)
+        );
+        assert_eq!(
+            state.ver_mem_x_detailed(&old.root, &w_xy, &xs[0], &xs[1]),
+            Err(VerifyError::WrongState)
+        );
+
+        // a state sharing a factor with a modulus of known factors, 3233 = 53 * 61
+        let weak = PublicState {
+            n: BigUint::from(3233u32),
+            g: BigUint::from(2u32),
+            root: BigUint::from(61u32),
+        };
+        let w_neg = NonMembershipWitness {
+            d: BigUint::from(2u32),
+            b: -BigInt::one(),
+        };
+        assert_eq!(
+            weak.ver_non_mem_detailed(&w_neg, &BigUint::from(17u32)),
+            Err(VerifyError::NotInvertible)
+        );
+    }
+
+    #[test]
+    fn test_verify_bounds() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = rng.gen_distinct_primes(3, 128);
+        acc.add_batch_no_proof(&xs[..2]);
+        let state = acc.public_state();
+        let w = acc.mem_wit_create(&xs[0]);
+        let w_non = acc.non_mem_wit_create(&xs[2]);
+        let pi_mem = acc.mem_wit_create_star(&xs[0]);
+        let pi_non = acc.non_mem_wit_create_star(&xs[2]);
+        let proof = acc.batch_add(&xs[2..]);
+        let new_state = acc.public_state();
+        let (old, new) = (
+            OldState(state.root.clone()),
+            NewState(new_state.root.clone()),
+        );
+
+        // a megabyte, exponentiating by it would take seconds
+        let huge = BigUint::one() << (8 * DEFAULT_MAX_INT_BYTES);
+        let huge_int = BigInt::from_biguint(Sign::Minus, huge.clone());
+        let start = std::time::Instant::now();
+        let malformed = Err(VerifyError::MalformedInput);
+
+        // group elements
+        let unreduced = MembershipWitness(&w.0 + &huge);
+        assert_eq!(state.ver_mem_detailed(&unreduced, &xs[0]), malformed);
+        assert!(!state.ver_mem_at(&huge, &w, &xs[0]));
+        let d = NonMembershipWitness {
+            d: huge.clone(),
+            ..w_non.clone()
+        };
+        assert_eq!(state.ver_non_mem_detailed(&d, &xs[2]), malformed);
+        assert_eq!(
+            new_state.ver_batch_add_detailed(&proof, &OldState(huge.clone()), &new, &xs[2..]),
+            malformed
+        );
+        assert_eq!(
+            state.ver_batch_del_detailed(&proof, &old, &NewState(huge.clone()), &xs[2..]),
+            malformed
+        );
+        let q = BatchProof(PoeProof { q: huge.clone() });
+        assert_eq!(
+            new_state.ver_batch_add_detailed(&q, &old, &new, &xs[2..]),
+            malformed
+        );
+        let pi = MembershipProof {
+            w: huge.clone(),
+            ..pi_mem.clone()
+        };
+        assert_eq!(state.ver_mem_star_detailed(&xs[0], &pi), malformed);
+        let mut pi = pi_non.clone();
+        pi.pi_d.z = huge.clone();
+        assert_eq!(state.ver_non_mem_star_detailed(&xs[2], &pi), malformed);
+        let mut pi = pi_non.clone();
+        pi.v = huge.clone();
+        assert_eq!(state.ver_non_mem_star_detailed(&xs[2], &pi), malformed);
+        assert_eq!(
+            state.ver_mem_x_detailed(&huge, &w, &xs[0], &xs[1]),
+            malformed
+        );
+
+        // exponents
+        let b = NonMembershipWitness {
+            b: huge_int,
+            ..w_non.clone()
+        };
+        assert_eq!(state.ver_non_mem_detailed(&b, &xs[2]), malformed);
+
+        // elements
+        assert_eq!(state.ver_mem_detailed(&w, &huge), malformed);
+        assert_eq!(state.ver_non_mem_detailed(&w_non, &huge), malformed);
+        let mut batch = xs[2..].to_vec();
+        batch.push(huge.clone());
+        assert_eq!(
+            new_state.ver_batch_add_detailed(&proof, &old, &new, &batch),
+            malformed
+        );
+        assert!(!new_state.ver_batch_add_iter(&proof, &state.root, batch.clone()));
+        assert_eq!(
+            state.ver_batch_del_detailed(
+                &proof,
+                &OldState(new_state.root.clone()),
+                &NewState(state.root.clone()),
+                &batch
+            ),
+            malformed
+        );
+        assert_eq!(
+            state.ver_mem_x_detailed(&state.root, &w, &huge, &xs[1]),
+            malformed
+        );
+        assert!(!state.ver_agg_mem_wit(&pi_mem, &huge, &xs[1]));
+        let cw = CompressedWitness {
+            w: w.0.clone(),
+            pi: pi_mem.pi.clone(),
+        };
+        assert!(!state.ver_compressed(&[xs[0].clone(), huge.clone()], &cw));
+        assert_eq!(state.ver_mem_detailed(&w, &BigUint::one()), malformed);
+
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
     }
 }
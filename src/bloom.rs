@@ -0,0 +1,176 @@
+//! A counting Bloom filter over accumulated elements, for fast negative membership checks.
+//!
+//! Checking membership exactly divides the product of the whole set, which gets slow as the
+//! set grows. The filter answers "definitely not a member" for most non-members with a few
+//! hashes instead. It never gives false negatives: counters that overflow stick at their
+//! maximum and are never decremented again.
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::BigUint;
+
+const BLOOM_TAG: &[u8] = b"accumulators/bloom";
+
+/// A counting Bloom filter, supporting deletions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountingBloomFilter {
+    counters: Vec<u8>,
+    hashes: usize,
+}
+
+impl CountingBloomFilter {
+    /// Creates a filter sized for `capacity` elements, with a false positive rate of about
+    /// `fp_rate` once it holds as many.
+    ///
+    /// Uses `-capacity ln(fp_rate) / ln(2)^2` counters of one byte each. Panics if `fp_rate`
+    /// is not between `0` and `1`.
+    pub fn new(capacity: usize, fp_rate: f64) -> Self {
+        assert!(
+            fp_rate > 0.0 && fp_rate < 1.0,
+            "invalid false positive rate {}",
+            fp_rate
+        );
+
+        let ln2 = std::f64::consts::LN_2;
+        let capacity = capacity.max(1) as f64;
+        let counters = (-capacity * fp_rate.ln() / (ln2 * ln2)).ceil().max(1.0);
+        let hashes = (counters / capacity * ln2).round().max(1.0);
+
+        CountingBloomFilter {
+            counters: vec![0; counters as usize],
+            hashes: hashes as usize,
+        }
+    }
+
+    /// Returns the number of counters.
+    pub fn num_counters(&self) -> usize {
+        self.counters.len()
+    }
+
+    /// Returns the number of hash functions.
+    pub fn num_hashes(&self) -> usize {
+        self.hashes
+    }
+
+    /// Records `x`.
+    pub fn insert(&mut self, x: &BigUint) {
+        for i in self.positions(x) {
+            self.increment(i);
+        }
+    }
+
+    /// Removes `x`, which must have been inserted before.
+    pub fn remove(&mut self, x: &BigUint) {
+        for i in self.positions(x) {
+            let c = &mut self.counters[i];
+            // saturated counters may count more than they show
+            if *c > 0 && *c < u8::MAX {
+                *c -= 1;
+            }
+        }
+    }
+
+    /// Returns `false` if `x` was definitely not inserted, or not inserted since it was last
+    /// removed.
+    pub fn may_contain(&self, x: &BigUint) -> bool {
+        self.positions(x).all(|i| self.counters[i] > 0)
+    }
+
+    /// Adds the counters of `other`, resulting in a filter of the elements of both.
+    ///
+    /// Returns `None`, leaving this filter unchanged, if the filters differ in size.
+    pub fn union_with(&mut self, other: &CountingBloomFilter) -> Option<()> {
+        if self.counters.len() != other.counters.len() || self.hashes != other.hashes {
+            return None;
+        }
+
+        for (c, o) in self.counters.iter_mut().zip(&other.counters) {
+            *c = if *c == u8::MAX || *o == u8::MAX {
+                u8::MAX
+            } else {
+                c.saturating_add(*o)
+            };
+        }
+
+        Some(())
+    }
+
+    /// Returns the counters of `x`, with double hashing over a single Blake2b digest.
+    pub(crate) fn positions(&self, x: &BigUint) -> impl Iterator<Item = usize> {
+        let mut hasher = Blake2b::new();
+        hasher.input(BLOOM_TAG);
+        hasher.input(x.to_bytes_be());
+        let digest = hasher.result();
+
+        let h1 = BigEndian::read_u64(&digest[..8]);
+        let h2 = BigEndian::read_u64(&digest[8..16]) | 1;
+        let m = self.counters.len() as u64;
+
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % m) as usize)
+    }
+
+    /// Increments the counter `i`, saturating.
+    pub(crate) fn increment(&mut self, i: usize) {
+        let c = &mut self.counters[i];
+        *c = c.saturating_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizing() {
+        // about 9.6 counters per element and 7 hashes for 1%
+        let f = CountingBloomFilter::new(1000, 0.01);
+        assert_eq!(f.num_counters(), 9586);
+        assert_eq!(f.num_hashes(), 7);
+
+        let f = CountingBloomFilter::new(0, 0.5);
+        assert_eq!(f.num_counters(), 2);
+        assert_eq!(f.num_hashes(), 1);
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut f = CountingBloomFilter::new(100, 0.01);
+        let xs = (0u32..100).map(BigUint::from).collect::<Vec<_>>();
+        for x in &xs {
+            f.insert(x);
+        }
+        assert!(xs.iter().all(|x| f.may_contain(x)));
+
+        let fp = (100u32..10_100)
+            .filter(|x| f.may_contain(&BigUint::from(*x)))
+            .count();
+        assert!(fp < 300, "{} false positives", fp);
+
+        for x in &xs[..50] {
+            f.remove(x);
+        }
+        assert!(xs[50..].iter().all(|x| f.may_contain(x)));
+        assert!(xs[..50].iter().filter(|x| f.may_contain(x)).count() < 10);
+    }
+
+    #[test]
+    fn test_saturation() {
+        let mut f = CountingBloomFilter::new(1, 0.5);
+        let (x, y) = (BigUint::from(1u32), BigUint::from(2u32));
+        for _ in 0..300 {
+            f.insert(&x);
+        }
+        f.insert(&y);
+
+        // the counters of x are stuck at the maximum, so y is never lost
+        for _ in 0..300 {
+            f.remove(&x);
+        }
+        assert!(f.may_contain(&y));
+
+        let mut g = f.clone();
+        assert_eq!(g.union_with(&f), Some(()));
+        assert!(g.may_contain(&x) && g.may_contain(&y));
+        assert_eq!(g.union_with(&CountingBloomFilter::new(100, 0.5)), None);
+    }
+}
@@ -0,0 +1,409 @@
+//! Configurable construction of accumulators in RSA groups.
+
+use std::sync::Arc;
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::One;
+use rand::{CryptoRng, Rng};
+
+use crate::accumulator::{Accumulator, CapacityLimits, ElementPolicy, ParamError};
+use crate::group::RSAGroup;
+use crate::hash::derive_generator;
+#[cfg(feature = "params")]
+use crate::params::StandardParams;
+use crate::primes::{check_modulus, ModulusPolicy};
+use crate::setup::{GroupMode, SetupParams};
+use crate::traits::PrimeGroup;
+
+const GENERATOR_TAG: &[u8] = b"accumulators/builder/generator";
+
+/// The default size of the modulus, in bits.
+pub const DEFAULT_LAMBDA: usize = 2048;
+
+/// Smallest element accepted with `strict_elements`, in bits. Smaller elements can be found by
+/// searching through all of them.
+pub const STRICT_MIN_ELEMENT_BITS: usize = 64;
+
+/// Where the modulus comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModulusSource {
+    /// Generated with a trusted setup, from the random number generator passed to `build`.
+    Generate,
+    /// A modulus of unknown factorization, e.g. from an earlier setup or an MPC ceremony.
+    Fixed(BigUint),
+    /// The modulus of the RSA-2048 factoring challenge, see `StandardParams::Rsa2048`.
+    #[cfg(feature = "params")]
+    Rsa2048,
+}
+
+/// Where the generator comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeneratorSource {
+    /// The generator of the setup, or of the standard parameters.
+    FromSetup,
+    /// Derived from the modulus with `derive_generator`, so that anyone can check how it was
+    /// chosen. The default for fixed moduli.
+    Derived,
+    /// A fixed generator.
+    Fixed(BigUint),
+}
+
+/// Builder for `Accumulator`s in RSA groups.
+///
+/// The defaults match `Accumulator::setup::<RSAGroup, _>(rng, DEFAULT_LAMBDA)`: a generated
+/// modulus, with the generator from the same setup, accepting all elements without limits.
+/// For a fixed modulus, the generator is derived from it by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsaAccumulatorBuilder {
+    lambda: Option<usize>,
+    modulus: ModulusSource,
+    generator: Option<GeneratorSource>,
+    group_mode: GroupMode,
+    modulus_policy: ModulusPolicy,
+    strict_elements: bool,
+    capacity: Option<usize>,
+    witness_cache: Option<usize>,
+}
+
+impl Default for RsaAccumulatorBuilder {
+    fn default() -> Self {
+        RsaAccumulatorBuilder {
+            lambda: None,
+            modulus: ModulusSource::Generate,
+            generator: None,
+            group_mode: GroupMode::Full,
+            modulus_policy: ModulusPolicy::default(),
+            strict_elements: false,
+            capacity: None,
+            witness_cache: None,
+        }
+    }
+}
+
+impl RsaAccumulatorBuilder {
+    /// Creates a builder with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the size of the modulus in bits, `DEFAULT_LAMBDA` for generated moduli by default.
+    ///
+    /// For other sources, the modulus must have this size if set.
+    pub fn lambda(mut self, bits: usize) -> Self {
+        self.lambda = Some(bits);
+        self
+    }
+
+    /// Sets where the modulus comes from, generated by default.
+    pub fn modulus(mut self, source: ModulusSource) -> Self {
+        self.modulus = source;
+        self
+    }
+
+    /// Sets where the generator comes from. By default, it is derived for fixed moduli, and
+    /// taken from the setup otherwise.
+    pub fn generator(mut self, source: GeneratorSource) -> Self {
+        self.generator = Some(source);
+        self
+    }
+
+    /// Sets the group, `GroupMode::Full` by default. For `QuadraticResidues`, the generator
+    /// from the source is squared, see `SetupParams::new_with_mode`.
+    pub fn group_mode(mut self, mode: GroupMode) -> Self {
+        self.group_mode = mode;
+        self
+    }
+
+    /// Sets the checks of fixed moduli, see `check_modulus`. The default policy checks for
+    /// prime factors below `DEFAULT_TRIAL_DIVISION_BOUND`.
+    pub fn modulus_policy(mut self, policy: ModulusPolicy) -> Self {
+        self.modulus_policy = policy;
+        self
+    }
+
+    /// Restricts the elements accepted by `add_checked` and `batch_add_checked` to those of
+    /// at least `STRICT_MIN_ELEMENT_BITS` bits and at most half the size of the modulus.
+    pub fn strict_elements(mut self, strict: bool) -> Self {
+        self.strict_elements = strict;
+        self
+    }
+
+    /// Limits the number of elements, see `Accumulator::with_capacity_limits`.
+    pub fn capacity(mut self, max_elements: usize) -> Self {
+        self.capacity = Some(max_elements);
+        self
+    }
+
+    /// Enables the witness cache, see `Accumulator::with_witness_cache`.
+    pub fn witness_cache(mut self, capacity: usize) -> Self {
+        self.witness_cache = Some(capacity);
+        self
+    }
+
+    /// Builds the accumulator, running the setup with `rng` if the modulus is generated.
+    ///
+    /// Fails if the modulus or generator are invalid, or don't fit together. Fixed moduli are
+    /// checked with `check_modulus` as well.
+    pub fn build<R: CryptoRng + Rng>(self, rng: &mut R) -> Result<Accumulator, ParamError> {
+        let fixed = matches!(self.modulus, ModulusSource::Fixed(_));
+        let (n, setup_g) = match self.modulus {
+            ModulusSource::Generate => {
+                let bits = self.lambda.unwrap_or(DEFAULT_LAMBDA);
+                let setup = RSAGroup::generate_primes(rng, bits)
+                    .map_err(|_| ParamError::ModulusTooSmall { bits })?;
+                (setup.n.clone(), Some(setup.g.clone()))
+            }
+            ModulusSource::Fixed(n) => (n, None),
+            #[cfg(feature = "params")]
+            ModulusSource::Rsa2048 => {
+                let params = StandardParams::Rsa2048;
+                (params.modulus().clone(), Some(params.generator().clone()))
+            }
+        };
+
+        if n.is_even() {
+            return Err(ParamError::EvenModulus);
+        }
+        if let Some(expected) = self.lambda {
+            if n.bits() != expected {
+                return Err(ParamError::ModulusSize {
+                    expected,
+                    actual: n.bits(),
+                });
+            }
+        }
+        if fixed {
+            check_modulus(&n, &self.modulus_policy).map_err(ParamError::WeakModulus)?;
+        }
+
+        let source = self.generator.unwrap_or(if fixed {
+            GeneratorSource::Derived
+        } else {
+            GeneratorSource::FromSetup
+        });
+        let g = match source {
+            GeneratorSource::FromSetup => setup_g.ok_or(ParamError::MissingGenerator)?,
+            GeneratorSource::Derived => derive_generator(&n, GENERATOR_TAG),
+            GeneratorSource::Fixed(g) => g,
+        };
+        if g <= BigUint::one() || g >= n {
+            return Err(ParamError::InvalidGenerator);
+        }
+
+        let lambda = n.bits();
+        let params = SetupParams::new_with_mode(lambda, n, g, self.group_mode)?;
+        let mut acc = Accumulator::new_with_params(Arc::new(params));
+        if self.strict_elements {
+            acc = acc.with_element_policy(ElementPolicy {
+                min_bits: STRICT_MIN_ELEMENT_BITS,
+                max_bits: lambda / 2,
+            });
+        }
+        if let Some(max_elements) = self.capacity {
+            acc = acc.with_capacity_limits(CapacityLimits {
+                max_elements: Some(max_elements),
+                max_set_bits: None,
+            });
+        }
+        if let Some(capacity) = self.witness_cache {
+            acc = acc.with_witness_cache(capacity);
+        }
+
+        Ok(acc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::AccumulatorError;
+    use crate::primes::{ModulusError, RandPrimes};
+    use crate::traits::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_defaults_match_setup() {
+        let int_size_bits = 256; // insecure, but faster tests
+        let built = RsaAccumulatorBuilder::new()
+            .lambda(int_size_bits)
+            .build(&mut ChaChaRng::from_seed([0u8; 32]))
+            .unwrap();
+        let setup =
+            Accumulator::setup::<RSAGroup, _>(&mut ChaChaRng::from_seed([0u8; 32]), int_size_bits);
+
+        assert_eq!(built.public_state(), setup.public_state());
+        assert_eq!(built.lambda(), setup.lambda());
+        assert_eq!(built.element_bits_policy(), &ElementPolicy::default());
+        assert_eq!(built.cached_witnesses(), 0);
+    }
+
+    #[test]
+    fn test_fixed_and_derived() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let setup = Accumulator::setup::<RSAGroup, _>(rng, 256);
+        let n = setup.modulus().clone();
+
+        let fixed = RsaAccumulatorBuilder::new()
+            .modulus(ModulusSource::Fixed(n.clone()))
+            .generator(GeneratorSource::Fixed(setup.generator().clone()))
+            .build(rng)
+            .unwrap();
+        assert_eq!(fixed.public_state(), setup.public_state());
+        assert_eq!(fixed.lambda(), 256);
+
+        // the derived generator only depends on the modulus
+        let derived = |rng: &mut ChaChaRng| {
+            RsaAccumulatorBuilder::new()
+                .lambda(256)
+                .modulus(ModulusSource::Fixed(n.clone()))
+                .generator(GeneratorSource::Derived)
+                .build(rng)
+                .unwrap()
+        };
+        let a = derived(rng);
+        let b = derived(&mut ChaChaRng::from_seed([1u8; 32]));
+        assert_eq!(a.public_state(), b.public_state());
+        assert_ne!(a.generator(), setup.generator());
+        assert_eq!(a.generator(), &derive_generator(&n, GENERATOR_TAG));
+
+        // and is the default for fixed moduli
+        let default = RsaAccumulatorBuilder::new()
+            .modulus(ModulusSource::Fixed(n.clone()))
+            .build(rng)
+            .unwrap();
+        assert_eq!(default.public_state(), a.public_state());
+    }
+
+    #[test]
+    fn test_options() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let mut acc = RsaAccumulatorBuilder::new()
+            .lambda(256)
+            .strict_elements(true)
+            .capacity(2)
+            .witness_cache(4)
+            .build(rng)
+            .unwrap();
+
+        let x = BigUint::from(65537u32);
+        assert_eq!(
+            acc.add_checked(&x),
+            Err(AccumulatorError::ElementSize { bits: 17 })
+        );
+
+        let xs = rng.gen_distinct_primes(3, 128);
+        acc.add_checked(&xs[0]).unwrap();
+        acc.add_checked(&xs[1]).unwrap();
+        assert!(acc.add_checked(&xs[2]).is_err());
+
+        acc.mem_wit_create(&xs[0]);
+        assert_eq!(acc.cached_witnesses(), 1);
+
+        let acc = RsaAccumulatorBuilder::new()
+            .lambda(256)
+            .generator(GeneratorSource::Derived)
+            .group_mode(GroupMode::QuadraticResidues)
+            .build(rng)
+            .unwrap();
+        assert_eq!(acc.params().mode(), GroupMode::QuadraticResidues);
+        let g = acc.params().generator();
+        assert!(acc.params().check_state(&acc.public_state()).is_ok());
+        assert_ne!(g, &derive_generator(acc.params().modulus(), GENERATOR_TAG));
+    }
+
+    #[test]
+    fn test_invalid_combinations() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let n = Accumulator::setup::<RSAGroup, _>(rng, 256)
+            .modulus()
+            .clone();
+        let fixed = || RsaAccumulatorBuilder::new().modulus(ModulusSource::Fixed(n.clone()));
+
+        // a fixed modulus comes without a setup
+        assert_eq!(
+            fixed()
+                .generator(GeneratorSource::FromSetup)
+                .build(rng)
+                .err(),
+            Some(ParamError::MissingGenerator)
+        );
+        assert_eq!(
+            fixed()
+                .lambda(512)
+                .generator(GeneratorSource::Derived)
+                .build(rng)
+                .err(),
+            Some(ParamError::ModulusSize {
+                expected: 512,
+                actual: 256
+            })
+        );
+        assert_eq!(
+            fixed()
+                .generator(GeneratorSource::Fixed(n.clone()))
+                .build(rng)
+                .err(),
+            Some(ParamError::InvalidGenerator)
+        );
+        assert_eq!(
+            RsaAccumulatorBuilder::new()
+                .modulus(ModulusSource::Fixed(&n + 1u32))
+                .generator(GeneratorSource::Derived)
+                .build(rng)
+                .err(),
+            Some(ParamError::EvenModulus)
+        );
+        assert_eq!(
+            RsaAccumulatorBuilder::new().lambda(32).build(rng).err(),
+            Some(ParamError::ModulusTooSmall { bits: 32 })
+        );
+
+        // fixed moduli are checked for small factors
+        let weak = &n * 17u32;
+        let mut build_weak = |policy| {
+            RsaAccumulatorBuilder::new()
+                .modulus(ModulusSource::Fixed(weak.clone()))
+                .generator(GeneratorSource::Derived)
+                .modulus_policy(policy)
+                .build(rng)
+        };
+        assert_eq!(
+            build_weak(ModulusPolicy::default()).err(),
+            Some(ParamError::WeakModulus(ModulusError::SmallFactor(17)))
+        );
+        assert!(build_weak(ModulusPolicy {
+            bits: None,
+            trial_division_bound: 17,
+        })
+        .is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "params")]
+    fn test_rsa_2048() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let acc = RsaAccumulatorBuilder::new()
+            .modulus(ModulusSource::Rsa2048)
+            .build(rng)
+            .unwrap();
+        assert_eq!(
+            acc.public_state(),
+            Accumulator::with_params(StandardParams::Rsa2048).public_state()
+        );
+
+        assert_eq!(
+            RsaAccumulatorBuilder::new()
+                .lambda(3072)
+                .modulus(ModulusSource::Rsa2048)
+                .build(rng)
+                .err(),
+            Some(ParamError::ModulusSize {
+                expected: 3072,
+                actual: 2048
+            })
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! A set of arbitrary data, backed by an accumulator.
+//!
+//! `AccumulatorSet` maps each item to a prime with `PrimeElement::from_hash`, and keeps the
+//! items next to the accumulator, so callers never deal with primes or group elements. Proofs
+//! are bundled with the version of the set they were created for, and checked by an
+//! `AccumulatorSetVerifier` that only knows the `PublishedState`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use num_bigint::BigUint;
+
+use crate::accumulator::{
+    Accumulator, AccumulatorError, MembershipProof, NonMembershipProof, PublicState,
+};
+use crate::element::PrimeElement;
+#[cfg(feature = "serde")]
+use crate::math::product;
+use crate::setup::SetupParams;
+use crate::traits::*;
+
+/// The state of an `AccumulatorSet`, everything a verifier needs.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PublishedState {
+    /// The public state of the accumulator.
+    pub state: PublicState,
+    /// Number of changes to the set, incremented by every successful insert and remove.
+    pub version: u64,
+}
+
+/// Proof that an item is in an `AccumulatorSet`, from `AccumulatorSet::prove_member`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MembershipProofBundle {
+    /// Version of the set the proof was created for.
+    pub version: u64,
+    /// Membership proof for the prime of the item.
+    pub proof: MembershipProof,
+}
+
+/// Proof that an item is not in an `AccumulatorSet`, from `AccumulatorSet::prove_absent`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NonMembershipProofBundle {
+    /// Version of the set the proof was created for.
+    pub version: u64,
+    /// Non-membership proof for the prime of the item.
+    pub proof: NonMembershipProof,
+}
+
+/// A set of items, with membership and non-membership proofs.
+///
+/// Items are identified by their bytes. With `serde`, the set is encoded as the accumulator,
+/// the version and the items, and decoding it checks that the items match the accumulator.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        into = "AccumulatorSetRepr<T>",
+        try_from = "AccumulatorSetRepr<T>",
+        bound(
+            serialize = "T: serde::Serialize + Clone",
+            deserialize = "T: serde::Deserialize<'de> + AsRef<[u8]>"
+        )
+    )
+)]
+#[derive(Debug, Clone)]
+pub struct AccumulatorSet<T> {
+    acc: Accumulator,
+    version: u64,
+    /// The items, by their prime.
+    items: BTreeMap<BigUint, T>,
+}
+
+/// Encoded form of `AccumulatorSet`.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct AccumulatorSetRepr<T> {
+    accumulator: Accumulator,
+    version: u64,
+    items: Vec<T>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetError {
+    /// The item at this index is repeated, or maps to the same prime as an earlier one.
+    Duplicate(usize),
+    /// The items are not the elements of the accumulator.
+    Mismatch,
+}
+
+impl fmt::Display for SetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SetError::Duplicate(i) => write!(f, "item {} is repeated", i),
+            SetError::Mismatch => write!(f, "the items don't match the accumulator"),
+        }
+    }
+}
+
+impl std::error::Error for SetError {}
+
+#[cfg(feature = "serde")]
+impl<T: Clone> From<AccumulatorSet<T>> for AccumulatorSetRepr<T> {
+    fn from(set: AccumulatorSet<T>) -> Self {
+        AccumulatorSetRepr {
+            accumulator: set.acc,
+            version: set.version,
+            items: set.items.into_values().collect(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: AsRef<[u8]>> std::convert::TryFrom<AccumulatorSetRepr<T>> for AccumulatorSet<T> {
+    type Error = SetError;
+
+    fn try_from(repr: AccumulatorSetRepr<T>) -> Result<Self, SetError> {
+        let AccumulatorSetRepr {
+            accumulator,
+            version,
+            items,
+        } = repr;
+
+        let mut map = BTreeMap::new();
+        for (i, item) in items.into_iter().enumerate() {
+            if map.insert(prime(&item), item).is_some() {
+                return Err(SetError::Duplicate(i));
+            }
+        }
+        let primes = map.keys().cloned().collect::<Vec<_>>();
+        if accumulator.len() != map.len() || &product(&primes) != accumulator.product() {
+            return Err(SetError::Mismatch);
+        }
+
+        Ok(AccumulatorSet {
+            acc: accumulator,
+            version,
+            items: map,
+        })
+    }
+}
+
+/// The prime `item` is accumulated as.
+fn prime<T: AsRef<[u8]>>(item: &T) -> BigUint {
+    PrimeElement::from_hash(item.as_ref()).into_biguint()
+}
+
+impl<T: AsRef<[u8]>> AccumulatorSet<T> {
+    /// Creates an empty set with the parameters `params`.
+    pub fn new(params: Arc<SetupParams>) -> Self {
+        AccumulatorSet {
+            acc: Accumulator::new_with_params(params),
+            version: 0,
+            items: BTreeMap::new(),
+        }
+    }
+
+    /// Inserts `item`, returns `false` if it was already in the set.
+    ///
+    /// Panics if the accumulator exceeds its capacity limits.
+    pub fn insert(&mut self, item: &T) -> bool
+    where
+        T: Clone,
+    {
+        self.try_insert(item).expect("capacity exceeded")
+    }
+
+    /// Inserts `item`, like `insert`.
+    ///
+    /// Fails with `AccumulatorError::CapacityExceeded`, leaving the set unchanged, if the
+    /// accumulator exceeds its capacity limits.
+    pub fn try_insert(&mut self, item: &T) -> Result<bool, AccumulatorError>
+    where
+        T: Clone,
+    {
+        let x = prime(item);
+        if self.items.contains_key(&x) {
+            return Ok(false);
+        }
+
+        self.acc.try_add(&x)?;
+        self.items.insert(x, item.clone());
+        self.version += 1;
+
+        Ok(true)
+    }
+
+    /// Removes `item`, returns `false` if it was not in the set.
+    pub fn remove(&mut self, item: &T) -> bool {
+        let x = prime(item);
+        if !self.items.contains_key(&x) {
+            return false;
+        }
+
+        self.acc.del(&x).expect("items and accumulator out of sync");
+        self.items.remove(&x);
+        self.version += 1;
+
+        true
+    }
+
+    /// Returns `true` if `item` is in the set.
+    pub fn contains(&self, item: &T) -> bool {
+        self.items.contains_key(&prime(item))
+    }
+
+    /// Returns the number of items.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns `true` if the set has no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the items, ordered by their primes.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.values()
+    }
+
+    /// Proves that `item` is in the set, `None` if it isn't.
+    pub fn prove_member(&self, item: &T) -> Option<MembershipProofBundle> {
+        let x = prime(item);
+        if !self.items.contains_key(&x) {
+            return None;
+        }
+
+        Some(MembershipProofBundle {
+            version: self.version,
+            proof: self.acc.mem_wit_create_star(&x),
+        })
+    }
+
+    /// Proves that `item` is not in the set, `None` if it is.
+    ///
+    /// Also `None` while the set is empty, as non-membership proofs for the empty accumulator
+    /// don't verify.
+    pub fn prove_absent(&self, item: &T) -> Option<NonMembershipProofBundle> {
+        let x = prime(item);
+        if self.items.is_empty() || self.items.contains_key(&x) {
+            return None;
+        }
+
+        let proof = self.acc.try_non_mem_wit_create_star(&x).ok()?;
+        Some(NonMembershipProofBundle {
+            version: self.version,
+            proof,
+        })
+    }
+
+    /// Returns the state to publish for verifiers.
+    pub fn state(&self) -> PublishedState {
+        PublishedState {
+            state: self.acc.public_state(),
+            version: self.version,
+        }
+    }
+
+    /// Returns the accumulator of the set.
+    pub fn accumulator(&self) -> &Accumulator {
+        &self.acc
+    }
+}
+
+/// Checks the proofs of an `AccumulatorSet`, given only its published state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccumulatorSetVerifier {
+    state: PublishedState,
+}
+
+impl AccumulatorSetVerifier {
+    pub fn new(state: PublishedState) -> Self {
+        AccumulatorSetVerifier { state }
+    }
+
+    /// Returns the published state the proofs are checked against.
+    pub fn state(&self) -> &PublishedState {
+        &self.state
+    }
+
+    /// Verifies that `item` is in the set.
+    ///
+    /// Fails for proofs of another version, even if the item is still in the set.
+    pub fn verify_member<T: AsRef<[u8]>>(&self, item: &T, bundle: &MembershipProofBundle) -> bool {
+        bundle.version == self.state.version
+            && self.state.state.ver_mem_star(&prime(item), &bundle.proof)
+    }
+
+    /// Verifies that `item` is not in the set.
+    ///
+    /// Fails for proofs of another version, even if the item is still absent.
+    pub fn verify_absent<T: AsRef<[u8]>>(
+        &self,
+        item: &T,
+        bundle: &NonMembershipProofBundle,
+    ) -> bool {
+        bundle.version == self.state.version
+            && self
+                .state
+                .state
+                .ver_non_mem_star(&prime(item), &bundle.proof)
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn setup() -> AccumulatorSet<String> {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        AccumulatorSet::new(Arc::new(SetupParams::generate(rng, int_size_bits).unwrap()))
+    }
+
+    fn items(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_set_lifecycle() {
+        let mut set = setup();
+        let names = items(&["alice", "bob", "carol", "dave"]);
+        assert!(set.is_empty());
+        assert!(set.prove_absent(&names[0]).is_none());
+
+        assert!(set.insert(&names[1]));
+        let first = AccumulatorSetVerifier::new(set.state());
+        let pi = set.prove_absent(&names[0]).unwrap();
+        assert!(first.verify_absent(&names[0], &pi));
+
+        for name in &names[..3] {
+            set.insert(name);
+        }
+        assert!(!set.insert(&names[0]));
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&names[1]) && !set.contains(&names[3]));
+        assert_eq!(set.state().version, 3);
+
+        let verifier = AccumulatorSetVerifier::new(set.state());
+        let bob = set.prove_member(&names[1]).unwrap();
+        assert!(verifier.verify_member(&names[1], &bob));
+        assert!(!verifier.verify_member(&names[2], &bob));
+        assert!(!verifier.verify_absent(&names[0], &pi));
+        assert!(set.prove_member(&names[3]).is_none());
+
+        let dave = set.prove_absent(&names[3]).unwrap();
+        assert!(verifier.verify_absent(&names[3], &dave));
+        assert!(!verifier.verify_absent(&names[1], &dave));
+        assert!(set.prove_absent(&names[1]).is_none());
+
+        // removed, the old proofs are stale
+        assert!(set.remove(&names[1]));
+        assert!(!set.remove(&names[1]));
+        assert!(!set.contains(&names[1]));
+        let verifier = AccumulatorSetVerifier::new(set.state());
+        assert!(!verifier.verify_member(&names[1], &bob));
+        let pi = set.prove_absent(&names[1]).unwrap();
+        assert!(verifier.verify_absent(&names[1], &pi));
+        let alice = set.prove_member(&names[0]).unwrap();
+        assert!(verifier.verify_member(&names[0], &alice));
+
+        // the version is part of the bundle
+        let wrong = MembershipProofBundle {
+            version: alice.version - 1,
+            ..alice
+        };
+        assert!(!verifier.verify_member(&names[0], &wrong));
+
+        assert_eq!(set.iter().count(), 2);
+        assert!(set.accumulator().contains(&prime(&names[2])));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_set_serde() {
+        let mut set = setup();
+        let names = items(&["alice", "bob", "carol"]);
+        for name in &names {
+            set.insert(name);
+        }
+        set.remove(&names[0]);
+
+        let s = serde_json::to_string(&set).unwrap();
+        let decoded: AccumulatorSet<String> = serde_json::from_str(&s).unwrap();
+        assert_eq!(decoded.state(), set.state());
+        assert!(decoded.contains(&names[1]) && !decoded.contains(&names[0]));
+
+        // proofs from the decoded set verify against the published state of the original
+        let verifier = AccumulatorSetVerifier::new(set.state());
+        let pi = decoded.prove_member(&names[2]).unwrap();
+        assert!(verifier.verify_member(&names[2], &pi));
+
+        let state = serde_json::to_string(&set.state()).unwrap();
+        let published: PublishedState = serde_json::from_str(&state).unwrap();
+        assert_eq!(published, set.state());
+
+        // items that don't match the accumulator
+        let mut value: serde_json::Value = serde_json::from_str(&s).unwrap();
+        value["items"][0] = "mallory".into();
+        assert!(serde_json::from_value::<AccumulatorSet<String>>(value.clone()).is_err());
+        value["items"][0] = value["items"][1].clone();
+        assert!(serde_json::from_value::<AccumulatorSet<String>>(value).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! Elements that are known to be primes.
+//!
+//! The accumulator methods taking `&BigUint` assume, but don't check, that the elements are
+//! primes. `PrimeElement` can only be constructed by checking or deriving one, so the methods
+//! taking it don't need to check again. Decoding it with serde or borsh checks it as well.
+
+use std::fmt;
+
+use blake2::Blake2b;
+use byteorder::{BigEndian, WriteBytesExt};
+use num_bigint::BigUint;
+
+use crate::hash::hash_prime;
+use crate::primality::{is_probable_prime, PrimalityConfig};
+
+/// A prime, to be accumulated.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PrimeElement(BigUint);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElementError {
+    /// The number is not a (probable) prime.
+    NotPrime,
+}
+
+impl fmt::Display for ElementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ElementError::NotPrime => write!(f, "the element is not a prime"),
+        }
+    }
+}
+
+impl std::error::Error for ElementError {}
+
+impl PrimeElement {
+    /// Checks that `x` is a prime with `is_probable_prime`.
+    ///
+    /// Elements that may be chosen by an adversary should be checked with the default config,
+    /// see `PrimalityConfig`.
+    pub fn new_checked(x: BigUint, cfg: &PrimalityConfig) -> Result<Self, ElementError> {
+        if !is_probable_prime(&x, cfg) {
+            return Err(ElementError::NotPrime);
+        }
+
+        Ok(PrimeElement(x))
+    }
+
+    /// Hashes `data` to a prime, with `hash_prime`.
+    pub fn from_hash(data: &[u8]) -> Self {
+        PrimeElement(hash_prime::<_, Blake2b>(data).expect("no prime found"))
+    }
+
+    /// Wraps `x` without checking it.
+    ///
+    /// Only for values that are known to be primes, e.g. generated by `gen_distinct_primes`
+    /// or loaded from a trusted store. Accumulating a composite lets anyone knowing its factors
+    /// create membership witnesses for them.
+    pub fn new_unchecked(x: BigUint) -> Self {
+        PrimeElement(x)
+    }
+
+    /// Returns the prime.
+    pub fn as_biguint(&self) -> &BigUint {
+        &self.0
+    }
+
+    /// Returns the prime, consuming the element.
+    pub fn into_biguint(self) -> BigUint {
+        self.0
+    }
+}
+
+const EPOCH_TAG: &[u8] = b"accumulators/epoch-element";
+
+/// Maps the identifier `id`, valid during `epoch`, to a prime.
+///
+/// The prime is `hash_prime` of
+///
+/// ```text
+/// "accumulators/epoch-element" || len(id) || id || epoch
+/// ```
+///
+/// with `len(id)` and `epoch` as big-endian `u64`. The same `id` gives unrelated primes in
+/// different epochs, and identifiers can't collide with each other or with data hashed to
+/// primes without the tag.
+pub fn epoch_element(id: &[u8], epoch: u64) -> PrimeElement {
+    let mut data = EPOCH_TAG.to_vec();
+    data.write_u64::<BigEndian>(id.len() as u64).unwrap();
+    data.extend_from_slice(id);
+    data.write_u64::<BigEndian>(epoch).unwrap();
+
+    PrimeElement::from_hash(&data)
+}
+
+impl AsRef<BigUint> for PrimeElement {
+    fn as_ref(&self) -> &BigUint {
+        &self.0
+    }
+}
+
+impl From<PrimeElement> for BigUint {
+    fn from(x: PrimeElement) -> Self {
+        x.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_checked() {
+        let cfg = PrimalityConfig::default();
+        for x in &[2u32, 3, 65537] {
+            let p = PrimeElement::new_checked(BigUint::from(*x), &cfg).unwrap();
+            assert_eq!(p.as_biguint(), &BigUint::from(*x));
+        }
+
+        // 561 is a Carmichael number, 2^64 + 1 = 274177 * 67280421310721
+        let composites = [
+            BigUint::from(0u32),
+            BigUint::from(1u32),
+            BigUint::from(4u32),
+            BigUint::from(561u32),
+            (BigUint::from(1u32) << 64) + 1u32,
+        ];
+        for x in &composites {
+            assert_eq!(
+                PrimeElement::new_checked(x.clone(), &cfg),
+                Err(ElementError::NotPrime)
+            );
+        }
+
+        // unchecked elements are taken as they are
+        assert_eq!(
+            PrimeElement::new_unchecked(BigUint::from(4u32)).into_biguint(),
+            BigUint::from(4u32)
+        );
+    }
+
+    #[test]
+    fn test_from_hash() {
+        let p = PrimeElement::from_hash(b"element");
+        assert_eq!(p, PrimeElement::from_hash(b"element"));
+        assert_ne!(p, PrimeElement::from_hash(b"other"));
+        assert_eq!(
+            p.as_biguint(),
+            &hash_prime::<_, Blake2b>(b"element").unwrap()
+        );
+        assert!(PrimeElement::new_checked(p.into_biguint(), &Default::default()).is_ok());
+    }
+
+    #[test]
+    fn test_epoch_element() {
+        let p = epoch_element(b"id", 1);
+        assert_eq!(p, epoch_element(b"id", 1));
+        assert_ne!(p, epoch_element(b"id", 2));
+        assert_ne!(p, epoch_element(b"id\0", 1));
+
+        // pinned, for independent implementations
+        let mut data = b"accumulators/epoch-element".to_vec();
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 2]);
+        data.extend_from_slice(b"id");
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(p, PrimeElement::from_hash(&data));
+    }
+}
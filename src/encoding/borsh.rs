@@ -0,0 +1,346 @@
+//! Borsh encoding.
+//!
+//! Big integers are encoded as `Vec<u8>`s of their minimal little-endian representation, zero
+//! being the empty vector. Signed integers are prefixed with a sign byte, `0` for non-negative
+//! and `1` for negative values. Non-canonical encodings are rejected.
+
+use std::io::{self, Read, Write};
+
+use ::borsh::{BorshDeserialize, BorshSerialize};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::{Signed, Zero};
+
+use super::DEFAULT_MAX_INT_BYTES;
+use crate::accumulator::{
+    BatchNonMemStarProof, BatchProof, CompressedWitness, MembershipProof, MembershipWitness,
+    NonMembershipProof, NonMembershipWitness, PublicState,
+};
+use crate::element::PrimeElement;
+use crate::proofs::{PoeProof, Poke2Proof};
+
+/// Deserialization with an upper bound on the size of every encoded integer.
+pub trait BorshDeserializeBounded: Sized {
+    /// Deserializes `Self`, rejecting any integer longer than `max_int_bytes` before allocating it.
+    fn deserialize_bounded<R: Read>(reader: &mut R, max_int_bytes: usize) -> io::Result<Self>;
+
+    /// Deserializes `Self` from `bytes`, which must be consumed completely.
+    fn try_from_slice_bounded(bytes: &[u8], max_int_bytes: usize) -> io::Result<Self> {
+        let mut reader = bytes;
+        let res = Self::deserialize_bounded(&mut reader, max_int_bytes)?;
+        if !reader.is_empty() {
+            return Err(invalid_data("not all bytes read"));
+        }
+
+        Ok(res)
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_biguint<W: Write>(x: &BigUint, writer: &mut W) -> io::Result<()> {
+    let bytes = if x.is_zero() {
+        Vec::new()
+    } else {
+        x.to_bytes_le()
+    };
+
+    bytes.serialize(writer)
+}
+
+fn read_biguint<R: Read>(reader: &mut R, max_int_bytes: usize) -> io::Result<BigUint> {
+    let len = u32::deserialize_reader(reader)? as usize;
+    if len > max_int_bytes {
+        return Err(invalid_data("integer too large"));
+    }
+
+    let mut bytes = vec![0u8; len];
+    reader.read_exact(&mut bytes)?;
+
+    if bytes.last() == Some(&0) {
+        return Err(invalid_data("non-canonical integer"));
+    }
+
+    Ok(BigUint::from_bytes_le(&bytes))
+}
+
+fn write_bigint<W: Write>(x: &BigInt, writer: &mut W) -> io::Result<()> {
+    let sign: u8 = if x.sign() == Sign::Minus { 1 } else { 0 };
+    sign.serialize(writer)?;
+
+    write_biguint(&x.abs().to_biguint().unwrap(), writer)
+}
+
+fn read_bigint<R: Read>(reader: &mut R, max_int_bytes: usize) -> io::Result<BigInt> {
+    let sign = match u8::deserialize_reader(reader)? {
+        0 => Sign::Plus,
+        1 => Sign::Minus,
+        _ => return Err(invalid_data("invalid sign")),
+    };
+
+    let magnitude = read_biguint(reader, max_int_bytes)?;
+    if sign == Sign::Minus && magnitude.is_zero() {
+        return Err(invalid_data("non-canonical integer"));
+    }
+
+    Ok(BigInt::from_biguint(sign, magnitude))
+}
+
+/// Implements `BorshSerialize`, `BorshDeserialize` and `BorshDeserializeBounded` for a struct,
+/// by encoding its fields in order.
+macro_rules! impl_borsh {
+    ($name:ident { $($field:ident: $write:ident / $read:ident),* }) => {
+        impl BorshSerialize for $name {
+            fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                $($write(&self.$field, writer)?;)*
+                Ok(())
+            }
+        }
+
+        impl BorshDeserializeBounded for $name {
+            fn deserialize_bounded<R: Read>(
+                reader: &mut R,
+                max_int_bytes: usize,
+            ) -> io::Result<Self> {
+                Ok($name {
+                    $($field: $read(reader, max_int_bytes)?,)*
+                })
+            }
+        }
+
+        impl BorshDeserialize for $name {
+            fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+                Self::deserialize_bounded(reader, DEFAULT_MAX_INT_BYTES)
+            }
+        }
+    };
+}
+
+impl_borsh!(PublicState {
+    n: write_biguint / read_biguint,
+    g: write_biguint / read_biguint,
+    root: write_biguint / read_biguint
+});
+
+impl_borsh!(NonMembershipWitness {
+    d: write_biguint / read_biguint,
+    b: write_bigint / read_bigint
+});
+
+impl_borsh!(PoeProof {
+    q: write_biguint / read_biguint
+});
+
+impl_borsh!(Poke2Proof {
+    z: write_biguint / read_biguint,
+    q: write_biguint / read_biguint,
+    r: write_bigint / read_bigint
+});
+
+impl_borsh!(MembershipProof {
+    w: write_biguint / read_biguint,
+    pi: write_nested / read_nested
+});
+
+impl_borsh!(CompressedWitness {
+    w: write_biguint / read_biguint,
+    pi: write_nested / read_nested
+});
+
+impl_borsh!(NonMembershipProof {
+    d: write_biguint / read_biguint,
+    v: write_biguint / read_biguint,
+    pi_d: write_nested / read_nested,
+    pi_g: write_nested / read_nested
+});
+
+impl BorshSerialize for MembershipWitness {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_biguint(&self.0, writer)
+    }
+}
+
+impl BorshDeserializeBounded for MembershipWitness {
+    fn deserialize_bounded<R: Read>(reader: &mut R, max_int_bytes: usize) -> io::Result<Self> {
+        read_biguint(reader, max_int_bytes).map(MembershipWitness)
+    }
+}
+
+impl BorshDeserialize for MembershipWitness {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Self::deserialize_bounded(reader, DEFAULT_MAX_INT_BYTES)
+    }
+}
+
+impl BorshSerialize for BatchProof {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.0.serialize(writer)
+    }
+}
+
+impl BorshDeserializeBounded for BatchProof {
+    fn deserialize_bounded<R: Read>(reader: &mut R, max_int_bytes: usize) -> io::Result<Self> {
+        PoeProof::deserialize_bounded(reader, max_int_bytes).map(BatchProof)
+    }
+}
+
+impl BorshDeserialize for BatchProof {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Self::deserialize_bounded(reader, DEFAULT_MAX_INT_BYTES)
+    }
+}
+
+impl BorshSerialize for BatchNonMemStarProof {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.0.serialize(writer)
+    }
+}
+
+impl BorshDeserializeBounded for BatchNonMemStarProof {
+    fn deserialize_bounded<R: Read>(reader: &mut R, max_int_bytes: usize) -> io::Result<Self> {
+        NonMembershipProof::deserialize_bounded(reader, max_int_bytes).map(BatchNonMemStarProof)
+    }
+}
+
+impl BorshDeserialize for BatchNonMemStarProof {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Self::deserialize_bounded(reader, DEFAULT_MAX_INT_BYTES)
+    }
+}
+
+/// Decoding checks that the element is a prime, with the default `PrimalityConfig`.
+impl BorshSerialize for PrimeElement {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_biguint(self.as_biguint(), writer)
+    }
+}
+
+impl BorshDeserializeBounded for PrimeElement {
+    fn deserialize_bounded<R: Read>(reader: &mut R, max_int_bytes: usize) -> io::Result<Self> {
+        let x = read_biguint(reader, max_int_bytes)?;
+        PrimeElement::new_checked(x, &Default::default()).map_err(|_| invalid_data("not a prime"))
+    }
+}
+
+impl BorshDeserialize for PrimeElement {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Self::deserialize_bounded(reader, DEFAULT_MAX_INT_BYTES)
+    }
+}
+
+fn write_nested<T: BorshSerialize, W: Write>(val: &T, writer: &mut W) -> io::Result<()> {
+    val.serialize(writer)
+}
+
+fn read_nested<T: BorshDeserializeBounded, R: Read>(
+    reader: &mut R,
+    max_int_bytes: usize,
+) -> io::Result<T> {
+    T::deserialize_bounded(reader, max_int_bytes)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::{Accumulator, NewState, OldState};
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn round_trip<T: BorshSerialize + BorshDeserialize + PartialEq + std::fmt::Debug>(val: &T) {
+        let bytes = ::borsh::to_vec(val).unwrap();
+        assert_eq!(&T::try_from_slice(&bytes).unwrap(), val);
+    }
+
+    #[test]
+    fn test_borsh_round_trip() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..4).map(|_| rng.gen_prime(128)).collect::<Vec<_>>();
+        let root = acc.state().clone();
+        let pi = acc.batch_add(&xs);
+        let y = rng.gen_prime(128);
+
+        round_trip(&acc.public_state());
+        round_trip(&acc.mem_wit_create(&xs[0]));
+        round_trip(&acc.non_mem_wit_create(&y));
+        round_trip(&acc.mem_wit_create_star(&xs[1]));
+        round_trip(&acc.non_mem_wit_create_star(&y));
+        round_trip(&pi);
+
+        // decoded values still verify
+        let bytes = ::borsh::to_vec(&pi).unwrap();
+        let pi = BatchProof::try_from_slice(&bytes).unwrap();
+        let (old, new) = (OldState(root), NewState(acc.state().clone()));
+        assert!(acc.ver_batch_add_transition(&pi, &old, &new, &xs));
+    }
+
+    #[test]
+    fn test_borsh_fixture() {
+        let w = MembershipWitness(BigUint::from(0x0102u32));
+        assert_eq!(::borsh::to_vec(&w).unwrap(), vec![2, 0, 0, 0, 0x02, 0x01]);
+
+        let w = NonMembershipWitness {
+            d: BigUint::from(5u32),
+            b: BigInt::from(-3),
+        };
+        let bytes = vec![1, 0, 0, 0, 5, 1, 1, 0, 0, 0, 3];
+        assert_eq!(::borsh::to_vec(&w).unwrap(), bytes);
+        assert_eq!(NonMembershipWitness::try_from_slice(&bytes).unwrap(), w);
+
+        let pi = NonMembershipProof {
+            d: BigUint::zero(),
+            v: BigUint::from(1u32),
+            pi_d: Poke2Proof {
+                z: BigUint::from(2u32),
+                q: BigUint::from(0x0300u32),
+                r: BigInt::zero(),
+            },
+            pi_g: PoeProof {
+                q: BigUint::from(4u32),
+            },
+        };
+        let bytes = vec![
+            0, 0, 0, 0, // d
+            1, 0, 0, 0, 1, // v
+            1, 0, 0, 0, 2, // z
+            2, 0, 0, 0, 0, 3, // Q
+            0, 0, 0, 0, 0, // r
+            1, 0, 0, 0, 4, // pi_g
+        ];
+        assert_eq!(::borsh::to_vec(&pi).unwrap(), bytes);
+        assert_eq!(NonMembershipProof::try_from_slice(&bytes).unwrap(), pi);
+    }
+
+    #[test]
+    fn test_borsh_rejects_invalid() {
+        // length above the bound, without the data being present
+        let bytes = vec![0xff, 0xff, 0xff, 0x7f];
+        assert!(MembershipWitness::try_from_slice(&bytes).is_err());
+        assert!(MembershipWitness::try_from_slice_bounded(&[3, 0, 0, 0, 1, 2, 3], 2).is_err());
+        assert!(MembershipWitness::try_from_slice_bounded(&[3, 0, 0, 0, 1, 2, 3], 3).is_ok());
+
+        // trailing zero byte
+        assert!(MembershipWitness::try_from_slice(&[2, 0, 0, 0, 1, 0]).is_err());
+        // negative zero
+        assert!(NonMembershipWitness::try_from_slice(&[0, 0, 0, 0, 1, 0, 0, 0, 0]).is_err());
+        // invalid sign
+        assert!(NonMembershipWitness::try_from_slice(&[0, 0, 0, 0, 2, 0, 0, 0, 0]).is_err());
+        // trailing bytes
+        assert!(MembershipWitness::try_from_slice(&[1, 0, 0, 0, 1, 0]).is_err());
+
+        // prime elements are checked
+        assert_eq!(
+            PrimeElement::try_from_slice(&[2, 0, 0, 0, 0x01, 0x01]).unwrap(),
+            PrimeElement::new_unchecked(BigUint::from(257u32))
+        );
+        assert!(PrimeElement::try_from_slice(&[2, 0, 0, 0, 0x03, 0x01]).is_err());
+        assert!(PrimeElement::try_from_slice(&[0, 0, 0, 0]).is_err());
+    }
+}
@@ -0,0 +1,474 @@
+//! Fixed width encoding of group elements.
+//!
+//! Every group element is encoded as exactly `ceil(|n|/8)` big-endian bytes, padded with leading
+//! zeros. This is the encoding bindings to other languages should use, so that they all agree.
+//!
+//! Signed integers are a sign byte, `0` for non-negative and `1` for negative values, followed
+//! by their magnitude with the fixed width of their bound: the `b` of a non-membership witness
+//! for `x` has the width of `x`, and the `r` of a PoKE2 proof the width of the 128 bit
+//! challenges, `CHALLENGE_BYTES`.
+
+use std::fmt;
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::Zero;
+
+use crate::accumulator::{
+    BatchNonMemStarProof, BatchProof, MembershipProof, MembershipWitness, NonMembershipProof,
+    NonMembershipWitness,
+};
+use crate::proofs::{PoeProof, Poke2Proof};
+
+/// The number of bytes of the challenges `l` of the proofs, see `hash::hash_prime`.
+pub const CHALLENGE_BYTES: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FixedBytesError {
+    /// The input does not have the expected length.
+    WrongLength { expected: usize, found: usize },
+    /// The encoded value is not smaller than the modulus, or than the bound of a signed integer.
+    OutOfRange,
+    /// The sign byte is neither `0` nor `1`, or marks a negative zero.
+    InvalidSign(u8),
+}
+
+impl fmt::Display for FixedBytesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FixedBytesError::WrongLength { expected, found } => {
+                write!(f, "expected {} bytes, found {}", expected, found)
+            }
+            FixedBytesError::OutOfRange => write!(f, "value is out of range"),
+            FixedBytesError::InvalidSign(b) => write!(f, "invalid sign byte {}", b),
+        }
+    }
+}
+
+impl std::error::Error for FixedBytesError {}
+
+/// Returns the number of bytes a single group element of `Z_n` is encoded with.
+pub fn modulus_bytes(n: &BigUint) -> usize {
+    n.bits().div_ceil(8)
+}
+
+/// Encodes `x` as exactly `modulus_bytes` big-endian bytes.
+///
+/// Panics if `x` does not fit.
+pub fn to_fixed_bytes(x: &BigUint, modulus_bytes: usize) -> Vec<u8> {
+    let bytes = x.to_bytes_be();
+    let bytes: &[u8] = if bytes == [0] { &[] } else { &bytes };
+    assert!(bytes.len() <= modulus_bytes, "value too large");
+
+    let mut res = vec![0u8; modulus_bytes - bytes.len()];
+    res.extend_from_slice(bytes);
+
+    res
+}
+
+/// Decodes a group element of `Z_n`, encoded with `to_fixed_bytes`.
+pub fn from_fixed_bytes(bytes: &[u8], n: &BigUint) -> Result<BigUint, FixedBytesError> {
+    let expected = modulus_bytes(n);
+    if bytes.len() != expected {
+        return Err(FixedBytesError::WrongLength {
+            expected,
+            found: bytes.len(),
+        });
+    }
+
+    let x = BigUint::from_bytes_be(bytes);
+    if &x >= n {
+        return Err(FixedBytesError::OutOfRange);
+    }
+
+    Ok(x)
+}
+
+/// Encodes `x` as a sign byte, followed by its magnitude as exactly `width` big-endian bytes.
+///
+/// Panics if the magnitude does not fit.
+pub fn to_fixed_bytes_signed(x: &BigInt, width: usize) -> Vec<u8> {
+    let mut res = vec![if x.sign() == Sign::Minus { 1 } else { 0 }];
+    res.extend(to_fixed_bytes(&magnitude(x), width));
+
+    res
+}
+
+/// Decodes a signed integer with a magnitude of `width` bytes, encoded with
+/// `to_fixed_bytes_signed`.
+pub fn from_fixed_bytes_signed(bytes: &[u8], width: usize) -> Result<BigInt, FixedBytesError> {
+    if bytes.len() != width + 1 {
+        return Err(FixedBytesError::WrongLength {
+            expected: width + 1,
+            found: bytes.len(),
+        });
+    }
+
+    let sign = match bytes[0] {
+        0 => Sign::Plus,
+        1 => Sign::Minus,
+        b => return Err(FixedBytesError::InvalidSign(b)),
+    };
+    let magnitude = BigUint::from_bytes_be(&bytes[1..]);
+    if sign == Sign::Minus && magnitude.is_zero() {
+        return Err(FixedBytesError::InvalidSign(1));
+    }
+
+    Ok(BigInt::from_biguint(sign, magnitude))
+}
+
+fn magnitude(x: &BigInt) -> BigUint {
+    BigUint::from_bytes_be(&x.to_bytes_be().1)
+}
+
+/// Splits `bytes` into parts of the given lengths, failing unless they add up exactly.
+fn split_parts<'a>(bytes: &'a [u8], lens: &[usize]) -> Result<Vec<&'a [u8]>, FixedBytesError> {
+    let expected = lens.iter().sum();
+    if bytes.len() != expected {
+        return Err(FixedBytesError::WrongLength {
+            expected,
+            found: bytes.len(),
+        });
+    }
+
+    let mut rest = bytes;
+    let parts = lens
+        .iter()
+        .map(|&len| {
+            let (part, tail) = rest.split_at(len);
+            rest = tail;
+            part
+        })
+        .collect();
+
+    Ok(parts)
+}
+
+/// Fixed width encoding for values consisting only of group elements, and signed integers
+/// with a fixed bound.
+pub trait FixedBytes: Sized {
+    /// Encodes `self`, using `modulus_bytes` bytes per group element.
+    fn to_fixed_bytes(&self, modulus_bytes: usize) -> Vec<u8>;
+
+    /// Decodes a value encoded with `to_fixed_bytes`, for the modulus `n`.
+    fn from_fixed_bytes(bytes: &[u8], n: &BigUint) -> Result<Self, FixedBytesError>;
+}
+
+impl FixedBytes for MembershipWitness {
+    fn to_fixed_bytes(&self, modulus_bytes: usize) -> Vec<u8> {
+        to_fixed_bytes(&self.0, modulus_bytes)
+    }
+
+    fn from_fixed_bytes(bytes: &[u8], n: &BigUint) -> Result<Self, FixedBytesError> {
+        from_fixed_bytes(bytes, n).map(MembershipWitness)
+    }
+}
+
+impl FixedBytes for PoeProof {
+    fn to_fixed_bytes(&self, modulus_bytes: usize) -> Vec<u8> {
+        to_fixed_bytes(&self.q, modulus_bytes)
+    }
+
+    fn from_fixed_bytes(bytes: &[u8], n: &BigUint) -> Result<Self, FixedBytesError> {
+        from_fixed_bytes(bytes, n).map(|q| PoeProof { q })
+    }
+}
+
+impl FixedBytes for BatchProof {
+    fn to_fixed_bytes(&self, modulus_bytes: usize) -> Vec<u8> {
+        self.0.to_fixed_bytes(modulus_bytes)
+    }
+
+    fn from_fixed_bytes(bytes: &[u8], n: &BigUint) -> Result<Self, FixedBytesError> {
+        PoeProof::from_fixed_bytes(bytes, n).map(BatchProof)
+    }
+}
+
+impl FixedBytes for MembershipProof {
+    fn to_fixed_bytes(&self, modulus_bytes: usize) -> Vec<u8> {
+        let mut res = to_fixed_bytes(&self.w, modulus_bytes);
+        res.extend(self.pi.to_fixed_bytes(modulus_bytes));
+
+        res
+    }
+
+    fn from_fixed_bytes(bytes: &[u8], n: &BigUint) -> Result<Self, FixedBytesError> {
+        let len = modulus_bytes(n);
+        if bytes.len() != 2 * len {
+            return Err(FixedBytesError::WrongLength {
+                expected: 2 * len,
+                found: bytes.len(),
+            });
+        }
+
+        let (w, pi) = bytes.split_at(len);
+
+        Ok(MembershipProof {
+            w: from_fixed_bytes(w, n)?,
+            pi: PoeProof::from_fixed_bytes(pi, n)?,
+        })
+    }
+}
+
+impl FixedBytes for Poke2Proof {
+    fn to_fixed_bytes(&self, modulus_bytes: usize) -> Vec<u8> {
+        let mut res = to_fixed_bytes(&self.z, modulus_bytes);
+        res.extend(to_fixed_bytes(&self.q, modulus_bytes));
+        res.extend(to_fixed_bytes_signed(&self.r, CHALLENGE_BYTES));
+
+        res
+    }
+
+    fn from_fixed_bytes(bytes: &[u8], n: &BigUint) -> Result<Self, FixedBytesError> {
+        let len = modulus_bytes(n);
+        let parts = split_parts(bytes, &[len, len, CHALLENGE_BYTES + 1])?;
+
+        Ok(Poke2Proof {
+            z: from_fixed_bytes(parts[0], n)?,
+            q: from_fixed_bytes(parts[1], n)?,
+            r: from_fixed_bytes_signed(parts[2], CHALLENGE_BYTES)?,
+        })
+    }
+}
+
+impl FixedBytes for NonMembershipProof {
+    fn to_fixed_bytes(&self, modulus_bytes: usize) -> Vec<u8> {
+        let mut res = to_fixed_bytes(&self.d, modulus_bytes);
+        res.extend(to_fixed_bytes(&self.v, modulus_bytes));
+        res.extend(self.pi_d.to_fixed_bytes(modulus_bytes));
+        res.extend(self.pi_g.to_fixed_bytes(modulus_bytes));
+
+        res
+    }
+
+    fn from_fixed_bytes(bytes: &[u8], n: &BigUint) -> Result<Self, FixedBytesError> {
+        let len = modulus_bytes(n);
+        let parts = split_parts(bytes, &[len, len, 2 * len + CHALLENGE_BYTES + 1, len])?;
+
+        Ok(NonMembershipProof {
+            d: from_fixed_bytes(parts[0], n)?,
+            v: from_fixed_bytes(parts[1], n)?,
+            pi_d: Poke2Proof::from_fixed_bytes(parts[2], n)?,
+            pi_g: PoeProof::from_fixed_bytes(parts[3], n)?,
+        })
+    }
+}
+
+impl FixedBytes for BatchNonMemStarProof {
+    fn to_fixed_bytes(&self, modulus_bytes: usize) -> Vec<u8> {
+        self.0.to_fixed_bytes(modulus_bytes)
+    }
+
+    fn from_fixed_bytes(bytes: &[u8], n: &BigUint) -> Result<Self, FixedBytesError> {
+        NonMembershipProof::from_fixed_bytes(bytes, n).map(BatchNonMemStarProof)
+    }
+}
+
+/// Fixed width encoding for values that also hold an integer bounded by the element `x` they
+/// are for, which is then needed to encode and decode them.
+pub trait FixedBytesFor: Sized {
+    /// Encodes `self`, the value for `x`, using `modulus_bytes` bytes per group element.
+    fn to_fixed_bytes_for(&self, x: &BigUint, modulus_bytes: usize) -> Vec<u8>;
+
+    /// Decodes a value for `x` encoded with `to_fixed_bytes_for`, for the modulus `n`.
+    fn from_fixed_bytes_for(
+        bytes: &[u8],
+        x: &BigUint,
+        n: &BigUint,
+    ) -> Result<Self, FixedBytesError>;
+}
+
+/// `d` as a group element, and `b` as a signed integer with the width of `x`.
+///
+/// Encoding panics unless `|b| < x`, and decoding rejects such witnesses, as verifiers do.
+impl FixedBytesFor for NonMembershipWitness {
+    fn to_fixed_bytes_for(&self, x: &BigUint, modulus_bytes: usize) -> Vec<u8> {
+        assert!(&magnitude(&self.b) < x, "value too large");
+
+        let mut res = to_fixed_bytes(&self.d, modulus_bytes);
+        res.extend(to_fixed_bytes_signed(&self.b, self::modulus_bytes(x)));
+
+        res
+    }
+
+    fn from_fixed_bytes_for(
+        bytes: &[u8],
+        x: &BigUint,
+        n: &BigUint,
+    ) -> Result<Self, FixedBytesError> {
+        let width = modulus_bytes(x);
+        let parts = split_parts(bytes, &[modulus_bytes(n), width + 1])?;
+
+        let b = from_fixed_bytes_signed(parts[1], width)?;
+        if &magnitude(&b) >= x {
+            return Err(FixedBytesError::OutOfRange);
+        }
+
+        Ok(NonMembershipWitness {
+            d: from_fixed_bytes(parts[0], n)?,
+            b,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use num_traits::{One, Zero};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_fixed_bytes_leading_zeros() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let n = rng.gen_prime(256) * rng.gen_prime(256);
+        let len = modulus_bytes(&n);
+        assert_eq!(len, 64);
+
+        for x in &[BigUint::zero(), BigUint::one(), BigUint::from(0x0100u32)] {
+            let bytes = to_fixed_bytes(x, len);
+            assert_eq!(bytes.len(), len);
+            assert!(bytes[..len - 2].iter().all(|b| *b == 0));
+            assert_eq!(&from_fixed_bytes(&bytes, &n).unwrap(), x);
+        }
+
+        // maximum value
+        let max = &n - 1u32;
+        let bytes = to_fixed_bytes(&max, len);
+        assert_eq!(from_fixed_bytes(&bytes, &n).unwrap(), max);
+
+        // n itself is out of range
+        let bytes = to_fixed_bytes(&n, len);
+        assert_eq!(
+            from_fixed_bytes(&bytes, &n),
+            Err(FixedBytesError::OutOfRange)
+        );
+
+        // wrong lengths
+        assert_eq!(
+            from_fixed_bytes(&[1], &n),
+            Err(FixedBytesError::WrongLength {
+                expected: 64,
+                found: 1
+            })
+        );
+        assert!(from_fixed_bytes(&vec![0u8; len + 1], &n).is_err());
+    }
+
+    #[test]
+    fn test_fixed_bytes_witnesses() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let n = acc.modulus().clone();
+        let len = modulus_bytes(&n);
+
+        let xs = (0..4).map(|_| rng.gen_prime(128)).collect::<Vec<_>>();
+        let pi = acc.batch_add(&xs);
+
+        let state = to_fixed_bytes(acc.state(), len);
+        assert_eq!(&from_fixed_bytes(&state, &n).unwrap(), acc.state());
+
+        let w = acc.mem_wit_create(&xs[0]);
+        let bytes = w.to_fixed_bytes(len);
+        assert_eq!(bytes.len(), len);
+        assert_eq!(MembershipWitness::from_fixed_bytes(&bytes, &n).unwrap(), w);
+
+        let bytes = pi.to_fixed_bytes(len);
+        assert_eq!(BatchProof::from_fixed_bytes(&bytes, &n).unwrap(), pi);
+
+        let p = acc.mem_wit_create_star(&xs[1]);
+        let bytes = p.to_fixed_bytes(len);
+        assert_eq!(bytes.len(), 2 * len);
+        assert_eq!(MembershipProof::from_fixed_bytes(&bytes, &n).unwrap(), p);
+        assert!(MembershipProof::from_fixed_bytes(&bytes[1..], &n).is_err());
+    }
+
+    #[test]
+    fn test_fixed_bytes_signed() {
+        for x in &[BigInt::zero(), BigInt::from(0x0100), BigInt::from(-0x0100)] {
+            let bytes = to_fixed_bytes_signed(x, 4);
+            assert_eq!(bytes.len(), 5);
+            assert_eq!(&from_fixed_bytes_signed(&bytes, 4).unwrap(), x);
+        }
+        assert_eq!(to_fixed_bytes_signed(&BigInt::from(-1), 2), vec![1, 0, 1]);
+
+        assert_eq!(
+            from_fixed_bytes_signed(&[2, 0, 1], 2),
+            Err(FixedBytesError::InvalidSign(2))
+        );
+        // negative zero
+        assert_eq!(
+            from_fixed_bytes_signed(&[1, 0, 0], 2),
+            Err(FixedBytesError::InvalidSign(1))
+        );
+        assert_eq!(
+            from_fixed_bytes_signed(&[0, 1], 2),
+            Err(FixedBytesError::WrongLength {
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_fixed_bytes_non_membership() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let n = acc.modulus().clone();
+        let len = modulus_bytes(&n);
+
+        let xs = (0..4).map(|_| rng.gen_prime(128)).collect::<Vec<_>>();
+        acc.batch_add(&xs);
+        let x = rng.gen_prime(128);
+
+        let w = acc.non_mem_wit_create(&x);
+        let bytes = w.to_fixed_bytes_for(&x, len);
+        assert_eq!(bytes.len(), len + 1 + 16);
+        let decoded = NonMembershipWitness::from_fixed_bytes_for(&bytes, &x, &n).unwrap();
+        assert_eq!(decoded, w);
+        assert!(acc.ver_non_mem(&decoded, &x));
+        assert!(NonMembershipWitness::from_fixed_bytes_for(&bytes[1..], &x, &n).is_err());
+
+        // negative b, as accepted by verifiers
+        let neg = NonMembershipWitness {
+            d: w.d.clone(),
+            b: &w.b - BigInt::from(x.clone()),
+        };
+        let bytes = neg.to_fixed_bytes_for(&x, len);
+        assert_eq!(bytes[len], 1);
+        assert_eq!(
+            NonMembershipWitness::from_fixed_bytes_for(&bytes, &x, &n).unwrap(),
+            neg
+        );
+
+        // |b| >= x
+        let mut bytes = w.to_fixed_bytes_for(&x, len);
+        bytes[len + 1..].copy_from_slice(&to_fixed_bytes(&x, 16));
+        assert_eq!(
+            NonMembershipWitness::from_fixed_bytes_for(&bytes, &x, &n),
+            Err(FixedBytesError::OutOfRange)
+        );
+
+        let p = acc.non_mem_wit_create_star(&x);
+        let bytes = p.to_fixed_bytes(len);
+        assert_eq!(bytes.len(), 5 * len + 1 + CHALLENGE_BYTES);
+        let decoded = NonMembershipProof::from_fixed_bytes(&bytes, &n).unwrap();
+        assert_eq!(decoded, p);
+        assert!(acc.ver_non_mem_star(&x, &decoded));
+        assert!(NonMembershipProof::from_fixed_bytes(&bytes[1..], &n).is_err());
+
+        let ys = (0..2).map(|_| rng.gen_prime(128)).collect::<Vec<_>>();
+        let p = acc.batch_non_mem_wit_create_star(&ys).unwrap();
+        let bytes = p.to_fixed_bytes(len);
+        assert_eq!(
+            BatchNonMemStarProof::from_fixed_bytes(&bytes, &n).unwrap(),
+            p
+        );
+    }
+}
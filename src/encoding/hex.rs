@@ -0,0 +1,84 @@
+//! Hex strings, as used in human readable formats.
+
+use failure::{bail, Error};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::Zero;
+
+/// Encodes `bytes` as lowercase hex.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes a hex string, accepting both lower and upper case.
+pub fn from_hex(s: &str) -> Result<Vec<u8>, Error> {
+    if s.len() % 2 != 0 {
+        bail!("odd number of hex digits");
+    }
+    if !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        bail!("invalid hex digit");
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| match u8::from_str_radix(&s[i..i + 2], 16) {
+            Ok(b) => Ok(b),
+            Err(_) => bail!("invalid hex digit"),
+        })
+        .collect()
+}
+
+/// Encodes `x` as minimal big-endian hex, `"00"` for zero.
+pub fn biguint_to_hex(x: &BigUint) -> String {
+    to_hex(&x.to_bytes_be())
+}
+
+/// Decodes a big-endian hex string.
+pub fn biguint_from_hex(s: &str) -> Result<BigUint, Error> {
+    Ok(BigUint::from_bytes_be(&from_hex(s)?))
+}
+
+/// Encodes `x` as minimal big-endian hex, prefixed with `-` if negative.
+pub fn bigint_to_hex(x: &BigInt) -> String {
+    let (sign, bytes) = x.to_bytes_be();
+    match sign {
+        Sign::Minus => format!("-{}", to_hex(&bytes)),
+        _ => to_hex(&bytes),
+    }
+}
+
+/// Decodes a big-endian hex string, optionally prefixed with `-`.
+pub fn bigint_from_hex(s: &str) -> Result<BigInt, Error> {
+    let (sign, digits) = if let Some(digits) = s.strip_prefix('-') {
+        (Sign::Minus, digits)
+    } else {
+        (Sign::Plus, s)
+    };
+
+    let magnitude = biguint_from_hex(digits)?;
+    if magnitude.is_zero() {
+        return Ok(BigInt::zero());
+    }
+
+    Ok(BigInt::from_biguint(sign, magnitude))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(to_hex(&[0x00, 0xab, 0x01]), "00ab01");
+        assert_eq!(from_hex("00AB01").unwrap(), vec![0x00, 0xab, 0x01]);
+        assert!(from_hex("abc").is_err());
+        assert!(from_hex("zz").is_err());
+        assert!(from_hex("+1").is_err());
+        assert!(from_hex("a\u{e9}a").is_err());
+
+        let x = BigInt::from(-0x1234);
+        assert_eq!(bigint_to_hex(&x), "-1234");
+        assert_eq!(bigint_from_hex("-1234").unwrap(), x);
+        assert_eq!(bigint_from_hex("-00").unwrap(), BigInt::zero());
+        assert_eq!(biguint_to_hex(&BigUint::zero()), "00");
+    }
+}
@@ -0,0 +1,20 @@
+//! Binary encodings of accumulator states, witnesses and proofs.
+
+#[cfg(feature = "borsh")]
+mod borsh;
+mod fixed;
+pub mod hex;
+mod packed;
+#[cfg(feature = "serde")]
+pub mod serde_hex;
+
+#[cfg(feature = "borsh")]
+pub use self::borsh::*;
+pub use self::fixed::*;
+pub use self::packed::*;
+
+/// Default upper bound for the encoded size of a single integer, in bytes.
+///
+/// Bezout coefficients in non-membership witnesses grow with the accumulated set, so this is
+/// generous compared to the size of group elements.
+pub const DEFAULT_MAX_INT_BYTES: usize = 1 << 20;
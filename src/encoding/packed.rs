@@ -0,0 +1,431 @@
+//! Length prefixed encoding of witnesses and proofs, for packing many of them back to back.
+//!
+//! Every integer is encoded as its length in bytes, a big-endian `u32`, followed by its
+//! minimal big-endian bytes, so zero is encoded as an empty integer. Signed integers are
+//! preceded by a sign byte, `0` for non-negative and `1` for negative values.
+//!
+//! Parsing reads directly from the input, without intermediate buffers, and rejects any
+//! integer longer than the modulus before looking at its bytes, so that the length prefix
+//! can't be used to make the parser allocate arbitrary amounts of memory.
+
+use std::fmt;
+
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::Zero;
+
+use super::modulus_bytes;
+use crate::accumulator::{
+    BatchNonMemStarProof, BatchProof, MembershipProof, MembershipWitness, NonMembershipProof,
+    NonMembershipWitness,
+};
+use crate::proofs::{PoeProof, Poke2Proof};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The input ends before the value does.
+    Truncated { needed: usize, available: usize },
+    /// An integer is longer than the modulus.
+    TooLarge { len: usize, max: usize },
+    /// An integer has leading zeros, or is a negative zero.
+    NonCanonical,
+    /// The sign byte is neither `0` nor `1`.
+    InvalidSign(u8),
+    /// A group element is not smaller than the modulus.
+    OutOfRange,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Truncated { needed, available } => write!(
+                f,
+                "unexpected end of input, needed {} bytes, {} available",
+                needed, available
+            ),
+            ParseError::TooLarge { len, max } => {
+                write!(f, "integer of {} bytes, at most {} allowed", len, max)
+            }
+            ParseError::NonCanonical => write!(f, "integer is not minimally encoded"),
+            ParseError::InvalidSign(b) => write!(f, "invalid sign byte {}", b),
+            ParseError::OutOfRange => write!(f, "value is not smaller than the modulus"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Appends the length prefixed encoding of `x` to `out`.
+pub fn write_biguint(out: &mut Vec<u8>, x: &BigUint) {
+    let bytes = if x.is_zero() {
+        Vec::new()
+    } else {
+        x.to_bytes_be()
+    };
+    write_bytes(out, &bytes);
+}
+
+/// Appends the length prefixed encoding of `x`, preceded by its sign, to `out`.
+pub fn write_bigint(out: &mut Vec<u8>, x: &BigInt) {
+    let (sign, bytes) = x.to_bytes_be();
+    out.push(if sign == Sign::Minus { 1 } else { 0 });
+    write_bytes(out, if x.is_zero() { &[] } else { &bytes });
+}
+
+/// Appends `bytes`, preceded by their length, to `out`.
+pub(crate) fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    let mut len = [0u8; 4];
+    BigEndian::write_u32(&mut len, bytes.len() as u32);
+    out.extend_from_slice(&len);
+    out.extend_from_slice(bytes);
+}
+
+/// Reads length prefixed values from a byte slice.
+#[derive(Debug)]
+pub struct PackedReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    max_len: usize,
+}
+
+impl<'a> PackedReader<'a> {
+    /// Creates a reader for `buf`, accepting integers of at most `max_len` bytes.
+    pub fn new(buf: &'a [u8], max_len: usize) -> Self {
+        PackedReader {
+            buf,
+            pos: 0,
+            max_len,
+        }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Reads the next `len` bytes as they are.
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], ParseError> {
+        let available = self.buf.len() - self.pos;
+        if len > available {
+            return Err(ParseError::Truncated {
+                needed: len,
+                available,
+            });
+        }
+
+        let bytes = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+
+        Ok(bytes)
+    }
+
+    /// Reads bytes preceded by their length, of at most `max_len` bytes.
+    pub(crate) fn read_bytes(&mut self) -> Result<&'a [u8], ParseError> {
+        let len = BigEndian::read_u32(self.take(4)?) as usize;
+        if len > self.max_len {
+            return Err(ParseError::TooLarge {
+                len,
+                max: self.max_len,
+            });
+        }
+
+        self.take(len)
+    }
+
+    /// Reads a non-negative integer.
+    pub fn read_biguint(&mut self) -> Result<BigUint, ParseError> {
+        let bytes = self.read_bytes()?;
+        if bytes.first() == Some(&0) {
+            return Err(ParseError::NonCanonical);
+        }
+
+        Ok(BigUint::from_bytes_be(bytes))
+    }
+
+    /// Reads a signed integer.
+    pub fn read_bigint(&mut self) -> Result<BigInt, ParseError> {
+        let sign = match self.take(1)?[0] {
+            0 => Sign::Plus,
+            1 => Sign::Minus,
+            b => return Err(ParseError::InvalidSign(b)),
+        };
+
+        let magnitude = self.read_biguint()?;
+        if sign == Sign::Minus && magnitude.is_zero() {
+            return Err(ParseError::NonCanonical);
+        }
+
+        Ok(BigInt::from_biguint(sign, magnitude))
+    }
+
+    /// Reads a group element of `Z_n`.
+    pub fn read_element(&mut self, n: &BigUint) -> Result<BigUint, ParseError> {
+        let x = self.read_biguint()?;
+        if &x >= n {
+            return Err(ParseError::OutOfRange);
+        }
+
+        Ok(x)
+    }
+}
+
+/// Length prefixed encoding, see the module documentation.
+pub trait Packed: Sized {
+    /// Appends the encoding of `self` to `out`.
+    fn write_packed(&self, out: &mut Vec<u8>);
+
+    /// Reads a value for the modulus `n` from `r`.
+    fn read_packed(r: &mut PackedReader, n: &BigUint) -> Result<Self, ParseError>;
+
+    /// Encodes `self`.
+    fn to_packed_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write_packed(&mut out);
+
+        out
+    }
+
+    /// Parses a value for the modulus `n` from the start of `buf`.
+    ///
+    /// Returns the value and the number of bytes it was encoded with, the next value starts
+    /// right after it.
+    fn parse(buf: &[u8], n: &BigUint) -> Result<(Self, usize), ParseError> {
+        let mut r = PackedReader::new(buf, modulus_bytes(n));
+        let x = Self::read_packed(&mut r, n)?;
+
+        Ok((x, r.position()))
+    }
+}
+
+impl Packed for PoeProof {
+    fn write_packed(&self, out: &mut Vec<u8>) {
+        write_biguint(out, &self.q);
+    }
+
+    fn read_packed(r: &mut PackedReader, n: &BigUint) -> Result<Self, ParseError> {
+        Ok(PoeProof {
+            q: r.read_element(n)?,
+        })
+    }
+}
+
+impl Packed for Poke2Proof {
+    fn write_packed(&self, out: &mut Vec<u8>) {
+        write_biguint(out, &self.z);
+        write_biguint(out, &self.q);
+        write_bigint(out, &self.r);
+    }
+
+    fn read_packed(r: &mut PackedReader, n: &BigUint) -> Result<Self, ParseError> {
+        Ok(Poke2Proof {
+            z: r.read_element(n)?,
+            q: r.read_element(n)?,
+            r: r.read_bigint()?,
+        })
+    }
+}
+
+impl Packed for BatchProof {
+    fn write_packed(&self, out: &mut Vec<u8>) {
+        self.0.write_packed(out);
+    }
+
+    fn read_packed(r: &mut PackedReader, n: &BigUint) -> Result<Self, ParseError> {
+        PoeProof::read_packed(r, n).map(BatchProof)
+    }
+}
+
+impl Packed for MembershipWitness {
+    fn write_packed(&self, out: &mut Vec<u8>) {
+        write_biguint(out, &self.0);
+    }
+
+    fn read_packed(r: &mut PackedReader, n: &BigUint) -> Result<Self, ParseError> {
+        r.read_element(n).map(MembershipWitness)
+    }
+}
+
+impl Packed for NonMembershipWitness {
+    fn write_packed(&self, out: &mut Vec<u8>) {
+        write_biguint(out, &self.d);
+        write_bigint(out, &self.b);
+    }
+
+    fn read_packed(r: &mut PackedReader, n: &BigUint) -> Result<Self, ParseError> {
+        Ok(NonMembershipWitness {
+            d: r.read_element(n)?,
+            b: r.read_bigint()?,
+        })
+    }
+}
+
+impl Packed for MembershipProof {
+    fn write_packed(&self, out: &mut Vec<u8>) {
+        write_biguint(out, &self.w);
+        self.pi.write_packed(out);
+    }
+
+    fn read_packed(r: &mut PackedReader, n: &BigUint) -> Result<Self, ParseError> {
+        Ok(MembershipProof {
+            w: r.read_element(n)?,
+            pi: PoeProof::read_packed(r, n)?,
+        })
+    }
+}
+
+impl Packed for NonMembershipProof {
+    fn write_packed(&self, out: &mut Vec<u8>) {
+        write_biguint(out, &self.d);
+        write_biguint(out, &self.v);
+        self.pi_d.write_packed(out);
+        self.pi_g.write_packed(out);
+    }
+
+    fn read_packed(r: &mut PackedReader, n: &BigUint) -> Result<Self, ParseError> {
+        Ok(NonMembershipProof {
+            d: r.read_element(n)?,
+            v: r.read_element(n)?,
+            pi_d: Poke2Proof::read_packed(r, n)?,
+            pi_g: PoeProof::read_packed(r, n)?,
+        })
+    }
+}
+
+impl Packed for BatchNonMemStarProof {
+    fn write_packed(&self, out: &mut Vec<u8>) {
+        self.0.write_packed(out);
+    }
+
+    fn read_packed(r: &mut PackedReader, n: &BigUint) -> Result<Self, ParseError> {
+        NonMembershipProof::read_packed(r, n).map(BatchNonMemStarProof)
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    fn proofs(rng: &mut ChaChaRng) -> (BigUint, MembershipProof, NonMembershipProof) {
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = (0..4).map(|_| rng.gen_prime(128)).collect::<Vec<_>>();
+        acc.batch_add(&xs);
+        let y = rng.gen_prime(128);
+
+        (
+            acc.modulus().clone(),
+            acc.mem_wit_create_star(&xs[0]),
+            acc.non_mem_wit_create_star(&y),
+        )
+    }
+
+    #[test]
+    fn test_packed_round_trip() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (n, mem, non_mem) = proofs(rng);
+
+        // back to back
+        let mut buf = mem.to_packed_bytes();
+        non_mem.write_packed(&mut buf);
+        mem.pi.write_packed(&mut buf);
+
+        let (p, len) = MembershipProof::parse(&buf, &n).unwrap();
+        assert_eq!(p, mem);
+        let (q, len2) = NonMembershipProof::parse(&buf[len..], &n).unwrap();
+        assert_eq!(q, non_mem);
+        let (pi, len3) = PoeProof::parse(&buf[len + len2..], &n).unwrap();
+        assert_eq!(pi, mem.pi);
+        assert_eq!(len + len2 + len3, buf.len());
+
+        let w = NonMembershipWitness {
+            d: BigUint::zero(),
+            b: BigInt::from(-5),
+        };
+        let bytes = w.to_packed_bytes();
+        assert_eq!(bytes, vec![0, 0, 0, 0, 1, 0, 0, 0, 1, 5]);
+        assert_eq!(NonMembershipWitness::parse(&bytes, &n).unwrap(), (w, 10));
+    }
+
+    #[test]
+    fn test_packed_invalid() {
+        let n = BigUint::from(0xffffu32);
+
+        assert_eq!(
+            PoeProof::parse(&[0, 0, 0], &n),
+            Err(ParseError::Truncated {
+                needed: 4,
+                available: 3
+            })
+        );
+        assert_eq!(
+            PoeProof::parse(&[0, 0, 0, 2, 1], &n),
+            Err(ParseError::Truncated {
+                needed: 2,
+                available: 1
+            })
+        );
+        // a huge length is rejected before reading any further
+        assert_eq!(
+            PoeProof::parse(&[0xff, 0xff, 0xff, 0xff], &n),
+            Err(ParseError::TooLarge {
+                len: 0xffff_ffff,
+                max: 2
+            })
+        );
+        assert_eq!(
+            PoeProof::parse(&[0, 0, 0, 2, 0, 1], &n),
+            Err(ParseError::NonCanonical)
+        );
+        assert_eq!(
+            PoeProof::parse(&[0, 0, 0, 2, 0xff, 0xff], &n),
+            Err(ParseError::OutOfRange)
+        );
+        assert_eq!(
+            NonMembershipWitness::parse(&[0, 0, 0, 0, 2, 0, 0, 0, 0], &n),
+            Err(ParseError::InvalidSign(2))
+        );
+        assert_eq!(
+            NonMembershipWitness::parse(&[0, 0, 0, 0, 1, 0, 0, 0, 0], &n),
+            Err(ParseError::NonCanonical)
+        );
+    }
+
+    #[test]
+    fn test_packed_fuzz() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (n, _, non_mem) = proofs(rng);
+        let bytes = non_mem.to_packed_bytes();
+
+        // every truncation fails
+        for len in 0..bytes.len() {
+            assert!(NonMembershipProof::parse(&bytes[..len], &n).is_err());
+        }
+
+        // flipped bits either fail, or parse to a different proof
+        for i in 0..bytes.len() * 8 {
+            let mut bytes = bytes.clone();
+            bytes[i / 8] ^= 1 << (i % 8);
+            if let Ok((p, _)) = NonMembershipProof::parse(&bytes, &n) {
+                assert_ne!(p, non_mem);
+            }
+        }
+
+        // random garbage, with lengths that are mostly in range
+        for _ in 0..1000 {
+            let len = rng.gen_range(0, 2 * bytes.len());
+            let mut bytes = (0..len).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+            for i in (0..len.saturating_sub(4)).step_by(37) {
+                bytes[i..i + 4].copy_from_slice(&[0, 0, 0, rng.gen_range(0, 40)]);
+            }
+            let _ = NonMembershipProof::parse(&bytes, &n);
+            let _ = Poke2Proof::parse(&bytes, &n);
+        }
+    }
+}
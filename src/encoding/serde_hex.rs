@@ -0,0 +1,512 @@
+//! Serde representations of big integers, for use with `#[serde(with = "...")]`.
+//!
+//! Human readable formats get lowercase big-endian hex strings, integers are prefixed with `-`
+//! if negative. Binary formats get the big-endian bytes, integers with a leading sign byte,
+//! `1` if negative and `0` otherwise.
+//!
+//! Deserialization accepts hex with and without leading zeros, also an odd number of digits.
+//! Integers longer than `DEFAULT_MAX_INT_BYTES` are rejected before they are parsed.
+//!
+//! Only `PublicState` is encoded with the width of its modulus, and checked to be smaller than
+//! it when decoded, as the modulus is part of it. Witnesses and proofs are encoded without
+//! leading zeros, and decoded without any range check, as there is no modulus to check them
+//! against. Their group elements are only checked by the verifiers, before any arithmetic.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::Zero;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::{SerializeSeq, SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+
+use super::hex::{bigint_from_hex, bigint_to_hex, biguint_from_hex, biguint_to_hex, to_hex};
+use super::{modulus_bytes, to_fixed_bytes, DEFAULT_MAX_INT_BYTES};
+use crate::accumulator::{ParamError, PublicState};
+use crate::element::PrimeElement;
+
+/// `BigUint` as hex or bytes.
+pub mod biguint {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(x: &BigUint, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&biguint_to_hex(x))
+        } else {
+            serializer.serialize_bytes(&x.to_bytes_be())
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigUint, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            biguint_from_hex(&pad_hex(&s)?).map_err(de::Error::custom)
+        } else {
+            let bytes = deserializer.deserialize_bytes(BytesVisitor)?;
+            Ok(BigUint::from_bytes_be(&bytes))
+        }
+    }
+}
+
+/// `BigInt` as hex or bytes, with the sign.
+pub mod bigint {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(x: &BigInt, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&bigint_to_hex(x))
+        } else {
+            let (sign, magnitude) = x.to_bytes_be();
+            let mut bytes = vec![(sign == Sign::Minus) as u8];
+            bytes.extend(magnitude);
+            serializer.serialize_bytes(&bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigInt, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            let (sign, digits) = match s.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", &s[..]),
+            };
+            bigint_from_hex(&format!("{}{}", sign, pad_hex(digits)?)).map_err(de::Error::custom)
+        } else {
+            let bytes = deserializer.deserialize_bytes(BytesVisitor)?;
+            let sign = match bytes.first() {
+                Some(0) => Sign::Plus,
+                Some(1) => Sign::Minus,
+                _ => return Err(de::Error::custom("invalid sign byte")),
+            };
+            let magnitude = BigUint::from_bytes_be(&bytes[1..]);
+            if magnitude.is_zero() {
+                return Ok(BigInt::zero());
+            }
+            Ok(BigInt::from_biguint(sign, magnitude))
+        }
+    }
+}
+
+/// `Vec<BigUint>`, each element as with `biguint`.
+pub mod biguint_vec {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(xs: &[BigUint], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(xs.len()))?;
+        for x in xs {
+            seq.serialize_element(&Ref(x))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<BigUint>, D::Error> {
+        let xs = Vec::<Owned>::deserialize(deserializer)?;
+        Ok(xs.into_iter().map(|x| x.0).collect())
+    }
+
+    struct Ref<'a>(&'a BigUint);
+
+    impl<'a> Serialize for Ref<'a> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::biguint::serialize(self.0, serializer)
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Owned(#[serde(with = "super::biguint")] BigUint);
+}
+
+impl Serialize for PublicState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let len = modulus_bytes(&self.n);
+
+        let mut state = serializer.serialize_struct("PublicState", 3)?;
+        state.serialize_field("n", &FixedWidth(&self.n, len))?;
+        state.serialize_field("g", &FixedWidth(&self.g, len))?;
+        state.serialize_field("root", &FixedWidth(&self.root, len))?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicState {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let state = PublicStateRepr::deserialize(deserializer)?;
+        PublicState::try_from(state).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "PublicState")]
+struct PublicStateRepr {
+    #[serde(with = "biguint")]
+    n: BigUint,
+    #[serde(with = "biguint")]
+    g: BigUint,
+    #[serde(with = "biguint")]
+    root: BigUint,
+}
+
+impl TryFrom<PublicStateRepr> for PublicState {
+    type Error = ParamError;
+
+    fn try_from(state: PublicStateRepr) -> Result<Self, ParamError> {
+        let PublicStateRepr { n, g, root } = state;
+        if g >= n {
+            return Err(ParamError::InvalidGenerator);
+        }
+        if root >= n {
+            return Err(ParamError::InvalidState);
+        }
+
+        Ok(PublicState { n, g, root })
+    }
+}
+
+/// Encoded like `biguint`. Decoding checks that the element is a prime, with the default
+/// `PrimalityConfig`.
+impl Serialize for PrimeElement {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        biguint::serialize(self.as_biguint(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PrimeElement {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let x = biguint::deserialize(deserializer)?;
+        PrimeElement::new_checked(x, &Default::default()).map_err(de::Error::custom)
+    }
+}
+
+/// A group element, encoded with the width of the modulus.
+struct FixedWidth<'a>(&'a BigUint, usize);
+
+impl<'a> Serialize for FixedWidth<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = to_fixed_bytes(self.0, self.1);
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&to_hex(&bytes))
+        } else {
+            serializer.serialize_bytes(&bytes)
+        }
+    }
+}
+
+/// Left pads hex strings with an odd number of digits, empty strings are invalid.
+fn pad_hex<E: de::Error>(s: &str) -> Result<String, E> {
+    if s.is_empty() {
+        return Err(E::custom("empty hex string"));
+    }
+    if s.len() > 2 * DEFAULT_MAX_INT_BYTES {
+        return Err(E::custom("integer too large"));
+    }
+
+    if s.len() % 2 != 0 {
+        Ok(format!("0{}", s))
+    } else {
+        Ok(s.to_string())
+    }
+}
+
+struct BytesVisitor;
+
+/// Longest accepted byte encoding, with the sign byte of signed integers.
+const MAX_BYTES: usize = DEFAULT_MAX_INT_BYTES + 1;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "big-endian bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        if v.len() > MAX_BYTES {
+            return Err(E::custom("integer too large"));
+        }
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        if v.len() > MAX_BYTES {
+            return Err(E::custom("integer too large"));
+        }
+        Ok(v)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(MAX_BYTES));
+        while let Some(b) = seq.next_element()? {
+            if bytes.len() == MAX_BYTES {
+                return Err(de::Error::custom("integer too large"));
+            }
+            bytes.push(b);
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::de::value::Error;
+    use serde::forward_to_deserialize_any;
+    use serde::ser::{self, Impossible};
+
+    use crate::accumulator::{MembershipWitness, NonMembershipProof, NonMembershipWitness};
+    use crate::proofs::{PoeProof, Poke2Proof};
+
+    fn state() -> PublicState {
+        PublicState {
+            n: BigUint::from(0xc5u32 * 0x10000 + 0x1234),
+            g: BigUint::from(0x41u32),
+            root: BigUint::from(0x12_3456u32),
+        }
+    }
+
+    fn round_trip<T>(x: &T) -> String
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + fmt::Debug,
+    {
+        let s = serde_json::to_string(x).unwrap();
+        assert_eq!(&serde_json::from_str::<T>(&s).unwrap(), x);
+        s
+    }
+
+    #[test]
+    fn test_json() {
+        let state = state();
+        assert_eq!(
+            round_trip(&state),
+            r#"{"n":"c51234","g":"000041","root":"123456"}"#
+        );
+
+        assert_eq!(
+            round_trip(&MembershipWitness(BigUint::from(0xabcdu32))),
+            r#""abcd""#
+        );
+        assert_eq!(
+            round_trip(&NonMembershipWitness {
+                d: BigUint::zero(),
+                b: BigInt::from(-0x1f),
+            }),
+            r#"{"d":"00","b":"-1f"}"#
+        );
+
+        let pi = NonMembershipProof {
+            d: BigUint::from(1u32),
+            v: BigUint::from(2u32),
+            pi_d: Poke2Proof {
+                z: BigUint::from(3u32),
+                q: BigUint::from(4u32),
+                r: BigInt::from(0x500),
+            },
+            pi_g: PoeProof {
+                q: BigUint::from(6u32),
+            },
+        };
+        assert_eq!(
+            round_trip(&pi),
+            r#"{"d":"01","v":"02","pi_d":{"z":"03","q":"04","r":"0500"},"pi_g":{"q":"06"}}"#
+        );
+    }
+
+    #[test]
+    fn test_json_prime_element() {
+        let p = PrimeElement::new_checked(BigUint::from(65537u32), &Default::default()).unwrap();
+        assert_eq!(round_trip(&p), r#""010001""#);
+
+        // composites are rejected
+        assert!(serde_json::from_str::<PrimeElement>(r#""010005""#).is_err());
+        assert!(serde_json::from_str::<PrimeElement>(r#""01""#).is_err());
+    }
+
+    #[test]
+    fn test_json_lenient() {
+        // padded, unpadded and upper case hex are all accepted
+        let state: PublicState =
+            serde_json::from_str(r#"{"n":"C51234","g":"41","root":"00123456"}"#).unwrap();
+        assert_eq!(state, self::state());
+
+        let w: NonMembershipWitness = serde_json::from_str(r#"{"d":"0","b":"-01f"}"#).unwrap();
+        assert_eq!(w.d, BigUint::zero());
+        assert_eq!(w.b, BigInt::from(-0x1f));
+    }
+
+    #[test]
+    fn test_json_invalid() {
+        for s in &[
+            r#""""#,
+            r#""-""#,
+            r#""0x12""#,
+            r#""+1""#,
+            r#""1g""#,
+            r#"" 12""#,
+            "12",
+        ] {
+            assert!(
+                serde_json::from_str::<MembershipWitness>(s).is_err(),
+                "{}",
+                s
+            );
+        }
+        assert!(serde_json::from_str::<NonMembershipWitness>(r#"{"d":"00","b":"--1"}"#).is_err());
+
+        // oversized integers
+        let huge = format!(r#""{}""#, "f".repeat(2 * DEFAULT_MAX_INT_BYTES + 1));
+        assert!(serde_json::from_str::<MembershipWitness>(&huge).is_err());
+        let huge = format!(
+            r#"{{"d":"00","b":"-{}"}}"#,
+            "f".repeat(2 * DEFAULT_MAX_INT_BYTES + 1)
+        );
+        assert!(serde_json::from_str::<NonMembershipWitness>(&huge).is_err());
+
+        // values not smaller than the modulus
+        for s in &[
+            r#"{"n":"c51234","g":"c51234","root":"01"}"#,
+            r#"{"n":"c51234","g":"02","root":"c51235"}"#,
+            r#"{"n":"00","g":"00","root":"00"}"#,
+        ] {
+            assert!(serde_json::from_str::<PublicState>(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_binary() {
+        let ser = |x: &BigUint| biguint::serialize(x, BytesSerializer).unwrap();
+        let de = |bytes: &[u8]| biguint::deserialize(BytesDeserializer(bytes));
+
+        for x in &[BigUint::zero(), BigUint::from(0x1234u32)] {
+            assert_eq!(&de(&ser(x)).unwrap(), x);
+        }
+        assert_eq!(ser(&BigUint::from(0x1234u32)), vec![0x12, 0x34]);
+        assert_eq!(de(&[0, 0, 0x12, 0x34]).unwrap(), BigUint::from(0x1234u32));
+
+        let ser = |x: &BigInt| bigint::serialize(x, BytesSerializer).unwrap();
+        let de = |bytes: &[u8]| bigint::deserialize(BytesDeserializer(bytes));
+
+        for x in &[BigInt::zero(), BigInt::from(0x1234), BigInt::from(-0x1234)] {
+            assert_eq!(&de(&ser(x)).unwrap(), x);
+        }
+        assert_eq!(ser(&BigInt::from(-0x1234)), vec![1, 0x12, 0x34]);
+        assert!(de(&[]).is_err());
+        assert!(de(&[2, 1]).is_err());
+        assert!(de(&vec![1; DEFAULT_MAX_INT_BYTES + 1]).is_ok());
+        assert!(de(&vec![1; DEFAULT_MAX_INT_BYTES + 2]).is_err());
+        assert!(
+            biguint::deserialize(BytesDeserializer(&vec![1; DEFAULT_MAX_INT_BYTES + 2])).is_err()
+        );
+
+        // group elements have the width of the modulus
+        let bytes = FixedWidth(&BigUint::from(0x41u32), 3)
+            .serialize(BytesSerializer)
+            .unwrap();
+        assert_eq!(bytes, vec![0, 0, 0x41]);
+    }
+
+    /// A binary serializer for single byte strings.
+    struct BytesSerializer;
+
+    macro_rules! unsupported {
+        ($($name:ident($($ty:ty),*) -> $ret:ty;)*) => {
+            $(fn $name(self, $(_: $ty),*) -> Result<$ret, Error> {
+                Err(ser::Error::custom("unsupported"))
+            })*
+        };
+    }
+
+    impl Serializer for BytesSerializer {
+        type Ok = Vec<u8>;
+        type Error = Error;
+        type SerializeSeq = Impossible<Vec<u8>, Error>;
+        type SerializeTuple = Impossible<Vec<u8>, Error>;
+        type SerializeTupleStruct = Impossible<Vec<u8>, Error>;
+        type SerializeTupleVariant = Impossible<Vec<u8>, Error>;
+        type SerializeMap = Impossible<Vec<u8>, Error>;
+        type SerializeStruct = Impossible<Vec<u8>, Error>;
+        type SerializeStructVariant = Impossible<Vec<u8>, Error>;
+
+        fn is_human_readable(&self) -> bool {
+            false
+        }
+
+        fn serialize_bytes(self, v: &[u8]) -> Result<Vec<u8>, Error> {
+            Ok(v.to_vec())
+        }
+
+        unsupported! {
+            serialize_bool(bool) -> Vec<u8>;
+            serialize_i8(i8) -> Vec<u8>;
+            serialize_i16(i16) -> Vec<u8>;
+            serialize_i32(i32) -> Vec<u8>;
+            serialize_i64(i64) -> Vec<u8>;
+            serialize_u8(u8) -> Vec<u8>;
+            serialize_u16(u16) -> Vec<u8>;
+            serialize_u32(u32) -> Vec<u8>;
+            serialize_u64(u64) -> Vec<u8>;
+            serialize_f32(f32) -> Vec<u8>;
+            serialize_f64(f64) -> Vec<u8>;
+            serialize_char(char) -> Vec<u8>;
+            serialize_str(&str) -> Vec<u8>;
+            serialize_none() -> Vec<u8>;
+            serialize_unit() -> Vec<u8>;
+            serialize_unit_struct(&'static str) -> Vec<u8>;
+            serialize_unit_variant(&'static str, u32, &'static str) -> Vec<u8>;
+            serialize_seq(Option<usize>) -> Self::SerializeSeq;
+            serialize_tuple(usize) -> Self::SerializeTuple;
+            serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+            serialize_tuple_variant(&'static str, u32, &'static str, usize)
+                -> Self::SerializeTupleVariant;
+            serialize_map(Option<usize>) -> Self::SerializeMap;
+            serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+            serialize_struct_variant(&'static str, u32, &'static str, usize)
+                -> Self::SerializeStructVariant;
+        }
+
+        fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<Vec<u8>, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_newtype_struct<T: ?Sized + Serialize>(
+            self,
+            _: &'static str,
+            _: &T,
+        ) -> Result<Vec<u8>, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_newtype_variant<T: ?Sized + Serialize>(
+            self,
+            _: &'static str,
+            _: u32,
+            _: &'static str,
+            _: &T,
+        ) -> Result<Vec<u8>, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+    }
+
+    /// A binary deserializer for single byte strings.
+    struct BytesDeserializer<'a>(&'a [u8]);
+
+    impl<'de, 'a> Deserializer<'de> for BytesDeserializer<'a> {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            visitor.visit_bytes(self.0)
+        }
+
+        fn is_human_readable(&self) -> bool {
+            false
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct enum identifier ignored_any
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! Proofs that two accumulators hold the same set, without revealing it.
+//!
+//! Both accumulators may use different moduli and generators. The proof consists of a
+//! NI-PoKE2 for `g_1^s = root_1` and one for `g_2^s = root_2`, sharing the challenge `l` and the
+//! residue `r = s mod l`. A prover that knows different exponents for both states can only
+//! answer with the same `r` if they happen to agree mod the random prime `l`.
+
+use std::fmt;
+
+use blake2::{Blake2b, Digest};
+use num_bigint::BigUint;
+#[cfg(feature = "prover")]
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+#[cfg(feature = "prover")]
+use crate::accumulator::Accumulator;
+use crate::accumulator::PublicState;
+use crate::encoding::{modulus_bytes, to_fixed_bytes};
+use crate::hash::{hash_group, hash_prime};
+use crate::math::multi_modpow;
+
+const SAME_SET_TAG: &[u8] = b"accumulators/same-set";
+
+/// Proof that two accumulator states commit to the same product `s`.
+///
+/// More generally, a proof that `u_1^s = w_1` and `u_2^s = w_2` for the same `s`, with the
+/// generators as `u_i` and the states as `w_i` here.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SameSetProof {
+    /// `h_1^s`, with `h_1` hashed into the first group.
+    pub z1: BigUint,
+    /// `h_2^s`, with `h_2` hashed into the second group.
+    pub z2: BigUint,
+    /// `(u_1 h_1^alpha)^floor(s/l)` in the first group.
+    pub q1: BigUint,
+    /// `(u_2 h_2^alpha)^floor(s/l)` in the second group.
+    pub q2: BigUint,
+    /// `s mod l`, the same for both groups.
+    pub r: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SameSetError {
+    /// The accumulators hold different sets.
+    SetMismatch,
+}
+
+impl fmt::Display for SameSetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SameSetError::SetMismatch => write!(f, "the accumulators hold different sets"),
+        }
+    }
+}
+
+impl std::error::Error for SameSetError {}
+
+/// Proves that `acc1` and `acc2` hold the same set.
+///
+/// Fails if the products of both sets differ.
+#[cfg(feature = "prover")]
+pub fn prove_same_set(
+    acc1: &Accumulator,
+    acc2: &Accumulator,
+) -> Result<SameSetProof, SameSetError> {
+    let s = acc1.product();
+    if s != acc2.product() {
+        return Err(SameSetError::SetMismatch);
+    }
+
+    let (state1, state2) = (acc1.public_state(), acc2.public_state());
+    let statements = [
+        Statement::new(&state1.n, &state1.g, &state1.root),
+        Statement::new(&state2.n, &state2.g, &state2.root),
+    ];
+
+    Ok(prove_shared_exponent(SAME_SET_TAG, &statements, s))
+}
+
+/// Verifies that the accumulators with the states `state1` and `state2` hold the same set.
+///
+/// Both states carry their own parameters, which may differ.
+pub fn ver_same_set(state1: &PublicState, state2: &PublicState, pi: &SameSetProof) -> bool {
+    let statements = [
+        Statement::new(&state1.n, &state1.g, &state1.root),
+        Statement::new(&state2.n, &state2.g, &state2.root),
+    ];
+
+    ver_shared_exponent(SAME_SET_TAG, &statements, pi)
+}
+
+/// The statement `u^s = w` in the group of `n`, for a secret `s`.
+pub(crate) struct Statement<'a> {
+    n: &'a BigUint,
+    u: &'a BigUint,
+    w: &'a BigUint,
+}
+
+impl<'a> Statement<'a> {
+    pub(crate) fn new(n: &'a BigUint, u: &'a BigUint, w: &'a BigUint) -> Self {
+        Statement { n, u, w }
+    }
+}
+
+/// Proves that both `statements` hold for the same `s`, i.e. `u_1^s = w_1` and `u_2^s = w_2`.
+///
+/// `tag` separates the protocols built on this.
+#[cfg(feature = "prover")]
+pub(crate) fn prove_shared_exponent(
+    tag: &[u8],
+    statements: &[Statement; 2],
+    s: &BigUint,
+) -> SameSetProof {
+    let [st1, st2] = statements;
+    let mut to_hash = transcript(tag, statements);
+    let (h1, h2) = (
+        hash_group::<_, Blake2b>(&to_hash, st1.n),
+        hash_group::<_, Blake2b>(&to_hash, st2.n),
+    );
+
+    // z_i = h_i^s
+    let z1 = h1.modpow(s, st1.n);
+    let z2 = h2.modpow(s, st2.n);
+
+    let (l, alpha) = challenge(&mut to_hash, statements, &z1, &z2).expect("no prime found");
+
+    // q <- floor(s/l), r <- s mod l
+    let (q, r) = s.div_mod_floor(&l);
+
+    // Q_i <- (u_i h_i^alpha)^q
+    let q_big = |st: &Statement, h: &BigUint| (st.u * h.modpow(&alpha, st.n)).modpow(&q, st.n);
+
+    SameSetProof {
+        q1: q_big(st1, &h1),
+        q2: q_big(st2, &h2),
+        z1,
+        z2,
+        r,
+    }
+}
+
+/// Verifies a proof from `prove_shared_exponent`.
+pub(crate) fn ver_shared_exponent(
+    tag: &[u8],
+    statements: &[Statement; 2],
+    pi: &SameSetProof,
+) -> bool {
+    let [st1, st2] = statements;
+
+    // all group elements must be reduced, z_i = h_i^s can only be 1 for s = 0
+    let in_group = |x: &BigUint, n: &BigUint| !x.is_zero() && x < n;
+    let one = BigUint::one();
+    for (st, z, q) in &[(st1, &pi.z1, &pi.q1), (st2, &pi.z2, &pi.q2)] {
+        if !in_group(st.u, st.n) || !in_group(st.w, st.n) {
+            return false;
+        }
+        if *z <= &one || !in_group(z, st.n) || !in_group(q, st.n) {
+            return false;
+        }
+    }
+
+    let mut to_hash = transcript(tag, statements);
+    let (h1, h2) = (
+        hash_group::<_, Blake2b>(&to_hash, st1.n),
+        hash_group::<_, Blake2b>(&to_hash, st2.n),
+    );
+
+    let (l, alpha) = match challenge(&mut to_hash, statements, &pi.z1, &pi.z2) {
+        Some(c) => c,
+        None => return false,
+    };
+    if pi.r >= l {
+        return false;
+    }
+
+    // Q_i^l (u_i h_i^alpha)^r == w_i z_i^alpha
+    let check = |st: &Statement, h: &BigUint, z: &BigUint, q: &BigUint| {
+        let n = st.n;
+        let lhs = multi_modpow(q, &l, &(st.u * h.modpow(&alpha, n)), &pi.r, n);
+        let rhs = (st.w * z.modpow(&alpha, n)) % n;
+        lhs == rhs
+    };
+
+    check(st1, &h1, &pi.z1, &pi.q1) && check(st2, &h2, &pi.z2, &pi.q2)
+}
+
+/// Encodes both statements, each with the width of its modulus.
+fn transcript(tag: &[u8], statements: &[Statement; 2]) -> Vec<u8> {
+    let mut res = tag.to_vec();
+    for st in statements {
+        let len = modulus_bytes(st.n);
+        for x in &[st.n, st.u, st.w] {
+            res.extend(to_fixed_bytes(x, len));
+        }
+    }
+
+    res
+}
+
+/// Extends `to_hash` with the commitments, and derives `l <- H_prime(..)` and
+/// `alpha = H(.., l)` from it.
+fn challenge(
+    to_hash: &mut Vec<u8>,
+    statements: &[Statement; 2],
+    z1: &BigUint,
+    z2: &BigUint,
+) -> Option<(BigUint, BigUint)> {
+    to_hash.extend(to_fixed_bytes(z1, modulus_bytes(statements[0].n)));
+    to_hash.extend(to_fixed_bytes(z2, modulus_bytes(statements[1].n)));
+    let l = hash_prime::<_, Blake2b>(to_hash)?;
+
+    to_hash.extend(&l.to_bytes_be());
+    let alpha = BigUint::from_bytes_be(&Blake2b::digest(to_hash)[..]);
+
+    Some((l, alpha))
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use crate::primes::RandPrimes;
+    use crate::traits::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn setup(rng: &mut ChaChaRng) -> (Accumulator, Accumulator, Vec<BigUint>) {
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc1 = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc2 = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits + 128);
+
+        let xs = rng.gen_distinct_primes(5, 128);
+        acc1.add_batch_no_proof(&xs[..4]);
+        // in a different order
+        for x in xs[..4].iter().rev() {
+            acc2.add(x);
+        }
+
+        (acc1, acc2, xs)
+    }
+
+    #[test]
+    fn test_same_set() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc1, acc2, _) = setup(rng);
+        let (state1, state2) = (acc1.public_state(), acc2.public_state());
+        assert_ne!(state1.n, state2.n);
+
+        let pi = prove_same_set(&acc1, &acc2).unwrap();
+        assert!(ver_same_set(&state1, &state2, &pi));
+        assert!(!ver_same_set(&state2, &state1, &pi));
+
+        // empty sets are equal as well
+        let empty1 = Accumulator::new(state1.n.clone(), state1.g.clone());
+        let empty2 = Accumulator::new(state2.n.clone(), state2.g.clone());
+        let pi = prove_same_set(&empty1, &empty2).unwrap();
+        assert!(ver_same_set(
+            &empty1.public_state(),
+            &empty2.public_state(),
+            &pi
+        ));
+        assert!(!ver_same_set(&state1, &state2, &pi));
+    }
+
+    #[test]
+    fn test_different_sets() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc1, mut acc2, xs) = setup(rng);
+
+        let pi = prove_same_set(&acc1, &acc2).unwrap();
+        acc2.add(&xs[4]);
+        assert_eq!(prove_same_set(&acc1, &acc2), Err(SameSetError::SetMismatch));
+        assert!(!ver_same_set(
+            &acc1.public_state(),
+            &acc2.public_state(),
+            &pi
+        ));
+    }
+
+    #[test]
+    fn test_tampered_proof() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc1, acc2, _) = setup(rng);
+        let (state1, state2) = (acc1.public_state(), acc2.public_state());
+        let pi = prove_same_set(&acc1, &acc2).unwrap();
+
+        let tampered = [
+            SameSetProof {
+                z1: &pi.z1 + 1u32,
+                ..pi.clone()
+            },
+            SameSetProof {
+                z2: &pi.z2 + 1u32,
+                ..pi.clone()
+            },
+            SameSetProof {
+                q1: &pi.q1 + 1u32,
+                ..pi.clone()
+            },
+            SameSetProof {
+                q2: pi.q1.clone(),
+                ..pi.clone()
+            },
+            SameSetProof {
+                r: &pi.r + 1u32,
+                ..pi.clone()
+            },
+            SameSetProof {
+                q1: pi.q1.clone() + &state1.n,
+                ..pi.clone()
+            },
+        ];
+        for t in &tampered {
+            assert!(!ver_same_set(&state1, &state2, t));
+        }
+
+        // other parameters for the same root
+        let mut other = state2.clone();
+        other.g = &other.g + 1u32;
+        assert!(!ver_same_set(&state1, &other, &pi));
+    }
+}
@@ -0,0 +1,537 @@
+//! An accumulator that keeps its elements individually, instead of only their product.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use num_bigint::BigUint;
+use rand::CryptoRng;
+use rand::Rng;
+
+use crate::accumulator::{
+    agg_mem_proof, batch_del_proof, extend_mem_wits, non_mem_proof, non_mem_witness,
+    AccumulatorError, BatchProof, MembershipProof, MembershipWitness, NewState, NonMembershipProof,
+    NonMembershipWitness, OldState, PublicState,
+};
+use crate::math::{product, root_factor, SubproductTree};
+use crate::proofs;
+use crate::snapshot::{merkle_proof, MerkleProof, MerkleSnapshot};
+use crate::traits::*;
+
+/// An RSA accumulator, like `Accumulator`, that stores the accumulated elements instead of
+/// only their product.
+///
+/// Membership checks are lookups, and deletions remove the element instead of dividing the
+/// product. The exponents for witnesses are assembled from a product tree over the elements,
+/// which is built on first use after a change, and shared by all witnesses until the next one.
+///
+/// States, witnesses and proofs are the same as those of an `Accumulator` with the same
+/// parameters and operations. Only the accumulated elements are members though, products of
+/// several of them are not.
+#[derive(Debug, Clone)]
+pub struct FactoredAccumulator {
+    int_size_bits: usize,
+    g: BigUint,
+    n: BigUint,
+    root: BigUint,
+    /// The accumulated elements, with their multiplicity.
+    factors: BTreeMap<BigUint, usize>,
+    /// Product tree over `factors`, sorted, if built since the last change.
+    tree: RefCell<Option<SubproductTree>>,
+}
+
+impl FactoredAccumulator {
+    /// Creates an empty accumulator in `Z_n`, using the generator `g`.
+    ///
+    /// The factorization of `n` must be unknown, for the accumulator to be secure.
+    pub fn new(n: BigUint, g: BigUint) -> Self {
+        FactoredAccumulator {
+            int_size_bits: n.bits(),
+            root: g.clone(),
+            g,
+            n,
+            factors: BTreeMap::new(),
+            tree: RefCell::new(None),
+        }
+    }
+
+    /// Returns the modulus `n` of the underlying group.
+    pub fn modulus(&self) -> &BigUint {
+        &self.n
+    }
+
+    /// Returns the generator `g` the accumulator was initialized with.
+    pub fn generator(&self) -> &BigUint {
+        &self.g
+    }
+
+    /// Returns the public part of the accumulator.
+    pub fn public_state(&self) -> PublicState {
+        PublicState {
+            n: self.n.clone(),
+            g: self.g.clone(),
+            root: self.root.clone(),
+        }
+    }
+
+    /// Returns `true` if `x` is a member.
+    pub fn contains(&self, x: &BigUint) -> bool {
+        self.factors.contains_key(x)
+    }
+
+    /// Returns the number of accumulated elements.
+    pub fn len(&self) -> usize {
+        self.factors.values().sum()
+    }
+
+    /// Returns `true` if no elements are accumulated.
+    pub fn is_empty(&self) -> bool {
+        self.factors.is_empty()
+    }
+
+    /// Creates a membership witness for `x`, like `Accumulator::try_mem_wit_create`.
+    pub fn try_mem_wit_create(&self, x: &BigUint) -> Result<MembershipWitness, AccumulatorError> {
+        let exponent = self
+            .exponent_without(x)
+            .ok_or(AccumulatorError::NotAMember)?;
+
+        Ok(MembershipWitness(self.g.modpow(&exponent, &self.n)))
+    }
+
+    /// Creates a membership proof for `x`, like `Accumulator::try_mem_wit_create_star`.
+    pub fn try_mem_wit_create_star(
+        &self,
+        x: &BigUint,
+    ) -> Result<MembershipProof, AccumulatorError> {
+        let w_x = self.try_mem_wit_create(x)?.0;
+        let pi = proofs::ni_poe_prove(x, &w_x, &self.root, &self.n);
+
+        Ok(MembershipProof { w: w_x, pi })
+    }
+
+    /// Creates a non-membership witness for `x`, like `Accumulator::try_non_mem_wit_create`.
+    pub fn try_non_mem_wit_create(
+        &self,
+        x: &BigUint,
+    ) -> Result<NonMembershipWitness, AccumulatorError> {
+        non_mem_witness(&self.g, &self.n, &self.set(), x)
+    }
+
+    /// Creates a non-membership proof for `x`, like `Accumulator::try_non_mem_wit_create_star`.
+    pub fn try_non_mem_wit_create_star(
+        &self,
+        x: &BigUint,
+    ) -> Result<NonMembershipProof, AccumulatorError> {
+        non_mem_proof(&self.g, &self.n, &self.root, &self.set(), x)
+    }
+
+    /// Returns a Merkle snapshot of the elements, bound to the current state, for auditors
+    /// enumerating the set, see `verify_snapshot`.
+    pub fn export_merkle_snapshot(&self) -> MerkleSnapshot {
+        let _span = span!("export_merkle_snapshot", elements = self.len());
+        self.with_tree(|tree| MerkleSnapshot::new(self.public_state(), tree.leaves()))
+    }
+
+    /// Returns a proof that `x` is in the snapshot of `export_merkle_snapshot`, if `x` is a
+    /// member. Verified with `MerkleSnapshot::ver_inclusion`.
+    pub fn merkle_proof(&self, x: &BigUint) -> Option<MerkleProof> {
+        if !self.contains(x) {
+            return None;
+        }
+
+        self.with_tree(|tree| {
+            let i = tree.leaves().binary_search(x).ok()?;
+            Some(merkle_proof(tree.leaves(), i))
+        })
+    }
+
+    /// Runs `f` on the product tree, building it first if needed.
+    fn with_tree<T>(&self, f: impl FnOnce(&SubproductTree) -> T) -> T {
+        let mut tree = self.tree.borrow_mut();
+        let tree = tree.get_or_insert_with(|| {
+            let leaves = self
+                .factors
+                .iter()
+                .flat_map(|(x, &count)| std::iter::repeat(x.clone()).take(count))
+                .collect::<Vec<_>>();
+            SubproductTree::build(&leaves)
+        });
+
+        f(tree)
+    }
+
+    /// Returns the product of all elements.
+    fn set(&self) -> BigUint {
+        self.with_tree(SubproductTree::product)
+    }
+
+    /// Returns the product of all elements but one occurrence of `x`, if `x` is a member.
+    fn exponent_without(&self, x: &BigUint) -> Option<BigUint> {
+        if !self.contains(x) {
+            return None;
+        }
+
+        self.with_tree(|tree| {
+            let i = tree.leaves().binary_search(x).ok()?;
+            Some(tree.product_excluding(i))
+        })
+    }
+
+    fn insert(&mut self, x: &BigUint) {
+        *self.factors.entry(x.clone()).or_insert(0) += 1;
+        *self.tree.get_mut() = None;
+    }
+
+    fn remove(&mut self, x: &BigUint) {
+        if let Some(count) = self.factors.get_mut(x) {
+            *count -= 1;
+            if *count == 0 {
+                self.factors.remove(x);
+            }
+        }
+        *self.tree.get_mut() = None;
+    }
+}
+
+impl AccumulatorSetup for FactoredAccumulator {
+    fn setup<T, R>(rng: &mut R, int_size_bits: usize) -> Self
+    where
+        T: PrimeGroup,
+        R: CryptoRng + Rng,
+    {
+        let setup = T::generate_primes(rng, int_size_bits).unwrap();
+
+        let mut acc = FactoredAccumulator::new(setup.n.clone(), setup.g.clone());
+        acc.int_size_bits = int_size_bits;
+        acc
+    }
+}
+
+impl StaticAccumulator for FactoredAccumulator {
+    type MemWitness = MembershipWitness;
+
+    fn state(&self) -> &BigUint {
+        &self.root
+    }
+
+    fn add(&mut self, x: &BigUint) {
+        self.insert(x);
+        self.root = self.root.modpow(x, &self.n);
+    }
+
+    fn mem_wit_create(&self, x: &BigUint) -> MembershipWitness {
+        self.try_mem_wit_create(x)
+            .expect("x was not a valid member of set")
+    }
+
+    fn ver_mem(&self, w: &MembershipWitness, x: &BigUint) -> bool {
+        self.public_state().ver_mem(w, x)
+    }
+}
+
+impl DynamicAccumulator for FactoredAccumulator {
+    fn del(&mut self, x: &BigUint) -> Option<()> {
+        let exponent = self.exponent_without(x)?;
+
+        self.remove(x);
+        self.root = self.g.modpow(&exponent, &self.n);
+        Some(())
+    }
+}
+
+impl UniversalAccumulator for FactoredAccumulator {
+    type NonMemWitness = NonMembershipWitness;
+
+    fn non_mem_wit_create(&self, x: &BigUint) -> NonMembershipWitness {
+        self.try_non_mem_wit_create(x)
+            .expect("x was a member of set")
+    }
+
+    fn ver_non_mem(&self, w: &NonMembershipWitness, x: &BigUint) -> bool {
+        self.public_state().ver_non_mem(w, x)
+    }
+}
+
+impl BatchUpdateAccumulator for FactoredAccumulator {
+    type BatchProof = BatchProof;
+
+    fn batch_add(&mut self, xs: &[BigUint]) -> BatchProof {
+        let root_t = self.root.clone();
+        let x_star = product(xs);
+
+        for x in xs {
+            self.insert(x);
+        }
+        self.root = self.root.modpow(&x_star, &self.n);
+
+        BatchProof(proofs::ni_poe_prove(&x_star, &root_t, &self.root, &self.n))
+    }
+
+    fn ver_batch_add(&self, w: &BatchProof, root: &BigUint, xs: &[BigUint]) -> bool {
+        let new = NewState(self.state().clone());
+        self.public_state()
+            .ver_batch_add_transition(w, &OldState(root.clone()), &new, xs)
+    }
+
+    fn ver_batch_add_transition(
+        &self,
+        w: &BatchProof,
+        old: &OldState,
+        new: &NewState,
+        xs: &[BigUint],
+    ) -> bool {
+        self.public_state()
+            .ver_batch_add_transition(w, old, new, xs)
+    }
+
+    fn batch_del(&mut self, pairs: &[(BigUint, MembershipWitness)]) -> Option<BatchProof> {
+        if pairs.is_empty() || pairs.iter().any(|(x, _)| !self.contains(x)) {
+            return None;
+        }
+        let (root, proof) = batch_del_proof(&self.n, &self.root, pairs);
+
+        for (x, _) in pairs {
+            self.remove(x);
+        }
+        self.root = root;
+
+        Some(proof)
+    }
+
+    fn ver_batch_del(&self, w: &BatchProof, root: &BigUint, xs: &[BigUint]) -> bool {
+        let new = NewState(self.state().clone());
+        self.public_state()
+            .ver_batch_del_transition(w, &OldState(root.clone()), &new, xs)
+    }
+
+    fn ver_batch_del_transition(
+        &self,
+        w: &BatchProof,
+        old: &OldState,
+        new: &NewState,
+        xs: &[BigUint],
+    ) -> bool {
+        self.public_state()
+            .ver_batch_del_transition(w, old, new, xs)
+    }
+
+    fn del_w_mem(&mut self, w: &MembershipWitness, x: &BigUint) -> Option<()> {
+        if !self.contains(x) || !self.ver_mem(w, x) {
+            return None;
+        }
+
+        self.remove(x);
+        self.root = w.0.clone();
+        Some(())
+    }
+
+    fn create_all_mem_wit(&self, set: &[BigUint]) -> Vec<MembershipWitness> {
+        root_factor(&self.g, set, &self.n)
+            .into_iter()
+            .map(MembershipWitness)
+            .collect()
+    }
+
+    fn extend_all_mem_wit(
+        &self,
+        existing: &mut Vec<(BigUint, MembershipWitness)>,
+        new_elements: &[BigUint],
+    ) {
+        extend_mem_wits(&self.g, &self.n, existing, new_elements)
+    }
+}
+
+impl AggregatableAccumulator for FactoredAccumulator {
+    type MemProof = MembershipProof;
+    type NonMemProof = NonMembershipProof;
+
+    fn agg_mem_wit(
+        &self,
+        w_x: &MembershipWitness,
+        w_y: &MembershipWitness,
+        x: &BigUint,
+        y: &BigUint,
+    ) -> MembershipProof {
+        agg_mem_proof(&self.n, &self.root, w_x, w_y, x, y)
+    }
+
+    fn ver_agg_mem_wit(&self, pi: &MembershipProof, x: &BigUint, y: &BigUint) -> bool {
+        self.public_state().ver_agg_mem_wit(pi, x, y)
+    }
+
+    fn mem_wit_create_star(&self, x: &BigUint) -> MembershipProof {
+        self.try_mem_wit_create_star(x)
+            .expect("x was not a valid member of set")
+    }
+
+    fn ver_mem_star(&self, x: &BigUint, pi: &MembershipProof) -> bool {
+        self.public_state().ver_mem_star(x, pi)
+    }
+
+    fn mem_wit_x(
+        &self,
+        _other: &BigUint,
+        w_x: &MembershipWitness,
+        w_y: &MembershipWitness,
+        _x: &BigUint,
+        _y: &BigUint,
+    ) -> MembershipWitness {
+        MembershipWitness((&w_x.0 * &w_y.0) % &self.n)
+    }
+
+    fn ver_mem_x(&self, other: &BigUint, pi: &MembershipWitness, x: &BigUint, y: &BigUint) -> bool {
+        self.public_state().ver_mem_x(other, pi, x, y)
+    }
+
+    fn non_mem_wit_create_star(&self, x: &BigUint) -> NonMembershipProof {
+        self.try_non_mem_wit_create_star(x)
+            .expect("x was a member of set")
+    }
+
+    fn ver_non_mem_star(&self, x: &BigUint, pi: &NonMembershipProof) -> bool {
+        self.public_state().ver_non_mem_star(x, pi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use num_bigint::RandPrime;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_factored_matches_accumulator() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut fac = FactoredAccumulator::new(acc.modulus().clone(), acc.generator().clone());
+        let mut members: Vec<BigUint> = Vec::new();
+
+        for _ in 0..60 {
+            match rng.gen_range(0, 6) {
+                0 => {
+                    let x = rng.gen_prime(128);
+                    acc.add(&x);
+                    fac.add(&x);
+                    members.push(x);
+                }
+                1 => {
+                    let xs = (0..rng.gen_range(1, 5))
+                        .map(|_| rng.gen_prime(128))
+                        .collect::<Vec<_>>();
+                    assert_eq!(acc.batch_add(&xs), fac.batch_add(&xs));
+                    members.extend(xs);
+                }
+                2 if !members.is_empty() => {
+                    let x = members.swap_remove(rng.gen_range(0, members.len()));
+                    assert_eq!(acc.del(&x), Some(()));
+                    assert_eq!(fac.del(&x), Some(()));
+                }
+                3 if members.len() > 2 => {
+                    members.shuffle(rng);
+                    let xs = members.split_off(members.len() - 2);
+                    let pairs = xs
+                        .iter()
+                        .map(|x| (x.clone(), fac.mem_wit_create(x)))
+                        .collect::<Vec<_>>();
+                    assert_eq!(acc.batch_del(&pairs), fac.batch_del(&pairs));
+                }
+                4 if !members.is_empty() => {
+                    let x = members.swap_remove(rng.gen_range(0, members.len()));
+                    let w = acc.mem_wit_create(&x);
+                    assert_eq!(acc.del_w_mem(&w, &x), Some(()));
+                    assert_eq!(fac.del_w_mem(&w, &x), Some(()));
+                }
+                _ => {
+                    let y = rng.gen_prime(128);
+                    assert_eq!(acc.del(&y), None);
+                    assert_eq!(fac.del(&y), None);
+                }
+            }
+
+            assert_eq!(fac.state(), acc.state());
+            assert_eq!(fac.len(), acc.len());
+            assert_eq!(fac.is_empty(), members.is_empty());
+
+            for x in members.iter().take(3) {
+                assert!(fac.contains(x));
+                assert_eq!(fac.mem_wit_create(x), acc.mem_wit_create(x));
+                assert_eq!(fac.mem_wit_create_star(x), acc.mem_wit_create_star(x));
+            }
+            if members.len() >= 2 {
+                let (x, y) = (&members[0], &members[1]);
+                let (w_x, w_y) = (fac.mem_wit_create(x), fac.mem_wit_create(y));
+                assert_eq!(
+                    fac.agg_mem_wit(&w_x, &w_y, x, y),
+                    acc.agg_mem_wit(&w_x, &w_y, x, y)
+                );
+            }
+
+            let y = rng.gen_prime(128);
+            assert!(!fac.contains(&y));
+            assert_eq!(fac.non_mem_wit_create(&y), acc.non_mem_wit_create(&y));
+            assert_eq!(
+                fac.non_mem_wit_create_star(&y),
+                acc.non_mem_wit_create_star(&y)
+            );
+            assert_eq!(
+                fac.try_mem_wit_create(&y),
+                Err(AccumulatorError::NotAMember)
+            );
+        }
+    }
+
+    #[test]
+    fn test_factored_members() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = FactoredAccumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..3).map(|_| rng.gen_prime(128)).collect::<Vec<_>>();
+        acc.batch_add(&xs);
+
+        let mut ws = vec![(xs[0].clone(), MembershipWitness(acc.g.clone()))];
+        acc.extend_all_mem_wit(&mut ws, &xs[1..]);
+        assert_eq!(
+            ws.into_iter().map(|(_, w)| w).collect::<Vec<_>>(),
+            acc.create_all_mem_wit(&xs)
+        );
+
+        // only elements are members, not their products
+        let xy = &xs[0] * &xs[1];
+        assert!(!acc.contains(&xy));
+        assert_eq!(
+            acc.try_mem_wit_create(&xy),
+            Err(AccumulatorError::NotAMember)
+        );
+        assert_eq!(acc.del(&xy), None);
+        assert_eq!(
+            acc.try_non_mem_wit_create(&xy),
+            Err(AccumulatorError::IsMember)
+        );
+
+        // witnesses stay valid while the tree is reused, and after it is rebuilt
+        let w = acc.mem_wit_create(&xs[0]);
+        assert!(acc.ver_mem(&w, &xs[0]));
+        assert!(acc.ver_mem(&acc.mem_wit_create(&xs[2]), &xs[2]));
+        acc.del(&xs[1]).unwrap();
+        assert!(!acc.ver_mem(&w, &xs[0]));
+        assert!(acc.ver_mem(&acc.mem_wit_create(&xs[0]), &xs[0]));
+        assert_eq!(acc.len(), 2);
+
+        // a batch deletion with a non-member changes nothing
+        let pairs = vec![
+            (xs[0].clone(), acc.mem_wit_create(&xs[0])),
+            (xs[1].clone(), w),
+        ];
+        let root = acc.state().clone();
+        assert_eq!(acc.batch_del(&pairs), None);
+        assert_eq!(acc.state(), &root);
+        assert_eq!(acc.len(), 2);
+    }
+}
@@ -0,0 +1,277 @@
+//! Immutable snapshots of an accumulator, that can be shared between threads.
+
+use std::sync::{Arc, OnceLock};
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+use crate::accumulator::{
+    mem_proof, mem_witness, non_mem_proof, non_mem_witness, AccumulatorError, MembershipProof,
+    MembershipWitness, NonMembershipProof, NonMembershipWitness, PublicState,
+};
+use crate::proofs::GroupParams;
+use crate::setup::SetupParams;
+
+/// A read-only snapshot of an `Accumulator`, created by `Accumulator::freeze`.
+///
+/// The snapshot is `Send + Sync` and cloning it only bumps a reference count, so a single
+/// snapshot can serve witnesses and proofs from many threads at once, while the original
+/// accumulator keeps changing. It shares the parameters of the accumulator, instead of copying
+/// them.
+#[derive(Debug, Clone)]
+pub struct FrozenAccumulator {
+    inner: Arc<Snapshot>,
+}
+
+#[derive(Debug)]
+struct Snapshot {
+    params: Arc<SetupParams>,
+    root: BigUint,
+    set: BigUint,
+    /// The public state for the verifiers, only copied out of `params` when first needed.
+    public: OnceLock<PublicState>,
+}
+
+impl FrozenAccumulator {
+    pub(crate) fn new(params: Arc<SetupParams>, root: BigUint, set: BigUint) -> Self {
+        FrozenAccumulator {
+            inner: Arc::new(Snapshot {
+                params,
+                root,
+                set,
+                public: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Returns the parameters of the accumulator, shared with it.
+    pub fn params(&self) -> &Arc<SetupParams> {
+        &self.inner.params
+    }
+
+    /// Returns the public part of the snapshot.
+    pub fn public_state(&self) -> &PublicState {
+        self.inner.public.get_or_init(|| PublicState {
+            n: self.inner.params.n.clone(),
+            g: self.inner.params.g.clone(),
+            root: self.inner.root.clone(),
+        })
+    }
+
+    /// Returns the accumulator state at the time of the snapshot.
+    pub fn state(&self) -> &BigUint {
+        &self.inner.root
+    }
+
+    /// Returns `true` if `x` is a member.
+    pub fn contains(&self, x: &BigUint) -> bool {
+        !x.is_zero() && !x.is_one() && self.inner.set.is_multiple_of(x)
+    }
+
+    /// Creates the membership witness for `x`, see `StaticAccumulator::mem_wit_create`.
+    pub fn mem_wit_create(&self, x: &BigUint) -> MembershipWitness {
+        self.try_mem_wit_create(x)
+            .expect("x was not a valid member of set")
+    }
+
+    /// Creates the membership witness for `x`, see `Accumulator::try_mem_wit_create`.
+    pub fn try_mem_wit_create(&self, x: &BigUint) -> Result<MembershipWitness, AccumulatorError> {
+        let SetupParams { n, g, .. } = &*self.inner.params;
+
+        mem_witness(g, n, &self.inner.set, x)
+    }
+
+    /// Verifies a membership witness for `x`, see `PublicState::ver_mem`.
+    pub fn ver_mem(&self, w: &MembershipWitness, x: &BigUint) -> bool {
+        self.public_state().ver_mem(w, x)
+    }
+
+    /// Creates the non-membership witness for `x`, see `UniversalAccumulator::non_mem_wit_create`.
+    pub fn non_mem_wit_create(&self, x: &BigUint) -> NonMembershipWitness {
+        self.try_non_mem_wit_create(x)
+            .expect("x was a member of set")
+    }
+
+    /// Creates the non-membership witness for `x`, see `Accumulator::try_non_mem_wit_create`.
+    pub fn try_non_mem_wit_create(
+        &self,
+        x: &BigUint,
+    ) -> Result<NonMembershipWitness, AccumulatorError> {
+        let SetupParams { n, g, .. } = &*self.inner.params;
+
+        non_mem_witness(g, n, &self.inner.set, x)
+    }
+
+    /// Verifies a non-membership witness for `x`, see `PublicState::ver_non_mem`.
+    pub fn ver_non_mem(&self, w: &NonMembershipWitness, x: &BigUint) -> bool {
+        self.public_state().ver_non_mem(w, x)
+    }
+
+    /// Creates the membership proof for `x`, see `AggregatableAccumulator::mem_wit_create_star`.
+    pub fn mem_wit_create_star(&self, x: &BigUint) -> MembershipProof {
+        self.try_mem_wit_create_star(x)
+            .expect("x was not a valid member of set")
+    }
+
+    /// Creates the membership proof for `x`, see `Accumulator::try_mem_wit_create_star`.
+    pub fn try_mem_wit_create_star(
+        &self,
+        x: &BigUint,
+    ) -> Result<MembershipProof, AccumulatorError> {
+        let w_x = self.try_mem_wit_create(x)?;
+        let params = GroupParams::new(self.inner.params.n.clone());
+
+        Ok(mem_proof(&params, &self.inner.root, x, w_x))
+    }
+
+    /// Verifies a membership proof for `x`, see `PublicState::ver_mem_star`.
+    pub fn ver_mem_star(&self, x: &BigUint, pi: &MembershipProof) -> bool {
+        self.public_state().ver_mem_star(x, pi)
+    }
+
+    /// Creates the non-membership proof for `x`, see `AggregatableAccumulator::non_mem_wit_create_star`.
+    pub fn non_mem_wit_create_star(&self, x: &BigUint) -> NonMembershipProof {
+        self.try_non_mem_wit_create_star(x)
+            .expect("x was a member of set")
+    }
+
+    /// Creates the non-membership proof for `x`, see `Accumulator::try_non_mem_wit_create_star`.
+    pub fn try_non_mem_wit_create_star(
+        &self,
+        x: &BigUint,
+    ) -> Result<NonMembershipProof, AccumulatorError> {
+        let SetupParams { n, g, .. } = &*self.inner.params;
+
+        non_mem_proof(g, n, &self.inner.root, &self.inner.set, x)
+    }
+
+    /// Verifies a non-membership proof for `x`, see `PublicState::ver_non_mem_star`.
+    pub fn ver_non_mem_star(&self, x: &BigUint, pi: &NonMembershipProof) -> bool {
+        self.public_state().ver_non_mem_star(x, pi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_frozen_send_sync() {
+        assert_send_sync::<FrozenAccumulator>();
+    }
+
+    #[test]
+    fn test_frozen_matches_accumulator() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = (0..4).map(|_| rng.gen_prime(128)).collect::<Vec<_>>();
+        acc.batch_add(&xs);
+        let y = rng.gen_prime(128);
+
+        let frozen = acc.freeze();
+        assert_eq!(frozen.public_state(), &acc.public_state());
+        assert!(frozen.contains(&xs[0]));
+        assert!(!frozen.contains(&y));
+        assert!(!frozen.contains(&BigUint::zero()));
+        assert!(!frozen.contains(&BigUint::one()));
+
+        assert_eq!(frozen.mem_wit_create(&xs[0]), acc.mem_wit_create(&xs[0]));
+        assert_eq!(frozen.non_mem_wit_create(&y), acc.non_mem_wit_create(&y));
+        assert_eq!(
+            frozen.mem_wit_create_star(&xs[1]),
+            acc.mem_wit_create_star(&xs[1])
+        );
+        assert_eq!(
+            frozen.non_mem_wit_create_star(&y),
+            acc.non_mem_wit_create_star(&y)
+        );
+
+        let pi = frozen.non_mem_wit_create_star(&y);
+        assert!(frozen.ver_non_mem_star(&y, &pi));
+        let pi = frozen.mem_wit_create_star(&xs[1]);
+        assert!(frozen.ver_mem_star(&xs[1], &pi));
+
+        assert_eq!(
+            frozen.try_mem_wit_create(&y),
+            Err(AccumulatorError::NotAMember)
+        );
+        assert_eq!(
+            frozen.try_mem_wit_create_star(&y),
+            Err(AccumulatorError::NotAMember)
+        );
+        assert_eq!(
+            frozen.try_non_mem_wit_create(&xs[0]),
+            Err(AccumulatorError::IsMember)
+        );
+        assert_eq!(
+            frozen.try_non_mem_wit_create_star(&xs[0]),
+            Err(AccumulatorError::IsMember)
+        );
+    }
+
+    #[test]
+    fn test_frozen_concurrent() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = (0..8).map(|_| rng.gen_prime(128)).collect::<Vec<_>>();
+        acc.batch_add(&xs);
+        let ys = (0..8).map(|_| rng.gen_prime(128)).collect::<Vec<_>>();
+
+        let frozen = acc.freeze();
+        let expected = xs
+            .iter()
+            .map(|x| frozen.mem_wit_create(x))
+            .collect::<Vec<_>>();
+
+        let handles = (0..8)
+            .map(|i| {
+                let frozen = frozen.clone();
+                let xs = xs.clone();
+                let ys = ys.clone();
+                let expected = expected.clone();
+                thread::spawn(move || {
+                    for round in 0..4 {
+                        let j = (i + round) % xs.len();
+                        let w = frozen.mem_wit_create(&xs[j]);
+                        assert_eq!(w, expected[j]);
+                        assert!(frozen.ver_mem(&w, &xs[j]));
+
+                        let u = frozen.non_mem_wit_create(&ys[j]);
+                        assert!(frozen.ver_non_mem(&u, &ys[j]));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // the original keeps changing in the meantime
+        for (x, y) in xs.iter().zip(&ys) {
+            acc.add(y);
+            acc.del(x).unwrap();
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_ne!(acc.state(), frozen.state());
+        for (x, w) in xs.iter().zip(&expected) {
+            assert_eq!(&frozen.mem_wit_create(x), w);
+        }
+    }
+}
@@ -1,5 +1,10 @@
 //! Prime Group Backend for our Accumulstors
 
+mod setup;
+
+pub(crate) use self::setup::zeroize;
+pub use self::setup::RsaSetup;
+
 //
 //Classgroup Backend
 //
@@ -16,4 +21,4 @@ pub use self::classgroup::ClassGroup;
 #[cfg(feature = "rsa_group")]
 mod rsa;
 #[cfg(feature = "rsa_group")]
-pub use self::rsa::RSAGroup;
+pub use self::rsa::{ModulusKind, RSAGroup, StrongPrimeSizes};
@@ -1,93 +1,270 @@
+use crate::group::RsaSetup;
+use crate::primality::{is_probable_prime, PrimalityConfig};
 use crate::traits::PrimeGroup;
 use failure::{bail, Error};
 use num_bigint::traits::ModInverse;
-use num_bigint::{BigUint, RandPrime};
-use num_traits::{FromPrimitive, One, Zero};
+use num_bigint::{BigUint, RandBigInt, RandPrime};
+use num_traits::{FromPrimitive, One};
 use rand::CryptoRng;
 use rand::Rng;
 
 pub struct RSAGroup;
 
-impl PrimeGroup for RSAGroup {
-    // Based on https://github.com/RustCrypto/RSA/blob/master/src/algorithms.rs
-    fn generate_primes<R: Rng + CryptoRng>(
+/// How the primes of the modulus are chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModulusKind {
+    /// Random primes, the default.
+    #[default]
+    Standard,
+    /// Strong primes from Gordon's algorithm, for regimes that require them.
+    ///
+    /// Each prime `p` has a large prime factor `r` of `p - 1` and `s` of `p + 1`, and `r - 1`
+    /// has a large prime factor `t`. This needs three more prime searches per prime, for the
+    /// auxiliary primes. Those are small, so for 2048 bit moduli generation takes about as
+    /// long as `Standard`, but with more variance.
+    Strong(StrongPrimeSizes),
+}
+
+/// Sizes of the auxiliary primes of Gordon's algorithm, in bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrongPrimeSizes {
+    /// Size of `r`, the large prime factor of `p - 1`.
+    pub r_bits: usize,
+    /// Size of `s`, the large prime factor of `p + 1`.
+    pub s_bits: usize,
+    /// Size of `t`, the large prime factor of `r - 1`. Must be smaller than `r_bits`.
+    pub t_bits: usize,
+}
+
+impl StrongPrimeSizes {
+    /// Sizes for a modulus of `bits` bits: `r` and `s` of `bits / 8` bits, `t` of 3/4 of that.
+    ///
+    /// For 2048 to 4096 bit moduli this gives auxiliary primes of 256 to 512 bits, above the
+    /// minimums of FIPS 186-4, table B.1.
+    pub fn for_modulus(bits: usize) -> Self {
+        let aux = bits / 8;
+        StrongPrimeSizes {
+            r_bits: aux,
+            s_bits: aux,
+            t_bits: aux * 3 / 4,
+        }
+    }
+}
+
+impl RSAGroup {
+    /// Smallest modulus `generate_primes` generates, in bits.
+    pub const MIN_MODULUS_BITS: usize = 64;
+
+    /// Generates a modulus like `generate_primes`, with primes of the given `kind`.
+    ///
+    /// Fails if `bit_size` is smaller than `MIN_MODULUS_BITS`, or for strong primes, if the
+    /// auxiliary primes don't fit into primes of `floor(bit_size / 2)` bits, with 8 bits to
+    /// spare.
+    pub fn generate_primes_with<R: Rng + CryptoRng>(
         rng: &mut R,
         bit_size: usize,
-    ) -> Result<(BigUint, BigUint), Error> {
+        kind: ModulusKind,
+    ) -> Result<RsaSetup, Error> {
         // Default exponent for RSA keys.
         const EXP: u64 = 65547;
 
-        if bit_size < 64 {
-            bail!("too few bits");
+        if bit_size < Self::MIN_MODULUS_BITS {
+            bail!(
+                "a modulus needs at least {} bits, not {}",
+                Self::MIN_MODULUS_BITS,
+                bit_size
+            );
         }
+        if let ModulusKind::Strong(sizes) = kind {
+            if sizes.t_bits < 2 || sizes.s_bits < 2 || sizes.t_bits >= sizes.r_bits {
+                bail!("invalid auxiliary prime sizes {:?}", sizes);
+            }
+            if sizes.r_bits + sizes.s_bits + 8 > bit_size / 2 {
+                bail!(
+                    "auxiliary primes {:?} are too large for a {} bit modulus",
+                    sizes,
+                    bit_size
+                );
+            }
+        }
+        let _span = span!("generate_primes", bits = bit_size);
+
+        // Primes of exactly `bits` bits, with the top two bits set.
+        let mut gen_prime = |bits: usize| match kind {
+            ModulusKind::Standard => rng.gen_prime(bits),
+            ModulusKind::Strong(sizes) => gen_strong_prime(rng, bits, &sizes).p,
+        };
 
-        let nprimes = 2;
-        let mut primes = vec![BigUint::zero(); nprimes];
-        let n_final: BigUint;
-        // let d_final: BigUint;
-
-        'next: loop {
-            let mut todo = bit_size;
-            // `gen_prime` should set the top two bits in each prime.
-            // Thus each prime has the form
-            //   p_i = 2^bitlen(p_i) × 0.11... (in base 2).
-            // And the product is:
-            //   P = 2^todo × α
-            // where α is the product of nprimes numbers of the form 0.11...
-            //
-            // If α < 1/2 (which can happen for nprimes > 2), we need to
-            // shift todo to compensate for lost bits: the mean value of 0.11...
-            // is 7/8, so todo + shift - nprimes * log2(7/8) ~= bits - 1/2
-            // will give good results.
-            if nprimes >= 7 {
-                todo += (nprimes - 2) / 5;
+        let exp = BigUint::from_u64(EXP).expect("invalid static exponent");
+        let (n, p, q) = loop {
+            // Both primes have their top two bits set, so `p >= 3/4 * 2^p_bits`, and the same
+            // for `q`. Their product is at least `9/16 * 2^bit_size`, so it never comes out a
+            // bit short.
+            let p = gen_prime(bit_size / 2);
+            let q = gen_prime(bit_size - bit_size / 2);
+            if p == q {
+                continue;
             }
 
-            for (i, prime) in primes.iter_mut().enumerate() {
-                *prime = rng.gen_prime(todo / (nprimes - i));
-                todo -= prime.bits();
+            let n = &p * &q;
+            if n.bits() != bit_size {
+                continue;
             }
 
-            // Makes sure that primes is pairwise unequal.
-            for (i, prime1) in primes.iter().enumerate() {
-                for prime2 in primes.iter().take(i) {
-                    if prime1 == prime2 {
-                        continue 'next;
-                    }
-                }
+            let totient = (&p - BigUint::one()) * (&q - BigUint::one());
+            if exp.clone().mod_inverse(totient).is_some() {
+                break (n, p, q);
             }
+        };
 
-            let mut n = BigUint::one();
-            let mut totient = BigUint::one();
+        // This is a trusted setup, as we do know `p` and `q`. They are returned to the
+        // caller, who decides whether to keep them.
+        RsaSetup::with_primes(n, exp, p, q)
+    }
+}
 
-            for prime in &primes {
-                n *= prime;
-                totient *= prime - BigUint::one();
-            }
+impl PrimeGroup for RSAGroup {
+    // Based on https://github.com/RustCrypto/RSA/blob/master/src/algorithms.rs
+    /// Generates a modulus `n = p * q` of exactly `bit_size` bits, with `p` of
+    /// `floor(bit_size / 2)` and `q` of `ceil(bit_size / 2)` bits.
+    ///
+    /// Fails if `bit_size` is smaller than `MIN_MODULUS_BITS`.
+    fn generate_primes<R: Rng + CryptoRng>(
+        rng: &mut R,
+        bit_size: usize,
+    ) -> Result<RsaSetup, Error> {
+        Self::generate_primes_with(rng, bit_size, ModulusKind::Standard)
+    }
+}
 
-            if n.bits() != bit_size {
-                // This should never happen for nprimes == 2 because
-                // gen_prime should set the top two bits in each prime.
-                // For nprimes > 2 we hope it does not happen often.
-                continue 'next;
+/// A strong prime, with the auxiliary primes it was built from.
+struct StrongPrime {
+    p: BigUint,
+    // only checked by the tests
+    #[allow(dead_code)]
+    r: BigUint,
+    #[allow(dead_code)]
+    s: BigUint,
+    #[allow(dead_code)]
+    t: BigUint,
+}
+
+/// Gordon's algorithm: returns a prime `p` of exactly `bits` bits with the top two bits set,
+/// with `r | p - 1`, `s | p + 1` and `t | r - 1`.
+///
+/// `sizes` must leave room for `2 r s` to fit into `p` many times, see `generate_primes_with`.
+fn gen_strong_prime<R: Rng + ?Sized>(
+    rng: &mut R,
+    bits: usize,
+    sizes: &StrongPrimeSizes,
+) -> StrongPrime {
+    let cfg = PrimalityConfig::default();
+    let one = BigUint::one();
+    let two = BigUint::from(2u32);
+
+    // the range of p, [3/4 * 2^bits, 2^bits)
+    let lower = BigUint::from(3u32) << (bits - 2);
+    let upper = &one << bits;
+
+    loop {
+        let s = rng.gen_prime(sizes.s_bits);
+        let t = rng.gen_prime(sizes.t_bits);
+
+        // r <- the first prime 2 i t + 1, with r of at least r_bits bits
+        let mut i = &one << (sizes.r_bits - sizes.t_bits - 1);
+        let mut r = &two * &i * &t + &one;
+        while !is_probable_prime(&r, &cfg) {
+            i += 1u32;
+            r = &two * &i * &t + &one;
+        }
+
+        // r came out larger than r_bits, which only happens for tiny sizes
+        let rs2 = &two * &r * &s;
+        if r == s || rs2.bits() + 8 > bits {
+            continue;
+        }
+
+        // p_0 <- 2 (s^(r - 2) mod r) s - 1, so p_0 = 1 mod r and p_0 = -1 mod s
+        let p0 = &two * s.modpow(&(&r - &two), &r) * &s - &one;
+
+        // p <- p_0 + 2 j r s, from a random j in range, searched upwards
+        let j_min = (&lower - &p0 + &rs2 - &one) / &rs2;
+        let j_max = (&upper - &p0) / &rs2;
+        let mut p = &p0 + &rs2 * rng.gen_biguint_range(&j_min, &j_max);
+        while p < upper {
+            if is_probable_prime(&p, &cfg) {
+                return StrongPrime { p, r, s, t };
             }
+            p += &rs2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use num_traits::Zero;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_gen_strong_prime() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let cfg = PrimalityConfig::default();
+        let sizes = StrongPrimeSizes {
+            r_bits: 40,
+            s_bits: 40,
+            t_bits: 30,
+        };
 
-            let exp = BigUint::from_u64(EXP).expect("invalid static exponent");
-            if let Some(_d) = exp.mod_inverse(totient) {
-                n_final = n;
-                // d_final = d;
-                break;
+        for &bits in &[128, 129, 160] {
+            let StrongPrime { p, r, s, t } = gen_strong_prime(rng, bits, &sizes);
+            assert_eq!(p.bits(), bits);
+            assert_eq!(&p >> (bits - 2), BigUint::from(3u32));
+
+            for x in &[&p, &r, &s, &t] {
+                assert!(is_probable_prime(x, &cfg), "{} is not a prime", x);
             }
+            assert!(r.bits() >= sizes.r_bits);
+            assert_eq!(s.bits(), sizes.s_bits);
+            assert_eq!(t.bits(), sizes.t_bits);
+
+            let one = BigUint::one();
+            assert!(((&p - &one) % &r).is_zero());
+            assert!(((&p + &one) % &s).is_zero());
+            assert!(((&r - &one) % &t).is_zero());
         }
+    }
 
-        // This is a trusted setup, as we do know `p` and `q`, even though
-        // we choose not to store them.
-        let _q = primes.pop().unwrap();
-        let _p = primes.pop().unwrap();
+    #[test]
+    fn test_generate_strong_modulus() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let kind = ModulusKind::Strong(StrongPrimeSizes::for_modulus(512));
+        assert_eq!(
+            StrongPrimeSizes::for_modulus(2048),
+            StrongPrimeSizes {
+                r_bits: 256,
+                s_bits: 256,
+                t_bits: 192
+            }
+        );
+
+        for &bits in &[512, 513] {
+            let setup = RSAGroup::generate_primes_with(rng, bits, kind).unwrap();
+            let (p, q) = setup.primes().unwrap();
+            assert_eq!(setup.n.bits(), bits);
+            assert_eq!(&(p * q), &setup.n);
+        }
 
-        Ok((
-            n_final,
-            BigUint::from_u64(EXP).expect("invalid static exponent"),
-        ))
+        // the auxiliary primes must fit
+        let too_large = ModulusKind::Strong(StrongPrimeSizes::for_modulus(4096));
+        assert!(RSAGroup::generate_primes_with(rng, 512, too_large).is_err());
+        let invalid = ModulusKind::Strong(StrongPrimeSizes {
+            r_bits: 40,
+            s_bits: 40,
+            t_bits: 40,
+        });
+        assert!(RSAGroup::generate_primes_with(rng, 512, invalid).is_err());
     }
 }
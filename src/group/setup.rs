@@ -0,0 +1,217 @@
+use std::fmt;
+
+use failure::{bail, Error};
+use num_bigint::traits::ModInverse;
+use num_bigint::BigUint;
+use num_integer::Integer;
+
+use crate::math::modpow_crt;
+
+/// The result of generating a group of unknown order: the modulus `n` and a generator `g`.
+///
+/// If the setup kept them, this also holds the secret primes `p` and `q` with `n = p * q`.
+/// Anyone knowing them can forge witnesses, so they are never printed, and overwritten on a
+/// best effort basis when this is dropped, see `zeroize`.
+pub struct RsaSetup {
+    /// Modulus.
+    pub n: BigUint,
+    /// Generator.
+    pub g: BigUint,
+    primes: Option<(BigUint, BigUint)>,
+}
+
+impl RsaSetup {
+    /// Creates a setup without the secret primes.
+    pub fn new(n: BigUint, g: BigUint) -> Self {
+        RsaSetup { n, g, primes: None }
+    }
+
+    /// Creates a setup that keeps the secret primes.
+    ///
+    /// Fails if `n` is not `p * q`, or `p` and `q` are equal.
+    pub fn with_primes(n: BigUint, g: BigUint, p: BigUint, q: BigUint) -> Result<Self, Error> {
+        if p == q {
+            bail!("p and q must be distinct");
+        }
+        if &p * &q != n {
+            bail!("n must be p * q");
+        }
+
+        Ok(RsaSetup {
+            n,
+            g,
+            primes: Some((p, q)),
+        })
+    }
+
+    /// Returns the secret primes `(p, q)`, if they were kept.
+    pub fn primes(&self) -> Option<(&BigUint, &BigUint)> {
+        self.primes.as_ref().map(|(p, q)| (p, q))
+    }
+
+    /// Returns the `x`-th root of `v`, `v^(1/x) mod n`, using the secret primes.
+    ///
+    /// This is the trapdoor of the accumulator: the root of the state by `x` is the membership
+    /// witness of `x`, or the state after deleting `x`, computed without the set. Exponentiates
+    /// mod `p` and `q` separately, see `math::modpow_crt`.
+    ///
+    /// Returns `None` without the primes, or if `x` is not coprime to `lcm(p - 1, q - 1)`, so
+    /// the root is not unique.
+    pub fn nth_root(&self, v: &BigUint, x: &BigUint) -> Option<BigUint> {
+        let (p, q) = self.primes()?;
+        let lambda = (p - 1u32).lcm(&(q - 1u32));
+        let e = x.clone().mod_inverse(&lambda)?.to_biguint()?;
+
+        modpow_crt(v, &e, p, q)
+    }
+
+    /// Drops the secret primes, overwriting them first, see `zeroize`.
+    pub fn forget_primes(&mut self) {
+        if let Some((mut p, mut q)) = self.primes.take() {
+            zeroize(&mut p);
+            zeroize(&mut q);
+        }
+    }
+}
+
+impl Drop for RsaSetup {
+    fn drop(&mut self) {
+        self.forget_primes();
+    }
+}
+
+impl fmt::Debug for RsaSetup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RsaSetup")
+            .field("n", &self.n)
+            .field("g", &self.g)
+            .field("primes", &self.primes.as_ref().map(|_| "<secret>"))
+            .finish()
+    }
+}
+
+/// Overwrites the digits of `x` with zeros, in place.
+///
+/// This is only best effort: `BigUint` doesn't expose its digits for volatile writes, so the
+/// writes are ordinary ones, only kept from being removed as dead stores by `black_box`,
+/// which is a hint. Copies of `x` made by the arithmetic, e.g. the primes during the setup,
+/// or reallocations as `x` grew, are not overwritten at all.
+pub(crate) fn zeroize(x: &mut BigUint) {
+    let len = x.bits().div_ceil(32);
+    x.assign_from_slice(&vec![0u32; len]);
+    std::hint::black_box(&*x);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "rsa_group")]
+    use crate::group::RSAGroup;
+    #[cfg(feature = "rsa_group")]
+    use crate::traits::PrimeGroup;
+    #[cfg(feature = "rsa_group")]
+    use rand::SeedableRng;
+    #[cfg(feature = "rsa_group")]
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_with_primes() {
+        let p = BigUint::from(61u32);
+        let q = BigUint::from(53u32);
+        let g = BigUint::from(2u32);
+
+        let setup =
+            RsaSetup::with_primes(BigUint::from(3233u32), g.clone(), p.clone(), q.clone()).unwrap();
+        assert_eq!(setup.primes(), Some((&p, &q)));
+
+        // n != p * q
+        assert!(
+            RsaSetup::with_primes(BigUint::from(3232u32), g.clone(), p.clone(), q.clone()).is_err()
+        );
+        assert!(RsaSetup::with_primes(q.clone(), g.clone(), p.clone(), q.clone()).is_err());
+        // p == q
+        assert!(RsaSetup::with_primes(&p * &p, g.clone(), p.clone(), p.clone()).is_err());
+
+        let setup = RsaSetup::new(BigUint::from(3233u32), g);
+        assert_eq!(setup.primes(), None);
+    }
+
+    #[test]
+    fn test_forget_primes() {
+        let mut setup = RsaSetup::with_primes(
+            BigUint::from(3233u32),
+            BigUint::from(2u32),
+            BigUint::from(61u32),
+            BigUint::from(53u32),
+        )
+        .unwrap();
+        assert!(!format!("{:?}", setup).contains("61"));
+
+        setup.forget_primes();
+        assert_eq!(setup.primes(), None);
+        assert_eq!(setup.n, BigUint::from(3233u32));
+
+        let mut x = BigUint::from(u64::MAX) << 100;
+        zeroize(&mut x);
+        assert_eq!(x, BigUint::from(0u32));
+    }
+
+    #[test]
+    #[cfg(feature = "rsa_group")]
+    fn test_generate_primes() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let setup = RSAGroup::generate_primes(rng, 256).unwrap();
+
+        let (p, q) = setup.primes().unwrap();
+        assert_eq!(&(p * q), &setup.n);
+        assert_eq!(setup.n.bits(), 256);
+    }
+
+    #[test]
+    #[cfg(all(feature = "rsa_group", feature = "prover"))]
+    fn test_nth_root() {
+        use crate::accumulator::Accumulator;
+        use crate::primes::RandPrimes;
+        use crate::traits::*;
+
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let mut setup = RSAGroup::generate_primes(rng, 256).unwrap();
+        let mut acc = Accumulator::new(setup.n.clone(), setup.g.clone());
+        let xs = rng.gen_distinct_primes(4, 128);
+        acc.add_batch_no_proof(&xs);
+
+        // witnesses and deletions without the set
+        let root = acc.state().clone();
+        let w = setup.nth_root(&root, &xs[0]).unwrap();
+        assert_eq!(w, acc.mem_wit_create(&xs[0]).0);
+        acc.del(&xs[1]).unwrap();
+        assert_eq!(setup.nth_root(&root, &xs[1]).as_ref(), Some(acc.state()));
+
+        // even exponents share the factor 2 with `lcm(p - 1, q - 1)`
+        assert_eq!(setup.nth_root(&root, &BigUint::from(2u32)), None);
+
+        setup.forget_primes();
+        assert_eq!(setup.nth_root(&root, &xs[0]), None);
+    }
+
+    #[test]
+    #[cfg(feature = "rsa_group")]
+    fn test_generate_primes_exact_bits() {
+        for &bits in &[64, 65, 127, 256, 257, 383] {
+            for seed in 0..16u8 {
+                let rng = &mut ChaChaRng::from_seed([seed; 32]);
+                let setup = RSAGroup::generate_primes(rng, bits).unwrap();
+                assert_eq!(setup.n.bits(), bits, "seed {}", seed);
+
+                let (p, q) = setup.primes().unwrap();
+                assert_eq!(p.bits(), bits / 2);
+                assert_eq!(q.bits(), bits - bits / 2);
+            }
+        }
+
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        assert!(RSAGroup::generate_primes(rng, RSAGroup::MIN_MODULUS_BITS - 1).is_err());
+        assert!(RSAGroup::generate_primes(rng, 0).is_err());
+    }
+}
@@ -0,0 +1,439 @@
+//! Running the expensive operations of an accumulator off the async executor.
+//!
+//! Adding a large batch or creating all membership witnesses takes seconds, which would stall
+//! every other task on an executor thread. An `AccumulatorHandle` moves the accumulator to a
+//! dedicated worker thread, in the style of an actor: the handle sends it jobs, and each job
+//! returns a future that completes once the worker has run it. Jobs run one at a time, in the
+//! order they were sent, with exclusive access to the accumulator, so no lock is held across
+//! an `.await`.
+//!
+//! The futures only rely on `std::task` and work with any executor, e.g. tokio, where the
+//! worker thread plays the role of `spawn_blocking`. `AccumulatorHandle::run` sends its job
+//! right away, the `async` methods once they are first polled.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use num_bigint::BigUint;
+
+use crate::accumulator::{
+    Accumulator, AccumulatorError, BatchAddResult, BatchProof, MembershipWitness, PublicState,
+};
+use crate::traits::*;
+
+type Job = Box<dyn FnOnce(&mut Accumulator) + Send>;
+
+/// A handle to an accumulator owned by a worker thread.
+///
+/// Cloning the handle is cheap, all clones send to the same worker. The worker, and the
+/// accumulator with it, is dropped once the last handle is.
+#[derive(Debug, Clone)]
+pub struct AccumulatorHandle {
+    jobs: mpsc::Sender<Job>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandleError {
+    /// The worker has stopped, as a previous job panicked.
+    Stopped,
+    /// The operation failed.
+    Accumulator(AccumulatorError),
+    /// A chunk of `batch_add_chunked` failed with `error`. The chunks before it stay added,
+    /// with the results in `added`.
+    Chunk {
+        added: Vec<BatchAddResult>,
+        error: AccumulatorError,
+    },
+}
+
+impl fmt::Display for HandleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HandleError::Stopped => write!(f, "the accumulator worker has stopped"),
+            HandleError::Accumulator(err) => err.fmt(f),
+            HandleError::Chunk { added, error } => {
+                write!(f, "chunk {} failed: {}", added.len(), error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HandleError {}
+
+impl From<AccumulatorError> for HandleError {
+    fn from(err: AccumulatorError) -> Self {
+        HandleError::Accumulator(err)
+    }
+}
+
+impl AccumulatorHandle {
+    /// Moves `acc` to a new worker thread.
+    pub fn spawn(acc: Accumulator) -> Self {
+        let (jobs, rx) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("accumulator".into())
+            .spawn(move || {
+                let mut acc = acc;
+                for job in rx {
+                    job(&mut acc);
+                }
+            })
+            .expect("failed to spawn the accumulator worker");
+
+        AccumulatorHandle { jobs }
+    }
+
+    /// Runs `f` on the worker, after all jobs sent before.
+    ///
+    /// The job is sent right away. Dropping the returned future does not cancel it, only its
+    /// result is discarded.
+    pub fn run<T, F>(&self, f: F) -> Pending<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Accumulator) -> T + Send + 'static,
+    {
+        let (tx, pending) = oneshot();
+        // a send error drops the job and with it `tx`, which resolves `pending` as stopped
+        let _ = self.jobs.send(Box::new(move |acc| tx.send(f(acc))));
+
+        pending
+    }
+
+    /// Returns the public state, see `Accumulator::public_state`.
+    pub async fn public_state(&self) -> Result<PublicState, HandleError> {
+        self.run(|acc| acc.public_state()).await
+    }
+
+    /// Adds `xs`, see `Accumulator::try_batch_add`.
+    pub async fn batch_add(&self, xs: Vec<BigUint>) -> Result<BatchProof, HandleError> {
+        Ok(self.run(move |acc| acc.try_batch_add(&xs)).await??)
+    }
+
+    /// Adds `xs` in chunks of at most `chunk_size` elements, see
+    /// `Accumulator::batch_add_chunked`.
+    ///
+    /// Once `cancel` is cancelled, no further chunk is started. The chunks added until then
+    /// stay added, and their results are returned, so fewer results than chunks means the
+    /// batch was cancelled.
+    ///
+    /// Fails with `HandleError::Chunk` if a chunk exceeds the capacity limits, see
+    /// `Accumulator::try_batch_add`, with the results of the chunks added before it.
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub async fn batch_add_chunked(
+        &self,
+        xs: Vec<BigUint>,
+        chunk_size: usize,
+        cancel: CancelToken,
+    ) -> Result<Vec<BatchAddResult>, HandleError> {
+        assert!(chunk_size > 0, "chunk size must not be zero");
+
+        self.run(move |acc| {
+            let mut added = Vec::new();
+            for chunk in xs.chunks(chunk_size) {
+                if cancel.is_cancelled() {
+                    break;
+                }
+
+                let old_root = acc.state().clone();
+                match acc.try_batch_add(chunk) {
+                    Ok(proof) => added.push(BatchAddResult {
+                        old_root,
+                        root: acc.state().clone(),
+                        proof,
+                    }),
+                    Err(error) => return Err(HandleError::Chunk { added, error }),
+                }
+            }
+
+            Ok(added)
+        })
+        .await?
+    }
+
+    /// Creates the membership witness for `x`, see `Accumulator::try_mem_wit_create`.
+    pub async fn mem_wit_create(&self, x: BigUint) -> Result<MembershipWitness, HandleError> {
+        Ok(self.run(move |acc| acc.try_mem_wit_create(&x)).await??)
+    }
+
+    /// Creates the membership witnesses of all of `xs`, see
+    /// `StaticAccumulator::create_all_mem_wit`.
+    pub async fn create_all_mem_wit(
+        &self,
+        xs: Vec<BigUint>,
+    ) -> Result<Vec<MembershipWitness>, HandleError> {
+        self.run(move |acc| acc.create_all_mem_wit(&xs)).await
+    }
+}
+
+/// A flag to stop a chunked operation between two chunks.
+///
+/// Clones share the flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Stops the operations using this token before their next chunk.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// The result of a job sent with `AccumulatorHandle::run`.
+#[derive(Debug)]
+pub struct Pending<T> {
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+#[derive(Debug)]
+struct Slot<T> {
+    value: Option<T>,
+    /// The sender is gone, with or without a value.
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+struct Sender<T> {
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+fn oneshot<T>() -> (Sender<T>, Pending<T>) {
+    let slot = Arc::new(Mutex::new(Slot {
+        value: None,
+        closed: false,
+        waker: None,
+    }));
+
+    (Sender { slot: slot.clone() }, Pending { slot })
+}
+
+impl<T> Sender<T> {
+    fn send(self, value: T) {
+        self.slot.lock().expect("poisoned").value = Some(value);
+        // completed on drop
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        // also reached when the job panics, or is dropped unrun by a stopped worker
+        let waker = match self.slot.lock() {
+            Ok(mut slot) => {
+                slot.closed = true;
+                slot.waker.take()
+            }
+            Err(_) => None,
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Future for Pending<T> {
+    type Output = Result<T, HandleError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().expect("poisoned");
+        if let Some(value) = slot.value.take() {
+            return Poll::Ready(Ok(value));
+        }
+        if slot.closed {
+            return Poll::Ready(Err(HandleError::Stopped));
+        }
+        slot.waker = Some(cx.waker().clone());
+
+        Poll::Pending
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc::channel;
+    use std::task::Wake;
+    use std::time::Duration;
+
+    use crate::accumulator::{CapacityLimits, NewState, OldState};
+    use crate::group::RSAGroup;
+    use crate::primes::RandPrimes;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    /// Wakes the test thread.
+    struct Unpark(thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// A minimal single threaded executor, polling `fut` until it completes.
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = Box::pin(fut);
+        loop {
+            if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+                return out;
+            }
+            thread::park();
+        }
+    }
+
+    /// Completes after `d`.
+    fn timer(d: Duration) -> Pending<()> {
+        let (tx, pending) = oneshot();
+        thread::spawn(move || {
+            thread::sleep(d);
+            tx.send(());
+        });
+        pending
+    }
+
+    fn setup() -> (Accumulator, Vec<BigUint>) {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = rng.gen_distinct_primes(12, 128);
+
+        (acc, xs)
+    }
+
+    #[test]
+    fn test_handle() {
+        let (acc, xs) = setup();
+        let mut expected = acc.clone();
+        let handle = AccumulatorHandle::spawn(acc);
+
+        block_on(async {
+            let old = handle.public_state().await.unwrap();
+            let proof = handle.batch_add(xs[..8].to_vec()).await.unwrap();
+            let state = handle.public_state().await.unwrap();
+            assert!(state.ver_batch_add_transition(
+                &proof,
+                &OldState(old.root.clone()),
+                &NewState(state.root.clone()),
+                &xs[..8]
+            ));
+            assert_eq!(proof, expected.try_batch_add(&xs[..8]).unwrap());
+
+            let w = handle.mem_wit_create(xs[3].clone()).await.unwrap();
+            assert!(state.ver_mem(&w, &xs[3]));
+            assert_eq!(
+                handle.mem_wit_create(xs[9].clone()).await,
+                Err(HandleError::Accumulator(AccumulatorError::NotAMember))
+            );
+
+            let ws = handle.create_all_mem_wit(xs[..8].to_vec()).await.unwrap();
+            assert!(ws.iter().zip(&xs).all(|(w, x)| state.ver_mem(w, x)));
+
+            // clones send to the same accumulator
+            let other = handle.clone();
+            other.batch_add(xs[8..].to_vec()).await.unwrap();
+            assert_eq!(handle.run(|acc| acc.len()).await, Ok(12));
+        });
+    }
+
+    #[test]
+    fn test_handle_cancel() {
+        let (acc, xs) = setup();
+        let handle = AccumulatorHandle::spawn(acc);
+        let old = block_on(handle.public_state()).unwrap();
+
+        let results = block_on(handle.batch_add_chunked(xs[..6].to_vec(), 4, CancelToken::new()));
+        let results = results.unwrap();
+        assert_eq!(results.len(), 2);
+        let state = block_on(handle.public_state()).unwrap();
+        assert!(state.ver_batch_add_chunked(&old.root, &xs[..6], 4, &results));
+
+        // cancelled while queued behind another job
+        let cancel = CancelToken::new();
+        let (release, released) = channel::<()>();
+        let gate = handle.run(move |_| released.recv().unwrap());
+        let chunked = handle.batch_add_chunked(xs[6..].to_vec(), 2, cancel.clone());
+        cancel.cancel();
+        release.send(()).unwrap();
+        block_on(gate).unwrap();
+        assert_eq!(block_on(chunked), Ok(vec![]));
+        assert_eq!(block_on(handle.run(|acc| acc.len())), Ok(6));
+    }
+
+    #[test]
+    fn test_handle_capacity() {
+        let (acc, xs) = setup();
+        let acc = acc.with_capacity_limits(CapacityLimits {
+            max_elements: Some(5),
+            max_set_bits: None,
+        });
+        let handle = AccumulatorHandle::spawn(acc);
+        let old = block_on(handle.public_state()).unwrap();
+
+        let added =
+            match block_on(handle.batch_add_chunked(xs[..8].to_vec(), 2, CancelToken::new())) {
+                Err(HandleError::Chunk { added, error }) => {
+                    assert!(matches!(
+                        error,
+                        AccumulatorError::CapacityExceeded { elements: 6, .. }
+                    ));
+                    added
+                }
+                res => panic!("unexpected result {:?}", res),
+            };
+        assert_eq!(added.len(), 2);
+        let state = block_on(handle.public_state()).unwrap();
+        assert!(state.ver_batch_add_chunked(&old.root, &xs[..4], 2, &added));
+
+        // the worker keeps running
+        assert_eq!(block_on(handle.run(|acc| acc.len())), Ok(4));
+        block_on(handle.batch_add(xs[4..5].to_vec())).unwrap();
+        assert_eq!(block_on(handle.run(|acc| acc.len())), Ok(5));
+    }
+
+    #[test]
+    fn test_handle_does_not_block() {
+        let (acc, xs) = setup();
+        let handle = AccumulatorHandle::spawn(acc);
+
+        // holds the worker until released
+        let (release, released) = channel::<()>();
+        let gate = handle.run(move |_| released.recv().unwrap());
+        let batch = handle.batch_add(xs.clone());
+
+        // the caller's executor keeps running other tasks while the worker is busy
+        block_on(async {
+            timer(Duration::from_millis(10)).await.unwrap();
+        });
+        release.send(()).unwrap();
+        block_on(gate).unwrap();
+        let proof = block_on(batch).unwrap();
+        let state = block_on(handle.public_state()).unwrap();
+        assert!(state.ver_batch_add_iter(&proof, &state.g, xs));
+    }
+
+    #[test]
+    fn test_handle_stopped() {
+        let (acc, xs) = setup();
+        let handle = AccumulatorHandle::spawn(acc);
+
+        let panicked = handle.run(|_| -> usize { panic!("job failed") });
+        assert_eq!(block_on(panicked), Err(HandleError::Stopped));
+        assert_eq!(block_on(handle.batch_add(xs)), Err(HandleError::Stopped));
+    }
+}
@@ -1,23 +1,58 @@
-use blake2::Digest;
+use blake2::{Blake2b, Digest};
 use byteorder::{BigEndian, WriteBytesExt};
 use generic_array::ArrayLength;
-use num_bigint::prime::probably_prime;
 use num_bigint::BigUint;
 use num_integer::Integer;
+use num_traits::One;
+
+use crate::primality::{is_probable_prime, PrimalityConfig};
 
 // When the proofs are made non-interactive, using the
 // Fiat-Shamir heuristic the challenge is generated by hashing the previous transcript
 
+/// The number of candidates `hash_prime` tries before giving up.
+///
+/// About one in 89 of the 128 bit candidates is prime, so the chance of an input without any
+/// prime among this many candidates is below `2^-1000`.
+pub const HASH_PRIME_MAX_COUNTER: u32 = 1 << 16;
+
 /// Hash the given numbers to a prime number.
 /// Currently uses only 128bits.
-pub fn hash_prime<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(input: &[u8]) -> BigUint {
-    let mut y = BigUint::from_bytes_be(&D::digest(input)[..16]);
+///
+/// Returns `None` if there is no prime among the first `HASH_PRIME_MAX_COUNTER` candidates.
+pub fn hash_prime<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(input: &[u8]) -> Option<BigUint> {
+    hash_prime_with_counter::<_, D>(input).map(|(p, _)| p)
+}
 
-    while !probably_prime(&y, 20) {
-        y = BigUint::from_bytes_be(&D::digest(&y.to_bytes_be())[..16]);
-    }
+/// Hash the given numbers to a prime number, also returning the counter of the candidate.
+///
+/// The candidates are `H(input || counter)`, truncated to 128bits, with the counter encoded as
+/// a big-endian `u32`, counting up from `0`. The result is the first candidate that is a
+/// (probable) prime, so anyone can recompute it, and check it with `hash_prime_candidate`.
+pub fn hash_prime_with_counter<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(
+    input: &[u8],
+) -> Option<(BigUint, u32)> {
+    find_prime::<_, D>(input, HASH_PRIME_MAX_COUNTER)
+}
+
+/// The candidate for `hash_prime` with the given counter.
+pub fn hash_prime_candidate<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(
+    input: &[u8],
+    counter: u32,
+) -> BigUint {
+    let mut to_hash = input.to_vec();
+    to_hash.write_u32::<BigEndian>(counter).unwrap();
+
+    BigUint::from_bytes_be(&D::digest(&to_hash)[..16])
+}
 
-    y
+fn find_prime<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(
+    input: &[u8],
+    max_counter: u32,
+) -> Option<(BigUint, u32)> {
+    (0..max_counter)
+        .map(|counter| (hash_prime_candidate::<_, D>(input, counter), counter))
+        .find(|(y, _)| is_probable_prime(y, &PrimalityConfig::default()))
 }
 
 /// Hash the given numbers into the given group.
@@ -31,6 +66,59 @@ pub fn hash_group<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(
     y.mod_floor(n)
 }
 
+/// The number of candidates `derive_generator` tries before giving up.
+///
+/// A candidate is only rejected if it is `1` or shares a factor with `n`, so for a modulus
+/// with large prime factors the first one is accepted, except with negligible probability.
+pub const DERIVE_GENERATOR_MAX_COUNTER: u32 = 1 << 16;
+
+/// Derives a nothing-up-my-sleeve generator from the modulus `n` and the domain separation
+/// tag `domain`, so that anyone can check it was not chosen with a hidden structure.
+///
+/// The result is the first `derive_generator_candidate`, counting up from `0`, that is
+/// neither `0` nor `1`, and coprime to `n`. As a square, it is in `QR_n`.
+///
+/// # Panics
+///
+/// If none of the first `DERIVE_GENERATOR_MAX_COUNTER` candidates is acceptable, e.g. for a
+/// tiny modulus whose only square unit is `1`, like `3`.
+pub fn derive_generator(n: &BigUint, domain: &[u8]) -> BigUint {
+    (0..DERIVE_GENERATOR_MAX_COUNTER)
+        .map(|counter| derive_generator_candidate(n, domain, counter))
+        .find(|g| g > &BigUint::one() && g.gcd(n).is_one())
+        .expect("no generator among the candidates")
+}
+
+/// The candidate for `derive_generator` with the given counter.
+///
+/// It is `h^2 mod n`, where `h` is the big-endian integer of the concatenated Blake2b hashes
+/// of `len(domain) || domain || len(n) || n || counter || i`, for the blocks
+/// `i = 0..ceil((|n| + 64) / 512)`, reduced `mod n`. Here `n` is big-endian without leading
+/// zeros, `|n|` its size in bits, and the lengths, in bytes, the counter and `i` are
+/// big-endian `u32`s.
+pub fn derive_generator_candidate(n: &BigUint, domain: &[u8], counter: u32) -> BigUint {
+    // 64 bits extra, to make the bias from the reduction negligible
+    let blocks = (n.bits() + 64).div_ceil(512);
+    let n_bytes = n.to_bytes_be();
+
+    let mut prefix = Vec::with_capacity(domain.len() + n_bytes.len() + 12);
+    prefix.write_u32::<BigEndian>(domain.len() as u32).unwrap();
+    prefix.extend_from_slice(domain);
+    prefix.write_u32::<BigEndian>(n_bytes.len() as u32).unwrap();
+    prefix.extend_from_slice(&n_bytes);
+    prefix.write_u32::<BigEndian>(counter).unwrap();
+
+    let mut bytes = Vec::with_capacity(blocks * 64);
+    for i in 0..blocks {
+        let mut to_hash = prefix.clone();
+        to_hash.write_u32::<BigEndian>(i as u32).unwrap();
+        bytes.extend_from_slice(&Blake2b::digest(&to_hash)[..]);
+    }
+    let h = BigUint::from_bytes_be(&bytes).mod_floor(n);
+
+    h.modpow(&BigUint::from(2u32), n)
+}
+
 /// Nonce based Hash to prime
 /// Prover provide a nonce such that H(nonce|| DATA ) = l with l ∈ Primes(λ).
 /// Verification becomes a constant time operation which uses only a single primality check.
@@ -50,13 +138,13 @@ pub fn nonce_hash<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(
     //input
     vec.extend_from_slice(input);
 
-    if root.is_some() {
-        vec.append(&mut root.unwrap().to_bytes_be());
+    if let Some(root) = root {
+        vec.append(&mut root.to_bytes_be());
     }
 
     let p = BigUint::from_bytes_be(&D::digest(vec.as_slice()));
 
-    if probably_prime(&p, 20) {
+    if is_probable_prime(&p, &PrimalityConfig::default()) {
         Some(p)
     } else {
         None
@@ -65,14 +153,15 @@ pub fn nonce_hash<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(
 
 ///Verify if given BIGUINT is a prime, complements the nonce_hash() function
 pub fn verify_nonce_hash(p: &BigUint) -> bool {
-    probably_prime(p, 20)
+    is_probable_prime(p, &PrimalityConfig::default())
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "prover"))]
 mod tests {
     use super::*;
 
     use blake2::Blake2b;
+    use num_bigint::prime::probably_prime;
     use num_bigint::RandBigInt;
     use rand::{thread_rng, Rng};
 
@@ -84,9 +173,47 @@ mod tests {
             let mut val = vec![0u8; i * 32];
             rng.fill(&mut val[..]);
 
-            let h = hash_prime::<_, Blake2b>(&val);
+            let h = hash_prime::<_, Blake2b>(&val).unwrap();
+            assert!(probably_prime(&h, 20));
+        }
+    }
+
+    #[test]
+    fn test_hash_prime_counter() {
+        // inputs that need several candidates, all earlier ones must be composite
+        let mut found = 0;
+        for i in 0u32..200 {
+            let val = i.to_be_bytes();
+            let (h, counter) = hash_prime_with_counter::<_, Blake2b>(&val).unwrap();
+            assert_eq!(hash_prime::<_, Blake2b>(&val), Some(h.clone()));
+            assert_eq!(hash_prime_candidate::<_, Blake2b>(&val, counter), h);
             assert!(probably_prime(&h, 20));
+
+            if counter >= 5 {
+                for c in 0..counter {
+                    let y = hash_prime_candidate::<_, Blake2b>(&val, c);
+                    assert!(!probably_prime(&y, 20), "{} at {}", y, c);
+                }
+                found += 1;
+            }
         }
+        assert!(found > 0);
+    }
+
+    #[test]
+    fn test_hash_prime_bounded() {
+        // an input whose first prime candidate is not the first one
+        let val = (0u32..)
+            .map(|i| i.to_be_bytes())
+            .find(|val| hash_prime_with_counter::<_, Blake2b>(val).unwrap().1 >= 3)
+            .unwrap();
+        let (h, counter) = hash_prime_with_counter::<_, Blake2b>(&val).unwrap();
+
+        assert_eq!(find_prime::<_, Blake2b>(&val, counter), None);
+        assert_eq!(
+            find_prime::<_, Blake2b>(&val, counter + 1),
+            Some((h, counter))
+        );
     }
 
     #[test]
@@ -103,6 +230,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_derive_generator() {
+        // 3233 = 53 * 61
+        let n = BigUint::from(3233u32);
+        let vectors = [
+            (&b"accumulators/test"[..], 1262u32),
+            (b"a", 2908),
+            (b"b", 1785),
+        ];
+        for (domain, g) in &vectors {
+            assert_eq!(derive_generator(&n, domain), BigUint::from(*g));
+        }
+
+        // the units mod 15 square to 1 or 4, so only 4 is acceptable
+        let n = BigUint::from(15u32);
+        let four = BigUint::from(4u32);
+        for i in 0u32..20 {
+            assert_eq!(derive_generator(&n, &i.to_be_bytes()), four);
+        }
+        // skipping 0, 1 and the squares sharing a factor with 15 before it
+        let domain = 12u32.to_be_bytes();
+        for counter in 0..13 {
+            let g = derive_generator_candidate(&n, &domain, counter);
+            assert!(g <= BigUint::one() || !g.gcd(&n).is_one(), "{}", g);
+        }
+        assert_eq!(derive_generator_candidate(&n, &domain, 13), four);
+    }
+
     #[test]
     fn test_hash_nonce() {
         let mut rng = thread_rng();
@@ -114,8 +269,8 @@ mod tests {
 
             let mut h = nonce_hash::<_, Blake2b>(nonce, &val, None);
 
-            while h == None {
-                nonce = nonce + 1;
+            while h.is_none() {
+                nonce += 1;
                 h = nonce_hash::<_, Blake2b>(nonce, &val, None);
             }
 
@@ -0,0 +1,860 @@
+//! Append-only log of accumulator states, with a transition proof per epoch.
+
+use std::fmt;
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, WriteBytesExt};
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+use crate::accumulator::{BatchProof, MembershipWitness};
+use crate::math::product;
+use crate::proofs::{self, PoeProof};
+use crate::stateless::BlockProof;
+
+/// The change applied to the accumulator in a single epoch.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transition {
+    /// The elements were added, using `batch_add`.
+    Add(
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::encoding::serde_hex::biguint_vec")
+        )]
+        Vec<BigUint>,
+    ),
+    /// The elements were deleted, using `batch_del`.
+    Del(
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::encoding::serde_hex::biguint_vec")
+        )]
+        Vec<BigUint>,
+    ),
+    /// The elements were deleted and then others added, using `multi_swap`.
+    Swap {
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::encoding::serde_hex::biguint_vec")
+        )]
+        deleted: Vec<BigUint>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::encoding::serde_hex::biguint_vec")
+        )]
+        added: Vec<BigUint>,
+    },
+}
+
+/// The additions of a swap epoch, which follow the deletions.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapStep {
+    /// The state after the deletions, before the additions.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub intermediate: BigUint,
+    /// Proof of the additions, from `intermediate` to the state of the epoch.
+    pub add_proof: BatchProof,
+}
+
+impl Transition {
+    /// Returns the added and the deleted elements.
+    fn elements(&self) -> (&[BigUint], &[BigUint]) {
+        match self {
+            Transition::Add(xs) => (xs, &[]),
+            Transition::Del(xs) => (&[], xs),
+            Transition::Swap { deleted, added } => (added, deleted),
+        }
+    }
+}
+
+/// A single epoch in the log.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// Number of this epoch, starting at `1`.
+    pub epoch: u64,
+    /// Accumulator state at the end of the epoch.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub state: BigUint,
+    /// Digest of the added elements.
+    pub added_digest: Vec<u8>,
+    /// Digest of the deleted elements.
+    pub deleted_digest: Vec<u8>,
+    /// Proof from `batch_add` or `batch_del`, for the transition from the previous state. For
+    /// swaps the proof of the deletions, to the intermediate state of `swap`.
+    pub proof: BatchProof,
+    /// The additions of a swap epoch, `None` for other epochs.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub swap: Option<SwapStep>,
+    /// The elements changed in this epoch, `None` once pruned.
+    pub transition: Option<Transition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainError {
+    /// An epoch is missing from the chain.
+    MissingEpoch { expected: u64, found: u64 },
+    /// The element lists don't match their recorded digests.
+    InvalidDigest { epoch: u64 },
+    /// The transition proof does not verify.
+    InvalidProof { epoch: u64 },
+    /// The element lists needed have been pruned.
+    Pruned { epoch: u64 },
+    /// All epochs have been pruned, so none of them can be checked.
+    FullyPruned,
+    /// The epoch does not start from the state the previous one ended with.
+    Broken { epoch: u64 },
+    /// The epoch deleted elements, so it does not extend the previous state.
+    NotAnExtension { epoch: u64 },
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChainError::MissingEpoch { expected, found } => {
+                write!(f, "epoch {} is missing, found epoch {}", expected, found)
+            }
+            ChainError::InvalidDigest { epoch } => write!(
+                f,
+                "the elements of epoch {} do not match their digest",
+                epoch
+            ),
+            ChainError::InvalidProof { epoch } => {
+                write!(f, "invalid transition proof for epoch {}", epoch)
+            }
+            ChainError::Pruned { epoch } => {
+                write!(f, "the elements of epoch {} have been pruned", epoch)
+            }
+            ChainError::FullyPruned => write!(f, "all epochs have been pruned"),
+            ChainError::Broken { epoch } => write!(
+                f,
+                "epoch {} does not start from the state of the previous one",
+                epoch
+            ),
+            ChainError::NotAnExtension { epoch } => {
+                write!(f, "epoch {} deleted elements", epoch)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+/// Proof that a state extends an earlier one by the elements added over several epochs, from
+/// `aggregate_extension_proofs`.
+///
+/// Replaces the `batch_add` proofs of all epochs with a single NI-PoE for their composition.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AggregatedExtensionProof {
+    /// `extension_digest` of the element digests of the epochs.
+    pub digest: Vec<u8>,
+    /// NI-PoE for `first^x* = last`, for the product `x*` of all added elements, with the
+    /// challenge bound to `digest`.
+    pub proof: PoeProof,
+}
+
+/// Append-only history of an accumulator, starting from the empty accumulator with state `g`.
+///
+/// Epochs whose elements have been pruned keep their digests and proofs, but can't be
+/// checked anymore, so auditors should verify the chain before pruning it. Only a prefix of
+/// the epochs can be pruned.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccumulatorLog {
+    /// Initial state, the generator of the accumulator.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    genesis: BigUint,
+
+    entries: Vec<LogEntry>,
+}
+
+impl AccumulatorLog {
+    /// Creates an empty log, for an accumulator with generator `g`.
+    pub fn new(g: &BigUint) -> Self {
+        AccumulatorLog {
+            genesis: g.clone(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns the number of the latest epoch, `0` if the log is empty.
+    pub fn epoch(&self) -> u64 {
+        self.entries.last().map(|e| e.epoch).unwrap_or(0)
+    }
+
+    /// Returns the state at the end of `epoch`, epoch `0` being the genesis state.
+    pub fn state_at(&self, epoch: u64) -> Option<&BigUint> {
+        if epoch == 0 {
+            return Some(&self.genesis);
+        }
+
+        self.entry(epoch).map(|e| &e.state)
+    }
+
+    /// Returns the entry for `epoch`.
+    pub fn entry(&self, epoch: u64) -> Option<&LogEntry> {
+        self.entries.iter().find(|e| e.epoch == epoch)
+    }
+
+    /// Returns all entries.
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    /// Records a new epoch, which applied `transition`, resulting in `state`.
+    /// Returns the number of the new epoch.
+    ///
+    /// Panics for a `Transition::Swap`, which is recorded with `append_swap_epoch`.
+    pub fn append_epoch(
+        &mut self,
+        transition: Transition,
+        state: BigUint,
+        proof: BatchProof,
+    ) -> u64 {
+        assert!(
+            !matches!(transition, Transition::Swap { .. }),
+            "swaps are recorded with append_swap_epoch"
+        );
+
+        self.push_entry(transition, state, proof, None)
+    }
+
+    /// Records a new epoch, which deleted `deleted` and then added `added` with `multi_swap`,
+    /// resulting in `state`. Returns the number of the new epoch.
+    pub fn append_swap_epoch(
+        &mut self,
+        deleted: Vec<BigUint>,
+        added: Vec<BigUint>,
+        state: BigUint,
+        proof: BlockProof,
+    ) -> u64 {
+        let swap = SwapStep {
+            intermediate: proof.intermediate,
+            add_proof: proof.add,
+        };
+
+        self.push_entry(
+            Transition::Swap { deleted, added },
+            state,
+            proof.del,
+            Some(swap),
+        )
+    }
+
+    fn push_entry(
+        &mut self,
+        transition: Transition,
+        state: BigUint,
+        proof: BatchProof,
+        swap: Option<SwapStep>,
+    ) -> u64 {
+        let epoch = self.epoch() + 1;
+        let (added, deleted) = transition.elements();
+
+        self.entries.push(LogEntry {
+            epoch,
+            state,
+            added_digest: digest(added),
+            deleted_digest: digest(deleted),
+            proof,
+            swap,
+            transition: Some(transition),
+        });
+
+        epoch
+    }
+
+    /// Checks every link in the chain whose elements are still present, for an accumulator
+    /// with modulus `n`.
+    ///
+    /// Returns the number of pruned epochs at the start of the chain, which could not be
+    /// checked. Fails with `ChainError::Pruned` for a pruned epoch after one that is not, and
+    /// with `ChainError::FullyPruned` if there is no epoch left to check.
+    ///
+    /// The states of the pruned epochs are taken on trust, and so is the state the first
+    /// checked epoch starts from: a log is only as trustworthy as the chain was when it was
+    /// pruned.
+    #[must_use = "the pruned epochs at the start of the chain are not checked"]
+    pub fn verify_chain(&self, n: &BigUint) -> Result<u64, ChainError> {
+        let mut prev = &self.genesis;
+        let mut pruned = 0;
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            let expected = i as u64 + 1;
+            if entry.epoch != expected {
+                return Err(ChainError::MissingEpoch {
+                    expected,
+                    found: entry.epoch,
+                });
+            }
+
+            if entry.transition.is_none() && pruned == i as u64 {
+                pruned += 1;
+            } else {
+                verify_entry(entry, prev, n)?;
+            }
+
+            prev = &entry.state;
+        }
+
+        if !self.entries.is_empty() && pruned == self.entries.len() as u64 {
+            return Err(ChainError::FullyPruned);
+        }
+
+        Ok(pruned)
+    }
+
+    /// Aggregates the transitions of the epochs after `from`, up to and including `to`, into a
+    /// single proof for `ver_aggregated_extension`. All of them must have added elements.
+    #[cfg(feature = "prover")]
+    pub fn prove_extension(
+        &self,
+        from: u64,
+        to: u64,
+        n: &BigUint,
+    ) -> Result<AggregatedExtensionProof, ChainError> {
+        let mut spans = Vec::new();
+        let mut products = Vec::new();
+        for epoch in from + 1..=to {
+            let entry = self.entry(epoch).ok_or(ChainError::MissingEpoch {
+                expected: epoch,
+                found: self.epoch(),
+            })?;
+            let xs = match &entry.transition {
+                Some(Transition::Add(xs)) => xs,
+                Some(_) => return Err(ChainError::NotAnExtension { epoch }),
+                None => return Err(ChainError::Pruned { epoch }),
+            };
+
+            let old = self.state_at(epoch - 1).expect("epochs are contiguous");
+            spans.push((old.clone(), entry.state.clone(), entry.added_digest.clone()));
+            products.push(product(xs));
+        }
+
+        aggregate_extension_proofs(&spans, &products, n).map_err(|err| match err {
+            ChainError::Broken { epoch } => ChainError::Broken {
+                epoch: from + epoch,
+            },
+            err => err,
+        })
+    }
+
+    /// Returns the `extension_digest` of the epochs after `from`, up to and including `to`, to
+    /// compare with the digest of an `AggregatedExtensionProof`.
+    pub fn extension_digest(&self, from: u64, to: u64) -> Option<Vec<u8>> {
+        let digests = (from + 1..=to)
+            .map(|epoch| self.entry(epoch).map(|e| e.added_digest.as_slice()))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(extension_digest(&digests))
+    }
+
+    /// Drops the element lists of all epochs up to `epoch`, keeping their digests and proofs.
+    pub fn prune(&mut self, epoch: u64) {
+        for entry in self.entries.iter_mut().take_while(|e| e.epoch <= epoch) {
+            entry.transition = None;
+        }
+    }
+
+    /// Creates a membership witness for `x`, against the state at the end of `epoch`.
+    /// Returns `None` if `x` was not a member at that point.
+    pub fn prove_membership_at(
+        &self,
+        epoch: u64,
+        x: &BigUint,
+        n: &BigUint,
+    ) -> Result<Option<MembershipWitness>, ChainError> {
+        let set = self.set_at(epoch)?;
+        let (rest, r) = set.div_rem(x);
+
+        if !r.is_zero() {
+            return Ok(None);
+        }
+
+        Ok(Some(MembershipWitness(self.genesis.modpow(&rest, n))))
+    }
+
+    /// Verifies a membership witness for `x`, against the state at the end of `epoch`.
+    pub fn ver_mem_at(&self, epoch: u64, w: &MembershipWitness, x: &BigUint, n: &BigUint) -> bool {
+        // untrusted inputs are bounded like in `PublicState::ver_mem`
+        if w.0.is_zero() || &w.0 >= n || x.bits() > n.bits() {
+            return false;
+        }
+
+        match self.state_at(epoch) {
+            Some(state) => &w.0.modpow(x, n) == state,
+            None => false,
+        }
+    }
+
+    /// Product of the elements accumulated at the end of `epoch`.
+    fn set_at(&self, epoch: u64) -> Result<BigUint, ChainError> {
+        let mut set = BigUint::one();
+
+        for entry in self.entries.iter().take_while(|e| e.epoch <= epoch) {
+            let (added, deleted) = entry
+                .transition
+                .as_ref()
+                .ok_or(ChainError::Pruned { epoch: entry.epoch })?
+                .elements();
+
+            for x in deleted {
+                set /= x;
+            }
+            for x in added {
+                set *= x;
+            }
+        }
+
+        Ok(set)
+    }
+}
+
+fn verify_entry(entry: &LogEntry, prev: &BigUint, n: &BigUint) -> Result<(), ChainError> {
+    let epoch = entry.epoch;
+    let transition = entry
+        .transition
+        .as_ref()
+        .ok_or(ChainError::Pruned { epoch })?;
+
+    let (added, deleted) = transition.elements();
+
+    if digest(added) != entry.added_digest || digest(deleted) != entry.deleted_digest {
+        return Err(ChainError::InvalidDigest { epoch });
+    }
+
+    let valid = match (transition, &entry.swap) {
+        (Transition::Add(xs), None) => {
+            proofs::ni_poe_verify(&product(xs), prev, &entry.state, &entry.proof.0, n)
+        }
+        (Transition::Del(xs), None) => {
+            proofs::ni_poe_verify(&product(xs), &entry.state, prev, &entry.proof.0, n)
+        }
+        // the deletions to the intermediate state, then the additions from it
+        (Transition::Swap { deleted, added }, Some(swap)) => {
+            let mid = &swap.intermediate;
+            proofs::ni_poe_verify(&product(deleted), mid, prev, &entry.proof.0, n)
+                && proofs::ni_poe_verify(&product(added), mid, &entry.state, &swap.add_proof.0, n)
+        }
+        _ => false,
+    };
+
+    if !valid {
+        return Err(ChainError::InvalidProof { epoch });
+    }
+
+    Ok(())
+}
+
+/// Aggregates the proofs of consecutive epochs that added elements into one.
+///
+/// `spans` are the states before and after each epoch, with the digest of its added elements,
+/// and `products` the products of the added elements of each epoch. As `old^x_i = new` for
+/// every epoch, the first state to the power of the product of all `x_i` is the last one.
+///
+/// Fails with `ChainError::Broken` if an epoch does not start where the previous one ended,
+/// with epochs counted from `1`. Panics if `spans` is empty, or `products` has a different
+/// length.
+#[cfg(feature = "prover")]
+pub fn aggregate_extension_proofs(
+    spans: &[(BigUint, BigUint, Vec<u8>)],
+    products: &[BigUint],
+    n: &BigUint,
+) -> Result<AggregatedExtensionProof, ChainError> {
+    assert!(!spans.is_empty(), "no epochs given");
+    assert_eq!(spans.len(), products.len(), "one product per epoch");
+
+    for (i, pair) in spans.windows(2).enumerate() {
+        if pair[0].1 != pair[1].0 {
+            return Err(ChainError::Broken {
+                epoch: i as u64 + 2,
+            });
+        }
+    }
+
+    let digests = spans.iter().map(|s| s.2.as_slice()).collect::<Vec<_>>();
+    let digest = extension_digest(&digests);
+    let first = &spans[0].0;
+    let last = &spans[spans.len() - 1].1;
+    let x_star = product(products);
+    let proof = proofs::ni_poe_prove_with_context(&digest, &x_star, first, last, n);
+
+    Ok(AggregatedExtensionProof { digest, proof })
+}
+
+/// Verifies that `last` is `first` with all of `elements` added, over one or more epochs,
+/// given the proof from `aggregate_extension_proofs`.
+///
+/// The digest in the proof is not checked, clients knowing the digests of the epochs should
+/// compare it with their `extension_digest`.
+pub fn ver_aggregated_extension(
+    first: &BigUint,
+    last: &BigUint,
+    elements: &[BigUint],
+    pi: &AggregatedExtensionProof,
+    n: &BigUint,
+) -> bool {
+    // untrusted inputs are bounded like in `PublicState::ver_batch_add_transition`
+    let in_group = |v: &BigUint| !v.is_zero() && v < n;
+    if elements.is_empty()
+        || !in_group(first)
+        || !in_group(last)
+        || elements
+            .iter()
+            .any(|x| x <= &BigUint::one() || x.bits() > n.bits())
+    {
+        return false;
+    }
+
+    let x_star = product(elements);
+    proofs::ni_poe_verify_with_context(&pi.digest, &x_star, first, last, &pi.proof, n)
+}
+
+/// Hashes the element digests of consecutive epochs, for binding an aggregated proof to them.
+pub fn extension_digest(digests: &[&[u8]]) -> Vec<u8> {
+    let mut to_hash = b"accumulators/extension".to_vec();
+    for d in digests {
+        to_hash.write_u32::<BigEndian>(d.len() as u32).unwrap();
+        to_hash.extend_from_slice(d);
+    }
+
+    Blake2b::digest(&to_hash).to_vec()
+}
+
+/// Hashes a list of elements, prefixing each one with its length.
+fn digest(xs: &[BigUint]) -> Vec<u8> {
+    let mut to_hash = Vec::new();
+    for x in xs {
+        let bytes = x.to_bytes_be();
+        to_hash.write_u32::<BigEndian>(bytes.len() as u32).unwrap();
+        to_hash.extend(&bytes);
+    }
+
+    Blake2b::digest(&to_hash).to_vec()
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use crate::primes::RandPrimes;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn build_log(rng: &mut ChaChaRng) -> (Accumulator, AccumulatorLog, Vec<BigUint>) {
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut log = AccumulatorLog::new(acc.generator());
+
+        let mut xs = Vec::new();
+        for _ in 0..3 {
+            let batch = rng.gen_distinct_primes(4, 128);
+            let proof = acc.batch_add(&batch);
+            log.append_epoch(Transition::Add(batch.clone()), acc.state().clone(), proof);
+            xs.extend(batch);
+        }
+
+        // delete two elements of the first epoch
+        let ws = acc.create_all_mem_wit(&xs);
+        let pairs = xs.iter().cloned().zip(ws).take(2).collect::<Vec<_>>();
+        let proof = acc.batch_del(&pairs).unwrap();
+        log.append_epoch(
+            Transition::Del(xs[..2].to_vec()),
+            acc.state().clone(),
+            proof,
+        );
+
+        (acc, log, xs)
+    }
+
+    #[test]
+    fn test_valid_chain() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, log, xs) = build_log(rng);
+        let n = acc.modulus();
+
+        assert_eq!(log.epoch(), 4);
+        assert_eq!(log.state_at(4), Some(acc.state()));
+        assert_eq!(log.verify_chain(n), Ok(0));
+
+        // x0 was a member after the first epoch, but not anymore after the last
+        let w = log.prove_membership_at(1, &xs[0], n).unwrap().unwrap();
+        assert!(log.ver_mem_at(1, &w, &xs[0], n));
+        assert!(!log.ver_mem_at(4, &w, &xs[0], n));
+        assert_eq!(log.prove_membership_at(4, &xs[0], n), Ok(None));
+
+        // x4 was added in the second epoch
+        assert_eq!(log.prove_membership_at(1, &xs[4], n), Ok(None));
+        let w = log.prove_membership_at(4, &xs[4], n).unwrap().unwrap();
+        assert!(acc.ver_mem(&w, &xs[4]));
+    }
+
+    #[test]
+    fn test_tampered_state() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, mut log, _) = build_log(rng);
+
+        log.entries[1].state = (&log.entries[1].state * 2u32) % acc.modulus();
+
+        // both the transition into, and out of the tampered epoch are invalid
+        assert_eq!(
+            log.verify_chain(acc.modulus()),
+            Err(ChainError::InvalidProof { epoch: 2 })
+        );
+    }
+
+    #[test]
+    fn test_tampered_elements() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, mut log, _) = build_log(rng);
+
+        if let Some(Transition::Add(xs)) = &mut log.entries[0].transition {
+            xs.pop();
+        }
+
+        assert_eq!(
+            log.verify_chain(acc.modulus()),
+            Err(ChainError::InvalidDigest { epoch: 1 })
+        );
+    }
+
+    #[test]
+    fn test_missing_epoch() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, mut log, _) = build_log(rng);
+
+        log.entries.remove(2);
+
+        assert_eq!(
+            log.verify_chain(acc.modulus()),
+            Err(ChainError::MissingEpoch {
+                expected: 3,
+                found: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_swap_epoch() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (mut acc, mut log, xs) = build_log(rng);
+        let n = acc.modulus().clone();
+
+        // swap x2 for two new elements
+        let ys = rng.gen_distinct_primes(2, 128);
+        let w = acc.mem_wit_create(&xs[2]);
+        let proof = acc.multi_swap(&[(xs[2].clone(), w)], &ys).unwrap();
+        log.append_swap_epoch(vec![xs[2].clone()], ys.clone(), acc.state().clone(), proof);
+        assert_eq!(log.verify_chain(&n), Ok(0));
+
+        assert_eq!(log.prove_membership_at(5, &xs[2], &n), Ok(None));
+        let w = log.prove_membership_at(5, &ys[1], &n).unwrap().unwrap();
+        assert!(acc.ver_mem(&w, &ys[1]));
+        assert_eq!(
+            log.prove_extension(4, 5, &n),
+            Err(ChainError::NotAnExtension { epoch: 5 })
+        );
+
+        // a forged intermediate state breaks the deletion proof
+        let mut tampered = log.clone();
+        let swap = tampered.entries[4].swap.as_mut().unwrap();
+        swap.intermediate = (&swap.intermediate * 2u32) % &n;
+        assert_eq!(
+            tampered.verify_chain(&n),
+            Err(ChainError::InvalidProof { epoch: 5 })
+        );
+
+        // the additions must be proven as well
+        let mut tampered = log.clone();
+        tampered.entries[4].swap = None;
+        assert_eq!(
+            tampered.verify_chain(&n),
+            Err(ChainError::InvalidProof { epoch: 5 })
+        );
+    }
+
+    #[test]
+    fn test_aggregated_extension() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let n = acc.modulus().clone();
+        let mut log = AccumulatorLog::new(acc.generator());
+
+        let mut batches = Vec::new();
+        for _ in 0..10 {
+            let batch = rng.gen_distinct_primes(3, 128);
+            let proof = acc.batch_add(&batch);
+            log.append_epoch(Transition::Add(batch.clone()), acc.state().clone(), proof);
+            batches.push(batch);
+        }
+
+        // a client at epoch 0 syncs to epoch 10 with one proof
+        let pi = log.prove_extension(0, 10, &n).unwrap();
+        let elements = batches.concat();
+        let (first, last) = (log.state_at(0).unwrap(), log.state_at(10).unwrap());
+        assert!(ver_aggregated_extension(first, last, &elements, &pi, &n));
+        assert_eq!(log.extension_digest(0, 10), Some(pi.digest.clone()));
+        assert!(!ver_aggregated_extension(
+            first,
+            log.state_at(9).unwrap(),
+            &elements,
+            &pi,
+            &n
+        ));
+
+        // a partial span
+        let pi = log.prove_extension(3, 7, &n).unwrap();
+        let (first, last) = (log.state_at(3).unwrap(), log.state_at(7).unwrap());
+        assert!(ver_aggregated_extension(
+            first,
+            last,
+            &batches[3..7].concat(),
+            &pi,
+            &n
+        ));
+        assert!(!ver_aggregated_extension(
+            first,
+            last,
+            &batches[3..6].concat(),
+            &pi,
+            &n
+        ));
+
+        // the digest is bound to the proof
+        let mut other = pi.clone();
+        other.digest = log.extension_digest(3, 6).unwrap();
+        assert!(!ver_aggregated_extension(
+            first,
+            last,
+            &batches[3..7].concat(),
+            &other,
+            &n
+        ));
+
+        // deletions can't be aggregated
+        let w = acc.mem_wit_create(&elements[0]);
+        let proof = acc.batch_del(&[(elements[0].clone(), w)]).unwrap();
+        log.append_epoch(
+            Transition::Del(vec![elements[0].clone()]),
+            acc.state().clone(),
+            proof,
+        );
+        assert_eq!(
+            log.prove_extension(9, 11, &n),
+            Err(ChainError::NotAnExtension { epoch: 11 })
+        );
+    }
+
+    #[test]
+    fn test_aggregated_extension_tampered() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let n = acc.modulus().clone();
+
+        let mut spans = Vec::new();
+        let mut batches = Vec::new();
+        for _ in 0..10 {
+            let batch = rng.gen_distinct_primes(3, 128);
+            let old = acc.state().clone();
+            acc.batch_add(&batch);
+            spans.push((old, acc.state().clone(), digest(&batch)));
+            batches.push(batch);
+        }
+        let products = batches.iter().map(|b| product(b)).collect::<Vec<_>>();
+        let (first, last) = (spans[0].0.clone(), spans[9].1.clone());
+
+        let pi = aggregate_extension_proofs(&spans, &products, &n).unwrap();
+        assert!(ver_aggregated_extension(
+            &first,
+            &last,
+            &batches.concat(),
+            &pi,
+            &n
+        ));
+
+        // an element of the middle epoch replaced
+        let mut tampered = batches.clone();
+        tampered[5][1] = rng.gen_prime(128);
+        assert!(!ver_aggregated_extension(
+            &first,
+            &last,
+            &tampered.concat(),
+            &pi,
+            &n
+        ));
+
+        // a middle epoch dropped
+        tampered[5].clear();
+        assert!(!ver_aggregated_extension(
+            &first,
+            &last,
+            &tampered.concat(),
+            &pi,
+            &n
+        ));
+
+        // a middle state that doesn't continue the chain
+        let mut broken = spans.clone();
+        broken[5].0 = (&broken[5].0 * 2u32) % &n;
+        assert_eq!(
+            aggregate_extension_proofs(&broken, &products, &n),
+            Err(ChainError::Broken { epoch: 6 })
+        );
+    }
+
+    #[test]
+    fn test_pruned_chain() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, mut log, xs) = build_log(rng);
+        let n = acc.modulus();
+
+        log.prune(2);
+
+        assert!(log.entry(2).unwrap().transition.is_none());
+        assert!(log.entry(3).unwrap().transition.is_some());
+        assert_eq!(log.verify_chain(n), Ok(2));
+        assert_eq!(
+            log.prove_membership_at(3, &xs[4], n),
+            Err(ChainError::Pruned { epoch: 1 })
+        );
+
+        // the epochs after the pruned ones are still checked
+        let mut tampered = log.clone();
+        tampered.entries[2].state = (&tampered.entries[2].state * 2u32) % n;
+        assert_eq!(
+            tampered.verify_chain(n),
+            Err(ChainError::InvalidProof { epoch: 3 })
+        );
+
+        // a pruned epoch in the middle can't be skipped
+        let mut gap = log.clone();
+        gap.entries[3].transition = None;
+        assert_eq!(gap.verify_chain(n), Err(ChainError::Pruned { epoch: 4 }));
+
+        // a forged log without elements can't pass
+        let mut forged = AccumulatorLog::new(acc.generator());
+        for entry in log.entries() {
+            forged.entries.push(LogEntry {
+                state: (&entry.state * 3u32) % n,
+                transition: None,
+                ..entry.clone()
+            });
+        }
+        assert_eq!(forged.verify_chain(n), Err(ChainError::FullyPruned));
+
+        let mut pruned = log.clone();
+        pruned.prune(4);
+        assert_eq!(pruned.verify_chain(n), Err(ChainError::FullyPruned));
+        assert_eq!(AccumulatorLog::new(acc.generator()).verify_chain(n), Ok(0));
+    }
+}
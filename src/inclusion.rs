@@ -0,0 +1,271 @@
+//! Proving that all of some elements are members of an accumulator, and none of others, at once.
+//!
+//! The bundle is a membership witness with a NI-PoE for the product `x_in` of the included
+//! elements, and a constant size non-membership proof for the product `x_out` of the excluded
+//! ones. The challenges of all three sub-proofs are bound to one transcript over both statements
+//! and all commitments, so the halves of different bundles can't be paired up into a new one.
+
+use std::collections::HashSet;
+use std::fmt;
+
+#[cfg(feature = "prover")]
+use num_bigint::BigInt;
+use num_bigint::BigUint;
+
+#[cfg(feature = "prover")]
+use crate::accumulator::non_mem_proof_parts;
+use crate::accumulator::{BatchMemError, BatchNonMemError, NonMembershipProof, PublicState};
+use crate::math::product;
+#[cfg(feature = "prover")]
+use crate::math::{modpow_uint_int, MathError};
+use crate::proofs::{self, GroupParams, PoeProof};
+use crate::transcript::{Blake2bTranscript, Transcript};
+
+const INCLUSION_TAG: &[u8] = b"accumulators/inclusion-exclusion";
+
+/// Proof that all of some elements are members and none of others are, from
+/// `Accumulator::prove_inclusion_exclusion`.
+///
+/// The sub-proofs only verify together, with `ver_inclusion_exclusion`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InclusionExclusionProof {
+    /// `w` with `w^x_in = A`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub w: BigUint,
+    /// NI-PoE for `w^x_in = A`.
+    pub pi_w: PoeProof,
+    /// Non-membership proof for `x_out`.
+    pub non_mem: NonMembershipProof,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InclusionExclusionError {
+    /// The excluded element at this index is included as well.
+    Overlap(usize),
+    /// The included elements are invalid.
+    Include(BatchMemError),
+    /// The excluded elements are invalid.
+    Exclude(BatchNonMemError),
+}
+
+impl fmt::Display for InclusionExclusionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InclusionExclusionError::Overlap(i) => {
+                write!(f, "excluded element {} is included as well", i)
+            }
+            InclusionExclusionError::Include(err) => write!(f, "included elements: {}", err),
+            InclusionExclusionError::Exclude(err) => write!(f, "excluded elements: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for InclusionExclusionError {}
+
+/// Returns the index of the first element of `exclude` that is in `include` as well.
+pub(crate) fn overlap(include: &[BigUint], exclude: &[BigUint]) -> Option<usize> {
+    let included = include.iter().collect::<HashSet<_>>();
+    exclude.iter().position(|x| included.contains(x))
+}
+
+/// Proves that `w^x_in = A`, and that `x_out` is coprime to the set, given the Bezout
+/// coefficients `a x_out + b s = 1`.
+#[cfg(feature = "prover")]
+pub(crate) fn prove_inclusion_exclusion(
+    state: &PublicState,
+    w: BigUint,
+    x_in: &BigUint,
+    x_out: &BigUint,
+    a: &BigInt,
+    b: BigInt,
+) -> Result<InclusionExclusionProof, MathError> {
+    let PublicState { n, g, root } = state;
+
+    // d <- g^a, v <- A^b
+    let d = modpow_uint_int(g, a, n)?;
+    let v = modpow_uint_int(root, &b, n)?;
+
+    let params = bound_params(state, x_in, x_out, &w, &d, &v);
+    let pi_w = proofs::poe_prove(&params, x_in, &w, root);
+    let non_mem = non_mem_proof_parts(&params, g, root, x_out, b, d, v)?;
+
+    Ok(InclusionExclusionProof { w, pi_w, non_mem })
+}
+
+/// Verifies that all of `include` are members of the accumulator with the state `state`, and
+/// none of `exclude` are.
+///
+/// Fails if either list is empty, an element is repeated in `include`, or is in both.
+pub fn ver_inclusion_exclusion(
+    state: &PublicState,
+    include: &[BigUint],
+    exclude: &[BigUint],
+    pi: &InclusionExclusionProof,
+) -> bool {
+    let _span = span!(
+        "ver_inclusion_exclusion",
+        included = include.len(),
+        excluded = exclude.len()
+    );
+    let mut seen = HashSet::with_capacity(include.len());
+    if include.is_empty()
+        || exclude.is_empty()
+        || !include.iter().all(|x| seen.insert(x))
+        || overlap(include, exclude).is_some()
+        || state.check_elements(include).is_err()
+        || state.check_elements(exclude).is_err()
+    {
+        return false;
+    }
+
+    let InclusionExclusionProof { w, pi_w, non_mem } = pi;
+    if [w, &non_mem.d, &non_mem.v]
+        .iter()
+        .any(|v| state.check_group_element(v).is_err())
+    {
+        return false;
+    }
+
+    let (x_in, x_out) = (product(include), product(exclude));
+    let params = bound_params(state, &x_in, &x_out, w, &non_mem.d, &non_mem.v);
+
+    proofs::poe_verify(&params, &x_in, w, &state.root, pi_w)
+        && state.check_non_mem_star(&params, &x_out, non_mem).is_ok()
+}
+
+/// The proof parameters, with the context derived from both statements and the commitments
+/// `w`, `d = g^a` and `v = A^b`.
+fn bound_params(
+    state: &PublicState,
+    x_in: &BigUint,
+    x_out: &BigUint,
+    w: &BigUint,
+    d: &BigUint,
+    v: &BigUint,
+) -> GroupParams {
+    let messages: [(&'static [u8], &BigUint); 8] = [
+        (b"n", &state.n),
+        (b"g", &state.g),
+        (b"A", &state.root),
+        (b"x_in", x_in),
+        (b"x_out", x_out),
+        (b"w", w),
+        (b"d", d),
+        (b"v", v),
+    ];
+
+    let mut t = Blake2bTranscript::new(INCLUSION_TAG);
+    for (label, x) in &messages {
+        t.append_message(label, &x.to_bytes_be());
+    }
+    let mut context = [0u8; 32];
+    t.challenge_bytes(b"context", &mut context);
+
+    GroupParams::with_context(state.n.clone(), &context)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use crate::primes::RandPrimes;
+    use crate::traits::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn setup(rng: &mut ChaChaRng) -> (Accumulator, Vec<BigUint>) {
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = rng.gen_distinct_primes(8, 128);
+        acc.add_batch_no_proof(&xs[..4]);
+
+        (acc, xs)
+    }
+
+    #[test]
+    fn test_inclusion_exclusion() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, xs) = setup(rng);
+        let state = acc.public_state();
+        let (include, exclude) = (&xs[..3], &xs[4..]);
+
+        let pi = acc.prove_inclusion_exclusion(include, exclude).unwrap();
+        assert!(ver_inclusion_exclusion(&state, include, exclude, &pi));
+        assert!(!ver_inclusion_exclusion(&state, &xs[..2], exclude, &pi));
+        assert!(!ver_inclusion_exclusion(&state, include, &xs[5..], &pi));
+        assert!(!ver_inclusion_exclusion(&state, exclude, include, &pi));
+
+        // the halves don't verify on their own
+        assert!(!state.ver_non_mem_star(&product(exclude), &pi.non_mem));
+
+        // an element moved from include to exclude
+        let mut moved = exclude.to_vec();
+        moved.push(xs[2].clone());
+        assert!(!ver_inclusion_exclusion(&state, &xs[..2], &moved, &pi));
+        assert!(!ver_inclusion_exclusion(&state, include, &moved, &pi));
+        assert_eq!(
+            acc.prove_inclusion_exclusion(&xs[..2], &moved),
+            Err(InclusionExclusionError::Exclude(
+                BatchNonMemError::IsMember(4)
+            ))
+        );
+        assert_eq!(
+            acc.prove_inclusion_exclusion(include, &moved),
+            Err(InclusionExclusionError::Overlap(4))
+        );
+        assert_eq!(
+            acc.prove_inclusion_exclusion(&xs[3..5], &xs[5..]),
+            Err(InclusionExclusionError::Include(BatchMemError::NotAMember(
+                1
+            )))
+        );
+    }
+
+    #[test]
+    fn test_inclusion_exclusion_cross_pairing() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, xs) = setup(rng);
+        let state = acc.public_state();
+
+        let pi1 = acc.prove_inclusion_exclusion(&xs[..2], &xs[4..6]).unwrap();
+        let pi2 = acc.prove_inclusion_exclusion(&xs[2..4], &xs[6..]).unwrap();
+        assert!(ver_inclusion_exclusion(&state, &xs[..2], &xs[4..6], &pi1));
+        assert!(ver_inclusion_exclusion(&state, &xs[2..4], &xs[6..], &pi2));
+
+        // the membership half of one bundle with the non-membership half of the other
+        let crossed = InclusionExclusionProof {
+            non_mem: pi2.non_mem.clone(),
+            ..pi1.clone()
+        };
+        assert!(!ver_inclusion_exclusion(
+            &state,
+            &xs[..2],
+            &xs[6..],
+            &crossed
+        ));
+        let crossed = InclusionExclusionProof {
+            non_mem: pi1.non_mem.clone(),
+            ..pi2
+        };
+        assert!(!ver_inclusion_exclusion(
+            &state,
+            &xs[2..4],
+            &xs[4..6],
+            &crossed
+        ));
+
+        // for another state
+        let mut other = acc.clone();
+        other.add(&xs[7]);
+        assert!(!ver_inclusion_exclusion(
+            &other.public_state(),
+            &xs[..2],
+            &xs[4..6],
+            &pi1
+        ));
+    }
+}
@@ -6,19 +6,106 @@
 #[macro_use]
 extern crate serde;
 
+#[macro_use]
+mod trace;
+
 // #[macro_use] extern crate failure_derive;
 
 #[cfg(feature = "class_group")]
 extern crate classygroup;
 
 pub mod accumulator;
+#[cfg(feature = "prover")]
+pub mod bloom;
+#[cfg(all(feature = "prover", feature = "rsa_group"))]
+pub mod builder;
+#[cfg(feature = "prover")]
+pub mod collection;
+pub mod element;
+pub mod encoding;
+pub mod equality;
+#[cfg(feature = "prover")]
+pub mod factored;
+#[cfg(feature = "prover")]
+pub mod frozen;
+#[cfg(feature = "prover")]
 pub mod group;
+#[cfg(feature = "async")]
+pub mod handle;
 pub mod hash;
+pub mod history;
+pub mod inclusion;
 pub mod math;
+pub mod merge;
+pub mod migration;
+#[cfg(all(feature = "prover", any(test, feature = "test-utils")))]
+pub mod naive;
+pub mod package;
+mod par;
+#[cfg(feature = "params")]
+pub mod params;
+pub mod primality;
+#[cfg(feature = "prover")]
+pub mod primes;
 pub mod proofs;
+#[cfg(feature = "prover")]
+pub mod rerandomize;
+pub mod revocation;
+pub mod security;
+pub mod setup;
+#[cfg(feature = "prover")]
+pub mod sharded;
+pub mod snapshot;
+#[cfg(all(feature = "prover", any(test, feature = "proptest-support")))]
+pub mod strategies;
+pub mod stateless;
+pub mod sync;
+#[cfg(any(test, feature = "test-vectors"))]
+pub mod test_vectors;
 pub mod traits;
+pub mod transcript;
+pub mod transfer;
+#[cfg(feature = "prover")]
 pub mod vc;
+#[cfg(feature = "prover")]
+pub mod witness;
+pub mod witness_io;
+pub mod zk;
 
 pub use self::accumulator::*;
+#[cfg(feature = "prover")]
+pub use self::bloom::*;
+#[cfg(all(feature = "prover", feature = "rsa_group"))]
+pub use self::builder::*;
+#[cfg(feature = "prover")]
+pub use self::collection::*;
+pub use self::element::*;
+pub use self::equality::*;
+#[cfg(feature = "prover")]
+pub use self::factored::*;
+#[cfg(feature = "prover")]
+pub use self::frozen::*;
+#[cfg(feature = "async")]
+pub use self::handle::*;
+pub use self::history::*;
+pub use self::inclusion::*;
+pub use self::merge::*;
+pub use self::migration::*;
+pub use self::package::*;
+pub use self::proofs::VerifyError;
+#[cfg(feature = "prover")]
+pub use self::rerandomize::*;
+pub use self::revocation::*;
+pub use self::security::*;
+pub use self::setup::*;
+#[cfg(feature = "prover")]
+pub use self::sharded::*;
+pub use self::snapshot::*;
+pub use self::stateless::*;
+pub use self::sync::*;
 pub use self::traits::*;
+pub use self::transfer::*;
+#[cfg(feature = "prover")]
 pub use self::vc::*;
+#[cfg(feature = "prover")]
+pub use self::witness::*;
@@ -1,9 +1,14 @@
-#![cfg_attr(feature = "cargo-clippy", allow(clippy::many_single_char_names))]
+#![allow(clippy::many_single_char_names)]
+
+use std::convert::TryInto;
+use std::fmt;
 
 use num_bigint::traits::{ExtendedGcd, ModInverse};
 use num_bigint::{BigInt, BigUint, Sign};
 use num_integer::Integer;
-use num_traits::{One, Signed, Zero};
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::par;
 
 /// Calculates a = a.pow(b).
 // TODO: this can be speed up using various techniques, like precomputations.
@@ -20,32 +25,365 @@ pub fn pow_assign(a: &mut BigUint, b: &BigUint) {
     }
 }
 
+/// Errors from modular arithmetic on untrusted inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MathError {
+    /// The exponent is negative, and the base has no inverse, as it shares a factor with the
+    /// modulus.
+    NotInvertible,
+    /// The modulus is zero.
+    ZeroModulus,
+}
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MathError::NotInvertible => write!(f, "the base is not invertible"),
+            MathError::ZeroModulus => write!(f, "the modulus is zero"),
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
+
 /// Calculates a ^ e % n.
-pub fn modpow_uint_int(a: &BigUint, e: &BigInt, n: &BigUint) -> Option<BigUint> {
+pub fn modpow_uint_int(a: &BigUint, e: &BigInt, n: &BigUint) -> Result<BigUint, MathError> {
+    if n.is_zero() {
+        return Err(MathError::ZeroModulus);
+    }
+
     match e.sign() {
         Sign::Plus => {
             // regular case
-            Some(a.clone().modpow(&e.to_biguint().unwrap(), n))
+            Ok(a.clone().modpow(&e.to_biguint().unwrap(), n))
         }
         Sign::Minus => {
             // exponent is negative, so we calculate the modular inverse of e.
             let a_signed = BigInt::from_biguint(Sign::Plus, a.clone());
             let n_signed = BigInt::from_biguint(Sign::Plus, n.clone());
 
-            if let Some(a_inv) = a_signed.mod_inverse(&n_signed) {
-                let e_abs = e.abs().to_biguint().unwrap();
-                Some(a_inv.to_biguint().unwrap().modpow(&e_abs, n))
-            } else {
-                None
-            }
+            let a_inv = a_signed
+                .mod_inverse(&n_signed)
+                .ok_or(MathError::NotInvertible)?;
+            let e_abs = e.abs().to_biguint().unwrap();
+            Ok(a_inv.to_biguint().unwrap().modpow(&e_abs, n))
         }
         Sign::NoSign => {
             // zero
-            Some(BigUint::one())
+            Ok(BigUint::one() % n)
         }
     }
 }
 
+/// Extended Euclidean algorithm, returns `(g, x, y)` with `a * x + b * y = g` and
+/// `g = gcd(a, b)`.
+///
+/// The coefficients are the ones of the Euclidean algorithm. If one operand is much larger
+/// than the other, e.g. an element and the product of a large set, it is reduced by the smaller
+/// one first, and the coefficients are recovered from the quotient, see `reduced_gcd`.
+pub fn extended_gcd(a: &BigUint, b: &BigUint) -> (BigUint, BigInt, BigInt) {
+    if b.bits() > a.bits() + REDUCE_GCD_BITS && !a.is_zero() {
+        return reduced_gcd(a, b);
+    }
+    if a.bits() > b.bits() + REDUCE_GCD_BITS && !b.is_zero() {
+        let (g, y, x) = reduced_gcd(b, a);
+        return (g, x, y);
+    }
+
+    let (g, x, y) = extended_gcd_int(
+        &BigInt::from_biguint(Sign::Plus, a.clone()),
+        &BigInt::from_biguint(Sign::Plus, b.clone()),
+    );
+
+    (g.to_biguint().expect("gcd is non-negative"), x, y)
+}
+
+/// Operands differing by more bits than this are reduced first in `extended_gcd`.
+const REDUCE_GCD_BITS: usize = 256;
+
+/// `extended_gcd` for `0 < a < b`, with the first step of the Euclidean algorithm,
+/// `b = q a + r`, done up front.
+///
+/// Lehmer's algorithm, used for the rest, recovers the coefficient of `b` by dividing by `b`
+/// at the end, which costs another full division for the large operand. With
+/// `u a + v r = g`, the coefficients are `(u - v q, v)` instead.
+fn reduced_gcd(a: &BigUint, b: &BigUint) -> (BigUint, BigInt, BigInt) {
+    let (q, r) = b.div_rem(a);
+    let (g, u, v) = extended_gcd(a, &r);
+    let q = BigInt::from_biguint(Sign::Plus, q);
+
+    (g, u - &v * q, v)
+}
+
+/// Extended Euclidean algorithm for signed inputs, returns `(g, x, y)` with
+/// `a * x + b * y = g` and `g = gcd(|a|, |b|) >= 0`.
+///
+/// If either input is zero, `g` is the absolute value of the other one, and for `a = b = 0`
+/// all of `g`, `x` and `y` are zero.
+pub fn extended_gcd_int(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    // solve for |a| and |b|, then move the signs into the coefficients
+    let (g, x, y) = ExtendedGcd::extended_gcd(a.abs(), &b.abs());
+
+    let x = if a.is_negative() { -x } else { x };
+    let y = if b.is_negative() { -y } else { y };
+
+    (g, x, y)
+}
+
+/// Chinese remainder theorem, returns the `x < \prod m_i` with `x = a_i mod m_i` for all
+/// pairs `(a_i, m_i)` in `residues`.
+///
+/// Returns `None` if the moduli are not pairwise coprime.
+pub fn crt(residues: &[(BigUint, BigUint)]) -> Option<BigUint> {
+    let mut x = BigUint::zero();
+    let mut m = BigUint::one();
+
+    for (a_i, m_i) in residues {
+        x = crt2(a_i, m_i, &x, &m)?;
+        m *= m_i;
+    }
+
+    Some(x)
+}
+
+/// Chinese remainder theorem for two moduli, returns the `x < pq` with `x = a_p mod p` and
+/// `x = a_q mod q`.
+///
+/// Returns `None` if `p` and `q` are not coprime.
+pub fn crt2(a_p: &BigUint, p: &BigUint, a_q: &BigUint, q: &BigUint) -> Option<BigUint> {
+    if p.is_zero() || q.is_zero() {
+        return None;
+    }
+    if p.is_one() {
+        return Some(a_q % q);
+    }
+
+    // q^-1 mod p
+    let q_inv = q.clone().mod_inverse(p)?.to_biguint()?;
+
+    // x = a_q + q * ((a_p - a_q) q^-1 mod p)
+    let a_q = a_q % q;
+    let diff = (a_p % p + p - (&a_q % p)) % p;
+    Some(a_q + q * ((diff * q_inv) % p))
+}
+
+/// Calculates `a ^ e % pq`, for distinct primes `p` and `q`, exponentiating mod `p` and `q`
+/// separately and recombining the results with `crt2`.
+///
+/// This only works with the factorization of the modulus, and is about four times faster
+/// than `modpow` mod `pq`, see `RsaSetup::nth_root`.
+pub fn modpow_crt(a: &BigUint, e: &BigUint, p: &BigUint, q: &BigUint) -> Option<BigUint> {
+    crt2(&modpow_prime(a, e, p), p, &modpow_prime(a, e, q), q)
+}
+
+/// Calculates `a ^ e % p` for a prime `p`, reducing the exponent mod `p - 1` when possible.
+fn modpow_prime(a: &BigUint, e: &BigUint, p: &BigUint) -> BigUint {
+    let a = a % p;
+    if a.is_zero() {
+        // Fermat's little theorem doesn't apply to multiples of `p`
+        return a.modpow(e, p);
+    }
+
+    a.modpow(&(e % (p - 1u32)), p)
+}
+
+/// Jacobi symbol `(a / n)`, using quadratic reciprocity, so without factoring `n`.
+///
+/// Returns `None` if `n` is even or zero. For prime `n` this is the Legendre symbol, which is
+/// `1` for quadratic residues, `-1` for non-residues and `0` for multiples of `n`.
+pub fn jacobi(a: &BigUint, n: &BigUint) -> Option<i8> {
+    if n.is_even() {
+        return None;
+    }
+
+    let mut a = a % n;
+    let mut n = n.clone();
+    let mut t = 1;
+
+    while !a.is_zero() {
+        // (2 / n) = -1 iff n = 3, 5 mod 8
+        while a.is_even() {
+            a >>= 1;
+            let r = (&n % 8u32).to_u32().unwrap();
+            if r == 3 || r == 5 {
+                t = -t;
+            }
+        }
+
+        // (a / n) = -(n / a) iff a = n = 3 mod 4
+        std::mem::swap(&mut a, &mut n);
+        if (&a % 4u32).to_u32() == Some(3) && (&n % 4u32).to_u32() == Some(3) {
+            t = -t;
+        }
+        a %= &n;
+    }
+
+    if n.is_one() {
+        Some(t)
+    } else {
+        Some(0)
+    }
+}
+
+/// Calculates `a^x b^y mod n`, with a single pass over the bits of both exponents.
+///
+/// Squares once per bit of the longer exponent, and multiplies by one of the 15 precomputed
+/// products `a^i b^j` for every 2 bits, which is much cheaper than two separate `modpow`s.
+/// Even moduli fall back to exactly that.
+pub fn multi_modpow(a: &BigUint, x: &BigUint, b: &BigUint, y: &BigUint, n: &BigUint) -> BigUint {
+    if n.is_even() || n.is_one() {
+        return (a.modpow(x, n) * b.modpow(y, n)) % n;
+    }
+
+    let m = Montgomery::new(n);
+
+    // table[i][j] = a^i b^j, in Montgomery form
+    let mut table = vec![m.one(); 16];
+    let (a, b) = (m.encode(&(a % n)), m.encode(&(b % n)));
+    for i in 0..4 {
+        for j in 0..4 {
+            let k = 4 * i + j;
+            table[k] = match (i, j) {
+                (0, 0) => continue,
+                (_, 0) => m.mul(&table[k - 4], &a),
+                _ => m.mul(&table[k - 1], &b),
+            };
+        }
+    }
+
+    let bits = x.bits().max(y.bits());
+    let (x, y) = (x.to_bytes_le(), y.to_bytes_le());
+    // the 2-bit digit of `e` at position `i`
+    let digit = |e: &[u8], i: usize| -> usize {
+        e.get(i / 4).map_or(0, |byte| (byte >> (2 * (i % 4))) & 3) as usize
+    };
+
+    let mut r = m.one();
+    for i in (0..bits.div_ceil(2)).rev() {
+        r = m.mul(&r, &r);
+        r = m.mul(&r, &r);
+        let k = 4 * digit(&x, i) + digit(&y, i);
+        if k != 0 {
+            r = m.mul(&r, &table[k]);
+        }
+    }
+
+    m.decode(&r)
+}
+
+/// Montgomery multiplication modulo an odd `n`, on little-endian 64-bit limbs.
+struct Montgomery {
+    n: Vec<u64>,
+    /// `-n^-1 mod 2^64`
+    n0_inv: u64,
+    /// `R^2 mod n`, with `R = 2^(64 len(n))`
+    rr: Vec<u64>,
+}
+
+impl Montgomery {
+    fn new(n: &BigUint) -> Self {
+        let limbs = to_limbs(n, n.bits().div_ceil(64));
+
+        // Newton iteration, each step doubles the number of correct low bits
+        let mut inv = 1u64;
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(limbs[0].wrapping_mul(inv)));
+        }
+
+        let rr = (BigUint::one() << (128 * limbs.len())) % n;
+        Montgomery {
+            rr: to_limbs(&rr, limbs.len()),
+            n0_inv: inv.wrapping_neg(),
+            n: limbs,
+        }
+    }
+
+    fn one(&self) -> Vec<u64> {
+        let mut one = vec![0; self.n.len()];
+        one[0] = 1;
+        self.mul(&one, &self.rr)
+    }
+
+    fn encode(&self, x: &BigUint) -> Vec<u64> {
+        self.mul(&to_limbs(x, self.n.len()), &self.rr)
+    }
+
+    fn decode(&self, x: &[u64]) -> BigUint {
+        let mut one = vec![0; self.n.len()];
+        one[0] = 1;
+        let limbs = self.mul(x, &one);
+
+        let bytes = limbs
+            .iter()
+            .flat_map(|l| l.to_le_bytes())
+            .collect::<Vec<_>>();
+        BigUint::from_bytes_le(&bytes)
+    }
+
+    /// `a b R^-1 mod n`, for `a, b < n`, with the CIOS method.
+    fn mul(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let s = self.n.len();
+        let mut t = vec![0u64; s + 2];
+
+        for &bi in b {
+            let mut c = 0u128;
+            for (tj, aj) in t.iter_mut().zip(a) {
+                let v = *tj as u128 + *aj as u128 * bi as u128 + c;
+                *tj = v as u64;
+                c = v >> 64;
+            }
+            let v = t[s] as u128 + c;
+            t[s] = v as u64;
+            t[s + 1] = (v >> 64) as u64;
+
+            let m = t[0].wrapping_mul(self.n0_inv);
+            let mut c = (t[0] as u128 + m as u128 * self.n[0] as u128) >> 64;
+            for j in 1..s {
+                let v = t[j] as u128 + m as u128 * self.n[j] as u128 + c;
+                t[j - 1] = v as u64;
+                c = v >> 64;
+            }
+            let v = t[s] as u128 + c;
+            t[s - 1] = v as u64;
+            t[s] = t[s + 1] + (v >> 64) as u64;
+        }
+
+        // t < 2n, subtract n once if needed
+        if t[s] != 0 || !less(&t[..s], &self.n) {
+            let mut borrow = 0u64;
+            for (tj, nj) in t.iter_mut().zip(&self.n) {
+                let (d, b1) = tj.overflowing_sub(*nj);
+                let (d, b2) = d.overflowing_sub(borrow);
+                *tj = d;
+                borrow = (b1 || b2) as u64;
+            }
+        }
+        t.truncate(s);
+
+        t
+    }
+}
+
+/// `a < b`, for little-endian limbs of the same length.
+fn less(a: &[u64], b: &[u64]) -> bool {
+    for (x, y) in a.iter().rev().zip(b.iter().rev()) {
+        if x != y {
+            return x < y;
+        }
+    }
+    false
+}
+
+/// The little-endian limbs of `x < 2^(64 len)`.
+fn to_limbs(x: &BigUint, len: usize) -> Vec<u64> {
+    let mut bytes = x.to_bytes_le();
+    bytes.resize(8 * len, 0);
+    bytes
+        .chunks(8)
+        .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+        .collect()
+}
+
 /// Calculates the `(xy)`-th root of `g`, given the `x`-th root and `y`-th root of `g.`
 /// Operations are `mod n`.
 pub fn shamir_trick(
@@ -64,60 +402,340 @@ pub fn shamir_trick(
     }
 
     // a, b <- Bezout(x, y)
-    //let (_, a, b) = x.extended_gcd(y);
-    let (_, a, b) = num_bigint::traits::ExtendedGcd::extended_gcd(x, y);
+    let (_, a, b) = extended_gcd(x, y);
 
-    let l = modpow_uint_int(&root_x, &b, n);
-    let r = modpow_uint_int(&root_y, &a, n);
+    let l = modpow_uint_int(root_x, &b, n).ok()?;
+    let r = modpow_uint_int(root_y, &a, n).ok()?;
 
-    if let Some(l) = l {
-        if let Some(r) = r {
-            return Some((l * r).mod_floor(n));
-        }
-    }
-
-    None
+    Some((l * r).mod_floor(n))
 }
 
 /// Given `y = g^x` and `x = \prod x_i`, calculates the `x_i`-th roots, for all `i`.
 /// All operations are `mod n`.
+///
+/// Builds a `SubproductTree` over `x`, see `SubproductTree::roots`.
 pub fn root_factor(g: &BigUint, x: &[BigUint], n: &BigUint) -> Vec<BigUint> {
-    let m = x.len();
-    if m == 1 {
-        return vec![g.clone()];
+    SubproductTree::build(x).roots(g, n)
+}
+
+/// Products of a list of elements, pairwise combined level by level, up to the product of
+/// all of them.
+///
+/// The `i`-th node of a level is the product of the nodes `2i` and `2i + 1` of the level
+/// below, or of only `2i` at the end of an odd level. So the leaves below it are those from
+/// `i 2^level` to `(i + 1) 2^level`, and parts of the product can be assembled from a few
+/// nodes, instead of multiplying the elements again.
+///
+/// Each level takes about as much memory as the product of all elements, so the tree takes
+/// about `log2(len) + 1` times that: for a million 256 bit elements, 32 MiB per level and
+/// about 700 MiB in total. Build it once and use it for everything needing partial products
+/// of the same elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubproductTree {
+    /// The leaves, followed by the levels above them, up to the single root.
+    levels: Vec<Vec<BigUint>>,
+}
+
+impl SubproductTree {
+    /// Builds the tree over `elements`, which must not be zero.
+    pub fn build(elements: &[BigUint]) -> Self {
+        let mut levels = vec![elements.to_vec()];
+        while levels.last().unwrap().len() > 1 {
+            let pairs = levels.last().unwrap().chunks(2).collect::<Vec<_>>();
+            let level = par::map(&pairs, |pair| pair.iter().product());
+            levels.push(level);
+        }
+
+        SubproductTree { levels }
+    }
+
+    /// Returns the number of leaves.
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
     }
 
-    let m_prime = m.div_floor(&2);
+    /// Returns `true` if the tree has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// Returns the elements the tree was built over.
+    pub fn leaves(&self) -> &[BigUint] {
+        &self.levels[0]
+    }
+
+    /// Returns the product of all leaves, one for an empty tree.
+    pub fn product(&self) -> BigUint {
+        self.levels
+            .last()
+            .unwrap()
+            .first()
+            .cloned()
+            .unwrap_or_else(BigUint::one)
+    }
 
-    let (x_l, x_r) = x.split_at(m_prime);
+    /// Returns the product of all leaves but the `index`-th, from the siblings along its path
+    /// to the root.
+    ///
+    /// Panics if `index` is out of range.
+    pub fn product_excluding(&self, mut index: usize) -> BigUint {
+        assert!(index < self.len(), "index out of range");
 
-    let g_l = {
         let mut p = BigUint::one();
-        // the paper uses the upper part for g_L
-        for x in x_r {
-            p *= x;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                p *= sibling;
+            }
+            index /= 2;
         }
 
-        g.modpow(&p, n)
-    };
+        p
+    }
 
-    let g_r = {
-        let mut p = BigUint::one();
-        // the paper uses the lower part for g_R
-        for x in x_l {
-            p *= x;
+    /// Returns the product of all leaves but those at `indices`, the exponent of the
+    /// membership witness for the subset at `indices`. Repeated indices are excluded once.
+    ///
+    /// Uses the nodes covering the remaining leaves, so takes about one multiplication per
+    /// excluded leaf and level. Panics if an index is out of range.
+    pub fn exponent_for_subset(&self, indices: &[usize]) -> BigUint {
+        let mut excluded = indices.to_vec();
+        excluded.sort_unstable();
+        excluded.dedup();
+        assert!(
+            excluded.iter().all(|&i| i < self.len()),
+            "index out of range"
+        );
+        if self.is_empty() {
+            return BigUint::one();
         }
 
-        g.modpow(&p, n)
-    };
+        self.excluding(self.levels.len() - 1, 0, &excluded)
+    }
+
+    /// The product of the leaves below the `i`-th node of `level`, except the `excluded`
+    /// ones, all below that node and sorted.
+    fn excluding(&self, level: usize, i: usize, excluded: &[usize]) -> BigUint {
+        if excluded.is_empty() {
+            return self.levels[level][i].clone();
+        }
+        if level == 0 {
+            return BigUint::one();
+        }
+
+        let (l, r) = (2 * i, 2 * i + 1);
+        let split = excluded.partition_point(|&j| j < r << (level - 1));
+        let mut p = self.excluding(level - 1, l, &excluded[..split]);
+        if r < self.levels[level - 1].len() {
+            p *= self.excluding(level - 1, r, &excluded[split..]);
+        }
+
+        p
+    }
+
+    /// Returns `v mod x_i` for all leaves `x_i`, reducing `v` down the tree.
+    pub fn remainders(&self, v: &BigUint) -> Vec<BigUint> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        let mut remainders = vec![v % self.product()];
+        for level in self.levels[..self.levels.len() - 1].iter().rev() {
+            remainders = level
+                .iter()
+                .enumerate()
+                .map(|(i, m)| &remainders[i / 2] % m)
+                .collect();
+        }
+
+        remainders
+    }
+
+    /// Returns the indices of the leaves sharing a factor with `m`, in order.
+    ///
+    /// Reduces `m` down the tree, so only takes one gcd with a leaf sized number per leaf.
+    pub fn gcd_against(&self, m: &BigUint) -> Vec<usize> {
+        self.remainders(m)
+            .iter()
+            .zip(self.leaves())
+            .enumerate()
+            .filter(|(_, (r, x))| !r.gcd(x).is_one())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Given `y = g^x`, with `x` the product of the leaves, calculates the `x_i`-th roots of
+    /// `y` for all leaves `x_i`. All operations are `mod n`.
+    ///
+    /// Each node passes `g` raised to the product of one child down to the other, so every
+    /// leaf gets `g` to the product of all other leaves.
+    pub fn roots(&self, g: &BigUint, n: &BigUint) -> Vec<BigUint> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        self.roots_below(self.levels.len() - 1, 0, g.clone(), n)
+    }
+
+    fn roots_below(&self, level: usize, i: usize, g: BigUint, n: &BigUint) -> Vec<BigUint> {
+        if level == 0 {
+            return vec![g];
+        }
+
+        let below = &self.levels[level - 1];
+        let (l, r) = (2 * i, 2 * i + 1);
+        match below.get(r) {
+            None => self.roots_below(level - 1, l, g, n),
+            Some(p_r) => {
+                // the paper uses the upper part for g_L, and the lower part for g_R
+                let (mut res, res_r) = par::join(
+                    || self.roots_below(level - 1, l, g.modpow(p_r, n), n),
+                    || self.roots_below(level - 1, r, g.modpow(&below[l], n), n),
+                );
+                res.extend(res_r);
+
+                res
+            }
+        }
+    }
+}
+
+/// Given `a_i` and `x_i`, calculates `\prod a_i^{x / x_i}` with `x = \prod x_i`, `mod n`.
+///
+/// Splits the list in halves, so that each half is raised to the product of the other one,
+/// instead of computing each `x / x_i`.
+pub fn cross_power(a: &[BigUint], x: &[BigUint], n: &BigUint) -> BigUint {
+    debug_assert_eq!(a.len(), x.len());
+    match a.len() {
+        0 => BigUint::one(),
+        1 => a[0].clone() % n,
+        len => {
+            let (a_l, a_r) = a.split_at(len / 2);
+            let (x_l, x_r) = x.split_at(len / 2);
+            let (l, r) = par::join(|| cross_power(a_l, x_l, n), || cross_power(a_r, x_r, n));
+
+            multi_modpow(&l, &product(x_r), &r, &product(x_l), n)
+        }
+    }
+}
+
+/// Returns the indices `(i, j)`, `i < j`, of the first two of `xs` that share a factor, or
+/// `None` if they are pairwise coprime. All `xs` must be larger than one.
+///
+/// With `x` the product of all `xs`, `x_i` is coprime to the others iff it is coprime to
+/// `(x mod x_i^2) / x_i`. The remainders come from a `SubproductTree` over the squares, so
+/// this takes about as long as a few products of all `xs`, instead of a gcd for every pair.
+pub fn common_factor(xs: &[BigUint]) -> Option<(usize, usize)> {
+    let i = shared_parts(xs).iter().position(|s| !s.is_one())?;
+    // a partner before `i` would have been found first
+    let j = (i + 1..xs.len()).find(|&j| !xs[i].gcd(&xs[j]).is_one())?;
+
+    Some((i, j))
+}
+
+/// Returns all pairs `(i, j, gcd(x_i, x_j))`, `i < j`, of `xs` that share a factor, ordered
+/// by `i` and then `j`. All `xs` must be larger than one.
+///
+/// Finds the elements sharing a factor with any other like `common_factor`, and only takes
+/// the gcds of the pairs among those. So this is quasi-linear in the number of `xs`, and
+/// quadratic only in the number of elements sharing a factor, like the number of pairs.
+pub fn find_common_factors(xs: &[BigUint]) -> Vec<(usize, usize, BigUint)> {
+    let shared = shared_parts(xs)
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| !s.is_one())
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    let mut pairs = Vec::new();
+    for (k, &i) in shared.iter().enumerate() {
+        for &j in &shared[k + 1..] {
+            let d = xs[i].gcd(&xs[j]);
+            if !d.is_one() {
+                pairs.push((i, j, d));
+            }
+        }
+    }
+
+    pairs
+}
+
+/// Returns `gcd(x_i, x / x_i)` for all `xs`, with `x` their product, the part of each
+/// sharing factors with the others.
+fn shared_parts(xs: &[BigUint]) -> Vec<BigUint> {
+    let squares = xs.iter().map(|x| x * x).collect::<Vec<_>>();
+    let remainders = SubproductTree::build(&squares).remainders(&product(xs));
+
+    xs.iter()
+        .zip(&remainders)
+        .map(|(x, r)| (r / x).gcd(x))
+        .collect()
+}
+
+/// Calculates the product of all `xs`, using a product tree.
+/// Multiplying numbers of similar size is much faster than multiplying one by one into
+/// an ever growing product.
+pub fn product(xs: &[BigUint]) -> BigUint {
+    match xs.len() {
+        0 => BigUint::one(),
+        1 => xs[0].clone(),
+        len => {
+            let (l, r) = xs.split_at(len / 2);
+            if len < par::MIN_SPLIT_LEN {
+                product(l) * product(r)
+            } else {
+                let (l, r) = par::join(|| product(l), || product(r));
+                l * r
+            }
+        }
+    }
+}
+
+/// Calculates the product of all `xs`, like `product`, without collecting them first.
+///
+/// Keeps one partial product per level of the product tree, so the elements can be
+/// streamed, while the multiplications stay balanced.
+pub fn product_iter<I: IntoIterator<Item = BigUint>>(xs: I) -> BigUint {
+    // partial products, with the number of elements in each, from largest to smallest
+    let mut stack: Vec<(BigUint, usize)> = Vec::new();
 
-    let mut res = root_factor(&g_l, x_l, n);
-    res.extend(root_factor(&g_r, x_r, n));
+    for x in xs {
+        let mut node = (x, 1);
+        while let Some((y, len)) = stack.pop() {
+            if len != node.1 {
+                stack.push((y, len));
+                break;
+            }
+            node = (y * node.0, len * 2);
+        }
+        stack.push(node);
+    }
 
-    res
+    stack
+        .into_iter()
+        .rev()
+        .fold(BigUint::one(), |acc, (x, _)| x * acc)
 }
 
-#[cfg(test)]
+/// Returns `(r, k)` with `r^k = n`, for the smallest prime `k` possible, or `None` if `n` is
+/// not a perfect power.
+///
+/// Tries the integer `k`-th root of `n` for all primes `k` up to the size of `n` in bits, as
+/// larger powers of `r >= 2` don't fit.
+pub fn perfect_power(n: &BigUint) -> Option<(BigUint, u32)> {
+    let bits = n.bits() as u32;
+    (2..=bits)
+        .filter(|k| (2..*k).take_while(|d| d * d <= *k).all(|d| k % d != 0))
+        .find_map(|k| {
+            let r = n.nth_root(k);
+            if num_traits::pow(r.clone(), k as usize) == *n {
+                Some((r, k))
+            } else {
+                None
+            }
+        })
+}
+
+#[cfg(all(test, feature = "prover"))]
 mod tests {
     use super::*;
 
@@ -153,6 +771,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_multi_modpow() {
+        let rng = &mut thread_rng();
+
+        for &bits in &[64, 256, 1024, 2048] {
+            for _ in 0..20 {
+                let n = rng.gen_biguint(bits) | BigUint::one();
+                // bases larger than the modulus, and exponents of different lengths
+                let a = rng.gen_biguint(bits + 10);
+                let b = rng.gen_biguint_below(&n);
+                let (x_bits, y_bits) = (rng.gen_range(0, 300), rng.gen_range(0, 300));
+                let x = rng.gen_biguint(x_bits);
+                let y = rng.gen_biguint(y_bits);
+
+                let expected = (a.modpow(&x, &n) * b.modpow(&y, &n)) % &n;
+                assert_eq!(multi_modpow(&a, &x, &b, &y, &n), expected);
+                assert_eq!(multi_modpow(&b, &y, &a, &x, &n), expected);
+            }
+        }
+
+        let n = BigUint::from(1000u32);
+        let (a, b) = (BigUint::from(3u32), BigUint::from(7u32));
+        let (x, y) = (BigUint::from(5u32), BigUint::from(2u32));
+        assert_eq!(
+            multi_modpow(&a, &x, &b, &y, &n),
+            BigUint::from(243u32 * 49 % 1000)
+        );
+        assert_eq!(
+            multi_modpow(
+                &a,
+                &BigUint::zero(),
+                &b,
+                &BigUint::zero(),
+                &BigUint::from(7u32)
+            ),
+            BigUint::one()
+        );
+        assert!(multi_modpow(&a, &x, &b, &y, &BigUint::one()).is_zero());
+    }
+
     #[test]
     fn test_modpow() {
         let cases = vec![["49", "-6193420858199668535", "2881", "6"]];
@@ -169,6 +827,261 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_modpow_not_invertible() {
+        // 2881 = 43 * 67
+        let n = BigUint::from(2881u32);
+        let e = BigInt::from(-5);
+
+        assert_eq!(
+            modpow_uint_int(&BigUint::from(43u32 * 3), &e, &n),
+            Err(MathError::NotInvertible)
+        );
+        assert_eq!(
+            modpow_uint_int(&BigUint::from(0u32), &e, &n),
+            Err(MathError::NotInvertible)
+        );
+        assert_eq!(
+            modpow_uint_int(&BigUint::from(49u32), &e, &BigUint::from(0u32)),
+            Err(MathError::ZeroModulus)
+        );
+
+        // non-negative exponents don't need an inverse
+        assert_eq!(
+            modpow_uint_int(&BigUint::from(43u32), &BigInt::from(2), &n),
+            Ok(BigUint::from(43u32 * 43))
+        );
+    }
+
+    #[test]
+    fn test_extended_gcd_int() {
+        let mut rng = thread_rng();
+
+        for i in 0..1000 {
+            // mix in zeros and common factors
+            let f = rng.gen_bigint(32);
+            let mut a = rng.gen_bigint(128) * &f;
+            let mut b = rng.gen_bigint(128) * &f;
+            match i % 10 {
+                0 => a = BigInt::zero(),
+                1 => b = BigInt::zero(),
+                2 => b = -a.clone(),
+                _ => {}
+            }
+
+            let (g, x, y) = extended_gcd_int(&a, &b);
+            assert_eq!(&a * &x + &b * &y, g, "{} {}", a, b);
+            assert_eq!(g, a.abs().gcd(&b.abs()), "{} {}", a, b);
+            assert!(!g.is_negative());
+        }
+
+        let zero = BigInt::zero();
+        assert_eq!(
+            extended_gcd_int(&zero, &zero),
+            (zero.clone(), zero.clone(), zero.clone())
+        );
+        assert_eq!(
+            extended_gcd_int(&zero, &BigInt::from(-7)),
+            (BigInt::from(7), zero.clone(), BigInt::from(-1))
+        );
+    }
+
+    #[test]
+    fn test_extended_gcd() {
+        let mut rng = thread_rng();
+
+        for _ in 0..100 {
+            let a = rng.gen_biguint(256);
+            let b = rng.gen_biguint(256);
+
+            // same result as for the unsigned algorithm
+            let (g, x, y) = extended_gcd(&a, &b);
+            let expected = ExtendedGcd::extended_gcd(&a, &b);
+            assert_eq!((BigInt::from(g), x, y), expected);
+        }
+    }
+
+    #[test]
+    fn test_extended_gcd_unbalanced() {
+        let mut rng = thread_rng();
+
+        for &(small, large) in &[
+            (1, 300),
+            (64, 1000),
+            (128, 20_000),
+            (128, 300_000),
+            (4096, 200_000),
+        ] {
+            for _ in 0..3 {
+                let a = rng.gen_biguint(small) | BigUint::one();
+                let b = rng.gen_biguint(large);
+                // with a common factor
+                let f = rng.gen_biguint(32);
+                let (fa, fb) = (&a * &f, &b * &f);
+
+                for (a, b) in &[(&a, &b), (&b, &a), (&fa, &fb), (&fb, &fa)] {
+                    let (g, x, y) = extended_gcd(a, b);
+                    let expected = ExtendedGcd::extended_gcd(*a, *b);
+                    assert_eq!((BigInt::from(g), x, y), expected);
+                }
+            }
+        }
+
+        // reduced to zero
+        let a = BigUint::from(7u32);
+        let b = &a << 1000;
+        assert_eq!(
+            extended_gcd(&a, &b),
+            (a.clone(), BigInt::one(), BigInt::zero())
+        );
+    }
+
+    #[test]
+    fn test_crt() {
+        let mut rng = thread_rng();
+
+        for _ in 0..10 {
+            let ms = (0..4).map(|_| rng.gen_prime(64)).collect::<Vec<_>>();
+            let m = product(&ms);
+            let x = rng.gen_biguint_below(&m);
+
+            let residues = ms
+                .iter()
+                .map(|m_i| (&x % m_i, m_i.clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(crt(&residues), Some(x.clone()));
+            assert_eq!(
+                crt2(&(&x % &ms[0]), &ms[0], &(&x % &ms[1]), &ms[1]),
+                Some(&x % (&ms[0] * &ms[1]))
+            );
+        }
+
+        assert_eq!(crt(&[]), Some(BigUint::zero()));
+    }
+
+    #[test]
+    fn test_crt_not_coprime() {
+        let six = BigUint::from(6u32);
+        let ten = BigUint::from(10u32);
+        let seven = BigUint::from(7u32);
+
+        assert_eq!(crt2(&BigUint::one(), &six, &BigUint::one(), &ten), None);
+        assert_eq!(crt2(&BigUint::one(), &seven, &BigUint::one(), &seven), None);
+        assert_eq!(
+            crt(&[
+                (BigUint::one(), seven.clone()),
+                (BigUint::one(), six),
+                (BigUint::one(), ten),
+            ]),
+            None
+        );
+        assert_eq!(
+            crt2(&BigUint::one(), &BigUint::zero(), &BigUint::one(), &seven),
+            None
+        );
+    }
+
+    #[test]
+    fn test_modpow_crt() {
+        let mut rng = thread_rng();
+
+        for _ in 0..10 {
+            let p = rng.gen_prime(128);
+            let q = rng.gen_prime(128);
+            let n = &p * &q;
+
+            let e = rng.gen_biguint(512);
+            for a in &[
+                rng.gen_biguint_below(&n),
+                // not coprime to n
+                &p * rng.gen_biguint(64),
+                &q * rng.gen_biguint(64),
+                BigUint::zero(),
+                BigUint::one(),
+            ] {
+                assert_eq!(modpow_crt(a, &e, &p, &q), Some(a.modpow(&e, &n)));
+                assert_eq!(
+                    modpow_crt(a, &BigUint::zero(), &p, &q),
+                    Some(BigUint::one())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_jacobi_small() {
+        let j = |a: u32, n: u32| jacobi(&BigUint::from(a), &BigUint::from(n));
+
+        assert_eq!(j(1, 1), Some(1));
+        assert_eq!(j(0, 1), Some(1));
+        assert_eq!(j(0, 3), Some(0));
+        assert_eq!(j(2, 7), Some(1));
+        assert_eq!(j(3, 7), Some(-1));
+        assert_eq!(j(1001, 9907), Some(-1));
+        assert_eq!(j(19, 45), Some(1));
+        assert_eq!(j(8, 21), Some(-1));
+        assert_eq!(j(5, 21), Some(1));
+        assert_eq!(j(6, 15), Some(0));
+        assert_eq!(j(3, 4), None);
+        assert_eq!(j(3, 0), None);
+    }
+
+    #[test]
+    fn test_jacobi_table() {
+        // the Legendre symbol by brute force, and the Jacobi symbol as a product of those
+        let legendre = |a: u32, p: u32| -> i8 {
+            if a % p == 0 {
+                0
+            } else if (1..p).any(|x| (x * x) % p == a % p) {
+                1
+            } else {
+                -1
+            }
+        };
+
+        for n in (1..200u32).step_by(2) {
+            for a in 0..100u32 {
+                let mut expected = 1;
+                let mut m = n;
+                let mut p = 3;
+                while m > 1 {
+                    while m % p == 0 {
+                        expected *= legendre(a, p);
+                        m /= p;
+                    }
+                    p += 2;
+                }
+
+                assert_eq!(
+                    jacobi(&BigUint::from(a), &BigUint::from(n)),
+                    Some(expected),
+                    "({} / {})",
+                    a,
+                    n
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_jacobi_euler_criterion() {
+        let mut rng = thread_rng();
+
+        // 2^127 - 1 is prime
+        let p = (BigUint::one() << 127) - 1u32;
+        let e = (&p - 1u32) >> 1;
+
+        for _ in 0..100 {
+            let a = rng.gen_biguint(256);
+            let expected = match a.modpow(&e, &p) {
+                ref r if r.is_zero() => 0,
+                ref r if r.is_one() => 1,
+                _ => -1,
+            };
+            assert_eq!(jacobi(&a, &p), Some(expected));
+        }
+    }
+
     #[test]
     fn test_root_factor() {
         let mut rng = thread_rng();
@@ -196,6 +1109,203 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_subproduct_tree() {
+        let mut rng = thread_rng();
+        let naive = |xs: &[BigUint], skip: &[usize]| -> BigUint {
+            xs.iter()
+                .enumerate()
+                .filter(|(i, _)| !skip.contains(i))
+                .map(|(_, x)| x)
+                .product()
+        };
+
+        for len in 0..20 {
+            // small values, so that some repeat and share factors
+            let xs = (0..len)
+                .map(|_| rng.gen_biguint(6) + 2u32)
+                .collect::<Vec<_>>();
+            let tree = SubproductTree::build(&xs);
+            assert_eq!(tree.len(), len);
+            assert_eq!(tree.leaves(), &xs[..]);
+            assert_eq!(tree.product(), naive(&xs, &[]));
+
+            for i in 0..len {
+                assert_eq!(tree.product_excluding(i), naive(&xs, &[i]));
+            }
+            for _ in 0..5 {
+                let subset = (0..rng.gen_range(0, len + 1))
+                    .map(|_| rng.gen_range(0, len))
+                    .collect::<Vec<_>>();
+                assert_eq!(tree.exponent_for_subset(&subset), naive(&xs, &subset));
+            }
+            let all = (0..len).collect::<Vec<_>>();
+            assert_eq!(tree.exponent_for_subset(&all), BigUint::one());
+
+            let m = rng.gen_biguint(64);
+            let expected = (0..len)
+                .filter(|&i| !xs[i].gcd(&m).is_one())
+                .collect::<Vec<_>>();
+            assert_eq!(tree.gcd_against(&m), expected);
+            let remainders = xs.iter().map(|x| &m % x).collect::<Vec<_>>();
+            assert_eq!(tree.remainders(&m), remainders);
+        }
+    }
+
+    #[test]
+    fn test_root_factor_duplicates() {
+        let mut rng = thread_rng();
+        let n = rng.gen_biguint(64);
+        let g = rng.gen_biguint(64);
+        let x = [3u32, 5, 3, 7, 5, 3]
+            .iter()
+            .map(|x| BigUint::from(*x))
+            .collect::<Vec<_>>();
+
+        let y = g.modpow(&product(&x), &n);
+        for (root, x_i) in root_factor(&g, &x, &n).iter().zip(&x) {
+            assert_eq!(root.modpow(x_i, &n), y);
+        }
+        assert!(root_factor(&g, &[], &n).is_empty());
+    }
+
+    #[test]
+    fn test_cross_power() {
+        let mut rng = thread_rng();
+        let n = rng.gen_biguint(64);
+
+        for m in 1..10 {
+            let a = (0..m).map(|_| rng.gen_biguint(64)).collect::<Vec<_>>();
+            let x = (0..m).map(|_| rng.gen_biguint(16)).collect::<Vec<_>>();
+
+            let expected = (0..m).fold(BigUint::one(), |acc, i| {
+                let others = product(&[&x[..i], &x[i + 1..]].concat());
+                acc * a[i].modpow(&others, &n) % &n
+            });
+            assert_eq!(cross_power(&a, &x, &n), expected);
+        }
+    }
+
+    #[test]
+    fn test_common_factor() {
+        let xs = [7u32, 11, 13, 17, 19]
+            .iter()
+            .map(|x| BigUint::from(*x))
+            .collect::<Vec<_>>();
+        assert_eq!(common_factor(&xs), None);
+        assert_eq!(common_factor(&xs[..1]), None);
+
+        let mut shared = xs.clone();
+        shared[3] = BigUint::from(11u32 * 23);
+        assert_eq!(common_factor(&shared), Some((1, 3)));
+        shared.push(BigUint::from(7u32));
+        assert_eq!(common_factor(&shared), Some((0, 5)));
+
+        // against all pairs
+        let mut rng = thread_rng();
+        for _ in 0..20 {
+            let xs = (0..8)
+                .map(|_| rng.gen_biguint(12) + 2u32)
+                .collect::<Vec<_>>();
+            let pair = (0..xs.len())
+                .flat_map(|i| (i + 1..xs.len()).map(move |j| (i, j)))
+                .find(|&(i, j)| !xs[i].gcd(&xs[j]).is_one());
+            assert_eq!(common_factor(&xs), pair);
+        }
+    }
+
+    #[test]
+    fn test_find_common_factors() {
+        let mut rng = thread_rng();
+        let ps = (0..6).map(|_| rng.gen_prime(64)).collect::<Vec<_>>();
+        assert_eq!(find_common_factors(&ps), Vec::new());
+        assert_eq!(find_common_factors(&ps[..1]), Vec::new());
+        assert_eq!(find_common_factors(&[]), Vec::new());
+
+        // planted duplicates
+        let mut xs = ps.clone();
+        xs.push(ps[1].clone());
+        xs.push(ps[4].clone());
+        xs.push(ps[1].clone());
+        assert_eq!(
+            find_common_factors(&xs),
+            vec![
+                (1, 6, ps[1].clone()),
+                (1, 8, ps[1].clone()),
+                (4, 7, ps[4].clone()),
+                (6, 8, ps[1].clone()),
+            ]
+        );
+
+        // planted composites sharing one prime, with each other and with a prime
+        let mut xs = ps[..4].to_vec();
+        xs.push(&ps[4] * &ps[0]);
+        xs.push(&ps[5] * &ps[0]);
+        xs.push(&ps[4] * &ps[5]);
+        assert_eq!(
+            find_common_factors(&xs),
+            vec![
+                (0, 4, ps[0].clone()),
+                (0, 5, ps[0].clone()),
+                (4, 5, ps[0].clone()),
+                (4, 6, ps[4].clone()),
+                (5, 6, ps[5].clone()),
+            ]
+        );
+        assert_eq!(common_factor(&xs), Some((0, 4)));
+
+        // against all pairs
+        for _ in 0..20 {
+            let xs = (0..12)
+                .map(|_| rng.gen_biguint(12) + 2u32)
+                .collect::<Vec<_>>();
+            let pairs = (0..xs.len())
+                .flat_map(|i| (i + 1..xs.len()).map(move |j| (i, j)))
+                .map(|(i, j)| (i, j, xs[i].gcd(&xs[j])))
+                .filter(|(_, _, d)| !d.is_one())
+                .collect::<Vec<_>>();
+            assert_eq!(find_common_factors(&xs), pairs);
+        }
+    }
+
+    #[test]
+    fn test_perfect_power() {
+        let three = BigUint::from(3u32);
+        for k in &[2usize, 3, 5, 40, 81] {
+            let (r, e) = perfect_power(&three.pow(*k)).unwrap();
+            assert_eq!(num_traits::pow(r, e as usize), three.pow(*k));
+        }
+        // 3^40 = (3^20)^2
+        assert_eq!(
+            perfect_power(&three.pow(40usize)),
+            Some((three.pow(20usize), 2))
+        );
+        assert_eq!(
+            perfect_power(&three.pow(81usize)),
+            Some((three.pow(27usize), 3))
+        );
+
+        let mut rng = thread_rng();
+        let p = rng.gen_prime(128);
+        let q = rng.gen_prime(128);
+        assert_eq!(perfect_power(&(&p * &q)), None);
+        assert_eq!(perfect_power(&(&p * &p * &q)), None);
+        assert_eq!(perfect_power(&(&p * &p * &p)), Some((p, 3)));
+        assert_eq!(perfect_power(&BigUint::from(1u32)), None);
+    }
+
+    #[test]
+    fn test_product() {
+        let mut rng = thread_rng();
+
+        for len in 0..20 {
+            let xs = (0..len).map(|_| rng.gen_biguint(64)).collect::<Vec<_>>();
+            let expected = xs.iter().fold(BigUint::one(), |acc, x| acc * x);
+            assert_eq!(product(&xs), expected);
+            assert_eq!(product_iter(xs), expected);
+        }
+    }
+
     #[test]
     fn test_shamir_trick() {
         let mut rng = thread_rng();
@@ -232,5 +1342,4 @@ mod tests {
             assert_eq!(&root.clone().modpow(&(x.clone() * &y), &n), &a);
         }
     }
-
 }
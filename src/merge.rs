@@ -0,0 +1,206 @@
+//! Merging two accumulators over disjoint sets, e.g. two shards, with a proof for outsiders.
+//!
+//! With the states `A_1 = g^s_1` and `A_2 = g^s_2`, the merged state is `A_1^s_2 = g^(s_1 s_2)`.
+//! The proof shows that the exponent taking `g` to `A_2` also takes `A_1` to the merged state,
+//! see `SameSetProof`, without revealing either set.
+
+use std::fmt;
+
+use num_bigint::BigUint;
+
+use crate::accumulator::{AccumulatorError, PublicState};
+#[cfg(feature = "prover")]
+use crate::equality::prove_shared_exponent;
+use crate::equality::{ver_shared_exponent, SameSetProof, Statement};
+
+const MERGE_TAG: &[u8] = b"accumulators/merge";
+
+/// Proof that a state is the merge of two others, from `Accumulator::merge`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MergeProof(pub SameSetProof);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+    /// The accumulators use different moduli or generators.
+    ParamMismatch,
+    /// The sets share an element, or the products a factor.
+    Overlap,
+    /// The merged accumulator would exceed the capacity limits of the first one.
+    Capacity(AccumulatorError),
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MergeError::ParamMismatch => write!(f, "the accumulators use different parameters"),
+            MergeError::Overlap => write!(f, "the sets are not disjoint"),
+            MergeError::Capacity(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+/// Proves that `merged = state1.root^s2`, with `state2.root = g^s2`.
+#[cfg(feature = "prover")]
+pub(crate) fn prove_merge(
+    state1: &PublicState,
+    state2: &PublicState,
+    merged: &BigUint,
+    s2: &BigUint,
+) -> MergeProof {
+    let statements = statements(state1, state2, merged);
+    MergeProof(prove_shared_exponent(MERGE_TAG, &statements, s2))
+}
+
+/// Verifies that `merged` is the state of the union of the accumulators with the states
+/// `state1` and `state2`.
+///
+/// All states must use the same parameters. That the sets were disjoint is only checked by
+/// the merging party.
+pub fn ver_merge(
+    state1: &PublicState,
+    state2: &PublicState,
+    merged: &PublicState,
+    pi: &MergeProof,
+) -> bool {
+    let same_params = |s: &PublicState| s.n == state1.n && s.g == state1.g;
+    if !same_params(state2) || !same_params(merged) {
+        return false;
+    }
+
+    let statements = statements(state1, state2, &merged.root);
+    ver_shared_exponent(MERGE_TAG, &statements, &pi.0)
+}
+
+/// `g^s2 = state2.root` and `state1.root^s2 = merged`.
+fn statements<'a>(
+    state1: &'a PublicState,
+    state2: &'a PublicState,
+    merged: &'a BigUint,
+) -> [Statement<'a>; 2] {
+    [
+        Statement::new(&state2.n, &state2.g, &state2.root),
+        Statement::new(&state1.n, &state1.root, merged),
+    ]
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::{Accumulator, CapacityLimits};
+    use crate::group::RSAGroup;
+    use crate::primes::RandPrimes;
+    use crate::traits::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn setup(rng: &mut ChaChaRng) -> (Accumulator, Accumulator, Vec<BigUint>) {
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc1 = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc2 = Accumulator::new(acc1.modulus().clone(), acc1.generator().clone());
+
+        let xs = rng.gen_distinct_primes(6, 128);
+        acc1.add_batch_no_proof(&xs[..3]);
+        acc2.add_batch_no_proof(&xs[3..5]);
+
+        (acc1, acc2, xs)
+    }
+
+    #[test]
+    fn test_merge() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc1, acc2, xs) = setup(rng);
+        let (state1, state2) = (acc1.public_state(), acc2.public_state());
+
+        let (merged, pi) = acc1.merge(acc2).unwrap();
+        assert!(ver_merge(&state1, &state2, &merged.public_state(), &pi));
+        assert_eq!(merged.len(), 5);
+
+        // the same state as adding all elements to one accumulator
+        let mut expected = Accumulator::new(state1.n.clone(), state1.g.clone());
+        expected.add_batch_no_proof(&xs[..5]);
+        assert_eq!(merged.state(), expected.state());
+        for x in &xs[..5] {
+            assert!(merged.ver_mem(&merged.mem_wit_create(x), x));
+        }
+
+        // the order of the states matters
+        assert!(!ver_merge(&state2, &state1, &merged.public_state(), &pi));
+    }
+
+    #[test]
+    fn test_merge_errors() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc1, mut acc2, xs) = setup(rng);
+
+        let mut overlapping = acc2.clone();
+        overlapping.add(&xs[0]);
+        assert_eq!(
+            acc1.clone().merge(overlapping).err(),
+            Some(MergeError::Overlap)
+        );
+
+        let other = Accumulator::setup::<RSAGroup, _>(rng, 256);
+        assert_eq!(
+            acc1.clone().merge(other).err(),
+            Some(MergeError::ParamMismatch)
+        );
+
+        acc2.add(&xs[5]);
+        let limited = acc1.with_capacity_limits(CapacityLimits {
+            max_elements: Some(5),
+            max_set_bits: None,
+        });
+        assert!(matches!(
+            limited.merge(acc2).err(),
+            Some(MergeError::Capacity(
+                AccumulatorError::CapacityExceeded { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_forged_merge() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc1, acc2, xs) = setup(rng);
+        let (state1, state2) = (acc1.public_state(), acc2.public_state());
+        let (merged, pi) = acc1.merge(acc2).unwrap();
+        let merged = merged.public_state();
+
+        // a state with an extra element
+        let mut forged = merged.clone();
+        forged.root = forged.root.modpow(&xs[5], &forged.n);
+        assert!(!ver_merge(&state1, &state2, &forged, &pi));
+
+        let tampered = [
+            SameSetProof {
+                z1: &pi.0.z1 + 1u32,
+                ..pi.0.clone()
+            },
+            SameSetProof {
+                q2: &pi.0.q2 + 1u32,
+                ..pi.0.clone()
+            },
+            SameSetProof {
+                r: &pi.0.r + 1u32,
+                ..pi.0.clone()
+            },
+        ];
+        for t in &tampered {
+            assert!(!ver_merge(
+                &state1,
+                &state2,
+                &merged,
+                &MergeProof(t.clone())
+            ));
+        }
+
+        // the merged state must have the same parameters
+        let mut other = merged.clone();
+        other.g = &other.g + 1u32;
+        assert!(!ver_merge(&state1, &state2, &other, &pi));
+    }
+}
@@ -0,0 +1,220 @@
+//! Moving an accumulated set to new parameters, e.g. when the modulus of a trusted setup is
+//! retired.
+//!
+//! `Accumulator::migrate` rebuilds the accumulator under the new parameters, and proves with a
+//! same-set proof, see `equality`, that the old and the new state commit to the same product.
+//! The proof is bound to both parameter sets and to a digest of the elements, see
+//! `elements_digest`, so it can't be replayed for another rotation or another set.
+
+use num_bigint::BigUint;
+
+#[cfg(feature = "prover")]
+use crate::accumulator::Accumulator;
+use crate::accumulator::PublicState;
+#[cfg(feature = "prover")]
+use crate::equality::prove_shared_exponent;
+use crate::equality::{ver_shared_exponent, SameSetProof, Statement};
+use crate::math::product;
+use crate::setup::SetupParams;
+use crate::transcript::{Blake2bTranscript, Transcript};
+
+const MIGRATION_TAG: &[u8] = b"accumulators/migration";
+const DIGEST_TAG: &[u8] = b"accumulators/elements-digest";
+
+/// Proof that an accumulator under new parameters holds the same set as one under the old
+/// parameters, from `Accumulator::migrate`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MigrationProof {
+    /// The old and the new state share the exponent.
+    pub same_set: SameSetProof,
+}
+
+/// Returns a digest of the set `xs`, independent of their order.
+///
+/// Auditors holding the elements compute it themselves and pass it to `ver_migration`.
+pub fn elements_digest(xs: &[BigUint]) -> [u8; 32] {
+    digest_product(&product(xs))
+}
+
+/// The digest of the set with the product `s`.
+pub(crate) fn digest_product(s: &BigUint) -> [u8; 32] {
+    let mut t = Blake2bTranscript::new(DIGEST_TAG);
+    t.append_message(b"s", &s.to_bytes_be());
+
+    let mut digest = [0u8; 32];
+    t.challenge_bytes(b"digest", &mut digest);
+    digest
+}
+
+/// Proves that `old` and `new` hold the same set, the product `s` with the digest `digest`.
+#[cfg(feature = "prover")]
+pub(crate) fn prove_migration(
+    old: &Accumulator,
+    new: &Accumulator,
+    s: &BigUint,
+    digest: &[u8; 32],
+) -> MigrationProof {
+    let (old_state, new_state) = (old.public_state(), new.public_state());
+    let tag = tag(old.params(), new.params(), digest);
+    let statements = statements(&old_state, &new_state);
+
+    MigrationProof {
+        same_set: prove_shared_exponent(&tag, &statements, s),
+    }
+}
+
+/// Verifies that the accumulator with the state `new_state` under `new_params` holds the same
+/// set as the one with `old_state` under `old_params`, the set with the digest
+/// `elements_digest`.
+///
+/// Both states must use the modulus and generator of their parameters.
+pub fn ver_migration(
+    old_state: &PublicState,
+    old_params: &SetupParams,
+    new_state: &PublicState,
+    new_params: &SetupParams,
+    proof: &MigrationProof,
+    elements_digest: &[u8; 32],
+) -> bool {
+    let _span = span!("ver_migration");
+    let matches = |state: &PublicState, params: &SetupParams| {
+        &state.n == params.modulus() && &state.g == params.generator()
+    };
+    if !matches(old_state, old_params) || !matches(new_state, new_params) {
+        return false;
+    }
+
+    let tag = tag(old_params, new_params, elements_digest);
+    let statements = statements(old_state, new_state);
+
+    ver_shared_exponent(&tag, &statements, &proof.same_set)
+}
+
+/// The domain of the proof: both parameter sets and the digest.
+fn tag(old: &SetupParams, new: &SetupParams, digest: &[u8; 32]) -> Vec<u8> {
+    let mut tag = MIGRATION_TAG.to_vec();
+    tag.extend(&old.params_hash());
+    tag.extend(&new.params_hash());
+    tag.extend(digest);
+
+    tag
+}
+
+fn statements<'a>(old: &'a PublicState, new: &'a PublicState) -> [Statement<'a>; 2] {
+    [
+        Statement::new(&old.n, &old.g, &old.root),
+        Statement::new(&new.n, &new.g, &new.root),
+    ]
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use crate::primes::RandPrimes;
+    use crate::traits::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn setup(rng: &mut ChaChaRng) -> (Accumulator, SetupParams, Vec<BigUint>) {
+        let int_size_bits = 256; // insecure, but faster tests
+        let old_params = SetupParams::generate(rng, int_size_bits).unwrap();
+        let new_params = SetupParams::generate(rng, int_size_bits).unwrap();
+        let mut acc = Accumulator::new_with_params(Arc::new(old_params));
+        let xs = rng.gen_distinct_primes(6, 128);
+        acc.add_batch_no_proof(&xs[..5]);
+
+        (acc, new_params, xs)
+    }
+
+    #[test]
+    fn test_migration() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, new_params, xs) = setup(rng);
+        let old_params = acc.params().clone();
+
+        let (new, pi) = acc.migrate(&new_params);
+        assert_eq!(new.params().as_ref(), &new_params);
+        assert_eq!(new.len(), 5);
+        assert!(xs[..5].iter().all(|x| new.contains(x)));
+        let w = new.mem_wit_create(&xs[0]);
+        assert!(new.ver_mem(&w, &xs[0]));
+
+        let (old_state, new_state) = (acc.public_state(), new.public_state());
+        let digest = elements_digest(&xs[..5]);
+        let mut shuffled = xs[..5].to_vec();
+        shuffled.reverse();
+        assert_eq!(elements_digest(&shuffled), digest);
+        assert!(ver_migration(
+            &old_state,
+            &old_params,
+            &new_state,
+            &new_params,
+            &pi,
+            &digest
+        ));
+
+        // for another set, or other parameters
+        assert!(!ver_migration(
+            &old_state,
+            &old_params,
+            &new_state,
+            &new_params,
+            &pi,
+            &elements_digest(&xs[..4])
+        ));
+        assert!(!ver_migration(
+            &new_state,
+            &new_params,
+            &old_state,
+            &old_params,
+            &pi,
+            &digest
+        ));
+        assert!(!ver_migration(
+            &old_state,
+            &old_params,
+            &old_state,
+            &old_params,
+            &pi,
+            &digest
+        ));
+    }
+
+    #[test]
+    fn test_migration_changed_set() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, new_params, xs) = setup(rng);
+        let old_params = acc.params().clone();
+        let (mut new, pi) = acc.migrate(&new_params);
+        let (old_state, digest) = (acc.public_state(), elements_digest(&xs[..5]));
+
+        // an element added during the migration
+        let mut added = new.clone();
+        added.add(&xs[5]);
+        let ver = |new: &Accumulator, pi: &MigrationProof| {
+            ver_migration(
+                &old_state,
+                &old_params,
+                &new.public_state(),
+                &new_params,
+                pi,
+                &digest,
+            )
+        };
+        assert!(!ver(&added, &pi));
+
+        // an element dropped
+        new.del(&xs[2]).unwrap();
+        assert!(!ver(&new, &pi));
+
+        // proving with the product of the old set for the changed state doesn't help
+        let forged = prove_migration(&acc, &new, acc.product(), &digest);
+        assert!(!ver(&new, &forged));
+        let forged = prove_migration(&acc, &added, acc.product(), &digest);
+        assert!(!ver(&added, &forged));
+    }
+}
@@ -0,0 +1,417 @@
+//! A reference accumulator, for differential tests and as a baseline for benchmarks.
+//!
+//! `NaiveAccumulator` keeps its elements in a list, and recomputes the state and every
+//! witness from the generator on each call, multiplying the elements one by one. It shares
+//! nothing with `Accumulator` but the parameters and the proof system, so that both can be
+//! run side by side and compared. It is far too slow for anything else.
+
+use std::sync::Arc;
+
+use num_bigint::traits::ModInverse;
+use num_bigint::{BigInt, BigUint};
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+
+use crate::accumulator::{
+    BatchProof, MembershipWitness, NewState, NonMembershipWitness, OldState, PublicState,
+};
+use crate::proofs::{self, GroupParams};
+use crate::setup::SetupParams;
+use crate::traits::*;
+
+/// An accumulator recomputing everything from its list of elements.
+///
+/// States and membership witnesses are the same as those of an `Accumulator` with the same
+/// parameters and operations. Non-membership witnesses may use other Bezout coefficients, but
+/// verify the same.
+#[derive(Debug, Clone)]
+pub struct NaiveAccumulator {
+    params: Arc<SetupParams>,
+    /// The accumulated elements, in the order they were added.
+    elements: Vec<BigUint>,
+    /// `g` to the product of `elements`, recomputed after every change.
+    root: BigUint,
+}
+
+impl NaiveAccumulator {
+    /// Creates an empty accumulator with the shared `params`, e.g. those of an `Accumulator`
+    /// to compare against.
+    pub fn new_with_params(params: Arc<SetupParams>) -> Self {
+        NaiveAccumulator {
+            root: params.g.clone(),
+            params,
+            elements: Vec::new(),
+        }
+    }
+
+    /// Returns the parameters of the accumulator.
+    pub fn params(&self) -> &Arc<SetupParams> {
+        &self.params
+    }
+
+    /// Returns the public part of the accumulator.
+    pub fn public_state(&self) -> PublicState {
+        PublicState {
+            n: self.params.n.clone(),
+            g: self.params.g.clone(),
+            root: self.root.clone(),
+        }
+    }
+
+    /// Returns the accumulated elements, in the order they were added.
+    pub fn elements(&self) -> &[BigUint] {
+        &self.elements
+    }
+
+    /// Returns `true` if `x` is a member.
+    pub fn contains(&self, x: &BigUint) -> bool {
+        self.elements.contains(x)
+    }
+
+    /// Returns the number of accumulated elements.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Returns `true` if no elements are accumulated.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// `g` to the product of `xs`, one exponentiation per element.
+    fn power_of(&self, xs: &[BigUint]) -> BigUint {
+        xs.iter().fold(self.params.g.clone(), |acc, x| {
+            acc.modpow(x, &self.params.n)
+        })
+    }
+
+    /// Returns the elements without one occurrence of `x`, if `x` is a member.
+    fn without(&self, x: &BigUint) -> Option<Vec<BigUint>> {
+        let i = self.elements.iter().position(|y| y == x)?;
+        let mut rest = self.elements.clone();
+        rest.remove(i);
+
+        Some(rest)
+    }
+
+    fn recompute(&mut self) {
+        self.root = self.power_of(&self.elements);
+    }
+
+    /// `a^e mod n`, for negative `e` with the inverse of `a`.
+    fn pow(&self, a: &BigUint, e: &BigInt) -> Option<BigUint> {
+        let n = &self.params.n;
+        let base = if e.is_negative() {
+            a.clone()
+                .mod_inverse(n)?
+                .mod_floor(&BigInt::from(n.clone()))
+        } else {
+            BigInt::from(a.clone())
+        };
+
+        Some(base.to_biguint()?.modpow(&e.abs().to_biguint()?, n))
+    }
+
+    /// Checks that `u^(x_1 ... x_k) = w`, directly and with the proof `pi`.
+    fn ver_transition(&self, pi: &BatchProof, u: &BigUint, w: &BigUint, xs: &[BigUint]) -> bool {
+        let n = &self.params.n;
+        if xs.iter().any(|x| x.is_zero() || x.is_one()) || u >= n || w >= n || u.is_zero() {
+            return false;
+        }
+        let x_star = xs.iter().fold(BigUint::one(), |acc, x| acc * x);
+        let params = GroupParams::new(n.clone());
+
+        &u.modpow(&x_star, n) == w && proofs::poe_verify(&params, &x_star, u, w, &pi.0)
+    }
+}
+
+impl StaticAccumulator for NaiveAccumulator {
+    type MemWitness = MembershipWitness;
+
+    fn state(&self) -> &BigUint {
+        &self.root
+    }
+
+    fn add(&mut self, x: &BigUint) {
+        self.elements.push(x.clone());
+        self.recompute();
+    }
+
+    fn mem_wit_create(&self, x: &BigUint) -> MembershipWitness {
+        let rest = self.without(x).expect("x was not a valid member of set");
+
+        MembershipWitness(self.power_of(&rest))
+    }
+
+    fn ver_mem(&self, w: &MembershipWitness, x: &BigUint) -> bool {
+        let n = &self.params.n;
+        if x.is_zero() || x.is_one() || w.0.is_zero() || &w.0 >= n {
+            return false;
+        }
+
+        w.0.modpow(x, n) == self.root
+    }
+}
+
+impl DynamicAccumulator for NaiveAccumulator {
+    fn del(&mut self, x: &BigUint) -> Option<()> {
+        self.elements = self.without(x)?;
+        self.recompute();
+
+        Some(())
+    }
+}
+
+impl UniversalAccumulator for NaiveAccumulator {
+    type NonMemWitness = NonMembershipWitness;
+
+    fn non_mem_wit_create(&self, x: &BigUint) -> NonMembershipWitness {
+        assert!(!x.is_zero() && !x.is_one(), "x was a member of set");
+        let set = self.elements.iter().fold(BigUint::one(), |acc, y| acc * y);
+
+        // a x + b set = 1
+        let e = BigInt::from(x.clone()).extended_gcd(&BigInt::from(set));
+        assert!(e.gcd.is_one(), "x was a member of set");
+        let d = self.pow(&self.params.g, &e.x).expect("invalid state");
+
+        NonMembershipWitness { d, b: e.y }
+    }
+
+    fn ver_non_mem(&self, w: &NonMembershipWitness, x: &BigUint) -> bool {
+        let n = &self.params.n;
+        if x.is_zero() || x.is_one() || w.d.is_zero() || &w.d >= n {
+            return false;
+        }
+
+        // d^x root^b = g^(a x + b set) = g
+        let d_x = w.d.modpow(x, n);
+        match self.pow(&self.root, &w.b) {
+            Some(root_b) => (d_x * root_b) % n == self.params.g,
+            None => false,
+        }
+    }
+}
+
+impl BatchUpdateAccumulator for NaiveAccumulator {
+    type BatchProof = BatchProof;
+
+    fn batch_add(&mut self, xs: &[BigUint]) -> BatchProof {
+        let old = self.root.clone();
+        self.elements.extend_from_slice(xs);
+        self.recompute();
+
+        let x_star = xs.iter().fold(BigUint::one(), |acc, x| acc * x);
+        let params = GroupParams::new(self.params.n.clone());
+        BatchProof(proofs::poe_prove(&params, &x_star, &old, &self.root))
+    }
+
+    fn ver_batch_add(&self, w: &BatchProof, a_t: &BigUint, xs: &[BigUint]) -> bool {
+        self.ver_transition(w, a_t, &self.root, xs)
+    }
+
+    fn ver_batch_add_transition(
+        &self,
+        w: &BatchProof,
+        old: &OldState,
+        new: &NewState,
+        xs: &[BigUint],
+    ) -> bool {
+        self.ver_transition(w, &old.0, &new.0, xs)
+    }
+
+    fn batch_del(&mut self, pairs: &[(BigUint, MembershipWitness)]) -> Option<BatchProof> {
+        if pairs.is_empty() || pairs.iter().any(|(x, w)| !self.ver_mem(w, x)) {
+            return None;
+        }
+        let old = self.root.clone();
+        let mut rest = self.elements.clone();
+        for (x, _) in pairs {
+            let i = rest.iter().position(|y| y == x)?;
+            rest.remove(i);
+        }
+        self.elements = rest;
+        self.recompute();
+
+        let x_star = pairs.iter().fold(BigUint::one(), |acc, (x, _)| acc * x);
+        let params = GroupParams::new(self.params.n.clone());
+        Some(BatchProof(proofs::poe_prove(
+            &params, &x_star, &self.root, &old,
+        )))
+    }
+
+    fn ver_batch_del(&self, w: &BatchProof, a_t: &BigUint, xs: &[BigUint]) -> bool {
+        self.ver_transition(w, &self.root, a_t, xs)
+    }
+
+    fn ver_batch_del_transition(
+        &self,
+        w: &BatchProof,
+        old: &OldState,
+        new: &NewState,
+        xs: &[BigUint],
+    ) -> bool {
+        self.ver_transition(w, &new.0, &old.0, xs)
+    }
+
+    fn del_w_mem(&mut self, w: &MembershipWitness, x: &BigUint) -> Option<()> {
+        if !self.ver_mem(w, x) {
+            return None;
+        }
+
+        self.del(x)
+    }
+
+    fn create_all_mem_wit(&self, s: &[BigUint]) -> Vec<MembershipWitness> {
+        (0..s.len())
+            .map(|i| {
+                let rest = [&s[..i], &s[i + 1..]].concat();
+                MembershipWitness(self.power_of(&rest))
+            })
+            .collect()
+    }
+
+    fn extend_all_mem_wit(
+        &self,
+        existing: &mut Vec<(BigUint, MembershipWitness)>,
+        new_elements: &[BigUint],
+    ) {
+        let mut s = existing.iter().map(|(x, _)| x.clone()).collect::<Vec<_>>();
+        s.extend_from_slice(new_elements);
+
+        let wits = self.create_all_mem_wit(&s);
+        *existing = s.into_iter().zip(wits).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use num_bigint::Sign;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use crate::accumulator::Accumulator;
+    use crate::primes::RandPrimes;
+
+    /// Runs `steps` random operations on both accumulators, checking after each that they
+    /// agree on the state and on every verification.
+    fn run(rng: &mut ChaChaRng, steps: usize) {
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = Arc::new(SetupParams::generate(rng, int_size_bits).unwrap());
+        let mut acc = Accumulator::new_with_params(params.clone());
+        let mut naive = NaiveAccumulator::new_with_params(params);
+        // a pool of elements, so that members are picked again
+        let pool = rng.gen_distinct_primes(24, 64);
+        let mut members: Vec<BigUint> = Vec::new();
+
+        for step in 0..steps {
+            let x = pool[rng.gen_range(0, pool.len())].clone();
+            let old = acc.state().clone();
+
+            match rng.gen_range(0, 6) {
+                0 if !members.contains(&x) => {
+                    acc.add(&x);
+                    naive.add(&x);
+                    members.push(x.clone());
+                }
+                1 => {
+                    assert_eq!(acc.del(&x), naive.del(&x), "step {}", step);
+                    members.retain(|y| y != &x);
+                }
+                2 => {
+                    let xs = (0..rng.gen_range(1, 4))
+                        .map(|_| pool[rng.gen_range(0, pool.len())].clone())
+                        .filter(|x| !members.contains(x))
+                        .fold(Vec::new(), |mut xs, x| {
+                            if !xs.contains(&x) {
+                                xs.push(x);
+                            }
+                            xs
+                        });
+                    if xs.is_empty() {
+                        continue;
+                    }
+                    let pi_acc = acc.batch_add(&xs);
+                    let pi_naive = naive.batch_add(&xs);
+                    assert_eq!(pi_acc, pi_naive, "step {}", step);
+                    let (o, n) = (OldState(old.clone()), NewState(acc.state().clone()));
+                    assert!(naive.ver_batch_add_transition(&pi_acc, &o, &n, &xs));
+                    assert!(acc.ver_batch_add_transition(&pi_naive, &o, &n, &xs));
+                    members.extend(xs);
+                }
+                3 if members.len() >= 2 => {
+                    let pairs = members[..2]
+                        .iter()
+                        .map(|x| (x.clone(), naive.mem_wit_create(x)))
+                        .collect::<Vec<_>>();
+                    let xs = members[..2].to_vec();
+                    let pi_acc = acc.batch_del(&pairs).unwrap();
+                    let pi_naive = naive.batch_del(&pairs).unwrap();
+                    assert_eq!(pi_acc, pi_naive, "step {}", step);
+                    let (o, n) = (OldState(old.clone()), NewState(acc.state().clone()));
+                    assert!(naive.ver_batch_del_transition(&pi_acc, &o, &n, &xs));
+                    assert!(!naive.ver_batch_del_transition(&pi_acc, &o, &n, &xs[..1]));
+                    assert!(!acc.ver_batch_del_transition(&pi_acc, &o, &n, &xs[..1]));
+                    members.drain(..2);
+                }
+                4 if members.contains(&x) => {
+                    let w = acc.mem_wit_create(&x);
+                    assert_eq!(acc.del_w_mem(&w, &x), naive.del_w_mem(&w, &x));
+                    members.retain(|y| y != &x);
+                }
+                _ => {}
+            }
+
+            assert_eq!(acc.state(), naive.state(), "step {}", step);
+            assert_eq!(acc.len(), naive.len(), "step {}", step);
+
+            // every element of the pool, against both implementations
+            for y in &pool {
+                if members.contains(y) {
+                    let w = naive.mem_wit_create(y);
+                    assert_eq!(w, acc.mem_wit_create(y), "step {}", step);
+                    assert!(acc.ver_mem(&w, y) && naive.ver_mem(&w, y));
+                    let forged = MembershipWitness(&w.0 + 1u32);
+                    assert_eq!(acc.ver_mem(&forged, y), naive.ver_mem(&forged, y));
+                } else {
+                    let u_naive = naive.non_mem_wit_create(y);
+                    let u_acc = acc.non_mem_wit_create(y);
+                    assert!(acc.ver_non_mem(&u_naive, y) && naive.ver_non_mem(&u_naive, y));
+                    assert!(acc.ver_non_mem(&u_acc, y) && naive.ver_non_mem(&u_acc, y));
+                    let w = MembershipWitness(acc.state().clone());
+                    assert_eq!(acc.ver_mem(&w, y), naive.ver_mem(&w, y));
+                }
+            }
+            for m in &members {
+                let u = NonMembershipWitness {
+                    d: naive.params().g.clone(),
+                    b: BigInt::from_biguint(Sign::Minus, m.clone()),
+                };
+                assert_eq!(acc.ver_non_mem(&u, m), naive.ver_non_mem(&u, m));
+            }
+        }
+
+        let mut existing = Vec::new();
+        naive.extend_all_mem_wit(&mut existing, &members);
+        let wits = acc.create_all_mem_wit(&members);
+        assert_eq!(
+            existing.into_iter().map(|(_, w)| w).collect::<Vec<_>>(),
+            wits
+        );
+    }
+
+    #[test]
+    fn test_naive_matches_accumulator() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        run(rng, 200);
+    }
+
+    #[test]
+    fn test_naive_matches_accumulator_seeds() {
+        for seed in 1..4u8 {
+            let rng = &mut ChaChaRng::from_seed([seed; 32]);
+            run(rng, 60);
+        }
+    }
+}
@@ -0,0 +1,401 @@
+//! Self-contained proof packages, for archiving a proof and verifying it again long after the
+//! accumulator moved on.
+//!
+//! A `ProofPackage` holds a proof together with everything needed to check it except the
+//! parameters: the digest of the parameters it was made under, the state and elements it is
+//! about, and the epoch it was created in. `verify_package` checks it against parameters the
+//! verifier trusts, and fails with `VerifyError::ParamsMismatch` for any others.
+//!
+//! The package vouches for the proof, not for the state: that the state was ever the state of
+//! the accumulator has to be established separately, e.g. from a signed `AccumulatorLog`.
+//!
+//! The layout of version 1, written by `ProofPackage::to_bytes`, is
+//!
+//! - the magic bytes `ACPP` and the version, a single byte,
+//! - the digest of the parameters, 32 bytes,
+//! - the epoch, a big-endian `u64`,
+//! - the kind, a single byte: `0` for membership, `1` for non-membership, `2` for a batch
+//!   addition,
+//! - the state, and for a batch addition the state before it, in the `packed` encoding,
+//! - the number of elements, a big-endian `u32`, and the elements in the `packed` encoding,
+//! - the proof, as its length, a big-endian `u32`, followed by its `packed` encoding,
+//! - the Blake2b hash of everything before it.
+//!
+//! Every value has exactly one encoding, so equal packages have equal bytes.
+
+use std::fmt;
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::BigUint;
+
+use crate::accumulator::{BatchProof, MembershipProof, NonMembershipProof, PublicState};
+use crate::encoding::{
+    write_biguint, write_bytes, Packed, PackedReader, ParseError, DEFAULT_MAX_INT_BYTES,
+};
+use crate::proofs::VerifyError;
+use crate::setup::SetupParams;
+
+const MAGIC: &[u8; 4] = b"ACPP";
+const VERSION: u8 = 1;
+
+const CHECKSUM_LEN: usize = 64;
+
+/// What a `ProofPackage` proves.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PackageKind {
+    /// The element is a member of the state, with a `MembershipProof`.
+    Membership,
+    /// The element is not a member of the state, with a `NonMembershipProof`.
+    NonMembership,
+    /// The state is `old_state` with the elements added, with a `BatchProof`.
+    BatchAdd { old_state: BigUint },
+}
+
+impl PackageKind {
+    fn tag(&self) -> u8 {
+        match self {
+            PackageKind::Membership => 0,
+            PackageKind::NonMembership => 1,
+            PackageKind::BatchAdd { .. } => 2,
+        }
+    }
+}
+
+/// A proof with the statement it proves, see the module documentation.
+///
+/// Created with `Accumulator::package_mem_proof`, `package_non_mem_proof` and
+/// `package_batch_add`, and checked with `verify_package`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProofPackage {
+    /// `SetupParams::params_hash` of the parameters the proof was made under.
+    pub params_hash: [u8; 32],
+    /// The state the proof is about, the state after the update for a batch addition.
+    pub state: BigUint,
+    /// The element for membership and non-membership, the added elements for a batch
+    /// addition.
+    pub elements: Vec<BigUint>,
+    /// What is proven.
+    pub kind: PackageKind,
+    /// The `packed` encoding of the proof.
+    pub proof_bytes: Vec<u8>,
+    /// The epoch of the accumulator when the package was created.
+    pub created_epoch: u64,
+}
+
+/// Errors parsing a `ProofPackage`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackageError {
+    /// The input does not start with the magic bytes.
+    BadMagic,
+    /// The version is not supported by this reader.
+    UnsupportedVersion(u8),
+    /// The kind is none of the known ones.
+    InvalidKind(u8),
+    /// A field is malformed.
+    Parse(ParseError),
+    /// The checksum does not match the contents.
+    Checksum,
+    /// There is more data after the checksum.
+    TrailingData,
+}
+
+impl fmt::Display for PackageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PackageError::BadMagic => write!(f, "not a proof package"),
+            PackageError::UnsupportedVersion(v) => write!(f, "unsupported version {}", v),
+            PackageError::InvalidKind(k) => write!(f, "invalid kind {}", k),
+            PackageError::Parse(err) => write!(f, "{}", err),
+            PackageError::Checksum => write!(f, "checksum mismatch"),
+            PackageError::TrailingData => write!(f, "unexpected data after the checksum"),
+        }
+    }
+}
+
+impl std::error::Error for PackageError {}
+
+impl From<ParseError> for PackageError {
+    fn from(err: ParseError) -> Self {
+        PackageError::Parse(err)
+    }
+}
+
+impl ProofPackage {
+    /// Packages `proof` for `kind` over `elements`, made by the accumulator with the
+    /// parameters `params` and the state `state`.
+    #[cfg(feature = "prover")]
+    pub(crate) fn new<P: Packed>(
+        params: &SetupParams,
+        state: &BigUint,
+        elements: Vec<BigUint>,
+        kind: PackageKind,
+        proof: &P,
+        created_epoch: u64,
+    ) -> Self {
+        ProofPackage {
+            params_hash: params.params_hash(),
+            state: state.clone(),
+            elements,
+            kind,
+            proof_bytes: proof.to_packed_bytes(),
+            created_epoch,
+        }
+    }
+
+    /// Encodes the package, see the module documentation for the layout.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        out.extend_from_slice(&self.params_hash);
+        let mut epoch = [0u8; 8];
+        BigEndian::write_u64(&mut epoch, self.created_epoch);
+        out.extend_from_slice(&epoch);
+
+        out.push(self.kind.tag());
+        write_biguint(&mut out, &self.state);
+        if let PackageKind::BatchAdd { old_state } = &self.kind {
+            write_biguint(&mut out, old_state);
+        }
+        let mut len = [0u8; 4];
+        BigEndian::write_u32(&mut len, self.elements.len() as u32);
+        out.extend_from_slice(&len);
+        for x in &self.elements {
+            write_biguint(&mut out, x);
+        }
+        write_bytes(&mut out, &self.proof_bytes);
+
+        let checksum = Blake2b::digest(&out);
+        out.extend_from_slice(&checksum);
+
+        out
+    }
+
+    /// Parses a package encoded with `to_bytes`.
+    ///
+    /// Only the structure is checked here, the proof is parsed and verified by
+    /// `verify_package`.
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, PackageError> {
+        let mut r = PackedReader::new(buf, DEFAULT_MAX_INT_BYTES);
+        if r.take(MAGIC.len()).map_err(|_| PackageError::BadMagic)? != MAGIC {
+            return Err(PackageError::BadMagic);
+        }
+        let version = r.take(1)?[0];
+        if version != VERSION {
+            return Err(PackageError::UnsupportedVersion(version));
+        }
+
+        let mut params_hash = [0u8; 32];
+        params_hash.copy_from_slice(r.take(32)?);
+        let created_epoch = BigEndian::read_u64(r.take(8)?);
+
+        let tag = r.take(1)?[0];
+        let state = r.read_biguint()?;
+        let kind = match tag {
+            0 => PackageKind::Membership,
+            1 => PackageKind::NonMembership,
+            2 => PackageKind::BatchAdd {
+                old_state: r.read_biguint()?,
+            },
+            k => return Err(PackageError::InvalidKind(k)),
+        };
+        let count = BigEndian::read_u32(r.take(4)?) as usize;
+        // every element takes at least its length, don't trust `count` for the allocation
+        let mut elements = Vec::with_capacity(count.min(buf.len() / 4));
+        for _ in 0..count {
+            elements.push(r.read_biguint()?);
+        }
+        let proof_bytes = r.read_bytes()?.to_vec();
+
+        let end = r.position();
+        if r.take(CHECKSUM_LEN)? != &Blake2b::digest(&buf[..end])[..] {
+            return Err(PackageError::Checksum);
+        }
+        if r.position() != buf.len() {
+            return Err(PackageError::TrailingData);
+        }
+
+        Ok(ProofPackage {
+            params_hash,
+            state,
+            elements,
+            kind,
+            proof_bytes,
+            created_epoch,
+        })
+    }
+}
+
+/// Verifies the proof in `pkg` against `trusted_params`.
+///
+/// Fails with `VerifyError::ParamsMismatch` if the package was made under other parameters,
+/// with `VerifyError::MalformedInput` if the proof can't be parsed or there isn't exactly one
+/// element for membership and non-membership, and like the `_detailed` verifiers otherwise.
+pub fn verify_package(pkg: &ProofPackage, trusted_params: &SetupParams) -> Result<(), VerifyError> {
+    let _span = span!("verify_package", elements = pkg.elements.len());
+    if pkg.params_hash != trusted_params.params_hash() {
+        return Err(VerifyError::ParamsMismatch);
+    }
+
+    let state = PublicState {
+        root: pkg.state.clone(),
+        ..trusted_params.public_state()
+    };
+    trusted_params
+        .check_state(&state)
+        .map_err(|_| VerifyError::MalformedInput)?;
+    state.check_elements(&pkg.elements)?;
+
+    match &pkg.kind {
+        PackageKind::Membership => {
+            let pi: MembershipProof = parse_proof(&pkg.proof_bytes, &state.n)?;
+            state.ver_mem_star_detailed(single(&pkg.elements)?, &pi)
+        }
+        PackageKind::NonMembership => {
+            let pi: NonMembershipProof = parse_proof(&pkg.proof_bytes, &state.n)?;
+            state.ver_non_mem_star_detailed(single(&pkg.elements)?, &pi)
+        }
+        PackageKind::BatchAdd { old_state } => {
+            let w: BatchProof = parse_proof(&pkg.proof_bytes, &state.n)?;
+            state.ver_batch_add_detailed(
+                &w,
+                &old_state.clone().into(),
+                &pkg.state.clone().into(),
+                &pkg.elements,
+            )
+        }
+    }
+}
+
+/// Parses a proof that takes up all of `bytes`.
+fn parse_proof<P: Packed>(bytes: &[u8], n: &BigUint) -> Result<P, VerifyError> {
+    match P::parse(bytes, n) {
+        Ok((pi, len)) if len == bytes.len() => Ok(pi),
+        _ => Err(VerifyError::MalformedInput),
+    }
+}
+
+fn single(xs: &[BigUint]) -> Result<&BigUint, VerifyError> {
+    match xs {
+        [x] => Ok(x),
+        _ => Err(VerifyError::MalformedInput),
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    use crate::accumulator::Accumulator;
+    use crate::primes::RandPrimes;
+    use crate::traits::*;
+
+    #[test]
+    fn test_proof_package() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = SetupParams::generate(rng, int_size_bits).unwrap();
+        let other = SetupParams::generate(rng, int_size_bits).unwrap();
+        let mut acc = Accumulator::new_with_params(Arc::new(params.clone()));
+        let xs = rng.gen_distinct_primes(6, 128);
+        acc.advance_epoch::<&[u8]>(&[]).unwrap();
+        let added = acc.package_batch_add(&xs[..4]).unwrap();
+        let mem = acc.package_mem_proof(&xs[1]).unwrap();
+        let non_mem = acc.package_non_mem_proof(&xs[4]).unwrap();
+        assert!(acc.package_mem_proof(&xs[5]).is_err());
+        assert!(acc.package_non_mem_proof(&xs[0]).is_err());
+        assert_eq!(mem.created_epoch, 1);
+        assert_eq!(added.elements, &xs[..4]);
+
+        for pkg in &[&added, &mem, &non_mem] {
+            assert_eq!(verify_package(pkg, &params), Ok(()));
+            assert_eq!(
+                verify_package(pkg, &other),
+                Err(VerifyError::ParamsMismatch)
+            );
+
+            // survives a round trip, byte for byte
+            let bytes = pkg.to_bytes();
+            let parsed = ProofPackage::from_bytes(&bytes).unwrap();
+            assert_eq!(&&parsed, pkg);
+            assert_eq!(parsed.to_bytes(), bytes);
+            assert_eq!(verify_package(&parsed, &params), Ok(()));
+        }
+
+        // the package still verifies once the accumulator moved on
+        acc.add(&xs[5]);
+        assert_eq!(verify_package(&mem, &params), Ok(()));
+
+        // for another element or state
+        let mut forged = mem.clone();
+        forged.elements = vec![xs[2].clone()];
+        assert!(verify_package(&forged, &params).is_err());
+        let mut forged = mem.clone();
+        forged.state = acc.state().clone();
+        assert!(verify_package(&forged, &params).is_err());
+        let mut forged = non_mem.clone();
+        forged.elements.push(xs[5].clone());
+        assert_eq!(
+            verify_package(&forged, &params),
+            Err(VerifyError::MalformedInput)
+        );
+        let mut forged = added.clone();
+        forged.proof_bytes.push(0);
+        assert_eq!(
+            verify_package(&forged, &params),
+            Err(VerifyError::MalformedInput)
+        );
+    }
+
+    #[test]
+    fn test_proof_package_bytes() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::new_with_params(Arc::new(
+            SetupParams::generate(rng, int_size_bits).unwrap(),
+        ));
+        let xs = rng.gen_distinct_primes(3, 128);
+        acc.add_batch_no_proof(&xs[..2]);
+        let bytes = acc.package_mem_proof(&xs[0]).unwrap().to_bytes();
+
+        let mut corrupt = bytes.clone();
+        corrupt[0] ^= 1;
+        assert_eq!(
+            ProofPackage::from_bytes(&corrupt),
+            Err(PackageError::BadMagic)
+        );
+        corrupt = bytes.clone();
+        corrupt[4] = 2;
+        assert_eq!(
+            ProofPackage::from_bytes(&corrupt),
+            Err(PackageError::UnsupportedVersion(2))
+        );
+        corrupt = bytes.clone();
+        corrupt[45] = 7;
+        assert_eq!(
+            ProofPackage::from_bytes(&corrupt),
+            Err(PackageError::InvalidKind(7))
+        );
+        corrupt = bytes.clone();
+        corrupt[20] ^= 1;
+        assert_eq!(
+            ProofPackage::from_bytes(&corrupt),
+            Err(PackageError::Checksum)
+        );
+        corrupt = bytes.clone();
+        corrupt.push(0);
+        assert_eq!(
+            ProofPackage::from_bytes(&corrupt),
+            Err(PackageError::TrailingData)
+        );
+        assert!(matches!(
+            ProofPackage::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(PackageError::Parse(ParseError::Truncated { .. }))
+        ));
+    }
+}
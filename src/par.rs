@@ -0,0 +1,108 @@
+//! Parallel execution, behind the `parallel` feature.
+//!
+//! All uses of rayon go through these helpers. Without the feature they run sequentially,
+//! in the same order and with the same results, so callers don't need to care which build
+//! they are part of. The bounds are the same in both builds, so code that compiles without
+//! the feature also compiles with it.
+
+/// Runs `a` and `b`, potentially in parallel, and returns both results.
+#[inline]
+pub(crate) fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    #[cfg(feature = "parallel")]
+    {
+        rayon::join(a, b)
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        (a(), b())
+    }
+}
+
+/// Applies `f` to all `xs`, potentially in parallel, keeping the order.
+#[inline]
+pub(crate) fn map<T, U, F>(xs: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        xs.par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        xs.iter().map(f).collect()
+    }
+}
+
+/// Below this many elements, splitting work is not worth the overhead.
+pub(crate) const MIN_SPLIT_LEN: usize = 32;
+
+#[cfg(test)]
+mod tests {
+    //! These run both with and without the `parallel` feature, against the same fixtures.
+
+    use super::*;
+
+    use num_bigint::BigUint;
+    use num_traits::{Num, One};
+
+    use crate::math::{product, root_factor};
+
+    fn fixture() -> (BigUint, BigUint, Vec<BigUint>) {
+        let n = BigUint::from_str_radix("d3ff6a5f7ec44c1e0f1c7ab3b3a6b3c5", 16).unwrap();
+        let g = BigUint::from(65537u32);
+        let xs = (0..200u32)
+            .map(|i| BigUint::from(2 * i + 3) << (i as usize % 7))
+            .collect();
+        (n, g, xs)
+    }
+
+    #[test]
+    fn test_join_map_order() {
+        let (a, b) = join(|| 1, || 2);
+        assert_eq!((a, b), (1, 2));
+
+        let xs = (0..1000u32).collect::<Vec<_>>();
+        assert_eq!(
+            map(&xs, |x| x * 2),
+            xs.iter().map(|x| x * 2).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_product_fixture() {
+        let (_, _, xs) = fixture();
+        let expected = xs.iter().fold(BigUint::one(), |acc, x| acc * x);
+
+        for len in 0..xs.len() {
+            assert_eq!(product(&xs[..len]), xs[..len].iter().product());
+        }
+        assert_eq!(product(&xs), expected);
+    }
+
+    #[test]
+    fn test_root_factor_fixture() {
+        let (n, g, xs) = fixture();
+        let roots = root_factor(&g, &xs, &n);
+
+        assert_eq!(roots.len(), xs.len());
+        for (i, root) in roots.iter().enumerate() {
+            let others: BigUint = xs
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, x)| x)
+                .product();
+            assert_eq!(root, &g.modpow(&others, &n));
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Precomputed public parameters, so that short-lived processes don't need to run a (trusted)
+//! setup to get a group of unknown order.
+//!
+//! Each parameter set pairs a published modulus of unknown factorization with a
+//! nothing-up-my-sleeve generator, derived from it and a fixed tag with `derive_generator`.
+//!
+//! Only RSA-2048 is available, larger sets are out of scope for now: the RSA factoring challenge
+//! stops at 2048 bits, and there is no larger published modulus of unknown factorization that
+//! we consider vetted. For 3072- or 4096-bit moduli, run a setup once with
+//! `SetupParams::generate` (or `RsaAccumulatorBuilder`) and share the resulting parameters
+//! instead. `StandardParams` is non-exhaustive, so that sets can be added without breaking
+//! matches on it.
+
+use std::sync::OnceLock;
+
+use num_bigint::BigUint;
+
+use crate::hash::derive_generator;
+
+/// The available parameter sets. Currently only RSA-2048, see the module docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum StandardParams {
+    /// The modulus of the RSA-2048 factoring challenge.
+    Rsa2048,
+}
+
+impl StandardParams {
+    /// Returns the modulus `n`.
+    pub fn modulus(self) -> &'static BigUint {
+        &self.parsed().0
+    }
+
+    /// Returns the generator `g`.
+    pub fn generator(self) -> &'static BigUint {
+        &self.parsed().1
+    }
+
+    /// Returns the size of the modulus in bits.
+    pub fn int_size_bits(self) -> usize {
+        match self {
+            StandardParams::Rsa2048 => 2048,
+        }
+    }
+
+    fn parsed(self) -> &'static (BigUint, BigUint) {
+        match self {
+            StandardParams::Rsa2048 => {
+                static PARSED: OnceLock<(BigUint, BigUint)> = OnceLock::new();
+                PARSED.get_or_init(|| parse(&RSA_2048_MODULUS, RSA_2048_GENERATOR_TAG))
+            }
+        }
+    }
+}
+
+fn parse(modulus: &[u8], tag: &[u8]) -> (BigUint, BigUint) {
+    let n = BigUint::from_bytes_be(modulus);
+    let g = derive_generator(&n, tag);
+
+    (n, g)
+}
+
+const RSA_2048_GENERATOR_TAG: &[u8] = b"accumulators/params/rsa-2048/generator";
+
+/// RSA-2048, from the RSA factoring challenge.
+#[rustfmt::skip]
+const RSA_2048_MODULUS: [u8; 256] = [
+    0xc7, 0x97, 0x0c, 0xee, 0xdc, 0xc3, 0xb0, 0x75, 0x44, 0x90, 0x20, 0x1a,
+    0x7a, 0xa6, 0x13, 0xcd, 0x73, 0x91, 0x10, 0x81, 0xc7, 0x90, 0xf5, 0xf1,
+    0xa8, 0x72, 0x6f, 0x46, 0x35, 0x50, 0xbb, 0x5b, 0x7f, 0xf0, 0xdb, 0x8e,
+    0x1e, 0xa1, 0x18, 0x9e, 0xc7, 0x2f, 0x93, 0xd1, 0x65, 0x00, 0x11, 0xbd,
+    0x72, 0x1a, 0xee, 0xac, 0xc2, 0xac, 0xde, 0x32, 0xa0, 0x41, 0x07, 0xf0,
+    0x64, 0x8c, 0x28, 0x13, 0xa3, 0x1f, 0x5b, 0x0b, 0x77, 0x65, 0xff, 0x8b,
+    0x44, 0xb4, 0xb6, 0xff, 0xc9, 0x33, 0x84, 0xb6, 0x46, 0xeb, 0x09, 0xc7,
+    0xcf, 0x5e, 0x85, 0x92, 0xd4, 0x0e, 0xa3, 0x3c, 0x80, 0x03, 0x9f, 0x35,
+    0xb4, 0xf1, 0x4a, 0x04, 0xb5, 0x1f, 0x7b, 0xfd, 0x78, 0x1b, 0xe4, 0xd1,
+    0x67, 0x31, 0x64, 0xba, 0x8e, 0xb9, 0x91, 0xc2, 0xc4, 0xd7, 0x30, 0xbb,
+    0xbe, 0x35, 0xf5, 0x92, 0xbd, 0xef, 0x52, 0x4a, 0xf7, 0xe8, 0xda, 0xef,
+    0xd2, 0x6c, 0x66, 0xfc, 0x02, 0xc4, 0x79, 0xaf, 0x89, 0xd6, 0x4d, 0x37,
+    0x3f, 0x44, 0x27, 0x09, 0x43, 0x9d, 0xe6, 0x6c, 0xeb, 0x95, 0x5f, 0x3e,
+    0xa3, 0x7d, 0x51, 0x59, 0xf6, 0x13, 0x58, 0x09, 0xf8, 0x53, 0x34, 0xb5,
+    0xcb, 0x18, 0x13, 0xad, 0xdc, 0x80, 0xcd, 0x05, 0x60, 0x9f, 0x10, 0xac,
+    0x6a, 0x95, 0xad, 0x65, 0x87, 0x2c, 0x90, 0x95, 0x25, 0xbd, 0xad, 0x32,
+    0xbc, 0x72, 0x95, 0x92, 0x64, 0x29, 0x20, 0xf2, 0x4c, 0x61, 0xdc, 0x5b,
+    0x3c, 0x3b, 0x79, 0x23, 0xe5, 0x6b, 0x16, 0xa4, 0xd9, 0xd3, 0x73, 0xd8,
+    0x72, 0x1f, 0x24, 0xa3, 0xfc, 0x0f, 0x1b, 0x31, 0x31, 0xf5, 0x56, 0x15,
+    0x17, 0x28, 0x66, 0xbc, 0xcc, 0x30, 0xf9, 0x50, 0x54, 0xc8, 0x24, 0xe7,
+    0x33, 0xa5, 0xeb, 0x68, 0x17, 0xf7, 0xbc, 0x16, 0x39, 0x9d, 0x48, 0xc6,
+    0x36, 0x1c, 0xc7, 0xe5,
+];
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::hash::derive_generator_candidate;
+    use crate::traits::*;
+    use blake2::{Blake2b, Digest};
+    use num_bigint::RandPrime;
+    use num_integer::Integer;
+    use num_traits::One;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_rsa_2048_digests() {
+        let params = StandardParams::Rsa2048;
+
+        assert_eq!(params.modulus().bits(), params.int_size_bits());
+        assert_eq!(
+            hex(&Blake2b::digest(&params.modulus().to_bytes_be())[..]),
+            "428430392e0c682133675d53115093dcaafa15659dcbda9fe3e15592ad5abca04229dbb6197102da92913ba0d80e4bc318f2a2a766deaa586015a4af2ee6bfb6"
+        );
+        assert_eq!(
+            hex(&Blake2b::digest(&params.generator().to_bytes_be())[..]),
+            "8d749419bca44cd54f7fd7554df25ca5f944dffaf28056ee80dca8b47ed3935e3022ece735839e8ddb0d8f55a66e4c4e769f8ba7f44614fd61d715d2326df278"
+        );
+        assert!(params.generator().gcd(params.modulus()).is_one());
+        // the first candidate
+        assert_eq!(
+            params.generator(),
+            &derive_generator_candidate(params.modulus(), RSA_2048_GENERATOR_TAG, 0)
+        );
+    }
+
+    #[test]
+    fn test_with_params() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        for params in &[StandardParams::Rsa2048] {
+            let mut acc = Accumulator::with_params(*params);
+            assert_eq!(acc.modulus(), params.modulus());
+            assert_eq!(acc.state(), params.generator());
+
+            let xs = (0..3).map(|_| rng.gen_prime(128)).collect::<Vec<_>>();
+            for x in &xs {
+                acc.add(x);
+            }
+
+            for x in &xs {
+                let w = acc.mem_wit_create(x);
+                assert!(acc.ver_mem(&w, x));
+            }
+
+            let y = rng.gen_prime(128);
+            let w = acc.non_mem_wit_create(&y);
+            assert!(acc.ver_non_mem(&w, &y));
+        }
+    }
+}
@@ -0,0 +1,329 @@
+//! Deterministic primality testing, for use on the verifier side.
+//!
+//! Hashing to primes has to give the same result for provers and verifiers, so this does not
+//! depend on any randomness. `is_probable_prime` with the default `PrimalityConfig` is the
+//! exact test the crate uses internally, so code that checks its own elements for primality
+//! should use it as well.
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+
+use crate::math::jacobi;
+
+/// The odd primes below 64.
+const SMALL_PRIMES: [u32; 17] = [
+    3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61,
+];
+
+/// Configuration of `is_probable_prime`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrimalityConfig {
+    /// Number of Miller-Rabin rounds, with the primes `2, 3, 5, ...` as bases, in that order.
+    /// At most 18 rounds are run.
+    ///
+    /// Each round lets at most a quarter of the composites through, so for random inputs the
+    /// error is below `4^-rounds`. The bases are fixed, so there are composites constructed to
+    /// pass all of them, use `lucas` for inputs that might be adversarial.
+    pub miller_rabin_rounds: usize,
+    /// Whether to run the "almost extra strong" Lucas test after the Miller-Rabin rounds.
+    pub lucas: bool,
+}
+
+impl Default for PrimalityConfig {
+    /// Baillie-PSW: a single Miller-Rabin round to base 2, followed by the Lucas test.
+    /// There are no known composites passing both, and none below 2^64.
+    fn default() -> Self {
+        PrimalityConfig {
+            miller_rabin_rounds: 1,
+            lucas: true,
+        }
+    }
+}
+
+/// Probable prime test.
+///
+/// Trial division by the primes below 64, followed by the Miller-Rabin rounds and the Lucas
+/// test as configured by `cfg`. Numbers below 64 are always classified exactly.
+pub fn is_probable_prime(n: &BigUint, cfg: &PrimalityConfig) -> bool {
+    if let Some(n) = n.to_u32() {
+        if n < 64 {
+            return n == 2 || SMALL_PRIMES.contains(&n);
+        }
+    }
+
+    if n.is_even() {
+        return false;
+    }
+
+    for p in SMALL_PRIMES.iter() {
+        if (n % *p).is_zero() {
+            return false;
+        }
+    }
+
+    let bases = std::iter::once(2).chain(SMALL_PRIMES.iter().cloned());
+    let mut bases = bases.take(cfg.miller_rabin_rounds);
+
+    bases.all(|base| miller_rabin(n, base)) && (!cfg.lucas || lucas(n))
+}
+
+/// Strong Fermat test to the given base, for odd `n > base`.
+fn miller_rabin(n: &BigUint, base: u32) -> bool {
+    let n_minus_1 = n - 1u32;
+    let k = n_minus_1.trailing_zeros().expect("n > 1");
+    let q = &n_minus_1 >> k;
+
+    let mut y = BigUint::from(base).modpow(&q, n);
+    if y.is_one() || y == n_minus_1 {
+        return true;
+    }
+
+    for _ in 1..k {
+        y = y.modpow(&BigUint::from(2u32), n);
+        if y == n_minus_1 {
+            return true;
+        }
+        if y.is_one() {
+            return false;
+        }
+    }
+
+    false
+}
+
+/// "Almost extra strong" Lucas test, for odd `n > 2` without small factors, using Baillie's
+/// parameter selection: the smallest `P >= 3` with `Jacobi(P^2 - 4, n) = -1`, and `Q = 1`.
+fn lucas(n: &BigUint) -> bool {
+    let mut p = 3u32;
+    loop {
+        let d = BigUint::from(p * p - 4);
+        match jacobi(&d, n).expect("n is odd") {
+            -1 => break,
+            // `d = (p - 2)(p + 2)` shares a factor with `n`
+            0 => return n == &BigUint::from(p + 2),
+            _ => {}
+        }
+
+        // `Jacobi(d, n)` is never -1 for squares, check after a few unsuccessful tries
+        if p == 40 {
+            let root = n.sqrt();
+            if &(&root * &root) == n {
+                return false;
+            }
+        }
+
+        p += 1;
+    }
+
+    // n + 1 = s * 2^r, with s odd
+    let n_plus_1 = n + 1u32;
+    let r = n_plus_1.trailing_zeros().expect("n > 0");
+    let s = &n_plus_1 >> r;
+    let n_minus_2 = n - 2u32;
+    let p_big = BigUint::from(p);
+
+    // compute V_s, V_{s+1} using V_{2k} = V_k^2 - 2 and V_{2k+1} = V_k V_{k+1} - P
+    let mut vk = BigUint::from(2u32);
+    let mut vk1 = p_big.clone();
+    for i in (0..s.bits()).rev() {
+        if (&s >> i).is_odd() {
+            vk = (&vk * &vk1 + n - &p_big) % n;
+            vk1 = (&vk1 * &vk1 + &n_minus_2) % n;
+        } else {
+            vk1 = (&vk * &vk1 + n - &p_big) % n;
+            vk = (&vk * &vk + &n_minus_2) % n;
+        }
+    }
+
+    // V_s = ±2 and U_s = 0, checked as P V_s = 2 V_{s+1}
+    if vk == BigUint::from(2u32) || vk == n_minus_2 {
+        let lhs = (&vk * &p_big) % n;
+        let rhs = (&vk1 << 1) % n;
+        if lhs == rhs {
+            return true;
+        }
+    }
+
+    // V_{s 2^t} = 0 for some 0 <= t < r - 1
+    for _ in 1..r {
+        if vk.is_zero() {
+            return true;
+        }
+        // V_{2k} = V_k^2 - 2
+        vk = (&vk * &vk + &n_minus_2) % n;
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_numbers() {
+        // sieve of Eratosthenes
+        let limit = 10_000;
+        let mut sieve = vec![true; limit];
+        sieve[0] = false;
+        sieve[1] = false;
+        for i in 2..limit {
+            if sieve[i] {
+                for j in (i * i..limit).step_by(i) {
+                    sieve[j] = false;
+                }
+            }
+        }
+
+        for (i, is_prime) in sieve.into_iter().enumerate() {
+            assert_eq!(
+                is_probable_prime(&BigUint::from(i), &PrimalityConfig::default()),
+                is_prime,
+                "wrong result for {}",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_pseudoprimes() {
+        // strong pseudoprimes to base 2, Carmichael numbers and a square of a prime
+        for n in &[
+            2047u64,
+            3277,
+            4033,
+            561,
+            1105,
+            3215031751,
+            3825123056546413051,
+            1_000_003 * 1_000_003,
+        ] {
+            assert!(
+                !is_probable_prime(&BigUint::from(*n), &PrimalityConfig::default()),
+                "{}",
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn test_large_numbers() {
+        let one = BigUint::one();
+
+        // Mersenne primes
+        for e in &[61, 89, 107, 127, 521] {
+            assert!(
+                is_probable_prime(&((&one << *e) - 1u32), &PrimalityConfig::default()),
+                "2^{} - 1",
+                e
+            );
+        }
+
+        // 2^128 + 1 and 2^67 - 1 are composite
+        assert!(!is_probable_prime(
+            &((&one << 128) + 1u32),
+            &PrimalityConfig::default()
+        ));
+        assert!(!is_probable_prime(
+            &((&one << 67) - 1u32),
+            &PrimalityConfig::default()
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "prover")]
+    fn test_matches_probably_prime() {
+        use num_bigint::prime::probably_prime;
+        use num_bigint::RandBigInt;
+        use rand::SeedableRng;
+        use rand_chacha::ChaChaRng;
+
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        for _ in 0..10_000 {
+            let n = rng.gen_biguint(128) | BigUint::one();
+            assert_eq!(
+                is_probable_prime(&n, &PrimalityConfig::default()),
+                probably_prime(&n, 20),
+                "{}",
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn test_configs() {
+        let configs = [
+            PrimalityConfig::default(),
+            PrimalityConfig {
+                miller_rabin_rounds: 18,
+                lucas: false,
+            },
+            PrimalityConfig {
+                miller_rabin_rounds: 0,
+                lucas: true,
+            },
+            PrimalityConfig {
+                miller_rabin_rounds: 100,
+                lucas: true,
+            },
+        ];
+
+        let one = BigUint::one();
+        for cfg in &configs {
+            // Carmichael numbers
+            for n in &[561u64, 1105, 1729, 2465, 2821, 6601, 8911, 41041, 825265] {
+                assert!(!is_probable_prime(&BigUint::from(*n), cfg), "{}", n);
+            }
+
+            // Mersenne primes
+            for e in &[127, 521, 607, 1279] {
+                assert!(
+                    is_probable_prime(&((&one << *e) - 1u32), cfg),
+                    "2^{} - 1",
+                    e
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_strong_pseudoprime_rounds() {
+        // a strong pseudoprime to the bases 2, 3, 5 and 7, but not 11
+        let n = BigUint::from(3215031751u64);
+        let rounds = |miller_rabin_rounds| PrimalityConfig {
+            miller_rabin_rounds,
+            lucas: false,
+        };
+
+        assert!(is_probable_prime(&n, &rounds(4)));
+        assert!(!is_probable_prime(&n, &rounds(5)));
+        assert!(!is_probable_prime(&n, &PrimalityConfig::default()));
+    }
+
+    #[test]
+    fn test_fixture_elements() {
+        // all elements in the test vectors are primes
+        for v in crate::test_vectors::builtin() {
+            let elements = v
+                .elements
+                .iter()
+                .chain(&v.batch_elements)
+                .chain(Some(&v.non_member));
+            for x in elements {
+                assert!(is_probable_prime(x, &PrimalityConfig::default()), "{}", x);
+                assert!(
+                    is_probable_prime(
+                        x,
+                        &PrimalityConfig {
+                            miller_rabin_rounds: 18,
+                            lucas: true,
+                        }
+                    ),
+                    "{}",
+                    x
+                );
+            }
+        }
+    }
+}
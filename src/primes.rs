@@ -0,0 +1,445 @@
+//! Generating many random primes at once.
+//!
+//! Calling `RandPrime::gen_prime` in a loop re-sieves for every prime, and may return the same
+//! prime twice, which breaks batch operations. `RandPrimes::gen_distinct_primes` shares the
+//! sieve across all candidates, and never returns duplicates.
+//!
+//! `derive_prime` derives primes from a seed instead, with a fixed procedure that doesn't
+//! depend on any random number generator.
+//!
+//! `check_modulus` rejects moduli that are obviously unsuitable, e.g. when accepting one from
+//! elsewhere instead of running a setup.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::OnceLock;
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, WriteBytesExt};
+use num_bigint::{BigUint, RandBigInt};
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+use rand::Rng;
+
+use crate::math::perfect_power;
+use crate::par;
+use crate::primality::{is_probable_prime, PrimalityConfig};
+
+/// Candidates divisible by an odd prime below this are skipped without a primality test.
+const SIEVE_LIMIT: u32 = 1 << 12;
+
+/// The default bound for trial division in `check_modulus`.
+pub const DEFAULT_TRIAL_DIVISION_BOUND: u32 = 1 << 16;
+
+/// The checks of `check_modulus`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModulusPolicy {
+    /// The size of the modulus in bits, if it must have a certain size.
+    pub bits: Option<usize>,
+    /// The modulus must not have prime factors below this.
+    pub trial_division_bound: u32,
+}
+
+impl Default for ModulusPolicy {
+    fn default() -> Self {
+        ModulusPolicy {
+            bits: None,
+            trial_division_bound: DEFAULT_TRIAL_DIVISION_BOUND,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModulusError {
+    /// The modulus is even, or less than `3`.
+    Even,
+    /// The modulus does not have the expected number of bits.
+    Size { expected: usize, actual: usize },
+    /// The modulus is a (probable) prime, so the order of the group is known.
+    Prime,
+    /// The modulus is `r^k`, for this `k`.
+    PerfectPower(u32),
+    /// The modulus is divisible by this prime, below the trial division bound.
+    SmallFactor(u32),
+}
+
+impl fmt::Display for ModulusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModulusError::Even => write!(f, "the modulus is even"),
+            ModulusError::Size { expected, actual } => write!(
+                f,
+                "expected a modulus of {} bits, got {} bits",
+                expected, actual
+            ),
+            ModulusError::Prime => write!(f, "the modulus is a prime"),
+            ModulusError::PerfectPower(k) => write!(f, "the modulus is a perfect {}-th power", k),
+            ModulusError::SmallFactor(p) => write!(f, "the modulus is divisible by {}", p),
+        }
+    }
+}
+
+impl std::error::Error for ModulusError {}
+
+/// Checks that `n` is not obviously unsuitable as the modulus of a group of unknown order: it
+/// must be odd, composite, not a perfect power, without prime factors below
+/// `policy.trial_division_bound`, and of `policy.bits` bits if set.
+///
+/// Passing does not mean that the factorization of `n` is unknown, only a trusted setup or
+/// a published challenge modulus can give that.
+pub fn check_modulus(n: &BigUint, policy: &ModulusPolicy) -> Result<(), ModulusError> {
+    let _span = span!("check_modulus", modulus_bits = n.bits());
+    if n.is_even() || n.is_one() {
+        return Err(ModulusError::Even);
+    }
+    if let Some(expected) = policy.bits {
+        if n.bits() != expected {
+            return Err(ModulusError::Size {
+                expected,
+                actual: n.bits(),
+            });
+        }
+    }
+    if is_probable_prime(n, &PrimalityConfig::default()) {
+        return Err(ModulusError::Prime);
+    }
+    if let Some((_, k)) = perfect_power(n) {
+        return Err(ModulusError::PerfectPower(k));
+    }
+
+    let bound = policy.trial_division_bound;
+    let small = if bound <= SIEVE_LIMIT {
+        sieve_primes()
+    } else {
+        &odd_primes_below(bound)[..]
+    };
+    match small
+        .iter()
+        .take_while(|p| **p < bound)
+        .find(|p| (n % **p).is_zero())
+    {
+        Some(p) => Err(ModulusError::SmallFactor(*p)),
+        None => Ok(()),
+    }
+}
+
+/// Generation of distinct random primes, for all random number generators.
+pub trait RandPrimes {
+    /// Generates `count` pairwise distinct random primes of exactly `bits` bits.
+    ///
+    /// The candidates are drawn from `self` up front and searched independently, in parallel
+    /// with the `parallel` feature, so the result only depends on the state of `self`.
+    ///
+    /// Panics if `bits < 2`. Does not return if there are fewer than `count` primes of `bits`
+    /// bits.
+    fn gen_distinct_primes(&mut self, count: usize, bits: usize) -> Vec<BigUint>;
+}
+
+impl<R: Rng + ?Sized> RandPrimes for R {
+    fn gen_distinct_primes(&mut self, count: usize, bits: usize) -> Vec<BigUint> {
+        assert!(bits >= 2, "primes have at least 2 bits");
+
+        let mut primes = Vec::with_capacity(count);
+        let mut seen = HashSet::with_capacity(count);
+
+        // searches that run past `bits` bits, or find a duplicate, are retried from new starts
+        while primes.len() < count {
+            let starts = (primes.len()..count)
+                .map(|_| random_start(self, bits))
+                .collect::<Vec<_>>();
+
+            let found = par::map(&starts, |start| next_prime(start, bits));
+            for p in found.into_iter().flatten() {
+                if seen.insert(p.clone()) {
+                    primes.push(p);
+                }
+            }
+        }
+
+        primes
+    }
+}
+
+const DERIVE_PRIME_TAG: &[u8] = b"accumulators/derive-prime";
+
+/// Derives a prime of exactly `bits` bits from `seed` and `index`.
+///
+/// The result is the same on all platforms and in all versions of this crate: for
+/// `counter = 0, 1, ..`, the candidate is the first `ceil(bits / 8)` bytes of
+///
+/// ```text
+/// Blake2b(tag || len(seed) || seed || index || bits || counter || 0)
+///   || Blake2b(.. || counter || 1) || ..
+/// ```
+///
+/// with the tag `accumulators/derive-prime`, `len(seed)` and `index` as big-endian `u64`, and
+/// `bits`, `counter` and the block number as big-endian `u32`. It is read as a big-endian
+/// integer, reduced to its lowest `bits` bits, and the top two and the lowest bit are set. The
+/// first candidate passing `is_probable_prime` with the default config is returned.
+///
+/// Panics if `bits < 2`.
+pub fn derive_prime(seed: &[u8], index: u64, bits: usize) -> BigUint {
+    assert!(bits >= 2, "primes have at least 2 bits");
+
+    let mut input = DERIVE_PRIME_TAG.to_vec();
+    input.write_u64::<BigEndian>(seed.len() as u64).unwrap();
+    input.extend_from_slice(seed);
+    input.write_u64::<BigEndian>(index).unwrap();
+    input.write_u32::<BigEndian>(bits as u32).unwrap();
+
+    let cfg = PrimalityConfig::default();
+    let len = bits.div_ceil(8);
+    let top = (BigUint::one() << (bits - 1)) | (BigUint::one() << (bits - 2));
+    let mask = (BigUint::one() << bits) - BigUint::one();
+
+    for counter in 0u32.. {
+        let mut bytes = Vec::with_capacity(len + 64);
+        for block in 0u32.. {
+            if bytes.len() >= len {
+                break;
+            }
+            let mut to_hash = input.clone();
+            to_hash.write_u32::<BigEndian>(counter).unwrap();
+            to_hash.write_u32::<BigEndian>(block).unwrap();
+            bytes.extend_from_slice(&Blake2b::digest(&to_hash)[..]);
+        }
+
+        let x = (BigUint::from_bytes_be(&bytes[..len]) & &mask) | &top | BigUint::one();
+        if is_probable_prime(&x, &cfg) {
+            return x;
+        }
+    }
+
+    unreachable!("no prime among 2^32 candidates")
+}
+
+/// Returns a random odd number of exactly `bits` bits.
+fn random_start<R: Rng + ?Sized>(rng: &mut R, bits: usize) -> BigUint {
+    rng.gen_biguint(bits) | (BigUint::one() << (bits - 1)) | BigUint::one()
+}
+
+/// The odd primes below `SIEVE_LIMIT`, computed once.
+fn sieve_primes() -> &'static [u32] {
+    static PRIMES: OnceLock<Vec<u32>> = OnceLock::new();
+    PRIMES.get_or_init(|| odd_primes_below(SIEVE_LIMIT))
+}
+
+/// Returns the odd primes below `limit`, with the sieve of Eratosthenes.
+fn odd_primes_below(limit: u32) -> Vec<u32> {
+    let mut composite = vec![false; limit as usize];
+    let mut primes = Vec::new();
+    for i in (3..limit).step_by(2) {
+        if composite[i as usize] {
+            continue;
+        }
+        primes.push(i);
+        for j in (u64::from(i) * u64::from(i)..u64::from(limit)).step_by(2 * i as usize) {
+            composite[j as usize] = true;
+        }
+    }
+    primes
+}
+
+/// Returns the first prime `>= start` of at most `bits` bits, or `None` if there is none.
+///
+/// `start` must be odd. The residues of `start` are computed once, candidates are then only
+/// tested if no sieve prime divides them.
+fn next_prime(start: &BigUint, bits: usize) -> Option<BigUint> {
+    let cfg = PrimalityConfig::default();
+    let primes = sieve_primes();
+
+    // the search ends at 2^bits
+    let end = ((BigUint::one() << bits) - start)
+        .to_u64()
+        .unwrap_or(u64::MAX);
+
+    // small candidates might be sieve primes themselves, those are tested directly
+    let small = start.to_u32().is_some_and(|x| x < SIEVE_LIMIT);
+    let residues = primes
+        .iter()
+        .map(|p| (start % *p).to_u32().unwrap())
+        .collect::<Vec<_>>();
+
+    let mut delta = 0u64;
+    while delta < end {
+        let sieved = !small
+            && primes
+                .iter()
+                .zip(&residues)
+                .any(|(p, r)| (u64::from(*r) + delta) % u64::from(*p) == 0);
+        if !sieved {
+            let x = start + delta;
+            if is_probable_prime(&x, &cfg) {
+                return Some(x);
+            }
+        }
+        delta += 2;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_gen_distinct_primes() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let cfg = PrimalityConfig::default();
+
+        for &(count, bits) in &[(0, 128), (1, 128), (50, 128), (20, 256), (30, 12), (2, 4)] {
+            let primes = rng.gen_distinct_primes(count, bits);
+
+            assert_eq!(primes.len(), count);
+            assert_eq!(primes.iter().collect::<HashSet<_>>().len(), count);
+            for p in &primes {
+                assert_eq!(p.bits(), bits);
+                assert!(is_probable_prime(p, &cfg), "{} is not a prime", p);
+            }
+        }
+    }
+
+    #[test]
+    fn test_gen_distinct_primes_exhaustive() {
+        // the 5 primes of 5 bits: 17, 19, 23, 29, 31
+        let rng = &mut ChaChaRng::from_seed([1u8; 32]);
+        let mut primes = rng.gen_distinct_primes(5, 5);
+        primes.sort();
+
+        let expected = [17u32, 19, 23, 29, 31];
+        assert_eq!(
+            primes,
+            expected
+                .iter()
+                .map(|p| BigUint::from(*p))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_gen_distinct_primes_deterministic() {
+        let a = ChaChaRng::from_seed([2u8; 32]).gen_distinct_primes(40, 128);
+        let b = ChaChaRng::from_seed([2u8; 32]).gen_distinct_primes(40, 128);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_derive_prime_pinned() {
+        // must never change, see `derive_prime`
+        let vectors: &[(&[u8], u64, usize, &str)] = &[
+            (b"", 0, 64, "c4e218e58ec2a471"),
+            (b"seed", 0, 128, "ea42e4426faf39df9fb9ca27e6dffb1b"),
+            (b"seed", 1, 128, "f29c79dd9297e4543bcaa95a4dde15f5"),
+            (
+                b"seed",
+                u64::MAX,
+                255,
+                "7de92954b35496379b0cf3036433a0e76f9b9783854d92ea0f77190d4065e53f",
+            ),
+            (b"seed", 7, 13, "187f"),
+            (b"seed", 7, 2, "3"),
+        ];
+
+        for &(seed, index, bits, expected) in vectors {
+            let p = derive_prime(seed, index, bits);
+            assert_eq!(p, BigUint::parse_bytes(expected.as_bytes(), 16).unwrap());
+            assert_eq!(p.bits(), bits);
+        }
+    }
+
+    #[test]
+    fn test_derive_prime_distinct() {
+        let cfg = PrimalityConfig::default();
+        let primes = (0..50)
+            .map(|i| derive_prime(b"distinct", i, 128))
+            .collect::<HashSet<_>>();
+        assert_eq!(primes.len(), 50);
+        for p in &primes {
+            assert_eq!(p.bits(), 128);
+            assert!(is_probable_prime(p, &cfg));
+        }
+
+        // the seed is length prefixed
+        assert_ne!(derive_prime(b"ab", 0, 128), derive_prime(b"a", 0, 128));
+        assert!(!primes.contains(&derive_prime(b"other", 0, 128)));
+    }
+
+    #[test]
+    fn test_sieve_primes() {
+        let primes = sieve_primes();
+        assert_eq!(&primes[..6], &[3, 5, 7, 11, 13, 17]);
+        assert_eq!(primes.len(), 564 - 1);
+        assert!(primes
+            .iter()
+            .all(|p| is_probable_prime(&BigUint::from(*p), &Default::default())));
+    }
+
+    #[test]
+    fn test_check_modulus() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let policy = ModulusPolicy::default();
+        let pq = rng.gen_distinct_primes(2, 128);
+        let n = &pq[0] * &pq[1];
+        assert_eq!(check_modulus(&n, &policy), Ok(()));
+        assert_eq!(
+            check_modulus(
+                &n,
+                &ModulusPolicy {
+                    bits: Some(n.bits()),
+                    ..policy
+                }
+            ),
+            Ok(())
+        );
+
+        assert_eq!(check_modulus(&pq[0], &policy), Err(ModulusError::Prime));
+        assert_eq!(
+            check_modulus(&(&n + 1u32), &policy),
+            Err(ModulusError::Even)
+        );
+        assert_eq!(
+            check_modulus(&num_traits::pow(BigUint::from(3u32), 101), &policy),
+            Err(ModulusError::PerfectPower(101))
+        );
+        assert_eq!(
+            check_modulus(&(&pq[0] * &pq[0]), &policy),
+            Err(ModulusError::PerfectPower(2))
+        );
+        assert_eq!(
+            check_modulus(&(&pq[0] * 17u32), &policy),
+            Err(ModulusError::SmallFactor(17))
+        );
+        assert_eq!(
+            check_modulus(
+                &n,
+                &ModulusPolicy {
+                    bits: Some(2048),
+                    ..policy
+                }
+            ),
+            Err(ModulusError::Size {
+                expected: 2048,
+                actual: n.bits()
+            })
+        );
+
+        // factors at or above the bound are not found
+        let factor = 1_000_003u32;
+        let weak = &pq[0] * factor;
+        assert_eq!(check_modulus(&weak, &policy), Ok(()));
+        assert_eq!(
+            check_modulus(
+                &weak,
+                &ModulusPolicy {
+                    trial_division_bound: factor + 1,
+                    ..policy
+                }
+            ),
+            Err(ModulusError::SmallFactor(factor))
+        );
+    }
+}
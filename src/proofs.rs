@@ -1,147 +1,612 @@
+//! Non-interactive proofs of exponentiation in groups of unknown order, from "Batching
+//! Techniques for Accumulators with Applications to IOPs and Stateless Blockchains" by Boneh,
+//! Bünz and Fisch.
+//!
+//! - NI-PoE proves `u^x = w` for a public `x`, with a proof of a single group element that is
+//!   verified with two exponentiations by 128 bit exponents, instead of one by `x`.
+//! - NI-PoKE2 proves knowledge of an `x` with `u^x = w`, without revealing it.
+//!
+//! Both only depend on the group, given by `GroupParams`, and can be used for any statement
+//! in it, e.g. a Wesolowski-style VDF with `x = 2^T`. The group is `Z_n^*` for an RSA modulus
+//! `n` of unknown factorization, all operations are `mod n`, and the inputs must be reduced.
+//!
+//! The challenges are derived with Fiat-Shamir from the statement. `H_prime` is `hash_prime`
+//! and `H_G` is `hash_group`, both over Blake2b, see the `hash` module. `ProofParams::context`
+//! is prepended to everything hashed:
+//!
+//! - NI-PoE: `l = H_prime(context || x || u || w)`, each integer as its minimal big-endian
+//!   bytes.
+//! - NI-PoKE2: `g = H_G(context || n || u || w)`, `l = H_prime(context || n || u || w || z)`
+//!   and `alpha = Blake2b(context || n || u || w || z || l)`, the group elements with the
+//!   fixed width of `n` and `l` as its minimal bytes.
+//!
+//! The context is not length-prefixed, so protocols should use contexts of a fixed length, or
+//! ones that are not prefixes of each other.
+
+use crate::encoding::{modulus_bytes, to_fixed_bytes};
 use crate::hash::{hash_group, hash_prime};
-use crate::math::modpow_uint_int;
+use crate::math::multi_modpow;
+#[cfg(feature = "prover")]
+use crate::math::{modpow_uint_int, product, MathError};
+use crate::transcript::Transcript;
 use blake2::{Blake2b, Digest};
 use num_bigint::{BigInt, BigUint};
 use num_integer::Integer;
+use num_traits::{One, Zero};
+use std::fmt;
+
+/// Why a witness or proof did not verify, from the `_detailed` verifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyError {
+    /// A value is out of range, e.g. a group element that is not reduced, or no challenge
+    /// could be derived from the statement.
+    MalformedInput,
+    /// The witness does not lead to the expected state, e.g. it is stale or for another
+    /// element.
+    WrongState,
+    /// The proof does not satisfy its verification equation for the recomputed challenge.
+    ChallengeMismatch,
+    /// The elements share a factor.
+    NotCoprime,
+    /// A value that has to be inverted shares a factor with the modulus.
+    NotInvertible,
+    /// The proof was made under other parameters, see `verify_package`.
+    ParamsMismatch,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::MalformedInput => write!(f, "the input is malformed"),
+            VerifyError::WrongState => write!(f, "the witness does not match the state"),
+            VerifyError::ChallengeMismatch => write!(f, "the proof does not match its challenge"),
+            VerifyError::NotCoprime => write!(f, "the elements are not coprime"),
+            VerifyError::NotInvertible => write!(f, "a value is not invertible"),
+            VerifyError::ParamsMismatch => write!(f, "the proof is for other parameters"),
+        }
+    }
+}
 
-// Let G be a group of unknown order.
-// Here both the prover and verifier are given (u, w, x) and
-// the prover wants to convince the verifier that w = u^x holds in G.
+impl std::error::Error for VerifyError {}
 
+/// The group the proofs are in, with the parameters of the proofs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GroupParams {
+    /// Modulus of the group, of unknown factorization.
+    pub n: BigUint,
+    /// Parameters of the proofs.
+    pub proof_params: ProofParams,
+}
+
+/// Parameters of the proofs, prover and verifier must use the same.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct ProofParams {
+    /// Domain separation, prepended to everything hashed for the challenges. Proofs with one
+    /// context never verify with another.
+    ///
+    /// Empty by default, which gives the proofs of `ni_poe_prove` and `ni_poke2_prove`.
+    pub context: Vec<u8>,
+}
+
+impl GroupParams {
+    /// The group `Z_n^*`, with the default proof parameters.
+    pub fn new(n: BigUint) -> Self {
+        GroupParams {
+            n,
+            proof_params: ProofParams::default(),
+        }
+    }
+
+    /// The group `Z_n^*`, with the proofs bound to `context`.
+    pub fn with_context(n: BigUint, context: &[u8]) -> Self {
+        GroupParams {
+            n,
+            proof_params: ProofParams {
+                context: context.to_vec(),
+            },
+        }
+    }
+}
+
+/// NI-PoE, a proof that `u^x = w`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PoeProof {
+    /// `Q = u^floor(x / l)`
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub q: BigUint,
+}
+
+/// NI-PoKE2, a proof of knowledge of `x`, such that `u^x = w`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Poke2Proof {
+    /// `z = g^x`
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub z: BigUint,
+    /// `Q = (u g^alpha)^floor(x / l)`
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub q: BigUint,
+    /// `r = x mod l`
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::bigint"))]
+    pub r: BigInt,
+}
+
+#[deprecated(note = "use `PoeProof` instead")]
 pub type ExponentProof = BigUint;
 
+#[deprecated(note = "use `Poke2Proof` instead")]
 pub type KnowledgeProof = (BigUint, BigUint, BigInt);
 
+impl From<BigUint> for PoeProof {
+    fn from(q: BigUint) -> Self {
+        PoeProof { q }
+    }
+}
+
+impl From<PoeProof> for BigUint {
+    fn from(pi: PoeProof) -> Self {
+        pi.q
+    }
+}
+
+impl From<(BigUint, BigUint, BigInt)> for Poke2Proof {
+    fn from((z, q, r): (BigUint, BigUint, BigInt)) -> Self {
+        Poke2Proof { z, q, r }
+    }
+}
+
+impl From<Poke2Proof> for (BigUint, BigUint, BigInt) {
+    fn from(pi: Poke2Proof) -> Self {
+        (pi.z, pi.q, pi.r)
+    }
+}
+
+/// Proves that `u^x = w`, which must hold, with a NI-PoE.
+///
+/// The proof is `Q = u^floor(x / l)`, for the challenge `l = H_prime(context || x || u || w)`.
+#[cfg(feature = "prover")]
+pub fn poe_prove(params: &GroupParams, x: &BigUint, u: &BigUint, w: &BigUint) -> PoeProof {
+    ni_poe_prove_with_context(&params.proof_params.context, x, u, w, &params.n)
+}
+
+/// Verifies a proof from `poe_prove` that `u^x = w`.
+///
+/// Checks `Q^l u^(x mod l) = w`, with `Q` reduced, and `Q = 1` if `x < l`.
+pub fn poe_verify(
+    params: &GroupParams,
+    x: &BigUint,
+    u: &BigUint,
+    w: &BigUint,
+    pi: &PoeProof,
+) -> bool {
+    ni_poe_verify_with_context(&params.proof_params.context, x, u, w, pi, &params.n)
+}
+
+/// Verifies a proof from `poe_prove`, like `poe_verify`, but returns why it failed.
+pub fn poe_verify_detailed(
+    params: &GroupParams,
+    x: &BigUint,
+    u: &BigUint,
+    w: &BigUint,
+    pi: &PoeProof,
+) -> Result<(), VerifyError> {
+    poe_check(&params.proof_params.context, x, u, w, pi, &params.n)
+}
+
 /// NI-PoE Prove
 /// Assumes `u^x = w`
 /// All operations are `mod n`.
-pub fn ni_poe_prove(x: &BigUint, u: &BigUint, w: &BigUint, n: &BigUint) -> ExponentProof {
-    debug_assert!(&u.modpow(x, n) == w, "invalid input");
+///
+/// Shorthand for `poe_prove` with the default `ProofParams`.
+#[cfg(feature = "prover")]
+pub fn ni_poe_prove(x: &BigUint, u: &BigUint, w: &BigUint, n: &BigUint) -> PoeProof {
+    ni_poe_prove_with_context(&[], x, u, w, n)
+}
 
-    // l <- H_prime(x, u, w)
-    let mut to_hash = x.to_bytes_be();
-    to_hash.extend(&u.to_bytes_be());
-    to_hash.extend(&w.to_bytes_be());
+/// NI-PoE Prove, with the challenge bound to `context`.
+/// The proof only verifies with `ni_poe_verify_with_context` and the same `context`.
+///
+/// Shorthand for `poe_prove` with `GroupParams::with_context`.
+#[cfg(feature = "prover")]
+pub fn ni_poe_prove_with_context(
+    context: &[u8],
+    x: &BigUint,
+    u: &BigUint,
+    w: &BigUint,
+    n: &BigUint,
+) -> PoeProof {
+    debug_assert!(&u.modpow(x, n) == w, "invalid input");
+    let _span = span!("ni_poe_prove", exponent_bits = x.bits());
 
-    let l = hash_prime::<_, Blake2b>(&to_hash);
+    // l <- H_prime(context, x, u, w)
+    let l = poe_challenge(context, x, u, w).expect("no prime found");
 
     // q <- floor(x/l)
     let q = x.div_floor(&l);
 
     //Prover sends Q <- u^q ∈ G to the Verifier.
-    u.modpow(&q, n)
+    PoeProof { q: u.modpow(&q, n) }
+}
+
+/// Proves that `u^x = w`, like `poe_prove`, for `x` the product of `factors`.
+///
+/// The proof is the same, but the quotient `floor(x / l)` is never computed: keeping
+/// `u^prefix = Q^l u^r`, with `r < l`, over the products of the first factors, each factor
+/// `f` only needs `r f = q' l + r'`, for the next `Q^f u^q'` and `r'`. The challenge hashes
+/// `x`, so its product is still built once.
+#[cfg(feature = "prover")]
+pub fn poe_prove_factored(
+    params: &GroupParams,
+    factors: &[BigUint],
+    u: &BigUint,
+    w: &BigUint,
+) -> PoeProof {
+    let x = product(factors);
+    prove_factored(&params.proof_params.context, &x, factors, u, w, &params.n)
+}
+
+/// NI-PoE Prove for `x` the product of `factors`, see `poe_prove_factored`.
+///
+/// Shorthand for `poe_prove_factored` with the default `ProofParams`.
+#[cfg(feature = "prover")]
+pub fn ni_poe_prove_factored(
+    factors: &[BigUint],
+    u: &BigUint,
+    w: &BigUint,
+    n: &BigUint,
+) -> PoeProof {
+    prove_factored(&[], &product(factors), factors, u, w, n)
+}
+
+/// Proves `u^x = w` for `x`, the product of `factors` already computed by the caller, with
+/// the quotient streamed over the factors, see `poe_prove_factored`.
+#[cfg(feature = "prover")]
+pub(crate) fn prove_factored(
+    context: &[u8],
+    x: &BigUint,
+    factors: &[BigUint],
+    u: &BigUint,
+    w: &BigUint,
+    n: &BigUint,
+) -> PoeProof {
+    debug_assert!(&u.modpow(x, n) == w, "invalid input");
+    let _span = span!(
+        "ni_poe_prove",
+        exponent_bits = x.bits(),
+        factors = factors.len()
+    );
+
+    let l = poe_challenge(context, x, u, w).expect("no prime found");
+
+    // u^1 = 1^l u^1, as l > 1
+    let (mut q, mut r) = (BigUint::one(), BigUint::one());
+    for f in factors {
+        let (q_f, r_f) = (&r * f).div_rem(&l);
+        q = multi_modpow(&q, f, u, &q_f, n);
+        r = r_f;
+    }
+
+    PoeProof { q }
+}
+
+/// NI-PoE Prove, with the challenge drawn from the session transcript `t`.
+/// The proof only verifies with `ni_poe_verify_with_transcript` and a transcript in the same
+/// state.
+///
+/// Absorbs the domain label `accumulators/ni-poe`, then `n`, `x`, `u` and `w`, draws the
+/// challenge, and absorbs the proof `Q` last, each integer as its minimal big-endian bytes.
+#[cfg(feature = "prover")]
+pub fn ni_poe_prove_with_transcript<T: Transcript>(
+    t: &mut T,
+    x: &BigUint,
+    u: &BigUint,
+    w: &BigUint,
+    n: &BigUint,
+) -> PoeProof {
+    let context = poe_transcript_context(t, x, u, w, n);
+    let pi = ni_poe_prove_with_context(&context, x, u, w, n);
+    t.append_message(b"Q", &pi.q.to_bytes_be());
+
+    pi
+}
+
+/// NI-PoE Verify, for proofs from `ni_poe_prove_with_transcript`.
+///
+/// Absorbs the same messages as the prover, so `t` can be used for the next proof of the
+/// session afterwards.
+pub fn ni_poe_verify_with_transcript<T: Transcript>(
+    t: &mut T,
+    x: &BigUint,
+    u: &BigUint,
+    w: &BigUint,
+    pi: &PoeProof,
+    n: &BigUint,
+) -> bool {
+    let context = poe_transcript_context(t, x, u, w, n);
+    t.append_message(b"Q", &pi.q.to_bytes_be());
+
+    ni_poe_verify_with_context(&context, x, u, w, pi, n)
+}
+
+/// Absorbs the statement into `t`, and derives the context of the NI-PoE challenge from it.
+fn poe_transcript_context<T: Transcript>(
+    t: &mut T,
+    x: &BigUint,
+    u: &BigUint,
+    w: &BigUint,
+    n: &BigUint,
+) -> [u8; 32] {
+    t.append_message(b"dom-sep", b"accumulators/ni-poe");
+    for (label, v) in &[(b"n", n), (b"x", x), (b"u", u), (b"w", w)] {
+        t.append_message(*label, &v.to_bytes_be());
+    }
+
+    let mut context = [0u8; 32];
+    t.challenge_bytes(b"l", &mut context);
+
+    context
 }
 
 /// NI-PoE Verify
 /// Assumes `u^x = w`
 /// All operations are `mod n`.
-pub fn ni_poe_verify(
+///
+/// Shorthand for `poe_verify` with the default `ProofParams`.
+pub fn ni_poe_verify(x: &BigUint, u: &BigUint, w: &BigUint, pi: &PoeProof, n: &BigUint) -> bool {
+    ni_poe_verify_with_context(&[], x, u, w, pi, n)
+}
+
+/// NI-PoE Verify, for proofs from `ni_poe_prove_with_context`.
+///
+/// Shorthand for `poe_verify` with `GroupParams::with_context`.
+pub fn ni_poe_verify_with_context(
+    context: &[u8],
     x: &BigUint,
     u: &BigUint,
     w: &BigUint,
-    q: &ExponentProof,
+    pi: &PoeProof,
     n: &BigUint,
 ) -> bool {
-    // l <- H_prime(x, u, w)
-    let mut to_hash = x.to_bytes_be();
-    to_hash.extend(&u.to_bytes_be());
-    to_hash.extend(&w.to_bytes_be());
+    poe_check(context, x, u, w, pi, n).is_ok()
+}
+
+fn poe_check(
+    context: &[u8],
+    x: &BigUint,
+    u: &BigUint,
+    w: &BigUint,
+    pi: &PoeProof,
+    n: &BigUint,
+) -> Result<(), VerifyError> {
+    let _span = span!("ni_poe_verify", exponent_bits = x.bits());
+    // all group elements must be reduced, checked before hashing them
+    if u >= n || w >= n || pi.q.is_zero() || &pi.q >= n {
+        return Err(VerifyError::MalformedInput);
+    }
 
-    let l = hash_prime::<_, Blake2b>(&to_hash);
+    // l <- H_prime(context, x, u, w)
+    // Always recomputed from the transcript, `hash_prime` only returns (probable) primes.
+    let l = poe_challenge(context, x, u, w).ok_or(VerifyError::MalformedInput)?;
 
-    // r <- x mod l
-    let r = x.mod_floor(&l);
+    // q <- floor(x/l), r <- x mod l
+    // For x < l this gives q = 0 and r = x, for multiples of l r = 0.
+    let (q, r) = x.div_mod_floor(&l);
+
+    // Q = u^q must be reduced, so that there is only one valid encoding of each proof.
+    // Honest provers only send Q = 1 for q = 0, and Q = 0 is never in the group.
+    if q.is_zero() {
+        if !pi.q.is_one() {
+            return Err(VerifyError::MalformedInput);
+        }
+    } else if pi.q <= BigUint::one() || &pi.q >= n {
+        return Err(VerifyError::MalformedInput);
+    }
 
     // Q^l u^r == w
-    &((q.modpow(&l, &n) * &u.modpow(&r, &n)) % n) == w
+    if &multi_modpow(&pi.q, &l, u, &r, n) != w {
+        return Err(VerifyError::ChallengeMismatch);
+    }
+
+    Ok(())
+}
+
+/// The NI-PoE challenge, `H_prime(context, x, u, w)`.
+fn poe_challenge(context: &[u8], x: &BigUint, u: &BigUint, w: &BigUint) -> Option<BigUint> {
+    let mut to_hash = context.to_vec();
+    to_hash.extend(&x.to_bytes_be());
+    to_hash.extend(&u.to_bytes_be());
+    to_hash.extend(&w.to_bytes_be());
+
+    hash_prime::<_, Blake2b>(&to_hash)
 }
 
 //proof of knowledge of exponent, i.e. a proof that a computationally bounded prover knows the discrete logarithm between two elements in a group of unknown order. The proof is succinct in that the proof size and verification time is independent of the size of the discrete-log.
 
+/// Proves knowledge of `x` with `u^x = w`, which must hold, with a NI-PoKE2.
+///
+/// The proof is `z = g^x`, `Q = (u g^alpha)^floor(x / l)` and `r = x mod l`, for the
+/// challenges derived as described in the module documentation.
+///
+/// Fails if `x` is negative and `u`, or the derived base, is not invertible.
+#[cfg(feature = "prover")]
+pub fn poke2_prove(
+    params: &GroupParams,
+    x: impl Into<BigInt>,
+    u: &BigUint,
+    w: &BigUint,
+) -> Result<Poke2Proof, MathError> {
+    poke2_prove_with_context(&params.proof_params.context, x.into(), u, w, &params.n)
+}
+
+/// Verifies a proof from `poke2_prove` for `u` and `w`.
+///
+/// Checks `Q^l (u g^alpha)^r = w z^alpha`, with all group elements reduced and `0 <= r < l`.
+/// Returns `false` for any proof with components out of range, instead of panicking.
+pub fn poke2_verify(params: &GroupParams, u: &BigUint, w: &BigUint, pi: &Poke2Proof) -> bool {
+    poke2_verify_with_context(&params.proof_params.context, u, w, pi, &params.n)
+}
+
+/// Verifies a proof from `poke2_prove`, like `poke2_verify`, but returns why it failed.
+pub fn poke2_verify_detailed(
+    params: &GroupParams,
+    u: &BigUint,
+    w: &BigUint,
+    pi: &Poke2Proof,
+) -> Result<(), VerifyError> {
+    poke2_check(&params.proof_params.context, u, w, pi, &params.n)
+}
+
 /// NI-PoKE2 Prove
 /// assumes `u^x = w`
 /// All operations are `mod n`.
+///
+/// Shorthand for `poke2_prove` with the default `ProofParams`. Fails if `x` is negative and
+/// `u`, or the derived base, is not invertible.
+#[cfg(feature = "prover")]
 pub fn ni_poke2_prove(
     x: impl Into<BigInt>,
     u: &BigUint,
     w: &BigUint,
     n: &BigUint,
-) -> (BigUint, BigUint, BigInt) {
-    let x: BigInt = x.into();
+) -> Result<Poke2Proof, MathError> {
+    poke2_prove_with_context(&[], x.into(), u, w, n)
+}
 
-    debug_assert!(&modpow_uint_int(u, &x, n).unwrap() == w, "invalid input");
+#[cfg(feature = "prover")]
+fn poke2_prove_with_context(
+    context: &[u8],
+    x: BigInt,
+    u: &BigUint,
+    w: &BigUint,
+    n: &BigUint,
+) -> Result<Poke2Proof, MathError> {
+    debug_assert!(
+        modpow_uint_int(u, &x, n).map_or(true, |u_x| &u_x == w),
+        "invalid input"
+    );
+    debug_assert!(u < n && w < n, "invalid input");
+    let _span = span!("ni_poke2_prove", exponent_bits = x.bits());
 
-    // g <- H_G(u, w)
-    let mut to_hash = u.to_bytes_be();
-    to_hash.extend(&w.to_bytes_be());
+    // g <- H_G(context, n, u, w)
+    let mut to_hash = context.to_vec();
+    to_hash.extend(poke2_transcript(n, &[n, u, w]));
     let g = hash_group::<_, Blake2b>(&to_hash, n);
 
     // z = g^x
-    let z = modpow_uint_int(&g, &x, n).expect("invalid state");
+    let z = modpow_uint_int(&g, &x, n)?;
 
-    // l <- H_prime(u, w, z)
-    to_hash.extend(&z.to_bytes_be());
-    let l: BigInt = hash_prime::<_, Blake2b>(&to_hash).into();
+    // l <- H_prime(context, n, u, w, z)
+    to_hash.extend(poke2_transcript(n, &[&z]));
+    let l: BigInt = hash_prime::<_, Blake2b>(&to_hash)
+        .expect("no prime found")
+        .into();
 
-    // alpha = H(u, w, z, l)
+    // alpha = H(context, n, u, w, z, l)
     to_hash.extend(&l.to_bytes_be().1);
     let alpha = BigUint::from_bytes_be(&Blake2b::digest(&to_hash)[..]);
 
     // q <- floor(x/l)
-    // r <- x % l
-    let (q, r) = x.div_rem(&l);
+    // r <- x mod l, with 0 <= r < l also for negative x
+    let (q, r) = x.div_mod_floor(&l);
 
     // Q <- (ug^alpha)^q
-    let q_big = modpow_uint_int(&(u * &g.modpow(&alpha, n)), &q, n).expect("invalid state");
+    let q_big = modpow_uint_int(&(u * &g.modpow(&alpha, n)), &q, n)?;
 
-    (z, q_big, r)
+    Ok(Poke2Proof { z, q: q_big, r })
 }
 
 /// NI-PoKE2 Verify
 /// assumes `u^x = w`
 /// All operations are `mod n`
-pub fn ni_poke2_verify(
+///
+/// Shorthand for `poke2_verify` with the default `ProofParams`. Returns `false` for any proof
+/// with components out of range, instead of panicking.
+pub fn ni_poke2_verify(u: &BigUint, w: &BigUint, pi: &Poke2Proof, n: &BigUint) -> bool {
+    poke2_verify_with_context(&[], u, w, pi, n)
+}
+
+fn poke2_verify_with_context(
+    context: &[u8],
     u: &BigUint,
     w: &BigUint,
-    pi: &(BigUint, BigUint, BigInt),
+    pi: &Poke2Proof,
     n: &BigUint,
 ) -> bool {
+    poke2_check(context, u, w, pi, n).is_ok()
+}
+
+fn poke2_check(
+    context: &[u8],
+    u: &BigUint,
+    w: &BigUint,
+    pi: &Poke2Proof,
+    n: &BigUint,
+) -> Result<(), VerifyError> {
+    let _span = span!("ni_poke2_verify", modulus_bits = n.bits());
     // {z, Q, r} <- pi
-    let (z, q_big, r) = pi;
+    let Poke2Proof { z, q: q_big, r } = pi;
 
-    // g <- H_G(u, w)
-    let mut to_hash = u.to_bytes_be();
-    to_hash.extend(&w.to_bytes_be());
+    // all group elements must be reduced, z = g^x can only be 1 for x = 0, but Q is 1
+    // whenever floor(x/l) = 0, which the verifier can't rule out without knowing x
+    let one = BigUint::one();
+    if u >= n || w >= n || z <= &one || z >= n || q_big.is_zero() || q_big >= n {
+        return Err(VerifyError::MalformedInput);
+    }
+
+    // g <- H_G(context, n, u, w)
+    let mut to_hash = context.to_vec();
+    to_hash.extend(poke2_transcript(n, &[n, u, w]));
     let g = hash_group::<_, Blake2b>(&to_hash, n);
 
-    // l <- H_prime(u, w, z)
-    to_hash.extend(&z.to_bytes_be());
-    let l = hash_prime::<_, Blake2b>(&to_hash);
+    // l <- H_prime(context, n, u, w, z)
+    to_hash.extend(poke2_transcript(n, &[z]));
+    let l = hash_prime::<_, Blake2b>(&to_hash).ok_or(VerifyError::MalformedInput)?;
 
-    // alpha = H(u, w, z, l)
+    // 0 <= r < l
+    let r = match r.to_biguint() {
+        Some(r) if r < l => r,
+        _ => return Err(VerifyError::MalformedInput),
+    };
+
+    // alpha = H(context, n, u, w, z, l)
     to_hash.extend(&l.to_bytes_be());
     let alpha = BigUint::from_bytes_be(&Blake2b::digest(&to_hash)[..]);
 
     // Q^l(ug^alpha)^r
-    let lhs: BigInt = ((q_big.modpow(&l, n)
-        * modpow_uint_int(&(u * &g.modpow(&alpha, n)), &r, n).expect("invalid state"))
-        % n)
-        .into();
+    let lhs = multi_modpow(q_big, &l, &(u * &g.modpow(&alpha, n)), &r, n);
 
     // wz^alpha
     let z_alpha = z.modpow(&alpha, n);
-    let rhs: BigInt = ((w * z_alpha) % n).into();
+    let rhs = (w * z_alpha) % n;
+
+    if lhs != rhs {
+        return Err(VerifyError::ChallengeMismatch);
+    }
 
-    lhs == rhs
+    Ok(())
 }
 
-#[cfg(test)]
+/// Encodes `xs` for the PoKE2 transcript, all with the width of `n`, so that different inputs
+/// never hash the same.
+fn poke2_transcript(n: &BigUint, xs: &[&BigUint]) -> Vec<u8> {
+    let len = modulus_bytes(n);
+
+    let mut res = Vec::with_capacity(len * xs.len());
+    for x in xs {
+        res.extend(to_fixed_bytes(x, len));
+    }
+
+    res
+}
+
+#[cfg(all(test, feature = "prover"))]
 mod tests {
     use super::*;
 
-    use num_bigint::{RandBigInt, RandPrime};
-    use num_traits::One;
-    use rand::thread_rng;
+    use num_bigint::{RandBigInt, RandPrime, Sign};
+    use rand::{thread_rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
 
     #[test]
     fn test_ni_poe() {
@@ -167,6 +632,223 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ni_poe_factored() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let u = rng.gen_biguint_below(&n);
+
+        // empty, small factors, factors below and above the challenge
+        let sizes: &[&[usize]] = &[&[], &[8], &[128; 7], &[64, 300, 2, 129], &[1024, 1024, 77]];
+        for sizes in sizes {
+            let factors = sizes
+                .iter()
+                .map(|&bits| rng.gen_biguint(bits) | BigUint::one())
+                .collect::<Vec<_>>();
+            let x = product(&factors);
+            let w = u.modpow(&x, &n);
+
+            let pi = ni_poe_prove_factored(&factors, &u, &w, &n);
+            assert_eq!(pi, ni_poe_prove(&x, &u, &w, &n));
+            assert!(ni_poe_verify(&x, &u, &w, &pi, &n));
+
+            let params = GroupParams::with_context(n.clone(), b"factored");
+            let pi = poe_prove_factored(&params, &factors, &u, &w);
+            assert_eq!(pi, poe_prove(&params, &x, &u, &w));
+            assert!(poe_verify(&params, &x, &u, &w, &pi));
+        }
+    }
+
+    #[test]
+    fn test_ni_poe_challenge_counter() {
+        use crate::hash::hash_prime_with_counter;
+
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let x = rng.gen_prime(256) * rng.gen_prime(256);
+
+        // bases whose challenge is only found after several candidates
+        let mut found = 0;
+        while found < 3 {
+            let u = rng.gen_biguint_below(&n);
+            let w = u.modpow(&x, &n);
+
+            let mut to_hash = x.to_bytes_be();
+            to_hash.extend(&u.to_bytes_be());
+            to_hash.extend(&w.to_bytes_be());
+            let (l, counter) = hash_prime_with_counter::<_, Blake2b>(&to_hash).unwrap();
+            if counter < 3 {
+                continue;
+            }
+            found += 1;
+
+            // prover and verifier derive the same l
+            assert_eq!(poe_challenge(&[], &x, &u, &w), Some(l.clone()));
+            let pi = ni_poe_prove(&x, &u, &w, &n);
+            assert_eq!(pi.q, u.modpow(&(&x / &l), &n));
+            assert!(ni_poe_verify(&x, &u, &w, &pi, &n));
+        }
+    }
+
+    #[test]
+    fn test_ni_poe_malformed() {
+        let mut rng = thread_rng();
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let u = rng.gen_biguint_below(&n);
+
+        // x > l, so Q is a regular group element
+        let x = rng.gen_prime(256) * rng.gen_prime(256);
+        let w = u.modpow(&x, &n);
+        let pi = ni_poe_prove(&x, &u, &w, &n);
+        assert!(ni_poe_verify(&x, &u, &w, &pi, &n));
+
+        for q in [
+            BigUint::zero(),
+            BigUint::one(),
+            n.clone(),
+            &pi.q + &n,
+            &pi.q + &n + &n,
+        ] {
+            assert!(!ni_poe_verify(&x, &u, &w, &PoeProof { q }, &n));
+        }
+
+        // x < l, so Q = u^0 = 1 is the only valid proof
+        let x = BigUint::from(65537u32);
+        let w = u.modpow(&x, &n);
+        let pi = ni_poe_prove(&x, &u, &w, &n);
+        assert!(pi.q.is_one());
+        assert!(ni_poe_verify(&x, &u, &w, &pi, &n));
+
+        for q in [BigUint::zero(), &n + 1u32, u.clone()] {
+            assert!(!ni_poe_verify(&x, &u, &w, &PoeProof { q }, &n));
+        }
+        assert!(!ni_poe_verify(&(&x + 2u32), &u, &w, &pi, &n));
+
+        // x = 0
+        let x = BigUint::zero();
+        let pi = ni_poe_prove(&x, &u, &BigUint::one(), &n);
+        assert!(ni_poe_verify(&x, &u, &BigUint::one(), &pi, &n));
+        assert!(!ni_poe_verify(&x, &u, &u, &pi, &n));
+    }
+
+    #[cfg(feature = "merlin")]
+    #[test]
+    fn test_ni_poe_merlin_transcript() {
+        use merlin::Transcript;
+
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let u = rng.gen_biguint_below(&n);
+        let x = rng.gen_prime(256) * rng.gen_prime(256);
+        let w = u.modpow(&x, &n);
+
+        let mut prover = Transcript::new(b"session");
+        let pi = ni_poe_prove_with_transcript(&mut prover, &x, &u, &w, &n);
+
+        let mut verifier = Transcript::new(b"session");
+        assert!(ni_poe_verify_with_transcript(
+            &mut verifier,
+            &x,
+            &u,
+            &w,
+            &pi,
+            &n
+        ));
+
+        // one extra byte in the transcript
+        let mut verifier = Transcript::new(b"session");
+        verifier.append_message(b"extra", &[0]);
+        assert!(!ni_poe_verify_with_transcript(
+            &mut verifier,
+            &x,
+            &u,
+            &w,
+            &pi,
+            &n
+        ));
+
+        // another session
+        let mut verifier = Transcript::new(b"other");
+        assert!(!ni_poe_verify_with_transcript(
+            &mut verifier,
+            &x,
+            &u,
+            &w,
+            &pi,
+            &n
+        ));
+    }
+
+    #[test]
+    fn test_ni_poe_transcript() {
+        use crate::transcript::Blake2bTranscript;
+
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let u = rng.gen_biguint_below(&n);
+        let x = rng.gen_prime(256) * rng.gen_prime(256);
+        let w = u.modpow(&x, &n);
+
+        // two proofs in one session
+        let session = || {
+            let mut t = Blake2bTranscript::new(b"session");
+            t.append_message(b"other proof", b"commitment");
+            t
+        };
+        let mut prover = session();
+        let pi1 = ni_poe_prove_with_transcript(&mut prover, &x, &u, &w, &n);
+        let pi2 = ni_poe_prove_with_transcript(&mut prover, &x, &u, &w, &n);
+        assert_ne!(pi1, pi2);
+
+        let mut verifier = session();
+        assert!(ni_poe_verify_with_transcript(
+            &mut verifier,
+            &x,
+            &u,
+            &w,
+            &pi1,
+            &n
+        ));
+        assert!(ni_poe_verify_with_transcript(
+            &mut verifier,
+            &x,
+            &u,
+            &w,
+            &pi2,
+            &n
+        ));
+
+        // both transcripts end in the same state
+        let (mut a, mut b) = ([0u8; 32], [0u8; 32]);
+        prover.challenge_bytes(b"next", &mut a);
+        verifier.challenge_bytes(b"next", &mut b);
+        assert_eq!(a, b);
+
+        // one extra byte in the transcript
+        let mut verifier = session();
+        verifier.append_message(b"extra", &[0]);
+        assert!(!ni_poe_verify_with_transcript(
+            &mut verifier,
+            &x,
+            &u,
+            &w,
+            &pi1,
+            &n
+        ));
+
+        // proofs out of order, or without the session
+        let mut verifier = session();
+        assert!(!ni_poe_verify_with_transcript(
+            &mut verifier,
+            &x,
+            &u,
+            &w,
+            &pi2,
+            &n
+        ));
+        assert!(!ni_poe_verify(&x, &u, &w, &pi1, &n));
+    }
+
     #[test]
     fn test_ni_poke2() {
         let mut rng = thread_rng();
@@ -174,16 +856,180 @@ mod tests {
         for i in 1..4 {
             for j in 1..4 {
                 for k in 1..4 {
-                    let n = rng.gen_biguint(i * 64);
+                    let n = rng.gen_prime(i * 64) * rng.gen_prime(i * 64);
 
                     let x = rng.gen_prime(j * 128);
-                    let u = rng.gen_prime(k * 64);
+                    let u = rng.gen_prime(k * 64) % &n;
                     let w = u.modpow(&x, &n);
 
-                    let pi = ni_poke2_prove(x.clone(), &u, &w, &n);
-                    assert!(ni_poke2_verify(&u, &w, &pi, &n))
+                    let pi = ni_poke2_prove(x.clone(), &u, &w, &n).unwrap();
+                    assert!(ni_poke2_verify(&u, &w, &pi, &n));
+
+                    // the tuple representation carries the same proof
+                    let tuple: (BigUint, BigUint, BigInt) = pi.clone().into();
+                    assert_eq!(Poke2Proof::from(tuple), pi);
                 }
             }
         }
     }
+
+    #[test]
+    fn test_ni_poke2_malformed() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let u = rng.gen_biguint_below(&n);
+        let x = BigInt::from(rng.gen_prime(256)) * -1;
+        let w = modpow_uint_int(&u, &x, &n).unwrap();
+
+        // negative exponents give a reduced remainder as well
+        let pi = ni_poke2_prove(x, &u, &w, &n).unwrap();
+        assert!(pi.r.sign() != Sign::Minus);
+        assert!(ni_poke2_verify(&u, &w, &pi, &n));
+
+        // recompute l, to build remainders just out of range
+        let mut to_hash = poke2_transcript(&n, &[&n, &u, &w]);
+        to_hash.extend(poke2_transcript(&n, &[&pi.z]));
+        let l: BigInt = hash_prime::<_, Blake2b>(&to_hash).unwrap().into();
+
+        for r in &[
+            l.clone(),
+            &l + &pi.r,
+            BigInt::from(-1),
+            -(&l + 1u32),
+            &pi.r - &l,
+            -(BigInt::one() << 4096),
+        ] {
+            let pi = Poke2Proof {
+                r: r.clone(),
+                ..pi.clone()
+            };
+            assert!(!ni_poke2_verify(&u, &w, &pi, &n), "r = {}", r);
+        }
+
+        // group elements out of range
+        for z in &[BigUint::zero(), BigUint::one(), n.clone(), &pi.z + &n] {
+            let pi = Poke2Proof {
+                z: z.clone(),
+                ..pi.clone()
+            };
+            assert!(!ni_poke2_verify(&u, &w, &pi, &n), "z = {}", z);
+        }
+        for q in &[BigUint::zero(), n.clone(), &pi.q + &n] {
+            let pi = Poke2Proof {
+                q: q.clone(),
+                ..pi.clone()
+            };
+            assert!(!ni_poke2_verify(&u, &w, &pi, &n), "Q = {}", q);
+        }
+        assert!(!ni_poke2_verify(&(&u + &n), &w, &pi, &n));
+        assert!(!ni_poke2_verify(&u, &(&w + &n), &pi, &n));
+    }
+
+    #[test]
+    fn test_verify_detailed() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let params = GroupParams::new(rng.gen_prime(128) * rng.gen_prime(128));
+        let n = &params.n;
+        let u = rng.gen_biguint_below(n);
+        let x = rng.gen_prime(256) * rng.gen_prime(256);
+        let w = u.modpow(&x, n);
+
+        let pi = poe_prove(&params, &x, &u, &w);
+        assert_eq!(poe_verify_detailed(&params, &x, &u, &w, &pi), Ok(()));
+        assert_eq!(
+            poe_verify_detailed(&params, &(&x + 2u32), &u, &w, &pi),
+            Err(VerifyError::ChallengeMismatch)
+        );
+        let unreduced = PoeProof { q: &pi.q + n };
+        assert_eq!(
+            poe_verify_detailed(&params, &x, &u, &w, &unreduced),
+            Err(VerifyError::MalformedInput)
+        );
+        assert_eq!(
+            poe_verify_detailed(&params, &x, &u, &(&w + n), &pi),
+            Err(VerifyError::MalformedInput)
+        );
+
+        let pi = poke2_prove(&params, x.clone(), &u, &w).unwrap();
+        assert_eq!(poke2_verify_detailed(&params, &u, &w, &pi), Ok(()));
+        assert_eq!(
+            poke2_verify_detailed(&params, &u, &u, &pi),
+            Err(VerifyError::ChallengeMismatch)
+        );
+        let r_out_of_range = Poke2Proof {
+            r: -BigInt::one(),
+            ..pi.clone()
+        };
+        assert_eq!(
+            poke2_verify_detailed(&params, &u, &w, &r_out_of_range),
+            Err(VerifyError::MalformedInput)
+        );
+        assert_eq!(
+            poke2_verify_detailed(&params, &(&u + n), &w, &pi),
+            Err(VerifyError::MalformedInput)
+        );
+    }
+
+    #[test]
+    fn test_group_params() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let u = rng.gen_biguint_below(&n);
+        let x = rng.gen_prime(256) * rng.gen_prime(256);
+        let w = u.modpow(&x, &n);
+
+        // the default parameters give the same proofs as the shorthands
+        let params = GroupParams::new(n.clone());
+        let pi = poe_prove(&params, &x, &u, &w);
+        assert_eq!(pi, ni_poe_prove(&x, &u, &w, &n));
+        assert!(poe_verify(&params, &x, &u, &w, &pi));
+        let pi2 = poke2_prove(&params, x.clone(), &u, &w).unwrap();
+        assert_eq!(pi2, ni_poke2_prove(x.clone(), &u, &w, &n).unwrap());
+        assert!(poke2_verify(&params, &u, &w, &pi2));
+
+        // and the same as a context with `ni_poe_prove_with_context`
+        let other = GroupParams::with_context(n.clone(), b"other protocol");
+        let pi_other = poe_prove(&other, &x, &u, &w);
+        assert_eq!(
+            pi_other,
+            ni_poe_prove_with_context(b"other protocol", &x, &u, &w, &n)
+        );
+
+        // proofs don't verify under another context
+        assert!(poe_verify(&other, &x, &u, &w, &pi_other));
+        assert!(!poe_verify(&params, &x, &u, &w, &pi_other));
+        assert!(!poe_verify(&other, &x, &u, &w, &pi));
+        let pi2_other = poke2_prove(&other, x.clone(), &u, &w).unwrap();
+        assert!(poke2_verify(&other, &u, &w, &pi2_other));
+        assert!(!poke2_verify(&params, &u, &w, &pi2_other));
+        assert!(!poke2_verify(&other, &u, &w, &pi2));
+
+        // nor in another group
+        let m = rng.gen_prime(128) * rng.gen_prime(128);
+        assert!(!poe_verify(&GroupParams::new(m), &x, &u, &w, &pi));
+    }
+
+    #[test]
+    fn test_poe_vdf() {
+        // y = x^(2^T), evaluated by repeated squaring, proven with x = 2^T as exponent
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let params = GroupParams::with_context(rng.gen_prime(256) * rng.gen_prime(256), b"vdf");
+        let n = &params.n;
+        let t = 1000;
+        let input = rng.gen_biguint_below(n);
+
+        let mut output = input.clone();
+        for _ in 0..t {
+            output = (&output * &output) % n;
+        }
+        let exponent = BigUint::one() << t;
+        let pi = poe_prove(&params, &exponent, &input, &output);
+        assert!(poe_verify(&params, &exponent, &input, &output, &pi));
+
+        // one squaring short
+        let wrong = BigUint::one() << (t - 1);
+        assert!(!poe_verify(&params, &wrong, &input, &output, &pi));
+        let output = (&output * &output) % n;
+        assert!(!poe_verify(&params, &exponent, &input, &output, &pi));
+    }
 }
@@ -0,0 +1,161 @@
+//! Republishing an accumulator under a rerandomized generator, so that the copies can't be
+//! linked by their public values.
+//!
+//! `Accumulator::rerandomize` raises the generator `g` and the state `A = g^s` to a secret
+//! random prime `r`, giving an accumulator with the generator `g^r` and state `(g^r)^s` over
+//! the same set. Witnesses for the old state are raised to `r` as well, with
+//! `transform_witness` and `transform_non_mem_witness`, to verify against the new one.
+//!
+//! # What is hidden
+//!
+//! Without `r` or the set, telling whether `(g', A')` is `(g^r, A^r)` for the published
+//! `(g, A)`, or unrelated, is the decisional Diffie-Hellman problem in the group. That is
+//! believed hard in the quadratic residues `QR_n`, so with `GroupMode::QuadraticResidues`
+//! two publications of the same set look like publications of different sets.
+//!
+//! # Limits
+//!
+//! - Both copies use the same modulus `n`, which links them on its own unless many
+//!   accumulators share it, e.g. the standard parameters.
+//! - In `Z_n^*`, the default `GroupMode::Full`, DDH does not hold: the odd `r` keeps the
+//!   Jacobi symbols of `g` and `A`, which leaks a bit each.
+//! - The number of elements, epochs and anything else published alongside are unchanged.
+//! - Anyone knowing the whole set can recompute both states from the generators.
+//! - Transformed witnesses are linkable to their originals by whoever holds `r`.
+
+use std::fmt;
+
+use num_bigint::BigUint;
+
+use crate::accumulator::{MembershipWitness, NonMembershipWitness};
+use crate::group::zeroize;
+
+/// Size of the random exponent in bits. A prime this large divides the order of the group
+/// only with negligible probability, so raising to it is a bijection.
+pub const RERANDOMIZATION_BITS: usize = 256;
+
+/// The secret exponent `r` of `Accumulator::rerandomize`, for transforming witnesses.
+///
+/// Anyone holding it can link the rerandomized accumulator to the original, so it is never
+/// printed, and overwritten on a best effort basis when dropped, like the primes of an
+/// `RsaSetup`.
+pub struct RerandomizationSecret {
+    r: BigUint,
+    n: BigUint,
+}
+
+impl RerandomizationSecret {
+    pub(crate) fn new(r: BigUint, n: BigUint) -> Self {
+        RerandomizationSecret { r, n }
+    }
+
+    /// Returns the exponent `r`.
+    pub fn exponent(&self) -> &BigUint {
+        &self.r
+    }
+
+    /// Raises `v` to `r`, `mod n`.
+    pub(crate) fn apply(&self, v: &BigUint) -> BigUint {
+        v.modpow(&self.r, &self.n)
+    }
+}
+
+impl Drop for RerandomizationSecret {
+    fn drop(&mut self) {
+        zeroize(&mut self.r);
+    }
+}
+
+impl fmt::Debug for RerandomizationSecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RerandomizationSecret")
+            .field("r", &"<secret>")
+            .field("n", &self.n)
+            .finish()
+    }
+}
+
+/// Transforms the membership witness `w`, for the state before `rerandomize`, into one for
+/// the rerandomized state: `w^r`, as `(w^r)^x = (g^r)^s`.
+pub fn transform_witness(
+    w: &MembershipWitness,
+    secret: &RerandomizationSecret,
+) -> MembershipWitness {
+    MembershipWitness(secret.apply(&w.0))
+}
+
+/// Transforms the non-membership witness `(d, b)`, for the state before `rerandomize`, into
+/// one for the rerandomized state: `(d^r, b)`, as `d^x A^b = g` implies
+/// `(d^r)^x (A^r)^b = g^r`.
+pub fn transform_non_mem_witness(
+    w: &NonMembershipWitness,
+    secret: &RerandomizationSecret,
+) -> NonMembershipWitness {
+    NonMembershipWitness {
+        d: secret.apply(&w.d),
+        b: w.b.clone(),
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    use crate::accumulator::Accumulator;
+    use crate::primes::RandPrimes;
+    use crate::setup::{GroupMode, SetupParams};
+    use crate::traits::*;
+
+    #[test]
+    fn test_rerandomize() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = SetupParams::generate(rng, int_size_bits).unwrap();
+        let (n, g) = (params.modulus().clone(), params.generator().clone());
+        let params =
+            SetupParams::new_with_mode(int_size_bits, n, g, GroupMode::QuadraticResidues).unwrap();
+        let mut acc = Accumulator::new_with_params(Arc::new(params));
+        let xs = rng.gen_distinct_primes(5, 128);
+        acc.add_batch_no_proof(&xs[..4]);
+        let w = acc.mem_wit_create(&xs[0]);
+        let u = acc.non_mem_wit_create(&xs[4]);
+
+        let (copy, secret) = acc.rerandomize(rng);
+        assert!(!format!("{:?}", secret).contains(&secret.exponent().to_string()));
+        assert_eq!(copy.len(), acc.len());
+        assert_eq!(copy.params().mode(), GroupMode::QuadraticResidues);
+        assert_ne!(copy.params().generator(), acc.params().generator());
+        assert_ne!(copy.state(), acc.state());
+        assert!(!copy.shares_params(&acc));
+
+        // transformed witnesses verify, the originals don't
+        let w_r = transform_witness(&w, &secret);
+        assert!(copy.ver_mem(&w_r, &xs[0]));
+        assert!(!copy.ver_mem(&w, &xs[0]));
+        assert!(!acc.ver_mem(&w_r, &xs[0]));
+        assert!(!copy.ver_mem(&w_r, &xs[1]));
+        let u_r = transform_non_mem_witness(&u, &secret);
+        assert!(copy.ver_non_mem(&u_r, &xs[4]));
+        assert!(!copy.ver_non_mem(&u, &xs[4]));
+
+        // the same as witnesses created by the copy
+        assert_eq!(w_r, copy.mem_wit_create(&xs[0]));
+
+        // and the copy stays usable
+        let mut copy = copy;
+        copy.add(&xs[4]);
+        let w = copy.mem_wit_create(&xs[4]);
+        assert!(copy.ver_mem(&w, &xs[4]));
+        copy.del(&xs[0]).unwrap();
+        assert!(!copy.ver_mem(&w_r, &xs[0]));
+
+        // each rerandomization is different
+        let (other, _) = acc.rerandomize(rng);
+        assert_ne!(other.state(), copy.state());
+    }
+}
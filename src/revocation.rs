@@ -0,0 +1,514 @@
+//! Credential revocation, on top of an accumulator.
+//!
+//! A `RevocationRegistry` maps credential ids to primes, keeps the status witnesses of all
+//! issued credentials up to date, and publishes its state in epochs. Holders present a
+//! `NonRevokedProof` for the latest epoch, which anyone knowing the `RegistryState` checks
+//! with `verify_status`.
+
+#[cfg(feature = "prover")]
+use std::collections::HashMap;
+use std::fmt;
+
+use blake2::Blake2b;
+use num_bigint::BigUint;
+
+#[cfg(feature = "prover")]
+use crate::accumulator::Accumulator;
+use crate::accumulator::{MembershipWitness, NonMembershipWitness, PublicState};
+use crate::hash::hash_prime;
+#[cfg(feature = "prover")]
+use crate::history::{AccumulatorLog, Transition};
+#[cfg(feature = "prover")]
+use crate::math::product;
+#[cfg(feature = "prover")]
+use crate::traits::*;
+#[cfg(feature = "prover")]
+use crate::witness::WitnessManager;
+
+const CREDENTIAL_LABEL: &[u8] = b"accumulators/revocation/credential";
+
+/// What the accumulator of a registry contains.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RevocationMode {
+    /// The valid credentials, non-revoked credentials prove membership.
+    Allowlist,
+    /// The revoked credentials, non-revoked credentials prove non-membership.
+    Denylist,
+}
+
+/// A credential issued by a `RevocationRegistry`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RevocationHandle {
+    /// The id the credential was registered with.
+    pub cred_id: Vec<u8>,
+    /// The prime representing the credential in the accumulator.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub element: BigUint,
+}
+
+/// The public state of a registry at the end of an epoch, everything a verifier needs.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryState {
+    pub mode: RevocationMode,
+    /// The epoch this state was published in, `0` before any changes.
+    pub epoch: u64,
+    pub state: PublicState,
+}
+
+/// A witness for the status of a credential, depending on the `RevocationMode`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusWitness {
+    /// Membership in an allowlist.
+    Mem(MembershipWitness),
+    /// Non-membership in a denylist.
+    NonMem(NonMembershipWitness),
+}
+
+/// Proof that a credential was not revoked at the end of `epoch`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonRevokedProof {
+    pub epoch: u64,
+    pub witness: StatusWitness,
+}
+
+/// Errors of `RevocationRegistry::revoke`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevocationError {
+    /// The credential was not registered.
+    Unknown,
+    /// The credential is already revoked, or its revocation is pending.
+    AlreadyRevoked,
+}
+
+impl fmt::Display for RevocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RevocationError::Unknown => write!(f, "unknown credential"),
+            RevocationError::AlreadyRevoked => write!(f, "the credential is already revoked"),
+        }
+    }
+}
+
+impl std::error::Error for RevocationError {}
+
+/// Returns the prime representing the credential `cred_id`.
+pub fn credential_element(cred_id: &[u8]) -> BigUint {
+    let mut to_hash = CREDENTIAL_LABEL.to_vec();
+    to_hash.extend_from_slice(cred_id);
+
+    hash_prime::<_, Blake2b>(&to_hash).expect("no prime found")
+}
+
+/// Verifies that the credential `cred_id` was not revoked, according to `registry`.
+///
+/// Proofs are only valid for the epoch they were created in.
+pub fn verify_status(registry: &RegistryState, cred_id: &[u8], proof: &NonRevokedProof) -> bool {
+    if proof.epoch != registry.epoch {
+        return false;
+    }
+
+    let x = credential_element(cred_id);
+    match (&registry.mode, &proof.witness) {
+        (RevocationMode::Allowlist, StatusWitness::Mem(w)) => registry.state.ver_mem(w, &x),
+        (RevocationMode::Denylist, StatusWitness::NonMem(w)) => registry.state.ver_non_mem(w, &x),
+        _ => false,
+    }
+}
+
+/// A registry of credentials, any of which can be revoked.
+///
+/// Registrations and revocations are collected, and applied to the accumulator when the next
+/// epoch is published with `publish_epoch`. Until then, witnesses and proofs are those for
+/// the last published state. Every epoch is recorded in an `AccumulatorLog`, so that the
+/// transitions between published states can be audited.
+#[cfg(feature = "prover")]
+#[derive(Debug, Clone)]
+pub struct RevocationRegistry {
+    mode: RevocationMode,
+    acc: Accumulator,
+    log: AccumulatorLog,
+
+    /// Issued credentials, and whether their revocation has been published.
+    credentials: HashMap<BigUint, bool>,
+    /// Elements to add to the accumulator with the next epoch.
+    pending_add: Vec<BigUint>,
+    /// Elements to delete from the accumulator with the next epoch.
+    pending_del: Vec<BigUint>,
+
+    /// Membership witnesses of the valid credentials, in allowlist mode.
+    mem_witnesses: WitnessManager,
+    /// Non-membership witnesses of the valid credentials, in denylist mode.
+    non_mem_witnesses: HashMap<BigUint, NonMembershipWitness>,
+}
+
+#[cfg(feature = "prover")]
+impl RevocationRegistry {
+    /// Creates an empty registry, with an accumulator in `Z_n` using the generator `g`.
+    pub fn new(n: BigUint, g: BigUint, mode: RevocationMode) -> Self {
+        let acc = Accumulator::new(n, g);
+
+        RevocationRegistry {
+            mode,
+            log: AccumulatorLog::new(acc.generator()),
+            mem_witnesses: WitnessManager::new(&acc),
+            acc,
+            credentials: HashMap::new(),
+            pending_add: Vec::new(),
+            pending_del: Vec::new(),
+            non_mem_witnesses: HashMap::new(),
+        }
+    }
+
+    /// Returns the mode of the registry.
+    pub fn mode(&self) -> RevocationMode {
+        self.mode
+    }
+
+    /// Returns the latest published state.
+    pub fn state(&self) -> RegistryState {
+        RegistryState {
+            mode: self.mode,
+            epoch: self.log.epoch(),
+            state: self.acc.public_state(),
+        }
+    }
+
+    /// Returns the log of all published epochs.
+    pub fn log(&self) -> &AccumulatorLog {
+        &self.log
+    }
+
+    /// Issues the credential `cred_id`, registering the same id twice returns the same handle.
+    ///
+    /// In allowlist mode, the credential is valid from the next epoch on.
+    pub fn register(&mut self, cred_id: &[u8]) -> RevocationHandle {
+        let element = credential_element(cred_id);
+
+        if !self.credentials.contains_key(&element) {
+            self.credentials.insert(element.clone(), false);
+
+            match self.mode {
+                RevocationMode::Allowlist => self.pending_add.push(element.clone()),
+                RevocationMode::Denylist => {
+                    let w = self.acc.non_mem_wit_create(&element);
+                    self.non_mem_witnesses.insert(element.clone(), w);
+                }
+            }
+        }
+
+        RevocationHandle {
+            cred_id: cred_id.to_vec(),
+            element,
+        }
+    }
+
+    /// Revokes the credential, from the next epoch on.
+    pub fn revoke(&mut self, handle: &RevocationHandle) -> Result<(), RevocationError> {
+        let x = &handle.element;
+        match self.credentials.get(x) {
+            None => return Err(RevocationError::Unknown),
+            Some(true) => return Err(RevocationError::AlreadyRevoked),
+            Some(false) => {}
+        }
+        if self.pending_del.contains(x) {
+            return Err(RevocationError::AlreadyRevoked);
+        }
+
+        match self.mode {
+            RevocationMode::Allowlist => {
+                if let Some(i) = self.pending_add.iter().position(|y| y == x) {
+                    // never published, so there is nothing to delete
+                    self.pending_add.swap_remove(i);
+                    self.credentials.insert(x.clone(), true);
+                } else {
+                    self.pending_del.push(x.clone());
+                }
+            }
+            RevocationMode::Denylist => {
+                if self.pending_add.contains(x) {
+                    return Err(RevocationError::AlreadyRevoked);
+                }
+                self.pending_add.push(x.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if the revocation of the credential has been published.
+    pub fn is_revoked(&self, handle: &RevocationHandle) -> bool {
+        self.credentials.get(&handle.element) == Some(&true)
+    }
+
+    /// Applies all pending registrations and revocations, and returns the new state.
+    ///
+    /// Additions and deletions are recorded as separate epochs, so this advances the epoch by
+    /// up to two, or not at all if nothing changed.
+    pub fn publish_epoch(&mut self) -> RegistryState {
+        let adds = std::mem::take(&mut self.pending_add);
+        let dels = std::mem::take(&mut self.pending_del);
+
+        match self.mode {
+            RevocationMode::Allowlist => {
+                if !adds.is_empty() {
+                    let proof = self.mem_witnesses.batch_add(&mut self.acc, &adds);
+                    self.log.append_epoch(
+                        Transition::Add(adds.clone()),
+                        self.acc.state().clone(),
+                        proof,
+                    );
+                    for x in &adds {
+                        self.mem_witnesses.register(&self.acc, x);
+                    }
+                }
+
+                if !dels.is_empty() {
+                    let pairs = dels
+                        .iter()
+                        .map(|x| {
+                            let w = self
+                                .mem_witnesses
+                                .witness_for(x)
+                                .expect("untracked")
+                                .clone();
+                            (x.clone(), w)
+                        })
+                        .collect::<Vec<_>>();
+                    let proof = self
+                        .mem_witnesses
+                        .batch_del(&mut self.acc, &pairs)
+                        .expect("invalid witness");
+                    self.mem_witnesses.drain_events();
+                    self.log.append_epoch(
+                        Transition::Del(dels.clone()),
+                        self.acc.state().clone(),
+                        proof,
+                    );
+                }
+
+                for x in &dels {
+                    self.credentials.insert(x.clone(), true);
+                }
+            }
+            RevocationMode::Denylist => {
+                debug_assert!(dels.is_empty(), "denylists only grow");
+
+                if !adds.is_empty() {
+                    let root = self.acc.state().clone();
+                    let proof = self.acc.batch_add(&adds);
+                    self.log.append_epoch(
+                        Transition::Add(adds.clone()),
+                        self.acc.state().clone(),
+                        proof,
+                    );
+
+                    for x in &adds {
+                        self.credentials.insert(x.clone(), true);
+                        self.non_mem_witnesses.remove(x);
+                    }
+
+                    let y = product(&adds);
+                    let n = self.acc.modulus();
+                    for (x, w) in self.non_mem_witnesses.iter_mut() {
+                        w.update_on_add(x, &y, &root, n)
+                            .expect("revoked credentials are removed");
+                    }
+                }
+            }
+        }
+
+        self.state()
+    }
+
+    /// Returns the status witness for the credential, valid for the latest published state,
+    /// or `None` if it is revoked or, in allowlist mode, not yet published.
+    pub fn witness_for(&self, handle: &RevocationHandle) -> Option<StatusWitness> {
+        let x = &handle.element;
+        if self.credentials.get(x) != Some(&false) {
+            return None;
+        }
+
+        match self.mode {
+            RevocationMode::Allowlist => self
+                .mem_witnesses
+                .witness_for(x)
+                .cloned()
+                .map(StatusWitness::Mem),
+            RevocationMode::Denylist => self
+                .non_mem_witnesses
+                .get(x)
+                .cloned()
+                .map(StatusWitness::NonMem),
+        }
+    }
+
+    /// Returns the proof that the credential is not revoked in the latest published epoch,
+    /// see `witness_for`.
+    pub fn status_proof(&self, handle: &RevocationHandle) -> Option<NonRevokedProof> {
+        let witness = self.witness_for(handle)?;
+
+        Some(NonRevokedProof {
+            epoch: self.log.epoch(),
+            witness,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn registry(mode: RevocationMode) -> RevocationRegistry {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        RevocationRegistry::new(acc.modulus().clone(), acc.generator().clone(), mode)
+    }
+
+    fn issue_prove_revoke(mode: RevocationMode) {
+        let mut reg = registry(mode);
+        assert_eq!(reg.mode(), mode);
+
+        let alice = reg.register(b"alice");
+        let bob = reg.register(b"bob");
+        let carol = reg.register(b"carol");
+        assert_eq!(reg.register(b"alice"), alice);
+
+        let state = reg.publish_epoch();
+        let pi_alice = reg.status_proof(&alice).unwrap();
+        let pi_bob = reg.status_proof(&bob).unwrap();
+        assert!(verify_status(&state, b"alice", &pi_alice));
+        assert!(verify_status(&state, b"bob", &pi_bob));
+        if mode == RevocationMode::Allowlist {
+            // an empty denylist revokes nobody, so there any witness verifies
+            assert!(!verify_status(&state, b"bob", &pi_alice));
+            assert!(!verify_status(&state, b"mallory", &pi_alice));
+        }
+
+        // revocations only take effect when published
+        reg.revoke(&alice).unwrap();
+        assert_eq!(reg.revoke(&alice), Err(RevocationError::AlreadyRevoked));
+        assert!(!reg.is_revoked(&alice));
+        assert_eq!(reg.status_proof(&alice), Some(pi_alice.clone()));
+
+        let new_state = reg.publish_epoch();
+        assert!(new_state.epoch > state.epoch);
+        assert!(reg.is_revoked(&alice));
+        assert_eq!(reg.revoke(&alice), Err(RevocationError::AlreadyRevoked));
+
+        // the old proof is stale, and no new one can be created
+        assert!(!verify_status(&new_state, b"alice", &pi_alice));
+        let stale = NonRevokedProof {
+            epoch: new_state.epoch,
+            ..pi_alice
+        };
+        assert!(!verify_status(&new_state, b"alice", &stale));
+        assert_eq!(reg.status_proof(&alice), None);
+
+        // the others are kept up to date
+        assert!(!verify_status(&new_state, b"bob", &pi_bob));
+        let pi_bob = reg.status_proof(&bob).unwrap();
+        assert!(verify_status(&new_state, b"bob", &pi_bob));
+        let pi_carol = reg.status_proof(&carol).unwrap();
+        assert!(verify_status(&new_state, b"carol", &pi_carol));
+        assert!(!verify_status(&new_state, b"carol", &pi_bob));
+        assert!(!verify_status(&new_state, b"mallory", &pi_bob));
+
+        // a credential registered later
+        let dave = reg.register(b"dave");
+        reg.revoke(&bob).unwrap();
+        let state = reg.publish_epoch();
+        assert!(verify_status(
+            &state,
+            b"dave",
+            &reg.status_proof(&dave).unwrap()
+        ));
+        assert!(verify_status(
+            &state,
+            b"carol",
+            &reg.status_proof(&carol).unwrap()
+        ));
+        assert_eq!(reg.status_proof(&bob), None);
+
+        // nothing pending, nothing published
+        assert_eq!(reg.publish_epoch(), state);
+
+        let unknown = RevocationHandle {
+            cred_id: b"mallory".to_vec(),
+            element: credential_element(b"mallory"),
+        };
+        assert_eq!(reg.revoke(&unknown), Err(RevocationError::Unknown));
+        assert_eq!(reg.status_proof(&unknown), None);
+
+        assert_eq!(reg.log().epoch(), state.epoch);
+        assert!(reg.log().verify_chain(&state.state.n).is_ok());
+    }
+
+    #[test]
+    fn test_allowlist() {
+        issue_prove_revoke(RevocationMode::Allowlist);
+    }
+
+    #[test]
+    fn test_denylist() {
+        issue_prove_revoke(RevocationMode::Denylist);
+    }
+
+    #[test]
+    fn test_allowlist_revoke_unpublished() {
+        let mut reg = registry(RevocationMode::Allowlist);
+
+        let alice = reg.register(b"alice");
+        assert_eq!(reg.status_proof(&alice), None);
+        reg.revoke(&alice).unwrap();
+        assert!(reg.is_revoked(&alice));
+
+        let state = reg.publish_epoch();
+        assert_eq!(state.epoch, 0);
+        assert_eq!(reg.status_proof(&alice), None);
+    }
+
+    #[test]
+    fn test_mode_mismatch() {
+        let mut allow = registry(RevocationMode::Allowlist);
+        let mut deny = registry(RevocationMode::Denylist);
+
+        let a = allow.register(b"alice");
+        let d = deny.register(b"alice");
+        let allow_state = allow.publish_epoch();
+        let deny_state = deny.publish_epoch();
+
+        let pi_a = allow.status_proof(&a).unwrap();
+        let pi_d = deny.status_proof(&d).unwrap();
+        assert!(verify_status(&deny_state, b"alice", &pi_d));
+        assert!(!verify_status(&deny_state, b"alice", &pi_a));
+        assert!(!verify_status(&allow_state, b"alice", &pi_d));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_json() {
+        let mut reg = registry(RevocationMode::Denylist);
+        let alice = reg.register(b"alice");
+        let bob = reg.register(b"bob");
+        reg.revoke(&bob).unwrap();
+        let state = reg.publish_epoch();
+        let pi = reg.status_proof(&alice).unwrap();
+
+        let state: RegistryState =
+            serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+        let pi: NonRevokedProof =
+            serde_json::from_str(&serde_json::to_string(&pi).unwrap()).unwrap();
+        assert!(verify_status(&state, b"alice", &pi));
+    }
+}
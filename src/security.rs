@@ -0,0 +1,220 @@
+//! Security levels as types, so that accumulators of different sizes can't be mixed up.
+//!
+//! `Accumulator<L>` takes a `SecurityLevel` marker, `Dynamic` by default, which accepts any
+//! size of modulus like before. With `Lambda2048` or `Lambda3072`, the size is part of the
+//! type: converting into it with `Accumulator::into_level`, or deserializing it, fails if the
+//! declared size of the modulus or the modulus itself have a different size. Their element
+//! policy then only rejects elements of less than `ELEMENT_BITS` bits, unless another policy
+//! was set.
+//!
+//! ```compile_fail
+//! use accumulators::{Accumulator, Lambda2048, Lambda3072};
+//!
+//! fn production(acc: &Accumulator<Lambda3072>) {}
+//!
+//! fn mixed_up(acc: &Accumulator<Lambda2048>) {
+//!     production(acc);
+//! }
+//! ```
+
+use std::fmt;
+
+use crate::accumulator::{ParamError, PublicState};
+use crate::encoding::CHALLENGE_BYTES;
+
+/// A size of the modulus, and the sizes of the elements and challenges that go with it.
+pub trait SecurityLevel: fmt::Debug + Clone + Copy + Default + Send + Sync + 'static {
+    /// Size of the modulus in bits, `None` if any size is accepted.
+    const LAMBDA: Option<usize>;
+    /// Minimum size of the elements in bits, enforced by the default element policy of
+    /// `Accumulator<L>`, see `ElementPolicy::for_level`.
+    const ELEMENT_BITS: usize;
+    /// Size of the challenges of the non-interactive proofs in bits, those of `hash_prime`.
+    const CHALLENGE_BITS: usize = CHALLENGE_BYTES * 8;
+
+    /// Checks that `lambda` is the size of this level.
+    fn check_lambda(lambda: usize) -> Result<(), ParamError> {
+        match Self::LAMBDA {
+            Some(expected) if expected != lambda => Err(ParamError::ModulusSize {
+                expected,
+                actual: lambda,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the modulus of `state` has the size of this level, e.g. for a state
+    /// received from elsewhere.
+    fn check_state(state: &PublicState) -> Result<(), ParamError> {
+        Self::check_lambda(state.n.bits())
+    }
+}
+
+/// Any size of modulus, checked at runtime only, and elements of any size, the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Dynamic;
+
+impl SecurityLevel for Dynamic {
+    const LAMBDA: Option<usize> = None;
+    const ELEMENT_BITS: usize = 0;
+}
+
+/// Minimum size of the elements of the typed levels, half the size of the primes from
+/// `hash_prime`, which only fall below it with a probability of `2^-64`.
+const TYPED_ELEMENT_BITS: usize = 64;
+
+/// A modulus of 2048 bits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Lambda2048;
+
+impl SecurityLevel for Lambda2048 {
+    const LAMBDA: Option<usize> = Some(2048);
+    const ELEMENT_BITS: usize = TYPED_ELEMENT_BITS;
+}
+
+/// A modulus of 3072 bits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Lambda3072;
+
+impl SecurityLevel for Lambda3072 {
+    const LAMBDA: Option<usize> = Some(3072);
+    const ELEMENT_BITS: usize = TYPED_ELEMENT_BITS;
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use num_bigint::{BigUint, RandBigInt, RandPrime};
+    use num_traits::One;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    use blake2::Blake2b;
+
+    use crate::accumulator::{Accumulator, ElementPolicy};
+    use crate::group::RSAGroup;
+    use crate::hash::hash_prime;
+    use crate::primes::RandPrimes;
+    use crate::traits::*;
+
+    /// An accumulator with a modulus of `bits` bits, without a slow setup, as only the sizes
+    /// are checked.
+    fn accumulator(rng: &mut ChaChaRng, bits: usize) -> Accumulator {
+        let n = rng.gen_biguint(bits) | BigUint::one() | (BigUint::one() << (bits - 1));
+        Accumulator::new(n, BigUint::from(65537u32))
+    }
+
+    #[test]
+    fn test_into_level() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let small = Accumulator::setup::<RSAGroup, _>(rng, 256); // insecure, but faster tests
+        assert_eq!(
+            small.clone().into_level::<Lambda2048>().err(),
+            Some(ParamError::ModulusSize {
+                expected: 2048,
+                actual: 256
+            })
+        );
+        assert!(small.into_level::<Dynamic>().is_ok());
+
+        let mut acc = accumulator(rng, 2048).into_level::<Lambda2048>().unwrap();
+        let xs = rng.gen_distinct_primes(2, 128);
+        acc.add(&xs[0]);
+        let w = acc.mem_wit_create(&xs[0]);
+        assert!(acc.ver_mem(&w, &xs[0]));
+        assert_eq!(
+            acc.clone().into_dynamic().into_level::<Lambda3072>().err(),
+            Some(ParamError::ModulusSize {
+                expected: 3072,
+                actual: 2048
+            })
+        );
+
+        // converting keeps the state
+        let mut dynamic = acc.into_dynamic();
+        assert!(dynamic.contains(&xs[0]));
+        dynamic.add(&xs[1]);
+        let acc = dynamic.into_level::<Lambda2048>().unwrap();
+        assert_eq!(acc.len(), 2);
+
+        assert!(Lambda2048::check_state(&acc.public_state()).is_ok());
+        assert!(Lambda3072::check_state(&acc.public_state()).is_err());
+        assert!(Dynamic::check_state(&acc.public_state()).is_ok());
+    }
+
+    #[test]
+    fn test_level_element_policy() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let acc = accumulator(rng, 2048);
+        assert_eq!(acc.element_bits_policy(), &ElementPolicy::default());
+
+        let mut typed = acc.clone().into_level::<Lambda2048>().unwrap();
+        assert_eq!(
+            typed.element_bits_policy(),
+            &ElementPolicy::for_level::<Lambda2048>()
+        );
+        let small = rng.gen_prime(32);
+        assert!(typed.add_checked(&small).is_err());
+        assert!(typed
+            .add_checked(&rng.gen_prime(Lambda2048::ELEMENT_BITS))
+            .is_ok());
+        assert!(typed
+            .add_checked(&hash_prime::<_, Blake2b>(b"element").unwrap())
+            .is_ok());
+
+        // an explicit policy is kept
+        let policy = ElementPolicy {
+            min_bits: 16,
+            max_bits: 256,
+        };
+        let mut typed = acc
+            .with_element_policy(policy)
+            .into_level::<Lambda2048>()
+            .unwrap();
+        assert_eq!(typed.element_bits_policy(), &policy);
+        assert!(typed.add_checked(&small).is_ok());
+    }
+
+    #[test]
+    fn test_challenge_bits() {
+        assert_eq!(Dynamic::CHALLENGE_BITS, 128);
+        assert_eq!(Lambda2048::CHALLENGE_BITS, Lambda3072::CHALLENGE_BITS);
+
+        // the challenges of the proofs are the primes from `hash_prime`
+        for i in 0..20u32 {
+            let l = hash_prime::<_, Blake2b>(&i.to_be_bytes()).unwrap();
+            assert!(l.bits() <= Lambda2048::CHALLENGE_BITS);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_level() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let acc = accumulator(rng, 2048).into_level::<Lambda2048>().unwrap();
+        let s = serde_json::to_string(&acc).unwrap();
+
+        // the same format as without a level
+        assert_eq!(
+            s,
+            serde_json::to_string(&acc.clone().into_dynamic()).unwrap()
+        );
+        let typed: Accumulator<Lambda2048> = serde_json::from_str(&s).unwrap();
+        assert_eq!(typed.public_state(), acc.public_state());
+        assert!(serde_json::from_str::<Accumulator>(&s).is_ok());
+        assert!(serde_json::from_str::<Accumulator<Lambda3072>>(&s).is_err());
+
+        // a test sized state
+        let small = Accumulator::setup::<RSAGroup, _>(rng, 256); // insecure, but faster tests
+        let s = serde_json::to_string(&small).unwrap();
+        assert!(serde_json::from_str::<Accumulator>(&s).is_ok());
+        assert!(serde_json::from_str::<Accumulator<Lambda2048>>(&s).is_err());
+
+        // with a declared size that doesn't match the modulus
+        let mut value = serde_json::to_value(&acc).unwrap();
+        value["int_size_bits"] = 3072.into();
+        assert!(serde_json::from_value::<Accumulator<Lambda3072>>(value.clone()).is_err());
+        assert!(serde_json::from_value::<Accumulator<Lambda2048>>(value).is_err());
+    }
+}
@@ -0,0 +1,492 @@
+//! Public parameters shared by many accumulators.
+//!
+//! Accumulators created with `Accumulator::new_with_params` from one `Arc<SetupParams>`
+//! reference a single copy of the modulus and generator. Their proofs are checked against the
+//! same group, and they can be combined with each other, e.g. by `merge` or `move_element`.
+
+use byteorder::{BigEndian, WriteBytesExt};
+use num_bigint::BigUint;
+use num_traits::One;
+#[cfg(all(feature = "prover", feature = "rsa_group"))]
+use rand::{CryptoRng, Rng};
+
+use crate::accumulator::{ParamError, PublicState};
+#[cfg(all(feature = "prover", feature = "rsa_group"))]
+use crate::group::RSAGroup;
+use crate::math::jacobi;
+#[cfg(feature = "params")]
+use crate::params::StandardParams;
+#[cfg(all(feature = "prover", feature = "rsa_group"))]
+use crate::traits::PrimeGroup;
+use crate::transcript::{Blake2bTranscript, Transcript};
+
+const PARAMS_TAG: &[u8] = b"accumulators/setup-params";
+
+/// The group an accumulator works in.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GroupMode {
+    /// The generator as given, in `Z_n^*`, the default.
+    #[default]
+    Full,
+    /// The subgroup of quadratic residues `QR_n`, with the square of the given generator.
+    ///
+    /// `Z_n^*` contains `-1` and other elements of order 2, so a value and its product with
+    /// one of them can't be told apart by their powers to even exponents. `QR_n` has no
+    /// known elements of small order, which the security arguments of the proofs assume.
+    /// Membership in `QR_n` can't be checked without the factorization of `n` though, and
+    /// `-1` has the Jacobi symbol 1 for the usual moduli, so values received from provers are
+    /// still only known to be in `Z_n^*`: verifiers reject values with another Jacobi symbol,
+    /// which are certainly not quadratic residues, see `SetupParams::in_group`. This covers
+    /// states, and the witnesses and proofs checked by the verifiers of `Accumulator`, but not
+    /// those of `PublicState`, which doesn't know the group mode.
+    QuadraticResidues,
+}
+
+impl GroupMode {
+    /// Returns true for `Full`, which is not serialized.
+    pub fn is_full(&self) -> bool {
+        *self == GroupMode::Full
+    }
+}
+
+/// The security parameter, modulus and generator of an accumulator.
+///
+/// Serialized as part of an accumulator, under the keys `int_size_bits`, `g` and `n`, and
+/// `group_mode` if it isn't `GroupMode::Full`.
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SetupParams {
+    /// Size of the modulus in bits.
+    #[cfg_attr(feature = "serde", serde(rename = "int_size_bits"))]
+    pub(crate) lambda: usize,
+    /// Generator.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub(crate) g: BigUint,
+    /// Modulus, of unknown factorization.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub(crate) n: BigUint,
+    /// The group, `g` is already squared for `QuadraticResidues`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "group_mode", skip_serializing_if = "GroupMode::is_full")
+    )]
+    pub(crate) mode: GroupMode,
+}
+
+impl SetupParams {
+    /// Creates the parameters from a modulus `n` of unknown factorization, e.g. from an earlier
+    /// setup, and the generator `g`.
+    ///
+    /// Checks them like `PublicState::from_state`, with `lambda` as the size of `n`.
+    pub fn new(lambda: usize, n: BigUint, g: BigUint) -> Result<Self, ParamError> {
+        Self::new_with_mode(lambda, n, g, GroupMode::Full)
+    }
+
+    /// Creates the parameters like `new`, for the group `mode`.
+    ///
+    /// For `QuadraticResidues`, the generator of the parameters is `g^2 mod n`, which must not
+    /// be 1, e.g. for `g = n - 1`.
+    pub fn new_with_mode(
+        lambda: usize,
+        n: BigUint,
+        g: BigUint,
+        mode: GroupMode,
+    ) -> Result<Self, ParamError> {
+        let PublicState { n, mut g, .. } = PublicState::from_state(lambda, n, g.clone(), g)?;
+        if mode == GroupMode::QuadraticResidues {
+            g = g.modpow(&BigUint::from(2u32), &n);
+            if g.is_one() {
+                return Err(ParamError::InvalidGenerator);
+            }
+        }
+
+        Ok(SetupParams { lambda, n, g, mode })
+    }
+
+    /// Runs a trusted setup in an RSA group, with a modulus of `lambda` bits.
+    ///
+    /// Fails if the modulus is too small, see `RSAGroup::MIN_MODULUS_BITS`.
+    #[cfg(all(feature = "prover", feature = "rsa_group"))]
+    pub fn generate<R: CryptoRng + Rng>(rng: &mut R, lambda: usize) -> Result<Self, ParamError> {
+        let setup = RSAGroup::generate_primes(rng, lambda)
+            .map_err(|_| ParamError::ModulusTooSmall { bits: lambda })?;
+
+        Ok(SetupParams {
+            lambda,
+            n: setup.n.clone(),
+            g: setup.g.clone(),
+            mode: GroupMode::Full,
+        })
+    }
+
+    /// Returns the standard parameters `params`.
+    #[cfg(feature = "params")]
+    pub fn standard(params: StandardParams) -> Self {
+        SetupParams {
+            lambda: params.int_size_bits(),
+            n: params.modulus().clone(),
+            g: params.generator().clone(),
+            mode: GroupMode::Full,
+        }
+    }
+
+    /// Returns the size of the modulus in bits, the security parameter.
+    pub fn lambda(&self) -> usize {
+        self.lambda
+    }
+
+    /// Returns the modulus `n`.
+    pub fn modulus(&self) -> &BigUint {
+        &self.n
+    }
+
+    /// Returns the generator `g`.
+    pub fn generator(&self) -> &BigUint {
+        &self.g
+    }
+
+    /// Returns the group the accumulator works in.
+    pub fn mode(&self) -> GroupMode {
+        self.mode
+    }
+
+    /// Checks that `state`, e.g. received from elsewhere, follows the rules of the group mode.
+    ///
+    /// For `QuadraticResidues`, the generator and the root must have the Jacobi symbol 1.
+    /// There are no further rules for `Full`.
+    pub fn check_state(&self, state: &PublicState) -> Result<(), ParamError> {
+        if self.mode == GroupMode::Full {
+            return Ok(());
+        }
+        if jacobi(&state.g, &state.n) != Some(1) {
+            return Err(ParamError::InvalidGenerator);
+        }
+        if jacobi(&state.root, &state.n) != Some(1) {
+            return Err(ParamError::InvalidState);
+        }
+
+        Ok(())
+    }
+
+    /// Returns `false` if `v`, e.g. a witness received from a prover, is certainly not in the
+    /// group.
+    ///
+    /// For `QuadraticResidues`, these are the values with a Jacobi symbol other than 1, like
+    /// in `check_state`. There are no further rules for `Full`.
+    pub fn in_group(&self, v: &BigUint) -> bool {
+        self.mode == GroupMode::Full || jacobi(v, &self.n) == Some(1)
+    }
+
+    /// Returns a hash identifying the parameters, for comparing them without sending the
+    /// modulus.
+    pub fn params_hash(&self) -> [u8; 32] {
+        let mut lambda = Vec::with_capacity(8);
+        lambda.write_u64::<BigEndian>(self.lambda as u64).unwrap();
+
+        let mut t = Blake2bTranscript::new(PARAMS_TAG);
+        t.append_message(b"lambda", &lambda);
+        t.append_message(b"n", &self.n.to_bytes_be());
+        t.append_message(b"g", &self.g.to_bytes_be());
+        // not appended for `Full`, so that hashes from before the mode stay the same
+        if self.mode == GroupMode::QuadraticResidues {
+            t.append_message(b"mode", b"quadratic-residues");
+        }
+
+        let mut hash = [0u8; 32];
+        t.challenge_bytes(b"params", &mut hash);
+        hash
+    }
+
+    /// Returns the public state of an empty accumulator with these parameters.
+    pub fn public_state(&self) -> PublicState {
+        PublicState {
+            n: self.n.clone(),
+            g: self.g.clone(),
+            root: self.g.clone(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use crate::accumulator::{
+        non_mem_proof_parts, Accumulator, MembershipProof, MembershipWitness, NewState,
+        NonMembershipWitness, OldState,
+    };
+    use crate::math::jacobi;
+    use crate::merge::{ver_merge, MergeError};
+    use crate::primes::RandPrimes;
+    use crate::proofs;
+    use crate::traits::*;
+    use crate::transfer::{move_element, ver_move, MoveError};
+    use num_bigint::traits::ModInverse;
+    use num_bigint::IntoBigUint;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_setup_params() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = SetupParams::generate(rng, int_size_bits).unwrap();
+        assert_eq!(params.lambda(), int_size_bits);
+
+        let (n, g) = (params.modulus().clone(), params.generator().clone());
+        let copy = SetupParams::new(int_size_bits, n.clone(), g.clone()).unwrap();
+        assert_eq!(copy, params);
+        assert_eq!(copy.params_hash(), params.params_hash());
+
+        let other = SetupParams::new(int_size_bits, n.clone(), &g + 1u32).unwrap();
+        assert_ne!(other.params_hash(), params.params_hash());
+
+        assert_eq!(
+            SetupParams::new(int_size_bits, n.clone(), n.clone()),
+            Err(ParamError::InvalidGenerator)
+        );
+        assert_eq!(
+            SetupParams::new(int_size_bits, &n + 1u32, g),
+            Err(ParamError::EvenModulus)
+        );
+        assert_eq!(
+            SetupParams::generate(rng, 32),
+            Err(ParamError::ModulusTooSmall { bits: 32 })
+        );
+
+        let acc = Accumulator::new_with_params(Arc::new(params.clone()));
+        assert_eq!(acc.public_state(), params.public_state());
+        assert_eq!(acc.lambda(), int_size_bits);
+        assert!(acc.is_empty());
+    }
+
+    #[test]
+    fn test_shared_params() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let params = Arc::new(SetupParams::generate(rng, 256).unwrap());
+        let mut tenants = (0..3)
+            .map(|_| Accumulator::new_with_params(params.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(Arc::strong_count(&params), 4);
+
+        let xs = rng.gen_distinct_primes(6, 128);
+        for (acc, xs) in tenants.iter_mut().zip(xs.chunks(2)) {
+            acc.add_batch_no_proof(xs);
+        }
+        assert!(tenants.iter().all(|acc| acc.shares_params(&tenants[0])));
+
+        // aggregated membership across tenants
+        let (a, b) = (&tenants[0], &tenants[1]);
+        let w_x = a.mem_wit_create(&xs[0]);
+        let w_y = b.mem_wit_create(&xs[2]);
+        let w = a.mem_wit_x_shared(b, &w_x, &w_y, &xs[0], &xs[2]).unwrap();
+        assert!(a.ver_mem_x(b.state(), &w, &xs[0], &xs[2]));
+
+        // moved from one tenant to another
+        let (mut from, mut to) = (tenants[1].clone(), tenants[2].clone());
+        let (old_from, old_to) = (from.public_state(), to.public_state());
+        let w = from.mem_wit_create(&xs[3]);
+        let pi = move_element(&mut from, &mut to, &xs[3], &w).unwrap();
+        assert!(ver_move(
+            &old_from,
+            &from.public_state(),
+            &old_to,
+            &to.public_state(),
+            &xs[3],
+            &pi
+        ));
+
+        // merged into one
+        let state2 = tenants[2].public_state();
+        let (merged, pi) = tenants[0].clone().merge(tenants[2].clone()).unwrap();
+        assert!(ver_merge(
+            &tenants[0].public_state(),
+            &state2,
+            &merged.public_state(),
+            &pi
+        ));
+        assert!(Arc::ptr_eq(merged.params(), &params));
+
+        // the same values in another instance are the same parameters
+        let copy = Accumulator::new_with_params(Arc::new((*params).clone()));
+        assert!(copy.shares_params(&tenants[0]));
+
+        // but others are not
+        let mut other = Accumulator::setup::<RSAGroup, _>(rng, 256);
+        other.add(&xs[3]);
+        assert!(!other.shares_params(&tenants[0]));
+        assert_eq!(
+            tenants[0].mem_wit_x_shared(&other, &w_x, &w_y, &xs[0], &xs[3]),
+            None
+        );
+        assert_eq!(
+            tenants[0].clone().merge(other.clone()).err(),
+            Some(MergeError::ParamMismatch)
+        );
+        let w = other.mem_wit_create(&xs[3]);
+        assert_eq!(
+            move_element(&mut other, &mut tenants[0], &xs[3], &w),
+            Err(MoveError::ParamMismatch)
+        );
+        assert!(tenants[0].contains(&xs[0]) && other.contains(&xs[3]));
+    }
+
+    #[test]
+    fn test_quadratic_residues() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+                                 // with a prime `3 mod 4`, for an element of order 2 outside of the subgroup below
+        let setup = std::iter::repeat_with(|| RSAGroup::generate_primes(rng, int_size_bits))
+            .map(Result::unwrap)
+            .find(|s| {
+                let (p, q) = s.primes().unwrap();
+                (p % 4u32 == BigUint::from(3u32)) || (q % 4u32 == BigUint::from(3u32))
+            })
+            .unwrap();
+        let (n, g) = (setup.n.clone(), setup.g.clone());
+        let full = SetupParams::new(int_size_bits, n.clone(), g.clone()).unwrap();
+        let qr = SetupParams::new_with_mode(
+            int_size_bits,
+            n.clone(),
+            g.clone(),
+            GroupMode::QuadraticResidues,
+        )
+        .unwrap();
+        assert_eq!(qr.mode(), GroupMode::QuadraticResidues);
+        assert_eq!(qr.generator(), &g.modpow(&BigUint::from(2u32), &n));
+        assert_ne!(qr.params_hash(), full.params_hash());
+        assert_eq!(
+            SetupParams::new_with_mode(
+                int_size_bits,
+                n.clone(),
+                &n - 1u32,
+                GroupMode::QuadraticResidues
+            ),
+            Err(ParamError::InvalidGenerator)
+        );
+
+        // the whole workflow
+        let xs = rng.gen_distinct_primes(4, 128);
+        let mut acc = Accumulator::new_with_params(Arc::new(qr.clone()));
+        acc.add(&xs[0]);
+        let old = acc.state().clone();
+        let pi = acc.batch_add(&xs[1..3]);
+        assert!(acc.ver_batch_add_transition(
+            &pi,
+            &OldState::from(old),
+            &NewState::from(acc.state().clone()),
+            &xs[1..3]
+        ));
+        let w = acc.mem_wit_create(&xs[1]);
+        assert!(acc.ver_mem(&w, &xs[1]));
+        let pi = acc.mem_wit_create_star(&xs[2]);
+        assert!(acc.ver_mem_star(&xs[2], &pi));
+        let u = acc.non_mem_wit_create(&xs[3]);
+        assert!(acc.ver_non_mem(&u, &xs[3]));
+        acc.del(&xs[0]).unwrap();
+        let w = acc.mem_wit_create(&xs[2]);
+        assert!(acc.ver_mem(&w, &xs[2]));
+        assert!(qr.check_state(&acc.public_state()).is_ok());
+
+        // an element of order 2 outside of the subgroup, `1` mod one prime and `-1` mod the
+        // other, which the witnesses of even elements absorb
+        let (p, q) = setup.primes().unwrap();
+        let t = [(p, q), (q, p)]
+            .iter()
+            .map(|&(p, q)| {
+                let p_inv = p.clone().mod_inverse(q).unwrap().into_biguint().unwrap();
+                (q - 2u32) * p_inv % q * p + 1u32
+            })
+            .find(|t| jacobi(t, &n) == Some(-1))
+            .unwrap();
+        assert!(t.modpow(&BigUint::from(2u32), &n).is_one());
+        let params = acc.group_params();
+
+        // same-state witnesses and proofs, moved out of the subgroup, verify in `Z_n^*` only
+        let y = &xs[0] * 2u32;
+        acc.add(&y);
+        let w = acc.mem_wit_create(&y);
+        let forged = MembershipWitness((&w.0 * &t) % &n);
+        assert!(acc.public_state().ver_mem(&forged, &y));
+        assert!(!acc.ver_mem(&forged, &y));
+        assert_eq!(
+            acc.ver_mem_all(&[(y.clone(), forged.clone()), (y.clone(), w)]),
+            vec![0]
+        );
+        let pi = MembershipProof {
+            pi: proofs::poe_prove(&params, &y, &forged.0, acc.state()),
+            w: forged.0,
+        };
+        assert!(acc.public_state().ver_mem_star(&y, &pi));
+        assert!(!acc.ver_mem_star(&y, &pi));
+        assert_eq!(acc.ver_mem_star_all(&[(y.clone(), pi)]), vec![0]);
+        acc.del(&y).unwrap();
+
+        let x = &xs[3] * 2u32;
+        let u = acc.non_mem_wit_create(&x);
+        let forged = NonMembershipWitness {
+            d: (&u.d * &t) % &n,
+            b: u.b.clone(),
+        };
+        assert!(acc.public_state().ver_non_mem(&forged, &x));
+        assert!(!acc.ver_non_mem(&forged, &x));
+        let v = acc.state().modpow(&u.b.to_biguint().unwrap(), &n);
+        let pi = non_mem_proof_parts(&params, acc.generator(), acc.state(), &x, u.b, forged.d, v)
+            .unwrap();
+        assert!(acc.public_state().ver_non_mem_star(&x, &pi));
+        assert!(!acc.ver_non_mem_star(&x, &pi));
+
+        // states that can't be in the subgroup
+        let outside = (2u32..)
+            .map(BigUint::from)
+            .find(|v| jacobi(v, &n) == Some(-1))
+            .unwrap();
+        let mut state = acc.public_state();
+        state.root = outside.clone();
+        assert_eq!(qr.check_state(&state), Err(ParamError::InvalidState));
+        state.g = outside;
+        assert_eq!(qr.check_state(&state), Err(ParamError::InvalidGenerator));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_group_mode() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let full = SetupParams::generate(rng, 256).unwrap(); // insecure, but faster tests
+        let (n, g) = (full.modulus().clone(), full.generator().clone());
+        let qr =
+            SetupParams::new_with_mode(256, n.clone(), g, GroupMode::QuadraticResidues).unwrap();
+        let x = rng.gen_distinct_primes(1, 128).remove(0);
+        let mut acc = Accumulator::new_with_params(Arc::new(qr));
+        acc.add(&x);
+
+        let mut value = serde_json::to_value(&acc).unwrap();
+        assert_eq!(value["group_mode"], "quadratic_residues");
+        let acc2: Accumulator = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(acc2.params(), acc.params());
+        assert!(acc2.ver_mem(&acc.mem_wit_create(&x), &x));
+
+        // the default is not serialized
+        let plain = serde_json::to_value(Accumulator::new_with_params(Arc::new(full))).unwrap();
+        assert!(plain.get("group_mode").is_none());
+
+        // a root outside of the subgroup
+        let outside = (2u32..)
+            .map(BigUint::from)
+            .find(|v| jacobi(v, &n) == Some(-1))
+            .unwrap();
+        value["root"] = format!(
+            "{:0width$x}",
+            outside,
+            width = value["root"].as_str().unwrap().len()
+        )
+        .into();
+        assert!(serde_json::from_value::<Accumulator>(value.clone()).is_err());
+        value["group_mode"] = "full".into();
+        assert!(serde_json::from_value::<Accumulator>(value).is_ok());
+    }
+}
@@ -0,0 +1,402 @@
+//! An accumulated set, split across several accumulators.
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::BigUint;
+
+use crate::accumulator::{Accumulator, BatchProof, MembershipWitness, NewState, OldState};
+use crate::math;
+use crate::traits::*;
+
+/// Membership witness for an element of a `ShardedAccumulator`, together with the index
+/// of the shard the element lives in.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShardedWitness {
+    pub shard: usize,
+    pub w: MembershipWitness,
+}
+
+/// Proofs for a batch operation on a `ShardedAccumulator`, one per shard.
+/// Shards that were not touched by the operation have no proof.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShardedBatchProof {
+    pub proofs: Vec<Option<BatchProof>>,
+}
+
+/// A set split across `k` accumulators with the same parameters.
+///
+/// Every element lives in the shard selected by its hash, which keeps the accumulated
+/// product of each shard, and so the cost of creating witnesses, `k` times smaller.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct ShardedAccumulator {
+    shards: Vec<Accumulator>,
+}
+
+impl ShardedAccumulator {
+    /// Creates `k` empty shards in `Z_n`, all using the generator `g`.
+    pub fn new(n: BigUint, g: BigUint, k: usize) -> Self {
+        assert!(k > 0, "at least one shard is required");
+
+        ShardedAccumulator {
+            shards: vec![Accumulator::new(n, g); k],
+        }
+    }
+
+    /// Returns the shards.
+    pub fn shards(&self) -> &[Accumulator] {
+        &self.shards
+    }
+
+    /// Returns the current states of all shards.
+    pub fn states(&self) -> Vec<BigUint> {
+        self.shards.iter().map(|acc| acc.state().clone()).collect()
+    }
+
+    /// Returns the index of the shard `x` belongs to.
+    pub fn shard_of(&self, x: &BigUint) -> usize {
+        let h = Blake2b::digest(&x.to_bytes_be());
+
+        (BigEndian::read_u64(&h[..8]) % self.shards.len() as u64) as usize
+    }
+
+    /// Adds `x` to its shard.
+    pub fn add(&mut self, x: &BigUint) {
+        let i = self.shard_of(x);
+        self.shards[i].add(x);
+    }
+
+    /// Deletes `x` from its shard.
+    pub fn del(&mut self, x: &BigUint) -> Option<()> {
+        let i = self.shard_of(x);
+        self.shards[i].del(x)
+    }
+
+    /// Creates a membership witness for `x`, against the state of its shard.
+    pub fn mem_wit_create(&self, x: &BigUint) -> ShardedWitness {
+        let shard = self.shard_of(x);
+
+        ShardedWitness {
+            shard,
+            w: self.shards[shard].mem_wit_create(x),
+        }
+    }
+
+    /// Verifies a membership witness for `x`.
+    /// Witnesses only verify against the shard `x` belongs to.
+    pub fn ver_mem(&self, w: &ShardedWitness, x: &BigUint) -> bool {
+        w.shard == self.shard_of(x) && self.shards[w.shard].ver_mem(&w.w, x)
+    }
+
+    /// Adds all `xs`, with one `batch_add` per shard.
+    pub fn batch_add(&mut self, xs: &[BigUint]) -> ShardedBatchProof {
+        let proofs = self
+            .group(xs)
+            .into_iter()
+            .zip(self.shards.iter_mut())
+            .map(|(xs, acc)| {
+                if xs.is_empty() {
+                    None
+                } else {
+                    Some(acc.batch_add(&xs))
+                }
+            })
+            .collect();
+
+        ShardedBatchProof { proofs }
+    }
+
+    /// Verifies the proofs from `batch_add`, given the states of all shards before the batch was added.
+    pub fn ver_batch_add(
+        &self,
+        pi: &ShardedBatchProof,
+        states: &[BigUint],
+        xs: &[BigUint],
+    ) -> bool {
+        self.ver_batch(pi, states, xs, |acc, w, root, xs| {
+            let (old, new) = (OldState(root.clone()), NewState(acc.state().clone()));
+            acc.ver_batch_add_transition(w, &old, &new, xs)
+        })
+    }
+
+    /// Deletes all elements in `pairs`, with one `batch_del` per shard.
+    ///
+    /// Returns `None` and leaves all shards unchanged, if any of the witnesses is invalid, or
+    /// the elements of a shard are not pairwise coprime, e.g. because one is listed twice.
+    pub fn batch_del(&mut self, pairs: &[(BigUint, ShardedWitness)]) -> Option<ShardedBatchProof> {
+        if pairs.is_empty() || pairs.iter().any(|(x, w)| !self.ver_mem(w, x)) {
+            return None;
+        }
+
+        let mut grouped = vec![Vec::new(); self.shards.len()];
+        for (x, w) in pairs {
+            grouped[w.shard].push((x.clone(), w.w.clone()));
+        }
+
+        for pairs in &grouped {
+            let xs = pairs.iter().map(|(x, _)| x.clone()).collect::<Vec<_>>();
+            if math::common_factor(&xs).is_some() {
+                return None;
+            }
+        }
+
+        // delete from copies of the touched shards, so a failing shard leaves the others as well
+        let mut updated = Vec::new();
+        for (i, pairs) in grouped.iter().enumerate() {
+            if pairs.is_empty() {
+                continue;
+            }
+
+            let mut acc = self.shards[i].clone();
+            let proof = acc.batch_del(pairs)?;
+            updated.push((i, acc, proof));
+        }
+
+        let mut proofs = vec![None; self.shards.len()];
+        for (i, acc, proof) in updated {
+            self.shards[i] = acc;
+            proofs[i] = Some(proof);
+        }
+
+        Some(ShardedBatchProof { proofs })
+    }
+
+    /// Verifies the proofs from `batch_del`, given the states of all shards before the batch was deleted.
+    pub fn ver_batch_del(
+        &self,
+        pi: &ShardedBatchProof,
+        states: &[BigUint],
+        xs: &[BigUint],
+    ) -> bool {
+        self.ver_batch(pi, states, xs, |acc, w, root, xs| {
+            let (old, new) = (OldState(root.clone()), NewState(acc.state().clone()));
+            acc.ver_batch_del_transition(w, &old, &new, xs)
+        })
+    }
+
+    /// Aggregates the witnesses of two elements from different shards into a single witness,
+    /// using `mem_wit_x`. Returns `None` if both live in the same shard, use `agg_mem_wit` of
+    /// that shard instead.
+    pub fn mem_wit_x(
+        &self,
+        w_x: &ShardedWitness,
+        w_y: &ShardedWitness,
+        x: &BigUint,
+        y: &BigUint,
+    ) -> Option<MembershipWitness> {
+        if w_x.shard == w_y.shard {
+            return None;
+        }
+
+        let other = self.shards[w_y.shard].state();
+        Some(self.shards[w_x.shard].mem_wit_x(other, &w_x.w, &w_y.w, x, y))
+    }
+
+    /// Verifies a witness from `mem_wit_x` for the elements `x` and `y`.
+    pub fn ver_mem_x(&self, pi: &MembershipWitness, x: &BigUint, y: &BigUint) -> bool {
+        let (i, j) = (self.shard_of(x), self.shard_of(y));
+        if i == j {
+            return false;
+        }
+
+        self.shards[i].ver_mem_x(self.shards[j].state(), pi, x, y)
+    }
+
+    /// Splits `xs` into one list per shard.
+    fn group(&self, xs: &[BigUint]) -> Vec<Vec<BigUint>> {
+        let mut grouped = vec![Vec::new(); self.shards.len()];
+        for x in xs {
+            grouped[self.shard_of(x)].push(x.clone());
+        }
+
+        grouped
+    }
+
+    fn ver_batch<F>(
+        &self,
+        pi: &ShardedBatchProof,
+        states: &[BigUint],
+        xs: &[BigUint],
+        ver: F,
+    ) -> bool
+    where
+        F: Fn(&Accumulator, &BatchProof, &BigUint, &[BigUint]) -> bool,
+    {
+        if pi.proofs.len() != self.shards.len() || states.len() != self.shards.len() {
+            return false;
+        }
+
+        self.group(xs)
+            .iter()
+            .zip(&self.shards)
+            .zip(states.iter().zip(&pi.proofs))
+            .all(|((xs, acc), (root, w))| match w {
+                Some(w) => !xs.is_empty() && ver(acc, w, root, xs),
+                // untouched shards keep their state
+                None => xs.is_empty() && acc.state() == root,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use crate::primes::RandPrimes;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn setup(rng: &mut ChaChaRng, k: usize) -> ShardedAccumulator {
+        let int_size_bits = 256; // insecure, but faster tests
+        let acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        ShardedAccumulator::new(acc.modulus().clone(), acc.generator().clone(), k)
+    }
+
+    #[test]
+    fn test_routing() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let mut acc = setup(rng, 4);
+        let other = ShardedAccumulator::new(
+            acc.shards()[0].modulus().clone(),
+            acc.shards()[0].generator().clone(),
+            4,
+        );
+
+        let xs = rng.gen_distinct_primes(40, 128);
+        for x in &xs {
+            assert_eq!(acc.shard_of(x), other.shard_of(x));
+            assert_eq!(acc.shard_of(x), acc.shard_of(&x.clone()));
+            acc.add(x);
+        }
+
+        // all shards are used, and only the ones with elements changed
+        let g = acc.shards()[0].generator();
+        for (i, state) in acc.states().iter().enumerate() {
+            assert!(xs.iter().any(|x| acc.shard_of(x) == i));
+            assert_ne!(state, g);
+        }
+    }
+
+    #[test]
+    fn test_mem_wit() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let mut acc = setup(rng, 3);
+
+        let xs = rng.gen_distinct_primes(10, 128);
+        for x in &xs {
+            acc.add(x);
+        }
+
+        for x in &xs {
+            let w = acc.mem_wit_create(x);
+            assert_eq!(w.shard, acc.shard_of(x));
+            assert!(acc.ver_mem(&w, x));
+
+            // never verifies against any other shard
+            for shard in (0..3).filter(|i| *i != w.shard) {
+                assert!(!acc.shards()[shard].ver_mem(&w.w, x));
+                let w = ShardedWitness {
+                    shard,
+                    w: w.w.clone(),
+                };
+                assert!(!acc.ver_mem(&w, x));
+            }
+        }
+
+        acc.del(&xs[0]).unwrap();
+        assert!(!acc.ver_mem(&acc.mem_wit_create(&xs[1]), &xs[0]));
+        assert!(acc.del(&xs[0]).is_none());
+    }
+
+    #[test]
+    fn test_batch() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let mut acc = setup(rng, 4);
+
+        let xs = rng.gen_distinct_primes(10, 128);
+        let states = acc.states();
+        let pi = acc.batch_add(&xs);
+        assert!(acc.ver_batch_add(&pi, &states, &xs));
+        assert!(!acc.ver_batch_add(&pi, &states, &xs[1..]));
+        assert!(!acc.ver_batch_add(&pi, &acc.states(), &xs));
+
+        // elements from a single shard only touch that shard
+        let shard = acc.shard_of(&xs[0]);
+        let ys = (0..)
+            .map(|_| rng.gen_prime(128))
+            .filter(|y| acc.shard_of(y) == shard)
+            .take(2)
+            .collect::<Vec<_>>();
+        let states = acc.states();
+        let pi = acc.batch_add(&ys);
+        assert_eq!(pi.proofs.iter().filter(|p| p.is_some()).count(), 1);
+        assert!(acc.ver_batch_add(&pi, &states, &ys));
+
+        let pairs = xs[..5]
+            .iter()
+            .map(|x| (x.clone(), acc.mem_wit_create(x)))
+            .collect::<Vec<_>>();
+        let states = acc.states();
+        let pi = acc.batch_del(&pairs).unwrap();
+        assert!(acc.ver_batch_del(&pi, &states, &xs[..5]));
+        for x in &xs[..5] {
+            assert!(!acc.ver_mem(&acc.mem_wit_create(&xs[5]), x));
+        }
+        for x in &xs[5..] {
+            assert!(acc.ver_mem(&acc.mem_wit_create(x), x));
+        }
+
+        // invalid witnesses leave everything unchanged
+        let states = acc.states();
+        let mut pairs = vec![(xs[5].clone(), acc.mem_wit_create(&xs[5]))];
+        pairs.push((xs[0].clone(), pairs[0].1.clone()));
+        assert!(acc.batch_del(&pairs).is_none());
+        assert_eq!(acc.states(), states);
+
+        // a failing shard leaves the ones before it unchanged as well
+        let x = xs[5..].iter().min_by_key(|x| acc.shard_of(x)).unwrap();
+        let y = xs[5..].iter().max_by_key(|y| acc.shard_of(y)).unwrap();
+        assert!(acc.shard_of(x) < acc.shard_of(y));
+        let (w_x, w_y) = (acc.mem_wit_create(x), acc.mem_wit_create(y));
+        let pairs = [(x.clone(), w_x), (y.clone(), w_y.clone()), (y.clone(), w_y)];
+        assert!(acc.batch_del(&pairs).is_none());
+        assert_eq!(acc.states(), states);
+    }
+
+    #[test]
+    fn test_mem_wit_x() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let mut acc = setup(rng, 2);
+
+        let xs = rng.gen_distinct_primes(10, 128);
+        for x in &xs {
+            acc.add(x);
+        }
+
+        let x = &xs[0];
+        let y = xs
+            .iter()
+            .find(|y| acc.shard_of(y) != acc.shard_of(x))
+            .unwrap();
+        let z = xs[1..]
+            .iter()
+            .find(|z| acc.shard_of(z) == acc.shard_of(x))
+            .unwrap();
+
+        let (w_x, w_y, w_z) = (
+            acc.mem_wit_create(x),
+            acc.mem_wit_create(y),
+            acc.mem_wit_create(z),
+        );
+        let pi = acc.mem_wit_x(&w_x, &w_y, x, y).unwrap();
+        assert!(acc.ver_mem_x(&pi, x, y));
+        assert!(!acc.ver_mem_x(&pi, x, z));
+
+        assert!(acc.mem_wit_x(&w_x, &w_z, x, z).is_none());
+    }
+}
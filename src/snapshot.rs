@@ -0,0 +1,320 @@
+//! Merkle snapshots of an accumulated set, for auditors who need to enumerate the elements.
+//!
+//! The state of an RSA accumulator commits to its set, but can't be opened to list it.
+//! A `MerkleSnapshot`, from `FactoredAccumulator::export_merkle_snapshot`, adds the root of a
+//! Merkle tree over the sorted elements, and a digest binding it to the state, for signing.
+//! An auditor handed the full list checks it with `verify_snapshot`, which recomputes both the
+//! Merkle root and the accumulator state from scratch, and can spot check single elements
+//! with `MerkleSnapshot::ver_inclusion`.
+//!
+//! The leaves are the hashes of the elements, in ascending order and with their multiplicity.
+//! A node without a sibling is promoted to the next level unchanged. Leaves and inner nodes
+//! are hashed with distinct prefixes, so that a node can't pass for a leaf.
+
+use std::fmt;
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, WriteBytesExt};
+use num_bigint::BigUint;
+
+use crate::accumulator::PublicState;
+use crate::math::product;
+use crate::transcript::{Blake2bTranscript, Transcript};
+
+const SNAPSHOT_TAG: &[u8] = b"accumulators/merkle-snapshot";
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+const EMPTY_PREFIX: u8 = 2;
+
+/// A hash in the Merkle tree.
+pub type MerkleHash = [u8; 32];
+
+/// The Merkle root of the elements of an accumulator, bound to its state.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MerkleSnapshot {
+    /// The state of the accumulator.
+    pub state: PublicState,
+    /// The number of elements, counting repeated ones repeatedly.
+    pub len: u64,
+    /// The root of the Merkle tree over the sorted elements.
+    pub merkle_root: MerkleHash,
+    /// `snapshot_digest` of the above, for signing.
+    pub digest: [u8; 32],
+}
+
+/// Proof that an element is a leaf of the tree of a `MerkleSnapshot`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MerkleProof {
+    /// Position of the element in the sorted list.
+    pub index: u64,
+    /// The siblings on the path to the root, from the leaf up, skipping promoted nodes.
+    pub siblings: Vec<MerkleHash>,
+}
+
+/// Why `verify_snapshot` rejected a list of elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The list has another number of elements than the snapshot.
+    Length { expected: u64, actual: u64 },
+    /// The Merkle root of the list differs.
+    MerkleRoot,
+    /// The accumulator state of the list differs.
+    State,
+    /// The digest does not match the snapshot.
+    Digest,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Length { expected, actual } => {
+                write!(f, "expected {} elements, got {}", expected, actual)
+            }
+            SnapshotError::MerkleRoot => write!(f, "the Merkle root does not match the elements"),
+            SnapshotError::State => write!(f, "the state does not match the elements"),
+            SnapshotError::Digest => write!(f, "the digest does not match the snapshot"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl MerkleSnapshot {
+    /// Creates the snapshot of `state`, for its elements `sorted` in ascending order.
+    #[cfg(feature = "prover")]
+    pub(crate) fn new(state: PublicState, sorted: &[BigUint]) -> Self {
+        let len = sorted.len() as u64;
+        let merkle_root = merkle_root(sorted);
+        let digest = snapshot_digest(&state, len, &merkle_root);
+
+        MerkleSnapshot {
+            state,
+            len,
+            merkle_root,
+            digest,
+        }
+    }
+
+    /// Verifies that `x` is an element of the snapshot, given the proof from
+    /// `FactoredAccumulator::merkle_proof`.
+    pub fn ver_inclusion(&self, x: &BigUint, proof: &MerkleProof) -> bool {
+        if proof.index >= self.len {
+            return false;
+        }
+
+        let mut siblings = proof.siblings.iter();
+        let (mut i, mut width) = (proof.index, self.len);
+        let mut h = leaf_hash(x);
+        while width > 1 {
+            let sibling = i ^ 1;
+            if sibling < width {
+                let s = match siblings.next() {
+                    Some(s) => s,
+                    None => return false,
+                };
+                h = if i & 1 == 0 {
+                    node_hash(&h, s)
+                } else {
+                    node_hash(s, &h)
+                };
+            }
+            i /= 2;
+            width = width.div_ceil(2);
+        }
+
+        siblings.next().is_none() && h == self.merkle_root
+    }
+}
+
+/// Returns the digest binding the Merkle root `merkle_root` of `len` elements to the
+/// accumulator state `state`.
+pub fn snapshot_digest(state: &PublicState, len: u64, merkle_root: &MerkleHash) -> [u8; 32] {
+    let mut len_bytes = Vec::with_capacity(8);
+    len_bytes.write_u64::<BigEndian>(len).unwrap();
+
+    let mut t = Blake2bTranscript::new(SNAPSHOT_TAG);
+    t.append_message(b"n", &state.n.to_bytes_be());
+    t.append_message(b"g", &state.g.to_bytes_be());
+    t.append_message(b"root", &state.root.to_bytes_be());
+    t.append_message(b"len", &len_bytes);
+    t.append_message(b"merkle_root", merkle_root);
+
+    let mut digest = [0u8; 32];
+    t.challenge_bytes(b"digest", &mut digest);
+    digest
+}
+
+/// Verifies that `elements`, in any order, are exactly the elements of `snapshot`.
+///
+/// Recomputes the Merkle root and the accumulator state from all of them, one exponentiation
+/// by their product, so this is meant for offline audits.
+pub fn verify_snapshot(
+    snapshot: &MerkleSnapshot,
+    elements: &[BigUint],
+) -> Result<(), SnapshotError> {
+    let _span = span!("verify_snapshot", elements = elements.len());
+    if snapshot.digest != snapshot_digest(&snapshot.state, snapshot.len, &snapshot.merkle_root) {
+        return Err(SnapshotError::Digest);
+    }
+    if elements.len() as u64 != snapshot.len {
+        return Err(SnapshotError::Length {
+            expected: snapshot.len,
+            actual: elements.len() as u64,
+        });
+    }
+
+    let mut sorted = elements.to_vec();
+    sorted.sort();
+    if merkle_root(&sorted) != snapshot.merkle_root {
+        return Err(SnapshotError::MerkleRoot);
+    }
+
+    let state = &snapshot.state;
+    if state.g.modpow(&product(&sorted), &state.n) != state.root {
+        return Err(SnapshotError::State);
+    }
+
+    Ok(())
+}
+
+/// Returns the proof for the element at `index` of `sorted`.
+#[cfg(feature = "prover")]
+pub(crate) fn merkle_proof(sorted: &[BigUint], index: usize) -> MerkleProof {
+    let levels = levels(sorted);
+    let mut siblings = Vec::with_capacity(levels.len());
+    let mut i = index;
+    for level in &levels[..levels.len() - 1] {
+        if let Some(s) = level.get(i ^ 1) {
+            siblings.push(*s);
+        }
+        i /= 2;
+    }
+
+    MerkleProof {
+        index: index as u64,
+        siblings,
+    }
+}
+
+fn merkle_root(sorted: &[BigUint]) -> MerkleHash {
+    if sorted.is_empty() {
+        return hash(&[EMPTY_PREFIX]);
+    }
+
+    levels(sorted).pop().unwrap()[0]
+}
+
+/// All levels of the tree over the non-empty `sorted`, from the leaves to the root.
+fn levels(sorted: &[BigUint]) -> Vec<Vec<MerkleHash>> {
+    let mut levels = vec![sorted.iter().map(leaf_hash).collect::<Vec<_>>()];
+    while levels[levels.len() - 1].len() > 1 {
+        let next = levels[levels.len() - 1]
+            .chunks(2)
+            .map(|pair| match pair {
+                [l, r] => node_hash(l, r),
+                [h] => *h,
+                _ => unreachable!(),
+            })
+            .collect();
+        levels.push(next);
+    }
+
+    levels
+}
+
+fn leaf_hash(x: &BigUint) -> MerkleHash {
+    let mut to_hash = vec![LEAF_PREFIX];
+    to_hash.extend(&x.to_bytes_be());
+
+    hash(&to_hash)
+}
+
+fn node_hash(l: &MerkleHash, r: &MerkleHash) -> MerkleHash {
+    let mut to_hash = vec![NODE_PREFIX];
+    to_hash.extend(l);
+    to_hash.extend(r);
+
+    hash(&to_hash)
+}
+
+/// Blake2b, truncated to 32 bytes.
+fn hash(bytes: &[u8]) -> MerkleHash {
+    let mut h = [0u8; 32];
+    h.copy_from_slice(&Blake2b::digest(bytes)[..32]);
+    h
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    use crate::factored::FactoredAccumulator;
+    use crate::group::RSAGroup;
+    use crate::primes::RandPrimes;
+    use crate::traits::*;
+
+    #[test]
+    fn test_merkle_snapshot() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = FactoredAccumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let empty = acc.export_merkle_snapshot();
+        assert_eq!(verify_snapshot(&empty, &[]), Ok(()));
+
+        let xs = rng.gen_distinct_primes(8, 128);
+        acc.batch_add(&xs[..7]);
+        acc.add(&xs[3]);
+        let snapshot = acc.export_merkle_snapshot();
+        assert_eq!(snapshot.len, 8);
+        assert_eq!(snapshot.state, acc.public_state());
+
+        // the elements in any order, with the repeated one
+        let mut list = xs[..7].to_vec();
+        list.push(xs[3].clone());
+        list.reverse();
+        assert_eq!(verify_snapshot(&snapshot, &list), Ok(()));
+
+        // one element removed
+        let mut short = list.clone();
+        short.remove(2);
+        assert!(verify_snapshot(&snapshot, &short).is_err());
+        // or replaced
+        let mut other = list.clone();
+        other[2] = xs[7].clone();
+        assert_eq!(
+            verify_snapshot(&snapshot, &other),
+            Err(SnapshotError::MerkleRoot)
+        );
+        // a snapshot with the right root, but for another state
+        let mut forged = snapshot.clone();
+        forged.state.root = empty.state.root.clone();
+        forged.digest = snapshot_digest(&forged.state, forged.len, &forged.merkle_root);
+        assert_eq!(verify_snapshot(&forged, &list), Err(SnapshotError::State));
+        forged.digest = snapshot.digest;
+        assert_eq!(verify_snapshot(&forged, &list), Err(SnapshotError::Digest));
+
+        // spot checks, for every size of tree
+        for len in 1..=7 {
+            let mut acc = FactoredAccumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+            acc.batch_add(&xs[..len]);
+            let snapshot = acc.export_merkle_snapshot();
+            for x in &xs[..len] {
+                let proof = acc.merkle_proof(x).unwrap();
+                assert!(snapshot.ver_inclusion(x, &proof));
+                assert!(!snapshot.ver_inclusion(&xs[7], &proof));
+            }
+            assert!(acc.merkle_proof(&xs[7]).is_none());
+        }
+        let proof = acc.merkle_proof(&xs[0]).unwrap();
+        let mut wrong = proof.clone();
+        wrong.index += 1;
+        assert!(!snapshot.ver_inclusion(&xs[0], &wrong));
+        let mut wrong = proof;
+        wrong.siblings.pop();
+        assert!(!snapshot.ver_inclusion(&xs[0], &wrong));
+    }
+}
@@ -0,0 +1,497 @@
+//! Stateless clients, for UTXO-style commitments.
+//!
+//! The set of unspent outputs is kept in an accumulator, every output is identified by its
+//! outpoint, mapped to a prime with `outpoint_element`. Each block deletes the outputs it
+//! spends and adds the outputs it creates, proven with a single MultiSwap `BlockProof`.
+//! Nodes only need the accumulator state to check blocks, and wallets keep the witnesses of
+//! their own coins up to date from the public block data alone.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use blake2::Blake2b;
+use num_bigint::BigUint;
+use num_traits::One;
+
+#[cfg(feature = "prover")]
+use crate::accumulator::Accumulator;
+use crate::accumulator::{batch_del_root, BatchProof, MembershipWitness, PublicState};
+use crate::encoding::hex::to_hex;
+use crate::hash::hash_prime;
+use crate::math::{product, shamir_trick};
+use crate::proofs;
+
+const OUTPOINT_LABEL: &[u8] = b"accumulators/stateless/outpoint";
+
+/// An output of a transaction, the 32 byte transaction id followed by the 4 byte output index.
+pub type Outpoint = [u8; 36];
+
+/// The changes a block makes to the set of unspent outputs.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BlockDelta {
+    /// Outputs created by the block.
+    pub created: Vec<Outpoint>,
+    /// Outputs spent by the block, with their membership witnesses for the state before it.
+    pub spent: Vec<(Outpoint, MembershipWitness)>,
+}
+
+/// The products of the elements spent and created by a block, as needed by `verify_block`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeltaDigest {
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub spent: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub created: BigUint,
+}
+
+/// MultiSwap proof for a block, deleting the spent outputs and then adding the created ones.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockProof {
+    /// The state after the deletions, before the additions.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub intermediate: BigUint,
+    /// NI-PoE for the deletions, `intermediate^spent = prev`.
+    pub del: BatchProof,
+    /// NI-PoE for the additions, `intermediate^created = new`.
+    pub add: BatchProof,
+}
+
+/// Errors of `apply_block` and `Wallet::advance`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockError {
+    /// The witness of a spent output does not verify against the state before the block.
+    InvalidWitness(Outpoint),
+    /// The outpoint appears more than once in the block.
+    Duplicate(Outpoint),
+    /// The created output is already unspent.
+    AlreadyUnspent(Outpoint),
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlockError::InvalidWitness(o) => {
+                write!(f, "invalid witness for spent output {}", to_hex(o))
+            }
+            BlockError::Duplicate(o) => write!(f, "duplicate outpoint {}", to_hex(o)),
+            BlockError::AlreadyUnspent(o) => {
+                write!(f, "created output {} already exists", to_hex(o))
+            }
+        }
+    }
+}
+
+impl std::error::Error for BlockError {}
+
+/// Returns the prime representing `outpoint` in the accumulator.
+pub fn outpoint_element(outpoint: &Outpoint) -> BigUint {
+    let mut to_hash = OUTPOINT_LABEL.to_vec();
+    to_hash.extend_from_slice(outpoint);
+
+    hash_prime::<_, Blake2b>(&to_hash).expect("no prime found")
+}
+
+impl BlockDelta {
+    /// Returns the digest of the block, for `verify_block`.
+    pub fn digest(&self) -> DeltaDigest {
+        DeltaDigest {
+            spent: product(&self.spent_elements()),
+            created: product(&self.created_elements()),
+        }
+    }
+
+    fn spent_elements(&self) -> Vec<BigUint> {
+        self.spent
+            .iter()
+            .map(|(o, _)| outpoint_element(o))
+            .collect()
+    }
+
+    fn created_elements(&self) -> Vec<BigUint> {
+        self.created.iter().map(outpoint_element).collect()
+    }
+
+    /// Checks that no outpoint appears twice, and that all spent outputs are members of `state`.
+    /// Returns the spent elements with their witnesses.
+    fn check_spent(
+        &self,
+        state: &PublicState,
+    ) -> Result<Vec<(BigUint, MembershipWitness)>, BlockError> {
+        let mut seen = HashSet::new();
+        let outpoints = self.spent.iter().map(|(o, _)| o).chain(&self.created);
+        for o in outpoints {
+            if !seen.insert(o) {
+                return Err(BlockError::Duplicate(*o));
+            }
+        }
+
+        self.spent
+            .iter()
+            .map(|(o, w)| {
+                let x = outpoint_element(o);
+                if state.ver_mem(w, &x) {
+                    Ok((x, w.clone()))
+                } else {
+                    Err(BlockError::InvalidWitness(*o))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Applies the block to the set of unspent outputs in `acc`.
+///
+/// All checks happen before the accumulator is modified, so on error it is left unchanged.
+///
+/// Panics if the created outputs exceed the capacity limits of `acc`.
+#[cfg(feature = "prover")]
+pub fn apply_block(acc: &mut Accumulator, delta: &BlockDelta) -> Result<BlockProof, BlockError> {
+    let pairs = delta.check_spent(&acc.public_state())?;
+    let created = delta.created_elements();
+    for (o, x) in delta.created.iter().zip(&created) {
+        if acc.contains(x) {
+            return Err(BlockError::AlreadyUnspent(*o));
+        }
+    }
+
+    Ok(acc
+        .multi_swap(&pairs, &created)
+        .expect("witnesses and created outputs are checked"))
+}
+
+/// Verifies that the block with `delta_digest` transforms `prev_state` into `new_state`.
+pub fn verify_block(
+    prev_state: &PublicState,
+    new_state: &PublicState,
+    delta_digest: &DeltaDigest,
+    proof: &BlockProof,
+) -> bool {
+    if prev_state.n != new_state.n || prev_state.g != new_state.g {
+        return false;
+    }
+    let n = &new_state.n;
+
+    proofs::ni_poe_verify(
+        &delta_digest.spent,
+        &proof.intermediate,
+        &prev_state.root,
+        &proof.del.0,
+        n,
+    ) && proofs::ni_poe_verify(
+        &delta_digest.created,
+        &proof.intermediate,
+        &new_state.root,
+        &proof.add.0,
+        n,
+    )
+}
+
+/// A wallet, tracking the witnesses of its own unspent outputs.
+///
+/// The wallet follows the chain with `advance`, one block at a time, and never needs more than
+/// the data of the block itself.
+#[derive(Debug, Clone)]
+pub struct Wallet {
+    state: PublicState,
+    /// The unspent outputs of the wallet, with their elements and witnesses.
+    coins: HashMap<Outpoint, (BigUint, MembershipWitness)>,
+    /// Outputs the wallet expects to receive in a later block.
+    watched: HashSet<Outpoint>,
+}
+
+impl Wallet {
+    /// Creates an empty wallet, following the chain from `state` on.
+    pub fn new(state: PublicState) -> Self {
+        Wallet {
+            state,
+            coins: HashMap::new(),
+            watched: HashSet::new(),
+        }
+    }
+
+    /// Returns the state the wallet is synced to.
+    pub fn state(&self) -> &PublicState {
+        &self.state
+    }
+
+    /// Marks `outpoint` as belonging to the wallet, it is tracked once a block creates it.
+    pub fn watch(&mut self, outpoint: Outpoint) {
+        self.watched.insert(outpoint);
+    }
+
+    /// Adds an existing unspent output, with its witness for the current state.
+    ///
+    /// Returns `false`, without adding it, if the witness does not verify.
+    pub fn insert(&mut self, outpoint: Outpoint, w: MembershipWitness) -> bool {
+        let x = outpoint_element(&outpoint);
+        if !self.state.ver_mem(&w, &x) {
+            return false;
+        }
+
+        self.watched.remove(&outpoint);
+        self.coins.insert(outpoint, (x, w));
+        true
+    }
+
+    /// Returns the witness of the output, for the current state.
+    pub fn witness(&self, outpoint: &Outpoint) -> Option<&MembershipWitness> {
+        self.coins.get(outpoint).map(|(_, w)| w)
+    }
+
+    /// Returns the entry to spend the output in the next block, see `BlockDelta::spent`.
+    pub fn spend(&self, outpoint: &Outpoint) -> Option<(Outpoint, MembershipWitness)> {
+        self.witness(outpoint).map(|w| (*outpoint, w.clone()))
+    }
+
+    /// Returns the unspent outputs of the wallet, in no particular order.
+    pub fn coins(&self) -> impl Iterator<Item = &Outpoint> {
+        self.coins.keys()
+    }
+
+    /// Returns the number of unspent outputs of the wallet.
+    pub fn len(&self) -> usize {
+        self.coins.len()
+    }
+
+    /// Returns `true` if the wallet has no unspent outputs.
+    pub fn is_empty(&self) -> bool {
+        self.coins.is_empty()
+    }
+
+    /// Applies the next block to the wallet, refreshing all witnesses.
+    ///
+    /// Spent outputs of the wallet are dropped, and watched outputs the block creates are
+    /// added. On error the wallet is left unchanged.
+    pub fn advance(&mut self, delta: &BlockDelta) -> Result<(), BlockError> {
+        let pairs = delta.check_spent(&self.state)?;
+        let n = &self.state.n;
+
+        // the witnesses are all for the current state, so this can't fail
+        let (intermediate, spent) =
+            batch_del_root(n, &pairs).unwrap_or_else(|| (self.state.root.clone(), BigUint::one()));
+
+        for (o, _) in &delta.spent {
+            self.coins.remove(o);
+        }
+        if !spent.is_one() {
+            for (x, w) in self.coins.values_mut() {
+                // w^x = prev and intermediate^spent = prev, so the combined root is the x-th
+                // root of intermediate
+                w.0 = shamir_trick(&w.0, &intermediate, x, &spent, n)
+                    .expect("unspent outputs are coprime to the spent ones");
+            }
+        }
+
+        let created = delta.created_elements();
+        let created_product = product(&created);
+        for (_, w) in self.coins.values_mut() {
+            w.0 = w.0.modpow(&created_product, n);
+        }
+        for (o, x) in delta.created.iter().zip(created) {
+            if self.watched.remove(o) {
+                let w = intermediate.modpow(&(&created_product / &x), n);
+                self.coins.insert(*o, (x, MembershipWitness(w)));
+            }
+        }
+
+        self.state.root = intermediate.modpow(&created_product, n);
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn outpoint(tx: u8, vout: u32) -> Outpoint {
+        let mut o = [tx; 36];
+        o[32..].copy_from_slice(&vout.to_le_bytes());
+        o
+    }
+
+    fn setup() -> Accumulator {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        Accumulator::setup::<RSAGroup, _>(rng, int_size_bits)
+    }
+
+    /// Applies the block to the chain, checks the proof, and advances all wallets.
+    fn mine(acc: &mut Accumulator, wallets: &mut [&mut Wallet], delta: &BlockDelta) {
+        let prev = acc.public_state();
+        let proof = apply_block(acc, delta).unwrap();
+        let new = acc.public_state();
+
+        assert!(verify_block(&prev, &new, &delta.digest(), &proof));
+        if new != prev {
+            assert!(!verify_block(&new, &prev, &delta.digest(), &proof));
+        }
+
+        for wallet in wallets.iter_mut() {
+            wallet.advance(delta).unwrap();
+            assert_eq!(wallet.state(), &new);
+            for o in wallet.coins() {
+                assert!(acc.ver_mem(wallet.witness(o).unwrap(), &outpoint_element(o)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_blocks_with_wallets() {
+        let mut acc = setup();
+        let mut alice = Wallet::new(acc.public_state());
+        let mut bob = Wallet::new(acc.public_state());
+
+        // genesis, paying alice three coins and bob one
+        for o in &[outpoint(1, 0), outpoint(1, 1), outpoint(1, 2)] {
+            alice.watch(*o);
+        }
+        bob.watch(outpoint(1, 3));
+        let genesis = BlockDelta {
+            created: (0..4).map(|i| outpoint(1, i)).collect(),
+            spent: Vec::new(),
+        };
+        mine(&mut acc, &mut [&mut alice, &mut bob], &genesis);
+        assert_eq!(alice.len(), 3);
+        assert_eq!(bob.len(), 1);
+
+        // alice pays bob, with change, and an unrelated output is created
+        bob.watch(outpoint(2, 0));
+        alice.watch(outpoint(2, 1));
+        let block = BlockDelta {
+            created: vec![outpoint(2, 0), outpoint(2, 1), outpoint(3, 0)],
+            spent: vec![alice.spend(&outpoint(1, 0)).unwrap()],
+        };
+        mine(&mut acc, &mut [&mut alice, &mut bob], &block);
+        assert_eq!(alice.len(), 3);
+        assert_eq!(bob.len(), 2);
+        assert!(alice.witness(&outpoint(1, 0)).is_none());
+
+        // both spend in the same block
+        let block = BlockDelta {
+            created: vec![outpoint(4, 0)],
+            spent: vec![
+                alice.spend(&outpoint(1, 1)).unwrap(),
+                alice.spend(&outpoint(2, 1)).unwrap(),
+                bob.spend(&outpoint(1, 3)).unwrap(),
+            ],
+        };
+        mine(&mut acc, &mut [&mut alice, &mut bob], &block);
+        assert_eq!(alice.len(), 1);
+        assert_eq!(bob.len(), 1);
+
+        // an empty block changes nothing
+        let state = acc.public_state();
+        mine(
+            &mut acc,
+            &mut [&mut alice, &mut bob],
+            &BlockDelta::default(),
+        );
+        assert_eq!(acc.public_state(), state);
+
+        // a late wallet joins with a witness from a full node
+        let mut carol = Wallet::new(acc.public_state());
+        assert!(carol.insert(
+            outpoint(3, 0),
+            acc.mem_wit_create(&outpoint_element(&outpoint(3, 0)))
+        ));
+        assert!(!carol.insert(
+            outpoint(4, 0),
+            alice.witness(&outpoint(1, 2)).unwrap().clone()
+        ));
+
+        let block = BlockDelta {
+            created: vec![outpoint(5, 0)],
+            spent: vec![
+                carol.spend(&outpoint(3, 0)).unwrap(),
+                bob.spend(&outpoint(2, 0)).unwrap(),
+            ],
+        };
+        mine(&mut acc, &mut [&mut alice, &mut bob, &mut carol], &block);
+        assert!(carol.is_empty());
+        assert!(bob.is_empty());
+        assert_eq!(alice.coins().collect::<Vec<_>>(), vec![&outpoint(1, 2)]);
+    }
+
+    #[test]
+    fn test_invalid_blocks() {
+        let mut acc = setup();
+        let mut wallet = Wallet::new(acc.public_state());
+        wallet.watch(outpoint(1, 0));
+        let genesis = BlockDelta {
+            created: vec![outpoint(1, 0), outpoint(1, 1)],
+            spent: Vec::new(),
+        };
+        mine(&mut acc, &mut [&mut wallet], &genesis);
+
+        let state = acc.public_state();
+        let spend = wallet.spend(&outpoint(1, 0)).unwrap();
+
+        let double_spend = BlockDelta {
+            created: Vec::new(),
+            spent: vec![spend.clone(), spend.clone()],
+        };
+        let err = Some(BlockError::Duplicate(outpoint(1, 0)));
+        assert_eq!(apply_block(&mut acc, &double_spend).err(), err);
+        assert_eq!(wallet.clone().advance(&double_spend).err(), err);
+
+        let recreate = BlockDelta {
+            created: vec![outpoint(1, 1)],
+            spent: vec![spend.clone()],
+        };
+        let err = Some(BlockError::AlreadyUnspent(outpoint(1, 1)));
+        assert_eq!(apply_block(&mut acc, &recreate).err(), err);
+
+        let bad_witness = BlockDelta {
+            created: Vec::new(),
+            spent: vec![(outpoint(1, 1), spend.1.clone())],
+        };
+        let err = Some(BlockError::InvalidWitness(outpoint(1, 1)));
+        assert_eq!(apply_block(&mut acc, &bad_witness).err(), err);
+        assert_eq!(wallet.clone().advance(&bad_witness).err(), err);
+        assert_eq!(acc.public_state(), state);
+
+        // the proof is bound to the block contents
+        let block = BlockDelta {
+            created: vec![outpoint(2, 0)],
+            spent: vec![spend],
+        };
+        let proof = apply_block(&mut acc, &block).unwrap();
+        let other = BlockDelta {
+            created: vec![outpoint(2, 1)],
+            spent: block.spent.clone(),
+        };
+        assert!(!verify_block(
+            &state,
+            &acc.public_state(),
+            &other.digest(),
+            &proof
+        ));
+        assert!(verify_block(
+            &state,
+            &acc.public_state(),
+            &block.digest(),
+            &proof
+        ));
+
+        // a wallet that missed a block can't follow
+        let next = BlockDelta {
+            created: Vec::new(),
+            spent: vec![(
+                outpoint(2, 0),
+                acc.mem_wit_create(&outpoint_element(&outpoint(2, 0))),
+            )],
+        };
+        assert_eq!(
+            wallet.advance(&next),
+            Err(BlockError::InvalidWitness(outpoint(2, 0)))
+        );
+    }
+}
@@ -0,0 +1,364 @@
+//! Proptest strategies for accumulators, witnesses and proofs, for property tests of protocols
+//! built on top of this crate.
+//!
+//! The strategies draw seeds from proptest and derive parameters and elements from them, so
+//! failing cases replay exactly, and shrink towards fewer elements. The moduli have
+//! `TEST_LAMBDA` bits by default, insecure but fast.
+//!
+//! The `Arbitrary` impls of the witness and proof types give values that are well-formed,
+//! group elements reduced by the modulus in `ArbModulus` and coefficients of the size of an
+//! element, so that they parse, but that verify against nothing.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Zero};
+use proptest::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::accumulator::{
+    Accumulator, BatchProof, MembershipProof, MembershipWitness, NonMembershipProof,
+    NonMembershipWitness,
+};
+use crate::encoding::modulus_bytes;
+use crate::primes::RandPrimes;
+use crate::proofs::{PoeProof, Poke2Proof};
+use crate::setup::SetupParams;
+use crate::traits::*;
+
+/// Size of the moduli of `arb_accumulator_with_elements` and `ArbModulus`, in bits.
+pub const TEST_LAMBDA: usize = 256;
+
+/// Size of the generated elements, in bits.
+pub const TEST_ELEMENT_BITS: usize = 128;
+
+/// An accumulator with its elements and their witnesses, from
+/// `arb_accumulator_with_elements`.
+#[derive(Debug, Clone)]
+pub struct AccumulatorFixture {
+    pub acc: Accumulator,
+    /// The accumulated elements, distinct primes.
+    pub elements: Vec<BigUint>,
+    /// The membership witness of each element, for the current state.
+    pub witnesses: Vec<MembershipWitness>,
+}
+
+/// Parameters with a fresh modulus of a size in `lambda_range`, in bits.
+///
+/// The range must not go below `RSAGroup::MIN_MODULUS_BITS`.
+pub fn arb_params(lambda_range: Range<usize>) -> impl Strategy<Value = SetupParams> {
+    (lambda_range, any::<[u8; 32]>()).prop_map(|(lambda, seed)| {
+        SetupParams::generate(&mut StdRng::from_seed(seed), lambda).expect("modulus too small")
+    })
+}
+
+/// Distinct primes of `TEST_ELEMENT_BITS` bits, as many as in `count_range`.
+///
+/// Primes of that size collide with the elements of another strategy with negligible
+/// probability, so they serve as non-members of a fixture.
+pub fn arb_elements(count_range: Range<usize>) -> impl Strategy<Value = Vec<BigUint>> {
+    (count_range, any::<[u8; 32]>()).prop_map(|(count, seed)| {
+        StdRng::from_seed(seed).gen_distinct_primes(count, TEST_ELEMENT_BITS)
+    })
+}
+
+/// An accumulator over `TEST_LAMBDA` bit parameters, holding as many elements as in
+/// `count_range`, with the witnesses for all of them.
+pub fn arb_accumulator_with_elements(
+    count_range: Range<usize>,
+) -> impl Strategy<Value = AccumulatorFixture> {
+    (
+        arb_params(TEST_LAMBDA..TEST_LAMBDA + 1),
+        arb_elements(count_range),
+    )
+        .prop_map(|(params, elements)| {
+            let mut acc = Accumulator::new_with_params(Arc::new(params));
+            acc.add_batch_no_proof(&elements);
+            let witnesses = acc.create_all_mem_wit(&elements);
+
+            AccumulatorFixture {
+                acc,
+                elements,
+                witnesses,
+            }
+        })
+}
+
+/// Pairs of an element and a membership witness that don't verify against the state of
+/// `fixture`: a member with a random witness, a member with the witness of another member, or
+/// a non-member with the witness of a member.
+///
+/// `fixture` must have at least one element.
+pub fn arb_invalid_witness_for(
+    fixture: &AccumulatorFixture,
+) -> impl Strategy<Value = (BigUint, MembershipWitness)> {
+    assert!(!fixture.elements.is_empty(), "no elements to pick from");
+    let state = fixture.acc.public_state();
+    let len = fixture.elements.len();
+
+    let elements = fixture.elements.clone();
+    let random_witness = (0..len, arb_group_element(&state.n))
+        .prop_map(move |(i, w)| (elements[i].clone(), MembershipWitness(w)));
+
+    let (elements, witnesses) = (fixture.elements.clone(), fixture.witnesses.clone());
+    let other_witness =
+        (0..len, 0..len).prop_map(move |(i, j)| (elements[i].clone(), witnesses[j].clone()));
+
+    let witnesses = fixture.witnesses.clone();
+    let non_member = (arb_elements(1..2), 0..len)
+        .prop_map(move |(mut ys, i)| (ys.remove(0), witnesses[i].clone()));
+
+    prop_oneof![random_witness, other_witness, non_member]
+        .prop_filter("the witness is valid", move |(x, w)| !state.ver_mem(w, x))
+}
+
+/// The modulus the group elements of the `Arbitrary` impls are reduced by.
+///
+/// Defaults to `2^(TEST_LAMBDA - 1) + 1`, which is no RSA modulus, but has the same size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArbModulus(pub BigUint);
+
+impl Default for ArbModulus {
+    fn default() -> Self {
+        ArbModulus((BigUint::one() << (TEST_LAMBDA - 1)) + 1u32)
+    }
+}
+
+/// Non-zero group elements of `Z_n`.
+fn arb_group_element(n: &BigUint) -> BoxedStrategy<BigUint> {
+    let n = n.clone();
+    proptest::collection::vec(any::<u8>(), modulus_bytes(&n))
+        .prop_map(move |bytes| {
+            let v = BigUint::from_bytes_be(&bytes) % &n;
+            if v.is_zero() {
+                BigUint::one()
+            } else {
+                v
+            }
+        })
+        .boxed()
+}
+
+/// Non-negative coefficients of the size of an element, like `b` of reduced
+/// non-membership witnesses and `r` of NI-PoKE2 proofs, and smaller than `n`.
+fn arb_coefficient(n: &BigUint) -> BoxedStrategy<BigInt> {
+    let n = n.clone();
+    any::<u128>()
+        .prop_map(move |c| BigInt::from(BigUint::from(c) % &n))
+        .boxed()
+}
+
+impl Arbitrary for PoeProof {
+    type Parameters = ArbModulus;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(n: ArbModulus) -> Self::Strategy {
+        arb_group_element(&n.0).prop_map(|q| PoeProof { q }).boxed()
+    }
+}
+
+impl Arbitrary for Poke2Proof {
+    type Parameters = ArbModulus;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(n: ArbModulus) -> Self::Strategy {
+        (
+            arb_group_element(&n.0),
+            arb_group_element(&n.0),
+            arb_coefficient(&n.0),
+        )
+            .prop_map(|(z, q, r)| Poke2Proof { z, q, r })
+            .boxed()
+    }
+}
+
+impl Arbitrary for BatchProof {
+    type Parameters = ArbModulus;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(n: ArbModulus) -> Self::Strategy {
+        any_with::<PoeProof>(n).prop_map(BatchProof).boxed()
+    }
+}
+
+impl Arbitrary for MembershipWitness {
+    type Parameters = ArbModulus;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(n: ArbModulus) -> Self::Strategy {
+        arb_group_element(&n.0).prop_map(MembershipWitness).boxed()
+    }
+}
+
+impl Arbitrary for NonMembershipWitness {
+    type Parameters = ArbModulus;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(n: ArbModulus) -> Self::Strategy {
+        (arb_group_element(&n.0), arb_coefficient(&n.0))
+            .prop_map(|(d, b)| NonMembershipWitness { d, b })
+            .boxed()
+    }
+}
+
+impl Arbitrary for MembershipProof {
+    type Parameters = ArbModulus;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(n: ArbModulus) -> Self::Strategy {
+        (arb_group_element(&n.0), any_with::<PoeProof>(n))
+            .prop_map(|(w, pi)| MembershipProof { w, pi })
+            .boxed()
+    }
+}
+
+impl Arbitrary for NonMembershipProof {
+    type Parameters = ArbModulus;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(n: ArbModulus) -> Self::Strategy {
+        (
+            arb_group_element(&n.0),
+            arb_group_element(&n.0),
+            any_with::<Poke2Proof>(n.clone()),
+            any_with::<PoeProof>(n),
+        )
+            .prop_map(|(d, v, pi_d, pi_g)| NonMembershipProof { d, v, pi_d, pi_g })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::{NewState, OldState};
+    use crate::encoding::Packed;
+    use crate::witness::WitnessManager;
+
+    fn config() -> ProptestConfig {
+        // every case generates a modulus
+        ProptestConfig::with_cases(16)
+    }
+
+    proptest! {
+        #![proptest_config(config())]
+
+        #[test]
+        fn prop_fixture_valid(fixture in arb_accumulator_with_elements(0..6)) {
+            let acc = &fixture.acc;
+            prop_assert_eq!(acc.len(), fixture.elements.len());
+            for (x, w) in fixture.elements.iter().zip(&fixture.witnesses) {
+                prop_assert!(acc.ver_mem(w, x));
+            }
+        }
+
+        #[test]
+        fn prop_witness_update(
+            fixture in arb_accumulator_with_elements(2..6),
+            added in arb_elements(1..4),
+        ) {
+            let AccumulatorFixture { mut acc, elements, .. } = fixture;
+            let n = acc.modulus().clone();
+            let mut manager = WitnessManager::new(&acc);
+            for x in &elements[1..] {
+                manager.register(&acc, x);
+            }
+            // and a non-member, updated element by element
+            let y = added[0].clone();
+            let mut u = acc.non_mem_wit_create(&y);
+
+            for x in &added[1..] {
+                let root = acc.state().clone();
+                manager.add(&mut acc, x);
+                prop_assert!(u.update_on_add(&y, x, &root, &n).is_some());
+            }
+            manager.del(&mut acc, &elements[0]).unwrap();
+            prop_assert!(u.update_on_del(&y, &elements[0], acc.state(), &n).is_some());
+
+            for x in &elements[1..] {
+                prop_assert!(acc.ver_mem(manager.witness_for(x).unwrap(), x));
+            }
+            prop_assert!(acc.ver_non_mem(&u, &y));
+        }
+
+        #[test]
+        fn prop_batch_add_del(
+            fixture in arb_accumulator_with_elements(1..6),
+            added in arb_elements(1..5),
+        ) {
+            let AccumulatorFixture { mut acc, elements, witnesses } = fixture;
+            let old = OldState(acc.state().clone());
+            let pi = acc.batch_add(&added);
+            let new = NewState(acc.state().clone());
+            prop_assert!(acc.ver_batch_add_transition(&pi, &old, &new, &added));
+            prop_assert!(!acc.ver_batch_add_transition(&pi, &old, &new, &added[1..]));
+
+            // the extended witnesses are the ones created for the whole set
+            let mut existing = elements.iter().cloned().zip(witnesses).collect::<Vec<_>>();
+            acc.extend_all_mem_wit(&mut existing, &added);
+            let all = elements.iter().chain(&added).cloned().collect::<Vec<_>>();
+            prop_assert_eq!(
+                existing.iter().map(|(_, w)| w.clone()).collect::<Vec<_>>(),
+                acc.create_all_mem_wit(&all)
+            );
+
+            // and delete the original elements again
+            let pairs = existing[..elements.len()].to_vec();
+            let old = OldState(acc.state().clone());
+            let pi = acc.batch_del(&pairs).unwrap();
+            let new = NewState(acc.state().clone());
+            prop_assert!(acc.ver_batch_del_transition(&pi, &old, &new, &elements));
+            prop_assert_eq!(acc.len(), added.len());
+        }
+
+        #[test]
+        fn prop_invalid_witness_rejected(
+            (fixture, (x, w)) in arb_accumulator_with_elements(1..6)
+                .prop_flat_map(|f| {
+                    let invalid = arb_invalid_witness_for(&f);
+                    (Just(f), invalid)
+                })
+        ) {
+            prop_assert!(!fixture.acc.ver_mem(&w, &x));
+        }
+
+        #[test]
+        fn prop_arbitrary_proofs(
+            (n, mem, non_mem, batch) in arb_params(64..512).prop_flat_map(|params| {
+                let n = ArbModulus(params.modulus().clone());
+                (
+                    Just(n.0.clone()),
+                    any_with::<MembershipProof>(n.clone()),
+                    any_with::<NonMembershipProof>(n.clone()),
+                    any_with::<BatchProof>(n),
+                )
+            }),
+        ) {
+            // well-formed, so they parse
+            prop_assert_eq!(MembershipProof::parse(&mem.to_packed_bytes(), &n).unwrap().0, mem);
+            prop_assert_eq!(
+                NonMembershipProof::parse(&non_mem.to_packed_bytes(), &n).unwrap().0,
+                non_mem
+            );
+            prop_assert_eq!(BatchProof::parse(&batch.to_packed_bytes(), &n).unwrap().0, batch);
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_default_modulus() {
+        use proptest::strategy::ValueTree;
+        use proptest::test_runner::TestRunner;
+
+        let mut runner = TestRunner::deterministic();
+        let n = ArbModulus::default().0;
+        let w = any::<NonMembershipWitness>()
+            .new_tree(&mut runner)
+            .unwrap()
+            .current();
+        assert!(w.d < n && !w.d.is_zero());
+        assert!(w.b.bits() <= 128);
+    }
+}
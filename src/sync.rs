@@ -0,0 +1,501 @@
+//! Keeping followers in sync with an accumulator, from a stream of proven updates.
+//!
+//! The manager enables recording with `Accumulator::record_updates`, and hands the recorded
+//! messages to its followers in order. A follower only holds the public state, in a
+//! `VerifierState`, and checks the proof of every message against it before advancing. Each
+//! message carries a sequence number, so replayed, reordered and missing messages are noticed.
+
+use std::fmt;
+
+use num_bigint::BigUint;
+
+use crate::accumulator::{BatchProof, NewState, OldState, PublicState};
+use crate::math::product;
+use crate::merge::{ver_merge, MergeProof};
+use crate::stateless::{verify_block, BlockProof, DeltaDigest};
+
+/// A single mutation of the accumulator, with the proof for the transition from the state
+/// before it to `new_state`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateMessage {
+    /// The elements were added.
+    BatchAdd {
+        seq: u64,
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::encoding::serde_hex::biguint_vec")
+        )]
+        elements: Vec<BigUint>,
+        proof: BatchProof,
+        #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+        new_state: BigUint,
+    },
+    /// The elements were deleted.
+    BatchDel {
+        seq: u64,
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::encoding::serde_hex::biguint_vec")
+        )]
+        elements: Vec<BigUint>,
+        proof: BatchProof,
+        #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+        new_state: BigUint,
+    },
+    /// The elements `deleted` were deleted and then `added` added, see
+    /// `Accumulator::multi_swap`.
+    MultiSwap {
+        seq: u64,
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::encoding::serde_hex::biguint_vec")
+        )]
+        deleted: Vec<BigUint>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::encoding::serde_hex::biguint_vec")
+        )]
+        added: Vec<BigUint>,
+        proof: BlockProof,
+        #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+        new_state: BigUint,
+    },
+    /// The accumulator with the state `other_state` was merged in, see `Accumulator::merge`.
+    Merge {
+        seq: u64,
+        #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+        other_state: BigUint,
+        proof: MergeProof,
+        #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+        new_state: BigUint,
+    },
+}
+
+impl UpdateMessage {
+    /// Returns the sequence number of the message.
+    pub fn seq(&self) -> u64 {
+        match self {
+            UpdateMessage::BatchAdd { seq, .. }
+            | UpdateMessage::BatchDel { seq, .. }
+            | UpdateMessage::MultiSwap { seq, .. }
+            | UpdateMessage::Merge { seq, .. } => *seq,
+        }
+    }
+
+    /// Returns the state after the update.
+    pub fn new_state(&self) -> &BigUint {
+        match self {
+            UpdateMessage::BatchAdd { new_state, .. }
+            | UpdateMessage::BatchDel { new_state, .. }
+            | UpdateMessage::MultiSwap { new_state, .. }
+            | UpdateMessage::Merge { new_state, .. } => new_state,
+        }
+    }
+}
+
+/// Messages recorded by `Accumulator::record_updates`, in order.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateLog {
+    pub messages: Vec<UpdateMessage>,
+}
+
+impl UpdateLog {
+    /// Returns the number of messages.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Returns `true` if no mutations were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+impl IntoIterator for UpdateLog {
+    type Item = UpdateMessage;
+    type IntoIter = std::vec::IntoIter<UpdateMessage>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.messages.into_iter()
+    }
+}
+
+/// Recorded messages of an `Accumulator`, not yet taken.
+#[cfg(feature = "prover")]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct UpdateRecorder {
+    /// Sequence number of the next message.
+    next_seq: u64,
+    log: UpdateLog,
+}
+
+#[cfg(feature = "prover")]
+impl UpdateRecorder {
+    pub(crate) fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Records the message created by `message` for the next sequence number.
+    pub(crate) fn push<F: FnOnce(u64) -> UpdateMessage>(&mut self, message: F) {
+        self.log.messages.push(message(self.next_seq));
+        self.next_seq += 1;
+    }
+
+    /// Returns the messages recorded so far.
+    pub(crate) fn take(&mut self) -> UpdateLog {
+        std::mem::take(&mut self.log)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncError {
+    /// The message was applied already.
+    Replayed { seq: u64 },
+    /// Messages before this one are missing.
+    OutOfOrder { expected: u64, found: u64 },
+    /// The proof of the message does not verify against the current state.
+    InvalidProof { seq: u64 },
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyncError::Replayed { seq } => write!(f, "update {} was applied already", seq),
+            SyncError::OutOfOrder { expected, found } => {
+                write!(f, "expected update {}, got update {}", expected, found)
+            }
+            SyncError::InvalidProof { seq } => write!(f, "invalid proof for update {}", seq),
+        }
+    }
+}
+
+impl std::error::Error for SyncError {}
+
+/// The public state of an accumulator kept by a follower, with the sequence number of the
+/// next update it expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifierState {
+    state: PublicState,
+    next_seq: u64,
+}
+
+impl VerifierState {
+    /// Creates a follower of the accumulator with state `state`, expecting the update
+    /// `next_seq` next, see `Accumulator::verifier_state`.
+    pub fn new(state: PublicState, next_seq: u64) -> Self {
+        VerifierState { state, next_seq }
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> &PublicState {
+        &self.state
+    }
+
+    /// Returns the sequence number of the next update.
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Verifies `msg` against the current state, and advances to its new state.
+    ///
+    /// Fails, leaving the state unchanged, if `msg` is not the next update, or its proof does
+    /// not verify.
+    pub fn apply_update(&mut self, msg: &UpdateMessage) -> Result<(), SyncError> {
+        let seq = msg.seq();
+        let _span = span!("apply_update", seq = seq);
+        if seq < self.next_seq {
+            return Err(SyncError::Replayed { seq });
+        }
+        if seq > self.next_seq {
+            return Err(SyncError::OutOfOrder {
+                expected: self.next_seq,
+                found: seq,
+            });
+        }
+
+        if !self.verify(msg) {
+            return Err(SyncError::InvalidProof { seq });
+        }
+
+        self.state.root = msg.new_state().clone();
+        self.next_seq += 1;
+
+        Ok(())
+    }
+
+    /// Verifies the proof of `msg` for the transition from the current state.
+    fn verify(&self, msg: &UpdateMessage) -> bool {
+        let state = &self.state;
+        let old = OldState(state.root.clone());
+        match msg {
+            UpdateMessage::BatchAdd {
+                elements,
+                proof,
+                new_state,
+                ..
+            } => {
+                let new = NewState(new_state.clone());
+                state.ver_batch_add_transition(proof, &old, &new, elements)
+            }
+            UpdateMessage::BatchDel {
+                elements,
+                proof,
+                new_state,
+                ..
+            } => {
+                let new = NewState(new_state.clone());
+                state.ver_batch_del_transition(proof, &old, &new, elements)
+            }
+            UpdateMessage::MultiSwap {
+                deleted,
+                added,
+                proof,
+                new_state,
+                ..
+            } => {
+                if [new_state, &proof.intermediate]
+                    .iter()
+                    .any(|v| state.check_group_element(v).is_err())
+                    || state.check_elements(deleted).is_err()
+                    || state.check_elements(added).is_err()
+                {
+                    return false;
+                }
+
+                let digest = DeltaDigest {
+                    spent: product(deleted),
+                    created: product(added),
+                };
+                verify_block(state, &self.with_root(new_state), &digest, proof)
+            }
+            UpdateMessage::Merge {
+                other_state,
+                proof,
+                new_state,
+                ..
+            } => {
+                if [new_state, other_state]
+                    .iter()
+                    .any(|v| state.check_group_element(v).is_err())
+                {
+                    return false;
+                }
+
+                ver_merge(
+                    state,
+                    &self.with_root(other_state),
+                    &self.with_root(new_state),
+                    proof,
+                )
+            }
+        }
+    }
+
+    /// The public state of an accumulator with the same parameters, and state `root`.
+    fn with_root(&self, root: &BigUint) -> PublicState {
+        PublicState {
+            root: root.clone(),
+            ..self.state.clone()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::{Accumulator, MembershipWitness};
+    use crate::group::RSAGroup;
+    use crate::primes::RandPrimes;
+    use crate::traits::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_follow_random_workload() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        acc.add_batch_no_proof(&rng.gen_distinct_primes(4, 128));
+
+        // followers start from the state when recording starts
+        assert!(acc.record_updates().is_empty());
+        let mut follower = acc.verifier_state();
+        assert_eq!(follower.next_seq(), 0);
+
+        let mut pool = rng.gen_distinct_primes(200, 128).into_iter();
+        let mut members: Vec<BigUint> = Vec::new();
+        let mut take = |n: usize| pool.by_ref().take(n).collect::<Vec<_>>();
+
+        for round in 0..60 {
+            match rng.gen_range(0, 7) {
+                0 => {
+                    let x = take(1).remove(0);
+                    acc.add(&x);
+                    members.push(x);
+                }
+                1 => {
+                    let xs = take(rng.gen_range(1, 5));
+                    acc.batch_add(&xs);
+                    members.extend(xs);
+                }
+                2 if !members.is_empty() => {
+                    let x = members.swap_remove(rng.gen_range(0, members.len()));
+                    acc.del(&x).unwrap();
+                }
+                3 if !members.is_empty() => {
+                    let x = members.swap_remove(rng.gen_range(0, members.len()));
+                    let w = acc.mem_wit_create(&x);
+                    acc.del_w_mem(&w, &x).unwrap();
+                }
+                4 if members.len() >= 2 => {
+                    let pairs = members
+                        .split_off(members.len() - 2)
+                        .into_iter()
+                        .map(|x| {
+                            let w = acc.mem_wit_create(&x);
+                            (x, w)
+                        })
+                        .collect::<Vec<(BigUint, MembershipWitness)>>();
+                    acc.batch_del(&pairs).unwrap();
+                }
+                5 => {
+                    let pairs = members
+                        .drain(..members.len().min(1))
+                        .map(|x| {
+                            let w = acc.mem_wit_create(&x);
+                            (x, w)
+                        })
+                        .collect::<Vec<_>>();
+                    let xs = take(2);
+                    acc.multi_swap(&pairs, &xs).unwrap();
+                    members.extend(xs);
+                }
+                _ => {
+                    let xs = take(2);
+                    let mut other = Accumulator::new_with_params(acc.params().clone());
+                    other.add_batch_no_proof(&xs);
+                    acc = acc.merge(other).unwrap().0;
+                    members.extend(xs);
+                }
+            }
+
+            // the follower catches up every few rounds
+            if round % 3 == 0 {
+                for msg in acc.record_updates() {
+                    follower.apply_update(&msg).unwrap();
+                }
+                assert_eq!(follower.state(), &acc.public_state());
+            }
+        }
+
+        for msg in acc.record_updates() {
+            follower.apply_update(&msg).unwrap();
+        }
+        assert_eq!(follower.state(), &acc.public_state());
+        assert_eq!(follower, acc.verifier_state());
+        for x in &members {
+            assert!(follower.state().ver_mem(&acc.mem_wit_create(x), x));
+        }
+    }
+
+    #[test]
+    fn test_rejected_updates() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, 256);
+        acc.record_updates();
+        let mut follower = acc.verifier_state();
+
+        let xs = rng.gen_distinct_primes(4, 128);
+        acc.add(&xs[0]);
+        acc.batch_add(&xs[1..3]);
+        acc.del(&xs[1]).unwrap();
+        let msgs = acc.record_updates().messages;
+        assert_eq!(
+            msgs.iter().map(UpdateMessage::seq).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+
+        assert_eq!(
+            follower.apply_update(&msgs[1]),
+            Err(SyncError::OutOfOrder {
+                expected: 0,
+                found: 1
+            })
+        );
+        follower.apply_update(&msgs[0]).unwrap();
+        assert_eq!(
+            follower.apply_update(&msgs[0]),
+            Err(SyncError::Replayed { seq: 0 })
+        );
+
+        // tampered messages don't verify, and leave the follower unchanged
+        let before = follower.clone();
+        let mut tampered = msgs[1].clone();
+        if let UpdateMessage::BatchAdd { elements, .. } = &mut tampered {
+            elements[0] = xs[3].clone();
+        }
+        assert_eq!(
+            follower.apply_update(&tampered),
+            Err(SyncError::InvalidProof { seq: 1 })
+        );
+        let mut tampered = msgs[1].clone();
+        if let UpdateMessage::BatchAdd { new_state, .. } = &mut tampered {
+            *new_state = acc.state().clone();
+        }
+        assert_eq!(
+            follower.apply_update(&tampered),
+            Err(SyncError::InvalidProof { seq: 1 })
+        );
+        // a deletion passed off as an addition
+        let forged = match &msgs[2] {
+            UpdateMessage::BatchDel {
+                elements,
+                proof,
+                new_state,
+                ..
+            } => UpdateMessage::BatchAdd {
+                seq: 1,
+                elements: elements.clone(),
+                proof: proof.clone(),
+                new_state: new_state.clone(),
+            },
+            msg => panic!("unexpected message {:?}", msg),
+        };
+        assert_eq!(
+            follower.apply_update(&forged),
+            Err(SyncError::InvalidProof { seq: 1 })
+        );
+        assert_eq!(follower, before);
+
+        for msg in &msgs[1..] {
+            follower.apply_update(msg).unwrap();
+        }
+        assert_eq!(follower.state(), &acc.public_state());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_updates() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, 256);
+        acc.record_updates();
+        let mut follower = acc.verifier_state();
+
+        let xs = rng.gen_distinct_primes(3, 128);
+        acc.batch_add(&xs[..2]);
+        let w = acc.mem_wit_create(&xs[0]);
+        acc.multi_swap(&[(xs[0].clone(), w)], &xs[2..]).unwrap();
+
+        let log = acc.record_updates();
+        let json = serde_json::to_string(&log).unwrap();
+        let decoded: UpdateLog = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, log);
+        for msg in &decoded.messages {
+            follower.apply_update(msg).unwrap();
+        }
+        assert_eq!(follower.state(), &acc.public_state());
+    }
+}
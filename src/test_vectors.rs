@@ -0,0 +1,318 @@
+//! Fixed test vectors, for checking other implementations against this one.
+//!
+//! The vectors are stored as JSON, with group elements encoded as fixed width big-endian hex
+//! (see `encoding::to_fixed_bytes`), and all other integers as minimal big-endian hex, prefixed
+//! with `-` if negative.
+
+use failure::{bail, format_err, Error};
+use num_bigint::{BigInt, BigUint};
+use serde_json::{json, Value};
+
+#[cfg(feature = "prover")]
+use crate::accumulator::Accumulator;
+use crate::accumulator::{BatchProof, MembershipWitness, NonMembershipProof, NonMembershipWitness};
+use crate::encoding::hex::{bigint_from_hex, bigint_to_hex, biguint_from_hex, biguint_to_hex};
+use crate::encoding::hex::{from_hex, to_hex};
+use crate::encoding::{from_fixed_bytes, modulus_bytes, to_fixed_bytes};
+use crate::proofs::{PoeProof, Poke2Proof};
+#[cfg(feature = "prover")]
+use crate::traits::*;
+
+const RSA_256: &str = include_str!("../test-vectors/rsa-256.json");
+
+/// A single test vector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestVector {
+    pub name: String,
+    /// Modulus.
+    pub n: BigUint,
+    /// Generator.
+    pub g: BigUint,
+    /// Elements, added one by one with `add`.
+    pub elements: Vec<BigUint>,
+    /// The state after each `add`.
+    pub states: Vec<BigUint>,
+    /// Witnesses for `elements`, against the last of `states`.
+    pub witnesses: Vec<MembershipWitness>,
+    /// Elements added with `batch_add`, after all `elements`.
+    pub batch_elements: Vec<BigUint>,
+    /// The state after `batch_add`.
+    pub batch_state: BigUint,
+    /// The proof returned by `batch_add`.
+    pub batch_proof: BatchProof,
+    /// An element that is not a member, after `batch_add`.
+    pub non_member: BigUint,
+    /// Result of `non_mem_wit_create` for `non_member`.
+    pub non_mem_witness: NonMembershipWitness,
+    /// Result of `non_mem_wit_create_star` for `non_member`.
+    pub non_mem_proof: NonMembershipProof,
+}
+
+/// Returns the test vectors shipped with this crate.
+pub fn builtin() -> Vec<TestVector> {
+    vec![TestVector::from_json(RSA_256).expect("invalid builtin test vector")]
+}
+
+impl TestVector {
+    /// Computes a test vector from its inputs.
+    #[cfg(feature = "prover")]
+    pub fn compute(
+        name: &str,
+        n: BigUint,
+        g: BigUint,
+        elements: Vec<BigUint>,
+        batch_elements: Vec<BigUint>,
+        non_member: BigUint,
+    ) -> Self {
+        let mut acc = Accumulator::new(n.clone(), g.clone());
+
+        let states = elements
+            .iter()
+            .map(|x| {
+                acc.add(x);
+                acc.state().clone()
+            })
+            .collect();
+        let witnesses = elements.iter().map(|x| acc.mem_wit_create(x)).collect();
+
+        let batch_proof = acc.batch_add(&batch_elements);
+        let batch_state = acc.state().clone();
+
+        let non_mem_witness = acc.non_mem_wit_create(&non_member);
+        let non_mem_proof = acc.non_mem_wit_create_star(&non_member);
+
+        TestVector {
+            name: name.to_string(),
+            n,
+            g,
+            elements,
+            states,
+            witnesses,
+            batch_elements,
+            batch_state,
+            batch_proof,
+            non_member,
+            non_mem_witness,
+            non_mem_proof,
+        }
+    }
+
+    /// Parses a test vector from its JSON representation.
+    pub fn from_json(s: &str) -> Result<Self, Error> {
+        let v: Value = serde_json::from_str(s)?;
+
+        let n = biguint_from_hex(string(&v, "n")?)?;
+        let elem = |v: &Value, key: &str| -> Result<BigUint, Error> {
+            Ok(from_fixed_bytes(&from_hex(string(v, key)?)?, &n)?)
+        };
+
+        let add = array(&v, "add")?;
+        let elements = add
+            .iter()
+            .map(|a| biguint_from_hex(string(a, "element")?))
+            .collect::<Result<_, _>>()?;
+        let states = add
+            .iter()
+            .map(|a| elem(a, "state"))
+            .collect::<Result<_, _>>()?;
+        let witnesses = add
+            .iter()
+            .map(|a| elem(a, "witness").map(MembershipWitness))
+            .collect::<Result<_, _>>()?;
+
+        let batch = field(&v, "batch_add")?;
+        let batch_elements = array(batch, "elements")?
+            .iter()
+            .map(|x| biguint_from_hex(as_str(x)?))
+            .collect::<Result<_, _>>()?;
+
+        let non_mem = field(&v, "non_membership")?;
+        let witness = field(non_mem, "witness")?;
+        let proof = field(non_mem, "proof")?;
+        let pi_d = field(proof, "pi_d")?;
+
+        Ok(TestVector {
+            name: string(&v, "name")?.to_string(),
+            g: elem(&v, "g")?,
+            elements,
+            states,
+            witnesses,
+            batch_elements,
+            batch_state: elem(batch, "state")?,
+            batch_proof: BatchProof(PoeProof {
+                q: elem(batch, "proof")?,
+            }),
+            non_member: biguint_from_hex(string(non_mem, "element")?)?,
+            non_mem_witness: NonMembershipWitness {
+                d: elem(witness, "d")?,
+                b: bigint_from_hex(string(witness, "b")?)?,
+            },
+            non_mem_proof: NonMembershipProof {
+                d: elem(proof, "d")?,
+                v: elem(proof, "v")?,
+                pi_d: Poke2Proof {
+                    z: elem(pi_d, "z")?,
+                    q: elem(pi_d, "q")?,
+                    r: bigint_from_hex(string(pi_d, "r")?)?,
+                },
+                pi_g: PoeProof {
+                    q: elem(proof, "pi_g")?,
+                },
+            },
+            n,
+        })
+    }
+
+    /// Returns the JSON representation of this test vector.
+    pub fn to_json(&self) -> String {
+        let len = modulus_bytes(&self.n);
+        let elem = |x: &BigUint| to_hex(&to_fixed_bytes(x, len));
+        let int = |x: &BigInt| bigint_to_hex(x);
+
+        let add = self
+            .elements
+            .iter()
+            .zip(&self.states)
+            .zip(&self.witnesses)
+            .map(|((x, state), w)| {
+                json!({
+                    "element": biguint_to_hex(x),
+                    "state": elem(state),
+                    "witness": elem(&w.0),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let proof = &self.non_mem_proof;
+        let v = json!({
+            "name": self.name,
+            "n": biguint_to_hex(&self.n),
+            "g": elem(&self.g),
+            "add": add,
+            "batch_add": {
+                "elements": self.batch_elements.iter().map(biguint_to_hex).collect::<Vec<_>>(),
+                "state": elem(&self.batch_state),
+                "proof": elem(&self.batch_proof.0.q),
+            },
+            "non_membership": {
+                "element": biguint_to_hex(&self.non_member),
+                "witness": {
+                    "d": elem(&self.non_mem_witness.d),
+                    "b": int(&self.non_mem_witness.b),
+                },
+                "proof": {
+                    "d": elem(&proof.d),
+                    "v": elem(&proof.v),
+                    "pi_d": {
+                        "z": elem(&proof.pi_d.z),
+                        "q": elem(&proof.pi_d.q),
+                        "r": int(&proof.pi_d.r),
+                    },
+                    "pi_g": elem(&proof.pi_g.q),
+                },
+            },
+        });
+
+        serde_json::to_string_pretty(&v).expect("failed to serialize") + "\n"
+    }
+}
+
+fn field<'a>(v: &'a Value, key: &str) -> Result<&'a Value, Error> {
+    v.get(key)
+        .ok_or_else(|| format_err!("missing field `{}`", key))
+}
+
+fn as_str(v: &Value) -> Result<&str, Error> {
+    match v.as_str() {
+        Some(s) => Ok(s),
+        None => bail!("expected a string"),
+    }
+}
+
+fn string<'a>(v: &'a Value, key: &str) -> Result<&'a str, Error> {
+    as_str(field(v, key)?)
+}
+
+fn array<'a>(v: &'a Value, key: &str) -> Result<&'a Vec<Value>, Error> {
+    match field(v, key)?.as_array() {
+        Some(a) => Ok(a),
+        None => bail!("expected `{}` to be an array", key),
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::{NewState, OldState};
+    use crate::group::RSAGroup;
+    use crate::primes::derive_prime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    /// Regenerates `test-vectors/rsa-256.json`, run with `--ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn generate_rsa_256() {
+        let rng = &mut ChaChaRng::from_seed([1u8; 32]);
+        let acc = Accumulator::setup::<RSAGroup, _>(rng, 256);
+
+        let elements = (0..4).map(|i| derive_prime(b"rsa-256", i, 64)).collect();
+        let batch_elements = (4..7).map(|i| derive_prime(b"rsa-256", i, 64)).collect();
+        let non_member = derive_prime(b"rsa-256", 7, 64);
+
+        let v = TestVector::compute(
+            "rsa-256",
+            acc.modulus().clone(),
+            acc.generator().clone(),
+            elements,
+            batch_elements,
+            non_member,
+        );
+        print!("{}", v.to_json());
+    }
+
+    #[test]
+    fn test_builtin_vectors() {
+        for v in builtin() {
+            // recomputing from the inputs must give exactly the same results
+            let computed = TestVector::compute(
+                &v.name,
+                v.n.clone(),
+                v.g.clone(),
+                v.elements.clone(),
+                v.batch_elements.clone(),
+                v.non_member.clone(),
+            );
+            assert_eq!(computed, v, "{} does not match", v.name);
+            assert_eq!(computed.to_json(), RSA_256);
+
+            // and the fixtures verify
+            let mut acc = Accumulator::new(v.n.clone(), v.g.clone());
+            for x in &v.elements {
+                acc.add(x);
+            }
+            for (w, x) in v.witnesses.iter().zip(&v.elements) {
+                assert!(acc.ver_mem(w, x));
+            }
+
+            let root = acc.state().clone();
+            acc.batch_add(&v.batch_elements);
+            let (old, new) = (OldState(root), NewState(acc.state().clone()));
+            assert!(acc.ver_batch_add_transition(&v.batch_proof, &old, &new, &v.batch_elements));
+            assert!(acc.ver_non_mem(&v.non_mem_witness, &v.non_member));
+            assert!(acc.ver_non_mem_star(&v.non_member, &v.non_mem_proof));
+        }
+    }
+
+    #[test]
+    fn test_from_json_invalid() {
+        assert!(TestVector::from_json("{}").is_err());
+
+        // group elements must have the full width
+        let mut v: Value = serde_json::from_str(RSA_256).unwrap();
+        let g = v["g"].as_str().unwrap()[2..].to_string();
+        v["g"] = Value::String(g);
+        assert!(TestVector::from_json(&v.to_string()).is_err());
+    }
+}
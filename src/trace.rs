@@ -0,0 +1,173 @@
+//! Spans around expensive phases, logged at the trace level of the `log` crate with the `log`
+//! feature. They are plain log records, not spans of the `tracing` crate.
+//!
+//! `span!("name", field = value, ...)` returns a guard, which logs the name and fields when it
+//! is created, and again with the elapsed time when it is dropped. Fields are sizes and
+//! counts, never the values themselves. Without the feature the guard is a unit struct, and
+//! the fields are not evaluated.
+
+#[cfg(feature = "log")]
+use std::time::Instant;
+
+/// Target of all log records.
+#[cfg(feature = "log")]
+pub(crate) const TARGET: &str = "accumulators";
+
+#[cfg(feature = "log")]
+macro_rules! span {
+    ($name:expr $(, $field:ident = $value:expr)* $(,)?) => {
+        $crate::trace::Span::enter($name, || {
+            #[allow(unused_mut)]
+            let mut fields = String::new();
+            $(fields.push_str(&format!(" {}={}", stringify!($field), $value));)*
+            fields
+        })
+    };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! span {
+    ($($args:tt)*) => {
+        $crate::trace::Span
+    };
+}
+
+/// Guard of an entered span.
+#[cfg(feature = "log")]
+pub(crate) struct Span(Option<(&'static str, String, Instant)>);
+
+#[cfg(not(feature = "log"))]
+pub(crate) struct Span;
+
+#[cfg(feature = "log")]
+impl Span {
+    /// Enters the span `name`, only formatting the fields if trace logging is enabled.
+    pub(crate) fn enter<F: FnOnce() -> String>(name: &'static str, fields: F) -> Self {
+        if !log::log_enabled!(target: TARGET, log::Level::Trace) {
+            return Span(None);
+        }
+
+        let fields = fields();
+        log::trace!(target: TARGET, "enter {}{}", name, fields);
+        Span(Some((name, fields, Instant::now())))
+    }
+}
+
+#[cfg(feature = "log")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some((name, fields, start)) = &self.0 {
+            log::trace!(
+                target: TARGET,
+                "exit {}{} elapsed={:?}",
+                name,
+                fields,
+                start.elapsed()
+            );
+        }
+    }
+}
+
+#[cfg(all(test, feature = "log", feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use std::sync::{Mutex, Once};
+    use std::thread::{self, ThreadId};
+
+    use log::{Log, Metadata, Record};
+    use num_bigint::BigUint;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    use crate::accumulator::{Accumulator, NewState, OldState};
+    use crate::group::RSAGroup;
+    use crate::primes::RandPrimes;
+    use crate::traits::*;
+
+    /// Collects the messages of all records, with the thread they were logged on, as tests
+    /// run in parallel.
+    struct Capture(Mutex<Vec<(ThreadId, String)>>);
+
+    impl Log for Capture {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.target() == TARGET
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                let msg = record.args().to_string();
+                self.0.lock().unwrap().push((thread::current().id(), msg));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+    static INIT: Once = Once::new();
+
+    /// Runs `f`, and returns the messages logged by it.
+    fn capture<F: FnOnce()>(f: F) -> Vec<String> {
+        INIT.call_once(|| {
+            log::set_logger(&CAPTURE).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+
+        let id = thread::current().id();
+        CAPTURE.0.lock().unwrap().retain(|(t, _)| *t != id);
+        f();
+        CAPTURE
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(t, _)| *t == id)
+            .map(|(_, msg)| msg.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_batch_add_spans() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = rng.gen_distinct_primes(3, 128);
+
+        let root = acc.state().clone();
+        let mut w = None;
+        let msgs = capture(|| w = Some(acc.batch_add(&xs)));
+
+        let entered = msgs
+            .iter()
+            .filter_map(|m| m.strip_prefix("enter "))
+            .collect::<Vec<_>>();
+        let bits = xs.iter().fold(BigUint::from(1u32), |acc, x| acc * x).bits();
+        assert_eq!(
+            entered,
+            vec![
+                "batch_add elements=3 modulus_bits=256".to_string(),
+                "product elements=3".to_string(),
+                format!("update_root exponent_bits={}", bits),
+                format!("ni_poe_prove exponent_bits={} factors=3", bits),
+            ]
+        );
+        assert_eq!(
+            msgs.iter().filter(|m| m.starts_with("exit ")).count(),
+            entered.len()
+        );
+        assert!(msgs.last().unwrap().starts_with("exit batch_add "));
+
+        // sizes only, never values
+        for x in &xs {
+            assert!(msgs.iter().all(|m| !m.contains(&x.to_string())));
+        }
+
+        let (old, new) = (OldState(root), NewState(acc.state().clone()));
+        let msgs = capture(|| assert!(acc.ver_batch_add_transition(&w.unwrap(), &old, &new, &xs)));
+        assert!(msgs
+            .iter()
+            .any(|m| m.starts_with("enter ver_batch_add elements=3")));
+        assert!(msgs.iter().any(|m| m.starts_with("enter ni_poe_verify")));
+    }
+}
@@ -1,25 +1,44 @@
+use std::collections::HashMap;
+
+use crate::accumulator::{NewState, OldState};
+#[cfg(feature = "prover")]
+use crate::group::RsaSetup;
+#[cfg(feature = "prover")]
 use failure::Error;
-use num_bigint::{BigInt, BigUint};
+use num_bigint::BigUint;
+#[cfg(feature = "prover")]
 use rand::CryptoRng;
+#[cfg(feature = "prover")]
 use rand::Rng;
 
-pub trait StaticAccumulator {
+/// Construction of an accumulator.
+///
+/// This is kept separate from the other accumulator traits, so that those stay object safe.
+#[cfg(feature = "prover")]
+pub trait AccumulatorSetup: Sized {
     /// Setup generates a group of unknown order and initializes the group
     /// with a generator of that group.
+    ///
+    /// `int_size_bits` is the size of the modulus, see `PrimeGroup::generate_primes`.
     fn setup<T, R>(rng: &mut R, int_size_bits: usize) -> Self
     where
         T: PrimeGroup,
         R: CryptoRng + Rng;
+}
+
+pub trait StaticAccumulator {
+    /// Witness proving membership of a single element.
+    type MemWitness;
 
     /// Update the accumulator.
     fn add(&mut self, x: &BigUint);
 
     /// Create a membership proof.
-    /// Returns `None`, iff `x` is not a member.
-    fn mem_wit_create(&self, x: &BigUint) -> BigUint;
+    /// Panics if `x` is not a member.
+    fn mem_wit_create(&self, x: &BigUint) -> Self::MemWitness;
 
     /// Verify a membership proof.
-    fn ver_mem(&self, w: &BigUint, x: &BigUint) -> bool;
+    fn ver_mem(&self, w: &Self::MemWitness, x: &BigUint) -> bool;
 
     fn state(&self) -> &BigUint;
 }
@@ -30,100 +49,163 @@ pub trait DynamicAccumulator: StaticAccumulator {
 }
 
 pub trait UniversalAccumulator: DynamicAccumulator {
+    /// Witness proving non-membership of a single element.
+    type NonMemWitness;
+
     /// Create a non-membership proof.
-    /// Returns `None`, iff `x` is a member.
-    fn non_mem_wit_create(&self, x: &BigUint) -> (BigUint, BigInt);
+    /// Panics if `x` is a member.
+    fn non_mem_wit_create(&self, x: &BigUint) -> Self::NonMemWitness;
 
     /// Verify a non-membership proof.
-    fn ver_non_mem(&self, w: &(BigUint, BigInt), x: &BigUint) -> bool;
+    fn ver_non_mem(&self, w: &Self::NonMemWitness, x: &BigUint) -> bool;
 }
 
-pub trait BatchedAccumulator: StaticAccumulator {
+/// Batched state updates, and creation of witnesses for many elements at once.
+pub trait BatchUpdateAccumulator: StaticAccumulator {
+    /// Proof that a batch was added or deleted correctly.
+    type BatchProof;
+
     /// Batch add.
     /// Given a list of new elements, adds them.
-    fn batch_add(&mut self, xs: &[BigUint]) -> BigUint;
+    fn batch_add(&mut self, xs: &[BigUint]) -> Self::BatchProof;
 
     /// Batch delete.
     /// Given a list of witnesses and members, deletes all of them.
-    fn batch_del(&mut self, pairs: &[(BigUint, BigUint)]) -> Option<BigUint>;
+    fn batch_del(&mut self, pairs: &[(BigUint, Self::MemWitness)]) -> Option<Self::BatchProof>;
 
     /// Delete with member witness.
     /// Deletes a single element, given the element and a wittness for it.
     /// Returns `None` if the element was not actual a member.
-    fn del_w_mem(&mut self, w: &BigUint, x: &BigUint) -> Option<()>;
+    fn del_w_mem(&mut self, w: &Self::MemWitness, x: &BigUint) -> Option<()>;
 
     /// Create membership witnesses for all elements in `s`.
     /// Needs to be passed in, as we don't hold onto the whole set in the accumulator currently.
-    fn create_all_mem_wit(&self, s: &[BigUint]) -> Vec<BigUint>;
+    ///
+    /// The `i`-th witness belongs to `s[i]`, so the result must not be used with a reordered or
+    /// deduplicated copy of `s`. Prefer `create_all_mem_wit_map`, which keys the witnesses by element.
+    fn create_all_mem_wit(&self, s: &[BigUint]) -> Vec<Self::MemWitness>;
+
+    /// Extend the witnesses from `create_all_mem_wit` to `new_elements`, added to the set.
+    /// The existing witnesses are updated in place, and the ones for `new_elements` appended,
+    /// resulting in the same witnesses as `create_all_mem_wit` over the extended set.
+    fn extend_all_mem_wit(
+        &self,
+        existing: &mut Vec<(BigUint, Self::MemWitness)>,
+        new_elements: &[BigUint],
+    );
+
+    /// Create membership witnesses for all elements in `s`, keyed by element.
+    fn create_all_mem_wit_map(&self, s: &[BigUint]) -> HashMap<BigUint, Self::MemWitness> {
+        s.iter().cloned().zip(self.create_all_mem_wit(s)).collect()
+    }
 
     /// Verify Batch Add.
     /// Given the proof `w` from [batch_add] and the list of members `xs`,
     /// and the previous state of the accumulator `a_t` this verifies if the `add` was done correctly.
     ///
     /// Note: This is not explicitly defined in the paper, but here for convenience.
-    fn ver_batch_add(&self, w: &BigUint, a_t: &BigUint, xs: &[BigUint]) -> bool;
+    #[deprecated(note = "use `ver_batch_add_transition` instead, with both states explicit")]
+    fn ver_batch_add(&self, w: &Self::BatchProof, a_t: &BigUint, xs: &[BigUint]) -> bool;
+
+    /// Verifies that `xs` were added to the state `old`, resulting in `new`, given the proof
+    /// `w` from [batch_add].
+    fn ver_batch_add_transition(
+        &self,
+        w: &Self::BatchProof,
+        old: &OldState,
+        new: &NewState,
+        xs: &[BigUint],
+    ) -> bool;
 
     /// Verify Batch Del
     /// Given the proof `w` from [batch_del] and the list of members `xs`,
     /// and the previous state of the accumulator `a_t` this verifies if the `del` was done correctly.
     ///
     /// Note: This is not explicitly defined in the paper, but here for convenience.
-    fn ver_batch_del(&self, w: &BigUint, a_t: &BigUint, xs: &[BigUint]) -> bool;
+    #[deprecated(note = "use `ver_batch_del_transition` instead, with both states explicit")]
+    fn ver_batch_del(&self, w: &Self::BatchProof, a_t: &BigUint, xs: &[BigUint]) -> bool;
+
+    /// Verifies that `xs` were deleted from the state `old`, resulting in `new`, given the
+    /// proof `w` from [batch_del].
+    fn ver_batch_del_transition(
+        &self,
+        w: &Self::BatchProof,
+        old: &OldState,
+        new: &NewState,
+        xs: &[BigUint],
+    ) -> bool;
+}
+
+/// Aggregation of witnesses, and constant size proofs for them.
+pub trait AggregatableAccumulator: StaticAccumulator {
+    /// Membership witness together with a proof for it.
+    type MemProof;
+
+    /// Constant size non-membership proof.
+    type NonMemProof;
 
     /// Aggregate two membership wittnesses, from the same accumulator.
     fn agg_mem_wit(
         &self,
-        w_x: &BigUint,
-        w_y: &BigUint,
+        w_x: &Self::MemWitness,
+        w_y: &Self::MemWitness,
         x: &BigUint,
         y: &BigUint,
-    ) -> (BigUint, BigUint);
+    ) -> Self::MemProof;
 
     /// Verify an aggregated membership wittness.
-    fn ver_agg_mem_wit(&self, w_xy: &BigUint, pi: &BigUint, x: &BigUint, y: &BigUint) -> bool;
+    fn ver_agg_mem_wit(&self, pi: &Self::MemProof, x: &BigUint, y: &BigUint) -> bool;
 
     /// Create a membership wittness for `x` and a NI-PoE for it.
-    fn mem_wit_create_star(&self, x: &BigUint) -> (BigUint, BigUint);
+    /// Panics if `x` is not a member.
+    fn mem_wit_create_star(&self, x: &BigUint) -> Self::MemProof;
 
     /// Verify a membership wittness with a NI-PoE.
-    fn ver_mem_star(&self, x: &BigUint, pi: &(BigUint, BigUint)) -> bool;
+    fn ver_mem_star(&self, x: &BigUint, pi: &Self::MemProof) -> bool;
 
     /// Aggregate two membership witness, from different accumulators.
     fn mem_wit_x(
         &self,
         other: &BigUint,
-        w_x: &BigUint,
-        w_y: &BigUint,
+        w_x: &Self::MemWitness,
+        w_y: &Self::MemWitness,
         x: &BigUint,
         y: &BigUint,
-    ) -> BigUint;
+    ) -> Self::MemWitness;
 
     /// Verify aggregated membership witness.
-    fn ver_mem_x(&self, other: &BigUint, pi: &BigUint, x: &BigUint, y: &BigUint) -> bool;
+    fn ver_mem_x(&self, other: &BigUint, pi: &Self::MemWitness, x: &BigUint, y: &BigUint) -> bool;
 
     /// Efficient non membership proof.
-    fn non_mem_wit_create_star(
-        &self,
-        x: &BigUint,
-    ) -> (BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint);
+    /// Panics if `x` is a member.
+    fn non_mem_wit_create_star(&self, x: &BigUint) -> Self::NonMemProof;
 
     /// Verify non membership proof.
-    fn ver_non_mem_star(
-        &self,
-        x: &BigUint,
-        pi: &(BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint),
-    ) -> bool;
+    fn ver_non_mem_star(&self, x: &BigUint, pi: &Self::NonMemProof) -> bool;
 }
 
-pub trait StaticVectorCommitment {
-    type Domain;
-    type Commitment;
-    type BatchCommitment;
+/// Batched updates together with aggregation, implemented for every accumulator providing both.
+#[deprecated(note = "use `BatchUpdateAccumulator` and `AggregatableAccumulator` instead")]
+pub trait BatchedAccumulator: BatchUpdateAccumulator + AggregatableAccumulator {}
+
+#[allow(deprecated)]
+impl<A: BatchUpdateAccumulator + AggregatableAccumulator + ?Sized> BatchedAccumulator for A {}
 
+/// Construction of a vector commitment.
+///
+/// This is kept separate from the other vector commitment traits, so that those stay object safe.
+#[cfg(feature = "prover")]
+pub trait VectorCommitmentSetup: Sized {
     fn setup<T, R>(rng: &mut R, int_size_bits: usize, n: usize) -> Self
     where
         T: PrimeGroup,
         R: CryptoRng + Rng;
+}
+
+pub trait StaticVectorCommitment {
+    type Domain;
+    type Commitment;
+    type BatchCommitment;
 
     fn commit(&mut self, m: &[Self::Domain]);
 
@@ -145,11 +227,15 @@ pub trait DynamicVectorCommitment: StaticVectorCommitment {
 
 /// This trait abstracts the Group of unknown order that is used to sample our primes
 /// RSA or Class groups of imaginary quadratic order
+#[cfg(feature = "prover")]
 pub trait PrimeGroup {
     /// Generates the Prime elements from the group that is used
-    /// Returns first the prime and second the generator used
+    /// Returns the modulus and the generator used, and the secret primes if known
+    ///
+    /// The modulus has exactly `int_size_bits` bits. Fails if that is below the minimum of
+    /// the group, e.g. `RSAGroup::MIN_MODULUS_BITS`.
     fn generate_primes<R: Rng + CryptoRng>(
         rng: &mut R,
         int_size_bits: usize,
-    ) -> Result<(BigUint, BigUint), Error>;
+    ) -> Result<RsaSetup, Error>;
 }
@@ -0,0 +1,123 @@
+//! Fiat-Shamir transcripts, for deriving the challenges of several proofs from one session.
+//!
+//! The `Transcript` trait has the same shape as `merlin::Transcript`, which implements it with
+//! the `merlin` feature, so the challenges can come from the session transcript of a protocol
+//! composed with other proofs. `Blake2bTranscript` is a self-contained implementation.
+//!
+//! The standalone proofs, like `proofs::ni_poe_prove`, are not wrappers over a fresh
+//! transcript either. They keep their own challenge derivation, so existing proofs and
+//! fixtures stay valid.
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, WriteBytesExt};
+
+/// A transcript of the messages of a protocol, from which the challenges are derived.
+pub trait Transcript {
+    /// Absorbs `message`, under `label`.
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]);
+
+    /// Fills `dest` with a challenge derived from all messages so far, under `label`.
+    ///
+    /// The challenge is absorbed as well, so later challenges differ.
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]);
+}
+
+/// A transcript that hashes all messages with Blake2b.
+///
+/// Labels and messages are absorbed with their lengths, so different sequences of messages
+/// never hash the same.
+#[derive(Debug, Clone)]
+pub struct Blake2bTranscript {
+    hasher: Blake2b,
+}
+
+impl Blake2bTranscript {
+    /// Starts a transcript for the protocol `label`.
+    pub fn new(label: &'static [u8]) -> Self {
+        let mut t = Blake2bTranscript {
+            hasher: Blake2b::new(),
+        };
+        t.append_message(b"dom-sep", label);
+
+        t
+    }
+}
+
+impl Transcript for Blake2bTranscript {
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        let mut lens = Vec::with_capacity(12);
+        lens.write_u32::<BigEndian>(label.len() as u32).unwrap();
+        lens.write_u64::<BigEndian>(message.len() as u64).unwrap();
+
+        self.hasher.input(&lens);
+        self.hasher.input(label);
+        self.hasher.input(message);
+    }
+
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        let mut len = Vec::with_capacity(8);
+        len.write_u64::<BigEndian>(dest.len() as u64).unwrap();
+        self.append_message(label, &len);
+
+        let seed = self.hasher.clone().result();
+        for (i, chunk) in dest.chunks_mut(64).enumerate() {
+            let mut block = Blake2b::new();
+            block.input(seed.as_slice());
+            block.input((i as u32).to_be_bytes());
+            chunk.copy_from_slice(&block.result()[..chunk.len()]);
+        }
+
+        self.hasher.input(seed);
+    }
+}
+
+#[cfg(feature = "merlin")]
+impl Transcript for merlin::Transcript {
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        merlin::Transcript::append_message(self, label, message)
+    }
+
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        merlin::Transcript::challenge_bytes(self, label, dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenge(t: &mut Blake2bTranscript) -> [u8; 100] {
+        let mut c = [0u8; 100];
+        t.challenge_bytes(b"c", &mut c);
+        c
+    }
+
+    #[test]
+    fn test_blake2b_transcript() {
+        let mut a = Blake2bTranscript::new(b"test");
+        let mut b = a.clone();
+        a.append_message(b"m", b"hello");
+        b.append_message(b"m", b"hello");
+        assert_eq!(challenge(&mut a)[..], challenge(&mut b)[..]);
+
+        // every challenge is new
+        let (c1, c2) = (challenge(&mut a), challenge(&mut a));
+        assert_ne!(c1[..], c2[..]);
+        assert_ne!(c1[..64], c1[64..]);
+
+        // messages are framed, labels and protocols are separated
+        let mut c = Blake2bTranscript::new(b"test");
+        c.append_message(b"m", b"hell");
+        c.append_message(b"m", b"o");
+        let mut d = Blake2bTranscript::new(b"test");
+        d.append_message(b"n", b"hello");
+        let mut e = Blake2bTranscript::new(b"other");
+        e.append_message(b"m", b"hello");
+        let mut f = Blake2bTranscript::new(b"test");
+        f.append_message(b"m", b"hello");
+        let expected = challenge(&mut f);
+        for t in &mut [c, d, e] {
+            assert_ne!(challenge(t)[..], expected[..]);
+        }
+    }
+}
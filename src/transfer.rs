@@ -0,0 +1,302 @@
+//! Moving elements between accumulators, with a single linked proof.
+
+use std::fmt;
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, WriteBytesExt};
+use num_bigint::BigUint;
+
+use crate::accumulator::PublicState;
+#[cfg(feature = "prover")]
+use crate::accumulator::{Accumulator, MembershipWitness};
+use crate::proofs::{self, PoeProof};
+#[cfg(feature = "prover")]
+use crate::traits::*;
+
+/// Proof that an element was moved from one accumulator to another.
+///
+/// Both halves are bound to the same transcript of all states involved, so they can't be
+/// combined with halves of other moves.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MoveProof {
+    /// NI-PoE for the deletion, `new_from^x = old_from`.
+    pub del: PoeProof,
+    /// NI-PoE for the addition, `old_to^x = new_to`.
+    pub add: PoeProof,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    /// The witness for the element does not verify against the source accumulator.
+    InvalidWitness,
+    /// The element is already a member of the target accumulator.
+    AlreadyMember,
+    /// The accumulators use different parameters, see `Accumulator::shares_params`.
+    ParamMismatch,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::InvalidWitness => write!(f, "invalid membership witness"),
+            MoveError::AlreadyMember => write!(f, "the element is already a member of the target"),
+            MoveError::ParamMismatch => write!(f, "the accumulators use different parameters"),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// Moves `x` from `from` to `to`, given its membership witness `w` in `from`. Both must share
+/// their parameters, see `Accumulator::shares_params`.
+///
+/// All checks happen before either accumulator is modified, so on error both are left unchanged.
+#[cfg(feature = "prover")]
+pub fn move_element(
+    from: &mut Accumulator,
+    to: &mut Accumulator,
+    x: &BigUint,
+    w: &MembershipWitness,
+) -> Result<MoveProof, MoveError> {
+    if !from.shares_params(to) {
+        return Err(MoveError::ParamMismatch);
+    }
+    if !from.ver_mem(w, x) {
+        return Err(MoveError::InvalidWitness);
+    }
+    if to.contains(x) {
+        return Err(MoveError::AlreadyMember);
+    }
+
+    let old_from = from.public_state();
+    let old_to = to.public_state();
+
+    from.del_w_mem(w, x).ok_or(MoveError::InvalidWitness)?;
+    to.add(x);
+
+    let new_from = from.public_state();
+    let new_to = to.public_state();
+
+    let (del_context, add_context) = contexts(&old_from, &new_from, &old_to, &new_to, x);
+
+    Ok(MoveProof {
+        del: proofs::ni_poe_prove_with_context(
+            &del_context,
+            x,
+            &new_from.root,
+            &old_from.root,
+            &new_from.n,
+        ),
+        add: proofs::ni_poe_prove_with_context(
+            &add_context,
+            x,
+            &old_to.root,
+            &new_to.root,
+            &new_to.n,
+        ),
+    })
+}
+
+/// Verifies that `x` was moved from the accumulator with state `old_from` (now `new_from`)
+/// to the one with state `old_to` (now `new_to`). All states must have the same parameters.
+pub fn ver_move(
+    old_from: &PublicState,
+    new_from: &PublicState,
+    old_to: &PublicState,
+    new_to: &PublicState,
+    x: &BigUint,
+    pi: &MoveProof,
+) -> bool {
+    // both accumulators must use the same parameters, and keep them
+    let same_params = |s: &PublicState| s.n == old_from.n && s.g == old_from.g;
+    if ![new_from, old_to, new_to].iter().all(|s| same_params(s)) {
+        return false;
+    }
+
+    let (del_context, add_context) = contexts(old_from, new_from, old_to, new_to, x);
+
+    proofs::ni_poe_verify_with_context(
+        &del_context,
+        x,
+        &new_from.root,
+        &old_from.root,
+        &pi.del,
+        &new_from.n,
+    ) && proofs::ni_poe_verify_with_context(
+        &add_context,
+        x,
+        &old_to.root,
+        &new_to.root,
+        &pi.add,
+        &new_to.n,
+    )
+}
+
+/// Derives the contexts for both halves of a move proof from the shared transcript.
+fn contexts(
+    old_from: &PublicState,
+    new_from: &PublicState,
+    old_to: &PublicState,
+    new_to: &PublicState,
+    x: &BigUint,
+) -> (Vec<u8>, Vec<u8>) {
+    let mut transcript = Vec::new();
+    for v in &[
+        &old_from.n,
+        &old_from.g,
+        &old_from.root,
+        &new_from.root,
+        &old_to.n,
+        &old_to.g,
+        &old_to.root,
+        &new_to.root,
+        x,
+    ] {
+        let bytes = v.to_bytes_be();
+        transcript
+            .write_u32::<BigEndian>(bytes.len() as u32)
+            .unwrap();
+        transcript.extend(bytes);
+    }
+
+    let context = |label: &[u8]| {
+        let mut hasher = Blake2b::new();
+        hasher.input(label);
+        hasher.input(&transcript);
+        hasher.result().to_vec()
+    };
+
+    (
+        context(b"accumulators/move/del"),
+        context(b"accumulators/move/add"),
+    )
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn setup(rng: &mut ChaChaRng) -> (Accumulator, Accumulator, Vec<BigUint>) {
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut pending = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let active = Accumulator::new_with_params(pending.params().clone());
+
+        let xs = (0..4).map(|_| rng.gen_prime(128)).collect::<Vec<_>>();
+        for x in &xs {
+            pending.add(x);
+        }
+
+        (pending, active, xs)
+    }
+
+    #[test]
+    fn test_move_element() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (mut pending, mut active, xs) = setup(rng);
+
+        let (old_from, old_to) = (pending.public_state(), active.public_state());
+        let w = pending.mem_wit_create(&xs[0]);
+        let pi = move_element(&mut pending, &mut active, &xs[0], &w).unwrap();
+        let (new_from, new_to) = (pending.public_state(), active.public_state());
+
+        assert!(ver_move(
+            &old_from, &new_from, &old_to, &new_to, &xs[0], &pi
+        ));
+        assert!(!ver_move(
+            &old_from, &new_from, &old_to, &new_to, &xs[1], &pi
+        ));
+        assert!(!ver_move(
+            &new_from, &old_from, &old_to, &new_to, &xs[0], &pi
+        ));
+        assert!(!ver_move(
+            &old_to, &new_to, &old_from, &new_from, &xs[0], &pi
+        ));
+
+        assert!(!pending.ver_mem(&w, &xs[0]));
+        let w = active.mem_wit_create(&xs[0]);
+        assert!(active.ver_mem(&w, &xs[0]));
+
+        // and back again
+        let pi = move_element(&mut active, &mut pending, &xs[0], &w).unwrap();
+        assert!(ver_move(
+            &new_to,
+            &active.public_state(),
+            &new_from,
+            &pending.public_state(),
+            &xs[0],
+            &pi
+        ));
+    }
+
+    #[test]
+    fn test_move_element_invalid() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (mut pending, mut active, xs) = setup(rng);
+        let (from, to) = (pending.clone(), active.clone());
+
+        // witness for another element
+        let w = pending.mem_wit_create(&xs[1]);
+        assert_eq!(
+            move_element(&mut pending, &mut active, &xs[0], &w),
+            Err(MoveError::InvalidWitness)
+        );
+        assert_eq!(pending.public_state(), from.public_state());
+        assert_eq!(active.public_state(), to.public_state());
+
+        // already a member of the target
+        active.add(&xs[0]);
+        let to = active.clone();
+        let w = pending.mem_wit_create(&xs[0]);
+        assert_eq!(
+            move_element(&mut pending, &mut active, &xs[0], &w),
+            Err(MoveError::AlreadyMember)
+        );
+        assert_eq!(pending.public_state(), from.public_state());
+        assert_eq!(active.public_state(), to.public_state());
+    }
+
+    #[test]
+    fn test_mixed_halves() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (mut pending, mut active, xs) = setup(rng);
+
+        let (s0_from, s0_to) = (pending.public_state(), active.public_state());
+        let w = pending.mem_wit_create(&xs[0]);
+        let pi_0 = move_element(&mut pending, &mut active, &xs[0], &w).unwrap();
+        let (s1_from, s1_to) = (pending.public_state(), active.public_state());
+
+        let w = pending.mem_wit_create(&xs[1]);
+        let pi_1 = move_element(&mut pending, &mut active, &xs[1], &w).unwrap();
+        let (s2_from, s2_to) = (pending.public_state(), active.public_state());
+
+        assert!(ver_move(&s0_from, &s1_from, &s0_to, &s1_to, &xs[0], &pi_0));
+        assert!(ver_move(&s1_from, &s2_from, &s1_to, &s2_to, &xs[1], &pi_1));
+
+        // halves of different moves don't combine
+        let mixed = MoveProof {
+            del: pi_0.del.clone(),
+            add: pi_1.add.clone(),
+        };
+        assert!(!ver_move(
+            &s0_from, &s1_from, &s1_to, &s2_to, &xs[0], &mixed
+        ));
+        assert!(!ver_move(
+            &s0_from, &s1_from, &s0_to, &s1_to, &xs[0], &mixed
+        ));
+
+        // nor do the halves of a single move when swapped
+        let swapped = MoveProof {
+            del: pi_0.add.clone(),
+            add: pi_0.del.clone(),
+        };
+        assert!(!ver_move(
+            &s0_from, &s1_from, &s0_to, &s1_to, &xs[0], &swapped
+        ));
+    }
+}
@@ -1,16 +1,21 @@
 use crate::hash::hash_prime;
+use crate::par;
 use crate::traits::*;
 use blake2::Blake2b;
 use byteorder::{BigEndian, ByteOrder};
-use num_bigint::{BigInt, BigUint};
-use num_traits::{One, Zero};
+use num_bigint::BigUint;
+use num_traits::One;
 use rand::CryptoRng;
 use rand::Rng;
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone)]
-pub struct BinaryVectorCommitment<A: UniversalAccumulator + BatchedAccumulator> {
+pub struct BinaryVectorCommitment<
+    A: UniversalAccumulator + BatchUpdateAccumulator + AggregatableAccumulator,
+> {
+    #[allow(dead_code)]
     lambda: usize,
+    #[allow(dead_code)]
     n: usize,
     acc: A,
     pos: usize,
@@ -18,27 +23,24 @@ pub struct BinaryVectorCommitment<A: UniversalAccumulator + BatchedAccumulator>
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Commitment {
-    Mem(BigUint),
-    NonMem((BigUint, BigInt)),
+pub enum Commitment<M, N> {
+    Mem(M),
+    NonMem(N),
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct BatchCommitment(
-    // membership proof
-    (BigUint, BigUint),
-    // non membership proof
-    (BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint),
+pub struct BatchCommitment<P, Q>(
+    // membership proof, `None` if no ones were opened
+    Option<P>,
+    // non membership proof, `None` if no zeros were opened
+    Option<Q>,
 );
 
-impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment
-    for BinaryVectorCommitment<A>
+impl<
+        A: UniversalAccumulator + BatchUpdateAccumulator + AggregatableAccumulator + AccumulatorSetup,
+    > VectorCommitmentSetup for BinaryVectorCommitment<A>
 {
-    type Domain = bool;
-    type Commitment = Commitment;
-    type BatchCommitment = BatchCommitment;
-
     fn setup<G, R>(rng: &mut R, lambda: usize, n: usize) -> Self
     where
         G: PrimeGroup,
@@ -51,14 +53,23 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment
             pos: 0,
         }
     }
+}
+
+impl<A: UniversalAccumulator + BatchUpdateAccumulator + AggregatableAccumulator>
+    StaticVectorCommitment for BinaryVectorCommitment<A>
+{
+    type Domain = bool;
+    type Commitment = Commitment<A::MemWitness, A::NonMemWitness>;
+    type BatchCommitment = BatchCommitment<A::MemProof, A::NonMemProof>;
 
     fn commit(&mut self, m: &[Self::Domain]) {
-        let primes = m
+        let positions = m
             .iter()
             .enumerate()
             .filter(|(_, &m_i)| m_i)
-            .map(|(i, _)| map_i_to_p_i(self.pos + i))
+            .map(|(i, _)| self.pos + i)
             .collect::<Vec<_>>();
+        let primes = par::map(&positions, |&i| map_i_to_p_i(i));
 
         self.pos += m.len();
         self.acc.batch_add(&primes);
@@ -86,7 +97,7 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment
         } else {
             match pi {
                 Commitment::Mem(_) => false,
-                Commitment::NonMem(v) => self.acc.ver_non_mem(&v, &p_i),
+                Commitment::NonMem(v) => self.acc.ver_non_mem(v, &p_i),
             }
         }
     }
@@ -112,9 +123,9 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment
         }
 
         let pi_i = if p_ones.is_one() {
-            (BigUint::zero(), BigUint::zero())
+            None
         } else {
-            self.acc.mem_wit_create_star(&p_ones)
+            Some(self.acc.mem_wit_create_star(&p_ones))
         };
 
         let mut p_zeros = BigUint::one();
@@ -123,14 +134,9 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment
         }
 
         let pi_e = if p_zeros.is_one() {
-            (
-                BigUint::zero(),
-                BigUint::zero(),
-                (BigUint::zero(), BigUint::zero(), BigInt::zero()),
-                BigUint::zero(),
-            )
+            None
         } else {
-            self.acc.non_mem_wit_create_star(&p_zeros)
+            Some(self.acc.non_mem_wit_create_star(&p_zeros))
         };
 
         BatchCommitment(pi_i, pi_e)
@@ -150,8 +156,11 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment
             p_ones *= map_i_to_p_i(i[j]);
         }
 
-        if !p_ones.is_one() && !self.acc.ver_mem_star(&p_ones, &pi.0) {
-            return false;
+        if !p_ones.is_one() {
+            match &pi.0 {
+                Some(pi_i) if self.acc.ver_mem_star(&p_ones, pi_i) => {}
+                _ => return false,
+            }
         }
 
         let zeros = b
@@ -165,8 +174,11 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment
             p_zeros *= map_i_to_p_i(i[j]);
         }
 
-        if !p_zeros.is_one() && !self.acc.ver_non_mem_star(&p_zeros, &pi.1) {
-            return false;
+        if !p_zeros.is_one() {
+            match &pi.1 {
+                Some(pi_e) if self.acc.ver_non_mem_star(&p_zeros, pi_e) => {}
+                _ => return false,
+            }
         }
 
         true
@@ -177,8 +189,8 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment
     }
 }
 
-impl<A: UniversalAccumulator + BatchedAccumulator> DynamicVectorCommitment
-    for BinaryVectorCommitment<A>
+impl<A: UniversalAccumulator + BatchUpdateAccumulator + AggregatableAccumulator>
+    DynamicVectorCommitment for BinaryVectorCommitment<A>
 {
     fn update(&mut self, b: &Self::Domain, b_prime: &Self::Domain, i: usize) {
         if b == b_prime {
@@ -194,7 +206,7 @@ impl<A: UniversalAccumulator + BatchedAccumulator> DynamicVectorCommitment
 fn map_i_to_p_i(i: usize) -> BigUint {
     let mut to_hash = [0u8; 8];
     BigEndian::write_u64(&mut to_hash, i as u64);
-    hash_prime::<_, Blake2b>(&to_hash)
+    hash_prime::<_, Blake2b>(&to_hash).expect("no prime found")
 }
 
 #[cfg(test)]
@@ -245,7 +257,7 @@ mod tests {
         let val: Vec<bool> = (0..64).map(|_| rng.gen()).collect();
         vc.commit(&val);
 
-        let committed = vec![val[2].clone(), val[3].clone(), val[9].clone()];
+        let committed = vec![val[2], val[3], val[9]];
         let comm = vc.batch_open(&committed, &[2, 3, 9]);
         assert!(
             vc.batch_verify(&committed, &[2, 3, 9], &comm),
@@ -7,7 +7,10 @@ use rand::{CryptoRng, Rng};
 use crate::traits::*;
 use crate::vc::BinaryVectorCommitment;
 
-pub fn create_vector_commitment<A: UniversalAccumulator + BatchedAccumulator, G: PrimeGroup>(
+pub fn create_vector_commitment<
+    A: UniversalAccumulator + BatchUpdateAccumulator + AggregatableAccumulator + AccumulatorSetup,
+    G: PrimeGroup,
+>(
     lambda: usize,
     n: usize,
 ) -> VectorCommitment<A> {
@@ -17,17 +20,19 @@ pub fn create_vector_commitment<A: UniversalAccumulator + BatchedAccumulator, G:
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone)]
-pub struct VectorCommitment<A: UniversalAccumulator + BatchedAccumulator> {
+pub struct VectorCommitment<
+    A: UniversalAccumulator + BatchUpdateAccumulator + AggregatableAccumulator,
+> {
     lambda: usize,
+    #[allow(dead_code)]
     n: usize,
     vc: BinaryVectorCommitment<A>,
 }
 
-impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment for VectorCommitment<A> {
-    type Domain = BigUint;
-    type Commitment = <BinaryVectorCommitment<A> as StaticVectorCommitment>::BatchCommitment;
-    type BatchCommitment = <BinaryVectorCommitment<A> as StaticVectorCommitment>::BatchCommitment;
-
+impl<
+        A: UniversalAccumulator + BatchUpdateAccumulator + AggregatableAccumulator + AccumulatorSetup,
+    > VectorCommitmentSetup for VectorCommitment<A>
+{
     fn setup<G, R>(rng: &mut R, lambda: usize, n: usize) -> Self
     where
         G: PrimeGroup,
@@ -39,6 +44,14 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment for Ve
             vc: BinaryVectorCommitment::<A>::setup::<G, _>(rng, lambda, n),
         }
     }
+}
+
+impl<A: UniversalAccumulator + BatchUpdateAccumulator + AggregatableAccumulator>
+    StaticVectorCommitment for VectorCommitment<A>
+{
+    type Domain = BigUint;
+    type Commitment = <BinaryVectorCommitment<A> as StaticVectorCommitment>::BatchCommitment;
+    type BatchCommitment = <BinaryVectorCommitment<A> as StaticVectorCommitment>::BatchCommitment;
 
     // Internally we map the incoming integers onto a binary vc in the following way
     // ms: [a, b, c]
@@ -46,7 +59,7 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment for Ve
     // vc[a'..., b'..., c'...]
     fn commit(&mut self, ms: &[Self::Domain]) {
         for m in ms {
-            let comm = hash_binary(&m, self.lambda).into_iter().collect::<Vec<_>>();
+            let comm = hash_binary(m, self.lambda).into_iter().collect::<Vec<_>>();
             debug_assert!(comm.len() == self.lambda);
             self.vc.commit(&comm);
         }
@@ -105,7 +118,9 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment for Ve
     }
 }
 
-impl<A: UniversalAccumulator + BatchedAccumulator> DynamicVectorCommitment for VectorCommitment<A> {
+impl<A: UniversalAccumulator + BatchUpdateAccumulator + AggregatableAccumulator>
+    DynamicVectorCommitment for VectorCommitment<A>
+{
     fn update(&mut self, b: &Self::Domain, b_prime: &Self::Domain, i: usize) {
         if b == b_prime {
             // Nothing to do
@@ -150,9 +165,9 @@ mod tests {
         let val: Vec<BigUint> = (0..3).map(|_| rng.gen_biguint(16)).collect();
         vc.commit(&val);
 
-        for i in 0..3 {
-            let comm = vc.open(&val[i], i);
-            assert!(vc.verify(&val[i], i, &comm), "invalid commitment {}", i);
+        for (i, v) in val.iter().enumerate() {
+            let comm = vc.open(v, i);
+            assert!(vc.verify(v, i, &comm), "invalid commitment {}", i);
         }
     }
 
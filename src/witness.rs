@@ -0,0 +1,830 @@
+//! Bookkeeping for issued membership witnesses.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::One;
+
+use crate::accumulator::{Accumulator, BatchProof, MembershipWitness};
+use crate::math::{product, shamir_trick};
+use crate::traits::*;
+use crate::witness_io::{self, BatchIoError, HashingReader, HashingWriter, MAX_WIDTH};
+
+const MANAGER_MAGIC: &[u8; 4] = b"ACWM";
+const MANAGER_VERSION: u8 = 1;
+
+/// Largest product of pending updates accepted by `WitnessManager::load`, in bytes.
+const MAX_PENDING_BYTES: usize = 1 << 24;
+
+/// Events surfaced by the `WitnessManager` when tracked elements change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WitnessEvent {
+    /// The element was deleted from the accumulator and its witness dropped.
+    Dropped(BigUint),
+}
+
+/// Tracks membership witnesses for registered elements and keeps them valid
+/// across mutations of the accumulator.
+///
+/// Mutations can either be driven through the manager (`add`, `del`, ...) or
+/// reported after the fact through `observe_add` and `observe_del`. Each observed
+/// mutation is applied to all witnesses right away, with a single exponentiation
+/// per witness for a whole batch, so that `witness_for` only needs a shared borrow.
+#[derive(Debug, Clone)]
+pub struct WitnessManager {
+    /// Modulus of the tracked accumulator.
+    n: BigUint,
+
+    /// Latest observed accumulator state.
+    root: BigUint,
+
+    /// Registered elements and their witnesses, valid for the state before any pending updates.
+    witnesses: HashMap<BigUint, MembershipWitness>,
+
+    /// Product of the elements added since the last refresh.
+    added: BigUint,
+
+    /// Product of the elements deleted since the last refresh.
+    deleted: BigUint,
+
+    events: Vec<WitnessEvent>,
+
+    /// State of the accumulator when the manager was loaded, if the witnesses are behind it.
+    target: Option<BigUint>,
+}
+
+/// Errors from `WitnessManager::load`.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The saved data is corrupt or truncated.
+    Format(BatchIoError),
+    /// The witnesses were saved for an accumulator with a different modulus.
+    ModulusMismatch,
+    /// A saved witness is not smaller than the modulus.
+    InvalidWitness(BigUint),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Format(err) => write!(f, "{}", err),
+            LoadError::ModulusMismatch => write!(f, "saved for a different modulus"),
+            LoadError::InvalidWitness(x) => write!(f, "invalid witness for element {}", x),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<BatchIoError> for LoadError {
+    fn from(err: BatchIoError) -> Self {
+        LoadError::Format(err)
+    }
+}
+
+impl WitnessManager {
+    /// Creates a manager following the given accumulator, without any registered elements.
+    pub fn new(acc: &Accumulator) -> Self {
+        WitnessManager {
+            n: acc.modulus().clone(),
+            root: acc.state().clone(),
+            witnesses: HashMap::new(),
+            added: BigUint::one(),
+            deleted: BigUint::one(),
+            events: Vec::new(),
+            target: None,
+        }
+    }
+
+    /// Writes the tracked witnesses, the state they belong to and any pending updates to `w`.
+    ///
+    /// The manager is written as a header with the modulus, the state and the pending
+    /// updates, followed by the witnesses in the format of `witness_io::write_batch`. Both
+    /// parts end in a checksum.
+    pub fn save<W: Write>(&self, w: W) -> Result<(), BatchIoError> {
+        let mut out = HashingWriter::new(BufWriter::new(w));
+        out.write_all(MANAGER_MAGIC)?;
+        out.write_all(&[MANAGER_VERSION])?;
+        for x in &[&self.n, &self.root, &self.added, &self.deleted] {
+            out.write_biguint(x)?;
+        }
+        let mut out = out.finish()?;
+
+        let items = self
+            .witnesses
+            .iter()
+            .map(|(x, w)| (x.clone(), w.0.clone()))
+            .collect::<Vec<_>>();
+        witness_io::write_batch(&mut out, &items)?;
+        out.flush()?;
+
+        Ok(())
+    }
+
+    /// Reads a manager written by `save`, to follow `acc` from now on.
+    ///
+    /// If `acc` has changed since the manager was saved, the witnesses are not trusted: the
+    /// manager is stale until the missed updates are replayed with `observe_add` and
+    /// `observe_del`, or the witnesses are recomputed with `resync`. Stale managers don't hand
+    /// out witnesses.
+    pub fn load<R: Read>(r: R, acc: &Accumulator) -> Result<Self, LoadError> {
+        let mut r = BufReader::new(r);
+
+        let mut input = HashingReader::new(&mut r, 0);
+        let mut magic = [0u8; 5];
+        input.read_exact(&mut magic)?;
+        if &magic[..4] != MANAGER_MAGIC {
+            return Err(BatchIoError::BadMagic.into());
+        }
+        if magic[4] != MANAGER_VERSION {
+            return Err(BatchIoError::UnsupportedVersion(magic[4]).into());
+        }
+        let n = input.read_biguint(MAX_WIDTH)?;
+        let root = input.read_biguint(MAX_WIDTH)?;
+        let added = input.read_biguint(MAX_PENDING_BYTES)?;
+        let deleted = input.read_biguint(MAX_PENDING_BYTES)?;
+        input.check_checksum()?;
+        let offset = input.position();
+
+        if &n != acc.modulus() {
+            return Err(LoadError::ModulusMismatch);
+        }
+
+        let mut witnesses = HashMap::new();
+        for (x, w) in witness_io::read_batch_at(r, offset)? {
+            if w >= n {
+                return Err(LoadError::InvalidWitness(x));
+            }
+            witnesses.insert(x, MembershipWitness(w));
+        }
+
+        let target = if &root != acc.state() {
+            Some(acc.state().clone())
+        } else {
+            None
+        };
+
+        let mut manager = WitnessManager {
+            n,
+            root,
+            witnesses,
+            added,
+            deleted,
+            events: Vec::new(),
+            target,
+        };
+        manager.refresh_all();
+
+        Ok(manager)
+    }
+
+    /// Returns `true` if the manager was loaded behind its accumulator, and the missed
+    /// updates have not been replayed yet.
+    pub fn is_stale(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// Recomputes the witnesses of all tracked elements from `acc`, and follows `acc` from
+    /// now on.
+    ///
+    /// Elements that are no longer members are dropped, with a `WitnessEvent::Dropped` each.
+    pub fn resync(&mut self, acc: &Accumulator) {
+        let mut witnesses = HashMap::with_capacity(self.witnesses.len());
+        for x in self.witnesses.keys() {
+            match acc.try_mem_wit_create(x) {
+                Ok(w) => {
+                    witnesses.insert(x.clone(), w);
+                }
+                Err(_) => self.events.push(WitnessEvent::Dropped(x.clone())),
+            }
+        }
+
+        self.witnesses = witnesses;
+        self.root = acc.state().clone();
+        self.added = BigUint::one();
+        self.deleted = BigUint::one();
+        self.target = None;
+    }
+
+    /// Starts tracking the witness for `x`, which must be a member of `acc`.
+    pub fn register(&mut self, acc: &Accumulator, x: &BigUint) {
+        debug_assert!(acc.state() == &self.root, "manager is out of sync");
+
+        self.witnesses.insert(x.clone(), acc.mem_wit_create(x));
+    }
+
+    /// Stops tracking the witness for `x`.
+    pub fn unregister(&mut self, x: &BigUint) -> Option<MembershipWitness> {
+        self.witnesses.remove(x)
+    }
+
+    /// Returns `true` if the witness for `x` is tracked.
+    pub fn is_tracked(&self, x: &BigUint) -> bool {
+        self.witnesses.contains_key(x)
+    }
+
+    /// Returns the number of tracked witnesses.
+    pub fn len(&self) -> usize {
+        self.witnesses.len()
+    }
+
+    /// Returns `true` if no witnesses are tracked.
+    pub fn is_empty(&self) -> bool {
+        self.witnesses.is_empty()
+    }
+
+    /// Returns the witness for `x`, valid for the latest observed state.
+    ///
+    /// Returns `None` if `x` is not tracked, or while the manager is stale, see `load`.
+    pub fn witness_for(&self, x: &BigUint) -> Option<&MembershipWitness> {
+        if self.is_stale() {
+            return None;
+        }
+        self.witnesses.get(x)
+    }
+
+    /// Returns and clears the events collected so far.
+    pub fn drain_events(&mut self) -> Vec<WitnessEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Records that `xs` were added, resulting in `new_root`.
+    pub fn observe_add(&mut self, xs: &[BigUint], new_root: &BigUint) {
+        for x in xs {
+            self.added *= x;
+        }
+        self.observe_root(new_root);
+        self.refresh_all();
+    }
+
+    /// Records that `xs` were deleted, resulting in `new_root`.
+    pub fn observe_del(&mut self, xs: &[BigUint], new_root: &BigUint) {
+        for x in xs {
+            self.deleted *= x;
+
+            if self.witnesses.remove(x).is_some() {
+                self.events.push(WitnessEvent::Dropped(x.clone()));
+            }
+        }
+        self.observe_root(new_root);
+        self.refresh_all();
+    }
+
+    /// Moves to `new_root`, which ends the staleness once it reaches the loaded state.
+    fn observe_root(&mut self, new_root: &BigUint) {
+        self.root = new_root.clone();
+        if self.target.as_ref() == Some(new_root) {
+            self.target = None;
+        }
+    }
+
+    /// Adds `x` to `acc`.
+    pub fn add(&mut self, acc: &mut Accumulator, x: &BigUint) {
+        acc.add(x);
+        self.observe_add(std::slice::from_ref(x), acc.state());
+    }
+
+    /// Batch adds `xs` to `acc`, returning the proof from `batch_add`.
+    pub fn batch_add(&mut self, acc: &mut Accumulator, xs: &[BigUint]) -> BatchProof {
+        let proof = acc.batch_add(xs);
+        self.observe_add(xs, acc.state());
+
+        proof
+    }
+
+    /// Deletes `x` from `acc`.
+    pub fn del(&mut self, acc: &mut Accumulator, x: &BigUint) -> Option<()> {
+        acc.del(x)?;
+        self.observe_del(std::slice::from_ref(x), acc.state());
+
+        Some(())
+    }
+
+    /// Deletes `x` from `acc`, using the membership witness `w`.
+    pub fn del_w_mem(
+        &mut self,
+        acc: &mut Accumulator,
+        w: &MembershipWitness,
+        x: &BigUint,
+    ) -> Option<()> {
+        acc.del_w_mem(w, x)?;
+        self.observe_del(std::slice::from_ref(x), acc.state());
+
+        Some(())
+    }
+
+    /// Batch deletes the `(element, witness)` pairs from `acc`, returning the proof from `batch_del`.
+    pub fn batch_del(
+        &mut self,
+        acc: &mut Accumulator,
+        pairs: &[(BigUint, MembershipWitness)],
+    ) -> Option<BatchProof> {
+        let proof = acc.batch_del(pairs)?;
+        let xs = pairs.iter().map(|(x, _)| x.clone()).collect::<Vec<_>>();
+        self.observe_del(&xs, acc.state());
+
+        Some(proof)
+    }
+
+    /// Applies all pending updates to the tracked witnesses.
+    ///
+    /// `observe_add`, `observe_del` and `load` already do, so that witnesses are never behind
+    /// the latest observed state.
+    pub fn refresh_all(&mut self) {
+        if self.added.is_one() && self.deleted.is_one() {
+            return;
+        }
+
+        // elements that were added and deleted again since the last refresh cancel out
+        let common = self.added.gcd(&self.deleted);
+        let added = &self.added / &common;
+        let deleted = &self.deleted / &common;
+
+        // w^added is an x-th root of A_old^added, which equals A_new^deleted,
+        // so the shamir trick gives us the x-th root of A_new.
+        for (x, w) in self.witnesses.iter_mut() {
+            let mut w_new = w.0.modpow(&added, &self.n);
+            if !deleted.is_one() {
+                w_new = shamir_trick(&w_new, &self.root, x, &deleted, &self.n)
+                    .expect("invalid witness state");
+            }
+            w.0 = w_new;
+        }
+
+        self.added = BigUint::one();
+        self.deleted = BigUint::one();
+    }
+}
+
+/// A mutation recorded by the `WitnessCache`, with the product of the affected elements.
+#[derive(Debug, Clone)]
+enum Update {
+    Add(BigUint),
+    Del(BigUint),
+}
+
+/// Memoized membership witnesses of an `Accumulator`, see `Accumulator::with_witness_cache`.
+///
+/// Each witness is stored with the version of the state it was created for. A hit for an older
+/// version is repaired with the updates recorded since, which is much cheaper than recomputing
+/// the witness from the full set.
+#[derive(Debug, Clone)]
+pub(crate) struct WitnessCache {
+    /// Maximum number of cached witnesses.
+    capacity: usize,
+
+    /// Cached witnesses and the version they are valid for.
+    entries: HashMap<BigUint, (MembershipWitness, usize)>,
+
+    /// Updates since version `base`, in order.
+    journal: Vec<Update>,
+
+    /// Version of the state before the first update in the journal.
+    base: usize,
+}
+
+impl WitnessCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        WitnessCache {
+            capacity,
+            entries: HashMap::new(),
+            journal: Vec::new(),
+            base: 0,
+        }
+    }
+
+    /// Returns the number of cached witnesses.
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Version of the current state.
+    fn version(&self) -> usize {
+        self.base + self.journal.len()
+    }
+
+    /// Records that `x_star`, the product of the new elements, was added.
+    pub(crate) fn added(&mut self, x_star: &BigUint) {
+        if !self.entries.is_empty() {
+            self.journal.push(Update::Add(x_star.clone()));
+        }
+    }
+
+    /// Records that `xs` were deleted, dropping their own witnesses.
+    pub(crate) fn deleted(&mut self, xs: &[BigUint]) {
+        for x in xs {
+            self.entries.remove(x);
+        }
+        if !self.entries.is_empty() {
+            self.journal.push(Update::Del(product(xs)));
+        }
+        self.trim();
+    }
+
+    /// Returns the witness for `x`, valid for the current `root`.
+    pub(crate) fn get(
+        &mut self,
+        x: &BigUint,
+        root: &BigUint,
+        n: &BigUint,
+    ) -> Option<MembershipWitness> {
+        let version = self.version();
+        let (w, v) = self.entries.get(x)?;
+        if *v == version {
+            return Some(w.clone());
+        }
+
+        let mut added = BigUint::one();
+        let mut deleted = BigUint::one();
+        for update in &self.journal[v - self.base..] {
+            match update {
+                Update::Add(y) => added *= y,
+                Update::Del(y) => deleted *= y,
+            }
+        }
+
+        // same as in `WitnessManager::refresh_all`
+        let common = added.gcd(&deleted);
+        let added = added / &common;
+        let deleted = deleted / &common;
+
+        let mut w_new = w.0.modpow(&added, n);
+        if !deleted.is_one() {
+            let repaired = if x.gcd(&deleted).is_one() {
+                shamir_trick(&w_new, root, x, &deleted, n)
+            } else {
+                None
+            };
+            match repaired {
+                Some(w) => w_new = w,
+                None => {
+                    self.entries.remove(x);
+                    self.trim();
+                    return None;
+                }
+            }
+        }
+
+        let w = MembershipWitness(w_new);
+        self.entries.insert(x.clone(), (w.clone(), version));
+        self.trim();
+
+        Some(w)
+    }
+
+    /// Caches `w` as the witness for `x` in the current state, evicting the stalest
+    /// witness if the cache is full.
+    pub(crate) fn insert(&mut self, x: BigUint, w: MembershipWitness) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&x) {
+            let stalest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, v))| *v)
+                .map(|(y, _)| y.clone())
+                .expect("capacity is not zero");
+            self.entries.remove(&stalest);
+        }
+
+        let version = self.version();
+        self.entries.insert(x, (w, version));
+        self.trim();
+    }
+
+    /// Drops the updates no cached witness needs anymore.
+    fn trim(&mut self) {
+        let oldest = self
+            .entries
+            .values()
+            .map(|(_, v)| *v)
+            .min()
+            .unwrap_or_else(|| self.version());
+        self.journal.drain(..oldest - self.base);
+        self.base = oldest;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::{NewState, OldState};
+    use crate::group::RSAGroup;
+    use crate::primes::RandPrimes;
+    use num_bigint::RandPrime;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_witness_manager_random_ops() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut mgr = WitnessManager::new(&acc);
+
+        // all members, and the subset of them that is tracked
+        let mut members: Vec<BigUint> = Vec::new();
+        let mut tracked: Vec<BigUint> = Vec::new();
+
+        for _ in 0..60 {
+            match rng.gen_range(0, 5) {
+                0 => {
+                    let x = rng.gen_prime(128);
+                    mgr.add(&mut acc, &x);
+                    if rng.gen() {
+                        mgr.register(&acc, &x);
+                        tracked.push(x.clone());
+                    }
+                    members.push(x);
+                }
+                1 => {
+                    let xs = rng.gen_distinct_primes(3, 128);
+                    let root = acc.state().clone();
+                    let w = mgr.batch_add(&mut acc, &xs);
+                    let (old, new) = (OldState(root), NewState(acc.state().clone()));
+                    assert!(acc.ver_batch_add_transition(&w, &old, &new, &xs));
+
+                    mgr.register(&acc, &xs[0]);
+                    tracked.push(xs[0].clone());
+                    members.extend(xs);
+                }
+                2 if !members.is_empty() => {
+                    let i = rng.gen_range(0, members.len());
+                    let x = members.swap_remove(i);
+                    mgr.del(&mut acc, &x).unwrap();
+
+                    if let Some(j) = tracked.iter().position(|t| t == &x) {
+                        tracked.swap_remove(j);
+                        assert_eq!(mgr.drain_events(), vec![WitnessEvent::Dropped(x)]);
+                    }
+                }
+                3 if !tracked.is_empty() => {
+                    let x = tracked.swap_remove(rng.gen_range(0, tracked.len()));
+                    members.retain(|m| m != &x);
+
+                    let w = mgr.witness_for(&x).unwrap().clone();
+                    mgr.del_w_mem(&mut acc, &w, &x).unwrap();
+                    assert_eq!(mgr.drain_events(), vec![WitnessEvent::Dropped(x)]);
+                }
+                4 if tracked.len() >= 2 => {
+                    let pairs = tracked
+                        .drain(..2)
+                        .map(|x| {
+                            let w = mgr.witness_for(&x).unwrap().clone();
+                            (x, w)
+                        })
+                        .collect::<Vec<_>>();
+                    for (x, _) in &pairs {
+                        members.retain(|m| m != x);
+                    }
+
+                    mgr.batch_del(&mut acc, &pairs).unwrap();
+                    assert_eq!(mgr.drain_events().len(), 2);
+                }
+                _ => {}
+            }
+
+            assert_eq!(mgr.len(), tracked.len());
+            for x in &tracked {
+                let w = mgr.witness_for(x).unwrap();
+                assert!(acc.ver_mem(w, x), "stale witness");
+            }
+        }
+    }
+
+    #[test]
+    fn test_witness_manager_update_sequence() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut mgr = WitnessManager::new(&acc);
+
+        let xs = rng.gen_distinct_primes(4, 128);
+        mgr.batch_add(&mut acc, &xs);
+        mgr.register(&acc, &xs[0]);
+        mgr.register(&acc, &xs[1]);
+
+        // a sequence of mutations, with witnesses read in between
+        let y = rng.gen_prime(128);
+        let z = rng.gen_prime(128);
+        mgr.add(&mut acc, &y);
+        mgr.add(&mut acc, &z);
+        assert!(acc.ver_mem(mgr.witness_for(&xs[1]).unwrap(), &xs[1]));
+        mgr.del(&mut acc, &y).unwrap();
+        mgr.del(&mut acc, &xs[2]).unwrap();
+        mgr.del(&mut acc, &xs[1]).unwrap();
+
+        assert_eq!(
+            mgr.drain_events(),
+            vec![WitnessEvent::Dropped(xs[1].clone())]
+        );
+        assert!(!mgr.is_tracked(&xs[1]));
+
+        let w = mgr.witness_for(&xs[0]).unwrap();
+        assert!(acc.ver_mem(w, &xs[0]));
+        assert_eq!(w, &acc.mem_wit_create(&xs[0]));
+    }
+
+    #[test]
+    fn test_witness_manager_save_load() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut mgr = WitnessManager::new(&acc);
+
+        let xs = rng.gen_distinct_primes(5, 128);
+        mgr.batch_add(&mut acc, &xs[..4]);
+        for x in &xs[..3] {
+            mgr.register(&acc, x);
+        }
+        // and an untracked one
+        mgr.add(&mut acc, &xs[4]);
+
+        let mut saved = Vec::new();
+        mgr.save(&mut saved).unwrap();
+
+        // loading into the same state
+        let loaded = WitnessManager::load(&saved[..], &acc).unwrap();
+        assert!(!loaded.is_stale());
+        assert_eq!(loaded.len(), 3);
+        for x in &xs[..3] {
+            assert!(acc.ver_mem(loaded.witness_for(x).unwrap(), x));
+        }
+
+        // missed updates
+        let y = rng.gen_prime(128);
+        let root = acc.state().clone();
+        acc.add(&y);
+        let added_root = acc.state().clone();
+        acc.del(&xs[1]).unwrap();
+
+        let mut loaded = WitnessManager::load(&saved[..], &acc).unwrap();
+        assert!(loaded.is_stale());
+        assert!(loaded.witness_for(&xs[0]).is_none());
+
+        // replaying them
+        assert_eq!(loaded.root, root);
+        loaded.observe_add(std::slice::from_ref(&y), &added_root);
+        assert!(loaded.is_stale());
+        loaded.observe_del(std::slice::from_ref(&xs[1]), acc.state());
+        assert!(!loaded.is_stale());
+        assert_eq!(
+            loaded.drain_events(),
+            vec![WitnessEvent::Dropped(xs[1].clone())]
+        );
+        for x in &[&xs[0], &xs[2]] {
+            assert!(acc.ver_mem(loaded.witness_for(x).unwrap(), x));
+        }
+
+        // or recomputing them
+        let mut loaded = WitnessManager::load(&saved[..], &acc).unwrap();
+        loaded.resync(&acc);
+        assert!(!loaded.is_stale());
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(
+            loaded.drain_events(),
+            vec![WitnessEvent::Dropped(xs[1].clone())]
+        );
+        for x in &[&xs[0], &xs[2]] {
+            assert!(acc.ver_mem(loaded.witness_for(x).unwrap(), x));
+        }
+    }
+
+    #[test]
+    fn test_witness_manager_load_invalid() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut mgr = WitnessManager::new(&acc);
+        let x = rng.gen_prime(128);
+        mgr.add(&mut acc, &x);
+        mgr.register(&acc, &x);
+
+        let mut saved = Vec::new();
+        mgr.save(&mut saved).unwrap();
+
+        let other = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        assert!(matches!(
+            WitnessManager::load(&saved[..], &other),
+            Err(LoadError::ModulusMismatch)
+        ));
+
+        // in the header and in the witnesses
+        for &i in &[10, saved.len() - 70] {
+            let mut corrupt = saved.clone();
+            corrupt[i] ^= 0x01;
+            assert!(matches!(
+                WitnessManager::load(&corrupt[..], &acc),
+                Err(LoadError::Format(BatchIoError::Checksum { .. }))
+            ));
+        }
+        assert!(matches!(
+            WitnessManager::load(&saved[..saved.len() - 1], &acc),
+            Err(LoadError::Format(BatchIoError::Truncated { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_witness_cache_random_ops() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut fresh = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut cached = fresh.clone().with_witness_cache(4);
+
+        let mut members: Vec<BigUint> = Vec::new();
+        for _ in 0..80 {
+            match rng.gen_range(0, 5) {
+                0 => {
+                    let x = rng.gen_prime(128);
+                    fresh.add(&x);
+                    cached.add(&x);
+                    members.push(x);
+                }
+                1 => {
+                    let xs = rng.gen_distinct_primes(3, 128);
+                    fresh.batch_add(&xs);
+                    cached.batch_add(&xs);
+                    members.extend(xs);
+                }
+                2 if !members.is_empty() => {
+                    let x = members.swap_remove(rng.gen_range(0, members.len()));
+                    fresh.del(&x).unwrap();
+                    cached.del(&x).unwrap();
+                }
+                3 if !members.is_empty() => {
+                    let x = members.swap_remove(rng.gen_range(0, members.len()));
+                    let w = fresh.mem_wit_create(&x);
+                    fresh.del_w_mem(&w, &x).unwrap();
+                    cached.del_w_mem(&w, &x).unwrap();
+                }
+                4 if members.len() >= 2 => {
+                    let pairs = members
+                        .drain(..2)
+                        .map(|x| {
+                            let w = fresh.mem_wit_create(&x);
+                            (x, w)
+                        })
+                        .collect::<Vec<_>>();
+                    fresh.batch_del(&pairs).unwrap();
+                    cached.batch_del(&pairs).unwrap();
+                }
+                _ => {}
+            }
+            assert_eq!(cached.state(), fresh.state());
+
+            // request a few witnesses, mostly for the same elements
+            for _ in 0..2 {
+                if members.is_empty() {
+                    break;
+                }
+                let i = rng.gen_range(0, members.len().min(6));
+                let x = &members[i];
+                assert_eq!(cached.mem_wit_create(x), fresh.mem_wit_create(x));
+            }
+            assert!(cached.cached_witnesses() <= 4);
+        }
+    }
+
+    #[test]
+    fn test_witness_cache_eviction() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits).with_witness_cache(2);
+        let xs = rng.gen_distinct_primes(4, 128);
+        acc.batch_add(&xs);
+
+        acc.mem_wit_create(&xs[0]);
+        acc.mem_wit_create(&xs[1]);
+        assert_eq!(acc.cached_witnesses(), 2);
+
+        // the witness for xs[1] is refreshed, so xs[0] is the stalest
+        let y = rng.gen_prime(128);
+        acc.add(&y);
+        acc.mem_wit_create(&xs[1]);
+        acc.mem_wit_create(&xs[2]);
+        assert_eq!(acc.cached_witnesses(), 2);
+        let w = acc.mem_wit_create(&xs[1]);
+        assert!(acc.ver_mem(&w, &xs[1]));
+
+        // deleting an element drops its own witness
+        acc.del(&xs[1]).unwrap();
+        assert_eq!(acc.cached_witnesses(), 1);
+        let w = acc.mem_wit_create(&xs[2]);
+        assert!(acc.ver_mem(&w, &xs[2]));
+
+        let acc = acc.with_witness_cache(0);
+        acc.mem_wit_create(&xs[2]);
+        assert_eq!(acc.cached_witnesses(), 0);
+    }
+}
@@ -0,0 +1,433 @@
+//! Binary snapshots of many membership witnesses, e.g. to ship them to an offline signer.
+//!
+//! The layout of version 1 is
+//!
+//! - the magic bytes `ACWB` and the version, a single byte,
+//! - the maximal length of an element and the width of the witnesses, both big-endian `u32`,
+//! - the number of items, a big-endian `u64`,
+//! - every item, as the length of the element, a big-endian `u32`, followed by its minimal
+//!   big-endian bytes, and the witness with the fixed width from the header,
+//! - the Blake2b hash of everything before it.
+//!
+//! The witness width is the width of the largest witness, which is the width of the modulus
+//! for all but very unlucky batches. Both sides stream, only the items themselves are held in
+//! memory.
+
+use std::fmt;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::BigUint;
+use num_traits::Zero;
+
+const MAGIC: &[u8; 4] = b"ACWB";
+const VERSION: u8 = 1;
+
+/// Largest element and witness accepted, in bytes, so that a corrupt header can't make the
+/// reader allocate arbitrary amounts of memory.
+pub const MAX_WIDTH: usize = 1 << 16;
+
+/// Offsets of the header fields.
+const ELEMENT_WIDTH_POS: u64 = 5;
+const WITNESS_WIDTH_POS: u64 = 9;
+
+const CHECKSUM_LEN: usize = 64;
+
+/// Errors reading or writing a batch, with the offset of the offending field where possible.
+#[derive(Debug)]
+pub enum BatchIoError {
+    /// The underlying reader or writer failed.
+    Io(io::Error),
+    /// The input ends inside the field at `pos`.
+    Truncated { pos: u64 },
+    /// The input does not start with the magic bytes.
+    BadMagic,
+    /// The version is not supported by this reader.
+    UnsupportedVersion(u8),
+    /// A width in the header, or the length of an element, is larger than allowed.
+    TooLarge { pos: u64, len: usize, max: usize },
+    /// An integer has leading zeros.
+    NonCanonical { pos: u64 },
+    /// The checksum at `pos` does not match the contents.
+    Checksum { pos: u64 },
+    /// There is more data after the checksum at `pos`.
+    TrailingData { pos: u64 },
+}
+
+impl fmt::Display for BatchIoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchIoError::Io(err) => write!(f, "{}", err),
+            BatchIoError::Truncated { pos } => write!(f, "unexpected end of input at byte {}", pos),
+            BatchIoError::BadMagic => write!(f, "not a witness batch"),
+            BatchIoError::UnsupportedVersion(v) => write!(f, "unsupported version {}", v),
+            BatchIoError::TooLarge { pos, len, max } => write!(
+                f,
+                "length {} at byte {} is larger than the maximum {}",
+                len, pos, max
+            ),
+            BatchIoError::NonCanonical { pos } => {
+                write!(f, "integer at byte {} is not minimally encoded", pos)
+            }
+            BatchIoError::Checksum { pos } => write!(f, "checksum mismatch at byte {}", pos),
+            BatchIoError::TrailingData { pos } => {
+                write!(f, "unexpected data after the checksum, at byte {}", pos)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BatchIoError {}
+
+impl From<io::Error> for BatchIoError {
+    fn from(err: io::Error) -> Self {
+        BatchIoError::Io(err)
+    }
+}
+
+/// Writes `items`, pairs of elements and their witnesses, to `w`.
+///
+/// Fails if an element or witness is longer than `MAX_WIDTH` bytes, before anything is written.
+pub fn write_batch<W: Write>(w: W, items: &[(BigUint, BigUint)]) -> Result<(), BatchIoError> {
+    let element_width = items.iter().map(|(x, _)| byte_len(x)).max().unwrap_or(0);
+    let witness_width = items.iter().map(|(_, w)| byte_len(w)).max().unwrap_or(0);
+    for &(pos, len) in &[
+        (ELEMENT_WIDTH_POS, element_width),
+        (WITNESS_WIDTH_POS, witness_width),
+    ] {
+        if len > MAX_WIDTH {
+            return Err(BatchIoError::TooLarge {
+                pos,
+                len,
+                max: MAX_WIDTH,
+            });
+        }
+    }
+
+    let mut out = HashingWriter::new(BufWriter::new(w));
+
+    let mut header = [0u8; 21];
+    header[..4].copy_from_slice(MAGIC);
+    header[4] = VERSION;
+    BigEndian::write_u32(&mut header[5..9], element_width as u32);
+    BigEndian::write_u32(&mut header[9..13], witness_width as u32);
+    BigEndian::write_u64(&mut header[13..], items.len() as u64);
+    out.write_all(&header)?;
+
+    let mut witness = vec![0u8; witness_width];
+    for (x, w) in items {
+        out.write_biguint(x)?;
+
+        // left padded to the witness width
+        let w = minimal_bytes(w);
+        let pad = witness_width - w.len();
+        witness[..pad].iter_mut().for_each(|b| *b = 0);
+        witness[pad..].copy_from_slice(&w);
+        out.write_all(&witness)?;
+    }
+
+    out.finish()?.flush()?;
+
+    Ok(())
+}
+
+/// Reads a batch written by `write_batch` from `r`.
+///
+/// Fails on the first invalid field, and if the checksum doesn't match or is followed by more
+/// data.
+pub fn read_batch<R: Read>(r: R) -> Result<Vec<(BigUint, BigUint)>, BatchIoError> {
+    read_batch_at(r, 0)
+}
+
+/// Reads a batch that starts at byte `offset` of a larger file, like `read_batch`.
+///
+/// The positions in errors are counted from the start of the file.
+pub(crate) fn read_batch_at<R: Read>(
+    r: R,
+    offset: u64,
+) -> Result<Vec<(BigUint, BigUint)>, BatchIoError> {
+    let mut input = HashingReader::new(BufReader::new(r), offset);
+
+    let mut magic = [0u8; 4];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(BatchIoError::BadMagic);
+    }
+    let mut version = [0u8; 1];
+    input.read_exact(&mut version)?;
+    if version[0] != VERSION {
+        return Err(BatchIoError::UnsupportedVersion(version[0]));
+    }
+
+    let element_width = input.read_len(MAX_WIDTH)?;
+    let witness_width = input.read_len(MAX_WIDTH)?;
+    let mut count = [0u8; 8];
+    input.read_exact(&mut count)?;
+    let count = BigEndian::read_u64(&count);
+
+    // the count is not trusted before all items have been read
+    let mut items = Vec::with_capacity(count.min(1 << 16) as usize);
+    let mut witness = vec![0u8; witness_width];
+    for _ in 0..count {
+        let x = input.read_biguint(element_width)?;
+        input.read_exact(&mut witness)?;
+        items.push((x, BigUint::from_bytes_be(&witness)));
+    }
+    input.check_checksum()?;
+
+    let mut rest = [0u8; 1];
+    if input.inner.read(&mut rest)? != 0 {
+        return Err(BatchIoError::TrailingData {
+            pos: input.position(),
+        });
+    }
+
+    Ok(items)
+}
+
+/// The big-endian bytes of `x`, without any leading zeros, so empty for zero.
+fn minimal_bytes(x: &BigUint) -> Vec<u8> {
+    if x.is_zero() {
+        Vec::new()
+    } else {
+        x.to_bytes_be()
+    }
+}
+
+fn byte_len(x: &BigUint) -> usize {
+    if x.is_zero() {
+        0
+    } else {
+        x.bits().div_ceil(8)
+    }
+}
+
+/// Writes through to `inner`, hashing everything written, for sections that end in a
+/// checksum.
+pub(crate) struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: Blake2b,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        HashingWriter {
+            inner,
+            hasher: Blake2b::new(),
+        }
+    }
+
+    pub(crate) fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.hasher.input(bytes);
+        self.inner.write_all(bytes)
+    }
+
+    /// Writes `x` as its length, a big-endian `u32`, followed by its minimal bytes.
+    pub(crate) fn write_biguint(&mut self, x: &BigUint) -> io::Result<()> {
+        let x = minimal_bytes(x);
+        let mut len = [0u8; 4];
+        BigEndian::write_u32(&mut len, x.len() as u32);
+        self.write_all(&len)?;
+        self.write_all(&x)
+    }
+
+    /// Writes the checksum of everything written so far, and returns the inner writer.
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        let checksum = self.hasher.result();
+        self.inner.write_all(&checksum)?;
+        Ok(self.inner)
+    }
+}
+
+/// Reads from `inner`, hashing everything read, and keeping track of the position.
+pub(crate) struct HashingReader<R: Read> {
+    inner: R,
+    hasher: Blake2b,
+    pos: u64,
+}
+
+impl<R: Read> HashingReader<R> {
+    /// Creates a reader for a section starting at byte `pos`.
+    pub(crate) fn new(inner: R, pos: u64) -> Self {
+        HashingReader {
+            inner,
+            hasher: Blake2b::new(),
+            pos,
+        }
+    }
+
+    /// Returns the position of the next byte.
+    pub(crate) fn position(&self) -> u64 {
+        self.pos
+    }
+
+    pub(crate) fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), BatchIoError> {
+        match self.inner.read_exact(buf) {
+            Ok(()) => {
+                self.hasher.input(&buf[..]);
+                self.pos += buf.len() as u64;
+                Ok(())
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                Err(BatchIoError::Truncated { pos: self.pos })
+            }
+            Err(err) => Err(BatchIoError::Io(err)),
+        }
+    }
+
+    /// Reads a length, at most `max`.
+    pub(crate) fn read_len(&mut self, max: usize) -> Result<usize, BatchIoError> {
+        let pos = self.pos;
+        let mut len = [0u8; 4];
+        self.read_exact(&mut len)?;
+
+        let len = BigEndian::read_u32(&len) as usize;
+        if len > max {
+            return Err(BatchIoError::TooLarge { pos, len, max });
+        }
+
+        Ok(len)
+    }
+
+    /// Reads an integer written by `HashingWriter::write_biguint`, of at most `max` bytes.
+    pub(crate) fn read_biguint(&mut self, max: usize) -> Result<BigUint, BatchIoError> {
+        let len = self.read_len(max)?;
+        let pos = self.pos;
+        let mut x = vec![0u8; len];
+        self.read_exact(&mut x)?;
+        if x.first() == Some(&0) {
+            return Err(BatchIoError::NonCanonical { pos });
+        }
+
+        Ok(BigUint::from_bytes_be(&x))
+    }
+
+    /// Reads the checksum, and compares it to the one of everything read so far.
+    pub(crate) fn check_checksum(&mut self) -> Result<(), BatchIoError> {
+        let pos = self.pos;
+        let expected = self.hasher.clone().result();
+        let mut checksum = [0u8; CHECKSUM_LEN];
+        self.read_exact(&mut checksum)?;
+        if checksum[..] != expected[..] {
+            return Err(BatchIoError::Checksum { pos });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use num_bigint::RandBigInt;
+    use num_traits::One;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn items(count: usize) -> Vec<(BigUint, BigUint)> {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        (0..count)
+            .map(|_| (rng.gen_biguint(128), rng.gen_biguint(2048)))
+            .collect()
+    }
+
+    fn encode(items: &[(BigUint, BigUint)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_batch(&mut out, items).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for &count in &[0, 1, 10, 100_000] {
+            let items = items(count);
+            let bytes = encode(&items);
+            assert_eq!(read_batch(&bytes[..]).unwrap(), items);
+        }
+
+        // zeros and witnesses shorter than the width
+        let items = vec![
+            (BigUint::zero(), BigUint::zero()),
+            (BigUint::from(3u32), BigUint::from(1u32) << 100),
+            (BigUint::from(65537u32), BigUint::from(5u32)),
+        ];
+        assert_eq!(read_batch(&encode(&items)[..]).unwrap(), items);
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let items = items(20);
+        let bytes = encode(&items);
+        let checksum_pos = (bytes.len() - CHECKSUM_LEN) as u64;
+
+        for i in 0..bytes.len() {
+            let mut corrupt = bytes.clone();
+            corrupt[i] ^= 0x01;
+            assert!(read_batch(&corrupt[..]).is_err(), "flipped byte {}", i);
+        }
+
+        // the last byte of the last witness
+        let mut corrupt = bytes.clone();
+        corrupt[checksum_pos as usize - 1] ^= 0x80;
+        match read_batch(&corrupt[..]) {
+            Err(BatchIoError::Checksum { pos }) => assert_eq!(pos, checksum_pos),
+            res => panic!("unexpected result {:?}", res),
+        }
+
+        let mut corrupt = bytes.clone();
+        corrupt[0] = b'X';
+        assert!(matches!(
+            read_batch(&corrupt[..]),
+            Err(BatchIoError::BadMagic)
+        ));
+        let mut corrupt = bytes.clone();
+        corrupt[4] = 2;
+        assert!(matches!(
+            read_batch(&corrupt[..]),
+            Err(BatchIoError::UnsupportedVersion(2))
+        ));
+
+        // length of the first element
+        let mut corrupt = bytes.clone();
+        corrupt[21] = 0xff;
+        assert!(matches!(
+            read_batch(&corrupt[..]),
+            Err(BatchIoError::TooLarge { pos: 21, .. })
+        ));
+
+        let mut longer = bytes.clone();
+        longer.push(0);
+        match read_batch(&longer[..]) {
+            Err(BatchIoError::TrailingData { pos }) => assert_eq!(pos, bytes.len() as u64),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_truncated() {
+        let bytes = encode(&items(5));
+
+        for len in 0..bytes.len() {
+            match read_batch(&bytes[..len]) {
+                Err(BatchIoError::Truncated { pos }) => assert!(pos <= len as u64),
+                res => panic!("unexpected result for {} bytes: {:?}", len, res),
+            }
+        }
+    }
+
+    #[test]
+    fn test_too_large() {
+        let items = vec![(BigUint::from(3u32), BigUint::one() << (8 * MAX_WIDTH))];
+        let mut out = Vec::new();
+        assert!(matches!(
+            write_batch(&mut out, &items),
+            Err(BatchIoError::TooLarge {
+                pos: WITNESS_WIDTH_POS,
+                ..
+            })
+        ));
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,636 @@
+//! Zero-knowledge proofs of membership, that hide the element and its witness.
+//!
+//! This is the proof of knowledge of an accumulated value from Camenisch and Lysyanskaya,
+//! [Dynamic Accumulators and Application to Efficient Revocation of Anonymous Credentials](https://eprint.iacr.org/2002/105),
+//! made non-interactive with Fiat-Shamir. The prover commits to its element `x` and witness
+//! `w`, and proves knowledge of the committed values with `w^x = A`.
+//!
+//! The proof only bounds the size of `x`, instead of fixing it, so elements must come from
+//! a narrow range far away from zero: `x = 2^(B - 1) + o`, with `0 <= o < 2^offset_bits`.
+//! `ZkParams::hash_element` maps data to such elements. Everything the proof reveals about
+//! `x` is that it is in this range.
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, WriteBytesExt};
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+#[cfg(feature = "prover")]
+use rand::{CryptoRng, Rng};
+
+use crate::accumulator::PublicState;
+#[cfg(feature = "prover")]
+use crate::accumulator::{Accumulator, MembershipWitness};
+use crate::encoding::{modulus_bytes, to_fixed_bytes};
+use crate::hash::hash_group;
+use crate::primality::{is_probable_prime, PrimalityConfig};
+
+const MEM_LABEL: &[u8] = b"accumulators/zk/mem";
+const NON_MEM_LABEL: &[u8] = b"accumulators/zk/non-mem";
+
+/// Size of the Fiat-Shamir challenge, in bits.
+pub const ZK_CHALLENGE_BITS: usize = 128;
+
+/// Statistical security of the zero-knowledge property, in bits.
+pub const ZK_SLACK_BITS: usize = 80;
+
+/// Parameters for zero-knowledge membership proofs, shared by provers and verifiers.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ZkParams {
+    /// Second generator for the commitments, with a discrete logarithm nobody knows.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub h: BigUint,
+    /// Size of the elements in bits, `B`.
+    pub element_bits: usize,
+    /// Size of the offset of the elements from `2^(B - 1)`, in bits.
+    pub offset_bits: usize,
+}
+
+impl ZkParams {
+    /// Derives the parameters for the accumulator with modulus `n`.
+    ///
+    /// Panics if `offset_bits` is too large for `element_bits`: the range the proof
+    /// guarantees must neither contain `1` nor the product of two elements.
+    pub fn new(n: &BigUint, element_bits: usize, offset_bits: usize) -> Self {
+        assert!(
+            offset_bits + ZK_CHALLENGE_BITS + ZK_SLACK_BITS + 2 < element_bits - 1,
+            "offset too large for the element size"
+        );
+
+        // h <- H_G(label, n)^2, a quadratic residue
+        let mut to_hash = b"accumulators/zk/h".to_vec();
+        to_hash.extend(&n.to_bytes_be());
+        let h = hash_group::<_, Blake2b>(&to_hash, n).modpow(&BigUint::from(2u32), n);
+
+        ZkParams {
+            h,
+            element_bits,
+            offset_bits,
+        }
+    }
+
+    /// The smallest element, `2^(B - 1)`.
+    fn base(&self) -> BigUint {
+        BigUint::one() << (self.element_bits - 1)
+    }
+
+    /// Commits to the element `x`, with the randomness `r`, as `g^o h^r` for the offset
+    /// `o = x - 2^(B - 1)`.
+    ///
+    /// This is the commitment non-membership proofs are bound to, so they can be linked to
+    /// other proofs about `x`. `r` should be uniform below `n`.
+    pub fn commit(&self, state: &PublicState, x: &BigUint, r: &BigUint) -> BigUint {
+        debug_assert!(self.contains_element(x), "element out of range");
+        let o = x - self.base();
+
+        multi_exp(&state.g, &o, &self.h, r, &state.n)
+    }
+
+    /// Returns `true` if `x` is in the range of elements proofs can be created for.
+    pub fn contains_element(&self, x: &BigUint) -> bool {
+        let base = self.base();
+        x >= &base && (x - &base).bits() <= self.offset_bits
+    }
+
+    /// Hashes `input` to a prime in the range of elements.
+    pub fn hash_element(&self, input: &[u8]) -> BigUint {
+        let mut offset = BigUint::zero();
+        let mut counter = 0u32;
+        while offset.bits() < self.offset_bits {
+            let mut to_hash = input.to_vec();
+            to_hash.write_u32::<BigEndian>(counter).unwrap();
+            offset = (offset << 512) + BigUint::from_bytes_be(&Blake2b::digest(&to_hash));
+            counter += 1;
+        }
+        offset >>= offset.bits() - self.offset_bits;
+
+        // the next prime, wrapping around in the range
+        let max = BigUint::one() << self.offset_bits;
+        let cfg = PrimalityConfig::default();
+        offset |= BigUint::one();
+        loop {
+            let x = self.base() + &offset;
+            if is_probable_prime(&x, &cfg) {
+                return x;
+            }
+            offset = (offset + 2u32).mod_floor(&max);
+        }
+    }
+}
+
+/// Zero-knowledge proof of knowledge of an accumulated element and its witness.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ZkMemProof {
+    /// `C_e = g^o h^r1`, commitment to the offset `o` of the element.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub c_e: BigUint,
+    /// `C_u = w h^r2`, the blinded witness.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub c_u: BigUint,
+    /// `C_r = g^r2 h^r3`, commitment to the blinding of the witness.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub c_r: BigUint,
+    /// The first messages of the sigma protocol, one per relation.
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::encoding::serde_hex::biguint_vec")
+    )]
+    pub t: Vec<BigUint>,
+    /// The responses, for `o`, `r1`, `r2`, `r3`, `x r2` and `x r3`, in this order.
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::encoding::serde_hex::biguint_vec")
+    )]
+    pub s: Vec<BigUint>,
+}
+
+/// Zero-knowledge proof that a committed element is not accumulated.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ZkNonMemProof {
+    /// `C_d = d h^r2`, the blinded `d` of the non-membership witness.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub c_d: BigUint,
+    /// `C_r = g^r2 h^r3`, commitment to the blinding of `d`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::encoding::serde_hex::biguint"))]
+    pub c_r: BigUint,
+    /// The first messages of the sigma protocol, one per relation.
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::encoding::serde_hex::biguint_vec")
+    )]
+    pub t: Vec<BigUint>,
+    /// The responses, for `o`, `r`, `r2`, `r3`, `x r2`, `x r3` and `b`, in this order.
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::encoding::serde_hex::biguint_vec")
+    )]
+    pub s: Vec<BigUint>,
+}
+
+/// Bit sizes of the secrets shared by both proofs, in the order of the responses:
+/// the offset `o` of the element, the three blinding factors and the two products of
+/// the element with the blinding factors.
+fn secret_bits(params: &ZkParams, n: &BigUint) -> Vec<usize> {
+    let n_bits = n.bits();
+    let d_bits = params.element_bits + n_bits;
+
+    vec![params.offset_bits, n_bits, n_bits, n_bits, d_bits, d_bits]
+}
+
+/// Checks that the responses bound the secrets, in particular the offset of the element.
+fn responses_in_range(bits: &[usize], s: &[BigUint]) -> bool {
+    let slack = ZK_CHALLENGE_BITS + ZK_SLACK_BITS;
+
+    bits.len() == s.len()
+        && bits
+            .iter()
+            .zip(s)
+            .all(|(bits, s)| s.bits() <= bits + slack + 1)
+}
+
+/// Masks for secrets of the given sizes, large enough to hide them statistically.
+#[cfg(feature = "prover")]
+fn masks<R: CryptoRng + Rng>(rng: &mut R, bits: &[usize]) -> Vec<BigUint> {
+    use num_bigint::RandBigInt;
+
+    let slack = ZK_CHALLENGE_BITS + ZK_SLACK_BITS;
+    bits.iter()
+        .map(|bits| rng.gen_biguint(bits + slack))
+        .collect()
+}
+
+/// The responses `rho + c secret` over the integers.
+#[cfg(feature = "prover")]
+fn responses(rho: &[BigUint], c: &BigUint, secrets: &[BigUint]) -> Vec<BigUint> {
+    rho.iter()
+        .zip(secrets)
+        .map(|(rho, secret)| rho + c * secret)
+        .collect()
+}
+
+/// The Fiat-Shamir challenge, over the statement, commitments and first messages.
+fn challenge(label: &[u8], params: &ZkParams, state: &PublicState, values: &[&BigUint]) -> BigUint {
+    let len = modulus_bytes(&state.n);
+
+    let mut hasher = Blake2b::new();
+    hasher.input(label);
+    let mut sizes = Vec::new();
+    sizes.write_u32::<BigEndian>(len as u32).unwrap();
+    sizes
+        .write_u32::<BigEndian>(params.element_bits as u32)
+        .unwrap();
+    sizes
+        .write_u32::<BigEndian>(params.offset_bits as u32)
+        .unwrap();
+    hasher.input(&sizes);
+    for v in [&state.n, &state.g, &params.h, &state.root]
+        .iter()
+        .chain(values)
+    {
+        hasher.input(to_fixed_bytes(v, len));
+    }
+
+    BigUint::from_bytes_be(&hasher.result()[..ZK_CHALLENGE_BITS / 8])
+}
+
+/// `a^x b^y mod n`
+fn multi_exp(a: &BigUint, x: &BigUint, b: &BigUint, y: &BigUint, n: &BigUint) -> BigUint {
+    (a.modpow(x, n) * b.modpow(y, n)) % n
+}
+
+/// Proves in zero knowledge that the holder knows an element and its membership witness
+/// `w` in the accumulator with state `state`, without revealing either.
+///
+/// Proofs are randomized, two proofs for the same element can't be linked.
+/// `x` must be in the range of `params`, see `ZkParams::contains_element`.
+#[cfg(feature = "prover")]
+pub fn zk_mem_prove<R: CryptoRng + Rng>(
+    rng: &mut R,
+    x: &BigUint,
+    w: &MembershipWitness,
+    state: &PublicState,
+    params: &ZkParams,
+) -> ZkMemProof {
+    use num_bigint::{ModInverse, RandBigInt};
+
+    debug_assert!(params.contains_element(x), "element out of range");
+    debug_assert!(state.ver_mem(w, x), "invalid witness");
+
+    let PublicState { n, g, root: _ } = state;
+    let h = &params.h;
+    let inv = |a: BigUint| -> BigUint {
+        a.mod_inverse(n)
+            .and_then(|a| a.to_biguint())
+            .expect("not invertible")
+    };
+
+    let o = x - params.base();
+    let r1 = rng.gen_biguint_below(n);
+    let r2 = rng.gen_biguint_below(n);
+    let r3 = rng.gen_biguint_below(n);
+    let (d1, d2) = (x * &r2, x * &r3);
+    let secrets = [o, r1, r2, r3, d1, d2];
+
+    let c_e = multi_exp(g, &secrets[0], h, &secrets[1], n);
+    let c_u = (&w.0 * h.modpow(&secrets[2], n)) % n;
+    let c_r = multi_exp(g, &secrets[2], h, &secrets[3], n);
+
+    let rho = masks(rng, &secret_bits(params, n));
+
+    let t = vec![
+        multi_exp(g, &rho[0], h, &rho[1], n),
+        multi_exp(g, &rho[2], h, &rho[3], n),
+        (c_r.modpow(&rho[0], n) * inv(multi_exp(g, &rho[4], h, &rho[5], n))) % n,
+        (c_u.modpow(&rho[0], n) * inv(h.modpow(&rho[4], n))) % n,
+    ];
+
+    let c = challenge(
+        MEM_LABEL,
+        params,
+        state,
+        &[&c_e, &c_u, &c_r, &t[0], &t[1], &t[2], &t[3]],
+    );
+    let s = responses(&rho, &c, &secrets);
+
+    ZkMemProof {
+        c_e,
+        c_u,
+        c_r,
+        t,
+        s,
+    }
+}
+
+/// Verifies a proof from `zk_mem_prove`, that the prover knows a member of the accumulator
+/// with state `state`.
+pub fn zk_mem_verify(pi: &ZkMemProof, state: &PublicState, params: &ZkParams) -> bool {
+    let PublicState { n, g, root } = state;
+    let h = &params.h;
+
+    if pi.t.len() != 4 || !responses_in_range(&secret_bits(params, n), &pi.s) {
+        return false;
+    }
+    let values = [
+        &pi.c_e, &pi.c_u, &pi.c_r, &pi.t[0], &pi.t[1], &pi.t[2], &pi.t[3],
+    ];
+    if values.iter().any(|v| v.is_zero() || *v >= n) {
+        return false;
+    }
+
+    let c = challenge(MEM_LABEL, params, state, &values);
+    let s = &pi.s;
+    // exponent for the full element, x = 2^(B - 1) + o
+    let s_x = &s[0] + &c * params.base();
+
+    // C_e = g^o h^r1
+    multi_exp(g, &s[0], h, &s[1], n) == (&pi.t[0] * pi.c_e.modpow(&c, n)) % n
+        // C_r = g^r2 h^r3
+        && multi_exp(g, &s[2], h, &s[3], n) == (&pi.t[1] * pi.c_r.modpow(&c, n)) % n
+        // 1 = C_r^x g^-(x r2) h^-(x r3)
+        && pi.c_r.modpow(&s_x, n) == (&pi.t[2] * multi_exp(g, &s[4], h, &s[5], n)) % n
+        // A = C_u^x h^-(x r2)
+        && pi.c_u.modpow(&s_x, n)
+            == (&pi.t[3] * root.modpow(&c, n) * h.modpow(&s[4], n)) % n
+}
+
+/// Proves in zero knowledge that the element `x`, committed to with `params.commit` and the
+/// randomness `r`, is not a member of `acc`.
+///
+/// Returns `None` if `x` is a member. Proofs are randomized, two proofs for the same element
+/// and commitment can't be linked beyond sharing the commitment.
+#[cfg(feature = "prover")]
+pub fn zk_non_mem_prove<R: CryptoRng + Rng>(
+    rng: &mut R,
+    x: &BigUint,
+    r: &BigUint,
+    acc: &Accumulator,
+    params: &ZkParams,
+) -> Option<ZkNonMemProof> {
+    use num_bigint::{ModInverse, RandBigInt};
+
+    debug_assert!(params.contains_element(x), "element out of range");
+
+    let state = acc.public_state();
+    let PublicState { n, g, root } = &state;
+    let h = &params.h;
+    let inv = |a: &BigUint| -> Option<BigUint> { a.mod_inverse(n)?.to_biguint() };
+
+    // d^x A^b = g, with 0 <= b < x
+    let w = acc.try_non_mem_wit_create(x).ok()?;
+    let (d, b) = (w.d, w.b.to_biguint()?);
+
+    let o = x - params.base();
+    let r2 = rng.gen_biguint_below(n);
+    let r3 = rng.gen_biguint_below(n);
+    let (d1, d2) = (x * &r2, x * &r3);
+    let secrets = [o, r.clone(), r2, r3, d1, d2, b];
+
+    let c_x = params.commit(&state, x, r);
+    let c_d = (&d * h.modpow(&secrets[2], n)) % n;
+    let c_r = multi_exp(g, &secrets[2], h, &secrets[3], n);
+
+    let mut bits = secret_bits(params, n);
+    bits.push(params.element_bits);
+    let rho = masks(rng, &bits);
+
+    let t = vec![
+        multi_exp(g, &rho[0], h, &rho[1], n),
+        multi_exp(g, &rho[2], h, &rho[3], n),
+        (c_r.modpow(&rho[0], n) * inv(&multi_exp(g, &rho[4], h, &rho[5], n))?) % n,
+        (multi_exp(&c_d, &rho[0], root, &rho[6], n) * inv(&h.modpow(&rho[4], n))?) % n,
+    ];
+
+    let c = challenge(
+        NON_MEM_LABEL,
+        params,
+        &state,
+        &[&c_x, &c_d, &c_r, &t[0], &t[1], &t[2], &t[3]],
+    );
+    let s = responses(&rho, &c, &secrets);
+
+    Some(ZkNonMemProof { c_d, c_r, t, s })
+}
+
+/// Verifies a proof from `zk_non_mem_prove`, that the element committed to in `c_x` is not
+/// a member of the accumulator with state `state`.
+pub fn zk_non_mem_verify(
+    pi: &ZkNonMemProof,
+    c_x: &BigUint,
+    state: &PublicState,
+    params: &ZkParams,
+) -> bool {
+    let PublicState { n, g, root } = state;
+    let h = &params.h;
+
+    let mut bits = secret_bits(params, n);
+    bits.push(params.element_bits);
+    if pi.t.len() != 4 || !responses_in_range(&bits, &pi.s) {
+        return false;
+    }
+    let values = [
+        c_x, &pi.c_d, &pi.c_r, &pi.t[0], &pi.t[1], &pi.t[2], &pi.t[3],
+    ];
+    if values.iter().any(|v| v.is_zero() || *v >= n) {
+        return false;
+    }
+
+    let c = challenge(NON_MEM_LABEL, params, state, &values);
+    let s = &pi.s;
+    // exponent for the full element, x = 2^(B - 1) + o
+    let s_x = &s[0] + &c * params.base();
+
+    // C_x = g^o h^r
+    multi_exp(g, &s[0], h, &s[1], n) == (&pi.t[0] * c_x.modpow(&c, n)) % n
+        // C_r = g^r2 h^r3
+        && multi_exp(g, &s[2], h, &s[3], n) == (&pi.t[1] * pi.c_r.modpow(&c, n)) % n
+        // 1 = C_r^x g^-(x r2) h^-(x r3)
+        && pi.c_r.modpow(&s_x, n) == (&pi.t[2] * multi_exp(g, &s[4], h, &s[5], n)) % n
+        // g = C_d^x A^b h^-(x r2)
+        && multi_exp(&pi.c_d, &s_x, root, &s[6], n)
+            == (&pi.t[3] * g.modpow(&c, n) * h.modpow(&s[4], n)) % n
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::{RandBigInt, RandPrime};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn setup(rng: &mut ChaChaRng) -> (Accumulator, ZkParams, Vec<BigUint>) {
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let params = ZkParams::new(acc.modulus(), 400, 128);
+
+        let xs = (0..3u8)
+            .map(|i| params.hash_element(&[i]))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs);
+        acc.add(&rng.gen_prime(128));
+
+        (acc, params, xs)
+    }
+
+    #[test]
+    fn test_zk_mem() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, params, xs) = setup(rng);
+        let state = acc.public_state();
+
+        for x in &xs {
+            assert!(params.contains_element(x));
+            let w = acc.mem_wit_create(x);
+            let pi = zk_mem_prove(rng, x, &w, &state, &params);
+            assert!(zk_mem_verify(&pi, &state, &params));
+
+            // bound to the state
+            let mut other = state.clone();
+            other.root = w.0.clone();
+            assert!(!zk_mem_verify(&pi, &other, &params));
+        }
+    }
+
+    #[test]
+    fn test_zk_mem_unlinkable() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, params, xs) = setup(rng);
+        let state = acc.public_state();
+
+        let w = acc.mem_wit_create(&xs[0]);
+        let pi_1 = zk_mem_prove(rng, &xs[0], &w, &state, &params);
+        let pi_2 = zk_mem_prove(rng, &xs[0], &w, &state, &params);
+        assert!(zk_mem_verify(&pi_1, &state, &params));
+        assert!(zk_mem_verify(&pi_2, &state, &params));
+
+        // no part of the proofs repeats, and neither contains the element or witness
+        let parts = |pi: &ZkMemProof| {
+            let mut parts = vec![pi.c_e.clone(), pi.c_u.clone(), pi.c_r.clone()];
+            parts.extend(pi.t.iter().cloned());
+            parts.extend(pi.s.iter().cloned());
+            parts
+        };
+        let (parts_1, parts_2) = (parts(&pi_1), parts(&pi_2));
+        for part in &parts_1 {
+            assert!(!parts_2.contains(part));
+            assert!(part != &xs[0] && part != &w.0);
+        }
+
+        // the same for the proofs as sent
+        #[cfg(feature = "serde")]
+        {
+            use crate::encoding::hex::biguint_to_hex;
+
+            let (json_1, json_2) = (
+                serde_json::to_string(&pi_1).unwrap(),
+                serde_json::to_string(&pi_2).unwrap(),
+            );
+            assert_ne!(json_1, json_2);
+            let values = |json: &str| -> Vec<String> {
+                let value: serde_json::Value = serde_json::from_str(json).unwrap();
+                let mut values = Vec::new();
+                for field in value.as_object().unwrap().values() {
+                    match field {
+                        serde_json::Value::Array(items) => {
+                            values.extend(items.iter().map(|v| v.as_str().unwrap().to_string()))
+                        }
+                        v => values.push(v.as_str().unwrap().to_string()),
+                    }
+                }
+                values
+            };
+            let (values_1, values_2) = (values(&json_1), values(&json_2));
+            assert_eq!(values_1.len(), parts_1.len());
+            for value in &values_1 {
+                assert!(!values_2.contains(value));
+            }
+            for secret in &[biguint_to_hex(&xs[0]), biguint_to_hex(&w.0)] {
+                assert!(!json_1.contains(secret.as_str()));
+                assert!(!json_2.contains(secret.as_str()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_zk_mem_invalid() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, params, xs) = setup(rng);
+        let state = acc.public_state();
+
+        let w = acc.mem_wit_create(&xs[0]);
+        let pi = zk_mem_prove(rng, &xs[0], &w, &state, &params);
+
+        for i in 0..6 {
+            let mut bad = pi.clone();
+            bad.s[i] += 1u32;
+            assert!(!zk_mem_verify(&bad, &state, &params));
+        }
+        for i in 0..4 {
+            let mut bad = pi.clone();
+            bad.t[i] = (&bad.t[i] * 2u32) % &state.n;
+            assert!(!zk_mem_verify(&bad, &state, &params));
+        }
+        let mut bad = pi.clone();
+        bad.c_u = state.n.clone();
+        assert!(!zk_mem_verify(&bad, &state, &params));
+        let mut bad = pi.clone();
+        bad.s.pop();
+        assert!(!zk_mem_verify(&bad, &state, &params));
+
+        // the trivial witness for x = 1 is outside of the range
+        assert!(!params.contains_element(&BigUint::one()));
+        let mut bad = pi.clone();
+        bad.s[0] <<= 400;
+        assert!(!zk_mem_verify(&bad, &state, &params));
+    }
+
+    #[test]
+    fn test_zk_non_mem() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, params, xs) = setup(rng);
+        let state = acc.public_state();
+        let y = params.hash_element(b"not a member");
+
+        let r = rng.gen_biguint_below(&state.n);
+        let c_y = params.commit(&state, &y, &r);
+        let pi = zk_non_mem_prove(rng, &y, &r, &acc, &params).unwrap();
+        assert!(zk_non_mem_verify(&pi, &c_y, &state, &params));
+
+        // bound to the commitment and the state
+        let c_x = params.commit(&state, &xs[0], &r);
+        assert!(!zk_non_mem_verify(&pi, &c_x, &state, &params));
+        let mut other = state.clone();
+        other.root = state.g.clone();
+        assert!(!zk_non_mem_verify(&pi, &c_y, &other, &params));
+
+        for i in 0..7 {
+            let mut bad = pi.clone();
+            bad.s[i] += 1u32;
+            assert!(!zk_non_mem_verify(&bad, &c_y, &state, &params));
+        }
+
+        // more elements, some with a negative Euclidean coefficient before the reduction
+        for i in 0..4u8 {
+            let y = params.hash_element(&[b'y', i]);
+            let c_y = params.commit(&state, &y, &r);
+            let pi = zk_non_mem_prove(rng, &y, &r, &acc, &params).unwrap();
+            assert!(zk_non_mem_verify(&pi, &c_y, &state, &params));
+        }
+
+        // members can't be proven
+        for x in &xs {
+            assert!(zk_non_mem_prove(rng, x, &r, &acc, &params).is_none());
+        }
+    }
+
+    #[test]
+    fn test_zk_non_mem_unlinkable() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let (acc, params, _) = setup(rng);
+        let state = acc.public_state();
+        let y = params.hash_element(b"not a member");
+
+        let proofs = (0..2)
+            .map(|_| {
+                let r = rng.gen_biguint_below(&state.n);
+                let c_y = params.commit(&state, &y, &r);
+                let pi = zk_non_mem_prove(rng, &y, &r, &acc, &params).unwrap();
+                assert!(zk_non_mem_verify(&pi, &c_y, &state, &params));
+
+                let mut parts = vec![c_y, pi.c_d, pi.c_r];
+                parts.extend(pi.t);
+                parts.extend(pi.s);
+                parts
+            })
+            .collect::<Vec<_>>();
+
+        for part in &proofs[0] {
+            assert!(!proofs[1].contains(part));
+        }
+    }
+}
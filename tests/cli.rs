@@ -0,0 +1,159 @@
+//! Drives the `acc` example end-to-end, run with `cargo test --features cli`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// The example is built next to the test binary, in `target/<profile>/examples`.
+fn acc_bin() -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    if path.ends_with("deps") {
+        path.pop();
+    }
+    path.join("examples")
+        .join(format!("acc{}", std::env::consts::EXE_SUFFIX))
+}
+
+fn acc(dir: &Path, args: &[&str]) -> Output {
+    Command::new(acc_bin())
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("failed to run the acc example")
+}
+
+fn stderr(out: &Output) -> String {
+    String::from_utf8_lossy(&out.stderr).into_owned()
+}
+
+fn ok(dir: &Path, args: &[&str]) -> String {
+    let out = acc(dir, args);
+    assert!(out.status.success(), "{:?}: {}", args, stderr(&out));
+    String::from_utf8(out.stdout).unwrap()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("accumulators-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+const SEED: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+#[test]
+fn test_cli_end_to_end() {
+    let dir = temp_dir("e2e");
+    let dir = dir.as_path();
+
+    ok(
+        dir,
+        &["setup", "--lambda", "256", "--seed", SEED, "-o", "acc.json"],
+    );
+    ok(dir, &["params", "acc.json", "-o", "params.json"]);
+
+    // the same seed gives the same setup
+    ok(
+        dir,
+        &[
+            "setup",
+            "--lambda",
+            "256",
+            "--seed",
+            SEED,
+            "-o",
+            "acc2.json",
+        ],
+    );
+    assert_eq!(
+        fs::read(dir.join("acc.json")).unwrap(),
+        fs::read(dir.join("acc2.json")).unwrap()
+    );
+
+    // 0x65 = 101, 0x67 = 103, 0x6b = 107, 0x6d = 109
+    fs::write(dir.join("first.txt"), "# first batch\n65\n67\n\n").unwrap();
+    fs::write(dir.join("second.txt"), "6b\n6d\n").unwrap();
+
+    ok(dir, &["add", "acc.json", "first.txt"]);
+    ok(dir, &["state", "acc.json", "-o", "old.hex"]);
+
+    let states = ok(
+        dir,
+        &["batch-add", "acc.json", "second.txt", "-o", "proof.bin"],
+    );
+    ok(dir, &["state", "acc.json", "-o", "new.hex"]);
+    let old = fs::read_to_string(dir.join("old.hex")).unwrap();
+    let new = fs::read_to_string(dir.join("new.hex")).unwrap();
+    assert_eq!(states, format!("{}\n{}\n", old, new));
+
+    ok(
+        dir,
+        &[
+            "verify-batch",
+            "params.json",
+            "old.hex",
+            "new.hex",
+            "second.txt",
+            "proof.bin",
+        ],
+    );
+    let out = acc(
+        dir,
+        &[
+            "verify-batch",
+            "params.json",
+            "old.hex",
+            "new.hex",
+            "first.txt",
+            "proof.bin",
+        ],
+    );
+    assert_eq!(out.status.code(), Some(1), "{}", stderr(&out));
+
+    ok(dir, &["witness", "acc.json", "67", "-o", "wit.bin"]);
+    ok(dir, &["verify", "params.json", "new.hex", "wit.bin", "67"]);
+
+    // wrong element and stale state
+    let out = acc(dir, &["verify", "params.json", "new.hex", "wit.bin", "65"]);
+    assert_eq!(out.status.code(), Some(1), "{}", stderr(&out));
+    assert!(stderr(&out).contains("verification failed"));
+    let out = acc(dir, &["verify", "params.json", "old.hex", "wit.bin", "67"]);
+    assert_eq!(out.status.code(), Some(1), "{}", stderr(&out));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_errors() {
+    let dir = temp_dir("errors");
+    let dir = dir.as_path();
+
+    let err = |args: &[&str]| {
+        let out = acc(dir, args);
+        assert_eq!(out.status.code(), Some(2), "{:?}", args);
+        stderr(&out)
+    };
+
+    assert!(err(&[]).contains("missing command"));
+    assert!(err(&["frobnicate"]).contains("unknown command"));
+    assert!(err(&["setup", "--lambda", "256"]).contains("missing output file"));
+    assert!(err(&["setup", "--lambda", "256", "--seed", "abc", "-o", "a.json"]).contains("--seed"));
+    assert!(err(&["add", "missing.json", "xs.txt"]).contains("missing.json"));
+
+    ok(
+        dir,
+        &["setup", "--lambda", "256", "--seed", SEED, "-o", "acc.json"],
+    );
+    fs::write(dir.join("xs.txt"), "65\n").unwrap();
+    fs::write(dir.join("composite.txt"), "69\n").unwrap();
+    ok(dir, &["add", "acc.json", "xs.txt"]);
+
+    assert!(err(&["add", "acc.json", "composite.txt"]).contains("not a prime"));
+    assert!(err(&["add", "acc.json", "xs.txt"]).contains("the element is a member"));
+    assert!(err(&["witness", "acc.json", "67", "-o", "w.bin"]).contains("not a member"));
+    assert!(!dir.join("w.bin").exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1 @@
+//! Checks that the verifier side of `accumulators` builds and works without the `prover` feature.
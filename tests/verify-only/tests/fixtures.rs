@@ -0,0 +1,67 @@
+use accumulators::encoding::{modulus_bytes, FixedBytes};
+use accumulators::test_vectors::{builtin, TestVector};
+use accumulators::{BatchProof, MembershipWitness, NewState, OldState, PublicState};
+use num_bigint::BigUint;
+
+fn state(v: &TestVector, root: &BigUint) -> PublicState {
+    PublicState {
+        n: v.n.clone(),
+        g: v.g.clone(),
+        root: root.clone(),
+    }
+}
+
+#[test]
+fn verifies_builtin_vectors() {
+    for v in builtin() {
+        let added = state(&v, v.states.last().unwrap());
+        for (w, x) in v.witnesses.iter().zip(&v.elements) {
+            assert!(added.ver_mem(w, x));
+        }
+
+        let batched = state(&v, &v.batch_state);
+        let (old, new) = (OldState(added.root), NewState(batched.root.clone()));
+        assert!(batched.ver_batch_add_transition(&v.batch_proof, &old, &new, &v.batch_elements));
+        assert!(batched.ver_non_mem(&v.non_mem_witness, &v.non_member));
+        assert!(batched.ver_non_mem_star(&v.non_member, &v.non_mem_proof));
+    }
+}
+
+#[test]
+fn rejects_modified_proofs() {
+    for v in builtin() {
+        let added = state(&v, v.states.last().unwrap());
+        let w = MembershipWitness(&v.witnesses[0].0 + 1u32);
+        assert!(!added.ver_mem(&w, &v.elements[0]));
+        assert!(!added.ver_mem(&v.witnesses[0], &v.elements[1]));
+
+        let batched = state(&v, &v.batch_state);
+        let (old, new) = (OldState(added.root.clone()), NewState(batched.root.clone()));
+        let xs = &v.batch_elements[1..];
+        assert!(!batched.ver_batch_add_transition(&v.batch_proof, &old, &new, xs));
+        // the states swapped
+        let (old, new) = (OldState(new.0), NewState(old.0));
+        assert!(!batched.ver_batch_add_transition(&v.batch_proof, &old, &new, &v.batch_elements));
+        assert!(!batched.ver_non_mem(&v.non_mem_witness, &v.elements[0]));
+        assert!(!batched.ver_non_mem_star(&v.elements[0], &v.non_mem_proof));
+    }
+}
+
+#[test]
+fn decodes_fixed_bytes() {
+    for v in builtin() {
+        let len = modulus_bytes(&v.n);
+        let bytes = v.batch_proof.to_fixed_bytes(len);
+        assert_eq!(
+            BatchProof::from_fixed_bytes(&bytes, &v.n).unwrap(),
+            v.batch_proof
+        );
+
+        let bytes = v.witnesses[0].to_fixed_bytes(len);
+        assert_eq!(
+            MembershipWitness::from_fixed_bytes(&bytes, &v.n).unwrap(),
+            v.witnesses[0]
+        );
+        assert!(MembershipWitness::from_fixed_bytes(&bytes[1..], &v.n).is_err());
+    }
+}